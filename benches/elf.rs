@@ -0,0 +1,81 @@
+extern crate elfkit;
+
+use std::env;
+use std::fs;
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+use elfkit::{types, Elf, ElfBuilder, Stats};
+
+/// cargo bench [-- FILE...]
+/// time parsing, loading, storing and writing each file, this benchmark itself if none is
+/// given, and building an executable from scratch. criterion isn't a dependency, so this is
+/// the plain loop: the mean of ROUNDS runs after one to warm up, and the counters of Elf::stats
+const ROUNDS: u32 = 20;
+
+fn mean<F: FnMut()>(mut f: F) -> Duration {
+    f();
+    let started = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    started.elapsed() / ROUNDS
+}
+
+fn report(what: &str, t: Duration, bytes: u64) {
+    let mb_s = bytes as f64 / t.as_secs_f64().max(1e-9) / (1 << 20) as f64;
+    println!("{:<24} {:>12?} {:>10.1} MiB/s", what, t, mb_s);
+}
+
+fn file(path: &str) {
+    let data = fs::read(path).unwrap();
+    let len = data.len() as u64;
+    println!("{} ({} bytes)", path, len);
+
+    let parse = mean(|| {
+        Elf::from_reader(&mut Cursor::new(&data)).unwrap();
+    });
+    report("parse", parse, len);
+    let load = mean(|| {
+        Elf::from_reader(&mut Cursor::new(&data)).unwrap().load_all().unwrap();
+    });
+    report("parse + load_all", load, len);
+
+    let mut stats = Stats::default();
+    let write = mean(|| {
+        let mut elf = Elf::from_reader(&mut Cursor::new(&data)).unwrap();
+        elf.load_all().unwrap();
+        elf.store_all().unwrap();
+        elf.to_writer(&mut Cursor::new(Vec::new())).unwrap();
+        stats = elf.stats().clone();
+    });
+    report("+ store_all + to_writer", write, len);
+    println!("{:#?}", stats);
+}
+
+fn build() {
+    let t = mean(|| {
+        let mut b = ElfBuilder::new(types::Machine::X86_64).text(vec![0xc3; 0x10000]);
+        for i in 0..64 {
+            b = b.section(
+                &format!(".data.{}", i),
+                types::SectionType::PROGBITS,
+                types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+                vec![i as u8; 0x100],
+            );
+        }
+        b.build().unwrap();
+    });
+    report("ElfBuilder, 65 sections", t, 0x10000 + 64 * 0x100);
+}
+
+fn main() {
+    // cargo passes --bench
+    let mut files: Vec<String> = env::args().skip(1).filter(|a| !a.starts_with('-')).collect();
+    if files.is_empty() {
+        files.push(env::current_exe().unwrap().to_string_lossy().into_owned());
+    }
+    for f in &files {
+        file(f);
+    }
+    build();
+}
@@ -0,0 +1,42 @@
+extern crate elfkit;
+extern crate tempfile;
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process;
+use elfkit::archive;
+
+/// elfkit-ranlib ARCHIVE...
+/// regenerate the symbol index of each archive from the symbol tables of its members.
+/// the new archive replaces the old one only once it's complete
+fn main() {
+    let paths: Vec<String> = env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("usage: elfkit-ranlib ARCHIVE...");
+        process::exit(2);
+    }
+    let mut failed = false;
+    for path in &paths {
+        let r = fs::read(path)
+            .map_err(elfkit::Error::from)
+            .and_then(|data| archive::ranlib(&data))
+            .and_then(|data| {
+                // next to the archive, so the rename stays on one filesystem
+                let dir = Path::new(path).parent().filter(|d| !d.as_os_str().is_empty());
+                let mut tmp = tempfile::NamedTempFile::new_in(dir.unwrap_or(Path::new(".")))?;
+                tmp.write_all(&data)?;
+                tmp.set_permissions(fs::metadata(path)?.permissions())?;
+                tmp.persist(path).map_err(std::io::Error::from)?;
+                Ok(())
+            });
+        if let Err(e) = r {
+            eprintln!("elfkit-ranlib: {}: {}", path, e);
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
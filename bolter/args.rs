@@ -3,133 +3,725 @@ use std::env;
 use ::fail;
 use elfkit::*;
 use std::fs::OpenOptions;
-use ::goblin;
-use std::io::{Read, Cursor};
+use std::io::{Read, Seek, Cursor, Write};
+use std;
 use colored::*;
+use cache::ObjectCache;
+use tempfile::NamedTempFileOptions;
 
 
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum OutputKind {
+    #[default]
+    Pie,
+    Static,
+    /// relocated by rcrt1 from R_X86_64_RELATIVE entries instead of the bootstrap
+    StaticPie,
+    Shared,
+}
+
+#[derive(Default)]
+pub struct Input {
+    pub path:       String,
+    /// from --as-needed, only link this if something references it
+    pub as_needed:  bool,
+    /// from -b binary, the file is data to link in rather than an object
+    pub binary:     bool,
+}
+
 #[derive(Default)]
 pub struct LdOptions {
     pub dynamic_linker: String,
-    pub object_paths:   Vec<String>,
+    pub inputs:         Vec<Input>,
     pub output_path:    String,
+    pub output_kind:    OutputKind,
+    pub entry:          String,
+    pub search_paths:   Vec<String>,
+    /// -z keywords, like now or relro
+    pub z:              Vec<String>,
+    /// --allow-multiple-definition or -z muldefs
+    pub allow_multiple_definition: bool,
+    /// --unresolved-symbols=ignore-all or ignore-in-object-files
+    pub allow_undefined: bool,
+    /// -t, print every input that is loaded
+    pub trace_files:    bool,
+    /// -y, print where these are referenced and defined
+    pub trace_symbols:  Vec<String>,
+    pub print_gc_sections: bool,
+    /// --stats, print what each input contributed once the output is written
+    pub stats:          bool,
+    /// --symbol-ordering-file, symbols whose sections go first, in this order
+    pub symbol_ordering_file: Option<String>,
+    /// --export-dynamic and --export-dynamic-symbol, what goes into .dynsym
+    pub exports:        linker::Exports,
+    /// --cache-dir, where to keep the symbols of inputs between runs
+    pub cache_dir:      Option<String>,
+    /// --threads, 0 for one per cpu
+    pub threads:        usize,
+    /// -S and -s, what to leave out of the output
+    pub strip:          linker::Strip,
+    /// --lto-compiler, the command that turns LTO objects into native ones, see lto_compile
+    pub lto_compiler:   Option<String>,
+}
+
+/// an input as it appears on the command line, before -l names are looked up
+enum Request {
+    Path(String),
+    /// from -lname
+    Library(String),
+}
+
+/// find the file in the first of the search directories that has it
+fn search_file(search_paths: &[String], file: &str) -> Option<String> {
+    search_paths.iter()
+        .map(|p| Path::new(p).join(file))
+        .find(|pc| pc.is_file())
+        .map(|pc| pc.to_string_lossy().into_owned())
+}
+
+/// find the file a -l option refers to.
+/// every search directory is tried in order of the -L options.
+/// bolter can't link shared libraries, so -lname is always libname.a, as with -static,
+/// even where ld would take libname.so. -l:file looks for exactly that file name.
+fn search_lib(search_paths: &[String], name: &str) -> Option<String> {
+    match name.strip_prefix(":") {
+        Some(file) => search_file(search_paths, file),
+        None => search_file(search_paths, &format!("lib{}.a", name)),
+    }
+}
+
+/// turn requests into paths. all -L options apply to all -l options, regardless of order
+fn resolve(search_paths: &[String], requests: Vec<(Request, bool, bool)>) -> Vec<Input> {
+    requests.into_iter().map(|(request, as_needed, binary)| {
+        let path = match request {
+            Request::Path(path) => path,
+            Request::Library(name) => {
+                match search_lib(search_paths, &name) {
+                    Some(path) => path,
+                    None => match search_file(search_paths, &format!("lib{}.so", name)) {
+                        Some(so) => fail(format!(
+                            "ld.elfkit: cannot find lib{}.a for -l{}, only {}, and shared \
+                             libraries can't be linked", name, name, so)),
+                        None => fail(format!("ld.elfkit: cannot find -l{} in {:?}",
+                                             name, search_paths)),
+                    },
+                }
+            }
+        };
+        Input{path, as_needed, binary}
+    }).collect()
 }
 
-fn search_lib(search_paths: &Vec<String>, needle: &String) -> String{
-    let so = String::from("lib") + needle + ".a";
-    for p in search_paths {
-        let pc = Path::new(p).join(&so);
-        if pc.exists() {
-            return pc.into_os_string().into_string().unwrap();
+/// replace every @file argument with the arguments in that file, recursively.
+/// like gnu ld, arguments are separated by whitespace and may be quoted
+fn expand_response_files(args: Vec<String>, depth: usize) -> Vec<String> {
+    let mut r = Vec::new();
+    for arg in args {
+        if !arg.starts_with("@") || arg.len() < 2 {
+            r.push(arg);
+            continue;
+        }
+        if depth > 16 {
+            fail(format!("ld.elfkit: response files nested too deep at {}", arg));
+        }
+        let mut content = String::new();
+        match OpenOptions::new().read(true).open(&arg[1..]) {
+            Ok(mut f) => {
+                if let Err(e) = f.read_to_string(&mut content) {
+                    fail(format!("ld.elfkit: while reading {}: {}", arg, e));
+                }
+            }
+            // gnu ld treats a missing response file as a plain argument
+            Err(_) => {
+                r.push(arg);
+                continue;
+            }
         }
+        r.extend(expand_response_files(split_response_file(&content), depth + 1));
     }
-    fail(format!("ld.elfkit: cannot find: {} in {:?}", so, search_paths));
+    r
 }
 
-fn ldarg(arg: &String, argname: &str, argc: &mut usize) -> Option<String> {
-    if arg.starts_with(argname) {
-        Some(if arg.len() < argname.len() + 1 {
-            *argc += 1;
-            env::args().nth(*argc).unwrap()
-        } else {
-            String::from(&arg[2..])
-        })
+fn split_response_file(content: &str) -> Vec<String> {
+    let mut r = Vec::new();
+    let mut cur = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (_, '\\') => {
+                if let Some(c) = chars.next() {
+                    cur.push(c);
+                }
+                in_arg = true;
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => cur.push(c),
+            (None, '\'') | (None, '"') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    r.push(std::mem::take(&mut cur));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                cur.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        r.push(cur);
+    }
+    r
+}
+
+/// match an option that takes a value, in any of the forms gnu ld accepts:
+/// "-o out", "-oout", "--output out" and "--output=out".
+/// long is the name without dashes, which ld also accepts with a single dash
+fn ldarg(args: &[String], argc: &mut usize, short: &str, long: &str) -> Option<String> {
+    let arg = &args[*argc];
+    let mut value_next = false;
+    let mut value = None;
+
+    if !long.is_empty() {
+        for dashes in &["--", "-"] {
+            let name = String::from(*dashes) + long;
+            if *arg == name {
+                value_next = true;
+            } else if arg.starts_with(&(name + "=")) {
+                value = Some(String::from(&arg[dashes.len() + long.len() + 1..]));
+            }
+        }
+    }
+    if !short.is_empty() && !value_next && value.is_none() {
+        if *arg == short {
+            value_next = true;
+        } else if arg.starts_with(short) && !arg.starts_with("--") {
+            value = Some(String::from(&arg[short.len()..]));
+        }
+    }
+
+    if value_next {
+        *argc += 1;
+        match args.get(*argc) {
+            Some(v) => Some(v.clone()),
+            None => fail(format!("ld.elfkit: {} needs an argument", arg)),
+        }
     } else {
-        None
+        value
     }
 }
 
-pub fn parse_ld_options() -> LdOptions{
-    let mut options         = LdOptions::default();
-    options.output_path     = String::from("a.out");
-    let mut search_paths    = Vec::new();
+/// options that are accepted for compatibility but make no difference here
+const IGNORED: &[&str] = &[
+    "--eh-frame-hdr", "--build-id", "--gc-sections", "--no-gc-sections", "--start-group",
+    "--end-group", "-(", "-)", "--no-undefined", "--fatal-warnings", "-O1", "-O2", "--relax",
+    "--no-relax", "-nostdlib", "--discard-all", "-X", "-eh-frame-hdr",
+    // everything an executable refers to binds locally anyway, see linker::Symbolic
+    "-Bsymbolic", "-Bsymbolic-functions", "-Bno-symbolic",
+];
+
+/// long options gnu ld also takes with a single dash that start with -e, but aren't handled.
+/// everything -e would match has to be checked before it, or -e takes the rest for an entry
+/// point, as -export-dynamic and -eh-frame-hdr are
+const LONG_E_OPTIONS: &[&str] = &[
+    "-emit-relocs", "-enable-new-dtags", "-enable-linker-version", "-error-limit",
+    "-error-unresolved-symbols", "-error-handling-script", "-exclude-libs",
+    "-export-dynamic-symbol-list", "-execute-only",
+];
+
+/// options with a value that are accepted for compatibility but make no difference here
+const IGNORED_WITH_VALUE: &[(&str, &str)] = &[
+    ("", "hash-style"), ("", "plugin"), ("", "plugin-opt"), ("", "sysroot"),
+    ("", "soname"), ("-h", ""), ("", "rpath-link"), ("", "rpath"), ("", "build-id"),
+    ("", "version-script"),
+];
 
-    let mut argc = 1;
-    loop {
-        if argc >= env::args().len() {
-            break;
+/// skip an ignored option and its value, returning the index of the last argument used
+fn ignored_with_value(args: &[String], argc: usize) -> Option<usize> {
+    for &(short, long) in IGNORED_WITH_VALUE {
+        let mut i = argc;
+        if ldarg(args, &mut i, short, long).is_some() {
+            return Some(i);
         }
+    }
+    None
+}
 
-        let arg = env::args().nth(argc).unwrap();
-        if let Some(val) = ldarg(&arg, "-L", &mut argc) {
-            search_paths.push(val);
-        } else if let Some(val) = ldarg(&arg, "-z", &mut argc) {
-            argc += 1;
-            let arg2 = env::args().nth(argc).unwrap();
-            println!("{}", format!("argument ignored: {} {}", arg,arg2).yellow());
+pub fn parse_ld_options() -> LdOptions{
+    parse_args(env::args().skip(1).collect())
+}
+
+pub fn parse_args(args: Vec<String>) -> LdOptions {
+    let args                = expand_response_files(args, 0);
+    let mut options         = LdOptions{
+        output_path:    String::from("a.out"),
+        entry:          String::from("_start"),
+        ..Default::default()
+    };
+    let mut as_needed       = false;
+    let mut binary          = false;
+    let mut requests        = Vec::new();
 
-        } else if let Some(val) = ldarg(&arg, "-l", &mut argc) {
-            options.object_paths.push(search_lib(&search_paths, &val));
-        } else if let Some(val) = ldarg(&arg, "-m", &mut argc) {
+    let mut argc = 0;
+    while argc < args.len() {
+        let arg = args[argc].clone();
+        if arg == "--version" {
+            println!("GNU ld compatible elfkit linker {}", env!("CARGO_PKG_VERSION"));
+            std::process::exit(0);
+        } else if arg == "-v" || arg == "-V" {
+            println!("GNU ld compatible elfkit linker {}", env!("CARGO_PKG_VERSION"));
+        } else if IGNORED.contains(&arg.as_ref()) {
+        } else if let Some(val) = ldarg(&args, &mut argc, "-L", "library-path") {
+            options.search_paths.push(val);
+        } else if let Some(val) = ldarg(&args, &mut argc, "-z", "") {
+            if val == "muldefs" {
+                options.allow_multiple_definition = true;
+            }
+            options.z.push(val);
+        } else if let Some(val) = ldarg(&args, &mut argc, "-l", "library") {
+            requests.push((Request::Library(val), as_needed, binary));
+        } else if let Some(val) = ldarg(&args, &mut argc, "-m", "") {
             if val != "elf_x86_64" {
                 fail(format!("machine not supported: {}", val));
             }
-        } else if let Some(val) = ldarg(&arg, "-o", &mut argc) {
+        } else if let Some(val) = ldarg(&args, &mut argc, "-o", "output") {
             options.output_path = val;
-        } else if arg == "-pie" {
-        } else if arg == "-dynamic-linker" {
-            argc += 1;
-            options.dynamic_linker = env::args().nth(argc).unwrap()
-        } else if arg.starts_with("-") {
+        } else if arg == "-E" || arg == "--export-dynamic" || arg == "-export-dynamic" {
+            options.exports = linker::Exports::All;
+        } else if arg == "--no-export-dynamic" {
+            options.exports = linker::Exports::None;
+        } else if let Some(val) = ldarg(&args, &mut argc, "", "export-dynamic-symbol") {
+            match options.exports {
+                linker::Exports::All => {},
+                linker::Exports::Names(ref mut names) => { names.insert(val); },
+                linker::Exports::None => {
+                    options.exports = linker::Exports::Names(Some(val).into_iter().collect());
+                },
+            }
+        } else if LONG_E_OPTIONS.iter().any(|o| arg == *o || arg.starts_with(&format!("{}=", o))) {
+            println!("{}", format!("argument ignored: {}",arg).yellow());
+        } else if let Some(val) = ldarg(&args, &mut argc, "-e", "entry") {
+            options.entry = val;
+        } else if let Some(val) = ldarg(&args, &mut argc, "-I", "dynamic-linker") {
+            options.dynamic_linker = val;
+        } else if arg == "-pie" || arg == "--pie" || arg == "-pic-executable" {
+            options.output_kind = OutputKind::Pie;
+        } else if arg == "-no-pie" || arg == "--no-pie" {
+            // still a position independent executable, since that's all bolter makes
+        } else if arg == "-static" || arg == "--static" {
+            options.output_kind = OutputKind::Static;
+        } else if arg == "-static-pie" || arg == "--static-pie" {
+            options.output_kind = OutputKind::StaticPie;
+        } else if arg == "--no-dynamic-linker" || arg == "-no-dynamic-linker" {
+            // gcc -static-pie passes -static -pie --no-dynamic-linker
+            options.output_kind = OutputKind::StaticPie;
+        } else if arg == "-Bstatic" || arg == "-dn" || arg == "-non_shared" ||
+            arg == "-Bdynamic" || arg == "-dy" || arg == "-call_shared" {
+            // libraries are always static, see search_lib
+        } else if arg == "-shared" || arg == "--shared" || arg == "-Bshareable" {
+            options.output_kind = OutputKind::Shared;
+        } else if arg == "--allow-multiple-definition" || arg == "-allow-multiple-definition" {
+            options.allow_multiple_definition = true;
+        } else if let Some(val) = ldarg(&args, &mut argc, "", "unresolved-symbols") {
+            options.allow_undefined = match val.as_ref() {
+                "ignore-all" | "ignore-in-object-files" => true,
+                "report-all" | "ignore-in-shared-libs" => false,
+                _ => fail(format!("ld.elfkit: unknown --unresolved-symbols value {}", val)),
+            };
+        } else if arg == "--allow-undefined" {
+            options.allow_undefined = true;
+        } else if arg == "-S" || arg == "--strip-debug" {
+            // doesn't bring back the symbol table if -s came first
+            if options.strip == linker::Strip::None {
+                options.strip = linker::Strip::Debug;
+            }
+        } else if arg == "-s" || arg == "--strip-all" {
+            options.strip = linker::Strip::All;
+        } else if arg == "-t" || arg == "--trace" {
+            options.trace_files = true;
+        } else if let Some(val) = ldarg(&args, &mut argc, "-y", "trace-symbol") {
+            options.trace_symbols.push(val);
+        } else if arg == "--threads" || arg == "-threads" {
+            options.threads = 0;
+        } else if arg == "--no-threads" || arg == "-no-threads" {
+            options.threads = 1;
+        } else if let Some(val) = ldarg(&args, &mut argc, "", "threads") {
+            options.threads = match val.parse() {
+                Ok(n) => n,
+                Err(_) => fail(format!("ld.elfkit: invalid --threads value {}", val)),
+            };
+        } else if let Some(val) = ldarg(&args, &mut argc, "", "symbol-ordering-file") {
+            options.symbol_ordering_file = Some(val);
+        } else if arg == "--no-symbol-ordering-file" {
+            options.symbol_ordering_file = None;
+        } else if let Some(val) = ldarg(&args, &mut argc, "", "lto-compiler") {
+            options.lto_compiler = Some(val);
+        } else if let Some(val) = ldarg(&args, &mut argc, "", "cache-dir") {
+            options.cache_dir = Some(val);
+        } else if arg == "--print-gc-sections" {
+            options.print_gc_sections = true;
+        } else if arg == "--no-print-gc-sections" {
+            options.print_gc_sections = false;
+        } else if arg == "--stats" {
+            options.stats = true;
+        } else if let Some(val) = ldarg(&args, &mut argc, "", "format").or_else(|| {
+            if arg == "-b" { ldarg(&args, &mut argc, "-b", "") } else { None }
+        }) {
+            // the format of the inputs that follow
+            binary = match val.as_ref() {
+                "binary" => true,
+                "default" => false,
+                v if v.starts_with("elf") => false,
+                _ => fail(format!("ld.elfkit: unsupported input format {}", val)),
+            };
+        } else if arg == "--as-needed" {
+            as_needed = true;
+        } else if arg == "--no-as-needed" {
+            as_needed = false;
+        } else if let Some(i) = ignored_with_value(&args, argc) {
+            argc = i;
+        } else if arg.starts_with("-") && arg.len() > 1 {
             println!("{}", format!("argument ignored: {}",arg).yellow());
         } else {
-            options.object_paths.push(arg);
+            requests.push((Request::Path(arg), as_needed, binary));
         }
         argc +=1;
     }
-
-    println!("linking {:?}", options.object_paths);
+    options.inputs = resolve(&options.search_paths, requests);
+    println!("linking {:?}", options.inputs.iter().map(|i| &i.path).collect::<Vec<&String>>());
 
     options
 }
 
-pub fn load_elfs(paths: Vec<String>) -> Vec<(String,Elf)> {
+/// the inputs listed in a linker script, with libraries looked up.
+/// like gnu ld, a relative path is tried in the current directory first, then the search paths.
+fn script_inputs(path: &str, in_file: &mut std::fs::File, search_paths: &[String])
+    -> (Vec<Input>, Vec<String>) {
+    let mut text = String::new();
+    if let Err(e) = in_file.read_to_string(&mut text) {
+        fail(format!("while reading {}: {}", path, e));
+    }
+    let script = match ldscript::LinkerScript::parse(&text) {
+        Ok(s) => s,
+        Err(e) => fail(format!("{}: {}", path, e)),
+    };
+    let mut dirs = search_paths.to_vec();
+    dirs.extend(script.search_dirs);
+
+    let inputs = script.inputs.into_iter().map(|input| {
+        let found = if input.library {
+            search_lib(&dirs, &input.name)
+        } else if Path::new(&input.name).exists() {
+            Some(input.name.clone())
+        } else {
+            search_lib(&dirs, &(String::from(":") + &input.name))
+        };
+        match found {
+            Some(p) => Input{path: p, as_needed: input.as_needed, binary: false},
+            None => fail(format!("ld.elfkit: cannot find {} referenced by {}", input.name, path)),
+        }
+    }).collect();
+    (inputs, dirs)
+}
+
+/// the symbols of an input are only parsed once it is linked, Lookup::providers just needs
+/// the names it defines
+fn input_options() -> ElfOptions {
+    ElfOptions {
+        symbol_index_only: true,
+        ..ElfOptions::default()
+    }
+}
+
+/**
+ * compile an LTO input to a native object with the --lto-compiler command, which gets
+ * -o OUT IN appended, IN being the input in a temporary file ending in .o. for example
+ * "gcc -flto -r -nostdlib -flinker-output=nolto-rel" or "clang -c -x ir"
+ */
+fn lto_compile(command: &str, name: &str, data: &[u8]) -> std::result::Result<Vec<u8>, Error> {
+    let mut words = command.split_whitespace();
+    let program = match words.next() {
+        Some(p) => p,
+        None => fail(String::from("ld.elfkit: --lto-compiler is empty")),
+    };
+    // files of our own, nobody else can have put a link where they are.
+    // both are removed again when they go out of scope
+    let tmp = || NamedTempFileOptions::new().prefix("elfkit-lto-").suffix(".o").create();
+    let (mut input, output) = (tmp()?, tmp()?);
+    input.write_all(data)?;
+    let status = std::process::Command::new(program).args(words)
+        .arg("-o").arg(output.path()).arg(input.path()).status();
+    match status {
+        Ok(s) if s.success() => {},
+        Ok(s) => fail(format!("ld.elfkit: {}: {} failed with {}", name, command, s)),
+        Err(e) => fail(format!("ld.elfkit: {}: can't run {}: {}", name, program, e)),
+    }
+    Ok(std::fs::read(output.path())?)
+}
+
+fn read_all(path: &str, file: &mut std::fs::File) -> Vec<u8> {
+    let mut data = Vec::new();
+    let r = file.seek(std::io::SeekFrom::Start(0)).and_then(|_| file.read_to_end(&mut data));
+    if let Err(e) = r {
+        fail(format!("while reading {}: {}", path, e));
+    }
+    data
+}
+
+/// parse an input, compiling it first if it is LTO code and there is a --lto-compiler
+fn native_object(name: &str, data: Vec<u8>, lto_compiler: Option<&str>) -> Elf {
+    let compile = lto_compiler.map(|cmd| move |name: &str, data: &[u8]| lto_compile(cmd, name, data));
+    match linker::native_object(name, data, compile) {
+        Ok(elf) => elf,
+        Err(e @ Error::LtoObject(..)) => fail(format!("ld.elfkit: {}. pass --lto-compiler", e)),
+        Err(e) => fail(format!("error loading {} : {}", name, e)),
+    }
+}
+
+pub fn load_elfs(inputs: &[Input], search_paths: &[String], cache: Option<&ObjectCache>,
+                 lto_compiler: Option<&str>) -> Vec<(String,Elf)> {
     let mut elfs = Vec::new();
-    for in_path in paths {
-        let mut in_file  = match OpenOptions::new().read(true).open(&in_path) {
+    for input in inputs {
+        let in_path = &input.path;
+        let mut loaded = Vec::new();
+        let mut in_file  = match OpenOptions::new().read(true).open(in_path) {
             Ok(f) => f,
             Err(e) => {
                 fail(format!("while loading '{}' : {:?}", in_path, e));
             }
         };
-        let in_name = Path::new(&in_path).file_name().unwrap().to_string_lossy().into_owned();
+        let in_name = Path::new(in_path).file_name().unwrap().to_string_lossy().into_owned();
+        if input.binary {
+            let mut data = Vec::new();
+            if let Err(e) = in_file.read_to_end(&mut data) {
+                fail(format!("while reading {}: {}", in_path, e));
+            }
+            match builder::binary_object(types::Machine::X86_64, in_path, data) {
+                Ok(elf) => elfs.push((in_name, elf)),
+                Err(e) => fail(format!("error loading {} : {}", in_path, e)),
+            }
+            continue;
+        }
         match filetype::filetype(&in_file).unwrap() {
             filetype::FileType::Elf => {
-                elfs.push((in_name, match Elf::from_reader(&mut in_file) {
+                let mut elf = match Elf::from_reader_with_options(&mut in_file, input_options()) {
                     Ok(e) => e,
                     Err(e) => {
-                        fail(format!("error loading {} : {:?}",
+                        fail(format!("error loading {} : {}",
                                                in_path, e));
                     },
 
-                }));
+                };
+                if linker::lto_kind(&elf).map(|k| k.is_native()) == Some(false) {
+                    elf = native_object(in_path, read_all(in_path, &mut in_file), lto_compiler);
+                }
+                if !elf.header.etype.is_linkable() {
+                    fail(format!("ld.elfkit: {}: can not link {:?} files, only objects and libraries",
+                                 in_path, elf.header.etype));
+                }
+                if elf.header.etype == types::ElfType::DYN {
+                    // not worth a warning if it was only wanted as needed anyway
+                    if !input.as_needed {
+                        println!("{}", format!("skipping shared library {}", in_path).yellow());
+                    }
+                    continue;
+                }
+                loaded.push((in_name, elf));
             },
             filetype::FileType::Archive => {
                 let mut buffer = Vec::new();
                 in_file.read_to_end(&mut buffer).unwrap();
-                match goblin::Object::parse(&buffer).unwrap() {
-                    goblin::Object::Archive(archive) => {
-                        for (name,member,_) in archive.summarize() {
-                            let mut io = Cursor::new(&buffer[
-                                                     member.offset as usize ..
-                                                     member.offset as usize + member.header.size]);
-
-                            match Elf::from_reader(&mut io) {
-                                Ok(e)  => elfs.push((String::from(name), e)),
-                                Err(e) => {
-                                    println!("{}", format!("skipping {} in {}: {:?}",
-                                                     name, in_path, e).yellow());
-                                },
-                            }
-                        }
-                    },
-                    _ => unreachable!(),
+                let members = match archive::members(&buffer) {
+                    Ok(m) => m,
+                    Err(e) => fail(format!("error loading {} : {}", in_path, e)),
+                };
+                for member in members {
+                    let data = &buffer[member.start..member.end];
+                    let name = format!("{}({})", in_name, member.name);
+                    if let Ok(filetype::FileType::LlvmBitcode) = filetype::filetype(Cursor::new(data)) {
+                        loaded.push((name.clone(), native_object(&name, data.to_vec(), lto_compiler)));
+                        continue;
+                    }
+                    let mut io = Cursor::new(data);
+                    match Elf::from_reader_with_options(&mut io, input_options()) {
+                        Ok(ref e) if !e.header.etype.is_linkable() => {
+                            println!("{}", format!("skipping {} in {}: a {:?} file",
+                                             member.name, in_path, e.header.etype).yellow());
+                        },
+                        Ok(ref e) if linker::lto_kind(e).map(|k| k.is_native()) == Some(false) => {
+                            loaded.push((name.clone(), native_object(&name, data.to_vec(), lto_compiler)));
+                        },
+                        Ok(e)  => loaded.push((name, e)),
+                        Err(e) => {
+                            println!("{}", format!("skipping {} in {}: {}",
+                                             member.name, in_path, e).yellow());
+                        },
+                    }
                 }
             },
-            _ => {
-                fail(format!("{}: unknown file type", in_name));
+            filetype::FileType::LlvmBitcode => {
+                loaded.push((in_name, native_object(in_path, read_all(in_path, &mut in_file),
+                                                    lto_compiler)));
+            },
+            filetype::FileType::LinkerScript => {
+                let (inputs, dirs) = script_inputs(in_path, &mut in_file, search_paths);
+                elfs.extend(load_elfs(&inputs, &dirs, cache, lto_compiler));
+            },
+            t => {
+                fail(format!("{}: {}", in_name, t));
             }
         }
+        if let Some(cache) = cache {
+            if !loaded.is_empty() && !cache.restore(in_path, &mut loaded) {
+                cache.store(in_path, &mut loaded);
+            }
+        }
+        elfs.extend(loaded);
     }
     elfs
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn paths(options: &LdOptions) -> Vec<&str> {
+        options.inputs.iter().map(|i| i.path.as_str()).collect()
+    }
+
+    type Check = fn(&LdOptions) -> bool;
+
+    #[test]
+    fn gnu_ld_options() {
+        let table: &[(&str, Check)] = &[
+            ("-o out a.o", |o| o.output_path == "out" && paths(o) == ["a.o"]),
+            ("-oout a.o", |o| o.output_path == "out" && paths(o) == ["a.o"]),
+            ("--output out", |o| o.output_path == "out"),
+            ("--output=out", |o| o.output_path == "out"),
+            ("-output=out", |o| o.output_path == "out"),
+            ("a.o", |o| o.output_path == "a.out" && o.entry == "_start"),
+            ("--entry=main -e start", |o| o.entry == "start"),
+            ("-emain", |o| o.entry == "main"),
+            // long options with a single dash, not -e with the rest as entry point
+            ("-export-dynamic", |o| o.exports == linker::Exports::All && o.entry == "_start"),
+            ("-export-dynamic-symbol=f -export-dynamic-symbol g", |o| {
+                let names = ["f", "g"].iter().map(|s| s.to_string()).collect();
+                o.exports == linker::Exports::Names(names)
+                    && o.entry == "_start"
+            }),
+            ("-E --no-export-dynamic", |o| o.exports == linker::Exports::None),
+            ("-eh-frame-hdr -enable-new-dtags -emit-relocs", |o| o.entry == "_start"),
+            ("-z relro -znow", |o| o.z == ["relro", "now"]),
+            ("-z muldefs", |o| o.allow_multiple_definition),
+            ("-S", |o| o.strip == linker::Strip::Debug),
+            ("--strip-debug", |o| o.strip == linker::Strip::Debug),
+            ("-s", |o| o.strip == linker::Strip::All),
+            // -S doesn't bring the symbols back
+            ("-s -S", |o| o.strip == linker::Strip::All),
+            ("a.o", |o| o.strip == linker::Strip::None),
+            ("--threads=4", |o| o.threads == 4),
+            // like in gnu ld, the count has to be given with =
+            ("--threads a.o", |o| o.threads == 0 && paths(o) == ["a.o"]),
+            ("--no-threads", |o| o.threads == 1),
+            ("--threads=3 --threads", |o| o.threads == 0),
+            ("--symbol-ordering-file order.txt", |o| {
+                o.symbol_ordering_file.as_deref() == Some("order.txt")
+            }),
+            ("--symbol-ordering-file=order.txt --no-symbol-ordering-file", |o| {
+                o.symbol_ordering_file.is_none()
+            }),
+            ("--lto-compiler=cc1-lto a.o", |o| {
+                o.lto_compiler.as_deref() == Some("cc1-lto")
+                    && paths(o) == ["a.o"]
+            }),
+            ("--lto-compiler cc1-lto", |o| o.lto_compiler.is_some()),
+            ("-static-pie", |o| o.output_kind == OutputKind::StaticPie),
+            ("-static -pie --no-dynamic-linker", |o| o.output_kind == OutputKind::StaticPie),
+            ("-static", |o| o.output_kind == OutputKind::Static),
+            ("--as-needed a.o --no-as-needed b.o", |o| {
+                o.inputs.iter().map(|i| i.as_needed).collect::<Vec<_>>() == [true, false]
+            }),
+            ("-b binary blob --format=default a.o", |o| {
+                o.inputs.iter().map(|i| i.binary).collect::<Vec<_>>() == [true, false]
+            }),
+            ("--hash-style=gnu -soname x --build-id a.o", |o| paths(o) == ["a.o"]),
+            ("--cache-dir=.cache", |o| o.cache_dir.is_some()),
+        ];
+        for &(line, check) in table {
+            assert!(check(&parse_args(args(line))), "{}", line);
+        }
+    }
+
+    #[test]
+    fn response_files() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"-o 'out file'\n  \"b c.o\" d\\ e.o -z relro\n").unwrap();
+        let at = format!("@{}", file.path().display());
+        let options = parse_args(vec![String::from("a.o"), at, String::from("f.o")]);
+        assert_eq!(options.output_path, "out file");
+        assert_eq!(paths(&options), ["a.o", "b c.o", "d e.o", "f.o"]);
+        assert_eq!(options.z, ["relro"]);
+
+        // a response file that doesn't exist is an input of that name, like in gnu ld
+        let options = parse_args(args("@/nonexistent/args"));
+        assert_eq!(paths(&options), ["@/nonexistent/args"]);
+    }
+
+    #[test]
+    fn libraries_are_static() {
+        // named after a temporary file that stays ours, create_dir fails if it was taken anyway
+        let tmp = tempfile::NamedTempFile::new().unwrap();
+        let dir = tmp.path().with_extension("d");
+        std::fs::create_dir(&dir).unwrap();
+        let dir_name = dir.to_string_lossy().into_owned();
+        for file in &["libc.so", "libc.a", "libm.a", "libdl.so", "crt1.o"] {
+            std::fs::File::create(dir.join(file)).unwrap();
+        }
+        let search_paths = vec![String::from("/nonexistent"), dir_name.clone()];
+        let requests = ["c", "m", ":crt1.o"].iter()
+            .map(|name| (Request::Library(String::from(*name)), false, false))
+            .collect();
+        let inputs = resolve(&search_paths, requests);
+        let expected: Vec<String> = ["libc.a", "libm.a", "crt1.o"].iter()
+            .map(|f| dir.join(f).to_string_lossy().into_owned())
+            .collect();
+        let found = search_lib(&search_paths, "dl");
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(inputs.iter().map(|i| i.path.clone()).collect::<Vec<_>>(), expected);
+        assert_eq!(found, None);
+    }
+
+    #[test]
+    fn split_like_a_shell() {
+        let split = split_response_file("a  'b c'\t\"d'e\"\nf\\ g ''");
+        assert_eq!(split, ["a", "b c", "d'e", "f g", ""]);
+        assert!(split_response_file(" \n ").is_empty());
+    }
+
+    #[test]
+    fn values_in_every_form() {
+        let line = "-L/lib -L /usr/lib --library-path=/opt --library-path /x -library-path=/y";
+        let line = args(line);
+        let mut found = Vec::new();
+        let mut argc = 0;
+        while argc < line.len() {
+            found.push(ldarg(&line, &mut argc, "-L", "library-path").unwrap());
+            argc += 1;
+        }
+        assert_eq!(found, ["/lib", "/usr/lib", "/opt", "/x", "/y"]);
+        // --foo isn't -f with the value -oo
+        assert_eq!(ldarg(&args("--output=x"), &mut 0, "-o", ""), None);
+    }
+}
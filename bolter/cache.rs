@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use sha2::{Digest, Sha256};
+use elfkit::Elf;
+use tempfile::NamedTempFile;
+
+/// remembers the defined symbols of every input between runs, so relinking doesn't have to load
+/// the symbol table of every archive member just to find out it isn't needed.
+///
+/// there's one file per input, named after a hash of its path, size and modification time.
+/// a changed input gets a new name, so stale entries are never read.
+/// the file holds a "@member" line per elf, followed by one symbol name per line.
+pub struct ObjectCache {
+    dir: PathBuf,
+}
+
+impl ObjectCache {
+    pub fn new(dir: &str) -> ObjectCache {
+        if let Err(e) = fs::create_dir_all(dir) {
+            ::fail(format!("ld.elfkit: can not create cache directory {}: {}", dir, e));
+        }
+        ObjectCache{dir: PathBuf::from(dir)}
+    }
+
+    fn entry(&self, path: &str) -> Option<PathBuf> {
+        let meta  = fs::metadata(path).ok()?;
+        let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        let mut hasher = Sha256::default();
+        hasher.input(path.as_bytes());
+        let stamp = format!(":{}:{}.{}", meta.len(), mtime.as_secs(), mtime.subsec_nanos());
+        hasher.input(stamp.as_bytes());
+        let name = hasher.result().iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        Some(self.dir.join(name))
+    }
+
+    /// fill in the defined symbols of the elfs loaded from path, if they are cached.
+    /// returns false if they aren't, or if the cache doesn't match the members
+    pub fn restore(&self, path: &str, elfs: &mut [(String, Elf)]) -> bool {
+        let mut text = String::new();
+        match self.entry(path).map(fs::File::open) {
+            Some(Ok(mut f)) => if f.read_to_string(&mut text).is_err() {
+                return false;
+            },
+            _ => return false,
+        }
+
+        let mut members: Vec<(String, HashSet<String>)> = Vec::new();
+        for line in text.lines() {
+            if let Some(name) = line.strip_prefix("@") {
+                members.push((String::from(name), HashSet::new()));
+            } else if let Some(last) = members.last_mut() {
+                last.1.insert(String::from(line));
+            }
+        }
+        if members.len() != elfs.len() || members.iter().zip(elfs.iter()).any(|(m, e)| m.0 != e.0) {
+            return false;
+        }
+        for ((_, symbols), elf) in members.into_iter().zip(elfs.iter_mut()) {
+            elf.1.set_defined_symbols(symbols);
+        }
+        true
+    }
+
+    /// save the defined symbols of the elfs loaded from path
+    pub fn store(&self, path: &str, elfs: &mut [(String, Elf)]) {
+        let mut text = String::new();
+        for &mut (ref name, ref mut elf) in elfs.iter_mut() {
+            text += &format!("@{}\n", name);
+            match elf.defined_symbols() {
+                Ok(symbols) => for sym in symbols {
+                    text += sym;
+                    text += "\n";
+                },
+                // not worth failing for, the link will run into it again
+                Err(_) => return,
+            }
+        }
+        let entry = match self.entry(path) {
+            Some(e) => e,
+            None => return,
+        };
+        // write to a temporary of our own first, so a concurrent link never reads half a file.
+        // it is removed again if anything fails
+        let mut tmp = match NamedTempFile::new_in(entry.parent().unwrap_or(Path::new("."))) {
+            Ok(f) => f,
+            Err(_) => return,
+        };
+        if tmp.write_all(text.as_bytes()).is_ok() {
+            let _ = tmp.persist(&entry);
+        }
+    }
+}
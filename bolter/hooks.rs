@@ -0,0 +1,112 @@
+use std::collections::{BTreeMap, HashSet};
+use elfkit::{Relocation, Symbol};
+use ld::{Unit, UnitSegment};
+use relocations::Bootstrap;
+
+/// observer for the interesting steps of a link.
+/// every method does nothing by default, so a hook only implements what it cares about.
+/// this is where size analyzers, dependency graphs and custom diagnostics plug in.
+pub trait LinkerHook {
+    /// an input was pulled into the link because it defines something that's needed
+    fn object_loaded(&mut self, _name: &str, _units: &[Unit]) {}
+    /// a needed symbol was found in unit
+    fn symbol_resolved(&mut self, _name: &str, _needed_by: &Unit, _unit: &Unit) {}
+    /// unit was placed into the output at addr
+    fn unit_placed(&mut self, _unit: &Unit, _addr: u64) {}
+    /// unit came from a loaded object, but nothing needed it so it's not in the output
+    fn unit_discarded(&mut self, _unit: &Unit) {}
+    /// relocation in object has been turned into bootstrap code, or rejected
+    fn relocation_converted(&mut self, _object: &str, _reloc: &Relocation, _sym: &Symbol,
+                            _kind: &Bootstrap) {}
+    /// the output has been written
+    fn link_finished(&mut self) {}
+}
+
+/// the hook behind -t, -y and --print-gc-sections
+#[derive(Default)]
+pub struct Trace {
+    pub files:       bool,
+    pub symbols:     HashSet<String>,
+    pub gc_sections: bool,
+}
+
+impl LinkerHook for Trace {
+    fn object_loaded(&mut self, name: &str, _units: &[Unit]) {
+        if self.files {
+            println!("{}", name);
+        }
+    }
+
+    fn symbol_resolved(&mut self, name: &str, needed_by: &Unit, unit: &Unit) {
+        if self.symbols.contains(name) {
+            println!("{}: reference to {}", needed_by.object, name);
+            println!("{}: definition of {}", unit.object, name);
+        }
+    }
+
+    fn unit_discarded(&mut self, unit: &Unit) {
+        if self.gc_sections {
+            println!("removing unused section '{}' in file '{}'", unit.section, unit.object);
+        }
+    }
+}
+
+/// what one input contributed to the output, in bytes
+#[derive(Default, Clone, Debug)]
+pub struct ObjectStats {
+    pub code:        u64,
+    pub data:        u64,
+    pub bss:         u64,
+    /// in units that nothing needed
+    pub discarded:   u64,
+}
+
+impl ObjectStats {
+    /// bytes this object takes in the output file, bss only takes memory
+    pub fn file_size(&self) -> u64 {
+        self.code + self.data
+    }
+}
+
+/// the hook behind --stats, sizes per input object
+#[derive(Default)]
+pub struct Stats {
+    pub objects:     BTreeMap<String, ObjectStats>,
+}
+
+impl Stats {
+    pub fn total(&self) -> ObjectStats {
+        self.objects.values().fold(ObjectStats::default(), |acc, o| ObjectStats{
+            code:       acc.code + o.code,
+            data:       acc.data + o.data,
+            bss:        acc.bss + o.bss,
+            discarded:  acc.discarded + o.discarded,
+        })
+    }
+}
+
+impl LinkerHook for Stats {
+    fn unit_placed(&mut self, unit: &Unit, _addr: u64) {
+        let o = self.objects.entry(unit.object.clone()).or_default();
+        let size = unit.code.len() as u64;
+        match unit.segment {
+            UnitSegment::Executable => o.code += size,
+            UnitSegment::Data       => o.data += size,
+            UnitSegment::Bss        => o.bss += size,
+        }
+    }
+
+    fn unit_discarded(&mut self, unit: &Unit) {
+        self.objects.entry(unit.object.clone()).or_default().discarded += unit.code.len() as u64;
+    }
+
+    fn link_finished(&mut self) {
+        let mut objects: Vec<(&String, &ObjectStats)> = self.objects.iter().collect();
+        objects.sort_by_key(|&(_, o)| std::cmp::Reverse(o.file_size()));
+        println!("{:>10} {:>10} {:>10} {:>10}  object", "code", "data", "bss", "discarded");
+        let total = self.total();
+        for (name, o) in objects.into_iter().chain(Some((&String::from("total"), &total))) {
+            println!("{:>10} {:>10} {:>10} {:>10}  {}", o.code, o.data, o.bss, o.discarded, name);
+        }
+    }
+}
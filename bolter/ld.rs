@@ -1,12 +1,18 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::collections::HashSet;
 use std;
 use ::fail;
 use std::collections::hash_map::Entry;
+use std::fmt;
+use std::thread;
+use hooks::LinkerHook;
+use elfkit::attributes::{self, Attributes};
+use elfkit::linker;
+use elfkit::property::{self, GnuProperties};
 
 use elfkit::{
     Elf, Header, types, SegmentHeader, Section, SectionContent, Error,
-    SectionHeader, Dynamic, Symbol, Relocation, Strtab, SymbolSectionIndex};
+    SectionHeader, Dynamic, Symbol, Relocation, Strtab, SymbolSectionIndex, MachineFlags};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum LinkBehaviour {
@@ -19,7 +25,7 @@ impl Default for LinkBehaviour {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum UnitSegment {
     Executable,
     Data,
@@ -35,12 +41,24 @@ impl Default for UnitSegment{
 pub struct Unit {
     pub global_id:   u64,
     pub name:        String,
+    /// the input this unit came from, archive members as archive(member)
+    pub object:      String,
+    pub section:     String,
+    /// index of that section in the input, 0 for units the linker makes up
+    pub shndx:       usize,
     pub behaviour:   LinkBehaviour,
     pub segment:     UnitSegment,
     pub code:        Vec<u8>,
+    /// sh_addralign of the section, or the alignment of a COMMON symbol. at least 1
+    pub align:       u64,
     pub symbols:     Vec<Symbol>,
     pub relocations: Vec<Relocation>,
     pub deps:    Vec<u64>,
+    /// from SHF_GNU_RETAIN, link it whenever its object is loaded, even if nothing needs it
+    pub keep:        bool,
+    /// from SHF_LINK_ORDER, the unit this one describes. it is linked along with that one
+    /// and has to be laid out in the same order
+    pub link_order:  Option<u64>,
 
     s_lookup: HashMap<String, usize>,
 }
@@ -56,14 +74,20 @@ impl Unit {
 
         Unit {
             global_id:  0,
+            object:     name.clone(),
+            section:    String::new(),
+            shndx:      0,
             name:       name,
             behaviour:  behaviour,
             segment:    UnitSegment::Bss,
             code:       vec![0;8],
+            align:      8,
             symbols:    symbols,
             relocations:Vec::new(),
             s_lookup:   s_lookup,
             deps:   Vec::new(),
+            keep:   false,
+            link_order: None,
         }
     }
 
@@ -85,9 +109,11 @@ impl Unit {
                 format!("{}: linking to dynamic libraries is not implemented", name));
 
         let mut sec2global  = HashMap::new();
-        let mut units       = HashMap::new();
+        // by section index, so the units come out in the order of the sections
+        let mut units       = BTreeMap::new();
         let mut symbols     = (0, Vec::new());
         let mut relas       = Vec::new();
+        let mut linked      = Vec::new();
 
         for i in elf.sections.iter().enumerate().filter_map(|(i, ref sec)| {
             match sec.header.shtype {
@@ -108,10 +134,16 @@ impl Unit {
                     if sec.header.flags.contains(types::SectionFlags::ALLOC)  => {
                         *global_id_counter += 1;
                         sec2global.insert(*i, *global_id_counter);
+                        if sec.header.flags.contains(types::SectionFlags::LINK_ORDER) {
+                            linked.push((*i, sec.header.link as usize));
+                        }
 
                         units.insert(*i, Unit{
                             global_id:  *global_id_counter,
                             name:       sec.name.clone() + "." + &name.clone(),
+                            object:     name.clone(),
+                            section:    sec.name.clone(),
+                            shndx:      *i,
                             behaviour:  behaviour.clone(),
                             segment:    if sec.header.shtype == types::SectionType::NOBITS {
                                 UnitSegment::Bss
@@ -125,10 +157,13 @@ impl Unit {
                             } else {
                                 sec.content.into_raw().unwrap()
                             },
+                            align:      sec.header.addralign.max(1),
                             symbols:        Vec::new(),
                             relocations:    Vec::new(),
                             s_lookup:       HashMap::new(),
                             deps:       Vec::new(),
+                            keep:       sec.header.flags.contains(types::SectionFlags::GNU_RETAIN),
+                            link_order: None,
                         });
                     },
                     types::SectionType::SYMTAB if behaviour == LinkBehaviour::Static => {
@@ -149,7 +184,7 @@ impl Unit {
 
             // copy all symbols from symtab where .shndx is this obj
             for (i, sym) in symbols.1.iter().enumerate() {
-                if sym.shndx == SymbolSectionIndex::Section(*obj_shndx as u16) {
+                if sym.shndx == SymbolSectionIndex::Section(*obj_shndx as u32) {
                     let mut sym = sym.clone();
                     sym.shndx = SymbolSectionIndex::Global(obj.global_id);
                     smap.insert(i, obj.symbols.len());
@@ -198,6 +233,32 @@ impl Unit {
 
         }
 
+        // nothing refers to metadata like .ARM.exidx or __patchable_function_entries,
+        // so it comes along with the section it describes instead
+        for (i, link) in linked {
+            let id = match sec2global.get(&link) {
+                Some(id) => *id,
+                None => fail(format!("{}: SHF_LINK_ORDER section {} links to section {} which is not allocated",
+                                     name, units[&i].section, link)),
+            };
+            let meta = units.get_mut(&i).unwrap();
+            meta.link_order = Some(id);
+            let meta = meta.global_id;
+            units.get_mut(&link).unwrap().deps.push(meta);
+        }
+
+        // absolute symbols are in no section, so they go with the first unit of the object,
+        // which gets linked in when one of them is needed. -b binary makes a _size one
+        if let Some(first) = units.keys().next().cloned() {
+            let obj = units.get_mut(&first).unwrap();
+            for sym in &symbols.1 {
+                if sym.shndx == SymbolSectionIndex::Absolute && sym.bind != types::SymbolBind::LOCAL {
+                    obj.s_lookup.insert(sym.name.clone(), obj.symbols.len());
+                    obj.symbols.push(sym.clone());
+                }
+            }
+        }
+
         let mut units = units.into_iter().map(|(k,v)|v).collect::<Vec<Unit>>();
 
 
@@ -209,6 +270,8 @@ impl Unit {
 
                 let symname = sym.name.clone();
                 let symsize = sym.size;
+                // st_value of a COMMON symbol is its alignment
+                let symalign = sym.value.max(1);
                 let mut sym = sym.clone();
                 sym.value = 0;
                 sym.shndx = SymbolSectionIndex::Global(*global_id_counter);
@@ -221,13 +284,19 @@ impl Unit {
                 units.push(Unit{
                     global_id:      *global_id_counter,
                     name:           String::from(".common.") + &symname,
+                    object:         name.clone(),
+                    section:        String::from("COMMON"),
+                    shndx:          0,
                     behaviour:      behaviour.clone(),
                     segment:        UnitSegment::Bss,
                     code:           vec![0;symsize as usize],
+                    align:          symalign,
                     symbols:        symbols,
                     relocations:    Vec::new(),
                     s_lookup:       s_lookup,
                     deps:       Vec::new(),
+                    keep:       false,
+                    link_order: None,
                 });
             }
         }
@@ -262,6 +331,29 @@ impl Unit {
     }
 }
 
+/// a relocation that refers to a symbol
+pub struct Reference {
+    pub object:     String,
+    pub section:    String,
+    pub offset:     u64,
+}
+
+/// a global symbol that no input defines, with everything that refers to it
+pub struct UndefinedSymbol {
+    pub name:       String,
+    pub references: Vec<Reference>,
+}
+
+impl fmt::Display for UndefinedSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "undefined reference to {}", self.name)?;
+        for r in &self.references {
+            write!(f, "\n   referenced from {} section {}+0x{:x}", r.object, r.section, r.offset)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Default)]
 pub struct Lookup {
     pub units:          Vec<Unit>,
@@ -271,6 +363,27 @@ pub struct Lookup {
     pub by_name:        HashMap<String, usize>,
 
     pub symbols2units:  HashMap<usize, usize>,
+
+    /// from --allow-multiple-definition, keep the first definition instead of failing
+    pub allow_multiple_definition: bool,
+    /// from --unresolved-symbols=ignore-all, undefined symbols resolve to 0
+    pub allow_undefined: bool,
+
+    pub hooks:          Vec<Box<dyn LinkerHook>>,
+    /// build attributes of all objects pulled in so far
+    pub attributes:     Attributes,
+    /// e_flags of all objects pulled in so far, None before the first
+    pub machine_flags:  Option<MachineFlags>,
+    /// EI_OSABI of all objects pulled in so far
+    pub abi:            types::Abi,
+    /// program properties of every object pulled in so far, empty for those without any
+    pub properties:     Vec<GnuProperties>,
+    /// from --threads, how many threads index the inputs. 0 means one per cpu
+    pub threads:        usize,
+    /// the debug sections of every object pulled in so far, by the name of the object
+    pub debug:          Vec<(String, linker::DebugInput)>,
+    /// from -S and -s, whether to collect them
+    pub strip:          linker::Strip,
 }
 
 impl Lookup {
@@ -285,7 +398,7 @@ impl Lookup {
         let ui = self.units.len();
 
         for sym in &unit.symbols {
-            self.insert_symbol(sym.clone(), ui, &unit.name);
+            self.insert_symbol(sym.clone(), ui, &unit);
         }
 
         self.by_id.insert(unit.global_id.clone(), self.units.len());
@@ -327,7 +440,12 @@ impl Lookup {
     }
 
 
-    fn insert_symbol(&mut self, sym: Symbol, unit_index: usize, obj_name: &str) -> usize {
+    /// where a symbol is defined, for diagnostics
+    fn definition_site(unit: &Unit, sym: &Symbol) -> String {
+        format!("{} section {}+0x{:x}", unit.object, unit.section, sym.value)
+    }
+
+    fn insert_symbol(&mut self, sym: Symbol, unit_index: usize, unit: &Unit) -> usize {
         match sym.stype {
             types::SymbolType::NOTYPE | types::SymbolType::OBJECT | types::SymbolType::FUNC | types::SymbolType::TLS => {
                 if sym.bind == types::SymbolBind::LOCAL {
@@ -343,6 +461,8 @@ impl Lookup {
                     },
                     Entry::Occupied(o) => {
                         let sym2 = &mut self.symbols[*o.get()];
+                        // whichever wins, a hidden reference makes the definition hidden
+                        let vis = linker::merge_visibility(sym.vis(), sym2.vis());
 
                         match Lookup::symbol_lookup_priority(&sym, sym2) {
                             1 =>  {
@@ -350,12 +470,18 @@ impl Lookup {
                                 self.symbols2units.insert(*o.get(), unit_index);
                             },
                             2 => {},
+                            // like gnu ld, the first definition wins
+                            _ if self.allow_multiple_definition => {},
                             _ => {
+                                let first = &self.units[self.symbols2units[o.get()]];
                                 fail(format!(
-                                        "{}: re-export of symbol {:?} \n   already defined in {} as {:?}",
-                                        obj_name, sym, self.units[self.symbols2units[o.get()]].name, sym2));
+                                        "ld.elfkit: multiple definition of {}\n   \
+                                        first defined in {}\n   redefined in {}",
+                                        sym.name, Lookup::definition_site(first, sym2),
+                                        Lookup::definition_site(unit, &sym)));
                             }
                         }
+                        sym2.set_vis(vis);
                         *o.get()
                     }
                 }
@@ -365,13 +491,122 @@ impl Lookup {
     }
 
 
-    pub fn link(&mut self, mut elfs: Vec<(String,Elf)>) {
+    /// objects built for different abis can't be linked together,
+    /// so their attributes have to agree with everything loaded before
+    fn merge_attributes(&mut self, name: &str, elf: &mut Elf) {
+        let shtype = attributes::section_type(&elf.header.machine);
+        let i = match elf.sections.iter().position(|sec| sec.header.shtype == shtype) {
+            Some(i) => i,
+            None => return,
+        };
+        let r = elf.load_at(i).and_then(|_| {
+            match attributes::machine_attributes(&elf.sections, &elf.header)? {
+                Some(attrs) => self.attributes.merge(&attrs),
+                None => Ok(()),
+            }
+        });
+        if let Err(e) = r {
+            fail(format!("{}: {}", name, e));
+        }
+    }
+
+    /// e_flags and the os abi have to agree with the objects loaded before too
+    fn merge_machine_flags(&mut self, name: &str, elf: &Elf) {
+        let flags = elf.header.machine_flags();
+        match linker::merge_abi(&self.abi, &elf.header.ident_abi) {
+            Ok(abi) => self.abi = abi,
+            Err(e) => fail(format!("{}: {}", name, e)),
+        }
+        let r = match self.machine_flags {
+            Some(ref mut merged) => merged.merge(&flags),
+            None => {
+                self.machine_flags = Some(flags);
+                Ok(())
+            }
+        };
+        if let Err(e) = r {
+            fail(format!("{}: {}", name, e));
+        }
+    }
+
+    fn collect_properties(&mut self, name: &str, elf: &mut Elf) {
+        let i = elf.sections.iter().position(|sec| sec.name == property::SECTION_NAME);
+        let r = match i {
+            None => Ok(Some(GnuProperties::default())),
+            Some(i) => elf.load_at(i).and_then(|_| {
+                property::gnu_properties(&elf.sections, &elf.header)
+            }),
+        };
+        match r {
+            Ok(p) => self.properties.push(p.unwrap_or_default()),
+            Err(e) => fail(format!("{}: {}", name, e)),
+        }
+    }
+
+    fn collect_debug(&mut self, name: &str, elf: &mut Elf) {
+        if !self.strip.keeps_debug() {
+            return;
+        }
+        match linker::DebugInput::from_elf(elf) {
+            Ok(ref debug) if debug.is_empty() => {},
+            Ok(debug) => self.debug.push((String::from(name), debug)),
+            Err(e) => fail(format!("{}: {}", name, e)),
+        }
+    }
+
+    /// make units of one input, all of them candidates except the ones that have to be kept,
+    /// which are linked right away along with what they need
+    fn load_object(&mut self, name: String, mut elf: Elf, candidates: &mut BTreeMap<u64, Unit>,
+                   global_id_counter: &mut u64) {
+        self.merge_attributes(&name, &mut elf);
+        self.merge_machine_flags(&name, &elf);
+        self.collect_properties(&name, &mut elf);
+        self.collect_debug(&name, &mut elf);
+        let units = Unit::from_elf(name.clone(), elf, global_id_counter);
+        for hook in &mut self.hooks {
+            hook.object_loaded(&name, &units);
+        }
+        let keep: Vec<u64> = units.iter().filter(|u| u.keep)
+            .map(|u| u.global_id).collect();
+        for unit in units {
+            candidates.insert(unit.global_id, unit);
+        }
+        for id in keep {
+            if let Some(unit) = candidates.remove(&id) {
+                self.resursive_insert(candidates, unit, &mut HashSet::new());
+            }
+        }
+    }
+
+    /// pull in units until every global symbol is defined.
+    /// symbols that can't be found are all collected and returned at the end
+    pub fn link(&mut self, mut elfs: Vec<(String,Elf)>) -> Result<(), Vec<UndefinedSymbol>> {
+        let providers = Lookup::providers(&mut elfs, self.threads);
+        let mut elfs = elfs.into_iter().map(Some).collect::<Vec<Option<(String,Elf)>>>();
         let mut global_id_counter = 10;
-        let mut candidates = HashMap::new();
+        // by global id, which counts up as objects are loaded, so when several candidates
+        // define a symbol the one on the command line first is taken, every time
+        let mut candidates = BTreeMap::new();
+        let mut undefined = HashSet::new();
+
+        // objects on the command line are linked even if nothing needs them when they have
+        // sections that must survive gc. archive members only if something needs them
+        for slot in &mut elfs {
+            let retained = match *slot {
+                Some((ref name, ref elf)) => !name.ends_with(')') && linker::retains_sections(elf),
+                None => false,
+            };
+            if retained {
+                let (name, elf) = slot.take().unwrap();
+                self.load_object(name, elf, &mut candidates, &mut global_id_counter);
+            }
+        }
+
         loop {
             println!("lookup iteration");
             let missing = self.symbols.iter().enumerate().filter_map(|(i, ref sym)|{
-                if sym.shndx == SymbolSectionIndex::Undefined && sym.bind == types::SymbolBind::GLOBAL {
+                if sym.shndx == SymbolSectionIndex::Undefined && sym.bind == types::SymbolBind::GLOBAL
+                    && !undefined.contains(&i) {
                     Some(i)
                 } else {
                     None
@@ -383,38 +618,21 @@ impl Lookup {
             }
 
             for mi in missing {
-                let mut found = None;
-                let was_needed_by = self.units[self.symbols2units[&mi]].name.clone();
-
-                let mut cont = true;
-                while cont {
-                    cont = false;
-                    for ei in 0..elfs.len() {
-                        let contains = match elfs[ei].1.contains_symbol(&self.symbols[mi].name) {
-                            Ok(v)  => v,
-                            Err(e) => fail(format!("error in self in {} : {:?}",
-                                                   elfs[ei].0, e)),
-                        };
-                        if contains {
-                            let elf = elfs.swap_remove(ei);
-                            for unit in Unit::from_elf(elf.0, elf.1, &mut global_id_counter) {
-                                candidates.insert(unit.global_id.clone(), unit);
-                            }
-                            cont = true;
-                            break;
-                        }
+                let needed_by = self.symbols2units[&mi];
+                let was_needed_by = self.units[needed_by].name.clone();
+
+                // all inputs that define it, in command line order
+                for ei in providers.get(&self.symbols[mi].name).into_iter().flatten() {
+                    if let Some((name, elf)) = elfs[*ei].take() {
+                        self.load_object(name, elf, &mut candidates, &mut global_id_counter);
                     }
                 }
 
 
-                for (id, candidate) in candidates.iter() {
-                    for sym in candidate.lookup(&self.symbols[mi].name) {
-                        if sym.shndx != SymbolSectionIndex::Undefined {
-                            found = Some(id.clone());
-                            break;
-                        }
-                    }
-                }
+                let found = candidates.iter().find(|&(_, candidate)| {
+                    candidate.lookup(&self.symbols[mi].name)
+                        .is_some_and(|sym| sym.shndx != SymbolSectionIndex::Undefined)
+                }).map(|(id, _)| *id);
 
                 if let Some(id) = found  {
                     let unit = candidates.remove(&id).unwrap();
@@ -431,19 +649,91 @@ impl Lookup {
                         self.symbols[mi]);
                     }
 
+                    let unit = &self.units[self.symbols2units[&mi]];
+                    for hook in &mut self.hooks {
+                        hook.symbol_resolved(&self.symbols[mi].name, &self.units[needed_by], unit);
+                    }
+
                 } else {
                     let sym = &self.symbols[mi];
                     if sym.shndx == SymbolSectionIndex::Undefined && sym.bind == types::SymbolBind::GLOBAL {
-                        fail(format!("{}: undefined reference to {}",
-                                     self.units[self.symbols2units[&mi]].name,
-                                     self.symbols[mi].name));
+                        undefined.insert(mi);
+                    }
+                }
+            }
+        }
+
+        for unit in candidates.values() {
+            for hook in &mut self.hooks {
+                hook.unit_discarded(unit);
+            }
+        }
+
+        if undefined.is_empty() || self.allow_undefined {
+            return Ok(());
+        }
+        let mut names = undefined.iter().map(|i| self.symbols[*i].name.clone())
+            .collect::<Vec<String>>();
+        names.sort();
+        Err(names.into_iter().map(|name| {
+            let references = self.references(&name);
+            UndefinedSymbol{name, references}
+        }).collect())
+    }
+
+    /// which inputs define each symbol name.
+    /// loading the symbol tables is what takes time, so the inputs are split into one shard
+    /// per thread and indexed concurrently. threads 0 means one per cpu
+    fn providers(elfs: &mut [(String,Elf)], threads: usize) -> HashMap<String, Vec<usize>> {
+        let threads = match threads {
+            0 => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            n => n,
+        };
+        let shard_len = std::cmp::max(1, elfs.len().div_ceil(threads));
+
+        let shards = thread::scope(|scope| {
+            let workers = elfs.chunks_mut(shard_len).map(|shard| scope.spawn(move || {
+                shard.iter_mut().map(|&mut (ref name, ref mut elf)| {
+                    match elf.defined_symbols() {
+                        Ok(symbols) => symbols.iter().cloned().collect::<Vec<String>>(),
+                        Err(e) => fail(format!("error in self in {} : {:?}", name, e)),
                     }
+                }).collect::<Vec<Vec<String>>>()
+            })).collect::<Vec<_>>();
+            workers.into_iter().map(|w| match w.join() {
+                Ok(v) => v,
+                Err(_) => fail(String::from("ld.elfkit: symbol indexing thread failed")),
+            }).collect::<Vec<Vec<Vec<String>>>>()
+        });
+
+        let mut r : HashMap<String, Vec<usize>> = HashMap::new();
+        for (ei, symbols) in shards.into_iter().flatten().enumerate() {
+            for sym in symbols {
+                r.entry(sym).or_default().push(ei);
+            }
+        }
+        r
+    }
+
+    /// every relocation in the linked units that refers to the undefined symbol name
+    fn references(&self, name: &str) -> Vec<Reference> {
+        let mut r = Vec::new();
+        for unit in &self.units {
+            for rela in &unit.relocations {
+                let sym = &unit.symbols[rela.sym as usize];
+                if sym.name == name && sym.shndx == SymbolSectionIndex::Undefined {
+                    r.push(Reference{
+                        object:     unit.object.clone(),
+                        section:    unit.section.clone(),
+                        offset:     rela.addr,
+                    });
                 }
             }
         }
+        r
     }
 
-    fn resursive_insert(&mut self, candidates: &mut HashMap<u64, Unit>,
+    fn resursive_insert(&mut self, candidates: &mut BTreeMap<u64, Unit>,
                         unit: Unit, promise_insert: &mut HashSet<u64>) {
         promise_insert.insert(unit.global_id);
 
@@ -461,5 +751,50 @@ impl Lookup {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elfkit::ElfBuilder;
+    use std::io::Cursor;
+
+    fn defining_f(code: u8) -> Elf {
+        let f = Symbol {
+            name: String::from("f"),
+            stype: types::SymbolType::FUNC,
+            bind: types::SymbolBind::GLOBAL,
+            ..Symbol::default()
+        };
+        let mut elf = ElfBuilder::new(types::Machine::X86_64)
+            .etype(types::ElfType::REL)
+            .text(vec![code])
+            .symbol(".text", f)
+            .build()
+            .unwrap();
+        let mut io = Cursor::new(Vec::new());
+        elf.to_writer(&mut io).unwrap();
+        Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap()
+    }
 
-
+    #[test]
+    fn first_definition_on_the_command_line_wins() {
+        for threads in 1..4 {
+            let mut lookup = Lookup{threads, ..Lookup::default()};
+            let f = Symbol {
+                name: String::from("f"),
+                bind: types::SymbolBind::GLOBAL,
+                ..Symbol::default()
+            };
+            lookup.insert_unit(Unit::fake(String::from("exe"), LinkBehaviour::Static, vec![f]));
+
+            let inputs = ["a.o", "b.o", "c.o"].iter().enumerate()
+                .map(|(i, name)| (String::from(*name), defining_f(i as u8))).collect();
+            assert!(lookup.link(inputs).is_ok());
+            let unit = match lookup.get_by_name("f").unwrap().shndx {
+                SymbolSectionIndex::Global(id) => &lookup.units[lookup.by_id[&id]],
+                ref shndx => panic!("unexpected {:?}", shndx),
+            };
+            assert_eq!(unit.object, "a.o");
+            assert_eq!(unit.code, vec![0]);
+        }
+    }
+}
@@ -1,19 +1,21 @@
 extern crate colored;
 #[macro_use] extern crate elfkit;
 extern crate byteorder;
-extern crate goblin;
 extern crate sha2;
+extern crate tempfile;
 
 use elfkit::{
     Elf, Header, types, SegmentHeader, Section, SectionContent, Error,
     SectionHeader, Dynamic, Symbol, Relocation, Strtab, SymbolSectionIndex};
 
+use elfkit::attributes;
 use elfkit::filetype;
+use elfkit::property;
 use elfkit::linker;
 
 use std::fs::OpenOptions;
 use elfkit::dynamic::DynamicContent;
-use elfkit::relocation::RelocationType;
+use elfkit::arch;
 use std::collections::HashMap;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
@@ -27,6 +29,10 @@ mod args;
 use args::*;
 mod relocations;
 use relocations::*;
+mod hooks;
+use hooks::*;
+mod cache;
+use cache::ObjectCache;
 
 pub fn fail(msg: String) -> ! {
     println!("{}", msg.red());
@@ -35,19 +41,57 @@ pub fn fail(msg: String) -> ! {
 
 fn main() {
     let ldoptions  = parse_ld_options();
-    let mut elfs   = load_elfs(ldoptions.object_paths);
+    if ldoptions.output_kind == OutputKind::Shared {
+        fail(String::from("ld.elfkit: shared objects are not supported"));
+    }
+    let cache      = ldoptions.cache_dir.as_ref().map(|dir| ObjectCache::new(dir));
+    let mut elfs   = load_elfs(&ldoptions.inputs, &ldoptions.search_paths, cache.as_ref(),
+                               ldoptions.lto_compiler.as_deref());
     let mut lookup = Lookup::default();
+    lookup.allow_multiple_definition = ldoptions.allow_multiple_definition;
+    let static_pie = ldoptions.output_kind == OutputKind::StaticPie;
 
-    let mut start  = Symbol::default();
-    start.name     = String::from("_start");
-    start.bind     = types::SymbolBind::GLOBAL;
     let mut got    = Symbol::default();
     got.name       = String::from("_GLOBAL_OFFSET_TABLE_"); //TODO
     got.shndx      = SymbolSectionIndex::Global(0);
     got.bind       = types::SymbolBind::GLOBAL;
-    lookup.insert_unit(Unit::fake(String::from("exe"), LinkBehaviour::Static, vec![start, got]));
-
-    lookup.link(elfs);
+    let mut fake   = vec![got];
+    // -e with an address doesn't pull in anything
+    if linker::entry_address(&ldoptions.entry).is_none() {
+        let mut start  = Symbol::default();
+        start.name     = ldoptions.entry.clone();
+        start.bind     = types::SymbolBind::GLOBAL;
+        fake.insert(0, start);
+    }
+    // rcrt1 finds the relocations through _DYNAMIC and the load address through __ehdr_start.
+    // both get their address once the sections are placed
+    if static_pie {
+        for name in &["_DYNAMIC", "__ehdr_start"] {
+            let mut sym = Symbol::default();
+            sym.name    = String::from(*name);
+            sym.shndx   = SymbolSectionIndex::Absolute;
+            sym.bind    = types::SymbolBind::GLOBAL;
+            fake.push(sym);
+        }
+    }
+    lookup.insert_unit(Unit::fake(String::from("exe"), LinkBehaviour::Static, fake));
+
+    lookup.allow_undefined = ldoptions.allow_undefined;
+    lookup.threads         = ldoptions.threads;
+    lookup.strip           = ldoptions.strip;
+    if ldoptions.trace_files || !ldoptions.trace_symbols.is_empty() || ldoptions.print_gc_sections {
+        lookup.hooks.push(Box::new(Trace{
+            files:          ldoptions.trace_files,
+            symbols:        ldoptions.trace_symbols.iter().cloned().collect(),
+            gc_sections:    ldoptions.print_gc_sections,
+        }));
+    }
+    if ldoptions.stats {
+        lookup.hooks.push(Box::new(Stats::default()));
+    }
+    if let Err(undefined) = lookup.link(elfs) {
+        fail(undefined.iter().map(|u| format!("ld.elfkit: {}", u)).collect::<Vec<String>>().join("\n"));
+    }
     // TODO garbage collect unused units
 
     println!("linking {} units into exe", lookup.units.len());
@@ -56,113 +100,290 @@ fn main() {
     let mut out_elf = Elf::default();
     out_elf.header.ident_class      = types::Class::Class64;
     out_elf.header.ident_endianness = types::Endianness::LittleEndian;
-    out_elf.header.ident_abi        = types::Abi::SYSV;
+    out_elf.header.ident_abi        = match linker::symbol_abi(&lookup.abi, &lookup.symbols) {
+        Ok(abi) => abi,
+        Err(e) => fail(format!("ld.elfkit: {}", e)),
+    };
     out_elf.header.etype            = types::ElfType::DYN;
     out_elf.header.machine          = types::Machine::X86_64;
 
-    let mut sc_interp  : Vec<u8> = ldoptions.dynamic_linker.trim().bytes().collect();
-    sc_interp.push(0);
     let mut sc_rela    : Vec<Relocation>        = Vec::new();
-    let mut sc_dynsym  : Vec<Symbol>            = vec![Symbol::default()];
+    let (flags, flags1) = linker::z_flags(&ldoptions.z);
     let mut sc_dynamic : Vec<Dynamic>           = vec![
         Dynamic{
             dhtype: types::DynamicType::FLAGS_1,
-            content: DynamicContent::Flags1(types::DynamicFlags1::PIE),
+            content: DynamicContent::Flags1(types::DynamicFlags1::PIE | flags1),
         },
     ];
+    if !flags.is_empty() {
+        sc_dynamic.push(Dynamic{
+            dhtype: types::DynamicType::FLAGS,
+            content: DynamicContent::Flags(flags),
+        });
+    }
+    if flags.contains(types::DynamicFlags::BIND_NOW) {
+        sc_dynamic.push(Dynamic{
+            dhtype: types::DynamicType::BIND_NOW,
+            content: DynamicContent::Address(0),
+        });
+    }
+    if !static_pie {
+        // ld.so points it at r_debug, which is how debuggers find the link map
+        sc_dynamic.push(Dynamic{
+            dhtype: types::DynamicType::DEBUG,
+            content: DynamicContent::Address(0),
+        });
+    }
     let mut sc_symtab : Vec<Symbol> = vec![Symbol::default()];
 
 
     out_elf.sections.insert(0, Section::default());
-    if sc_interp.len() > 1 {
-        out_elf.sections.push(Section::new(String::from(".interp"), types::SectionType::PROGBITS,
-        types::SectionFlags::ALLOC,
-        SectionContent::Raw(sc_interp), 0,0));
+    let interp = ldoptions.dynamic_linker.trim();
+    if !interp.is_empty() && ldoptions.output_kind != OutputKind::Static && !static_pie {
+        out_elf.sections.push(linker::interp_section(interp));
+    }
+
+    if let Some(ref flags) = lookup.machine_flags {
+        if let Err(e) = out_elf.header.set_machine_flags(flags) {
+            fail(format!("ld.elfkit: {}", e));
+        }
+    }
+
+    // the output only supports cet if every object it was linked from does
+    let properties = property::merge(&lookup.properties);
+    if !properties.properties.is_empty() {
+        out_elf.sections.push(properties.section(&out_elf.header).unwrap());
+    }
+
+    // a static pie has rcrt1 apply the relocations instead of the bootstrap.
+    // it reads .dynamic before the code runs, so .dynamic is placed before the code,
+    // with slots for what linker::dynamic adds once the relocations are known: two for .dynstr,
+    // three for .dynsym and its .hash, up to five for .rela.dyn and the DT_NULL
+    let sh_index_dynamic = out_elf.sections.len();
+    let dynamic_slots = sc_dynamic.len() + 2 + 3 + 5 + 1;
+    if static_pie {
+        let null = || Dynamic{
+            dhtype: types::DynamicType::NULL,
+            content: DynamicContent::Address(0),
+        };
+        out_elf.sections.push(Section::new(String::from(".dynamic"), types::SectionType::DYNAMIC,
+        types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+        SectionContent::Dynamic((0..dynamic_slots).map(|_| null()).collect()), 0,0));
     }
 
     //--------------------- prepare bootstrap section
-    let boostrap_len = 1 + 4 + lookup.units.iter().fold(0, |acc, ref u| {
+    let handler = arch::handler(&out_elf.header).unwrap();
+    let relro = ldoptions.z.iter().any(|z| z == "relro");
+    let boostrap_len = 1 + 4 + if relro { BOOTSTRAP_MPROTECT_LEN } else { 0 } +
+        lookup.units.iter().fold(0, |acc, ref u| {
         acc + u.relocations.iter().fold(0, |acc, ref reloc|{
-            acc + match reloc.rtype {
-                RelocationType::R_X86_64_64 => 3 + 4 + 3 + 4,
-                RelocationType::R_X86_64_GOTPCREL |
-                    RelocationType::R_X86_64_GOTPCRELX |
-                    RelocationType::R_X86_64_REX_GOTPCRELX => 3 + 4 + 3 + 4 + 2 + 4 + 4,
-                RelocationType::R_X86_64_PC32 |
-                    RelocationType::R_X86_64_PLT32 => 2 + 4 + 4,
-                _ => 0,
-            }
+            acc + Bootstrap::of(handler, &reloc.rtype).len()
         })
     });
     let mut bootstrap = vec![0;boostrap_len];
     let sh_index_bootstrap = out_elf.sections.len();
-    out_elf.sections.push(Section::new(String::from(".xo.bootstrap"), types::SectionType::PROGBITS,
-                                       types::SectionFlags::ALLOC |
-                                       types::SectionFlags::EXECINSTR,
-                                       SectionContent::Raw(bootstrap),
-                                       0,0));
+    if !static_pie {
+        out_elf.sections.push(Section::new(String::from(".xo.bootstrap"),
+                                           types::SectionType::PROGBITS,
+                                           types::SectionFlags::ALLOC |
+                                           types::SectionFlags::EXECINSTR,
+                                           SectionContent::Raw(bootstrap),
+                                           0,0));
+    }
 
     out_elf.sync_all().unwrap();
     linker::relayout(&mut out_elf, 0x300).unwrap();
 
-    let blt_bootstrap_sym = Symbol{
-        shndx:  SymbolSectionIndex::Section(sh_index_bootstrap as u16),
-        value:  out_elf.sections[sh_index_bootstrap].header.addr,
-        size:   out_elf.sections[sh_index_bootstrap].header.size,
-        name:   String::from("__blt_bootstrap"),
-        stype:  types::SymbolType::FUNC,
-        bind:   types::SymbolBind::LOCAL,
-        vis:    types::SymbolVis::DEFAULT,
-    };
-    sc_symtab.push(blt_bootstrap_sym.clone());
+    if !static_pie {
+        let blt_bootstrap_sym = Symbol{
+            shndx:  SymbolSectionIndex::Section(sh_index_bootstrap as u32),
+            value:  out_elf.sections[sh_index_bootstrap].header.addr,
+            size:   out_elf.sections[sh_index_bootstrap].header.size,
+            name:   String::from("__blt_bootstrap"),
+            stype:  types::SymbolType::FUNC,
+            bind:   types::SymbolBind::LOCAL,
+            other:  0,
+        };
+        sc_symtab.push(blt_bootstrap_sym.clone());
+    }
 
 
 
     //----------------------------layout
     let mut sc_relink   = Vec::new();
-    let mut vaddr       = out_elf.sections[sh_index_bootstrap].header.addr +
-        out_elf.sections[sh_index_bootstrap].header.size;
+    let mut vaddr       = {
+        let last = &out_elf.sections[out_elf.sections.len() - 1].header;
+        last.addr + last.size
+    };
     let mut sc_text     = Vec::new();
     let mut sc_bss      = 0;
     let mut unit_addresses = HashMap::new();
 
-    lookup.units.sort_unstable_by(|a,b| {
-        a.segment.cmp(&b.segment)
+    // --symbol-ordering-file moves the units defining the listed symbols to the front of
+    // their segment, in the order of the file
+    let order = match ldoptions.symbol_ordering_file {
+        Some(ref path) => match std::fs::read_to_string(path) {
+            Ok(text) => linker::symbol_ordering(&text),
+            Err(e) => fail(format!("while reading {}: {}", path, e)),
+        },
+        None => HashMap::new(),
+    };
+    let mut missing: Vec<(&usize, &String)> = order.iter()
+        .filter(|&(name, _)| match lookup.get_by_name(name) {
+            Some(sym) => sym.shndx == SymbolSectionIndex::Undefined,
+            None => true,
+        })
+        .map(|(name, i)| (i, name)).collect();
+    missing.sort();
+    for (_, name) in missing {
+        println!("{}", format!("ld.elfkit: symbol ordering file: no such symbol: {}", name).yellow());
+    }
+    // there is no PT_TLS, so thread locals would end up shared by all threads
+    for unit in &lookup.units {
+        let out = linker::output_section(&unit.section, false);
+        if let Some(out @ ".tdata") | Some(out @ ".tbss") = out {
+            fail(format!("ld.elfkit: {}: {} goes into {}, thread local storage is not implemented",
+                         unit.object, unit.section, out));
+        }
+    }
+    // the input sections of one output section of GNU ld's script stay together,
+    // in the order of its script
+    lookup.units.sort_by_cached_key(|unit| {
+        (unit.segment.clone(), linker::output_section_rank(&unit.section),
+         linker::ordering_priority(&order, &unit.symbols))
+    });
+    let placed: HashMap<u64, usize> = lookup.units.iter().enumerate()
+        .map(|(i, unit)| (unit.global_id, i)).collect();
+    lookup.units.sort_by_cached_key(|unit| {
+        let linked = unit.link_order.map(|id| placed.get(&id).cloned().unwrap_or(usize::MAX));
+        (unit.segment.clone(), linker::link_order_key(&unit.section, linked))
     });
     lookup.reindex();
 
+    // where each input section ended up, for the relocations of the debug sections
+    // with -z relro the got gets pages of its own between the text and the bss,
+    // and the bootstrap makes them read only once it filled them
+    let sh_index_text = out_elf.sections.len();
+    let sh_index_got = sh_index_text + 1;
+    let sh_index_bss = if relro { sh_index_got + 1 } else { sh_index_text + 1 };
+    let got_len = if relro {
+        lookup.units.iter().fold(0, |acc, ref u| {
+            acc + u.relocations.iter()
+                .filter(|reloc| Bootstrap::of(handler, &reloc.rtype) == Bootstrap::Got).count()
+        })
+    } else {
+        0
+    };
+    let mut remap = linker::SectionRemap::new();
+    let mut bss_units = Vec::new();
+
+    // every unit starts at a multiple of its alignment. .xo.text gets the largest of them
+    // and .xo.bss the largest of its own, so relayout leaves both where they are placed here
+    let text_align = lookup.units.iter().map(|u| u.align).max().unwrap_or(1);
+    let bss_align = lookup.units.iter().filter(|u| u.segment == UnitSegment::Bss)
+        .map(|u| u.align).max().unwrap_or(1);
+    vaddr = vaddr.div_ceil(text_align) * text_align;
+    let text_addr = vaddr;
+    let mut bss_addr = None;
     for unit in &mut lookup.units {
+        if unit.segment == UnitSegment::Bss && bss_addr.is_none() {
+            if relro {
+                // .xo.bss starts behind the pages relayout gives the got
+                vaddr = linker::page_align(linker::page_align(vaddr) + got_len as u64 * 8);
+                vaddr = vaddr.div_ceil(bss_align) * bss_align;
+            } else {
+                // .xo.bss starts where the text ends
+                let pad = vaddr.div_ceil(bss_align) * bss_align - vaddr;
+                sc_text.resize(sc_text.len() + pad as usize, 0);
+                vaddr += pad;
+            }
+            bss_addr = Some(vaddr);
+        }
+        let pad = vaddr.div_ceil(unit.align) * unit.align - vaddr;
+        vaddr += pad;
+        for hook in &mut lookup.hooks {
+            hook.unit_placed(unit, vaddr);
+        }
+        let size = unit.code.len() as u64;
         match unit.segment {
             UnitSegment::Executable | UnitSegment::Data => {
+                sc_text.resize(sc_text.len() + pad as usize, 0);
+                if unit.shndx > 0 {
+                    remap.insert(&unit.object, unit.shndx, linker::PlacedSection{
+                        shndx: sh_index_text, offset: sc_text.len() as u64, size});
+                }
+                // .relink has 32 bit offsets
+                if sc_text.len() > u32::MAX as usize {
+                    fail(format!("ld.elfkit: {} starts past the 4GiB .relink can address",
+                                 unit.name));
+                }
                 sc_relink.push(sc_text.len() as u32);
                 unit_addresses.insert(unit.global_id, vaddr);
-                vaddr      += unit.code.len() as u64;
+                vaddr      += size;
                 sc_text.append(&mut unit.code);
             },
             UnitSegment::Bss => {
+                sc_bss     += pad;
+                if unit.shndx > 0 {
+                    bss_units.push((unit.object.clone(), unit.shndx, sc_bss, size));
+                }
                 unit_addresses.insert(unit.global_id, vaddr);
-                vaddr      += unit.code.len() as u64;
-                sc_bss     += unit.code.len() as u64;
+                vaddr      += size;
+                sc_bss     += size;
             }
         }
     }
 
-    let sh_index_text = out_elf.sections.len();
-    out_elf.sections.push(Section::new(String::from(".xo.text"),
+    // without -z relro the got slots go behind the bss
+    if !relro && sc_bss > 0 {
+        let pad = vaddr.div_ceil(8) * 8 - vaddr;
+        vaddr  += pad;
+        sc_bss += pad;
+    }
+
+    let bss_addr = bss_addr.unwrap_or(text_addr + sc_text.len() as u64);
+    for (object, shndx, offset, size) in bss_units {
+        remap.insert(&object, shndx, linker::PlacedSection{shndx: sh_index_bss, offset, size});
+    }
+
+    let mut text = Section::new(String::from(".xo.text"),
     types::SectionType::PROGBITS,
     types::SectionFlags::ALLOC | types::SectionFlags::WRITE | types::SectionFlags::EXECINSTR,
-    SectionContent::Raw(sc_text), 0, 0));
+    SectionContent::Raw(sc_text), 0, 0);
+    text.header.addr = text_addr;
+    text.header.addralign = text_align;
+    out_elf.sections.push(text);
+
+    // the got is PROGBITS, so it has to come before the NOBITS .xo.bss. behind it, the bss
+    // would be part of the file, and be written like any other gap
+    if relro {
+        out_elf.sections.push(Section::new(String::from(".got"), types::SectionType::PROGBITS,
+        types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+        SectionContent::Raw(vec![0; got_len * 8]), 0, 0));
+    }
 
-    let sh_index_bss = out_elf.sections.len();
     if sc_bss > 0 {
         let mut bss = Section::new(String::from(".xo.bss"),
         types::SectionType::NOBITS,
         types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
         SectionContent::None, 0, 0);
         bss.header.size = sc_bss;
+        bss.header.addr = bss_addr;
+        bss.header.addralign = bss_align;
         out_elf.sections.push(bss);
     }
 
+    let mut relro_range = None;
+    if relro {
+        out_elf.sync_all().unwrap();
+        linker::relayout(&mut out_elf, 0x300).unwrap();
+        if sc_bss > 0 && out_elf.sections[sh_index_bss].header.addr != bss_addr {
+            panic!("bug in elfkit linker: .xo.bss was placed at {:#x}, but relayout put it at {:#x}",
+                   bss_addr, out_elf.sections[sh_index_bss].header.addr);
+        }
+        relro_range = linker::relro(&out_elf).map(|seg| (seg.vaddr, seg.memsz));
+    }
+
     //reposition all the symbols
     for sym in &mut lookup.symbols {
         if let SymbolSectionIndex::Global(id) = sym.shndx {
@@ -174,11 +395,21 @@ fn main() {
                     UnitSegment::Bss => {
                         sh_index_bss
                     }
-                } as u16);
+                } as u32);
                 sym.value += unit_addresses[&unit.global_id];
         }
     }
-    out_elf.header.entry = lookup.get_by_name("_start").unwrap().value;
+    if static_pie {
+        let i = lookup.by_name["_DYNAMIC"];
+        lookup.symbols[i].shndx = SymbolSectionIndex::Section(sh_index_dynamic as u32);
+        lookup.symbols[i].value = out_elf.sections[sh_index_dynamic].header.addr;
+    }
+    out_elf.header.entry = match linker::entry(&ldoptions.entry, |name| {
+        lookup.get_by_name(name).map(|sym| sym.value)
+    }) {
+        Ok(v) => v,
+        Err(e) => fail(format!("ld.elfkit: {}", e)),
+    };
 
     //----------------------------------relocate
     let mut bootstrap : Vec<u8> = Vec::new();
@@ -187,6 +418,7 @@ fn main() {
     for mut unit in std::mem::replace(&mut lookup.units, Vec::new()) {
         for mut reloc in unit.relocations {
             let mut sym = &unit.symbols[reloc.sym as usize];
+            let mut defined = true;
             let sym_addr = match sym.stype {
                 types::SymbolType::SECTION => {
                     if let SymbolSectionIndex::Global(id) = sym.shndx {
@@ -200,6 +432,17 @@ fn main() {
                         match lookup.get_by_name(&sym.name) {
                             Some(s) => {
                                 assert!(s.name.len() > 0);
+                                // everything here moves with the load address, except the
+                                // value of an absolute symbol. __ehdr_start is the one that
+                                // is meant to be relative
+                                if s.shndx == SymbolSectionIndex::Absolute && s.name != "__ehdr_start" {
+                                    fail(format!("ld.elfkit: {}: {} against absolute symbol {} \
+                                                  is not possible in a position independent executable",
+                                                 unit.object,
+                                                 reloc.rtype.typename(&out_elf.header).unwrap_or("?"),
+                                                 s.name));
+                                }
+                                defined = s.shndx != SymbolSectionIndex::Undefined;
                                 s.value
                             },
                             None => {
@@ -217,13 +460,34 @@ fn main() {
             };
             reloc.addr += unit_addresses[&unit.global_id];
 
-            if sym_addr == 0 {
-                assert!(sym.bind == types::SymbolBind::WEAK);
-                println!("undefined weak (this is usually ok) {:?} to {}", reloc.rtype, sym.name);
+            if sym_addr == 0 && !defined {
+                if sym.bind == types::SymbolBind::WEAK {
+                    println!("undefined weak (this is usually ok) {:?} to {}", reloc.rtype, sym.name);
+                } else {
+                    assert!(lookup.allow_undefined);
+                    println!("{}", format!("undefined {} to {} resolved to 0",
+                                           reloc.rtype.typename(&out_elf.header).unwrap_or("?"),
+                                           sym.name).yellow());
+                }
             }
 
-            match reloc.rtype {
-                RelocationType::R_X86_64_64 => {
+            let kind = Bootstrap::of(handler, &reloc.rtype);
+            for hook in &mut lookup.hooks {
+                hook.relocation_converted(&unit.object, &reloc, sym, &kind);
+            }
+            let value = (sym_addr as i64 + reloc.addend) as u64;
+            match kind {
+                Bootstrap::Abs64 if static_pie => {
+                    if defined {
+                        sc_rela.push(relative(reloc.addr, value));
+                    }
+                },
+                Bootstrap::Rel32 if static_pie => {
+                    write_rel32(&out_elf.header, text_addr,
+                                out_elf.sections[sh_index_text].content.as_raw_mut().unwrap(),
+                                value, reloc.addr);
+                },
+                Bootstrap::Abs64 => {
                     write_bootstrap_abs64(&out_elf.header,
                                           out_elf.sections[sh_index_bootstrap].header.addr,
                                           &mut bootstrap,
@@ -231,7 +495,7 @@ fn main() {
                                           reloc.addr,
                                           );
                 },
-                RelocationType::R_X86_64_PC32 | RelocationType::R_X86_64_PLT32 => {
+                Bootstrap::Rel32 => {
                     write_bootstrap_rel32(&out_elf.header,
                                           out_elf.sections[sh_index_bootstrap].header.addr,
                                           &mut bootstrap,
@@ -239,99 +503,187 @@ fn main() {
                                           reloc.addr,
                                           );
                 },
-                RelocationType::R_X86_64_GOTPCREL | RelocationType::R_X86_64_GOTPCRELX | RelocationType::R_X86_64_REX_GOTPCRELX => {
-                    let got_slot = vaddr;
-                    write_bootstrap_rel32(&out_elf.header,
-                                          out_elf.sections[sh_index_bootstrap].header.addr,
-                                          &mut bootstrap,
-                                          (got_slot as i64 + reloc.addend) as u64,
-                                          reloc.addr,
-                                          );
+                Bootstrap::Got => {
+                    let got_slot = if relro {
+                        out_elf.sections[sh_index_got].header.addr + got_used * 8
+                    } else {
+                        vaddr
+                    };
+                    let slot_value = (got_slot as i64 + reloc.addend) as u64;
+                    if static_pie {
+                        write_rel32(&out_elf.header, text_addr,
+                                    out_elf.sections[sh_index_text].content.as_raw_mut().unwrap(),
+                                    slot_value, reloc.addr);
+                    } else {
+                        write_bootstrap_rel32(&out_elf.header,
+                                              out_elf.sections[sh_index_bootstrap].header.addr,
+                                              &mut bootstrap,
+                                              slot_value,
+                                              reloc.addr,
+                                              );
+                    }
 
                     //this is is only really used for debugging
                     sc_symtab.push(Symbol{
-                        shndx:  SymbolSectionIndex::Section(sh_index_bss  as u16),
+                        shndx:  SymbolSectionIndex::Section(
+                            if relro { sh_index_got } else { sh_index_bss } as u32),
                         value:  got_slot,
                         size:   8,
                         name:   sym.name.clone() + "__GOT",
                         stype:  types::SymbolType::OBJECT,
                         bind:   types::SymbolBind::LOCAL,
-                        vis:    types::SymbolVis::DEFAULT,
+                        other:  0,
                     });
 
-                    vaddr += 8;
-                    out_elf.sections[sh_index_bss].header.size += 8;
+                    if relro {
+                        got_used += 1;
+                    } else {
+                        vaddr += 8;
+                        out_elf.sections[sh_index_bss].header.size += 8;
+                    }
 
-                    write_bootstrap_abs64(&out_elf.header,
-                                          out_elf.sections[sh_index_bootstrap].header.addr,
-                                          &mut bootstrap,
-                                          sym_addr,
-                                          got_slot,
-                                          );
+                    if static_pie {
+                        if defined {
+                            sc_rela.push(relative(got_slot, sym_addr));
+                        }
+                    } else {
+                        write_bootstrap_abs64(&out_elf.header,
+                                              out_elf.sections[sh_index_bootstrap].header.addr,
+                                              &mut bootstrap,
+                                              sym_addr,
+                                              got_slot,
+                                              );
+                    }
                 },
 
 
-                RelocationType::R_X86_64_32 | RelocationType::R_X86_64_32S => {
+                Bootstrap::NotPic => {
                     fail(format!("unsupported relocation. maybe missing -fPIC ? {:?}", reloc));
                 },
-                _ => {
+                Bootstrap::Unsupported => {
                     fail(format!("unsupported relocation {:?} to {:?}", reloc, sym));
                 },
             }
         }
     }
 
+    // debug info refers to the final addresses, and to code that was left out as 0
+    let inputs: Vec<linker::DebugInput> = lookup.debug.iter().map(|d| d.1.clone()).collect();
+    let sc_debug = linker::merge_debug_sections(&out_elf.header, &inputs, |n, sym| {
+        match sym.shndx {
+            SymbolSectionIndex::Section(k) => {
+                remap.address(&out_elf.sections, &lookup.debug[n].0, k as usize, sym.value)
+            },
+            _ if sym.bind != types::SymbolBind::LOCAL => lookup.get_by_name(&sym.name)
+                .filter(|s| s.shndx != SymbolSectionIndex::Undefined)
+                .map(|s| s.value),
+            _ => None,
+        }
+    });
+    let sc_debug = match sc_debug {
+        Ok(v) => v,
+        Err(e) => fail(format!("ld.elfkit: while relocating debug sections: {}", e)),
+    };
+
+    // the symbols are placed now, so the exported ones can be copied
+    let sc_dynsym = linker::export_symbols(&lookup.symbols, &ldoptions.exports);
     sc_symtab.append(&mut lookup.symbols);
+    linker::localize_hidden(&mut sc_symtab);
+
+    // rcrt1 protects PT_GNU_RELRO itself and is the entry point
+    if !static_pie {
+        if let Some((addr, size)) = relro_range {
+            write_bootstrap_mprotect(&out_elf.header,
+                                     out_elf.sections[sh_index_bootstrap].header.addr,
+                                     &mut bootstrap,
+                                     addr,
+                                     size,
+                                     );
+        }
 
-    //indirect _start via __blt_bootstrap
-    write_reljumpto(&out_elf.header,
-                    out_elf.sections[sh_index_bootstrap].header.addr,
-                    &mut bootstrap,
-                    out_elf.header.entry,
-                    );
+        //indirect _start via __blt_bootstrap
+        write_reljumpto(&out_elf.header,
+                        out_elf.sections[sh_index_bootstrap].header.addr,
+                        &mut bootstrap,
+                        out_elf.header.entry,
+                        );
 
-    out_elf.header.entry = out_elf.sections[sh_index_bootstrap].header.addr;
+        out_elf.header.entry = out_elf.sections[sh_index_bootstrap].header.addr;
 
 
-    if bootstrap.len() < out_elf.sections[sh_index_bootstrap].header.size as usize {
-        let more = out_elf.sections[sh_index_bootstrap].header.size as usize - bootstrap.len();
-        bootstrap.extend(vec![0;more]);
+        if bootstrap.len() < out_elf.sections[sh_index_bootstrap].header.size as usize {
+            let more = out_elf.sections[sh_index_bootstrap].header.size as usize - bootstrap.len();
+            bootstrap.extend(vec![0;more]);
+        }
+        assert_eq!(bootstrap.len(), out_elf.sections[sh_index_bootstrap].header.size as usize);
+        out_elf.sections[sh_index_bootstrap].content = SectionContent::Raw(bootstrap);
     }
-    assert_eq!(bootstrap.len(), out_elf.sections[sh_index_bootstrap].header.size as usize);
-    out_elf.sections[sh_index_bootstrap].content = SectionContent::Raw(bootstrap);
 
 
     let sh_index_dynstr = out_elf.sections.len();
     out_elf.sections.push(Section::new(String::from(".dynstr"), types::SectionType::STRTAB,
     types::SectionFlags::ALLOC,
     SectionContent::Strtab(Strtab::default()), 0,0));
+    if sc_dynsym.len() > 1 {
+        out_elf.sections.extend(linker::dynsym_sections(&out_elf.header, sc_dynsym).unwrap());
+    }
+
+    if static_pie && !sc_rela.is_empty() {
+        out_elf.sections.push(Section::new(String::from(".rela.dyn"), types::SectionType::RELA,
+        types::SectionFlags::ALLOC,
+        SectionContent::Relocations(sc_rela), 0,0));
+    }
 
     out_elf.sync_all().unwrap();
     linker::relayout(&mut out_elf, 0x300).unwrap();
 
     sc_dynamic.extend(linker::dynamic(&out_elf).unwrap());
-    out_elf.sections.push(Section::new(String::from(".dynamic"), types::SectionType::DYNAMIC,
-    types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
-    SectionContent::Dynamic(sc_dynamic), sh_index_dynstr as u32,0));
-
-    let sh_index_strtab = out_elf.sections.len();
-    out_elf.sections.push(Section::new(String::from(".strtab"), types::SectionType::STRTAB,
-    types::SectionFlags::empty(),
-    SectionContent::Strtab(Strtab::default()), 0,0));
+    if static_pie {
+        // fill the slots reserved before the code, the rest stay DT_NULL
+        if sc_dynamic.len() > dynamic_slots {
+            fail(format!("ld.elfkit: -static-pie needs {} .dynamic entries, but only {} were \
+                          reserved in front of the code", sc_dynamic.len(), dynamic_slots));
+        }
+        let dynamic = &mut out_elf.sections[sh_index_dynamic];
+        dynamic.header.link = sh_index_dynstr as u32;
+        if let Some(slots) = dynamic.content.as_dynamic_mut() {
+            let n = sc_dynamic.len();
+            slots.splice(..n, sc_dynamic);
+        }
+    } else {
+        out_elf.sections.push(Section::new(String::from(".dynamic"), types::SectionType::DYNAMIC,
+        types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+        SectionContent::Dynamic(sc_dynamic), sh_index_dynstr as u32,0));
+    }
 
-    //sc_symtab.sort_unstable_by(|a,b| a.bind.cmp(&b.bind));
-    let first_global_symtab = sc_symtab.iter().enumerate()
-        .find(|&(_,s)|s.bind == types::SymbolBind::GLOBAL).map(|(i,_)|i).unwrap_or(0);;
-    out_elf.sections.push(Section::new(String::from(".symtab"), types::SectionType::SYMTAB,
-    types::SectionFlags::empty(),
-    SectionContent::Symbols(sc_symtab),
-    sh_index_strtab as u32, first_global_symtab as u32));
+    out_elf.sections.extend(sc_debug);
+
+    // with -s only what's in .dynsym stays
+    if ldoptions.strip.keeps_symtab() {
+        let sh_index_strtab = out_elf.sections.len();
+        out_elf.sections.push(Section::new(String::from(".strtab"), types::SectionType::STRTAB,
+        types::SectionFlags::empty(),
+        SectionContent::Strtab(Strtab::tail_merged()), 0,0));
+
+        //sc_symtab.sort_unstable_by(|a,b| a.bind.cmp(&b.bind));
+        let first_global_symtab = sc_symtab.iter().enumerate()
+            .find(|&(_,s)|s.bind == types::SymbolBind::GLOBAL).map(|(i,_)|i).unwrap_or(0);;
+        out_elf.sections.push(Section::new(String::from(".symtab"), types::SectionType::SYMTAB,
+        types::SectionFlags::empty(),
+        SectionContent::Symbols(sc_symtab),
+        sh_index_strtab as u32, first_global_symtab as u32));
+    }
 
-    out_elf.sections.push(Section::new(String::from(".shstrtab"), types::SectionType::STRTAB,
-    types::SectionFlags::from_bits_truncate(0),
-    SectionContent::Strtab(Strtab::default()),
-    0,0));
 
+    if !lookup.attributes.vendors.is_empty() {
+        let mut b_attributes = Vec::new();
+        lookup.attributes.to_writer(&mut b_attributes, &out_elf.header).unwrap();
+        out_elf.sections.push(Section::new(String::from(attributes::section_name(&out_elf.header.machine)),
+        attributes::section_type(&out_elf.header.machine),
+        types::SectionFlags::empty(),
+        SectionContent::Raw(b_attributes),
+        0,0));
+    }
 
     let mut b_relink = Vec::new();
     for cut in sc_relink {
@@ -347,10 +699,17 @@ fn main() {
     out_elf.sync_all().unwrap();
     linker::relayout(&mut out_elf, 0x300).unwrap();
     out_elf.segments = linker::segments(&out_elf).unwrap();
+    if let Err(e) = linker::check_program_headers(&out_elf) {
+        fail(format!("ld.elfkit: {}", e));
+    }
     out_elf.store_all().unwrap();
     out_elf.to_writer(&mut out_file).unwrap();
 
     let mut perms = out_file.metadata().unwrap().permissions();
     perms.set_mode(0o755);
     out_file.set_permissions(perms).unwrap();
+
+    for hook in &mut lookup.hooks {
+        hook.link_finished();
+    }
 }
@@ -2,6 +2,54 @@ use std::io::Write;
 use elfkit::{
     Elf, Header
 };
+use elfkit::arch::{RelocationHandler, RelocationKind};
+use elfkit::relocation::{Relocation, RelocationType};
+use ::fail;
+
+/// what the bootstrap code has to do for a relocation
+#[derive(PartialEq)]
+pub enum Bootstrap {
+    Abs64,
+    Rel32,
+    Got,
+    /// absolute relocations narrower than a pointer can't be done in a pie
+    NotPic,
+    Unsupported,
+}
+
+impl Bootstrap {
+    pub fn of(handler: &dyn RelocationHandler, rtype: &RelocationType) -> Bootstrap {
+        match (handler.classify(rtype), handler.size(rtype)) {
+            (RelocationKind::Absolute, Some(8)) => Bootstrap::Abs64,
+            (RelocationKind::Absolute, _) => Bootstrap::NotPic,
+            (RelocationKind::Relative, Some(4)) | (RelocationKind::Plt, Some(4)) => Bootstrap::Rel32,
+            // GOT32 is relative to the GOT rather than the place, which the GOT code doesn't do
+            (RelocationKind::Got, Some(4)) if *rtype != RelocationType::R_X86_64_GOT32 => Bootstrap::Got,
+            _ => Bootstrap::Unsupported,
+        }
+    }
+
+    /// size of the code emitted for it
+    pub fn len(&self) -> usize {
+        match *self {
+            Bootstrap::Abs64 => 3 + 4 + 3 + 4,
+            Bootstrap::Got => 3 + 4 + 3 + 4 + 2 + 4 + 4,
+            Bootstrap::Rel32 => 2 + 4 + 4,
+            _ => 0,
+        }
+    }
+}
+
+/// target relative to from, for the 32 bit displacements of x86_64 code.
+/// an output too large for them can't be linked
+fn displacement(target: u64, from: u64) -> i32 {
+    let v = (target as i64).wrapping_sub(from as i64);
+    if v != v as i32 as i64 {
+        fail(format!("ld.elfkit: 0x{:x} is too far from 0x{:x} for a 32 bit displacement",
+                     target, from));
+    }
+    v as i32
+}
 
 /// given value and addr as 64bit address relative to BASE
 /// at runtime write the absolute value into addr
@@ -13,7 +61,7 @@ pub fn write_bootstrap_abs64(eh: &Header, codeoff: u64, code: &mut Vec<u8>, valu
     // note that rip is the _next_ instruction
     let mut rip     = codeoff + code.len() as u64 + 3 + 4;
     let io          = code;
-    let relative_value = ((value as i64) - (rip as i64)) as i32;
+    let relative_value = displacement(value, rip);
 
     // lea -> %rax
     io.write(&[0x48,0x8d,0x05]);
@@ -21,7 +69,7 @@ pub fn write_bootstrap_abs64(eh: &Header, codeoff: u64, code: &mut Vec<u8>, valu
 
     // the write address is given as absolute too. again, undo the rip relative
     rip += 3 + 4;
-    let relative_address = ((addr as i64) - (rip  as i64)) as i32;
+    let relative_address = displacement(addr, rip);
 
     //mov %rax, ..(%rip)
     io.write(&[0x48,0x89,0x05]);
@@ -33,8 +81,8 @@ pub fn write_bootstrap_abs64(eh: &Header, codeoff: u64, code: &mut Vec<u8>, valu
 pub fn write_bootstrap_rel32(eh: &Header, codeoff: u64, code: &mut Vec<u8>, value: u64, addr: u64) {
     let mut rip     = codeoff + code.len() as u64 + 2 + 4 + 4;
     let io          = code;
-    let relative_address = ((addr as i64)  - (rip  as i64)) as i32;
-    let relative_value   = ((value as i64) - (addr as i64)) as i32;
+    let relative_address = displacement(addr, rip);
+    let relative_value   = displacement(value, addr);
 
     // movl relative_value,relative_address(%rip)
     io.write(&[0xc7,0x05]);
@@ -42,10 +90,41 @@ pub fn write_bootstrap_rel32(eh: &Header, codeoff: u64, code: &mut Vec<u8>, valu
     elf_write_u32!(&eh, io, relative_value   as u32);
 }
 
+/// size of the code write_bootstrap_mprotect emits
+pub const BOOTSTRAP_MPROTECT_LEN: usize = 7 + 5 + 5 + 5 + 2 + 2;
+
+/// at runtime make size bytes at the page aligned addr read only.
+/// clobbers the syscall registers, and clears %rdx again since _start takes
+/// the atexit function from it
+pub fn write_bootstrap_mprotect(eh: &Header, codeoff: u64, code: &mut Vec<u8>, addr: u64, size: u64) {
+    let rip     = codeoff + code.len() as u64 + 3 + 4;
+    let io      = code;
+    let relative_address = displacement(addr, rip);
+
+    // lea ..(%rip) -> %rdi
+    io.write(&[0x48,0x8d,0x3d]);
+    elf_write_u32!(&eh, io, relative_address as u32);
+    // mov size, %esi
+    if size > u32::MAX as u64 {
+        fail(format!("ld.elfkit: can not protect 0x{:x} bytes of relro at once", size));
+    }
+    io.write(&[0xbe]);
+    elf_write_u32!(&eh, io, size as u32);
+    // mov PROT_READ, %edx
+    io.write(&[0xba]);
+    elf_write_u32!(&eh, io, 1);
+    // mov SYS_mprotect, %eax
+    io.write(&[0xb8]);
+    elf_write_u32!(&eh, io, 10);
+    io.write(&[0x0f,0x05]);
+    // xor %edx, %edx
+    io.write(&[0x31,0xd2]);
+}
+
 pub fn write_reljumpto(eh: &Header, codeoff: u64, code: &mut Vec<u8>, targetaddr: u64) {
     let pc  = codeoff + code.len() as u64 + 1 + 4;
     let io  = code;
-    let rel = ((targetaddr as i64) - (pc as i64)) as i32;
+    let rel = displacement(targetaddr, pc);
     // jmpq
     io.write(&[0xe9]);
     elf_write_u32!(&eh, io, rel as u32);
@@ -53,3 +132,21 @@ pub fn write_reljumpto(eh: &Header, codeoff: u64, code: &mut Vec<u8>, targetaddr
 
 
 
+/// write value relative to addr into the code loaded at codeoff, at link time.
+/// what write_bootstrap_rel32 makes the bootstrap do, for outputs without one
+pub fn write_rel32(eh: &Header, codeoff: u64, code: &mut [u8], value: u64, addr: u64) {
+    let at  = (addr - codeoff) as usize;
+    let mut io = &mut code[at..at + 4];
+    let relative_value = displacement(value, addr);
+    elf_write_u32!(&eh, io, relative_value as u32);
+}
+
+/// R_X86_64_RELATIVE, which makes rcrt1 write the load address plus value to addr
+pub fn relative(addr: u64, value: u64) -> Relocation {
+    Relocation {
+        addr,
+        sym:    0,
+        rtype:  RelocationType::R_X86_64_RELATIVE,
+        addend: value as i64,
+    }
+}
@@ -0,0 +1,33 @@
+extern crate elfkit;
+
+use std::env;
+use std::fs::File;
+use std::process;
+use elfkit::Elf;
+
+/// diff OLD NEW
+/// print what differs between two elf files, one line per difference.
+/// exits 1 if they differ, like diff(1)
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("usage: {} OLD NEW", args[0]);
+        process::exit(2);
+    }
+    let load = |path: &str| {
+        let mut file = File::open(path).unwrap();
+        let mut elf = Elf::from_reader(&mut file).unwrap();
+        elf.load_all().unwrap();
+        elf
+    };
+    let old = load(&args[1]);
+    let new = load(&args[2]);
+
+    let differences = old.diff(&new);
+    for d in &differences {
+        println!("{}", d);
+    }
+    if !differences.is_empty() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,49 @@
+extern crate elfkit;
+
+use std::env;
+use std::process;
+use elfkit::scan::{self, Pattern, SymbolIndex};
+
+/// findsym [-i INDEX] PATTERN[,PATTERN...] PATH...
+/// print every defined symbol matching one of the patterns, with the object it's in.
+/// patterns are exact names or globs like 'mem*' or 'st[!x]cpy'.
+/// with an index file, repeated runs only read the files that changed since the last one
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let mut index = None;
+    if args.len() > 2 && args[1] == "-i" {
+        index = Some(SymbolIndex::open(&args[2]));
+        args.drain(1..3);
+    }
+    if args.len() < 3 {
+        eprintln!("usage: {} [-i INDEX] PATTERN[,PATTERN...] PATH...", args[0]);
+        process::exit(2);
+    }
+    let patterns: Vec<Pattern> = args[1].split(',').map(Pattern::parse).collect();
+
+    let mut found = vec![0; patterns.len()];
+    let hits = match index {
+        Some(ref mut index) => index.find(&args[2..], &patterns),
+        None => scan::scan(&args[2..], |_, elf| scan::matching_symbols(elf, &patterns).ok())
+            .collect(),
+    };
+    for r in hits {
+        match r {
+            Ok((source, matches)) => for (k, name) in matches {
+                found[k] += 1;
+                println!("{}: {} ({})", source, name, patterns[k]);
+            },
+            Err((source, e)) => eprintln!("skipping {}: {}", source, e),
+        }
+    }
+    if let Some(mut index) = index {
+        if let Err(e) = index.save() {
+            eprintln!("can not save the index: {}", e);
+        }
+    }
+    for (k, n) in found.iter().enumerate() {
+        if *n == 0 {
+            eprintln!("nothing matches {}", patterns[k]);
+        }
+    }
+}
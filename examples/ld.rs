@@ -6,7 +6,7 @@ extern crate goblin;
 use std::env;
 use std::io::{Cursor, Read};
 use std::fs::OpenOptions;
-use elfkit::{types, Dynamic, Elf, Relocation, Section, SectionContent,
+use elfkit::{types, Dynamic, Elf, LinkRef, Relocation, Section, SectionContent,
              Strtab, Symbol, SymbolSectionIndex};
 
 use elfkit::filetype;
@@ -27,21 +27,24 @@ fn main() {
     let mut elfs = load_elfs(ldoptions.object_paths);
     let mut lookup = Lookup::default();
 
-    let mut start = Symbol::default();
-    start.name = String::from("_start");
-    start.bind = types::SymbolBind::GLOBAL;
     let mut got = Symbol::default();
     got.name = String::from("_GLOBAL_OFFSET_TABLE_"); //TODO
     got.shndx = SymbolSectionIndex::Section(1);
     got.bind = types::SymbolBind::GLOBAL;
-    lookup.insert_unit(Unit::fake(
-        String::from("exe"),
-        LinkBehaviour::Static,
-        vec![start, got],
-    ));
+    let mut fake = vec![got];
+    // an entry address doesn't need anything to be pulled in
+    if linker::entry_address(&ldoptions.entry).is_none() {
+        let mut start = Symbol::default();
+        start.name = ldoptions.entry.clone();
+        start.bind = types::SymbolBind::GLOBAL;
+        fake.insert(0, start);
+    }
+    lookup.insert_unit(Unit::fake(String::from("exe"), LinkBehaviour::Static, fake));
 
     let mut global_id_counter = 10;
     let mut candidates = HashMap::new();
+    let providers = providers(&mut elfs);
+    let mut elfs: Vec<Option<(String, Elf)>> = elfs.into_iter().map(Some).collect();
 
     loop {
         println!("lookup iteration");
@@ -68,26 +71,18 @@ fn main() {
             let mut found = None;
             let was_needed_by = lookup.units[lookup.symbols2units[&mi]].name.clone();
 
-            let mut cont = true;
-            while cont {
-                cont = false;
-                for ei in 0..elfs.len() {
-                    let contains = match elfs[ei].1.contains_symbol(&lookup.symbols[mi].name) {
-                        Ok(v) => v,
-                        Err(e) => fail(format!("error in lookup in {} : {:?}", elfs[ei].0, e)),
-                    };
-                    if contains {
-                        let elf = elfs.swap_remove(ei);
+            // every object defining the symbol becomes a candidate, the usual priority
+            // rules pick one of them below
+            if let Some(providers) = providers.get(&lookup.symbols[mi].name) {
+                for ei in providers {
+                    if let Some(elf) = elfs[*ei].take() {
                         for unit in Unit::from_elf(elf.0, elf.1, &mut global_id_counter) {
                             candidates.insert(unit.global_id.clone(), unit);
                         }
-                        cont = true;
-                        break;
                     }
                 }
             }
 
-
             for (id, candidate) in candidates.iter() {
                 for sym in candidate.lookup(&lookup.symbols[mi].name) {
                     if sym.shndx != SymbolSectionIndex::Undefined {
@@ -156,6 +151,11 @@ fn main() {
             dhtype: types::DynamicType::FLAGS_1,
             content: DynamicContent::Flags1(types::DynamicFlags1::PIE),
         },
+        // ld.so points it at r_debug, which is how debuggers find the link map
+        Dynamic {
+            dhtype: types::DynamicType::DEBUG,
+            content: DynamicContent::Address(0),
+        },
     ];
     let mut sc_symtab: Vec<Symbol> = vec![Symbol::default()];
 
@@ -235,7 +235,7 @@ fn main() {
             let mut sym = sym.clone();
 
             if let SymbolSectionIndex::Global(id) = sym.shndx {
-                sym.shndx = SymbolSectionIndex::Section(global2section[&id] as u16);
+                sym.shndx = SymbolSectionIndex::Section(global2section[&id] as u32);
                 sym.value += out_elf.sections[global2section[&id]].header.addr;
 
                 //TODO ld.so doesn't like WEAK symbols
@@ -280,13 +280,13 @@ fn main() {
 
     let dynsym_index_got = sc_dynsym.len();
     sc_dynsym.push(Symbol {
-        shndx: SymbolSectionIndex::Section(sh_index_got as u16),
+        shndx: SymbolSectionIndex::Section(sh_index_got as u32),
         value: out_elf.sections[sh_index_got].header.addr,
         size: 0,
         name: String::from(".got"),
         stype: types::SymbolType::NOTYPE,
         bind: types::SymbolBind::LOCAL,
-        vis: types::SymbolVis::DEFAULT,
+        other: 0,
     });
 
     //resolve some relocations that ld can't do
@@ -312,13 +312,13 @@ fn main() {
 
                 //this is is only really used for debugging, hence symtab only
                 sc_symtab.push(Symbol {
-                    shndx: SymbolSectionIndex::Section(sh_index_got as u16),
+                    shndx: SymbolSectionIndex::Section(sh_index_got as u32),
                     value: sc_dynsym[dynsym_index_got].value + got_slot,
                     size: 8,
                     name: sc_dynsym[reloc.sym as usize].name.clone() + "@GOT",
                     stype: types::SymbolType::OBJECT,
                     bind: types::SymbolBind::LOCAL,
-                    vis: types::SymbolVis::DEFAULT,
+                    other: 0,
                 });
 
                 if sc_dynsym[reloc.sym as usize].shndx == SymbolSectionIndex::Undefined {
@@ -353,18 +353,21 @@ fn main() {
     for sym in &mut lookup.symbols {
         if let SymbolSectionIndex::Global(id) = sym.shndx {
             sym.value += out_elf.sections[global2section[&id]].header.addr;
-            sym.shndx = SymbolSectionIndex::Section(global2section[&id] as u16);
+            sym.shndx = SymbolSectionIndex::Section(global2section[&id] as u32);
             sym.bind = types::SymbolBind::LOCAL;
         }
     }
-    out_elf.header.entry = lookup.get_by_name("_start").unwrap().value;
+    out_elf.header.entry = match linker::entry(&ldoptions.entry, |name| {
+        lookup.get_by_name(name).map(|sym| sym.value)
+    }) {
+        Ok(v) => v,
+        Err(e) => fail(format!("{}", e)),
+    };
     sc_symtab.extend(lookup.symbols);
 
 
     //store on .dynamic may add strings to dynsym, which will change all the offsets.
-    //this is why dynstr is last. this index needs to be changed everytime something is added
-    //between here and dynstr
-    let sh_index_dynstr = out_elf.sections.len() + 3;
+    //this is why dynstr is last. it's referenced by name, so the index is resolved on sync
 
     let sh_index_dynsym = out_elf.sections.len();
     let symhash = elfkit::symbol::symhash(&out_elf.header, &sc_dynsym, sh_index_dynsym as u32)
@@ -375,14 +378,16 @@ fn main() {
         .find(|&(_, s)| s.bind == types::SymbolBind::GLOBAL)
         .map(|(i, _)| i)
         .unwrap_or(0);
-    out_elf.sections.push(Section::new(
+    let mut dynsym = Section::new(
         String::from(".dynsym"),
         types::SectionType::DYNSYM,
         types::SectionFlags::ALLOC,
         SectionContent::Symbols(sc_dynsym),
-        sh_index_dynstr as u32,
+        0,
         first_global_dynsym as u32,
-    ));
+    );
+    dynsym.link_ref = Some(LinkRef::Name(String::from(".dynstr")));
+    out_elf.sections.push(dynsym);
 
     out_elf.sections.push(symhash);
 
@@ -417,14 +422,16 @@ fn main() {
     linker::relayout(&mut out_elf, 0x300).unwrap();
 
     sc_dynamic.extend(linker::dynamic(&out_elf).unwrap());
-    out_elf.sections.push(Section::new(
+    let mut dynamic = Section::new(
         String::from(".dynamic"),
         types::SectionType::DYNAMIC,
         types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
         SectionContent::Dynamic(sc_dynamic),
-        sh_index_dynstr as u32,
         0,
-    ));
+        0,
+    );
+    dynamic.link_ref = Some(LinkRef::Name(String::from(".dynstr")));
+    out_elf.sections.push(dynamic);
 
     let sh_index_strtab = out_elf.sections.len();
     out_elf.sections.push(Section::new(
@@ -452,15 +459,6 @@ fn main() {
         first_global_symtab as u32,
     ));
 
-    out_elf.sections.push(Section::new(
-        String::from(".shstrtab"),
-        types::SectionType::STRTAB,
-        types::SectionFlags::from_bits_truncate(0),
-        SectionContent::Strtab(Strtab::default()),
-        0,
-        0,
-    ));
-
     out_elf.sync_all().unwrap();
     linker::relayout(&mut out_elf, 0x300).unwrap();
     out_elf.segments = linker::segments(&out_elf).unwrap();
@@ -500,6 +498,21 @@ fn resursive_insert(
 
 
 
+/// which objects define each symbol name, so a lookup doesn't ask every object in turn
+fn providers(elfs: &mut [(String, Elf)]) -> HashMap<String, Vec<usize>> {
+    let mut r: HashMap<String, Vec<usize>> = HashMap::new();
+    for (ei, &mut (ref name, ref mut elf)) in elfs.iter_mut().enumerate() {
+        let symbols = match elf.defined_symbols() {
+            Ok(v) => v,
+            Err(e) => fail(format!("error in lookup in {} : {:?}", name, e)),
+        };
+        for sym in symbols {
+            r.entry(sym.clone()).or_default().push(ei);
+        }
+    }
+    r
+}
+
 fn load_elfs(paths: Vec<String>) -> Vec<(String, Elf)> {
     let mut elfs = Vec::new();
     for in_path in paths {
@@ -521,7 +534,7 @@ fn load_elfs(paths: Vec<String>) -> Vec<(String, Elf)> {
                     match Elf::from_reader(&mut in_file) {
                         Ok(e) => e,
                         Err(e) => {
-                            fail(format!("error loading {} : {:?}", in_path, e));
+                            fail(format!("error loading {} : {}", in_path, e));
                         }
                     },
                 ));
@@ -542,7 +555,7 @@ fn load_elfs(paths: Vec<String>) -> Vec<(String, Elf)> {
                                 Err(e) => {
                                     println!(
                                         "{}",
-                                        format!("skipping {} in {}: {:?}", name, in_path, e)
+                                        format!("skipping {} in {}: {}", name, in_path, e)
                                             .yellow()
                                     );
                                 }
@@ -552,8 +565,8 @@ fn load_elfs(paths: Vec<String>) -> Vec<(String, Elf)> {
                     _ => unreachable!(),
                 }
             }
-            _ => {
-                fail(format!("{}: unknown file type", in_name));
+            t => {
+                fail(format!("{}: {}", in_name, t));
             }
         }
     }
@@ -709,7 +722,7 @@ impl Unit {
 
             // copy all symbols from symtab where .shndx is this obj
             for (i, sym) in symbols.1.iter().enumerate() {
-                if sym.shndx == SymbolSectionIndex::Section(*obj_shndx as u16) {
+                if sym.shndx == SymbolSectionIndex::Section(*obj_shndx as u32) {
                     let mut sym = sym.clone();
                     sym.shndx = SymbolSectionIndex::Global(obj.global_id);
                     smap.insert(i, obj.symbols.len());
@@ -959,6 +972,8 @@ fn ldarg(arg: &String, argname: &str, argc: &mut usize) -> Option<String> {
 #[derive(Default)]
 struct LdOptions {
     dynamic_linker: String,
+    /// symbol or address from -e
+    entry: String,
     object_paths: Vec<String>,
     output_path: String,
 }
@@ -981,6 +996,7 @@ fn search_lib(search_paths: &Vec<String>, needle: &String) -> String {
 fn parse_ld_options() -> LdOptions {
     let mut options = LdOptions::default();
     options.output_path = String::from("a.out");
+    options.entry = String::from("_start");
     let mut search_paths = Vec::new();
 
     let mut argc = 1;
@@ -1004,6 +1020,11 @@ fn parse_ld_options() -> LdOptions {
             }
         } else if let Some(val) = ldarg(&arg, "-o", &mut argc) {
             options.output_path = val;
+        } else if arg == "-e" {
+            argc += 1;
+            options.entry = env::args().nth(argc).unwrap();
+        } else if let Some(val) = arg.strip_prefix("--entry=") {
+            options.entry = String::from(val);
         } else if arg == "-pie" {
         } else if arg == "-dynamic-linker" {
             argc += 1;
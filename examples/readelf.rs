@@ -3,7 +3,8 @@ extern crate elfkit;
 
 use std::env;
 use std::fs::File;
-use elfkit::{types, DynamicContent, Elf, SectionContent};
+use elfkit::{types, DynamicContent, Elf, ElfOptions, SectionContent};
+use elfkit::attributes::{self, VendorContent};
 use elfkit::relocation::RelocationType;
 use elfkit::symbol::SymbolSectionIndex;
 use colored::*;
@@ -20,8 +21,15 @@ where
 fn main() {
     let filename = env::args().nth(1).unwrap();
     let mut file = File::open(filename).unwrap();
-    let mut elf = Elf::from_reader(&mut file).unwrap();
+    let options = ElfOptions {
+        strict: false,
+        ..ElfOptions::untrusted()
+    };
+    let mut elf = Elf::from_reader_with_options(&mut file, options).unwrap();
     elf.load_all().unwrap();
+    for warning in &elf.warnings {
+        println!("{} {}", "warning:".yellow(), warning);
+    }
 
     println!("{}", "ELF Header:".bold());
     println!(
@@ -77,6 +85,9 @@ fn main() {
         elf.header.flags,
         elf.header.flags
     );
+    if let Some(v) = elf.header.arm_eabi_version() {
+        println!("  ARM EABI version:                  {}", v);
+    }
     println!(
         "  Size of this header:               {} (bytes)",
         elf.header.ehsize
@@ -163,7 +174,10 @@ fn main() {
         for ph in &elf.segments {
             println!(
                 "  {:<14.14} 0x{} 0x{} 0x{}",
-                format!("{:?}", ph.phtype),
+                match ph.phtype.typename(&elf.header) {
+                    Some(s) => String::from(s),
+                    None => format!("0x{:x}", ph.phtype.to_u32()),
+                },
                 hextab(16, ph.offset),
                 hextab(16, ph.vaddr),
                 hextab(16, ph.paddr)
@@ -224,7 +238,10 @@ fn main() {
             let n = n;
             print!("                                         ");
             for segment in elf.segments.iter() {
-                let name = format!("{:?}", segment.phtype);
+                let name = match segment.phtype.typename(&elf.header) {
+                    Some(s) => String::from(s),
+                    None => format!("0x{:x}", segment.phtype.to_u32()),
+                };
                 print!(" {} |", if name.len() > n { &name[n..n + 1] } else { " " });
             }
             println!("");
@@ -297,7 +314,10 @@ fn main() {
                     print!(
                         "  {} {:<15.15} ",
                         hextab(16, reloc.addr),
-                        &format!("{:?}", reloc.rtype)[2..]
+                        match reloc.rtype.typename(&elf.header) {
+                            Some(s) => String::from(&s[2..]),
+                            None => format!("0x{:x}", reloc.rtype.to_u32()),
+                        }
                     );
 
                     elf.sections
@@ -341,13 +361,14 @@ fn main() {
                         symbol.size,
                         format!("{:?}", symbol.stype),
                         format!("{:?}", symbol.bind),
-                        format!("{:?}", symbol.vis),
+                        format!("{:?}", symbol.vis()),
                         match symbol.shndx {
                             SymbolSectionIndex::Undefined => String::from("UND"),
                             SymbolSectionIndex::Absolute => String::from("ABS"),
                             SymbolSectionIndex::Common => String::from("COM"),
                             SymbolSectionIndex::Section(i) => format!("{}", i),
                             SymbolSectionIndex::Global(i) => format!("g{}", i),
+                            SymbolSectionIndex::Reserved(i) => format!("0x{:x}", i),
                         },
                         symbol.name
                     );
@@ -365,11 +386,15 @@ fn main() {
                 for dyn in dynamics {
                     println!(
                         "  {:<12} {}",
-                        format!("{:?}", dyn.dhtype),
+                        match dyn.dhtype.typename(&elf.header) {
+                            Some(s) => String::from(s),
+                            None => format!("0x{:x}", dyn.dhtype.to_u64()),
+                        },
                         match dyn.content {
                             DynamicContent::None => String::default(),
                             DynamicContent::String(ref s) => s.clone(),
                             DynamicContent::Address(u) => hextab(16, u),
+                            DynamicContent::Flags(v) => format!("{:?}", v),
                             DynamicContent::Flags1(v) => format!("{:?}", v),
                         }
                     );
@@ -390,4 +415,37 @@ fn main() {
             _ => {}
         }
     }
+
+    match attributes::machine_attributes(&elf.sections, &elf.header) {
+        Ok(Some(attrs)) => {
+            println!("");
+            println!("{}", "Build attributes:".bold());
+            for v in &attrs.vendors {
+                println!("  Vendor: {}", v.vendor);
+                let sets = match v.content {
+                    VendorContent::Parsed(ref sets) => sets,
+                    VendorContent::Raw(ref raw) => {
+                        println!("    {} bytes of vendor specific data", raw.len());
+                        continue;
+                    }
+                };
+                for set in sets {
+                    println!("    {:?}", set.scope);
+                    for a in &set.attributes {
+                        let name = match v.vendor.as_ref() {
+                            "aeabi" => attributes::arm_tag_name(a.tag),
+                            _ => None,
+                        };
+                        let name = match name {
+                            Some(n) => String::from(n),
+                            None => format!("Tag_{}", a.tag),
+                        };
+                        println!("      {:<30} {:?}", name, a.value);
+                    }
+                }
+            }
+        }
+        Ok(None) => {}
+        Err(e) => println!("{} {}", "warning:".yellow(), e),
+    }
 }
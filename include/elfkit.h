@@ -0,0 +1,76 @@
+/*
+ * C interface of elfkit, built with
+ *
+ *   cargo rustc --release --lib --features capi --crate-type cdylib
+ *
+ * an elfkit_elf owns everything it hands out: names stay valid until elfkit_free.
+ * functions that fail return NULL or -1, elfkit_last_error says why.
+ */
+#ifndef ELFKIT_H
+#define ELFKIT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct ElfkitElf elfkit_elf;
+
+typedef struct {
+    uint8_t class;
+    uint8_t endianness;
+    uint16_t etype;
+    uint16_t machine;
+    uint64_t entry;
+    uint64_t phnum;
+    uint64_t shnum;
+} elfkit_header;
+
+typedef struct {
+    const char *name;
+    uint32_t shtype;
+    uint64_t flags;
+    uint64_t addr;
+    uint64_t offset;
+    uint64_t size;
+    uint32_t link;
+    uint32_t info;
+} elfkit_section;
+
+typedef struct {
+    const char *name;
+    uint64_t value;
+    uint64_t size;
+    uint8_t stype;
+    uint8_t bind;
+    uint8_t other;
+    /* the section index, with SHN_ABS and SHN_COMMON as in the file */
+    uint32_t shndx;
+} elfkit_symbol;
+
+/* why the last call on this thread failed, or NULL */
+const char *elfkit_last_error(void);
+
+elfkit_elf *elfkit_open(const char *path);
+/* data is copied */
+elfkit_elf *elfkit_parse(const uint8_t *data, size_t len);
+void elfkit_free(elfkit_elf *elf);
+
+int elfkit_get_header(const elfkit_elf *elf, elfkit_header *out);
+
+size_t elfkit_section_count(const elfkit_elf *elf);
+int elfkit_get_section(const elfkit_elf *elf, size_t i, elfkit_section *out);
+
+/* all symbols of .symtab and .dynsym, in the order of their sections */
+size_t elfkit_symbol_count(const elfkit_elf *elf);
+int elfkit_get_symbol(const elfkit_elf *elf, size_t i, elfkit_symbol *out);
+/* the first defined symbol with this name, or an undefined one. 0 if found, 1 if not */
+int elfkit_symbol_lookup(const elfkit_elf *elf, const char *name, elfkit_symbol *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,1133 @@
+use io::{Read, Write};
+use {types, Error, Header};
+use relocation::RelocationType;
+
+/**
+ * what a relocation does, independent of the machine.
+ * linkers mostly need to know whether a relocation needs a GOT or PLT entry,
+ * or can be resolved with just the symbol address.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationKind {
+    None,
+    /// S + A
+    Absolute,
+    /// S + A - P
+    Relative,
+    /// needs a GOT entry for the symbol
+    Got,
+    /// a call or jump that may go through a PLT entry
+    Plt,
+    /// B + A, what the dynamic linker does for position independent pointers
+    BaseRelative,
+    Copy,
+    GlobDat,
+    JumpSlot,
+    Tls,
+    /// anything with machine specific semantics
+    Other,
+}
+
+/// the inputs of a relocation, named as in the processor supplements
+#[derive(Debug, Default, Clone)]
+pub struct RelocationValues {
+    /// value of the symbol
+    pub s: u64,
+    /// addend
+    pub a: i64,
+    /// address of the place being relocated
+    pub p: u64,
+    /// base address of the loaded object
+    pub b: u64,
+    /// offset of the symbol's entry in the GOT
+    pub g: u64,
+    /// address of the GOT
+    pub got: u64,
+    /// address of the symbol's PLT entry
+    pub l: u64,
+    /// size of the symbol
+    pub z: u64,
+}
+
+/**
+ * everything elfkit knows about the relocations of one machine.
+ * use handler() to get the one for a file.
+ */
+pub trait RelocationHandler {
+    /// split r_info into symbol index and type
+    fn decode(&self, info: u64, eh: &Header) -> (u32, RelocationType) {
+        match eh.ident_class {
+            types::Class::Class64 => ((info >> 32) as u32, RelocationType(info as u32)),
+            types::Class::Class32 => ((info >> 8) as u32, RelocationType(info as u32 & 0xff)),
+        }
+    }
+
+    /// build r_info from symbol index and type
+    fn encode(&self, sym: u32, rtype: &RelocationType, eh: &Header) -> u64 {
+        match eh.ident_class {
+            types::Class::Class64 => ((sym as u64) << 32) + rtype.to_u32() as u64,
+            types::Class::Class32 => ((sym << 8) + (rtype.to_u32() & 0xff)) as u64,
+        }
+    }
+
+    fn typename(&self, rtype: &RelocationType) -> Option<&'static str>;
+
+    /// how many bytes at the place a relocation touches, None for unknown types
+    fn size(&self, rtype: &RelocationType) -> Option<usize>;
+
+    fn classify(&self, rtype: &RelocationType) -> RelocationKind;
+
+    /// the dynamic relocation ld.so resolves to B + A when it is against symbol 0
+    fn relative_type(&self) -> RelocationType;
+
+    /// compute the relocation and write it into place, which starts at the relocated field.
+    /// for REL sections the caller has to read the implicit addend into v.a first
+    fn apply(
+        &self,
+        rtype: &RelocationType,
+        v: &RelocationValues,
+        place: &mut [u8],
+        eh: &Header,
+    ) -> Result<(), Error>;
+}
+
+pub struct X86_64;
+pub struct Mips;
+pub struct Arm;
+pub struct Ppc64;
+
+static X86_64_HANDLER: X86_64 = X86_64;
+static MIPS_HANDLER: Mips = Mips;
+static ARM_HANDLER: Arm = Arm;
+static PPC64_HANDLER: Ppc64 = Ppc64;
+
+/// the relocation handler for the machine of a file.
+/// 64bit mips packs up to three types into r_info, which isn't modelled, so it has none
+pub fn handler(eh: &Header) -> Option<&'static dyn RelocationHandler> {
+    match (&eh.machine, &eh.ident_class) {
+        (&types::Machine::X86_64, _) => Some(&X86_64_HANDLER),
+        (&types::Machine::PPC64, _) => Some(&PPC64_HANDLER),
+        (&types::Machine::ARM, &types::Class::Class32) => Some(&ARM_HANDLER),
+        (&types::Machine::MIPS, &types::Class::Class32)
+        | (&types::Machine::MIPS_RS3_LE, &types::Class::Class32) => Some(&MIPS_HANDLER),
+        _ => None,
+    }
+}
+
+fn write8(place: &mut [u8], v: u8) -> Result<(), Error> {
+    match place.first_mut() {
+        Some(b) => *b = v,
+        None => return Err(Error::RelocationOutOfBounds),
+    }
+    Ok(())
+}
+
+fn write16(place: &mut [u8], v: u16, eh: &Header) -> Result<(), Error> {
+    if place.len() < 2 {
+        return Err(Error::RelocationOutOfBounds);
+    }
+    let mut io = &mut place[..2];
+    elf_write_u16!(eh, io, v)?;
+    Ok(())
+}
+
+fn write32(place: &mut [u8], v: u32, eh: &Header) -> Result<(), Error> {
+    if place.len() < 4 {
+        return Err(Error::RelocationOutOfBounds);
+    }
+    let mut io = &mut place[..4];
+    elf_write_u32!(eh, io, v)?;
+    Ok(())
+}
+
+fn write64(place: &mut [u8], v: u64, eh: &Header) -> Result<(), Error> {
+    if place.len() < 8 {
+        return Err(Error::RelocationOutOfBounds);
+    }
+    let mut io = &mut place[..8];
+    elf_write_u64!(eh, io, v)?;
+    Ok(())
+}
+
+fn read16(place: &[u8], eh: &Header) -> Result<u16, Error> {
+    if place.len() < 2 {
+        return Err(Error::RelocationOutOfBounds);
+    }
+    let mut io = &place[..2];
+    Ok(elf_read_u16!(eh, io)?)
+}
+
+fn read32(place: &[u8], eh: &Header) -> Result<u32, Error> {
+    if place.len() < 4 {
+        return Err(Error::RelocationOutOfBounds);
+    }
+    let mut io = &place[..4];
+    Ok(elf_read_u32!(eh, io)?)
+}
+
+/// check that a value fits into a signed field of `bits` bits
+fn signed(rtype: &RelocationType, v: i64, bits: u32) -> Result<i64, Error> {
+    let max = 1i64 << (bits - 1);
+    if v < -max || v >= max {
+        return Err(Error::RelocationOverflow(rtype.to_u32(), v));
+    }
+    Ok(v)
+}
+
+/// check that a value fits into an unsigned field of `bits` bits
+fn unsigned(rtype: &RelocationType, v: i64, bits: u32) -> Result<u64, Error> {
+    if v < 0 || v >= (1i64 << bits) {
+        return Err(Error::RelocationOverflow(rtype.to_u32(), v));
+    }
+    Ok(v as u64)
+}
+
+/// S + A
+fn sa(v: &RelocationValues) -> i64 {
+    (v.s as i64).wrapping_add(v.a)
+}
+
+/// S + A - P
+fn sap(v: &RelocationValues) -> i64 {
+    sa(v).wrapping_sub(v.p as i64)
+}
+
+/// B + A
+fn ba(v: &RelocationValues) -> i64 {
+    (v.b as i64).wrapping_add(v.a)
+}
+
+/// replace the bits in mask of the instruction at place
+fn merge32(place: &mut [u8], mask: u32, v: u32, eh: &Header) -> Result<(), Error> {
+    let old = read32(place, eh)?;
+    write32(place, (old & !mask) | (v & mask), eh)
+}
+
+fn merge16(place: &mut [u8], mask: u16, v: u16, eh: &Header) -> Result<(), Error> {
+    let old = read16(place, eh)?;
+    write16(place, (old & !mask) | (v & mask), eh)
+}
+
+/// check that a value fits into a field of `bits` bits, either as signed or unsigned
+fn either(rtype: &RelocationType, v: i64, bits: u32) -> Result<i64, Error> {
+    if v < -(1i64 << (bits - 1)) || v >= (1i64 << bits) {
+        return Err(Error::RelocationOverflow(rtype.to_u32(), v));
+    }
+    Ok(v)
+}
+
+fn aligned(rtype: &RelocationType, v: i64, align: i64) -> Result<i64, Error> {
+    if v % align != 0 {
+        return Err(Error::RelocationMisaligned(rtype.to_u32(), v));
+    }
+    Ok(v)
+}
+
+/// low 16 bits, and the high 16 bits adjusted for the sign extension of the low ones
+fn lo(v: i64) -> u16 {
+    v as u16
+}
+
+fn ha(v: i64) -> u16 {
+    (v.wrapping_add(0x8000) >> 16) as u16
+}
+
+impl RelocationHandler for X86_64 {
+    fn typename(&self, rtype: &RelocationType) -> Option<&'static str> {
+        match rtype {
+            &RelocationType::R_X86_64_NONE => Some("R_X86_64_NONE"),
+            &RelocationType::R_X86_64_64 => Some("R_X86_64_64"),
+            &RelocationType::R_X86_64_PC32 => Some("R_X86_64_PC32"),
+            &RelocationType::R_X86_64_GOT32 => Some("R_X86_64_GOT32"),
+            &RelocationType::R_X86_64_PLT32 => Some("R_X86_64_PLT32"),
+            &RelocationType::R_X86_64_COPY => Some("R_X86_64_COPY"),
+            &RelocationType::R_X86_64_GLOB_DAT => Some("R_X86_64_GLOB_DAT"),
+            &RelocationType::R_X86_64_JUMP_SLOT => Some("R_X86_64_JUMP_SLOT"),
+            &RelocationType::R_X86_64_RELATIVE => Some("R_X86_64_RELATIVE"),
+            &RelocationType::R_X86_64_GOTPCREL => Some("R_X86_64_GOTPCREL"),
+            &RelocationType::R_X86_64_32 => Some("R_X86_64_32"),
+            &RelocationType::R_X86_64_32S => Some("R_X86_64_32S"),
+            &RelocationType::R_X86_64_16 => Some("R_X86_64_16"),
+            &RelocationType::R_X86_64_PC16 => Some("R_X86_64_PC16"),
+            &RelocationType::R_X86_64_8 => Some("R_X86_64_8"),
+            &RelocationType::R_X86_64_PC8 => Some("R_X86_64_PC8"),
+            &RelocationType::R_X86_64_DTPMOD64 => Some("R_X86_64_DTPMOD64"),
+            &RelocationType::R_X86_64_DTPOFF64 => Some("R_X86_64_DTPOFF64"),
+            &RelocationType::R_X86_64_TPOFF64 => Some("R_X86_64_TPOFF64"),
+            &RelocationType::R_X86_64_TLSGD => Some("R_X86_64_TLSGD"),
+            &RelocationType::R_X86_64_TLSLD => Some("R_X86_64_TLSLD"),
+            &RelocationType::R_X86_64_DTPOFF32 => Some("R_X86_64_DTPOFF32"),
+            &RelocationType::R_X86_64_GOTTPOFF => Some("R_X86_64_GOTTPOFF"),
+            &RelocationType::R_X86_64_TPOFF32 => Some("R_X86_64_TPOFF32"),
+            &RelocationType::R_X86_64_PC64 => Some("R_X86_64_PC64"),
+            &RelocationType::R_X86_64_GOTOFF64 => Some("R_X86_64_GOTOFF64"),
+            &RelocationType::R_X86_64_GOTPC32 => Some("R_X86_64_GOTPC32"),
+            &RelocationType::R_X86_64_SIZE32 => Some("R_X86_64_SIZE32"),
+            &RelocationType::R_X86_64_SIZE64 => Some("R_X86_64_SIZE64"),
+            &RelocationType::R_X86_64_GOTPC32_TLSDESC => Some("R_X86_64_GOTPC32_TLSDESC"),
+            &RelocationType::R_X86_64_TLSDESC_CALL => Some("R_X86_64_TLSDESC_CALL"),
+            &RelocationType::R_X86_64_TLSDESC => Some("R_X86_64_TLSDESC"),
+            &RelocationType::R_X86_64_IRELATIVE => Some("R_X86_64_IRELATIVE"),
+            &RelocationType::R_X86_64_RELATIVE64 => Some("R_X86_64_RELATIVE64"),
+            &RelocationType::R_X86_64_GOTPCRELX => Some("R_X86_64_GOTPCRELX"),
+            &RelocationType::R_X86_64_REX_GOTPCRELX => Some("R_X86_64_REX_GOTPCRELX"),
+            _ => None,
+        }
+    }
+
+    fn size(&self, rtype: &RelocationType) -> Option<usize> {
+        match rtype {
+            &RelocationType::R_X86_64_NONE | &RelocationType::R_X86_64_TLSDESC_CALL => Some(0),
+            &RelocationType::R_X86_64_8 | &RelocationType::R_X86_64_PC8 => Some(1),
+            &RelocationType::R_X86_64_16 | &RelocationType::R_X86_64_PC16 => Some(2),
+            &RelocationType::R_X86_64_64
+            | &RelocationType::R_X86_64_GLOB_DAT
+            | &RelocationType::R_X86_64_JUMP_SLOT
+            | &RelocationType::R_X86_64_RELATIVE
+            | &RelocationType::R_X86_64_DTPMOD64
+            | &RelocationType::R_X86_64_DTPOFF64
+            | &RelocationType::R_X86_64_TPOFF64
+            | &RelocationType::R_X86_64_PC64
+            | &RelocationType::R_X86_64_GOTOFF64
+            | &RelocationType::R_X86_64_SIZE64
+            | &RelocationType::R_X86_64_TLSDESC
+            | &RelocationType::R_X86_64_IRELATIVE
+            | &RelocationType::R_X86_64_RELATIVE64 => Some(8),
+            _ => self.typename(rtype).map(|_| 4),
+        }
+    }
+
+    fn classify(&self, rtype: &RelocationType) -> RelocationKind {
+        match rtype {
+            &RelocationType::R_X86_64_NONE => RelocationKind::None,
+            &RelocationType::R_X86_64_64
+            | &RelocationType::R_X86_64_32
+            | &RelocationType::R_X86_64_32S
+            | &RelocationType::R_X86_64_16
+            | &RelocationType::R_X86_64_8 => RelocationKind::Absolute,
+            &RelocationType::R_X86_64_PC32
+            | &RelocationType::R_X86_64_PC16
+            | &RelocationType::R_X86_64_PC8
+            | &RelocationType::R_X86_64_PC64 => RelocationKind::Relative,
+            &RelocationType::R_X86_64_PLT32 => RelocationKind::Plt,
+            &RelocationType::R_X86_64_GOT32
+            | &RelocationType::R_X86_64_GOTPCREL
+            | &RelocationType::R_X86_64_GOTPCRELX
+            | &RelocationType::R_X86_64_REX_GOTPCRELX => RelocationKind::Got,
+            &RelocationType::R_X86_64_RELATIVE | &RelocationType::R_X86_64_RELATIVE64 => {
+                RelocationKind::BaseRelative
+            }
+            &RelocationType::R_X86_64_COPY => RelocationKind::Copy,
+            &RelocationType::R_X86_64_GLOB_DAT => RelocationKind::GlobDat,
+            &RelocationType::R_X86_64_JUMP_SLOT => RelocationKind::JumpSlot,
+            &RelocationType::R_X86_64_DTPMOD64
+            | &RelocationType::R_X86_64_DTPOFF64
+            | &RelocationType::R_X86_64_TPOFF64
+            | &RelocationType::R_X86_64_TLSGD
+            | &RelocationType::R_X86_64_TLSLD
+            | &RelocationType::R_X86_64_DTPOFF32
+            | &RelocationType::R_X86_64_GOTTPOFF
+            | &RelocationType::R_X86_64_TPOFF32
+            | &RelocationType::R_X86_64_GOTPC32_TLSDESC
+            | &RelocationType::R_X86_64_TLSDESC_CALL
+            | &RelocationType::R_X86_64_TLSDESC => RelocationKind::Tls,
+            _ => RelocationKind::Other,
+        }
+    }
+
+    fn relative_type(&self) -> RelocationType {
+        RelocationType::R_X86_64_RELATIVE
+    }
+
+    fn apply(
+        &self,
+        rtype: &RelocationType,
+        v: &RelocationValues,
+        place: &mut [u8],
+        eh: &Header,
+    ) -> Result<(), Error> {
+        let r = rtype;
+        let gotpc = ((v.g + v.got) as i64)
+            .wrapping_add(v.a)
+            .wrapping_sub(v.p as i64);
+        match rtype {
+            &RelocationType::R_X86_64_NONE => Ok(()),
+            &RelocationType::R_X86_64_64 => write64(place, sa(v) as u64, eh),
+            &RelocationType::R_X86_64_PC64 => write64(place, sap(v) as u64, eh),
+            &RelocationType::R_X86_64_32 => write32(place, unsigned(r, sa(v), 32)? as u32, eh),
+            &RelocationType::R_X86_64_32S => write32(place, signed(r, sa(v), 32)? as u32, eh),
+            &RelocationType::R_X86_64_16 => write16(place, either(r, sa(v), 16)? as u16, eh),
+            &RelocationType::R_X86_64_8 => write8(place, either(r, sa(v), 8)? as u8),
+            &RelocationType::R_X86_64_PC32 => write32(place, signed(r, sap(v), 32)? as u32, eh),
+            &RelocationType::R_X86_64_PC16 => write16(place, signed(r, sap(v), 16)? as u16, eh),
+            &RelocationType::R_X86_64_PC8 => write8(place, signed(r, sap(v), 8)? as u8),
+            &RelocationType::R_X86_64_PLT32 => {
+                let x = (v.l as i64).wrapping_add(v.a).wrapping_sub(v.p as i64);
+                write32(place, signed(r, x, 32)? as u32, eh)
+            }
+            &RelocationType::R_X86_64_GOT32 => write32(
+                place,
+                either(r, (v.g as i64).wrapping_add(v.a), 32)? as u32,
+                eh,
+            ),
+            &RelocationType::R_X86_64_GOTPCREL
+            | &RelocationType::R_X86_64_GOTPCRELX
+            | &RelocationType::R_X86_64_REX_GOTPCRELX => {
+                write32(place, signed(r, gotpc, 32)? as u32, eh)
+            }
+            &RelocationType::R_X86_64_GOTPC32 => {
+                let x = (v.got as i64).wrapping_add(v.a).wrapping_sub(v.p as i64);
+                write32(place, signed(r, x, 32)? as u32, eh)
+            }
+            &RelocationType::R_X86_64_GOTOFF64 => {
+                write64(place, sa(v).wrapping_sub(v.got as i64) as u64, eh)
+            }
+            &RelocationType::R_X86_64_GLOB_DAT | &RelocationType::R_X86_64_JUMP_SLOT => {
+                write64(place, v.s, eh)
+            }
+            &RelocationType::R_X86_64_RELATIVE | &RelocationType::R_X86_64_RELATIVE64 => {
+                write64(place, ba(v) as u64, eh)
+            }
+            &RelocationType::R_X86_64_SIZE32 => write32(
+                place,
+                unsigned(r, (v.z as i64).wrapping_add(v.a), 32)? as u32,
+                eh,
+            ),
+            &RelocationType::R_X86_64_SIZE64 => {
+                write64(place, (v.z as i64).wrapping_add(v.a) as u64, eh)
+            }
+            _ => Err(Error::UnsupportedRelocation(rtype.to_u32())),
+        }
+    }
+}
+
+/// 32bit mips uses REL, so v.a is the addend from the place.
+/// for HI16 and LO16 this has to be the combined AHL of the pair
+impl RelocationHandler for Mips {
+    fn typename(&self, rtype: &RelocationType) -> Option<&'static str> {
+        match rtype {
+            &RelocationType::R_MIPS_NONE => Some("R_MIPS_NONE"),
+            &RelocationType::R_MIPS_16 => Some("R_MIPS_16"),
+            &RelocationType::R_MIPS_32 => Some("R_MIPS_32"),
+            &RelocationType::R_MIPS_REL32 => Some("R_MIPS_REL32"),
+            &RelocationType::R_MIPS_26 => Some("R_MIPS_26"),
+            &RelocationType::R_MIPS_HI16 => Some("R_MIPS_HI16"),
+            &RelocationType::R_MIPS_LO16 => Some("R_MIPS_LO16"),
+            &RelocationType::R_MIPS_GPREL16 => Some("R_MIPS_GPREL16"),
+            &RelocationType::R_MIPS_LITERAL => Some("R_MIPS_LITERAL"),
+            &RelocationType::R_MIPS_GOT16 => Some("R_MIPS_GOT16"),
+            &RelocationType::R_MIPS_PC16 => Some("R_MIPS_PC16"),
+            &RelocationType::R_MIPS_CALL16 => Some("R_MIPS_CALL16"),
+            &RelocationType::R_MIPS_GPREL32 => Some("R_MIPS_GPREL32"),
+            &RelocationType::R_MIPS_SHIFT5 => Some("R_MIPS_SHIFT5"),
+            &RelocationType::R_MIPS_SHIFT6 => Some("R_MIPS_SHIFT6"),
+            &RelocationType::R_MIPS_64 => Some("R_MIPS_64"),
+            &RelocationType::R_MIPS_GOT_DISP => Some("R_MIPS_GOT_DISP"),
+            &RelocationType::R_MIPS_GOT_PAGE => Some("R_MIPS_GOT_PAGE"),
+            &RelocationType::R_MIPS_GOT_OFST => Some("R_MIPS_GOT_OFST"),
+            &RelocationType::R_MIPS_GOT_HI16 => Some("R_MIPS_GOT_HI16"),
+            &RelocationType::R_MIPS_GOT_LO16 => Some("R_MIPS_GOT_LO16"),
+            &RelocationType::R_MIPS_SUB => Some("R_MIPS_SUB"),
+            &RelocationType::R_MIPS_INSERT_A => Some("R_MIPS_INSERT_A"),
+            &RelocationType::R_MIPS_INSERT_B => Some("R_MIPS_INSERT_B"),
+            &RelocationType::R_MIPS_DELETE => Some("R_MIPS_DELETE"),
+            &RelocationType::R_MIPS_HIGHER => Some("R_MIPS_HIGHER"),
+            &RelocationType::R_MIPS_HIGHEST => Some("R_MIPS_HIGHEST"),
+            &RelocationType::R_MIPS_CALL_HI16 => Some("R_MIPS_CALL_HI16"),
+            &RelocationType::R_MIPS_CALL_LO16 => Some("R_MIPS_CALL_LO16"),
+            &RelocationType::R_MIPS_SCN_DISP => Some("R_MIPS_SCN_DISP"),
+            &RelocationType::R_MIPS_REL16 => Some("R_MIPS_REL16"),
+            &RelocationType::R_MIPS_ADD_IMMEDIATE => Some("R_MIPS_ADD_IMMEDIATE"),
+            &RelocationType::R_MIPS_PJUMP => Some("R_MIPS_PJUMP"),
+            &RelocationType::R_MIPS_RELGOT => Some("R_MIPS_RELGOT"),
+            &RelocationType::R_MIPS_JALR => Some("R_MIPS_JALR"),
+            &RelocationType::R_MIPS_TLS_DTPMOD32 => Some("R_MIPS_TLS_DTPMOD32"),
+            &RelocationType::R_MIPS_TLS_DTPREL32 => Some("R_MIPS_TLS_DTPREL32"),
+            &RelocationType::R_MIPS_TLS_DTPMOD64 => Some("R_MIPS_TLS_DTPMOD64"),
+            &RelocationType::R_MIPS_TLS_DTPREL64 => Some("R_MIPS_TLS_DTPREL64"),
+            &RelocationType::R_MIPS_TLS_GD => Some("R_MIPS_TLS_GD"),
+            &RelocationType::R_MIPS_TLS_LDM => Some("R_MIPS_TLS_LDM"),
+            &RelocationType::R_MIPS_TLS_DTPREL_HI16 => Some("R_MIPS_TLS_DTPREL_HI16"),
+            &RelocationType::R_MIPS_TLS_DTPREL_LO16 => Some("R_MIPS_TLS_DTPREL_LO16"),
+            &RelocationType::R_MIPS_TLS_GOTTPREL => Some("R_MIPS_TLS_GOTTPREL"),
+            &RelocationType::R_MIPS_TLS_TPREL32 => Some("R_MIPS_TLS_TPREL32"),
+            &RelocationType::R_MIPS_TLS_TPREL64 => Some("R_MIPS_TLS_TPREL64"),
+            &RelocationType::R_MIPS_TLS_TPREL_HI16 => Some("R_MIPS_TLS_TPREL_HI16"),
+            &RelocationType::R_MIPS_TLS_TPREL_LO16 => Some("R_MIPS_TLS_TPREL_LO16"),
+            &RelocationType::R_MIPS_GLOB_DAT => Some("R_MIPS_GLOB_DAT"),
+            &RelocationType::R_MIPS_COPY => Some("R_MIPS_COPY"),
+            &RelocationType::R_MIPS_JUMP_SLOT => Some("R_MIPS_JUMP_SLOT"),
+            _ => None,
+        }
+    }
+
+    fn size(&self, rtype: &RelocationType) -> Option<usize> {
+        match rtype {
+            &RelocationType::R_MIPS_NONE | &RelocationType::R_MIPS_JALR => Some(0),
+            &RelocationType::R_MIPS_64
+            | &RelocationType::R_MIPS_TLS_DTPMOD64
+            | &RelocationType::R_MIPS_TLS_DTPREL64
+            | &RelocationType::R_MIPS_TLS_TPREL64 => Some(8),
+            _ => self.typename(rtype).map(|_| 4),
+        }
+    }
+
+    fn classify(&self, rtype: &RelocationType) -> RelocationKind {
+        match rtype {
+            &RelocationType::R_MIPS_NONE => RelocationKind::None,
+            &RelocationType::R_MIPS_16
+            | &RelocationType::R_MIPS_32
+            | &RelocationType::R_MIPS_64
+            | &RelocationType::R_MIPS_HI16
+            | &RelocationType::R_MIPS_LO16 => RelocationKind::Absolute,
+            &RelocationType::R_MIPS_PC16 => RelocationKind::Relative,
+            &RelocationType::R_MIPS_GOT16
+            | &RelocationType::R_MIPS_CALL16
+            | &RelocationType::R_MIPS_GOT_DISP
+            | &RelocationType::R_MIPS_GOT_PAGE
+            | &RelocationType::R_MIPS_GOT_OFST
+            | &RelocationType::R_MIPS_GOT_HI16
+            | &RelocationType::R_MIPS_GOT_LO16
+            | &RelocationType::R_MIPS_CALL_HI16
+            | &RelocationType::R_MIPS_CALL_LO16 => RelocationKind::Got,
+            // as a dynamic relocation against symbol 0 this is B + A
+            &RelocationType::R_MIPS_REL32 => RelocationKind::BaseRelative,
+            &RelocationType::R_MIPS_COPY => RelocationKind::Copy,
+            &RelocationType::R_MIPS_GLOB_DAT => RelocationKind::GlobDat,
+            &RelocationType::R_MIPS_JUMP_SLOT => RelocationKind::JumpSlot,
+            &RelocationType::R_MIPS_TLS_DTPMOD32
+            | &RelocationType::R_MIPS_TLS_DTPREL32
+            | &RelocationType::R_MIPS_TLS_DTPMOD64
+            | &RelocationType::R_MIPS_TLS_DTPREL64
+            | &RelocationType::R_MIPS_TLS_GD
+            | &RelocationType::R_MIPS_TLS_LDM
+            | &RelocationType::R_MIPS_TLS_DTPREL_HI16
+            | &RelocationType::R_MIPS_TLS_DTPREL_LO16
+            | &RelocationType::R_MIPS_TLS_GOTTPREL
+            | &RelocationType::R_MIPS_TLS_TPREL32
+            | &RelocationType::R_MIPS_TLS_TPREL64
+            | &RelocationType::R_MIPS_TLS_TPREL_HI16
+            | &RelocationType::R_MIPS_TLS_TPREL_LO16 => RelocationKind::Tls,
+            _ => RelocationKind::Other,
+        }
+    }
+
+    fn relative_type(&self) -> RelocationType {
+        RelocationType::R_MIPS_REL32
+    }
+
+    fn apply(
+        &self,
+        rtype: &RelocationType,
+        v: &RelocationValues,
+        place: &mut [u8],
+        eh: &Header,
+    ) -> Result<(), Error> {
+        let r = rtype;
+        match rtype {
+            &RelocationType::R_MIPS_NONE => Ok(()),
+            &RelocationType::R_MIPS_32 => write32(place, sa(v) as u32, eh),
+            &RelocationType::R_MIPS_REL32 => write32(place, ba(v) as u32, eh),
+            &RelocationType::R_MIPS_16 => merge32(place, 0xffff, signed(r, sa(v), 16)? as u32, eh),
+            &RelocationType::R_MIPS_HI16 => merge32(place, 0xffff, ha(sa(v)) as u32, eh),
+            &RelocationType::R_MIPS_LO16 => merge32(place, 0xffff, lo(sa(v)) as u32, eh),
+            &RelocationType::R_MIPS_PC16 => {
+                let x = signed(r, aligned(r, sap(v), 4)?, 18)?;
+                merge32(place, 0xffff, (x >> 2) as u32, eh)
+            }
+            &RelocationType::R_MIPS_26 => {
+                let x = ((v.a as u64) | (v.p & 0xf0000000)).wrapping_add(v.s);
+                merge32(place, 0x03ffffff, (x >> 2) as u32, eh)
+            }
+            &RelocationType::R_MIPS_GLOB_DAT | &RelocationType::R_MIPS_JUMP_SLOT => {
+                write32(place, v.s as u32, eh)
+            }
+            _ => Err(Error::UnsupportedRelocation(rtype.to_u32())),
+        }
+    }
+}
+
+/// ARM uses REL, so v.a is the addend decoded from the place
+impl RelocationHandler for Arm {
+    fn typename(&self, rtype: &RelocationType) -> Option<&'static str> {
+        match rtype {
+            &RelocationType::R_ARM_NONE => Some("R_ARM_NONE"),
+            &RelocationType::R_ARM_PC24 => Some("R_ARM_PC24"),
+            &RelocationType::R_ARM_ABS32 => Some("R_ARM_ABS32"),
+            &RelocationType::R_ARM_REL32 => Some("R_ARM_REL32"),
+            &RelocationType::R_ARM_LDR_PC_G0 => Some("R_ARM_LDR_PC_G0"),
+            &RelocationType::R_ARM_ABS16 => Some("R_ARM_ABS16"),
+            &RelocationType::R_ARM_ABS12 => Some("R_ARM_ABS12"),
+            &RelocationType::R_ARM_THM_ABS5 => Some("R_ARM_THM_ABS5"),
+            &RelocationType::R_ARM_ABS8 => Some("R_ARM_ABS8"),
+            &RelocationType::R_ARM_SBREL32 => Some("R_ARM_SBREL32"),
+            &RelocationType::R_ARM_THM_CALL => Some("R_ARM_THM_CALL"),
+            &RelocationType::R_ARM_THM_PC8 => Some("R_ARM_THM_PC8"),
+            &RelocationType::R_ARM_BREL_ADJ => Some("R_ARM_BREL_ADJ"),
+            &RelocationType::R_ARM_TLS_DESC => Some("R_ARM_TLS_DESC"),
+            &RelocationType::R_ARM_THM_SWI8 => Some("R_ARM_THM_SWI8"),
+            &RelocationType::R_ARM_XPC25 => Some("R_ARM_XPC25"),
+            &RelocationType::R_ARM_THM_XPC22 => Some("R_ARM_THM_XPC22"),
+            &RelocationType::R_ARM_TLS_DTPMOD32 => Some("R_ARM_TLS_DTPMOD32"),
+            &RelocationType::R_ARM_TLS_DTPOFF32 => Some("R_ARM_TLS_DTPOFF32"),
+            &RelocationType::R_ARM_TLS_TPOFF32 => Some("R_ARM_TLS_TPOFF32"),
+            &RelocationType::R_ARM_COPY => Some("R_ARM_COPY"),
+            &RelocationType::R_ARM_GLOB_DAT => Some("R_ARM_GLOB_DAT"),
+            &RelocationType::R_ARM_JUMP_SLOT => Some("R_ARM_JUMP_SLOT"),
+            &RelocationType::R_ARM_RELATIVE => Some("R_ARM_RELATIVE"),
+            &RelocationType::R_ARM_GOTOFF => Some("R_ARM_GOTOFF"),
+            &RelocationType::R_ARM_BASE_PREL => Some("R_ARM_BASE_PREL"),
+            &RelocationType::R_ARM_GOT_BREL => Some("R_ARM_GOT_BREL"),
+            &RelocationType::R_ARM_PLT32 => Some("R_ARM_PLT32"),
+            &RelocationType::R_ARM_CALL => Some("R_ARM_CALL"),
+            &RelocationType::R_ARM_JUMP24 => Some("R_ARM_JUMP24"),
+            &RelocationType::R_ARM_THM_JUMP24 => Some("R_ARM_THM_JUMP24"),
+            &RelocationType::R_ARM_BASE_ABS => Some("R_ARM_BASE_ABS"),
+            &RelocationType::R_ARM_TARGET1 => Some("R_ARM_TARGET1"),
+            &RelocationType::R_ARM_V4BX => Some("R_ARM_V4BX"),
+            &RelocationType::R_ARM_TARGET2 => Some("R_ARM_TARGET2"),
+            &RelocationType::R_ARM_PREL31 => Some("R_ARM_PREL31"),
+            &RelocationType::R_ARM_MOVW_ABS_NC => Some("R_ARM_MOVW_ABS_NC"),
+            &RelocationType::R_ARM_MOVT_ABS => Some("R_ARM_MOVT_ABS"),
+            &RelocationType::R_ARM_MOVW_PREL_NC => Some("R_ARM_MOVW_PREL_NC"),
+            &RelocationType::R_ARM_MOVT_PREL => Some("R_ARM_MOVT_PREL"),
+            &RelocationType::R_ARM_THM_MOVW_ABS_NC => Some("R_ARM_THM_MOVW_ABS_NC"),
+            &RelocationType::R_ARM_THM_MOVT_ABS => Some("R_ARM_THM_MOVT_ABS"),
+            &RelocationType::R_ARM_THM_MOVW_PREL_NC => Some("R_ARM_THM_MOVW_PREL_NC"),
+            &RelocationType::R_ARM_THM_MOVT_PREL => Some("R_ARM_THM_MOVT_PREL"),
+            &RelocationType::R_ARM_THM_JUMP19 => Some("R_ARM_THM_JUMP19"),
+            &RelocationType::R_ARM_GOT_PREL => Some("R_ARM_GOT_PREL"),
+            &RelocationType::R_ARM_GNU_VTENTRY => Some("R_ARM_GNU_VTENTRY"),
+            &RelocationType::R_ARM_GNU_VTINHERIT => Some("R_ARM_GNU_VTINHERIT"),
+            &RelocationType::R_ARM_THM_JUMP11 => Some("R_ARM_THM_JUMP11"),
+            &RelocationType::R_ARM_THM_JUMP8 => Some("R_ARM_THM_JUMP8"),
+            &RelocationType::R_ARM_TLS_GD32 => Some("R_ARM_TLS_GD32"),
+            &RelocationType::R_ARM_TLS_LDM32 => Some("R_ARM_TLS_LDM32"),
+            &RelocationType::R_ARM_TLS_LDO32 => Some("R_ARM_TLS_LDO32"),
+            &RelocationType::R_ARM_TLS_IE32 => Some("R_ARM_TLS_IE32"),
+            &RelocationType::R_ARM_TLS_LE32 => Some("R_ARM_TLS_LE32"),
+            &RelocationType::R_ARM_TLS_LDO12 => Some("R_ARM_TLS_LDO12"),
+            &RelocationType::R_ARM_TLS_LE12 => Some("R_ARM_TLS_LE12"),
+            &RelocationType::R_ARM_TLS_IE12GP => Some("R_ARM_TLS_IE12GP"),
+            &RelocationType::R_ARM_IRELATIVE => Some("R_ARM_IRELATIVE"),
+            _ => None,
+        }
+    }
+
+    fn size(&self, rtype: &RelocationType) -> Option<usize> {
+        match rtype {
+            &RelocationType::R_ARM_NONE
+            | &RelocationType::R_ARM_V4BX
+            | &RelocationType::R_ARM_GNU_VTENTRY
+            | &RelocationType::R_ARM_GNU_VTINHERIT => Some(0),
+            &RelocationType::R_ARM_ABS8 => Some(1),
+            &RelocationType::R_ARM_ABS16
+            | &RelocationType::R_ARM_THM_ABS5
+            | &RelocationType::R_ARM_THM_PC8
+            | &RelocationType::R_ARM_THM_SWI8
+            | &RelocationType::R_ARM_THM_JUMP11
+            | &RelocationType::R_ARM_THM_JUMP8 => Some(2),
+            _ => self.typename(rtype).map(|_| 4),
+        }
+    }
+
+    fn classify(&self, rtype: &RelocationType) -> RelocationKind {
+        match rtype {
+            &RelocationType::R_ARM_NONE | &RelocationType::R_ARM_V4BX => RelocationKind::None,
+            &RelocationType::R_ARM_ABS32
+            | &RelocationType::R_ARM_ABS16
+            | &RelocationType::R_ARM_ABS12
+            | &RelocationType::R_ARM_ABS8
+            | &RelocationType::R_ARM_THM_ABS5
+            | &RelocationType::R_ARM_TARGET1
+            | &RelocationType::R_ARM_MOVW_ABS_NC
+            | &RelocationType::R_ARM_MOVT_ABS
+            | &RelocationType::R_ARM_THM_MOVW_ABS_NC
+            | &RelocationType::R_ARM_THM_MOVT_ABS => RelocationKind::Absolute,
+            &RelocationType::R_ARM_REL32
+            | &RelocationType::R_ARM_PREL31
+            | &RelocationType::R_ARM_MOVW_PREL_NC
+            | &RelocationType::R_ARM_MOVT_PREL
+            | &RelocationType::R_ARM_THM_MOVW_PREL_NC
+            | &RelocationType::R_ARM_THM_MOVT_PREL
+            | &RelocationType::R_ARM_THM_JUMP19
+            | &RelocationType::R_ARM_THM_JUMP11
+            | &RelocationType::R_ARM_THM_JUMP8 => RelocationKind::Relative,
+            &RelocationType::R_ARM_PC24
+            | &RelocationType::R_ARM_CALL
+            | &RelocationType::R_ARM_JUMP24
+            | &RelocationType::R_ARM_PLT32
+            | &RelocationType::R_ARM_THM_CALL
+            | &RelocationType::R_ARM_THM_JUMP24 => RelocationKind::Plt,
+            &RelocationType::R_ARM_GOT_BREL
+            | &RelocationType::R_ARM_GOT_PREL
+            | &RelocationType::R_ARM_TARGET2 => RelocationKind::Got,
+            &RelocationType::R_ARM_RELATIVE => RelocationKind::BaseRelative,
+            &RelocationType::R_ARM_COPY => RelocationKind::Copy,
+            &RelocationType::R_ARM_GLOB_DAT => RelocationKind::GlobDat,
+            &RelocationType::R_ARM_JUMP_SLOT => RelocationKind::JumpSlot,
+            &RelocationType::R_ARM_TLS_DESC
+            | &RelocationType::R_ARM_TLS_DTPMOD32
+            | &RelocationType::R_ARM_TLS_DTPOFF32
+            | &RelocationType::R_ARM_TLS_TPOFF32
+            | &RelocationType::R_ARM_TLS_GD32
+            | &RelocationType::R_ARM_TLS_LDM32
+            | &RelocationType::R_ARM_TLS_LDO32
+            | &RelocationType::R_ARM_TLS_IE32
+            | &RelocationType::R_ARM_TLS_LE32
+            | &RelocationType::R_ARM_TLS_LDO12
+            | &RelocationType::R_ARM_TLS_LE12
+            | &RelocationType::R_ARM_TLS_IE12GP => RelocationKind::Tls,
+            _ => RelocationKind::Other,
+        }
+    }
+
+    fn relative_type(&self) -> RelocationType {
+        RelocationType::R_ARM_RELATIVE
+    }
+
+    fn apply(
+        &self,
+        rtype: &RelocationType,
+        v: &RelocationValues,
+        place: &mut [u8],
+        eh: &Header,
+    ) -> Result<(), Error> {
+        let r = rtype;
+        // movw and movt split their 16 bit immediate into imm4:imm12
+        let movw = |x: i64| (((x as u32) & 0xf000) << 4) | ((x as u32) & 0xfff);
+        match rtype {
+            &RelocationType::R_ARM_NONE | &RelocationType::R_ARM_V4BX => Ok(()),
+            &RelocationType::R_ARM_ABS32 | &RelocationType::R_ARM_TARGET1 => {
+                write32(place, sa(v) as u32, eh)
+            }
+            &RelocationType::R_ARM_REL32 => write32(place, sap(v) as u32, eh),
+            &RelocationType::R_ARM_ABS16 => write16(place, either(r, sa(v), 16)? as u16, eh),
+            &RelocationType::R_ARM_ABS8 => write8(place, either(r, sa(v), 8)? as u8),
+            &RelocationType::R_ARM_PREL31 => {
+                merge32(place, 0x7fffffff, signed(r, sap(v), 31)? as u32, eh)
+            }
+            &RelocationType::R_ARM_PC24
+            | &RelocationType::R_ARM_CALL
+            | &RelocationType::R_ARM_JUMP24
+            | &RelocationType::R_ARM_PLT32 => {
+                let x = signed(r, aligned(r, sap(v), 4)?, 26)?;
+                merge32(place, 0x00ffffff, (x >> 2) as u32, eh)
+            }
+            &RelocationType::R_ARM_MOVW_ABS_NC => merge32(place, 0x000f0fff, movw(sa(v)), eh),
+            &RelocationType::R_ARM_MOVT_ABS => merge32(place, 0x000f0fff, movw(sa(v) >> 16), eh),
+            &RelocationType::R_ARM_MOVW_PREL_NC => merge32(place, 0x000f0fff, movw(sap(v)), eh),
+            &RelocationType::R_ARM_MOVT_PREL => merge32(place, 0x000f0fff, movw(sap(v) >> 16), eh),
+            &RelocationType::R_ARM_GLOB_DAT | &RelocationType::R_ARM_JUMP_SLOT => {
+                write32(place, v.s as u32, eh)
+            }
+            &RelocationType::R_ARM_RELATIVE => write32(place, ba(v) as u32, eh),
+            _ => Err(Error::UnsupportedRelocation(rtype.to_u32())),
+        }
+    }
+}
+
+/// v.got is the TOC pointer, see linker::ppc64_toc_base
+impl RelocationHandler for Ppc64 {
+    fn typename(&self, rtype: &RelocationType) -> Option<&'static str> {
+        match rtype {
+            &RelocationType::R_PPC64_NONE => Some("R_PPC64_NONE"),
+            &RelocationType::R_PPC64_ADDR32 => Some("R_PPC64_ADDR32"),
+            &RelocationType::R_PPC64_ADDR24 => Some("R_PPC64_ADDR24"),
+            &RelocationType::R_PPC64_ADDR16 => Some("R_PPC64_ADDR16"),
+            &RelocationType::R_PPC64_ADDR16_LO => Some("R_PPC64_ADDR16_LO"),
+            &RelocationType::R_PPC64_ADDR16_HI => Some("R_PPC64_ADDR16_HI"),
+            &RelocationType::R_PPC64_ADDR16_HA => Some("R_PPC64_ADDR16_HA"),
+            &RelocationType::R_PPC64_ADDR14 => Some("R_PPC64_ADDR14"),
+            &RelocationType::R_PPC64_ADDR14_BRTAKEN => Some("R_PPC64_ADDR14_BRTAKEN"),
+            &RelocationType::R_PPC64_ADDR14_BRNTAKEN => Some("R_PPC64_ADDR14_BRNTAKEN"),
+            &RelocationType::R_PPC64_REL24 => Some("R_PPC64_REL24"),
+            &RelocationType::R_PPC64_REL14 => Some("R_PPC64_REL14"),
+            &RelocationType::R_PPC64_REL14_BRTAKEN => Some("R_PPC64_REL14_BRTAKEN"),
+            &RelocationType::R_PPC64_REL14_BRNTAKEN => Some("R_PPC64_REL14_BRNTAKEN"),
+            &RelocationType::R_PPC64_GOT16 => Some("R_PPC64_GOT16"),
+            &RelocationType::R_PPC64_GOT16_LO => Some("R_PPC64_GOT16_LO"),
+            &RelocationType::R_PPC64_GOT16_HI => Some("R_PPC64_GOT16_HI"),
+            &RelocationType::R_PPC64_GOT16_HA => Some("R_PPC64_GOT16_HA"),
+            &RelocationType::R_PPC64_COPY => Some("R_PPC64_COPY"),
+            &RelocationType::R_PPC64_GLOB_DAT => Some("R_PPC64_GLOB_DAT"),
+            &RelocationType::R_PPC64_JMP_SLOT => Some("R_PPC64_JMP_SLOT"),
+            &RelocationType::R_PPC64_RELATIVE => Some("R_PPC64_RELATIVE"),
+            &RelocationType::R_PPC64_UADDR32 => Some("R_PPC64_UADDR32"),
+            &RelocationType::R_PPC64_UADDR16 => Some("R_PPC64_UADDR16"),
+            &RelocationType::R_PPC64_REL32 => Some("R_PPC64_REL32"),
+            &RelocationType::R_PPC64_PLT32 => Some("R_PPC64_PLT32"),
+            &RelocationType::R_PPC64_PLTREL32 => Some("R_PPC64_PLTREL32"),
+            &RelocationType::R_PPC64_PLT16_LO => Some("R_PPC64_PLT16_LO"),
+            &RelocationType::R_PPC64_PLT16_HI => Some("R_PPC64_PLT16_HI"),
+            &RelocationType::R_PPC64_PLT16_HA => Some("R_PPC64_PLT16_HA"),
+            &RelocationType::R_PPC64_SECTOFF => Some("R_PPC64_SECTOFF"),
+            &RelocationType::R_PPC64_SECTOFF_LO => Some("R_PPC64_SECTOFF_LO"),
+            &RelocationType::R_PPC64_SECTOFF_HI => Some("R_PPC64_SECTOFF_HI"),
+            &RelocationType::R_PPC64_SECTOFF_HA => Some("R_PPC64_SECTOFF_HA"),
+            &RelocationType::R_PPC64_ADDR30 => Some("R_PPC64_ADDR30"),
+            &RelocationType::R_PPC64_ADDR64 => Some("R_PPC64_ADDR64"),
+            &RelocationType::R_PPC64_ADDR16_HIGHER => Some("R_PPC64_ADDR16_HIGHER"),
+            &RelocationType::R_PPC64_ADDR16_HIGHERA => Some("R_PPC64_ADDR16_HIGHERA"),
+            &RelocationType::R_PPC64_ADDR16_HIGHEST => Some("R_PPC64_ADDR16_HIGHEST"),
+            &RelocationType::R_PPC64_ADDR16_HIGHESTA => Some("R_PPC64_ADDR16_HIGHESTA"),
+            &RelocationType::R_PPC64_UADDR64 => Some("R_PPC64_UADDR64"),
+            &RelocationType::R_PPC64_REL64 => Some("R_PPC64_REL64"),
+            &RelocationType::R_PPC64_PLT64 => Some("R_PPC64_PLT64"),
+            &RelocationType::R_PPC64_PLTREL64 => Some("R_PPC64_PLTREL64"),
+            &RelocationType::R_PPC64_TOC16 => Some("R_PPC64_TOC16"),
+            &RelocationType::R_PPC64_TOC16_LO => Some("R_PPC64_TOC16_LO"),
+            &RelocationType::R_PPC64_TOC16_HI => Some("R_PPC64_TOC16_HI"),
+            &RelocationType::R_PPC64_TOC16_HA => Some("R_PPC64_TOC16_HA"),
+            &RelocationType::R_PPC64_TOC => Some("R_PPC64_TOC"),
+            &RelocationType::R_PPC64_PLTGOT16 => Some("R_PPC64_PLTGOT16"),
+            &RelocationType::R_PPC64_PLTGOT16_LO => Some("R_PPC64_PLTGOT16_LO"),
+            &RelocationType::R_PPC64_PLTGOT16_HI => Some("R_PPC64_PLTGOT16_HI"),
+            &RelocationType::R_PPC64_PLTGOT16_HA => Some("R_PPC64_PLTGOT16_HA"),
+            &RelocationType::R_PPC64_ADDR16_DS => Some("R_PPC64_ADDR16_DS"),
+            &RelocationType::R_PPC64_ADDR16_LO_DS => Some("R_PPC64_ADDR16_LO_DS"),
+            &RelocationType::R_PPC64_GOT16_DS => Some("R_PPC64_GOT16_DS"),
+            &RelocationType::R_PPC64_GOT16_LO_DS => Some("R_PPC64_GOT16_LO_DS"),
+            &RelocationType::R_PPC64_PLT16_LO_DS => Some("R_PPC64_PLT16_LO_DS"),
+            &RelocationType::R_PPC64_SECTOFF_DS => Some("R_PPC64_SECTOFF_DS"),
+            &RelocationType::R_PPC64_SECTOFF_LO_DS => Some("R_PPC64_SECTOFF_LO_DS"),
+            &RelocationType::R_PPC64_TOC16_DS => Some("R_PPC64_TOC16_DS"),
+            &RelocationType::R_PPC64_TOC16_LO_DS => Some("R_PPC64_TOC16_LO_DS"),
+            &RelocationType::R_PPC64_PLTGOT16_DS => Some("R_PPC64_PLTGOT16_DS"),
+            &RelocationType::R_PPC64_PLTGOT16_LO_DS => Some("R_PPC64_PLTGOT16_LO_DS"),
+            &RelocationType::R_PPC64_TLS => Some("R_PPC64_TLS"),
+            &RelocationType::R_PPC64_DTPMOD64 => Some("R_PPC64_DTPMOD64"),
+            &RelocationType::R_PPC64_TPREL16 => Some("R_PPC64_TPREL16"),
+            &RelocationType::R_PPC64_TPREL16_LO => Some("R_PPC64_TPREL16_LO"),
+            &RelocationType::R_PPC64_TPREL16_HI => Some("R_PPC64_TPREL16_HI"),
+            &RelocationType::R_PPC64_TPREL16_HA => Some("R_PPC64_TPREL16_HA"),
+            &RelocationType::R_PPC64_TPREL64 => Some("R_PPC64_TPREL64"),
+            &RelocationType::R_PPC64_DTPREL16 => Some("R_PPC64_DTPREL16"),
+            &RelocationType::R_PPC64_DTPREL16_LO => Some("R_PPC64_DTPREL16_LO"),
+            &RelocationType::R_PPC64_DTPREL16_HI => Some("R_PPC64_DTPREL16_HI"),
+            &RelocationType::R_PPC64_DTPREL16_HA => Some("R_PPC64_DTPREL16_HA"),
+            &RelocationType::R_PPC64_DTPREL64 => Some("R_PPC64_DTPREL64"),
+            &RelocationType::R_PPC64_GOT_TLSGD16 => Some("R_PPC64_GOT_TLSGD16"),
+            &RelocationType::R_PPC64_GOT_TLSGD16_LO => Some("R_PPC64_GOT_TLSGD16_LO"),
+            &RelocationType::R_PPC64_GOT_TLSGD16_HI => Some("R_PPC64_GOT_TLSGD16_HI"),
+            &RelocationType::R_PPC64_GOT_TLSGD16_HA => Some("R_PPC64_GOT_TLSGD16_HA"),
+            &RelocationType::R_PPC64_GOT_TLSLD16 => Some("R_PPC64_GOT_TLSLD16"),
+            &RelocationType::R_PPC64_GOT_TLSLD16_LO => Some("R_PPC64_GOT_TLSLD16_LO"),
+            &RelocationType::R_PPC64_GOT_TLSLD16_HI => Some("R_PPC64_GOT_TLSLD16_HI"),
+            &RelocationType::R_PPC64_GOT_TLSLD16_HA => Some("R_PPC64_GOT_TLSLD16_HA"),
+            &RelocationType::R_PPC64_GOT_TPREL16_DS => Some("R_PPC64_GOT_TPREL16_DS"),
+            &RelocationType::R_PPC64_GOT_TPREL16_LO_DS => Some("R_PPC64_GOT_TPREL16_LO_DS"),
+            &RelocationType::R_PPC64_GOT_TPREL16_HI => Some("R_PPC64_GOT_TPREL16_HI"),
+            &RelocationType::R_PPC64_GOT_TPREL16_HA => Some("R_PPC64_GOT_TPREL16_HA"),
+            &RelocationType::R_PPC64_GOT_DTPREL16_DS => Some("R_PPC64_GOT_DTPREL16_DS"),
+            &RelocationType::R_PPC64_GOT_DTPREL16_LO_DS => Some("R_PPC64_GOT_DTPREL16_LO_DS"),
+            &RelocationType::R_PPC64_GOT_DTPREL16_HI => Some("R_PPC64_GOT_DTPREL16_HI"),
+            &RelocationType::R_PPC64_GOT_DTPREL16_HA => Some("R_PPC64_GOT_DTPREL16_HA"),
+            &RelocationType::R_PPC64_TPREL16_DS => Some("R_PPC64_TPREL16_DS"),
+            &RelocationType::R_PPC64_TPREL16_LO_DS => Some("R_PPC64_TPREL16_LO_DS"),
+            &RelocationType::R_PPC64_TPREL16_HIGHER => Some("R_PPC64_TPREL16_HIGHER"),
+            &RelocationType::R_PPC64_TPREL16_HIGHERA => Some("R_PPC64_TPREL16_HIGHERA"),
+            &RelocationType::R_PPC64_TPREL16_HIGHEST => Some("R_PPC64_TPREL16_HIGHEST"),
+            &RelocationType::R_PPC64_TPREL16_HIGHESTA => Some("R_PPC64_TPREL16_HIGHESTA"),
+            &RelocationType::R_PPC64_DTPREL16_DS => Some("R_PPC64_DTPREL16_DS"),
+            &RelocationType::R_PPC64_DTPREL16_LO_DS => Some("R_PPC64_DTPREL16_LO_DS"),
+            &RelocationType::R_PPC64_DTPREL16_HIGHER => Some("R_PPC64_DTPREL16_HIGHER"),
+            &RelocationType::R_PPC64_DTPREL16_HIGHERA => Some("R_PPC64_DTPREL16_HIGHERA"),
+            &RelocationType::R_PPC64_DTPREL16_HIGHEST => Some("R_PPC64_DTPREL16_HIGHEST"),
+            &RelocationType::R_PPC64_DTPREL16_HIGHESTA => Some("R_PPC64_DTPREL16_HIGHESTA"),
+            &RelocationType::R_PPC64_TLSGD => Some("R_PPC64_TLSGD"),
+            &RelocationType::R_PPC64_TLSLD => Some("R_PPC64_TLSLD"),
+            &RelocationType::R_PPC64_TOCSAVE => Some("R_PPC64_TOCSAVE"),
+            &RelocationType::R_PPC64_ADDR16_HIGH => Some("R_PPC64_ADDR16_HIGH"),
+            &RelocationType::R_PPC64_ADDR16_HIGHA => Some("R_PPC64_ADDR16_HIGHA"),
+            &RelocationType::R_PPC64_TPREL16_HIGH => Some("R_PPC64_TPREL16_HIGH"),
+            &RelocationType::R_PPC64_TPREL16_HIGHA => Some("R_PPC64_TPREL16_HIGHA"),
+            &RelocationType::R_PPC64_DTPREL16_HIGH => Some("R_PPC64_DTPREL16_HIGH"),
+            &RelocationType::R_PPC64_DTPREL16_HIGHA => Some("R_PPC64_DTPREL16_HIGHA"),
+            &RelocationType::R_PPC64_REL24_NOTOC => Some("R_PPC64_REL24_NOTOC"),
+            &RelocationType::R_PPC64_ADDR64_LOCAL => Some("R_PPC64_ADDR64_LOCAL"),
+            &RelocationType::R_PPC64_ENTRY => Some("R_PPC64_ENTRY"),
+            &RelocationType::R_PPC64_JMP_IREL => Some("R_PPC64_JMP_IREL"),
+            &RelocationType::R_PPC64_IRELATIVE => Some("R_PPC64_IRELATIVE"),
+            &RelocationType::R_PPC64_REL16 => Some("R_PPC64_REL16"),
+            &RelocationType::R_PPC64_REL16_LO => Some("R_PPC64_REL16_LO"),
+            &RelocationType::R_PPC64_REL16_HI => Some("R_PPC64_REL16_HI"),
+            &RelocationType::R_PPC64_REL16_HA => Some("R_PPC64_REL16_HA"),
+            _ => None,
+        }
+    }
+
+    fn size(&self, rtype: &RelocationType) -> Option<usize> {
+        match rtype {
+            &RelocationType::R_PPC64_NONE
+            | &RelocationType::R_PPC64_TLS
+            | &RelocationType::R_PPC64_TLSGD
+            | &RelocationType::R_PPC64_TLSLD
+            | &RelocationType::R_PPC64_TOCSAVE
+            | &RelocationType::R_PPC64_ENTRY => Some(0),
+            &RelocationType::R_PPC64_ADDR16
+            | &RelocationType::R_PPC64_ADDR16_LO
+            | &RelocationType::R_PPC64_ADDR16_HI
+            | &RelocationType::R_PPC64_ADDR16_HA
+            | &RelocationType::R_PPC64_GOT16
+            | &RelocationType::R_PPC64_GOT16_LO
+            | &RelocationType::R_PPC64_GOT16_HI
+            | &RelocationType::R_PPC64_GOT16_HA
+            | &RelocationType::R_PPC64_UADDR16
+            | &RelocationType::R_PPC64_PLT16_LO
+            | &RelocationType::R_PPC64_PLT16_HI
+            | &RelocationType::R_PPC64_PLT16_HA
+            | &RelocationType::R_PPC64_ADDR16_HIGHER
+            | &RelocationType::R_PPC64_ADDR16_HIGHERA
+            | &RelocationType::R_PPC64_ADDR16_HIGHEST
+            | &RelocationType::R_PPC64_ADDR16_HIGHESTA
+            | &RelocationType::R_PPC64_TOC16
+            | &RelocationType::R_PPC64_TOC16_LO
+            | &RelocationType::R_PPC64_TOC16_HI
+            | &RelocationType::R_PPC64_TOC16_HA
+            | &RelocationType::R_PPC64_PLTGOT16
+            | &RelocationType::R_PPC64_PLTGOT16_LO
+            | &RelocationType::R_PPC64_PLTGOT16_HI
+            | &RelocationType::R_PPC64_PLTGOT16_HA
+            | &RelocationType::R_PPC64_ADDR16_DS
+            | &RelocationType::R_PPC64_ADDR16_LO_DS
+            | &RelocationType::R_PPC64_GOT16_DS
+            | &RelocationType::R_PPC64_GOT16_LO_DS
+            | &RelocationType::R_PPC64_PLT16_LO_DS
+            | &RelocationType::R_PPC64_TOC16_DS
+            | &RelocationType::R_PPC64_TOC16_LO_DS
+            | &RelocationType::R_PPC64_PLTGOT16_DS
+            | &RelocationType::R_PPC64_PLTGOT16_LO_DS
+            | &RelocationType::R_PPC64_TPREL16
+            | &RelocationType::R_PPC64_TPREL16_LO
+            | &RelocationType::R_PPC64_TPREL16_HI
+            | &RelocationType::R_PPC64_TPREL16_HA
+            | &RelocationType::R_PPC64_DTPREL16
+            | &RelocationType::R_PPC64_DTPREL16_LO
+            | &RelocationType::R_PPC64_DTPREL16_HI
+            | &RelocationType::R_PPC64_DTPREL16_HA
+            | &RelocationType::R_PPC64_GOT_TLSGD16
+            | &RelocationType::R_PPC64_GOT_TLSGD16_LO
+            | &RelocationType::R_PPC64_GOT_TLSGD16_HI
+            | &RelocationType::R_PPC64_GOT_TLSGD16_HA
+            | &RelocationType::R_PPC64_GOT_TLSLD16
+            | &RelocationType::R_PPC64_GOT_TLSLD16_LO
+            | &RelocationType::R_PPC64_GOT_TLSLD16_HI
+            | &RelocationType::R_PPC64_GOT_TLSLD16_HA
+            | &RelocationType::R_PPC64_GOT_TPREL16_DS
+            | &RelocationType::R_PPC64_GOT_TPREL16_LO_DS
+            | &RelocationType::R_PPC64_GOT_TPREL16_HI
+            | &RelocationType::R_PPC64_GOT_TPREL16_HA
+            | &RelocationType::R_PPC64_GOT_DTPREL16_DS
+            | &RelocationType::R_PPC64_GOT_DTPREL16_LO_DS
+            | &RelocationType::R_PPC64_GOT_DTPREL16_HI
+            | &RelocationType::R_PPC64_GOT_DTPREL16_HA
+            | &RelocationType::R_PPC64_TPREL16_DS
+            | &RelocationType::R_PPC64_TPREL16_LO_DS
+            | &RelocationType::R_PPC64_TPREL16_HIGHER
+            | &RelocationType::R_PPC64_TPREL16_HIGHERA
+            | &RelocationType::R_PPC64_TPREL16_HIGHEST
+            | &RelocationType::R_PPC64_TPREL16_HIGHESTA
+            | &RelocationType::R_PPC64_DTPREL16_DS
+            | &RelocationType::R_PPC64_DTPREL16_LO_DS
+            | &RelocationType::R_PPC64_DTPREL16_HIGHER
+            | &RelocationType::R_PPC64_DTPREL16_HIGHERA
+            | &RelocationType::R_PPC64_DTPREL16_HIGHEST
+            | &RelocationType::R_PPC64_DTPREL16_HIGHESTA
+            | &RelocationType::R_PPC64_ADDR16_HIGH
+            | &RelocationType::R_PPC64_ADDR16_HIGHA
+            | &RelocationType::R_PPC64_TPREL16_HIGH
+            | &RelocationType::R_PPC64_TPREL16_HIGHA
+            | &RelocationType::R_PPC64_DTPREL16_HIGH
+            | &RelocationType::R_PPC64_DTPREL16_HIGHA
+            | &RelocationType::R_PPC64_REL16
+            | &RelocationType::R_PPC64_REL16_LO
+            | &RelocationType::R_PPC64_REL16_HI
+            | &RelocationType::R_PPC64_REL16_HA => Some(2),
+            &RelocationType::R_PPC64_GLOB_DAT
+            | &RelocationType::R_PPC64_JMP_SLOT
+            | &RelocationType::R_PPC64_RELATIVE
+            | &RelocationType::R_PPC64_ADDR64
+            | &RelocationType::R_PPC64_UADDR64
+            | &RelocationType::R_PPC64_REL64
+            | &RelocationType::R_PPC64_PLT64
+            | &RelocationType::R_PPC64_PLTREL64
+            | &RelocationType::R_PPC64_TOC
+            | &RelocationType::R_PPC64_DTPMOD64
+            | &RelocationType::R_PPC64_TPREL64
+            | &RelocationType::R_PPC64_DTPREL64
+            | &RelocationType::R_PPC64_ADDR64_LOCAL
+            | &RelocationType::R_PPC64_JMP_IREL
+            | &RelocationType::R_PPC64_IRELATIVE => Some(8),
+            _ => self.typename(rtype).map(|_| 4),
+        }
+    }
+
+    fn classify(&self, rtype: &RelocationType) -> RelocationKind {
+        match rtype {
+            &RelocationType::R_PPC64_NONE => RelocationKind::None,
+            &RelocationType::R_PPC64_ADDR32
+            | &RelocationType::R_PPC64_ADDR24
+            | &RelocationType::R_PPC64_ADDR16
+            | &RelocationType::R_PPC64_ADDR16_LO
+            | &RelocationType::R_PPC64_ADDR16_HI
+            | &RelocationType::R_PPC64_ADDR16_HA
+            | &RelocationType::R_PPC64_ADDR14
+            | &RelocationType::R_PPC64_ADDR14_BRTAKEN
+            | &RelocationType::R_PPC64_ADDR14_BRNTAKEN
+            | &RelocationType::R_PPC64_UADDR32
+            | &RelocationType::R_PPC64_UADDR16
+            | &RelocationType::R_PPC64_ADDR64
+            | &RelocationType::R_PPC64_ADDR16_HIGHER
+            | &RelocationType::R_PPC64_ADDR16_HIGHERA
+            | &RelocationType::R_PPC64_ADDR16_HIGHEST
+            | &RelocationType::R_PPC64_ADDR16_HIGHESTA
+            | &RelocationType::R_PPC64_UADDR64
+            | &RelocationType::R_PPC64_ADDR16_DS
+            | &RelocationType::R_PPC64_ADDR16_LO_DS
+            | &RelocationType::R_PPC64_ADDR16_HIGH
+            | &RelocationType::R_PPC64_ADDR16_HIGHA => RelocationKind::Absolute,
+            &RelocationType::R_PPC64_REL14
+            | &RelocationType::R_PPC64_REL14_BRTAKEN
+            | &RelocationType::R_PPC64_REL14_BRNTAKEN
+            | &RelocationType::R_PPC64_ADDR30
+            | &RelocationType::R_PPC64_REL32
+            | &RelocationType::R_PPC64_REL64
+            | &RelocationType::R_PPC64_REL16
+            | &RelocationType::R_PPC64_REL16_LO
+            | &RelocationType::R_PPC64_REL16_HI
+            | &RelocationType::R_PPC64_REL16_HA => RelocationKind::Relative,
+            &RelocationType::R_PPC64_REL24
+            | &RelocationType::R_PPC64_PLT32
+            | &RelocationType::R_PPC64_PLTREL32
+            | &RelocationType::R_PPC64_PLT16_LO
+            | &RelocationType::R_PPC64_PLT16_HI
+            | &RelocationType::R_PPC64_PLT16_HA
+            | &RelocationType::R_PPC64_PLT64
+            | &RelocationType::R_PPC64_PLTREL64
+            | &RelocationType::R_PPC64_REL24_NOTOC => RelocationKind::Plt,
+            &RelocationType::R_PPC64_GOT16
+            | &RelocationType::R_PPC64_GOT16_LO
+            | &RelocationType::R_PPC64_GOT16_HI
+            | &RelocationType::R_PPC64_GOT16_HA
+            | &RelocationType::R_PPC64_GOT16_DS
+            | &RelocationType::R_PPC64_GOT16_LO_DS => RelocationKind::Got,
+            &RelocationType::R_PPC64_TLS
+            | &RelocationType::R_PPC64_DTPMOD64
+            | &RelocationType::R_PPC64_TPREL16
+            | &RelocationType::R_PPC64_TPREL16_LO
+            | &RelocationType::R_PPC64_TPREL16_HI
+            | &RelocationType::R_PPC64_TPREL16_HA
+            | &RelocationType::R_PPC64_TPREL64
+            | &RelocationType::R_PPC64_DTPREL16
+            | &RelocationType::R_PPC64_DTPREL16_LO
+            | &RelocationType::R_PPC64_DTPREL16_HI
+            | &RelocationType::R_PPC64_DTPREL16_HA
+            | &RelocationType::R_PPC64_DTPREL64
+            | &RelocationType::R_PPC64_GOT_TLSGD16
+            | &RelocationType::R_PPC64_GOT_TLSGD16_LO
+            | &RelocationType::R_PPC64_GOT_TLSGD16_HI
+            | &RelocationType::R_PPC64_GOT_TLSGD16_HA
+            | &RelocationType::R_PPC64_GOT_TLSLD16
+            | &RelocationType::R_PPC64_GOT_TLSLD16_LO
+            | &RelocationType::R_PPC64_GOT_TLSLD16_HI
+            | &RelocationType::R_PPC64_GOT_TLSLD16_HA
+            | &RelocationType::R_PPC64_GOT_TPREL16_DS
+            | &RelocationType::R_PPC64_GOT_TPREL16_LO_DS
+            | &RelocationType::R_PPC64_GOT_TPREL16_HI
+            | &RelocationType::R_PPC64_GOT_TPREL16_HA
+            | &RelocationType::R_PPC64_GOT_DTPREL16_DS
+            | &RelocationType::R_PPC64_GOT_DTPREL16_LO_DS
+            | &RelocationType::R_PPC64_GOT_DTPREL16_HI
+            | &RelocationType::R_PPC64_GOT_DTPREL16_HA
+            | &RelocationType::R_PPC64_TPREL16_DS
+            | &RelocationType::R_PPC64_TPREL16_LO_DS
+            | &RelocationType::R_PPC64_TPREL16_HIGHER
+            | &RelocationType::R_PPC64_TPREL16_HIGHERA
+            | &RelocationType::R_PPC64_TPREL16_HIGHEST
+            | &RelocationType::R_PPC64_TPREL16_HIGHESTA
+            | &RelocationType::R_PPC64_DTPREL16_DS
+            | &RelocationType::R_PPC64_DTPREL16_LO_DS
+            | &RelocationType::R_PPC64_DTPREL16_HIGHER
+            | &RelocationType::R_PPC64_DTPREL16_HIGHERA
+            | &RelocationType::R_PPC64_DTPREL16_HIGHEST
+            | &RelocationType::R_PPC64_DTPREL16_HIGHESTA
+            | &RelocationType::R_PPC64_TLSGD
+            | &RelocationType::R_PPC64_TLSLD
+            | &RelocationType::R_PPC64_TPREL16_HIGH
+            | &RelocationType::R_PPC64_TPREL16_HIGHA
+            | &RelocationType::R_PPC64_DTPREL16_HIGH
+            | &RelocationType::R_PPC64_DTPREL16_HIGHA => RelocationKind::Tls,
+            &RelocationType::R_PPC64_COPY => RelocationKind::Copy,
+            &RelocationType::R_PPC64_GLOB_DAT => RelocationKind::GlobDat,
+            &RelocationType::R_PPC64_JMP_SLOT => RelocationKind::JumpSlot,
+            &RelocationType::R_PPC64_RELATIVE => RelocationKind::BaseRelative,
+            _ => RelocationKind::Other,
+        }
+    }
+
+    fn relative_type(&self) -> RelocationType {
+        RelocationType::R_PPC64_RELATIVE
+    }
+
+    fn apply(
+        &self,
+        rtype: &RelocationType,
+        v: &RelocationValues,
+        place: &mut [u8],
+        eh: &Header,
+    ) -> Result<(), Error> {
+        let r = rtype;
+        let toc = sa(v).wrapping_sub(v.got as i64);
+        match rtype {
+            &RelocationType::R_PPC64_NONE => Ok(()),
+            &RelocationType::R_PPC64_ADDR64 | &RelocationType::R_PPC64_UADDR64 => {
+                write64(place, sa(v) as u64, eh)
+            }
+            &RelocationType::R_PPC64_REL64 => write64(place, sap(v) as u64, eh),
+            &RelocationType::R_PPC64_ADDR32 | &RelocationType::R_PPC64_UADDR32 => {
+                write32(place, either(r, sa(v), 32)? as u32, eh)
+            }
+            &RelocationType::R_PPC64_REL32 => write32(place, signed(r, sap(v), 32)? as u32, eh),
+            &RelocationType::R_PPC64_ADDR16 | &RelocationType::R_PPC64_UADDR16 => {
+                write16(place, signed(r, sa(v), 16)? as u16, eh)
+            }
+            &RelocationType::R_PPC64_ADDR16_LO => write16(place, lo(sa(v)), eh),
+            &RelocationType::R_PPC64_ADDR16_HI => write16(place, (sa(v) >> 16) as u16, eh),
+            &RelocationType::R_PPC64_ADDR16_HA => write16(place, ha(sa(v)), eh),
+            &RelocationType::R_PPC64_ADDR16_HIGHER => write16(place, (sa(v) >> 32) as u16, eh),
+            &RelocationType::R_PPC64_ADDR16_HIGHERA => {
+                write16(place, (sa(v).wrapping_add(0x8000) >> 32) as u16, eh)
+            }
+            &RelocationType::R_PPC64_ADDR16_HIGHEST => write16(place, (sa(v) >> 48) as u16, eh),
+            &RelocationType::R_PPC64_ADDR16_HIGHESTA => {
+                write16(place, (sa(v).wrapping_add(0x8000) >> 48) as u16, eh)
+            }
+            &RelocationType::R_PPC64_REL16 => write16(place, signed(r, sap(v), 16)? as u16, eh),
+            &RelocationType::R_PPC64_REL16_LO => write16(place, lo(sap(v)), eh),
+            &RelocationType::R_PPC64_REL16_HI => write16(place, (sap(v) >> 16) as u16, eh),
+            &RelocationType::R_PPC64_REL16_HA => write16(place, ha(sap(v)), eh),
+            &RelocationType::R_PPC64_REL24 | &RelocationType::R_PPC64_REL24_NOTOC => {
+                let x = signed(r, aligned(r, sap(v), 4)?, 26)?;
+                merge32(place, 0x03fffffc, x as u32, eh)
+            }
+            &RelocationType::R_PPC64_TOC => write64(place, v.got, eh),
+            &RelocationType::R_PPC64_TOC16 => write16(place, signed(r, toc, 16)? as u16, eh),
+            &RelocationType::R_PPC64_TOC16_LO => write16(place, lo(toc), eh),
+            &RelocationType::R_PPC64_TOC16_HI => write16(place, (toc >> 16) as u16, eh),
+            &RelocationType::R_PPC64_TOC16_HA => write16(place, ha(toc), eh),
+            &RelocationType::R_PPC64_TOC16_DS => {
+                let x = signed(r, aligned(r, toc, 4)?, 16)?;
+                merge16(place, 0xfffc, x as u16, eh)
+            }
+            &RelocationType::R_PPC64_TOC16_LO_DS => {
+                merge16(place, 0xfffc, lo(aligned(r, toc, 4)?), eh)
+            }
+            &RelocationType::R_PPC64_GLOB_DAT | &RelocationType::R_PPC64_JMP_SLOT => {
+                write64(place, v.s, eh)
+            }
+            &RelocationType::R_PPC64_RELATIVE => write64(place, ba(v) as u64, eh),
+            _ => Err(Error::UnsupportedRelocation(rtype.to_u32())),
+        }
+    }
+}
@@ -0,0 +1,389 @@
+//! reading and writing ar archives, the static libraries ld searches with -l.
+//!
+//! archives are written in the gnu format with a symbol index, as `ar rcsD` makes them:
+//! ld only pulls in the members that define a symbol it's missing, and it finds them
+//! through the index. added members carry no timestamps or owners, so the same members
+//! always give the same archive. gnu and bsd archives can be read.
+
+use std::io::{Cursor, Write};
+use std::str;
+
+use elf::Elf;
+use error::Error;
+use symbol::Symbol;
+use types;
+
+const MAGIC: &[u8] = b"!<arch>\n";
+const HEADER_SIZE: usize = 60;
+
+/// one file in an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    /// the file name, without a directory
+    pub name: String,
+    pub data: Vec<u8>,
+    /// modification time in seconds
+    pub mtime: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u32,
+}
+
+impl Member {
+    /// a member with no timestamp or owner, readable by everyone
+    pub fn new(name: &str, data: Vec<u8>) -> Member {
+        Member {
+            name: String::from(name),
+            data,
+            mtime: 0,
+            uid: 0,
+            gid: 0,
+            mode: 0o644,
+        }
+    }
+}
+
+/// where a member is in the data of an archive, see members
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberRange {
+    pub name: String,
+    /// offset of the member header, which is what the symbol index points at
+    pub header: usize,
+    /// start and end of the content
+    pub start: usize,
+    pub end: usize,
+}
+
+/// the members of an archive, in the order ld sees them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Archive {
+    pub members: Vec<Member>,
+    /// write a "/SYM64/" index even if all offsets fit into 32 bits.
+    /// set by parse if the archive had one
+    pub sym64: bool,
+}
+
+/// the global and weak symbols an elf member defines, in symbol table order.
+/// anything that isn't elf has none, like the sources some makefiles put into archives
+fn defined_symbols(data: &[u8]) -> Result<Vec<String>, Error> {
+    if !data.starts_with(b"\x7fELF") {
+        return Ok(Vec::new());
+    }
+    let elf = Elf::from_reader(&mut Cursor::new(data))?;
+    let mut r = Vec::new();
+    for sec in &elf.sections {
+        if sec.header.shtype != types::SectionType::SYMTAB {
+            continue;
+        }
+        let strtab = elf.sections.get(sec.header.link as usize).and_then(|s| s.content.as_raw());
+        if let (Some(data), Some(strtab)) = (sec.content.as_raw(), strtab) {
+            r.extend(Symbol::defined_names(data, strtab, &elf.header)?);
+        }
+    }
+    Ok(r)
+}
+
+/// date, owner, group and mode of the index and the name table, as gnu ar writes them in
+/// deterministic mode
+const INDEX: [&str; 4] = ["0", "0", "0", "0"];
+const NAMES: [&str; 4] = ["", "", "", ""];
+
+/// a member header, with the fields padded with spaces
+fn header(name: &str, fields: [&str; 4], size: usize) -> Vec<u8> {
+    let [date, uid, gid, mode] = fields;
+    let h = format!("{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n", name, date, uid, gid, mode, size);
+    h.into_bytes()
+}
+
+fn padded(size: usize) -> usize {
+    size + (size & 1)
+}
+
+/// a space padded field of a member header
+fn field(b: &[u8]) -> &str {
+    str::from_utf8(b).map(|s| s.trim_end()).unwrap_or("")
+}
+
+/// the index as members found it, with 8 byte words for "/SYM64/"
+fn index_range(data: &[u8]) -> Option<(usize, usize, usize)> {
+    if data.len() < MAGIC.len() + HEADER_SIZE || !data.starts_with(MAGIC) {
+        return None;
+    }
+    let h = &data[MAGIC.len()..MAGIC.len() + HEADER_SIZE];
+    let word = match field(&h[0..16]) {
+        "/" => 4,
+        "/SYM64/" => 8,
+        _ => return None,
+    };
+    let size = field(&h[48..58]).parse::<usize>().ok()?;
+    let start = MAGIC.len() + HEADER_SIZE;
+    Some((word, start, start.checked_add(size)?))
+}
+
+/**
+ * list the members of a gnu or bsd archive, without the symbol index and the name table.
+ * only the headers are read, so this is cheap even for big archives
+ */
+pub fn members(data: &[u8]) -> Result<Vec<MemberRange>, Error> {
+    if !data.starts_with(MAGIC) {
+        return Err(Error::InvalidArchive("no archive magic"));
+    }
+    let mut members = Vec::new();
+    let mut names: &[u8] = &[];
+    let mut at = MAGIC.len();
+    while at + HEADER_SIZE <= data.len() {
+        let header = at;
+        let h = &data[at..at + HEADER_SIZE];
+        if &h[58..60] != b"`\n" {
+            return Err(Error::InvalidArchive("member header without terminator"));
+        }
+        let size = field(&h[48..58])
+            .parse::<usize>()
+            .map_err(|_| Error::InvalidArchive("member size is not a number"))?;
+        let mut start = at + HEADER_SIZE;
+        let end = match start.checked_add(size) {
+            Some(end) if end <= data.len() => end,
+            _ => return Err(Error::InvalidArchive("member extends beyond the end")),
+        };
+        at = end + (end & 1);
+
+        let name = field(&h[0..16]);
+        let name = if name == "/" || name == "/SYM64/" || name.starts_with("__.SYMDEF") {
+            continue;
+        } else if name == "//" {
+            names = &data[start..end];
+            continue;
+        } else if let Some(len) = name.strip_prefix("#1/") {
+            // bsd: the name is at the start of the content
+            let len = len
+                .parse::<usize>()
+                .map_err(|_| Error::InvalidArchive("bsd name length is not a number"))?;
+            if len > size {
+                return Err(Error::InvalidArchive("bsd name is longer than the member"));
+            }
+            let name = &data[start..start + len];
+            start += len;
+            let nul = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+            let name = String::from_utf8_lossy(&name[..nul]).into_owned();
+            // darwin's symbol table has a long name too, "__.SYMDEF SORTED"
+            if name.starts_with("__.SYMDEF") {
+                continue;
+            }
+            name
+        } else if let Some(off) = name.strip_prefix('/') {
+            // gnu: offset into the name table, each name ending in "/\n",
+            // or in a nul in the tables some other writers make
+            let off = off
+                .parse::<usize>()
+                .map_err(|_| Error::InvalidArchive("long name offset is not a number"))?;
+            let rest = names
+                .get(off..)
+                .ok_or(Error::InvalidArchive("long name offset out of range"))?;
+            let nl = rest.iter().position(|c| *c == b'\n' || *c == 0).unwrap_or(rest.len());
+            let name = &rest[..nl];
+            String::from_utf8_lossy(name.strip_suffix(b"/").unwrap_or(name)).into_owned()
+        } else {
+            String::from(name.trim_end_matches('/'))
+        };
+        members.push(MemberRange {
+            name,
+            header,
+            start,
+            end,
+        });
+    }
+    Ok(members)
+}
+
+/**
+ * the gnu symbol index of an archive, "/" or "/SYM64/": every symbol with the offset of the
+ * header of the member defining it, as in MemberRange::header.
+ * empty if the archive has no index, which is what ranlib is for
+ */
+pub fn read_index(data: &[u8]) -> Result<Vec<(String, u64)>, Error> {
+    let (word, start, end) = match index_range(data) {
+        Some(r) => r,
+        None => return Ok(Vec::new()),
+    };
+    let index = data
+        .get(start..end)
+        .ok_or(Error::InvalidArchive("member extends beyond the end"))?;
+    let get = |i: usize| -> Option<u64> {
+        let b = index.get(i * word..(i + 1) * word)?;
+        Some(b.iter().fold(0, |v, &b| v << 8 | b as u64))
+    };
+    let count = get(0).ok_or(Error::InvalidArchive("symbol index is truncated"))? as usize;
+    let mut strings = match count.checked_add(1).and_then(|n| n.checked_mul(word)) {
+        Some(n) if n <= index.len() => &index[n..],
+        _ => return Err(Error::InvalidArchive("symbol index is truncated")),
+    };
+    let mut r = Vec::with_capacity(count);
+    for i in 0..count {
+        let nul = strings
+            .iter()
+            .position(|c| *c == 0)
+            .ok_or(Error::InvalidArchive("symbol index is truncated"))?;
+        let offset = get(1 + i).unwrap_or(0);
+        r.push((String::from_utf8_lossy(&strings[..nul]).into_owned(), offset));
+        strings = &strings[nul + 1..];
+    }
+    Ok(r)
+}
+
+/// parse the archive and write it again with a fresh index of what its members define,
+/// as ranlib does. members keep their headers, an index in the 64 bit format stays one
+pub fn ranlib(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let ar = Archive::parse(data)?;
+    let mut io = Vec::with_capacity(data.len());
+    ar.to_writer(&mut io)?;
+    Ok(io)
+}
+
+impl Archive {
+    pub fn new() -> Archive {
+        Archive::default()
+    }
+
+    pub fn add(&mut self, name: &str, data: Vec<u8>) {
+        self.members.push(Member::new(name, data));
+    }
+
+    /// copy the members out of an archive, see members
+    pub fn parse(data: &[u8]) -> Result<Archive, Error> {
+        let mut r = Archive {
+            members: Vec::new(),
+            sym64: index_range(data).map(|(word, _, _)| word) == Some(8),
+        };
+        for m in members(data)? {
+            let h = &data[m.header..m.header + HEADER_SIZE];
+            let number = |b: &[u8], radix: u32| u64::from_str_radix(field(b), radix).unwrap_or(0);
+            r.members.push(Member {
+                name: m.name,
+                data: data[m.start..m.end].to_vec(),
+                mtime: number(&h[16..28], 10),
+                uid: number(&h[28..34], 10) as u32,
+                gid: number(&h[34..40], 10) as u32,
+                mode: number(&h[40..48], 8) as u32,
+            });
+        }
+        Ok(r)
+    }
+
+    /// what the index will contain: every symbol an elf member defines and the index of
+    /// that member. a symbol defined by several members is listed for each, like ar does
+    pub fn symbol_index(&self) -> Result<Vec<(String, usize)>, Error> {
+        let mut r = Vec::new();
+        for (i, m) in self.members.iter().enumerate() {
+            let syms = defined_symbols(&m.data)
+                .map_err(|e| Error::ArchiveMember(m.name.clone(), Box::new(e)))?;
+            r.extend(syms.into_iter().map(|s| (s, i)));
+        }
+        Ok(r)
+    }
+
+    /**
+     * write the archive: the symbol index, the table of names that don't fit into a header
+     * and the members.
+     *
+     * the index is "/" with 32 bit offsets, or "/SYM64/" when the archive is too big for
+     * them or sym64 is set. it's left out when no member defines anything
+     */
+    pub fn to_writer<W>(&self, io: &mut W) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        // names of up to 15 bytes go into the header, ending in '/', the rest into "//"
+        let mut long_names = Vec::new();
+        let mut names = Vec::with_capacity(self.members.len());
+        for m in &self.members {
+            if m.name.is_empty() || m.name.contains('/') || m.name.contains('\n') {
+                return Err(Error::InvalidArchive("member name is not a file name"));
+            }
+            if m.name.len() > 15 {
+                names.push(format!("/{}", long_names.len()));
+                long_names.extend_from_slice(m.name.as_bytes());
+                long_names.extend_from_slice(b"/\n");
+            } else {
+                names.push(format!("{}/", m.name));
+            }
+        }
+        // unlike the other members, the padding of the names counts into their size
+        if long_names.len() & 1 == 1 {
+            long_names.push(b'\n');
+        }
+
+        let index = self.symbol_index()?;
+        let strings: usize = index.iter().map(|(s, _)| s.len() + 1).sum();
+        // the names are padded with another nul, which counts into the size as well
+        let index_size = |word: usize| padded(word * (1 + index.len()) + strings);
+        let names_size = if long_names.is_empty() {
+            0
+        } else {
+            HEADER_SIZE + long_names.len()
+        };
+
+        let offsets = |word: usize| {
+            let mut at = MAGIC.len() + names_size;
+            if !index.is_empty() {
+                at += HEADER_SIZE + index_size(word);
+            }
+            let mut r = Vec::with_capacity(self.members.len());
+            for m in &self.members {
+                r.push(at as u64);
+                at += HEADER_SIZE + padded(m.data.len());
+            }
+            r
+        };
+        let mut word = if self.sym64 { 8 } else { 4 };
+        let mut at = offsets(word);
+        if word == 4 && at.last().is_some_and(|&last| last > u32::MAX as u64) {
+            word = 8;
+            at = offsets(word);
+        }
+
+        io.write_all(MAGIC)?;
+        if !index.is_empty() {
+            let size = index_size(word);
+            io.write_all(&header(if word == 8 { "/SYM64/" } else { "/" }, INDEX, size))?;
+            // big endian, whatever the members are
+            let put = |io: &mut W, v: u64| -> Result<(), Error> {
+                if word == 8 {
+                    io.write_all(&v.to_be_bytes())?;
+                } else {
+                    io.write_all(&(v as u32).to_be_bytes())?;
+                }
+                Ok(())
+            };
+            put(io, index.len() as u64)?;
+            for &(_, member) in &index {
+                put(io, at[member])?;
+            }
+            for (s, _) in &index {
+                io.write_all(s.as_bytes())?;
+                io.write_all(&[0])?;
+            }
+            if strings & 1 == 1 {
+                io.write_all(&[0])?;
+            }
+        }
+        if !long_names.is_empty() {
+            io.write_all(&header("//", NAMES, long_names.len()))?;
+            io.write_all(&long_names)?;
+        }
+        for (m, name) in self.members.iter().zip(names.iter()) {
+            let fields = [
+                m.mtime.to_string(),
+                m.uid.to_string(),
+                m.gid.to_string(),
+                format!("{:o}", m.mode),
+            ];
+            let fields = [&*fields[0], &*fields[1], &*fields[2], &*fields[3]];
+            io.write_all(&header(name, fields, m.data.len()))?;
+            io.write_all(&m.data)?;
+            if m.data.len() & 1 == 1 {
+                io.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+}
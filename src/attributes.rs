@@ -0,0 +1,600 @@
+use std::cmp;
+use std::io::{Read, Write};
+use {types, Error, Header, Section, SectionContent};
+
+/**
+ * build attributes, as found in SHT_ARM_ATTRIBUTES, SHT_RISCV_ATTRIBUTES and SHT_GNU_ATTRIBUTES
+ * sections.
+ *
+ * the section starts with a format version 'A' followed by one subsection per vendor.
+ * every vendor subsection holds a list of attribute sets that apply either to the whole file,
+ * or to a list of sections or symbols.
+ * vendors other than aeabi, riscv and gnu define their own encoding, so they are kept as raw bytes.
+ */
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Attributes {
+    pub vendors: Vec<VendorAttributes>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VendorAttributes {
+    pub vendor: String,
+    pub content: VendorContent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VendorContent {
+    Parsed(Vec<AttributeSet>),
+    Raw(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeScope {
+    File,
+    Sections(Vec<u64>),
+    Symbols(Vec<u64>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeSet {
+    pub scope: AttributeScope,
+    pub attributes: Vec<Attribute>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub tag: u64,
+    pub value: AttributeValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    Int(u64),
+    String(String),
+    /// Tag_compatibility is a flag followed by a vendor name
+    Compatibility(u64, String),
+}
+
+const FORMAT_VERSION: u8 = b'A';
+const TAG_FILE: u8 = 1;
+const TAG_SECTION: u8 = 2;
+const TAG_SYMBOL: u8 = 3;
+const TAG_COMPATIBILITY: u64 = 32;
+
+impl Attributes {
+    pub fn from_reader<R>(mut io: R, eh: &Header) -> Result<Attributes, Error>
+    where
+        R: Read,
+    {
+        let mut b = Vec::new();
+        io.read_to_end(&mut b)?;
+
+        let mut r = Attributes::default();
+        if b.is_empty() {
+            return Ok(r);
+        }
+        if b[0] != FORMAT_VERSION {
+            return Err(Error::InvalidAttributes("unknown format version"));
+        }
+
+        let mut b = &b[1..];
+        while !b.is_empty() {
+            let (vendor, rest) = subsection(b, 0, eh)?;
+            b = rest;
+
+            let end = match vendor.iter().position(|c| *c == 0) {
+                Some(i) => i,
+                None => return Err(Error::InvalidAttributes("vendor name is not terminated")),
+            };
+            let name = String::from_utf8_lossy(&vendor[..end]).into_owned();
+            let data = &vendor[end + 1..];
+
+            let content = match name.as_ref() {
+                "aeabi" | "riscv" | "gnu" => {
+                    VendorContent::Parsed(attribute_sets(data, eh, &name)?)
+                }
+                _ => VendorContent::Raw(data.to_vec()),
+            };
+            r.vendors.push(VendorAttributes {
+                vendor: name,
+                content,
+            });
+        }
+        Ok(r)
+    }
+
+    pub fn to_writer<W>(&self, mut io: W, eh: &Header) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        io.write_all(&[FORMAT_VERSION])?;
+        for v in &self.vendors {
+            let mut body = Vec::new();
+            body.extend(v.vendor.bytes());
+            body.push(0);
+            match v.content {
+                VendorContent::Raw(ref raw) => body.extend(raw),
+                VendorContent::Parsed(ref sets) => for set in sets {
+                    set.to_writer(&mut body, eh)?;
+                },
+            }
+            elf_write_u32!(eh, io, body.len() as u32 + 4)?;
+            io.write_all(&body)?;
+        }
+        Ok(())
+    }
+
+    /// the file scope attributes of a vendor
+    pub fn file_attributes(&self, vendor: &str) -> Vec<&Attribute> {
+        let mut r = Vec::new();
+        for v in &self.vendors {
+            if v.vendor != vendor {
+                continue;
+            }
+            if let VendorContent::Parsed(ref sets) = v.content {
+                for set in sets {
+                    if set.scope == AttributeScope::File {
+                        r.extend(set.attributes.iter());
+                    }
+                }
+            }
+        }
+        r
+    }
+
+    /**
+     * merge the attributes of another input object into these, the way a linker combines
+     * them for its output. for every file scope tag both sides carry, the vendor's rule decides
+     * whether the values are compatible and what the output gets, and a tag only one side
+     * carries is taken as is.
+     *
+     * sets that apply to sections or symbols are dropped, their indices only mean something
+     * in the input object. raw vendors can't be compared, so the first one seen is kept.
+     */
+    pub fn merge(&mut self, other: &Attributes) -> Result<(), Error> {
+        for v in &mut self.vendors {
+            if let VendorContent::Parsed(ref mut sets) = v.content {
+                let mut file = Vec::new();
+                for set in sets.drain(..) {
+                    if set.scope == AttributeScope::File {
+                        file.extend(set.attributes);
+                    }
+                }
+                sets.push(AttributeSet {
+                    scope: AttributeScope::File,
+                    attributes: file,
+                });
+            }
+        }
+
+        for theirs in &other.vendors {
+            let i = match self.vendors.iter().position(|v| v.vendor == theirs.vendor) {
+                Some(i) => i,
+                None => {
+                    self.vendors.push(VendorAttributes {
+                        vendor: theirs.vendor.clone(),
+                        content: match theirs.content {
+                            VendorContent::Raw(ref raw) => VendorContent::Raw(raw.clone()),
+                            VendorContent::Parsed(_) => VendorContent::Parsed(vec![AttributeSet {
+                                scope: AttributeScope::File,
+                                attributes: Vec::new(),
+                            }]),
+                        },
+                    });
+                    self.vendors.len() - 1
+                }
+            };
+            let ours = match self.vendors[i].content {
+                VendorContent::Parsed(ref mut sets) => &mut sets[0].attributes,
+                VendorContent::Raw(_) => continue,
+            };
+            let sets = match theirs.content {
+                VendorContent::Parsed(ref sets) => sets,
+                VendorContent::Raw(_) => continue,
+            };
+            for set in sets.iter().filter(|set| set.scope == AttributeScope::File) {
+                for a in &set.attributes {
+                    merge_attribute(&theirs.vendor, ours, a)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// how two values of the same tag combine
+enum MergeRule {
+    /// the values must be equal, except that the one given here is compatible with anything
+    Match(u64),
+    /// later architecture versions and extensions include the earlier ones
+    Max,
+    /// informational, the first object wins
+    First,
+    /// the union of the extensions in two riscv isa strings
+    RiscvArch,
+}
+
+fn merge_rule(vendor: &str, tag: u64, value: &AttributeValue) -> MergeRule {
+    match (vendor, tag, value) {
+        (_, _, &AttributeValue::String(_)) if vendor != "riscv" || tag != 5 => MergeRule::First,
+        (_, _, &AttributeValue::Compatibility(..)) => MergeRule::First,
+        // wchar_t and enum size are 0 when an object doesn't use them,
+        // VFP_args 3 means the code is compatible with both calling conventions
+        ("aeabi", 18, _) | ("aeabi", 26, _) => MergeRule::Match(0),
+        ("aeabi", 28, _) => MergeRule::Match(3),
+        ("aeabi", _, _) => MergeRule::Max,
+        // stack alignment and atomic abi
+        ("riscv", 4, _) | ("riscv", 14, _) => MergeRule::Match(0),
+        ("riscv", 5, _) => MergeRule::RiscvArch,
+        ("riscv", _, _) => MergeRule::Max,
+        // the gnu tags describe the floating point, vector and struct return abi
+        _ => MergeRule::Match(0),
+    }
+}
+
+fn merge_attribute(vendor: &str, ours: &mut Vec<Attribute>, a: &Attribute) -> Result<(), Error> {
+    let i = match ours.iter().position(|o| o.tag == a.tag) {
+        Some(i) => i,
+        None => {
+            ours.push(a.clone());
+            return Ok(());
+        }
+    };
+    if ours[i].value == a.value {
+        return Ok(());
+    }
+    let merged = match (merge_rule(vendor, a.tag, &a.value), &ours[i].value, &a.value) {
+        (MergeRule::Match(any), &AttributeValue::Int(x), _) if x == any => a.value.clone(),
+        (MergeRule::Match(any), _, &AttributeValue::Int(y)) if y == any => return Ok(()),
+        (MergeRule::Max, &AttributeValue::Int(x), &AttributeValue::Int(y)) => {
+            AttributeValue::Int(cmp::max(x, y))
+        }
+        (MergeRule::First, _, _) => return Ok(()),
+        (MergeRule::RiscvArch, &AttributeValue::String(ref x), &AttributeValue::String(ref y)) => {
+            match riscv_arch_union(x, y) {
+                Some(s) => AttributeValue::String(s),
+                None => return Err(Error::IncompatibleAttributes(vendor.to_string(), a.tag)),
+            }
+        }
+        _ => return Err(Error::IncompatibleAttributes(vendor.to_string(), a.tag)),
+    };
+    ours[i].value = merged;
+    Ok(())
+}
+
+/// single letter extensions in the order the isa manual wants them
+const RISCV_EXTENSION_ORDER: &str = "iemafdqlcbkjtpvh";
+
+/// split an isa string like rv64i2p1_m2p0_zicsr2p0 or rv32imac into the base and a list
+/// of extensions with their version, which may be empty
+fn riscv_extensions(arch: &str) -> Option<(&str, Vec<(String, String)>)> {
+    if !(arch.starts_with("rv32") || arch.starts_with("rv64")) {
+        return None;
+    }
+    let mut r = Vec::new();
+    for (n, part) in arch[4..].split('_').enumerate() {
+        if n > 0 {
+            let (name, version) = split_version(part);
+            if name.is_empty() {
+                return None;
+            }
+            r.push((name.to_string(), version.to_string()));
+            continue;
+        }
+        // the first part has the single letter extensions without separators
+        let mut part = part;
+        while let Some(c) = part.chars().next() {
+            if !c.is_ascii_lowercase() {
+                return None;
+            }
+            let end = part[1..]
+                .find(|c: char| !(c.is_ascii_digit() || c == 'p'))
+                .map(|i| i + 1)
+                .unwrap_or(part.len());
+            let (name, version) = split_version(&part[..end]);
+            if name.len() != 1 {
+                return None;
+            }
+            r.push((name.to_string(), version.to_string()));
+            part = &part[end..];
+        }
+    }
+    Some((&arch[..4], r))
+}
+
+/// split the version, digits optionally followed by p and more digits, off an extension
+fn split_version(ext: &str) -> (&str, &str) {
+    let b = ext.as_bytes();
+    let digits = |mut i: usize| {
+        while i > 0 && b[i - 1].is_ascii_digit() {
+            i -= 1;
+        }
+        i
+    };
+    let mut i = digits(b.len());
+    if i < b.len() && i > 1 && b[i - 1] == b'p' && b[i - 2].is_ascii_digit() {
+        i = digits(i - 1);
+    }
+    (&ext[..i], &ext[i..])
+}
+
+fn riscv_version(v: &str) -> (u64, u64) {
+    let mut v = v.splitn(2, 'p').map(|s| s.parse().unwrap_or(0));
+    (v.next().unwrap_or(0), v.next().unwrap_or(0))
+}
+
+/// the isa both objects together need. None if the base isa differs
+fn riscv_arch_union(a: &str, b: &str) -> Option<String> {
+    let (base, mut ours) = riscv_extensions(a)?;
+    let (other, theirs) = riscv_extensions(b)?;
+    if base != other {
+        return None;
+    }
+    for (name, version) in theirs {
+        match ours.iter().position(|e| e.0 == name) {
+            Some(i) => if riscv_version(&version) > riscv_version(&ours[i].1) {
+                ours[i].1 = version;
+            },
+            None => ours.push((name, version)),
+        }
+    }
+    ours.sort_by_key(|e| {
+        let single = RISCV_EXTENSION_ORDER.find(&e.0[..]);
+        let class = match e.0.chars().next() {
+            _ if single.is_some() => 0,
+            Some('z') => 1,
+            Some('s') => 2,
+            _ => 3,
+        };
+        (class, single, e.0.clone())
+    });
+    let mut r = String::from(base);
+    for (i, e) in ours.iter().enumerate() {
+        if i > 0 {
+            r.push('_');
+        }
+        r.push_str(&e.0);
+        r.push_str(&e.1);
+    }
+    Some(r)
+}
+
+impl AttributeSet {
+    fn to_writer(&self, io: &mut Vec<u8>, eh: &Header) -> Result<(), Error> {
+        let mut body = Vec::new();
+        let tag = match self.scope {
+            AttributeScope::File => TAG_FILE,
+            AttributeScope::Sections(ref v) | AttributeScope::Symbols(ref v) => {
+                for i in v {
+                    write_uleb(&mut body, *i);
+                }
+                write_uleb(&mut body, 0);
+                match self.scope {
+                    AttributeScope::Sections(_) => TAG_SECTION,
+                    _ => TAG_SYMBOL,
+                }
+            }
+        };
+        for a in &self.attributes {
+            write_uleb(&mut body, a.tag);
+            match a.value {
+                AttributeValue::Int(v) => write_uleb(&mut body, v),
+                AttributeValue::String(ref s) => write_ntbs(&mut body, s),
+                AttributeValue::Compatibility(v, ref s) => {
+                    write_uleb(&mut body, v);
+                    write_ntbs(&mut body, s);
+                }
+            }
+        }
+        io.push(tag);
+        elf_write_u32!(eh, io, body.len() as u32 + 5)?;
+        io.extend(body);
+        Ok(())
+    }
+}
+
+/// split off a subsection with a u32 length after `skip` bytes of tag.
+/// the length covers the tag and itself, so returns the body and the rest
+fn subsection<'a>(b: &'a [u8], skip: usize, eh: &Header) -> Result<(&'a [u8], &'a [u8]), Error> {
+    if b.len() < skip + 4 {
+        return Err(Error::InvalidAttributes("subsection is truncated"));
+    }
+    let mut io = &b[skip..];
+    let len = elf_read_u32!(eh, io)? as usize;
+    if len < skip + 4 || len > b.len() {
+        return Err(Error::InvalidAttributes("subsection length out of range"));
+    }
+    Ok((&b[skip + 4..len], &b[len..]))
+}
+
+fn attribute_sets(mut b: &[u8], eh: &Header, vendor: &str) -> Result<Vec<AttributeSet>, Error> {
+    let mut r = Vec::new();
+    while !b.is_empty() {
+        let tag = b[0];
+        let (mut set, rest) = subsection(b, 1, eh)?;
+        b = rest;
+
+        let scope = match tag {
+            TAG_FILE => AttributeScope::File,
+            TAG_SECTION | TAG_SYMBOL => {
+                let mut indices = Vec::new();
+                loop {
+                    let i = read_uleb(&mut set)?;
+                    if i == 0 {
+                        break;
+                    }
+                    indices.push(i);
+                }
+                if tag == TAG_SECTION {
+                    AttributeScope::Sections(indices)
+                } else {
+                    AttributeScope::Symbols(indices)
+                }
+            }
+            _ => return Err(Error::InvalidAttributes("unknown attribute scope")),
+        };
+
+        let mut attributes = Vec::new();
+        while !set.is_empty() {
+            let tag = read_uleb(&mut set)?;
+            let value = if tag == TAG_COMPATIBILITY {
+                let flag = read_uleb(&mut set)?;
+                AttributeValue::Compatibility(flag, read_ntbs(&mut set)?)
+            } else if is_string(vendor, tag) {
+                AttributeValue::String(read_ntbs(&mut set)?)
+            } else {
+                AttributeValue::Int(read_uleb(&mut set)?)
+            };
+            attributes.push(Attribute {
+                tag,
+                value,
+            });
+        }
+        r.push(AttributeSet {
+            scope,
+            attributes,
+        });
+    }
+    Ok(r)
+}
+
+/// tags from 32 up follow the rule that odd tags are strings, lower ones are defined one by one
+fn is_string(vendor: &str, tag: u64) -> bool {
+    if tag >= 32 {
+        return tag % 2 == 1;
+    }
+    match vendor {
+        "aeabi" => tag == 4 || tag == 5,
+        "riscv" => tag == 5,
+        _ => false,
+    }
+}
+
+fn read_uleb(b: &mut &[u8]) -> Result<u64, Error> {
+    let mut r = 0;
+    let mut shift = 0;
+    loop {
+        let c = match b.first() {
+            Some(c) => *c,
+            None => return Err(Error::InvalidAttributes("attribute value is truncated")),
+        };
+        *b = &b[1..];
+        if shift < 64 {
+            r |= ((c & 0x7f) as u64) << shift;
+        }
+        shift += 7;
+        if c & 0x80 == 0 {
+            return Ok(r);
+        }
+    }
+}
+
+fn read_ntbs(b: &mut &[u8]) -> Result<String, Error> {
+    match b.iter().position(|c| *c == 0) {
+        Some(i) => {
+            let s = String::from_utf8_lossy(&b[..i]).into_owned();
+            *b = &b[i + 1..];
+            Ok(s)
+        }
+        None => Err(Error::InvalidAttributes("attribute string is not terminated")),
+    }
+}
+
+fn write_uleb(io: &mut Vec<u8>, mut v: u64) {
+    loop {
+        let c = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            io.push(c);
+            return;
+        }
+        io.push(c | 0x80);
+    }
+}
+
+fn write_ntbs(io: &mut Vec<u8>, s: &str) {
+    io.extend(s.bytes());
+    io.push(0);
+}
+
+/// name of an aeabi attribute tag, as in the ARM ABI addenda but without the Tag_ prefix
+pub fn arm_tag_name(tag: u64) -> Option<&'static str> {
+    Some(match tag {
+        4 => "CPU_raw_name",
+        5 => "CPU_name",
+        6 => "CPU_arch",
+        7 => "CPU_arch_profile",
+        8 => "ARM_ISA_use",
+        9 => "THUMB_ISA_use",
+        10 => "FP_arch",
+        11 => "WMMX_arch",
+        12 => "Advanced_SIMD_arch",
+        13 => "PCS_config",
+        14 => "ABI_PCS_R9_use",
+        15 => "ABI_PCS_RW_data",
+        16 => "ABI_PCS_RO_data",
+        17 => "ABI_PCS_GOT_use",
+        18 => "ABI_PCS_wchar_t",
+        19 => "ABI_FP_rounding",
+        20 => "ABI_FP_denormal",
+        21 => "ABI_FP_exceptions",
+        22 => "ABI_FP_user_exceptions",
+        23 => "ABI_FP_number_model",
+        24 => "ABI_align_needed",
+        25 => "ABI_align_preserved",
+        26 => "ABI_enum_size",
+        27 => "ABI_HardFP_use",
+        28 => "ABI_VFP_args",
+        29 => "ABI_WMMX_args",
+        30 => "ABI_optimization_goals",
+        31 => "ABI_FP_optimization_goals",
+        32 => "compatibility",
+        34 => "CPU_unaligned_access",
+        36 => "FP_HP_extension",
+        38 => "ABI_FP_16bit_format",
+        42 => "MPextension_use",
+        44 => "DIV_use",
+        64 => "nodefaults",
+        65 => "also_compatible_with",
+        66 => "T2EE_use",
+        67 => "conformance",
+        68 => "Virtualization_use",
+        _ => return None,
+    })
+}
+
+/// the type of the section holding build attributes on this machine
+pub fn section_type(machine: &types::Machine) -> types::SectionType {
+    match machine {
+        &types::Machine::ARM => types::SectionType::ARM_ATTRIBUTES,
+        &types::Machine::RISCV => types::SectionType::RISCV_ATTRIBUTES,
+        _ => types::SectionType::GNU_ATTRIBUTES,
+    }
+}
+
+/// the name of the section holding build attributes on this machine
+pub fn section_name(machine: &types::Machine) -> &'static str {
+    match machine {
+        &types::Machine::ARM => ".ARM.attributes",
+        &types::Machine::RISCV => ".riscv.attributes",
+        _ => ".gnu.attributes",
+    }
+}
+
+/// parse the attributes section matching the machine of this file, if there is one
+pub fn machine_attributes(
+    sections: &[Section],
+    eh: &Header,
+) -> Result<Option<Attributes>, Error> {
+    let shtype = section_type(&eh.machine);
+    for sec in sections {
+        if sec.header.shtype != shtype {
+            continue;
+        }
+        return match sec.content {
+            SectionContent::Raw(ref raw) => Ok(Some(Attributes::from_reader(&raw[..], eh)?)),
+            _ => Err(Error::UnexpectedSectionContent),
+        };
+    }
+    Ok(None)
+}
@@ -0,0 +1,442 @@
+use {types, Elf, Error, Header, LinkRef, Section, SectionContent, SegmentHeader, Strtab,
+     Symbol, SymbolSectionIndex, SymbolicRelocation};
+use linker;
+
+/**
+ * declarative construction of small elf files.
+ * the builder takes care of the null section, the string tables, symtab ordering,
+ * layout and program headers, so a tiny executable is just a few calls:
+ *
+ * ```
+ * use elfkit::{types, ElfBuilder, Symbol};
+ *
+ * let mut start = Symbol::default();
+ * start.name  = String::from("_start");
+ * start.stype = types::SymbolType::FUNC;
+ * start.bind  = types::SymbolBind::GLOBAL;
+ *
+ * let elf = ElfBuilder::new(types::Machine::X86_64)
+ *     .text(vec![0xbf, 0x2a, 0, 0, 0, 0xb8, 0x3c, 0, 0, 0, 0x0f, 0x05])
+ *     .symbol(".text", start)
+ *     .entry("_start")
+ *     .build()
+ *     .unwrap();
+ * assert_eq!(elf.header.entry, elf.sections[1].header.addr);
+ * ```
+ *
+ * the output is position independent (ET_DYN loaded at 0), because that's what relayout produces.
+ *
+ * with etype(ET_REL) the output is a relocatable object for a linker instead, as an assembler
+ * or compiler back end writes it. sections aren't given addresses, symbol values stay relative
+ * to their section, and the relocations of each section go into a .rela section behind it:
+ *
+ * ```
+ * use elfkit::{types, ElfBuilder, Symbol, SymbolicRelocation, Target};
+ * use elfkit::relocation::RelocationType;
+ *
+ * let mut main = Symbol::default();
+ * main.name  = String::from("main");
+ * main.stype = types::SymbolType::FUNC;
+ * main.bind  = types::SymbolBind::GLOBAL;
+ *
+ * // jmp puts
+ * let puts = Target::Symbol(String::from("puts"));
+ * let jmp = SymbolicRelocation::new(1, RelocationType::R_X86_64_PLT32, puts).with_addend(-4);
+ * let elf = ElfBuilder::new(types::Machine::X86_64)
+ *     .etype(types::ElfType::REL)
+ *     .text(vec![0xe9, 0, 0, 0, 0])
+ *     .symbol(".text", main)
+ *     .relocation(".text", jmp)
+ *     .build()
+ *     .unwrap();
+ * assert_eq!(elf.sections[2].name, ".rela.text");
+ * ```
+ *
+ * the interpreter, the abi tag and the entry point only apply to linked files, a relocatable
+ * object has none of them. RELA is what x86_64 and ppc64 objects use, other machines may want
+ * REL, which the builder doesn't write.
+ */
+pub struct ElfBuilder {
+    header: Header,
+    sections: Vec<Section>,
+    symbols: Vec<(String, Symbol)>,
+    relocations: Vec<(String, SymbolicRelocation)>,
+    entry: Entry,
+    interpreter: Option<String>,
+    abi_tag: Option<linker::AbiTag>,
+}
+
+enum Entry {
+    None,
+    Symbol(String),
+    Address(u64),
+}
+
+impl ElfBuilder {
+    pub fn new(machine: types::Machine) -> ElfBuilder {
+        let header = Header {
+            etype: types::ElfType::DYN,
+            machine,
+            ..Header::default()
+        };
+        ElfBuilder {
+            header,
+            sections: Vec::new(),
+            symbols: Vec::new(),
+            relocations: Vec::new(),
+            entry: Entry::None,
+            interpreter: None,
+            abi_tag: None,
+        }
+    }
+
+    pub fn class(mut self, class: types::Class) -> Self {
+        self.header.ident_class = class;
+        self
+    }
+
+    pub fn endianness(mut self, endianness: types::Endianness) -> Self {
+        self.header.ident_endianness = endianness;
+        self
+    }
+
+    pub fn etype(mut self, etype: types::ElfType) -> Self {
+        self.header.etype = etype;
+        self
+    }
+
+    /// EI_OSABI, SYSV unless set here or by abi_tag
+    pub fn os_abi(mut self, abi: types::Abi) -> Self {
+        self.header.ident_abi = abi;
+        self
+    }
+
+    /**
+     * brand the file for an os with the note its kernel looks for, and set EI_OSABI to what
+     * that os uses. combine with interpreter(tag.interpreter(..)) for a dynamically linked file
+     */
+    pub fn abi_tag(mut self, tag: linker::AbiTag) -> Self {
+        self.header.ident_abi = tag.abi();
+        self.abi_tag = Some(tag);
+        self
+    }
+
+    /// add an executable .text section
+    pub fn text(self, code: Vec<u8>) -> Self {
+        self.section(
+            ".text",
+            types::SectionType::PROGBITS,
+            types::SectionFlags::ALLOC | types::SectionFlags::EXECINSTR,
+            code,
+        )
+    }
+
+    /// add an arbitrary section with raw content.
+    /// sections are laid out in the order they are added
+    pub fn section(
+        mut self,
+        name: &str,
+        shtype: types::SectionType,
+        flags: types::SectionFlags,
+        content: Vec<u8>,
+    ) -> Self {
+        let mut sec = Section::new(
+            String::from(name),
+            shtype,
+            flags,
+            SectionContent::Raw(Vec::new()),
+            0,
+            0,
+        );
+        if sec.header.shtype == types::SectionType::NOBITS {
+            sec.header.size = content.len() as u64;
+            sec.content = SectionContent::None;
+        } else {
+            sec.content = SectionContent::Raw(content);
+        }
+        self.sections.push(sec);
+        self
+    }
+
+    /// add a symbol defined in the named section.
+    /// sym.value is the offset into that section and is relocated to the final address on build
+    pub fn symbol(mut self, section: &str, sym: Symbol) -> Self {
+        self.symbols.push((String::from(section), sym));
+        self
+    }
+
+    /// add a relocation to the named section, with r.addr the offset into that section.
+    /// only a relocatable object can have relocations, build fails for other types
+    pub fn relocation(mut self, section: &str, r: SymbolicRelocation) -> Self {
+        self.relocations.push((String::from(section), r));
+        self
+    }
+
+    /// load the file through the dynamic linker at path, it gets a .interp section and a
+    /// PT_INTERP in front of all other sections
+    pub fn interpreter(mut self, path: &str) -> Self {
+        self.interpreter = Some(String::from(path));
+        self
+    }
+
+    /// use the address of a symbol added with symbol() as entry point
+    pub fn entry(mut self, name: &str) -> Self {
+        self.entry = Entry::Symbol(String::from(name));
+        self
+    }
+
+    pub fn entry_address(mut self, addr: u64) -> Self {
+        self.entry = Entry::Address(addr);
+        self
+    }
+
+    pub fn build(self) -> Result<Elf, Error> {
+        if self.header.etype == types::ElfType::REL {
+            return self.build_relocatable();
+        }
+        if let Some((name, _)) = self.relocations.first() {
+            return Err(Error::RelocationsInLinkedFile(name.clone()));
+        }
+        let mut elf = Elf::default();
+        elf.header = self.header;
+
+        // alloc sections first, so they end up in a contiguous range of LOAD segments
+        let (alloc, noalloc): (Vec<Section>, Vec<Section>) = self.sections
+            .into_iter()
+            .partition(|s| s.header.flags.contains(types::SectionFlags::ALLOC));
+
+        elf.sections.push(Section::default());
+        if let Some(ref path) = self.interpreter {
+            elf.sections.push(linker::interp_section(path));
+        }
+        if let Some(ref tag) = self.abi_tag {
+            elf.sections.push(tag.section(&elf.header)?);
+        }
+        elf.sections.extend(alloc);
+        elf.sections.extend(noalloc);
+
+        let symbols = placed_symbols(&elf, self.symbols)?;
+        let sh_index_symtab = if symbols.len() > 1 {
+            Some(elf.sections.len())
+        } else {
+            None
+        };
+        if sh_index_symtab.is_some() {
+            push_symtab(&mut elf, symbols);
+        }
+        elf.sections.push(strtab(".shstrtab"));
+
+        // the program headers live between the elf header and the first section,
+        // but how many there are depends on the layout. iterate until it's stable
+        elf.sync_all()?;
+        let mut phnum = 0;
+        loop {
+            let pstart = elf.header.size() + phnum * SegmentHeader::entsize(&elf.header);
+            linker::relayout(&mut elf, pstart as u64)?;
+            elf.segments = linker::segments(&elf)?;
+            if elf.segments.len() == phnum {
+                break;
+            }
+            phnum = elf.segments.len();
+        }
+        linker::check_program_headers(&elf)?;
+
+        // symbol values were given relative to their section
+        if let Some(i) = sh_index_symtab {
+            let addrs: Vec<u64> = elf.sections.iter().map(|s| s.header.addr).collect();
+            for sym in elf.sections[i].content.as_symbols_mut().unwrap() {
+                if let SymbolSectionIndex::Section(i) = sym.shndx {
+                    sym.value += addrs[i as usize];
+                }
+            }
+        }
+
+        elf.header.entry = match self.entry {
+            Entry::None => 0,
+            Entry::Address(addr) => addr,
+            Entry::Symbol(ref name) => {
+                let sym = sh_index_symtab.and_then(|i| {
+                    elf.sections[i]
+                        .content
+                        .as_symbols()
+                        .unwrap()
+                        .iter()
+                        .find(|s| &s.name == name)
+                });
+                match sym {
+                    Some(sym) => sym.value,
+                    None => return Err(Error::EntrySymbolNotFound(name.clone())),
+                }
+            }
+        };
+
+        elf.store_all()?;
+        Ok(elf)
+    }
+
+    /// see ElfBuilder, for etype(ET_REL)
+    fn build_relocatable(self) -> Result<Elf, Error> {
+        for (name, _) in &self.relocations {
+            if !self.sections.iter().any(|s| &s.name == name) {
+                return Err(Error::SectionNotFound(name.clone()));
+            }
+        }
+        let mut elf = Elf::default();
+        elf.header = self.header;
+
+        elf.sections.push(Section::default());
+        for sec in self.sections {
+            let name = sec.name.clone();
+            elf.sections.push(sec);
+            if !self.relocations.iter().any(|(n, _)| n == &name) {
+                continue;
+            }
+            let mut rela = Section::new(
+                format!(".rela{}", name),
+                types::SectionType::RELA,
+                types::SectionFlags::empty(),
+                SectionContent::Relocations(Vec::new()),
+                0,
+                0,
+            );
+            rela.header.addralign = 8;
+            rela.link_ref = Some(LinkRef::Name(String::from(".symtab")));
+            rela.info_ref = Some(LinkRef::Name(name));
+            elf.sections.push(rela);
+        }
+        // without it linkers assume the code needs an executable stack
+        let mut stack = Section::new(
+            String::from(".note.GNU-stack"),
+            types::SectionType::PROGBITS,
+            types::SectionFlags::empty(),
+            SectionContent::Raw(Vec::new()),
+            0,
+            0,
+        );
+        stack.header.addralign = 1;
+        elf.sections.push(stack);
+
+        let symbols = placed_symbols(&elf, self.symbols)?;
+        push_symtab(&mut elf, symbols);
+        elf.sections.push(strtab(".shstrtab"));
+
+        for (name, r) in self.relocations {
+            let rela = format!(".rela{}", name);
+            let i = elf.sections.iter().position(|s| s.name == rela).unwrap_or(0);
+            elf.add_relocation(i, r)?;
+        }
+        elf.sync_all()?;
+        pack(&mut elf);
+        elf.store_all()?;
+        Ok(elf)
+    }
+}
+
+/// the symbols with the index of the section they were added for, behind the null symbol.
+/// symtab wants all locals before the first global
+fn placed_symbols(elf: &Elf, symbols: Vec<(String, Symbol)>) -> Result<Vec<Symbol>, Error> {
+    let mut r = Vec::new();
+    for (secname, mut sym) in symbols {
+        let shndx = match elf.sections.iter().position(|s| s.name == secname) {
+            Some(i) => i,
+            None => return Err(Error::SectionNotFound(secname)),
+        };
+        sym.shndx = SymbolSectionIndex::Section(shndx as u32);
+        r.push(sym);
+    }
+    r.sort_by_key(|s| s.bind != types::SymbolBind::LOCAL);
+    r.insert(0, Symbol::default());
+    Ok(r)
+}
+
+/// a .symtab with symbols, followed by the .strtab it links to
+fn push_symtab(elf: &mut Elf, symbols: Vec<Symbol>) {
+    let mut symtab = Section::new(
+        String::from(".symtab"),
+        types::SectionType::SYMTAB,
+        types::SectionFlags::empty(),
+        SectionContent::Symbols(symbols),
+        0,
+        0,
+    );
+    symtab.link_ref = Some(LinkRef::Name(String::from(".strtab")));
+    elf.sections.push(symtab);
+    elf.sections.push(strtab(".strtab"));
+}
+
+fn strtab(name: &str) -> Section {
+    Section::new(
+        String::from(name),
+        types::SectionType::STRTAB,
+        types::SectionFlags::empty(),
+        SectionContent::Strtab(Strtab::default()),
+        0,
+        0,
+    )
+}
+
+/// nothing is loaded from a relocatable object, so its sections just follow each other in
+/// the file
+fn pack(elf: &mut Elf) {
+    let mut offset = elf.header.size() as u64;
+    for sec in &mut elf.sections[1..] {
+        let align = sec.header.addralign.max(1);
+        offset = offset.div_ceil(align) * align;
+        sec.header.offset = offset;
+        offset += sec.header.size;
+    }
+}
+
+/// the name objcopy -I binary gives the symbols of a file, with every character that can't be
+/// in a c identifier replaced by _, so "assets/logo.png" is _binary_assets_logo_png
+pub fn binary_symbol_prefix(path: &str) -> String {
+    let name: String = path
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("_binary_{}", name)
+}
+
+/**
+ * a relocatable object with the bytes of a file in a writable .data section, like
+ * `objcopy -I binary` makes it, so the file can be linked in like any other object.
+ * it has global _start and _end symbols at either end of the data, and an absolute _size
+ * symbol with its length, each behind binary_symbol_prefix(path)
+ */
+pub fn binary_object(machine: types::Machine, path: &str, data: Vec<u8>) -> Result<Elf, Error> {
+    let mut elf = Elf::default();
+    elf.header = ElfBuilder::new(machine).etype(types::ElfType::REL).header;
+    let size = data.len() as u64;
+    let mut sec = Section::new(
+        String::from(".data"),
+        types::SectionType::PROGBITS,
+        types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+        SectionContent::Raw(data),
+        0,
+        0,
+    );
+    sec.header.addralign = 1;
+
+    let prefix = binary_symbol_prefix(path);
+    let symbol = |suffix: &str, shndx, value| Symbol {
+        name: format!("{}_{}", prefix, suffix),
+        shndx,
+        value,
+        bind: types::SymbolBind::GLOBAL,
+        ..Symbol::default()
+    };
+    let symbols = vec![
+        Symbol::default(),
+        symbol("start", SymbolSectionIndex::Section(1), 0),
+        symbol("end", SymbolSectionIndex::Section(1), size),
+        symbol("size", SymbolSectionIndex::Absolute, size),
+    ];
+    elf.sections.push(Section::default());
+    elf.sections.push(sec);
+    push_symtab(&mut elf, symbols);
+    elf.sections.push(strtab(".shstrtab"));
+    elf.sync_all()?;
+    pack(&mut elf);
+    elf.store_all()?;
+    Ok(elf)
+}
@@ -0,0 +1,314 @@
+/*!
+ * the parser for C and everything else that can call C, declared in include/elfkit.h.
+ * build the shared library with
+ *
+ * ```text
+ * cargo rustc --release --lib --features capi --crate-type cdylib
+ * ```
+ *
+ * a handle owns everything it hands out, names stay valid until elfkit_free.
+ * failing functions return NULL or -1, and elfkit_last_error says why. that includes panics
+ * while parsing, which don't unwind into the caller.
+ * input is parsed with the limits of ElfOptions::untrusted, since the caller may be handing over
+ * anything.
+ */
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io::Cursor;
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use elf::{Elf, ElfOptions};
+use error::Error;
+use num_traits::ToPrimitive;
+use symbol::SymbolSectionIndex;
+
+/// the parsed file behind an elfkit_elf pointer
+pub struct ElfkitElf {
+    elf: Elf,
+    section_names: Vec<CString>,
+    symbols: Vec<(CString, ElfkitSymbol)>,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ElfkitHeader {
+    pub class: u8,
+    pub endianness: u8,
+    pub etype: u16,
+    pub machine: u16,
+    pub entry: u64,
+    pub phnum: u64,
+    pub shnum: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ElfkitSection {
+    pub name: *const c_char,
+    pub shtype: u32,
+    pub flags: u64,
+    pub addr: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+    pub info: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ElfkitSymbol {
+    pub name: *const c_char,
+    pub value: u64,
+    pub size: u64,
+    pub stype: u8,
+    pub bind: u8,
+    pub other: u8,
+    /// the section index, with SHN_ABS and SHN_COMMON as in the file
+    pub shndx: u32,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(e: Error) {
+    let msg = CString::new(e.to_string()).unwrap_or_default();
+    LAST_ERROR.with(|l| *l.borrow_mut() = Some(msg));
+}
+
+fn c_string(s: &str) -> CString {
+    // names can't contain the nul they end at in the file, but constructed ones could
+    CString::new(s.replace('\0', "")).unwrap_or_default()
+}
+
+fn shndx(i: &SymbolSectionIndex) -> u32 {
+    match *i {
+        SymbolSectionIndex::Section(i) => i,
+        SymbolSectionIndex::Undefined => 0,
+        SymbolSectionIndex::Absolute => 0xfff1,
+        SymbolSectionIndex::Common => 0xfff2,
+        SymbolSectionIndex::Reserved(i) => i as u32,
+        SymbolSectionIndex::Global(_) => u32::MAX,
+    }
+}
+
+/// parse and load, and make the handle. unwinding across the C boundary is undefined,
+/// so a panic is returned as Error::Panicked
+fn handle<F: FnOnce() -> Result<Elf, Error>>(parse: F) -> *mut ElfkitElf {
+    let loaded = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut elf = parse()?;
+        elf.load_all()?;
+        Ok(elf)
+    }));
+    let elf = match loaded {
+        Ok(Ok(elf)) => elf,
+        Ok(Err(e)) => {
+            set_error(e);
+            return ptr::null_mut();
+        }
+        Err(payload) => {
+            let msg = match payload.downcast_ref::<&str>() {
+                Some(msg) => msg.to_string(),
+                None => payload.downcast_ref::<String>().cloned().unwrap_or_default(),
+            };
+            set_error(Error::Panicked(msg));
+            return ptr::null_mut();
+        }
+    };
+    let section_names = elf.sections.iter().map(|s| c_string(&s.name)).collect();
+    let mut symbols = Vec::new();
+    for sec in &elf.sections {
+        for sym in sec.content.as_symbols().into_iter().flatten() {
+            let name = c_string(&sym.name);
+            let s = ElfkitSymbol {
+                name: name.as_ptr(),
+                value: sym.value,
+                size: sym.size,
+                stype: sym.stype.to_u8().unwrap_or(0),
+                bind: sym.bind.to_u8().unwrap_or(0),
+                other: sym.other,
+                shndx: shndx(&sym.shndx),
+            };
+            // moving a CString doesn't move its buffer, so the pointer stays valid
+            symbols.push((name, s));
+        }
+    }
+    Box::into_raw(Box::new(ElfkitElf {
+        elf,
+        section_names,
+        symbols,
+    }))
+}
+
+/// why the last call on this thread failed, or NULL. valid until the next failing call
+#[no_mangle]
+pub extern "C" fn elfkit_last_error() -> *const c_char {
+    LAST_ERROR.with(|l| match *l.borrow() {
+        Some(ref msg) => msg.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+/// # Safety
+/// path must be a nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn elfkit_open(path: *const c_char) -> *mut ElfkitElf {
+    if path.is_null() {
+        set_error(Error::Io(std::io::Error::from(std::io::ErrorKind::InvalidInput)));
+        return ptr::null_mut();
+    }
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => path,
+        Err(_) => {
+            set_error(Error::Io(std::io::Error::from(std::io::ErrorKind::InvalidInput)));
+            return ptr::null_mut();
+        }
+    };
+    match File::open(path) {
+        Ok(mut f) => handle(|| Elf::from_reader_with_options(&mut f, ElfOptions::untrusted())),
+        Err(e) => {
+            set_error(Error::Io(e));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+/// data must point to len readable bytes, which are copied
+#[no_mangle]
+pub unsafe extern "C" fn elfkit_parse(data: *const u8, len: usize) -> *mut ElfkitElf {
+    if data.is_null() {
+        set_error(Error::Io(std::io::Error::from(std::io::ErrorKind::InvalidInput)));
+        return ptr::null_mut();
+    }
+    let data = slice::from_raw_parts(data, len).to_vec();
+    handle(|| Elf::from_reader_with_options(&mut Cursor::new(data), ElfOptions::untrusted()))
+}
+
+/// # Safety
+/// elf must come from elfkit_open or elfkit_parse and not be used afterwards. NULL is ignored
+#[no_mangle]
+pub unsafe extern "C" fn elfkit_free(elf: *mut ElfkitElf) {
+    if !elf.is_null() {
+        drop(Box::from_raw(elf));
+    }
+}
+
+/// # Safety
+/// elf must be a live handle and out writable
+#[no_mangle]
+pub unsafe extern "C" fn elfkit_get_header(elf: *const ElfkitElf, out: *mut ElfkitHeader) -> c_int {
+    let (elf, out) = match (elf.as_ref(), out.as_mut()) {
+        (Some(elf), Some(out)) => (&elf.elf, out),
+        _ => return -1,
+    };
+    let eh = &elf.header;
+    *out = ElfkitHeader {
+        class: eh.ident_class.to_u8().unwrap_or(0),
+        endianness: eh.ident_endianness.to_u8().unwrap_or(0),
+        etype: eh.etype.to_u16().unwrap_or(0),
+        machine: eh.machine.to_u16().unwrap_or(0),
+        entry: eh.entry,
+        phnum: elf.segments.len() as u64,
+        shnum: elf.sections.len() as u64,
+    };
+    0
+}
+
+/// # Safety
+/// elf must be a live handle
+#[no_mangle]
+pub unsafe extern "C" fn elfkit_section_count(elf: *const ElfkitElf) -> usize {
+    elf.as_ref().map_or(0, |elf| elf.elf.sections.len())
+}
+
+/// # Safety
+/// elf must be a live handle and out writable
+#[no_mangle]
+pub unsafe extern "C" fn elfkit_get_section(
+    elf: *const ElfkitElf,
+    i: usize,
+    out: *mut ElfkitSection,
+) -> c_int {
+    let (elf, out) = match (elf.as_ref(), out.as_mut()) {
+        (Some(elf), Some(out)) => (elf, out),
+        _ => return -1,
+    };
+    let sec = match elf.elf.sections.get(i) {
+        Some(sec) => sec,
+        None => {
+            set_error(Error::SectionIndexOutOfRange(i));
+            return -1;
+        }
+    };
+    let h = &sec.header;
+    *out = ElfkitSection {
+        name: elf.section_names[i].as_ptr(),
+        shtype: h.shtype.to_u32(),
+        flags: h.flags.bits(),
+        addr: h.addr,
+        offset: h.offset,
+        size: h.size,
+        link: h.link,
+        info: h.info,
+    };
+    0
+}
+
+/// # Safety
+/// elf must be a live handle
+#[no_mangle]
+pub unsafe extern "C" fn elfkit_symbol_count(elf: *const ElfkitElf) -> usize {
+    elf.as_ref().map_or(0, |elf| elf.symbols.len())
+}
+
+/// all symbols of .symtab and .dynsym, in the order of their sections
+///
+/// # Safety
+/// elf must be a live handle and out writable
+#[no_mangle]
+pub unsafe extern "C" fn elfkit_get_symbol(
+    elf: *const ElfkitElf,
+    i: usize,
+    out: *mut ElfkitSymbol,
+) -> c_int {
+    match (elf.as_ref().and_then(|elf| elf.symbols.get(i)), out.as_mut()) {
+        (Some(sym), Some(out)) => {
+            *out = sym.1;
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// the first defined symbol with this name, or the first undefined one if none is defined.
+/// 0 if found, 1 if not
+///
+/// # Safety
+/// elf must be a live handle, name a nul terminated string and out writable
+#[no_mangle]
+pub unsafe extern "C" fn elfkit_symbol_lookup(
+    elf: *const ElfkitElf,
+    name: *const c_char,
+    out: *mut ElfkitSymbol,
+) -> c_int {
+    let (elf, out) = match (elf.as_ref(), out.as_mut()) {
+        (Some(elf), Some(out)) if !name.is_null() => (elf, out),
+        _ => return -1,
+    };
+    let name = CStr::from_ptr(name);
+    let mut found = elf.symbols.iter().filter(|s| s.0.as_c_str() == name);
+    let first = found.clone().next();
+    match found.find(|s| s.1.shndx != 0).or(first) {
+        Some(sym) => {
+            *out = sym.1;
+            0
+        }
+        None => 1,
+    }
+}
@@ -0,0 +1,286 @@
+use std::io::{Read, Seek, SeekFrom};
+use elf::{Elf, Mapping};
+use error::{Error, Location};
+use header::Header;
+use types;
+
+/// the auxiliary vector the kernel passed to the process, in core files
+pub const NT_AUXV: u32 = 6;
+
+const AT_NULL: u64 = 0;
+const AT_ENTRY: u64 = 9;
+
+/// PATH_MAX, the longest name in the link map
+const MAX_NAME: usize = 4096;
+/// more objects than any process loads, so a link map that goes in a circle ends
+const MAX_LINK_MAP: usize = 1 << 16;
+
+/// r_state, what the dynamic linker was doing to the link map when the process stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RState {
+    Consistent,
+    Add,
+    Delete,
+    Other(u32),
+}
+
+/// one object in the link map
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkMapEntry {
+    /// difference between the addresses in the file and in memory
+    pub addr: u64,
+    /// path the object was loaded from, empty for the executable
+    pub name: String,
+    /// address of its dynamic section in memory
+    pub ld: u64,
+}
+
+/// struct r_debug, which the dynamic linker points DT_DEBUG of the executable to
+#[derive(Debug, Clone, PartialEq)]
+pub struct RDebug {
+    pub version: u32,
+    pub map: Vec<LinkMapEntry>,
+    /// where debuggers put a breakpoint to hear about objects being loaded and unloaded
+    pub brk: u64,
+    pub state: RState,
+    /// load address of the dynamic linker
+    pub ldbase: u64,
+}
+
+fn pad(len: usize, align: usize) -> usize {
+    (len + align - 1) & !(align - 1)
+}
+
+/// the descriptor of the first note with this owner and type. core notes are 4 byte aligned
+fn find_note<'a>(
+    mut b: &'a [u8],
+    owner: &[u8],
+    ntype: u32,
+    eh: &Header,
+) -> Result<Option<&'a [u8]>, Error> {
+    while !b.is_empty() {
+        if b.len() < 12 {
+            return Err(Error::InvalidNote("note header is truncated"));
+        }
+        let mut h = b;
+        let namesz = elf_read_u32!(eh, h)? as usize;
+        let descsz = elf_read_u32!(eh, h)? as usize;
+        let t = elf_read_u32!(eh, h)?;
+        let desc = 12 + pad(namesz, 4);
+        if desc > b.len() || descsz > b.len() - desc {
+            return Err(Error::InvalidNote("note is truncated"));
+        }
+        let name = &b[12..12 + namesz];
+        if t == ntype && name.split(|c| *c == 0).next() == Some(owner) {
+            return Ok(Some(&b[desc..desc + descsz]));
+        }
+        let end = desc + pad(descsz, 4);
+        b = &b[end.min(b.len())..];
+    }
+    Ok(None)
+}
+
+impl Elf {
+    /// the auxiliary vector of a core file, as (type, value) from its NT_AUXV note
+    pub fn core_auxv<R>(&self, io: &mut R) -> Result<Vec<(u64, u64)>, Error>
+    where
+        R: Read + Seek,
+    {
+        if self.header.etype != types::ElfType::CORE {
+            return Err(Error::InvalidHeaderField("e_type"));
+        }
+        let eh = &self.header;
+        for i in 0..self.segments.len() {
+            if self.segments[i].phtype != types::SegmentType::NOTE {
+                continue;
+            }
+            let at = Location::Segment {
+                index: i,
+                offset: self.segments[i].offset,
+            };
+            let data = self.segment_data(i, io)?;
+            let desc = match find_note(&data, b"CORE", NT_AUXV, eh).map_err(|e| e.at(at))? {
+                Some(desc) => desc,
+                None => continue,
+            };
+            let mut r = Vec::new();
+            let mut b = desc;
+            while b.len() >= 2 * self.word_size() {
+                let atype = elf_read_uclass!(eh, b)?;
+                let value = elf_read_uclass!(eh, b)?;
+                if atype == AT_NULL {
+                    break;
+                }
+                r.push((atype, value));
+            }
+            return Ok(r);
+        }
+        Err(Error::InvalidNote("there is no NT_AUXV note"))
+    }
+
+    /// len bytes of the memory a core file has the content of at vaddr.
+    /// memory the kernel didn't dump, which has no file size in its segment, is unmapped
+    pub fn read_memory<R>(&self, io: &mut R, vaddr: u64, len: usize) -> Result<Vec<u8>, Error>
+    where
+        R: Read + Seek,
+    {
+        let mut r = Vec::new();
+        while r.len() < len {
+            let at = match vaddr.checked_add(r.len() as u64) {
+                Some(at) => at,
+                None => return Err(Error::UnmappedAddress(vaddr)),
+            };
+            let (offset, available) = match self.mapped_bytes(at) {
+                Some(m) => m,
+                None => return Err(Error::UnmappedAddress(at)),
+            };
+            let n = available.min((len - r.len()) as u64);
+            io.seek(SeekFrom::Start(offset))?;
+            let before = r.len();
+            io.take(n).read_to_end(&mut r)?;
+            if ((r.len() - before) as u64) < n {
+                return Err(Error::SectionBeyondEndOfFile(offset, n));
+            }
+        }
+        Ok(r)
+    }
+
+    /// file offset of vaddr, and how many bytes from there are mapped by the same segment
+    fn mapped_bytes(&self, vaddr: u64) -> Option<(u64, u64)> {
+        match self.vaddr_to_offset(vaddr)? {
+            (offset, Mapping::Segment(i)) => {
+                let seg = &self.segments[i];
+                Some((offset, seg.offset + seg.filesz - offset))
+            }
+            (offset, Mapping::Section(i)) => {
+                let sec = &self.sections[i].header;
+                Some((offset, sec.offset + sec.size - offset))
+            }
+        }
+    }
+
+    fn read_cstring<R>(&self, io: &mut R, vaddr: u64) -> Result<String, Error>
+    where
+        R: Read + Seek,
+    {
+        let mut r = Vec::new();
+        while r.len() < MAX_NAME {
+            let at = match vaddr.checked_add(r.len() as u64) {
+                Some(at) => at,
+                None => return Err(Error::UnmappedAddress(vaddr)),
+            };
+            let available = match self.mapped_bytes(at) {
+                Some((_, available)) => available,
+                None => return Err(Error::UnmappedAddress(at)),
+            };
+            let chunk = self.read_memory(io, at, available.min(256) as usize)?;
+            if let Some(end) = chunk.iter().position(|c| *c == 0) {
+                r.extend_from_slice(&chunk[..end]);
+                return Ok(String::from_utf8_lossy(&r).into_owned());
+            }
+            r.extend_from_slice(&chunk);
+        }
+        Err(Error::LimitExceeded("name length", r.len() as u64))
+    }
+
+    fn word_size(&self) -> usize {
+        match self.header.ident_class {
+            types::Class::Class32 => 4,
+            types::Class::Class64 => 8,
+        }
+    }
+
+    /**
+     * the link map of the process a core file was dumped from, found through DT_DEBUG
+     * of exe, the executable it ran. AT_ENTRY in the core tells where exe was loaded.
+     *
+     * None if exe has no dynamic section or the dynamic linker never filled in DT_DEBUG,
+     * as for static executables and those without the slot
+     */
+    pub fn r_debug<R>(&self, io: &mut R, exe: &Elf) -> Result<Option<RDebug>, Error>
+    where
+        R: Read + Seek,
+    {
+        let entry = match self.core_auxv(io)?.iter().find(|a| a.0 == AT_ENTRY) {
+            Some(&(_, entry)) => entry,
+            None => return Err(Error::InvalidNote("NT_AUXV has no AT_ENTRY")),
+        };
+        let bias = entry.wrapping_sub(exe.header.entry);
+        let dynamic = match exe.segments
+            .iter()
+            .find(|s| s.phtype == types::SegmentType::DYNAMIC)
+        {
+            Some(seg) => seg,
+            None => return Ok(None),
+        };
+
+        let eh = &self.header;
+        let word = self.word_size();
+        let mut debug = 0;
+        let start = dynamic.vaddr.wrapping_add(bias);
+        for i in 0..dynamic.memsz / (2 * word as u64) {
+            let addr = match start.checked_add(i * 2 * word as u64) {
+                Some(addr) => addr,
+                None => return Err(Error::UnmappedAddress(start)),
+            };
+            let b = self.read_memory(io, addr, 2 * word)?;
+            let mut b = &b[..];
+            let tag = elf_read_uclass!(eh, b)?;
+            let value = elf_read_uclass!(eh, b)?;
+            if tag == types::DynamicType::NULL.0 {
+                break;
+            }
+            if tag == types::DynamicType::DEBUG.0 {
+                debug = value;
+                break;
+            }
+        }
+        if debug == 0 {
+            return Ok(None);
+        }
+
+        // an int, then pointer sized fields, except r_state, which is an enum
+        let b = self.read_memory(io, debug, 5 * word)?;
+        let field = |i: usize| {
+            let mut b = &b[i * word..];
+            elf_read_uclass!(eh, b)
+        };
+        let version = {
+            let mut b = &b[..];
+            elf_read_u32!(eh, b)?
+        };
+        let state = {
+            let mut b = &b[3 * word..];
+            match elf_read_u32!(eh, b)? {
+                0 => RState::Consistent,
+                1 => RState::Add,
+                2 => RState::Delete,
+                v => RState::Other(v),
+            }
+        };
+        let mut r = RDebug {
+            version,
+            map: Vec::new(),
+            brk: field(2)?,
+            state,
+            ldbase: field(4)?,
+        };
+
+        // struct link_map starts with l_addr, l_name, l_ld, l_next
+        let mut next = field(1)?;
+        while next != 0 {
+            if r.map.len() >= MAX_LINK_MAP {
+                return Err(Error::LimitExceeded("link map entries", r.map.len() as u64));
+            }
+            let b = self.read_memory(io, next, 4 * word)?;
+            let mut b = &b[..];
+            let addr = elf_read_uclass!(eh, b)?;
+            let name = elf_read_uclass!(eh, b)?;
+            let ld = elf_read_uclass!(eh, b)?;
+            next = elf_read_uclass!(eh, b)?;
+            let name = if name == 0 { String::new() } else { self.read_cstring(io, name)? };
+            r.map.push(LinkMapEntry { addr, name, ld });
+        }
+        Ok(Some(r))
+    }
+}
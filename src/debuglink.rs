@@ -0,0 +1,160 @@
+/*!
+ * debug info in a file of its own, the way distributions ship it: the binary without its
+ * debug sections, and a companion with nothing but them that debuggers load on demand.
+ *
+ * split_debug does what `objcopy --only-keep-debug`, `strip --strip-debug` and
+ * `objcopy --add-gnu-debuglink` do together. the stripped file gets a .gnu_debuglink section
+ * with the name of the companion and the CRC-32 of its content, which is how gdb finds it next
+ * to the binary or in /usr/lib/debug and checks that it belongs to it.
+ */
+
+use std::io::{Cursor, Read, Write};
+
+use elf::{Elf, Holes, WriteOptions};
+use error::Error;
+use header::Header;
+use linker;
+use section::{Section, SectionContent};
+use types;
+
+pub const SECTION_NAME: &str = ".gnu_debuglink";
+
+/// the CRC-32 .gnu_debuglink has, the one of zlib and gzip
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for b in data {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/**
+ * the debug companion of a file, like objcopy --only-keep-debug makes it.
+ * all sections stay where they are in memory, so debuggers can match them up, but only the
+ * debug sections, notes (which hold the build id), the symbol table and the string tables keep
+ * their content. the others become NOBITS, and the program headers no longer map anything.
+ * it is packed when written with Holes::Pack
+ */
+pub fn only_keep_debug(elf: &mut Elf) -> Result<Elf, Error> {
+    elf.load_all()?;
+    let mut debug = elf.deep_clone();
+    for sec in &mut debug.sections[1..] {
+        let keep = linker::is_debug_section(&sec.name)
+            || match sec.header.shtype {
+                types::SectionType::NOTE | types::SectionType::SYMTAB => true,
+                types::SectionType::STRTAB => {
+                    !sec.header.flags.contains(types::SectionFlags::ALLOC)
+                }
+                _ => false,
+            };
+        if keep || sec.header.shtype == types::SectionType::NOBITS {
+            continue;
+        }
+        sec.header.size = sec.size(&debug.header) as u64;
+        sec.header.shtype = types::SectionType::NOBITS;
+        sec.content = SectionContent::None;
+    }
+    for seg in &mut debug.segments {
+        seg.filesz = 0;
+    }
+    Ok(debug)
+}
+
+/// a .gnu_debuglink section: the file name, zero padded to 4 bytes, and the crc32 of the file
+pub fn debuglink_section(eh: &Header, file: &str, crc: u32) -> Result<Section, Error> {
+    let mut content = file.as_bytes().to_vec();
+    content.push(0);
+    while !content.len().is_multiple_of(4) {
+        content.push(0);
+    }
+    {
+        let io = &mut content;
+        elf_write_u32!(eh, io, crc)?;
+    }
+    let mut sec = Section::new(
+        String::from(SECTION_NAME),
+        types::SectionType::PROGBITS,
+        types::SectionFlags::empty(),
+        SectionContent::Raw(content),
+        0,
+        0,
+    );
+    sec.header.addralign = 4;
+    Ok(sec)
+}
+
+/**
+ * append a .gnu_debuglink to elf, behind everything else in the file, replacing any it had.
+ * the section name table grows with it, which only works if it is at the end of the file,
+ * where linkers put it
+ */
+pub fn add_debuglink(elf: &mut Elf, file: &str, crc: u32) -> Result<(), Error> {
+    if let Some(i) = elf.sections.iter().position(|s| s.name == SECTION_NAME) {
+        elf.remove_section(i)?;
+    }
+    let sec = debuglink_section(&elf.header, file, crc)?;
+    let i = elf.sections.len();
+    elf.sections.push(sec);
+    elf.sync_all()?;
+    let header = elf.header.clone();
+    let end = elf.sections[..i]
+        .iter()
+        .filter(|s| s.header.shtype != types::SectionType::NOBITS)
+        .map(|s| s.header.offset + s.size(&header) as u64)
+        .max()
+        .unwrap_or(0);
+    elf.sections[i].header.offset = end.div_ceil(4) * 4;
+    Ok(())
+}
+
+/// the file name and crc32 in the .gnu_debuglink of elf, None if there is none
+pub fn debuglink(elf: &mut Elf) -> Result<Option<(String, u32)>, Error> {
+    let i = match elf.sections.iter().position(|s| s.name == SECTION_NAME) {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+    elf.load_at(i)?;
+    let at = Elf::location(i, &elf.sections[i]);
+    let raw = match elf.sections[i].content.as_raw() {
+        Some(raw) => raw,
+        None => return Err(Error::UnexpectedSectionContent.at(at)),
+    };
+    let end = match raw.iter().position(|b| *b == 0) {
+        Some(end) => end,
+        None => return Err(Error::InvalidDebuglink("file name is not terminated").at(at)),
+    };
+    let crc_at = (end + 4) / 4 * 4;
+    let mut io = match raw.get(crc_at..crc_at + 4) {
+        Some(b) => b,
+        None => return Err(Error::InvalidDebuglink("no crc behind the file name").at(at)),
+    };
+    let name = String::from_utf8_lossy(&raw[..end]).into_owned();
+    let crc = elf_read_u32!(elf.header, io)?;
+    Ok(Some((name, crc)))
+}
+
+/**
+ * split elf into a stripped file and its debug companion, and return what the companion file
+ * has to contain. elf loses its debug sections, like linker::strip_debug does it, and gets a
+ * .gnu_debuglink to file, which is the name the companion is going to be installed as.
+ * the companion is written packed, and the crc is over exactly the bytes returned
+ */
+pub fn split_debug(elf: &mut Elf, file: &str) -> Result<Vec<u8>, Error> {
+    let mut debug = only_keep_debug(elf)?;
+    debug.sync_all()?;
+    debug.store_all()?;
+    let mut io = Cursor::new(Vec::new());
+    let options = WriteOptions {
+        holes: Holes::Pack,
+        ..WriteOptions::default()
+    };
+    debug.to_writer_with_options(&mut io, &options)?;
+    let bytes = io.into_inner();
+
+    linker::strip_debug(elf)?;
+    add_debuglink(elf, file, crc32(&bytes))?;
+    Ok(bytes)
+}
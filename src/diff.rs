@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::fmt;
+use elf::Elf;
+use header::Header;
+use section::{Section, SectionContent};
+use segment::SegmentHeader;
+use symbol::{Symbol, SymbolSectionIndex};
+use dynamic::{Dynamic, DynamicContent};
+use types;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    /// only the other file has it
+    Added,
+    /// only this file has it
+    Removed,
+    Changed,
+}
+
+/// one difference found by Elf::diff
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    pub kind: DiffKind,
+    /// what differs, like "header entry", "section .text size" or "symbol main in .dynsym"
+    pub what: String,
+    /// the value in this file, None if it was added
+    pub old: Option<String>,
+    /// the value in the other file, None if it was removed
+    pub new: Option<String>,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.old, &self.new) {
+            (&Some(ref old), &Some(ref new)) => write!(f, "~ {}: {} -> {}", self.what, old, new),
+            (&None, &Some(ref new)) => write!(f, "+ {}: {}", self.what, new),
+            (&Some(ref old), &None) => write!(f, "- {}: {}", self.what, old),
+            (&None, &None) => write!(f, "~ {}", self.what),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Diff {
+    differences: Vec<Difference>,
+}
+
+impl Diff {
+    fn compare<T: PartialEq + fmt::Display>(&mut self, what: &str, old: T, new: T) {
+        if old != new {
+            self.differences.push(Difference {
+                kind: DiffKind::Changed,
+                what: String::from(what),
+                old: Some(old.to_string()),
+                new: Some(new.to_string()),
+            });
+        }
+    }
+
+    fn added(&mut self, what: String, new: String) {
+        self.differences.push(Difference {
+            kind: DiffKind::Added,
+            what,
+            old: None,
+            new: Some(new),
+        });
+    }
+
+    fn removed(&mut self, what: String, old: String) {
+        self.differences.push(Difference {
+            kind: DiffKind::Removed,
+            what,
+            old: Some(old),
+            new: None,
+        });
+    }
+
+    /// match up entries by key, the nth entry with a key in one list with the nth in the other,
+    /// and report those only one side has
+    fn keyed<'a, T, K, D, F>(
+        &mut self,
+        what: &str,
+        old: &'a [T],
+        new: &'a [T],
+        key: K,
+        describe: D,
+        mut changed: F,
+    ) where
+        K: Fn(&T) -> String,
+        D: Fn(&T) -> String,
+        F: FnMut(&mut Diff, &str, &'a T, &'a T),
+    {
+        let mut seen = HashMap::new();
+        let mut in_new = HashMap::new();
+        for n in new {
+            let k = key(n);
+            let nth = seen.entry(k.clone()).or_insert(0);
+            in_new.insert((k, *nth), n);
+            *nth += 1;
+        }
+        seen.clear();
+        let mut matched = Vec::new();
+        for o in old {
+            let k = key(o);
+            let nth = *seen.entry(k.clone()).or_insert(0);
+            seen.insert(k.clone(), nth + 1);
+            match in_new.remove(&(k.clone(), nth)) {
+                Some(n) => matched.push((k, o, n)),
+                None => self.removed(format!("{} {}", what, k), describe(o)),
+            }
+        }
+        // in the order of the other file, not of the hashmap
+        seen.clear();
+        for n in new {
+            let k = key(n);
+            let nth = *seen.entry(k.clone()).or_insert(0);
+            seen.insert(k.clone(), nth + 1);
+            if in_new.contains_key(&(k.clone(), nth)) {
+                self.added(format!("{} {}", what, k), describe(n));
+            }
+        }
+        for (k, o, n) in matched {
+            changed(self, &format!("{} {}", what, k), o, n);
+        }
+    }
+}
+
+fn hex(v: u64) -> String {
+    format!("0x{:x}", v)
+}
+
+fn section_type(t: &types::SectionType, eh: &Header) -> String {
+    t.typename(eh).map(String::from).unwrap_or_else(|| hex(t.to_u32() as u64))
+}
+
+fn segment_type(t: &types::SegmentType, eh: &Header) -> String {
+    t.typename(eh).map(String::from).unwrap_or_else(|| format!("{:?}", t))
+}
+
+fn dynamic_type(t: &types::DynamicType, eh: &Header) -> String {
+    t.typename(eh).map(String::from).unwrap_or_else(|| hex(t.to_u64()))
+}
+
+/// section indices change whenever a section is added, so symbols are compared by section name
+fn symbol_section(shndx: &SymbolSectionIndex, sections: &[Section]) -> String {
+    match *shndx {
+        SymbolSectionIndex::Section(i) => match sections.get(i as usize) {
+            Some(sec) => sec.name.clone(),
+            None => format!("section {}", i),
+        },
+        ref other => format!("{:?}", other),
+    }
+}
+
+impl Elf {
+    /**
+     * everything that differs between this file and other: the header, segments, the section
+     * table, symbols, dynamic entries and section content.
+     *
+     * sections are matched up by name, symbols by name within their table and dynamic
+     * entries by tag, so an added section doesn't make everything after it differ.
+     * file offsets aren't compared, they move whenever anything before them grows.
+     * only loaded content is compared as symbols or dynamic entries, call load_all first.
+     */
+    pub fn diff(&self, other: &Elf) -> Vec<Difference> {
+        let mut d = Diff::default();
+        self.diff_header(other, &mut d);
+        self.diff_segments(other, &mut d);
+
+        let name = |sec: &Section| sec.name.clone();
+        let describe = |sec: &Section| {
+            format!("{:?} size 0x{:x}", sec.header.flags, sec.header.size)
+        };
+        d.keyed("section", &self.sections, &other.sections, name, describe, |d, what, o, n| {
+            self.diff_section(other, what, o, n, d);
+        });
+        d.differences
+    }
+
+    fn diff_header(&self, other: &Elf, d: &mut Diff) {
+        let (a, b) = (&self.header, &other.header);
+        d.compare("header class", format!("{:?}", a.ident_class), format!("{:?}", b.ident_class));
+        d.compare(
+            "header endianness",
+            format!("{:?}", a.ident_endianness),
+            format!("{:?}", b.ident_endianness),
+        );
+        d.compare("header abi", format!("{:?}", a.ident_abi), format!("{:?}", b.ident_abi));
+        d.compare("header abiversion", a.ident_abiversion, b.ident_abiversion);
+        d.compare("header type", format!("{:?}", a.etype), format!("{:?}", b.etype));
+        d.compare("header machine", format!("{:?}", a.machine), format!("{:?}", b.machine));
+        d.compare("header entry", hex(a.entry), hex(b.entry));
+        d.compare("header flags", hex(a.flags.bits() as u64), hex(b.flags.bits() as u64));
+    }
+
+    fn diff_segments(&self, other: &Elf, d: &mut Diff) {
+        let describe = |s: &SegmentHeader, eh: &Header| {
+            format!(
+                "{} {:?} vaddr 0x{:x} memsz 0x{:x}",
+                segment_type(&s.phtype, eh),
+                s.flags,
+                s.vaddr,
+                s.memsz
+            )
+        };
+        for (i, (a, b)) in self.segments.iter().zip(other.segments.iter()).enumerate() {
+            let what = format!("segment {}", i);
+            d.compare(
+                &(what.clone() + " type"),
+                segment_type(&a.phtype, &self.header),
+                segment_type(&b.phtype, &other.header),
+            );
+            let flags = (format!("{:?}", a.flags), format!("{:?}", b.flags));
+            d.compare(&(what.clone() + " flags"), flags.0, flags.1);
+            d.compare(&(what.clone() + " vaddr"), hex(a.vaddr), hex(b.vaddr));
+            d.compare(&(what.clone() + " filesz"), hex(a.filesz), hex(b.filesz));
+            d.compare(&(what.clone() + " memsz"), hex(a.memsz), hex(b.memsz));
+            d.compare(&(what + " align"), hex(a.align), hex(b.align));
+        }
+        for (i, s) in self.segments.iter().enumerate().skip(other.segments.len()) {
+            d.removed(format!("segment {}", i), describe(s, &self.header));
+        }
+        for (i, s) in other.segments.iter().enumerate().skip(self.segments.len()) {
+            d.added(format!("segment {}", i), describe(s, &other.header));
+        }
+    }
+
+    fn diff_section(&self, other: &Elf, what: &str, a: &Section, b: &Section, d: &mut Diff) {
+        let (ah, bh) = (&a.header, &b.header);
+        d.compare(
+            &format!("{} type", what),
+            section_type(&ah.shtype, &self.header),
+            section_type(&bh.shtype, &other.header),
+        );
+        d.compare(&format!("{} flags", what), format!("{:?}", ah.flags), format!("{:?}", bh.flags));
+        d.compare(&format!("{} addr", what), hex(ah.addr), hex(bh.addr));
+        d.compare(&format!("{} size", what), hex(ah.size), hex(bh.size));
+        d.compare(&format!("{} addralign", what), ah.addralign, bh.addralign);
+        d.compare(&format!("{} entsize", what), ah.entsize, bh.entsize);
+        let link = |sections: &[Section], l: u32| match sections.get(l as usize) {
+            Some(sec) if l > 0 => sec.name.clone(),
+            _ => l.to_string(),
+        };
+        d.compare(
+            &format!("{} link", what),
+            link(&self.sections, ah.link),
+            link(&other.sections, bh.link),
+        );
+
+        match (&a.content, &b.content) {
+            (&SectionContent::Symbols(ref x), &SectionContent::Symbols(ref y)) => {
+                self.diff_symbols(other, &a.name, x, y, d)
+            }
+            (&SectionContent::Dynamic(ref x), &SectionContent::Dynamic(ref y)) => {
+                self.diff_dynamic(other, x, y, d)
+            }
+            (&SectionContent::Raw(ref x), &SectionContent::Raw(ref y)) if x != y => {
+                // only the first, many bytes usually move along with it
+                let at = x.iter().zip(y.iter()).position(|(p, q)| p != q);
+                let at = at.unwrap_or_else(|| x.len().min(y.len()));
+                d.differences.push(Difference {
+                    kind: DiffKind::Changed,
+                    what: format!("{} content at 0x{:x}", what, at),
+                    old: x.get(at).map(|v| format!("{:02x}", v)),
+                    new: y.get(at).map(|v| format!("{:02x}", v)),
+                });
+            }
+            (&SectionContent::Strtab(ref x), &SectionContent::Strtab(ref y)) => {
+                let x: Vec<&[u8]> = x.iter().map(|(_, s)| s).collect();
+                let y: Vec<&[u8]> = y.iter().map(|(_, s)| s).collect();
+                d.compare(&format!("{} strings", what), x.len(), y.len());
+                if let Some(at) = x.iter().zip(y.iter()).position(|(p, q)| p != q) {
+                    d.compare(
+                        &format!("{} string {}", what, at),
+                        String::from_utf8_lossy(x[at]),
+                        String::from_utf8_lossy(y[at]),
+                    );
+                }
+            }
+            (&SectionContent::Relocations(ref x), &SectionContent::Relocations(ref y)) => {
+                d.compare(&format!("{} relocations", what), x.len(), y.len());
+                let at = x.iter()
+                    .zip(y.iter())
+                    .position(|(p, q)| format!("{:?}", p) != format!("{:?}", q));
+                if let Some(at) = at {
+                    d.compare(
+                        &format!("{} relocation {}", what, at),
+                        format!("{:?}", x[at]),
+                        format!("{:?}", y[at]),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn diff_symbols(&self, other: &Elf, table: &str, a: &[Symbol], b: &[Symbol], d: &mut Diff) {
+        let what = format!("symbol in {}", table);
+        let key = |sym: &Symbol| sym.name.clone();
+        let describe = |sym: &Symbol| format!("{:?} {:?} 0x{:x}", sym.stype, sym.bind, sym.value);
+        d.keyed(&what, a, b, key, describe, |d, what, x, y| {
+            d.compare(&format!("{} value", what), hex(x.value), hex(y.value));
+            d.compare(&format!("{} size", what), x.size, y.size);
+            let stype = (format!("{:?}", x.stype), format!("{:?}", y.stype));
+            d.compare(&format!("{} type", what), stype.0, stype.1);
+            d.compare(&format!("{} bind", what), format!("{:?}", x.bind), format!("{:?}", y.bind));
+            d.compare(&format!("{} other", what), x.other, y.other);
+            d.compare(
+                &format!("{} section", what),
+                symbol_section(&x.shndx, &self.sections),
+                symbol_section(&y.shndx, &other.sections),
+            );
+        });
+    }
+
+    fn diff_dynamic(&self, other: &Elf, a: &[Dynamic], b: &[Dynamic], d: &mut Diff) {
+        let value = |dy: &Dynamic| match dy.content {
+            DynamicContent::None => String::new(),
+            DynamicContent::String(ref s) => s.clone(),
+            DynamicContent::Address(v) => hex(v),
+            DynamicContent::Flags(f) => format!("{:?}", f),
+            DynamicContent::Flags1(f) => format!("{:?}", f),
+        };
+        // NEEDED can appear many times, so it's matched by the library instead
+        let key = |dy: &Dynamic, eh: &Header| match dy.content {
+            DynamicContent::String(ref s) => format!("{} {}", dynamic_type(&dy.dhtype, eh), s),
+            _ => dynamic_type(&dy.dhtype, eh),
+        };
+        let entries = |v: &[Dynamic], eh: &Header| -> Vec<(String, String)> {
+            v.iter().map(|x| (key(x, eh), value(x))).collect()
+        };
+        let (a, b) = (entries(a, &self.header), entries(b, &other.header));
+        let key = |e: &(String, String)| e.0.clone();
+        let describe = |e: &(String, String)| e.1.clone();
+        d.keyed("dynamic", &a, &b, key, describe, |d, what, x, y| {
+            d.compare(what, &x.1, &y.1);
+        });
+    }
+}
@@ -0,0 +1,300 @@
+/*!
+ * hashes of what a file or section contains rather than how it is laid out, for cache keys
+ * of incremental links and for checking that a deployed file is the one that was built.
+ *
+ * file offsets, the padding between sections, section indices and string table offsets
+ * aren't hashed, they change whenever something unrelated moves. so the hash of a file stays
+ * the same when it is written again, the digest of a section stays the same when others are
+ * added or removed, and both change when any byte that ends up in memory or in a symbol
+ * table does.
+ */
+
+use sha2::{Digest, Sha256};
+
+use elf::Elf;
+use error::Error;
+use header::Header;
+use section::{Section, SectionContent};
+use strtab::Strtab;
+use types;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    /// 32 bytes, for integrity checks
+    Sha256,
+    /// 8 bytes, big endian as xxhsum prints it. much faster, but not a cryptographic hash
+    XxHash64,
+}
+
+/// lower case hex of a digest, like sha256sum and xxhsum print it
+pub fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// the digest of data as is
+pub fn hash(algorithm: HashAlgorithm, data: &[u8]) -> Vec<u8> {
+    let mut h = Hasher::new(algorithm);
+    h.input(data);
+    h.result()
+}
+
+const P1: u64 = 11400714785074694791;
+const P2: u64 = 14029467366897019727;
+const P3: u64 = 1609587929392839161;
+const P4: u64 = 9650029242287828579;
+const P5: u64 = 2870177450012600261;
+
+fn xx_round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(P2)).rotate_left(31).wrapping_mul(P1)
+}
+
+fn xx_merge(acc: u64, v: u64) -> u64 {
+    (acc ^ xx_round(0, v)).wrapping_mul(P1).wrapping_add(P4)
+}
+
+fn le64(b: &[u8]) -> u64 {
+    let mut r = [0; 8];
+    r.copy_from_slice(&b[..8]);
+    u64::from_le_bytes(r)
+}
+
+/// XXH64 with seed 0, fed in pieces
+struct XxHash64 {
+    v: [u64; 4],
+    len: u64,
+    buf: [u8; 32],
+    buffered: usize,
+}
+
+impl XxHash64 {
+    fn new() -> XxHash64 {
+        XxHash64 {
+            v: [P1.wrapping_add(P2), P2, 0, 0u64.wrapping_sub(P1)],
+            len: 0,
+            buf: [0; 32],
+            buffered: 0,
+        }
+    }
+
+    fn stripe(&mut self, b: &[u8]) {
+        for i in 0..4 {
+            self.v[i] = xx_round(self.v[i], le64(&b[i * 8..]));
+        }
+    }
+
+    fn input(&mut self, mut b: &[u8]) {
+        self.len += b.len() as u64;
+        if self.buffered > 0 {
+            let n = b.len().min(32 - self.buffered);
+            self.buf[self.buffered..self.buffered + n].copy_from_slice(&b[..n]);
+            self.buffered += n;
+            b = &b[n..];
+            if self.buffered < 32 {
+                return;
+            }
+            let buf = self.buf;
+            self.stripe(&buf);
+            self.buffered = 0;
+        }
+        while b.len() >= 32 {
+            self.stripe(&b[..32]);
+            b = &b[32..];
+        }
+        self.buf[..b.len()].copy_from_slice(b);
+        self.buffered = b.len();
+    }
+
+    fn result(&self) -> u64 {
+        let v = self.v;
+        let mut h = if self.len >= 32 {
+            let h = v[0].rotate_left(1)
+                .wrapping_add(v[1].rotate_left(7))
+                .wrapping_add(v[2].rotate_left(12))
+                .wrapping_add(v[3].rotate_left(18));
+            v.iter().fold(h, |h, v| xx_merge(h, *v))
+        } else {
+            P5
+        };
+        h = h.wrapping_add(self.len);
+
+        let mut rest = &self.buf[..self.buffered];
+        while rest.len() >= 8 {
+            h ^= xx_round(0, le64(rest));
+            h = h.rotate_left(27).wrapping_mul(P1).wrapping_add(P4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            let k = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as u64;
+            h ^= k.wrapping_mul(P1);
+            h = h.rotate_left(23).wrapping_mul(P2).wrapping_add(P3);
+            rest = &rest[4..];
+        }
+        for b in rest {
+            h ^= (*b as u64).wrapping_mul(P5);
+            h = h.rotate_left(11).wrapping_mul(P1);
+        }
+
+        h ^= h >> 33;
+        h = h.wrapping_mul(P2);
+        h ^= h >> 29;
+        h = h.wrapping_mul(P3);
+        h ^ (h >> 32)
+    }
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    XxHash64(XxHash64),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Hasher {
+        match algorithm {
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::default()),
+            HashAlgorithm::XxHash64 => Hasher::XxHash64(XxHash64::new()),
+        }
+    }
+
+    fn input(&mut self, b: &[u8]) {
+        match *self {
+            Hasher::Sha256(ref mut h) => h.input(b),
+            Hasher::XxHash64(ref mut h) => h.input(b),
+        }
+    }
+
+    fn number(&mut self, v: u64) {
+        self.input(&v.to_le_bytes());
+    }
+
+    /// with its length in front, so "ab" "c" doesn't hash like "a" "bc"
+    fn bytes(&mut self, b: &[u8]) {
+        self.number(b.len() as u64);
+        self.input(b);
+    }
+
+    fn result(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(h) => h.result().to_vec(),
+            Hasher::XxHash64(h) => h.result().to_be_bytes().to_vec(),
+        }
+    }
+}
+
+/// the bytes of loaded content, with names in a string table of their own
+/// instead of offsets into the shared one
+fn content(sec: &Section, eh: &Header, h: &mut Hasher) -> Result<(), Error> {
+    let mut raw = Vec::new();
+    let mut names = SectionContent::Strtab(Strtab::default());
+    match sec.content {
+        SectionContent::None => {}
+        SectionContent::Raw(ref v) => raw.extend_from_slice(v),
+        SectionContent::Relocations(ref vv) => {
+            let rela = sec.header.shtype != types::SectionType::REL;
+            for v in vv {
+                v.write(&mut raw, eh, rela)?;
+            }
+        }
+        SectionContent::Symbols(ref vv) => {
+            for v in vv {
+                v.to_writer(&mut raw, Some(&mut names), eh)?;
+            }
+        }
+        SectionContent::Dynamic(ref vv) => {
+            for v in vv {
+                v.to_writer(&mut raw, Some(&mut names), eh)?;
+            }
+        }
+        SectionContent::Strtab(ref v) => v.to_writer(&mut raw, None, eh)?,
+        SectionContent::Got(ref vv) => {
+            for v in vv {
+                v.to_writer(&mut raw, eh)?;
+            }
+        }
+        SectionContent::Ext(ref v) => v.to_writer(eh, &mut raw)?,
+    }
+    h.bytes(&raw);
+    raw.clear();
+    if let SectionContent::Strtab(ref names) = names {
+        names.to_writer(&mut raw, None, eh)?;
+    }
+    h.bytes(&raw);
+    Ok(())
+}
+
+impl Section {
+    /**
+     * hash of the name, type, flags, address, alignment, entry size and content.
+     *
+     * the offset, the index of the name and link and info, which are section indices or
+     * derived from the content, are left out. so is the size, except for NOBITS which has
+     * no content. symbols and dynamic entries that are loaded are hashed with their names
+     * rather than with offsets into the string table, raw ones with the offsets.
+     * symbols still refer to their section by index
+     */
+    pub fn digest(&self, eh: &Header, algorithm: HashAlgorithm) -> Result<Vec<u8>, Error> {
+        let mut h = Hasher::new(algorithm);
+        self.hash(eh, &mut h)?;
+        Ok(h.result())
+    }
+
+    fn hash(&self, eh: &Header, h: &mut Hasher) -> Result<(), Error> {
+        h.bytes(self.name.as_bytes());
+        h.number(self.header.shtype.to_u32() as u64);
+        h.number(self.header.flags.bits());
+        h.number(self.header.addr);
+        h.number(self.header.addralign);
+        h.number(self.header.entsize);
+        if self.header.shtype == types::SectionType::NOBITS {
+            h.number(self.header.size);
+        } else {
+            content(self, eh, h)?;
+        }
+        Ok(())
+    }
+}
+
+impl Elf {
+    /**
+     * hash of the header, the segments and every section as Section::digest hashes it,
+     * after loading all of them.
+     *
+     * what the header says about where the tables are is left out, as are the offsets of
+     * segments and trailing data past the last section. a section that links to another
+     * is hashed with the name of that section instead of its index.
+     */
+    pub fn content_hash(&mut self, algorithm: HashAlgorithm) -> Result<Vec<u8>, Error> {
+        self.load_all()?;
+        let mut h = Hasher::new(algorithm);
+        let eh = &self.header;
+        h.number(eh.ident_class.clone() as u64);
+        h.number(eh.ident_endianness.clone() as u64);
+        h.number(eh.ident_abi.clone() as u64);
+        h.number(eh.ident_abiversion as u64);
+        h.number(eh.etype.clone() as u64);
+        h.number(eh.machine.clone() as u64);
+        h.number(eh.entry);
+        h.number(eh.flags.bits() as u64);
+
+        h.number(self.segments.len() as u64);
+        for s in &self.segments {
+            h.number(s.phtype.to_u32() as u64);
+            h.number(s.flags.bits());
+            h.number(s.vaddr);
+            h.number(s.paddr);
+            h.number(s.filesz);
+            h.number(s.memsz);
+            h.number(s.align);
+        }
+
+        let linked = |i: u32| self.sections.get(i as usize).map(|s| s.name.as_bytes());
+        h.number(self.sections.len() as u64);
+        for sec in &self.sections {
+            sec.hash(eh, &mut h)?;
+            h.bytes(linked(sec.header.link).unwrap_or(b""));
+            if sec.header.flags.contains(types::SectionFlags::INFO_LINK) {
+                h.bytes(linked(sec.header.info).unwrap_or(b""));
+            }
+        }
+        Ok(h.result())
+    }
+}
@@ -1,13 +1,14 @@
-use std::io::{Read, Write};
+use io::{Read, Write};
 use {Error, Header, SectionContent};
 use types;
-use num_traits::{FromPrimitive, ToPrimitive};
+use prelude::*;
 
 #[derive(Debug, Clone)]
 pub enum DynamicContent {
     None,
     String(String),
     Address(u64),
+    Flags(types::DynamicFlags),
     Flags1(types::DynamicFlags1),
 }
 
@@ -44,16 +45,15 @@ impl Dynamic {
         while let Ok(tag) = elf_read_uclass!(eh, io) {
             let val = elf_read_uclass!(eh, io)?;
 
-            match types::DynamicType::from_u64(tag) {
-                None => return Err(Error::InvalidDynamicType(tag)),
-                Some(types::DynamicType::NULL) => {
+            match types::DynamicType(tag) {
+                types::DynamicType::NULL => {
                     r.push(Dynamic {
                         dhtype: types::DynamicType::NULL,
                         content: DynamicContent::None,
                     });
                     break;
                 }
-                Some(types::DynamicType::NEEDED) => {
+                types::DynamicType::NEEDED => {
                     r.push(Dynamic {
                         dhtype: types::DynamicType::NEEDED,
                         content: DynamicContent::String(match strtab {
@@ -62,18 +62,21 @@ impl Dynamic {
                         }),
                     });
                 }
-                Some(types::DynamicType::FLAGS_1) => {
+                types::DynamicType::FLAGS => {
+                    r.push(Dynamic {
+                        dhtype: types::DynamicType::FLAGS,
+                        content: DynamicContent::Flags(types::DynamicFlags::from_bits_retain(val)),
+                    });
+                }
+                types::DynamicType::FLAGS_1 => {
                     r.push(Dynamic {
                         dhtype: types::DynamicType::FLAGS_1,
                         content: DynamicContent::Flags1(
-                            match types::DynamicFlags1::from_bits(val) {
-                                Some(v) => v,
-                                None => return Err(Error::InvalidDynamicFlags1(val)),
-                            },
+                            types::DynamicFlags1::from_bits_retain(val),
                         ),
                     });
                 }
-                Some(x) => {
+                x => {
                     r.push(Dynamic {
                         dhtype: x,
                         content: DynamicContent::Address(val),
@@ -93,7 +96,7 @@ impl Dynamic {
     where
         W: Write,
     {
-        elf_write_uclass!(eh, io, self.dhtype.to_u64().unwrap())?;
+        elf_write_uclass!(eh, io, self.dhtype.to_u64())?;
 
         match self.content {
             DynamicContent::None => {
@@ -109,6 +112,9 @@ impl Dynamic {
             DynamicContent::Address(ref v) => {
                 elf_write_uclass!(eh, io, *v)?;
             }
+            DynamicContent::Flags(ref v) => {
+                elf_write_uclass!(eh, io, v.bits())?;
+            }
             DynamicContent::Flags1(ref v) => {
                 elf_write_uclass!(eh, io, v.bits())?;
             }
@@ -126,6 +132,7 @@ impl Dynamic {
             },
             DynamicContent::None => {}
             DynamicContent::Address(_) => {}
+            DynamicContent::Flags(_) => {}
             DynamicContent::Flags1(_) => {}
         }
         Ok(())
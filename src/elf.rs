@@ -1,53 +1,321 @@
 use header::Header;
 use types;
-use error::Error;
+use error::{Error, Location};
 use section::*;
 use symbol::*;
 use dynamic::*;
 use relocation::*;
 use strtab::*;
+use got::GotEntry;
 use segment::*;
+use utils::narrow;
+
+use scan::Pattern;
 
 use std::io::{Read, Seek, SeekFrom, Write};
 use std;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// sections link to their string or symbol table, which may link to another table.
+/// real files don't go deeper than a few levels
+const MAX_LINK_DEPTH: usize = 64;
+
+/// PT_INTERP is a path, which linux limits to PATH_MAX
+const MAX_INTERP_SIZE: u64 = 4096;
+
+/// to_writer fills gaps between sections up to this size with zeros instead of seeking over them
+const MAX_WRITE_GAP: u64 = 1 << 16;
+
+/// section ids come from one counter for all files, so an id can't name a section of another
+/// file, like a link_ref that was copied along with a section from a deep_clone
+static NEXT_SECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// how strictly input files are checked while parsing
+#[derive(Debug, Clone)]
+pub struct ElfOptions {
+    /// if false, unknown section flags, unknown relocation types and out of range sh_link
+    /// values are recorded in Elf::warnings instead of failing the parse.
+    /// flags, links and relocation types keep their raw value.
+    /// false by default, since toolchains keep adding relocation types elfkit doesn't know yet
+    pub strict: bool,
+
+    /// limits on what a file may claim, so a crafted header can't make the parser run out of
+    /// memory. from_reader fails with Error::LimitExceeded when one is reached.
+    /// they are unlimited by default, see untrusted()
+    pub max_sections: usize,
+    pub max_segments: usize,
+    /// bytes in one section
+    pub max_section_size: u64,
+    /// bytes in all sections together. sections may overlap, so this can be more than the file
+    pub max_total_size: u64,
+
+    /// parsers for sections elfkit doesn't know itself
+    pub content: ContentRegistry,
+
+    /// if true, defined_symbols reads only the names it needs from symbol tables that
+    /// haven't been loaded and leaves them raw, so the symbols are only parsed by load_at.
+    /// for linkers indexing archive members, most of which never get linked
+    pub symbol_index_only: bool,
+}
+
+impl Default for ElfOptions {
+    fn default() -> Self {
+        ElfOptions {
+            strict: false,
+            max_sections: usize::MAX,
+            max_segments: usize::MAX,
+            max_section_size: u64::MAX,
+            max_total_size: u64::MAX,
+            content: ContentRegistry::default(),
+            symbol_index_only: false,
+        }
+    }
+}
+
+impl ElfOptions {
+    /// limits for parsing input from anywhere, like a fuzzer,
+    /// still large enough for any binary a real toolchain produces
+    pub fn untrusted() -> Self {
+        ElfOptions {
+            max_sections: 1 << 20,
+            max_segments: 1 << 16,
+            max_section_size: 1 << 28,
+            max_total_size: 1 << 30,
+            ..ElfOptions::default()
+        }
+    }
+}
+
+/// what to_writer_with_options does with the space between sections that nothing is written
+/// to, like the gaps linkers leave so segments start on a 2MiB boundary.
+/// gaps up to 64KiB are always filled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Holes {
+    /// seek over them. files get a hole on filesystems that support them,
+    /// elsewhere the filesystem writes the zeros. this is what to_writer does.
+    /// gaps are filled instead if the fill isn't zeros
+    #[default]
+    Seek,
+    /// write the fill, for outputs that can't seek past their end or where sparse files
+    /// are unwanted
+    Fill,
+    /// move sections and segments down to close the gaps first, keeping file offsets
+    /// congruent to addresses modulo the page size and sections aligned.
+    /// LOAD segments that are no longer congruent modulo their alignment get page alignment.
+    /// gaps that alignment requires are seeked over
+    Pack,
+}
+
+/// how to_writer_with_options writes a file
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    pub holes: Holes,
+    /// what gaps between sections are filled with, repeated from the start of each gap like
+    /// =FILL in a linker script. empty means zeros.
+    /// [0xcc] fills x86 code with int3, [0x90] with nops
+    pub fill: Vec<u8>,
+}
+
+/**
+ * what edits made out of date that sync_all, store_all or a layout derive from the sections.
+ *
+ * - sizes: header.size of each section, from sync_all and store_all
+ * - names: the section name table with the header.name offsets into it, and the string tables
+ *   symbols and dynamic entries have their names in, from sync_all and store_all
+ * - offsets: where sections are in the file, from linker::relayout or Holes::Pack
+ *
+ * insert_section makes all three out of date, remove_section sizes and names, and
+ * add_relocation all three, because the relocation section grows and symbols may be added.
+ * edits through the pub fields aren't tracked. to_writer refuses to write stale sizes or names
+ * with Error::OutOfSync. stale offsets are only reported, callers may place sections themselves
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stale {
+    pub sizes: bool,
+    pub names: bool,
+    pub offsets: bool,
+}
+
+/**
+ * counters of the work an Elf did, to see where the time goes in the loader and the writer.
+ * they add up over the life of the Elf, clones start with the counts of the original.
+ * times are wall clock and include waiting for io
+ *
+ * - bytes_read: section content and trailing data from_reader read, parse_time all of it
+ * - sections_parsed: raw content load_at turned into symbols, relocations, strings or the like,
+ *   symbols_parsed and relocations_parsed what came out of it, load_time all of that
+ * - symbols_resolved: names of symbolic relocations that sync_all looked up
+ * - bytes_written: by to_writer, including headers and gaps it filled, write_time all of it
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub bytes_read: u64,
+    pub parse_time: Duration,
+    pub sections_parsed: u64,
+    pub symbols_parsed: u64,
+    pub relocations_parsed: u64,
+    pub load_time: Duration,
+    pub symbols_resolved: u64,
+    pub bytes_written: u64,
+    pub write_time: Duration,
+}
+
+/// n bytes of a fill pattern, starting at its beginning
+fn fill_bytes(fill: &[u8], n: usize) -> impl Iterator<Item = u8> + '_ {
+    let zero: &[u8] = &[0];
+    let pattern = if fill.is_empty() { zero } else { fill };
+    pattern.iter().cloned().cycle().take(n)
+}
+
+/// turns the raw content of a section into SectionContent::Ext
+pub type ContentParser =
+    fn(&[u8], &SectionHeader, &Header) -> Result<Box<dyn SectionContentExt>, Error>;
+
+/// which sections a registered parser is for
+#[derive(Debug, Clone)]
+pub enum ContentMatch {
+    Type(types::SectionType),
+    Name(Pattern),
+}
+
+/**
+ * custom parsers for domain specific sections, so they get typed access without changes to
+ * elfkit. parsers are tried in the order they were registered, before elfkit's own, so the
+ * first one matching a section wins, even over the builtin symbol or relocation parsers.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct ContentRegistry {
+    parsers: Vec<(ContentMatch, ContentParser)>,
+}
+
+impl ContentRegistry {
+    /// parse all sections of this type with parser
+    pub fn register_type(&mut self, shtype: types::SectionType, parser: ContentParser) {
+        self.parsers.push((ContentMatch::Type(shtype), parser));
+    }
+
+    /// parse all sections with a matching name with parser. see scan::Pattern for globs
+    pub fn register_name(&mut self, name: &str, parser: ContentParser) {
+        self.parsers.push((ContentMatch::Name(Pattern::parse(name)), parser));
+    }
+
+    pub fn find(&self, sec: &Section) -> Option<ContentParser> {
+        self.parsers
+            .iter()
+            .find(|&&(ref m, _)| match m {
+                &ContentMatch::Type(ref t) => *t == sec.header.shtype,
+                &ContentMatch::Name(ref p) => p.matches(&sec.name),
+            })
+            .map(|&(_, parser)| parser)
+    }
+}
+
+/// what Elf::quick_info finds out about a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuickInfo {
+    pub class: types::Class,
+    pub machine: types::Machine,
+    pub etype: types::ElfType,
+    pub entry: u64,
+    /// the path in PT_INTERP, None for static executables and most libraries
+    pub interpreter: Option<String>,
+}
+
+/// what translated an address in Elf::vaddr_to_offset and Elf::offset_to_vaddr
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mapping {
+    /// index of a LOAD segment
+    Segment(usize),
+    /// index of an allocated section, used when no segment covers the address
+    Section(usize),
+}
 
-pub struct Elf {
+/// D holds the raw content of sections and the trailing data. that's a Vec<u8> of their own
+/// unless parsed with from_bytes, which borrows them from the input
+pub struct Elf<D = Vec<u8>> {
     pub header: Header,
     pub segments: Vec<SegmentHeader>,
-    pub sections: Vec<Section>,
+    pub sections: Vec<Section<D>>,
+    pub options: ElfOptions,
+    /// problems that were tolerated in non strict mode
+    pub warnings: Vec<Error>,
 
     s_lookup: Option<HashSet<String>>,
+    stale: Stale,
+    stats: Stats,
+    section_index_map: HashMap<SectionId, usize>,
+    trailing_data: D,
+    /// the relocations sync_got_relocations made for each Got section, which it replaces
+    got_relocations: HashMap<SectionId, Vec<Relocation>>,
+    /// what add_relocation was given for each relocation section since the last sync
+    symbolic_relocations: HashMap<SectionId, Vec<SymbolicRelocation>>,
 }
 
-impl Default for Elf {
-    fn default() -> Self {
-        let r = Elf {
+impl<D> Elf<D> {
+    fn with_options(options: ElfOptions) -> Elf<D>
+    where
+        D: Default,
+    {
+        Elf {
             header: Header::default(),
             segments: Vec::default(),
             sections: Vec::default(),
+            options,
+            warnings: Vec::new(),
             s_lookup: None,
-        };
-        //always prepend a null section. i don't know yet why, but this is what everyone does.
-        //TODO this is part of the linker?
-        //r.sections.insert(0, Section::default());
-        r
+            stale: Stale::default(),
+            stats: Stats::default(),
+            section_index_map: HashMap::new(),
+            trailing_data: D::default(),
+            got_relocations: HashMap::new(),
+            symbolic_relocations: HashMap::new(),
+        }
     }
-}
 
-impl Elf {
-    pub fn from_reader<R>(io: &mut R) -> Result<Elf, Error>
+    /// how much work parsing, loading, syncing and writing this Elf was so far, see Stats
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// from_reader, with read(io, offset, size) making the content of sections and the
+    /// trailing data. sections that lie beyond the end of the file are refused before
+    fn parse<R, F>(io: &mut R, options: ElfOptions, mut read: F) -> Result<Elf<D>, Error>
     where
         R: Read + Seek,
+        F: FnMut(&mut R, u64, u64) -> Result<D, Error>,
+        D: AsRef<[u8]> + Default,
     {
-        let mut r = Elf::default();
+        let started = Instant::now();
+        let mut r = Elf::with_options(options);
         r.header = Header::from_reader(io)?;
 
+        // too many segments for e_phnum, the real number is the info of section 0
+        let mut phnum = r.header.phnum as usize;
+        if r.header.phnum == types::PN_XNUM && r.header.shoff > 0 {
+            let at = Location::Section {
+                index: 0,
+                name: String::new(),
+                offset: r.header.shoff,
+            };
+            io.seek(SeekFrom::Start(r.header.shoff))?;
+            phnum = SectionHeader::from_reader(io, &r.header).map_err(|e| e.at(at))?.info as usize;
+        }
+
+        if phnum > r.options.max_segments {
+            return Err(Error::LimitExceeded("number of segments", phnum as u64));
+        }
+
         // parse segments
         r.segments.clear();
         io.seek(SeekFrom::Start(r.header.phoff))?;
-        for _ in 0..r.header.phnum {
-            let segment = SegmentHeader::from_reader(io, &r.header)?;
+        for i in 0..phnum {
+            let at = Location::Segment {
+                index: i,
+                offset: r.header.phoff + (i * SegmentHeader::entsize(&r.header)) as u64,
+            };
+            let segment = SegmentHeader::from_reader(io, &r.header).map_err(|e| e.at(at))?;
             r.segments.push(segment);
         }
 
@@ -55,53 +323,440 @@ impl Elf {
         r.sections.clear();
         io.seek(SeekFrom::Start(r.header.shoff))?;
         let mut section_headers = Vec::new();
-        for _ in 0..r.header.shnum {
-            section_headers.push(SectionHeader::from_reader(io, &r.header)?);
+        let mut shnum = r.header.shnum as usize;
+        // too many sections for e_shnum, the real number is the size of section 0
+        if shnum == 0 && r.header.shoff > 0 {
+            let at = Location::Section {
+                index: 0,
+                name: String::new(),
+                offset: r.header.shoff,
+            };
+            let sh = SectionHeader::from_reader(io, &r.header).map_err(|e| e.at(at))?;
+            shnum = sh.size as usize;
+            io.seek(SeekFrom::Start(r.header.shoff))?;
+        }
+        if shnum > r.options.max_sections {
+            return Err(Error::LimitExceeded("number of sections", shnum as u64));
+        }
+        for i in 0..shnum {
+            let at = Location::Section {
+                index: i,
+                name: String::new(),
+                offset: r.header.shoff + (i * SectionHeader::entsize(&r.header)) as u64,
+            };
+            section_headers.push(SectionHeader::from_reader(io, &r.header).map_err(|e| e.at(at))?);
         }
 
         // read section content
-        for sh in section_headers {
+        let file_size = io.seek(SeekFrom::End(0))?;
+        let mut total_size = 0u64;
+        for (i, sh) in section_headers.into_iter().enumerate() {
+            let at = Location::Section {
+                index: i,
+                name: String::new(),
+                offset: sh.offset,
+            };
             r.sections.push(Section {
                 name: String::default(),
                 content: match sh.shtype {
                     types::SectionType::NULL | types::SectionType::NOBITS => SectionContent::None,
                     _ => {
-                        io.seek(SeekFrom::Start(sh.offset))?;
-                        let mut bb = vec![0; sh.size as usize];
-                        io.read_exact(&mut bb)?;
-                        SectionContent::Raw(bb)
+                        total_size = total_size.saturating_add(sh.size);
+                        if sh.size > r.options.max_section_size {
+                            let e = Error::LimitExceeded("section size", sh.size);
+                            return Err(e.at(at));
+                        }
+                        if total_size > r.options.max_total_size {
+                            let e = Error::LimitExceeded("size of all sections", total_size);
+                            return Err(e.at(at));
+                        }
+                        let end = sh.offset.checked_add(sh.size);
+                        if end.map(|end| end > file_size) != Some(false) {
+                            let e = Error::SectionBeyondEndOfFile(sh.offset, sh.size);
+                            return Err(e.at(at));
+                        }
+                        let raw = read(io, sh.offset, sh.size).map_err(|e| e.at(at))?;
+                        r.stats.bytes_read += sh.size;
+                        SectionContent::Raw(raw)
                     }
                 },
                 header: sh,
+                link_ref: None,
+                info_ref: None,
+                placement: Placement::default(),
+                id: SectionId::default(),
             });
         }
+        r.assign_section_ids();
+        r.check_section_headers()?;
 
-        // resolve section names
-        let shstrtab = match r.sections.get(r.header.shstrndx as usize) {
+        // resolve section names. core files have no section headers and so nothing to name
+        let shstrtab = match r.sections.get(r.shstrndx()) {
+            None if r.sections.is_empty() => Strtab::default(),
             None => return Err(Error::MissingShstrtabSection),
             Some(sec) => match sec.content {
-                SectionContent::Raw(ref s) => s,
+                SectionContent::Raw(ref s) => Strtab::from_raw(s.as_ref().to_vec()),
                 _ => return Err(Error::MissingShstrtabSection),
             },
-        }.clone();
+        };
+
+        for i in 0..r.sections.len() {
+            let name = match shstrtab.get_bytes(r.sections[i].header.name as usize) {
+                Some(name) => String::from_utf8_lossy(name).into_owned(),
+                // a table without any names is fine as long as nothing points into it
+                None if r.sections[i].header.name == 0 => String::new(),
+                None => {
+                    let e = Error::InvalidStrtabOffset(r.sections[i].header.name);
+                    return Err(e.at(Elf::location(i, &r.sections[i])));
+                }
+            };
+            r.sections[i].name = name;
+        }
 
-        for ref mut sec in &mut r.sections {
-            sec.name = String::from_utf8_lossy(
-                shstrtab[sec.header.name as usize..]
-                    .split(|e| *e == 0)
-                    .next()
-                    .unwrap_or(&[0; 0]),
-            ).into_owned();
+        let end = r.content_end();
+        if file_size > end {
+            if file_size - end > r.options.max_section_size {
+                return Err(Error::LimitExceeded("trailing data size", file_size - end));
+            }
+            r.trailing_data = read(io, end, file_size - end)?;
+            r.stats.bytes_read += file_size - end;
         }
 
+        r.stats.parse_time += started.elapsed();
         Ok(r)
     }
 
+    /// where the last thing the headers describe ends in the file
+    fn content_end(&self) -> u64 {
+        let eh = &self.header;
+        let mut end = eh.size() as u64;
+        let phdrs = self.segments.len() * SegmentHeader::entsize(eh);
+        end = end.max(eh.phoff.saturating_add(phdrs as u64));
+        if eh.shoff > 0 {
+            let shdrs = self.sections.len() * SectionHeader::entsize(eh);
+            end = end.max(eh.shoff.saturating_add(shdrs as u64));
+        }
+        for seg in &self.segments {
+            end = end.max(seg.offset.saturating_add(seg.filesz));
+        }
+        for sec in &self.sections {
+            if sec.header.shtype != types::SectionType::NULL
+                && sec.header.shtype != types::SectionType::NOBITS
+            {
+                end = end.max(sec.header.offset + sec.header.size);
+            }
+        }
+        end
+    }
+
+    /// flags and links are kept raw by SectionHeader::from_reader, check them here
+    fn check_section_headers(&mut self) -> Result<(), Error> {
+        for i in 0..self.sections.len() {
+            let mut problems = Vec::new();
+            {
+                let sh = &self.sections[i].header;
+                if !types::SectionFlags::all().contains(sh.flags) {
+                    problems.push(Error::InvalidSectionFlags(sh.flags.bits()));
+                }
+                if sh.link as usize >= self.sections.len() {
+                    problems.push(Error::InvalidSectionLink(sh.link));
+                }
+            }
+            for e in problems {
+                let e = e.at(Elf::location(i, &self.sections[i]));
+                if self.options.strict {
+                    return Err(e);
+                }
+                self.warnings.push(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// index of the section name table. e_shstrndx can't hold indices from SHN_LORESERVE up,
+    /// then it's SHN_XINDEX and the index is in the link of section 0
+    pub fn shstrndx(&self) -> usize {
+        if self.header.shstrndx == types::SHN_XINDEX {
+            self.sections.first().map(|s| s.header.link as usize).unwrap_or(0)
+        } else {
+            self.header.shstrndx as usize
+        }
+    }
+
+    fn assign_section_ids(&mut self) {
+        let mut seen = HashSet::new();
+        for sec in &mut self.sections {
+            // sections pushed directly or cloned from another section don't have a unique id yet
+            if sec.id == SectionId::default() || !seen.insert(sec.id) {
+                sec.id = SectionId(NEXT_SECTION_ID.fetch_add(1, Ordering::Relaxed));
+                seen.insert(sec.id);
+            }
+        }
+        self.section_index_map = self.sections
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.id, i))
+            .collect();
+    }
+
+    pub(crate) fn location(i: usize, sec: &Section<D>) -> Location {
+        Location::Section {
+            index: i,
+            name: sec.name.clone(),
+            offset: sec.header.offset,
+        }
+    }
+}
+
+impl<D: AsRef<[u8]>> Elf<D> {
+    /// bytes after everything the headers describe, like the payload of a self extracting
+    /// installer or an appended signature. to_writer puts them back after the section headers
+    pub fn trailing_data(&self) -> &[u8] {
+        self.trailing_data.as_ref()
+    }
+
+    /**
+     * a copy for reading that borrows the raw content of the sections and the trailing data,
+     * which makes it cheap for a file that is stored. parsed content is copied.
+     * it's the same file, so the sections keep their ids. warnings are not copied
+     */
+    pub fn view(&self) -> Elf<&[u8]> {
+        Elf {
+            header: self.header.clone(),
+            segments: self.segments.clone(),
+            sections: self.sections.iter().map(Section::view).collect(),
+            options: self.options.clone(),
+            warnings: Vec::new(),
+            s_lookup: self.s_lookup.clone(),
+            stale: self.stale,
+            stats: self.stats.clone(),
+            section_index_map: self.section_index_map.clone(),
+            trailing_data: self.trailing_data.as_ref(),
+            got_relocations: self.got_relocations.clone(),
+            symbolic_relocations: self.symbolic_relocations.clone(),
+        }
+    }
+}
+
+impl<D: Into<Vec<u8>>> Elf<D> {
+    /// a copy that owns its content, which every method that edits or loads sections needs
+    pub fn into_owned(self) -> Elf {
+        Elf {
+            header: self.header,
+            segments: self.segments,
+            sections: self.sections.into_iter().map(Section::into_owned).collect(),
+            options: self.options,
+            warnings: self.warnings,
+            s_lookup: self.s_lookup,
+            stale: self.stale,
+            stats: self.stats,
+            section_index_map: self.section_index_map,
+            trailing_data: self.trailing_data.into(),
+            got_relocations: self.got_relocations,
+            symbolic_relocations: self.symbolic_relocations,
+        }
+    }
+}
+
+/**
+ * an Elf that borrows the content of its sections from the bytes it was parsed from, instead
+ * of copying each of them, for reading many files or large ones. only the raw content is
+ * there, loading or editing needs into_owned.
+ * the Vec<u8> Elf that from_reader makes is the one the linker and everything else uses
+ */
+impl<'a> Elf<&'a [u8]> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Elf<&'a [u8]>, Error> {
+        Elf::from_bytes_with_options(data, ElfOptions::default())
+    }
+
+    pub fn from_bytes_with_options(
+        data: &'a [u8],
+        options: ElfOptions,
+    ) -> Result<Elf<&'a [u8]>, Error> {
+        let mut io = std::io::Cursor::new(data);
+        Elf::parse(&mut io, options, |_, offset, size| {
+            Ok(&data[offset as usize..(offset + size) as usize])
+        })
+    }
+}
+
+impl Default for Elf {
+    fn default() -> Self {
+        let r = Elf::with_options(ElfOptions::default());
+        //always prepend a null section. i don't know yet why, but this is what everyone does.
+        //TODO this is part of the linker?
+        //r.sections.insert(0, Section::default());
+        r
+    }
+}
+
+impl Elf {
+    /**
+     * triage a file without parsing it: only the header and the program headers are read.
+     * section headers are never looked at, unless the number of segments overflows into
+     * section 0.
+     * this is for scanning many files, where from_reader would read all section content.
+     */
+    pub fn quick_info<R>(io: &mut R) -> Result<QuickInfo, Error>
+    where
+        R: Read + Seek,
+    {
+        let mut b = [0; 64];
+        // a 32bit header with nothing else in the file is shorter than 64 bytes
+        let mut n = 0;
+        while n < b.len() {
+            match io.read(&mut b[n..])? {
+                0 => break,
+                k => n += k,
+            }
+        }
+        let eh = Header::peek(&b)?;
+        if n < eh.size() {
+            return Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+
+        let mut phnum = eh.phnum as usize;
+        if eh.phnum == types::PN_XNUM && eh.shoff > 0 {
+            io.seek(SeekFrom::Start(eh.shoff))?;
+            phnum = SectionHeader::from_reader(io, &eh)?.info as usize;
+        }
+
+        let mut interpreter = None;
+        for i in 0..phnum {
+            let at = Location::Segment {
+                index: i,
+                offset: eh.phoff + (i * eh.phentsize as usize) as u64,
+            };
+            io.seek(SeekFrom::Start(eh.phoff + (i * eh.phentsize as usize) as u64))?;
+            let ph = SegmentHeader::from_reader(io, &eh).map_err(|e| e.at(at.clone()))?;
+            if ph.phtype != types::SegmentType::INTERP {
+                continue;
+            }
+            if ph.filesz > MAX_INTERP_SIZE {
+                return Err(Error::LimitExceeded("interpreter size", ph.filesz).at(at));
+            }
+            let mut path = vec![0; ph.filesz as usize];
+            io.seek(SeekFrom::Start(ph.offset))?;
+            io.read_exact(&mut path).map_err(|e| Error::from(e).at(at))?;
+            let end = path.iter().position(|c| *c == 0).unwrap_or(path.len());
+            interpreter = Some(String::from_utf8_lossy(&path[..end]).into_owned());
+            break;
+        }
+
+        Ok(QuickInfo {
+            class: eh.ident_class,
+            machine: eh.machine,
+            etype: eh.etype,
+            entry: eh.entry,
+            interpreter,
+        })
+    }
+
+    pub fn from_reader<R>(io: &mut R) -> Result<Elf, Error>
+    where
+        R: Read + Seek,
+    {
+        Elf::from_reader_with_options(io, ElfOptions::default())
+    }
+
+    pub fn from_reader_with_options<R>(io: &mut R, options: ElfOptions) -> Result<Elf, Error>
+    where
+        R: Read + Seek,
+    {
+        Elf::parse(io, options, Elf::read_section)
+    }
+
+    pub fn set_trailing_data(&mut self, data: Vec<u8>) {
+        self.trailing_data = data;
+    }
+
+    /// what edits made out of date since it was last derived, see Stale
+    pub fn stale(&self) -> Stale {
+        self.stale
+    }
+
+    /// for layouts outside of this module, which place every section
+    pub(crate) fn set_laid_out(&mut self) {
+        self.stale.offsets = false;
+    }
+
+    /**
+     * a copy that shares nothing with this file, for writing both or editing them apart.
+     * its sections get ids of their own, and the link_ref and info_ref handles between them
+     * are rewritten to those, so a section moved from one file into the other can't link to a
+     * table there through an id that meant another table here. pending relocations go along.
+     * warnings are not copied, errors can't be
+     */
+    pub fn deep_clone(&self) -> Elf {
+        let mut r = self.snapshot();
+        let mut ids = HashMap::new();
+        for sec in &mut r.sections {
+            if sec.id != SectionId::default() {
+                let id = SectionId(NEXT_SECTION_ID.fetch_add(1, Ordering::Relaxed));
+                ids.insert(sec.id, id);
+                sec.id = id;
+            }
+        }
+        for sec in &mut r.sections {
+            for r in sec.link_ref.iter_mut().chain(sec.info_ref.iter_mut()) {
+                if let LinkRef::Id(ref mut id) = *r {
+                    *id = ids.get(id).cloned().unwrap_or(*id);
+                }
+            }
+        }
+        r.got_relocations = r.got_relocations
+            .drain()
+            .filter_map(|(id, v)| ids.get(&id).map(|id| (*id, v)))
+            .collect();
+        r.symbolic_relocations = r.symbolic_relocations
+            .drain()
+            .filter_map(|(id, v)| ids.get(&id).map(|id| (*id, v)))
+            .collect();
+        r.assign_section_ids();
+        r
+    }
+
+    /// a copy of everything but the warnings, with the same section ids, for putting it back
+    pub(crate) fn snapshot(&self) -> Elf {
+        Elf {
+            header: self.header.clone(),
+            segments: self.segments.clone(),
+            sections: self.sections.clone(),
+            options: self.options.clone(),
+            warnings: Vec::new(),
+            s_lookup: self.s_lookup.clone(),
+            stale: self.stale,
+            stats: self.stats.clone(),
+            section_index_map: self.section_index_map.clone(),
+            trailing_data: self.trailing_data.clone(),
+            got_relocations: self.got_relocations.clone(),
+            symbolic_relocations: self.symbolic_relocations.clone(),
+        }
+    }
+
+    /// the content of a section, read as it arrives instead of allocating the size it claims
+    /// upfront, so a size far beyond the end of the file fails without allocating it
+    fn read_section<R>(io: &mut R, offset: u64, size: u64) -> Result<Vec<u8>, Error>
+    where
+        R: Read + Seek,
+    {
+        io.seek(SeekFrom::Start(offset))?;
+        let mut bb = Vec::new();
+        io.take(size).read_to_end(&mut bb)?;
+        if (bb.len() as u64) < size {
+            return Err(Error::from(std::io::Error::from(std::io::ErrorKind::UnexpectedEof)));
+        }
+        Ok(bb)
+    }
+
+
     fn load(
         &self,
         raw: Vec<u8>,
         sh: &SectionHeader,
         linked: Option<&SectionContent>,
+        xindex: Option<&[u8]>,
+        warnings: &mut Vec<Error>,
     ) -> Result<(SectionContent), Error> {
         Ok(match sh.shtype {
             types::SectionType::STRTAB => {
@@ -110,11 +765,21 @@ impl Elf {
             }
             types::SectionType::RELA => {
                 let io = &raw[..];
-                Relocation::from_reader(io, linked, &self.header)?
+                if self.options.strict {
+                    Relocation::from_reader(io, linked, &self.header)?
+                } else {
+                    Relocation::from_reader_permissive(io, linked, &self.header, warnings)?
+                }
+            }
+            // REL used to be left raw, so keep doing that for machines without relocation support
+            types::SectionType::REL if Relocation::entsize_rel(&self.header) > 0 => {
+                let io = &raw[..];
+                let warnings = if self.options.strict { None } else { Some(warnings) };
+                Relocation::read_all(io, &self.header, false, warnings)?
             }
             types::SectionType::SYMTAB | types::SectionType::DYNSYM => {
                 let io = &raw[..];
-                Symbol::from_reader(io, linked, &self.header)?
+                Symbol::from_reader_with_xindex(io, linked, xindex, &self.header)?
             }
             types::SectionType::DYNAMIC => {
                 let io = &raw[..];
@@ -126,6 +791,11 @@ impl Elf {
 
 
     pub fn load_at(&mut self, i: usize) -> Result<(), Error> {
+        self.load_chain(i, &mut Vec::new())
+    }
+
+    /// load_at, with chain being the sections that link to this one and wait for it to load
+    fn load_chain(&mut self, i: usize, chain: &mut Vec<usize>) -> Result<(), Error> {
         let is_loaded = match self.sections[i].content {
             SectionContent::Raw(_) | SectionContent::None => false,
             _ => true,
@@ -137,24 +807,73 @@ impl Elf {
 
         //take out the original. this is to work around the borrow checker
         let mut sec = std::mem::replace(&mut self.sections[i], Section::default());
-        {
-            let linked = {
-                if sec.header.link < 1 || sec.header.link as usize >= self.sections.len() {
-                    None
-                } else {
-                    self.load_at(sec.header.link as usize)?;
-                    Some(&self.sections[sec.header.link as usize].content)
-                }
-            };
+        chain.push(i);
+        let r = self.load_taken(i, &mut sec, chain);
+        chain.pop();
+
+        //put it back in
+        self.sections[i] = sec;
+
+        r
+    }
 
-            sec.content = match sec.content {
-                SectionContent::Raw(raw) => self.load(raw, &sec.header, linked)?,
+    fn load_taken(&mut self, i: usize, sec: &mut Section, chain: &mut Vec<usize>)
+        -> Result<(), Error>
+    {
+        let mut warnings = Vec::new();
+        let at = Elf::location(i, sec);
+        let link = sec.header.link as usize;
+        let has_link = link > 0 && link < self.sections.len();
+        if has_link {
+            if chain.contains(&link) {
+                return Err(Error::SectionLinkCycle(sec.header.link).at(at));
+            }
+            if chain.len() >= MAX_LINK_DEPTH {
+                let e = Error::LimitExceeded("depth of section links", chain.len() as u64);
+                return Err(e.at(at));
+            }
+            self.load_chain(link, chain)?;
+        }
+        {
+            let linked = if has_link { Some(&self.sections[link].content) } else { None };
+
+            let is_symtab = sec.header.shtype == types::SectionType::SYMTAB
+                || sec.header.shtype == types::SectionType::DYNSYM;
+            let companion = if is_symtab { self.symtab_shndx(i) } else { None };
+            let xindex = companion
+                .and_then(|j| self.sections[j].content.as_raw())
+                .map(|x| &x[..]);
+
+            let started = Instant::now();
+            let was_raw = matches!(sec.content, SectionContent::Raw(_));
+            let parser = self.options.content.find(sec);
+            sec.content = match std::mem::replace(&mut sec.content, SectionContent::None) {
+                SectionContent::Raw(raw) => match parser {
+                    Some(parser) => SectionContent::Ext(
+                        parser(&raw, &sec.header, &self.header).map_err(|e| e.at(at.clone()))?,
+                    ),
+                    None => self
+                        .load(raw, &sec.header, linked, xindex, &mut warnings)
+                        .map_err(|e| e.at(at.clone()))?,
+                },
                 any => any,
             };
+            // content elfkit has no parser for stays raw, and is looked at again next time
+            if was_raw && !matches!(sec.content, SectionContent::Raw(_)) {
+                self.stats.sections_parsed += 1;
+                match sec.content {
+                    SectionContent::Symbols(ref v) => self.stats.symbols_parsed += v.len() as u64,
+                    SectionContent::Relocations(ref v) => {
+                        self.stats.relocations_parsed += v.len() as u64
+                    }
+                    _ => {}
+                }
+                self.stats.load_time += started.elapsed();
+            }
+        }
+        for e in warnings {
+            self.warnings.push(e.at(at.clone()));
         }
-
-        //put it back in
-        self.sections[i] = sec;
 
         Ok(())
     }
@@ -173,17 +892,23 @@ impl Elf {
     ) -> Result<(Section), Error> {
         match sec.content {
             SectionContent::Relocations(vv) => {
+                let rela = sec.header.shtype != types::SectionType::REL;
                 let mut raw = Vec::new();
                 for v in vv {
-                    v.to_writer(&mut raw, None, eh)?;
+                    v.write(&mut raw, eh, rela)?;
                 }
-                sec.header.entsize = Relocation::entsize(eh) as u64;
+                sec.header.entsize = if rela {
+                    Relocation::entsize(eh)
+                } else {
+                    Relocation::entsize_rel(eh)
+                } as u64;
                 sec.header.size = raw.len() as u64;
                 sec.content = SectionContent::Raw(raw);
             }
             SectionContent::Symbols(vv) => {
+                // one past the last local, weak symbols count as global here
                 for (i, sym) in vv.iter().enumerate() {
-                    if sym.bind == types::SymbolBind::GLOBAL {
+                    if sym.bind != types::SymbolBind::LOCAL {
                         sec.header.info = i as u32;
                         break;
                     }
@@ -212,6 +937,21 @@ impl Elf {
                 sec.header.size = raw.len() as u64;
                 sec.content = SectionContent::Raw(raw);
             }
+            SectionContent::Got(vv) => {
+                let mut raw = Vec::new();
+                for v in vv {
+                    v.to_writer(&mut raw, eh)?;
+                }
+                sec.header.entsize = GotEntry::entsize(eh) as u64;
+                sec.header.size = raw.len() as u64;
+                sec.content = SectionContent::Raw(raw);
+            }
+            SectionContent::Ext(v) => {
+                let mut raw = Vec::new();
+                v.to_writer(eh, &mut raw)?;
+                sec.header.size = raw.len() as u64;
+                sec.content = SectionContent::Raw(raw);
+            }
             SectionContent::None | SectionContent::Raw(_) => {}
         };
         Ok(sec)
@@ -241,7 +981,8 @@ impl Elf {
                 }
             };
 
-            sec = Elf::store(&self.header, sec, linked)?;
+            let at = Elf::location(i, &sec);
+            sec = Elf::store(&self.header, sec, linked).map_err(|e| e.at(at))?;
         }
 
         //put it back in
@@ -250,50 +991,86 @@ impl Elf {
         Ok((true))
     }
 
-    pub fn store_all(&mut self) -> Result<(), Error> {
-        self.header.shstrndx = match self.sections.iter().position(|s| s.name == ".shstrtab") {
-            Some(i) => i as u16,
-            None => 0,
-        };
-        loop {
-            let mut still_need_to_store = false;
-            for i in 0..self.sections.len() {
-                still_need_to_store = still_need_to_store || self.store_at(i)?;
+    /// turn all symbolic link_ref/info_ref into section indices
+    fn resolve_link_refs(&mut self) -> Result<(), Error> {
+        for i in 0..self.sections.len() {
+            if let Some(ref r) = self.sections[i].link_ref {
+                self.sections[i].header.link = self.resolve_link_ref(r)?;
             }
-            if !still_need_to_store {
-                break;
+            if let Some(ref r) = self.sections[i].info_ref {
+                self.sections[i].header.info = self.resolve_link_ref(r)?;
+                self.sections[i]
+                    .header
+                    .flags
+                    .insert(types::SectionFlags::INFO_LINK);
             }
         }
-
         Ok(())
     }
 
-    /// write out everything to linked sections, such as string tables
-    /// after calling this function, size() is reliable for all sections
-    pub fn sync_all(&mut self) -> Result<(), Error> {
-        match self.sections.iter().position(|s| s.name == ".shstrtab") {
-            Some(i) => {
-                self.header.shstrndx = i as u16;
-                let mut shstrtab = std::mem::replace(
-                    &mut self.sections[self.header.shstrndx as usize].content,
-                    SectionContent::default(),
-                );
+    fn resolve_link_ref(&self, r: &LinkRef) -> Result<u32, Error> {
+        match r {
+            &LinkRef::Name(ref name) => match self.sections.iter().position(|s| &s.name == name) {
+                Some(i) => Ok(i as u32),
+                None => Err(Error::SectionNotFound(name.clone())),
+            },
+            &LinkRef::Id(id) => match self.section_index(id) {
+                Some(i) => Ok(i as u32),
+                None => Err(Error::SectionIdNotFound(id)),
+            },
+        }
+    }
 
-                for sec in &mut self.sections {
-                    sec.header.name = shstrtab
-                        .as_strtab_mut()
-                        .unwrap()
-                        .insert(sec.name.as_bytes().to_vec())
-                        as u32;
+    /// write all loaded content back as raw bytes, which is what to_writer writes.
+    /// a file with sections but no section name table gets a .shstrtab behind everything else
+    pub fn store_all(&mut self) -> Result<(), Error> {
+        self.resolve_link_refs()?;
+        self.resolve_symbolic_relocations()?;
+        self.sync_got_relocations()?;
+        self.sync_section_names(true)?;
+        let shstrndx = self.shstrtab_index();
+        self.set_shstrndx(shstrndx.unwrap_or(0));
+        self.sync_symtab_shndx(false)?;
+        // storing a section loads the one it links to again, so every round gets one step
+        // further down the links. more rounds than there are sections means they go in a circle
+        let mut still_need_to_store = None;
+        for _ in 0..self.sections.len() + 2 {
+            still_need_to_store = None;
+            for i in 0..self.sections.len() {
+                if self.store_at(i)? && still_need_to_store.is_none() {
+                    still_need_to_store = Some(i);
                 }
-                self.sections[self.header.shstrndx as usize].content = shstrtab;
             }
-            None => {}
-        };
+            if still_need_to_store.is_none() {
+                self.stale.sizes = false;
+                self.stale.names = false;
+                return Ok(());
+            }
+        }
+
+        Err(Error::SectionLinkCycle(still_need_to_store.unwrap_or(0) as u32))
+    }
 
+    /// write out everything to linked sections, such as string tables
+    /// after calling this function, size() is reliable for all sections.
+    /// the section name table is rebuilt from the names of the sections there are now.
+    /// a file without one gets it from store_all, once the layout is done
+    pub fn sync_all(&mut self) -> Result<(), Error> {
+        self.sync_symtab_shndx(true)?;
+        self.resolve_link_refs()?;
+        self.resolve_symbolic_relocations()?;
+        self.sync_got_relocations()?;
+        self.sync_section_names(false)?;
 
         let mut dirty: Vec<usize> = (0..self.sections.len()).collect();
+        let mut rounds = 0;
         while dirty.len() > 0 {
+            // every round follows the links one step further, more rounds than there are
+            // sections can only mean the links go in a circle
+            rounds += 1;
+            if rounds > self.sections.len() + 1 {
+                return Err(Error::SectionLinkCycle(dirty[0] as u32));
+            }
             for i in std::mem::replace(&mut dirty, Vec::new()).iter() {
                 //work around the borrow checker
                 let mut sec = std::mem::replace(&mut self.sections[*i], Section::default());
@@ -307,7 +1084,8 @@ impl Elf {
                             Some(&mut self.sections[sec.header.link as usize].content)
                         }
                     };
-                    sec.sync(&self.header, linked)?;
+                    sec.sync(&self.header, linked)
+                        .map_err(|e| e.at(Elf::location(*i, &sec)))?;
                 }
 
                 //put it back in
@@ -315,71 +1093,355 @@ impl Elf {
             }
         }
 
+        self.optimize_strtabs()?;
+        // syncing section 0 like any other section reset its size
+        let shstrndx = self.shstrndx();
+        self.sync_null_section(shstrndx);
+        self.stale.sizes = false;
+        self.stale.names = false;
         Ok(())
     }
 
-    pub fn to_writer<R>(&mut self, io: &mut R) -> Result<(), Error>
-    where
-        R: Write + Seek,
-    {
-        io.seek(SeekFrom::Start(0))?;
-        let off = self.header.size();
-        io.write(&vec![0; off])?;
+    /// pack the tail merged string tables once everything has been inserted
+    fn optimize_strtabs(&mut self) -> Result<(), Error> {
+        let shstrndx = self.shstrndx();
+        let eh = &self.header;
+        let mut shstrtab_moved = false;
+        for (i, sec) in self.sections.iter_mut().enumerate() {
+            if let SectionContent::Strtab(ref mut strtab) = sec.content {
+                if !strtab.tail_merge() {
+                    continue;
+                }
+                strtab.optimize();
+                shstrtab_moved |= i == shstrndx;
+            } else {
+                continue;
+            }
+            sec.header.size = sec.size(eh) as u64;
+        }
 
-        // segment headers
-        // MUST be written before section content, because it MUST be in the first LOAD
-        // otherwise the kernel passes an invalid aux vector
+        if shstrtab_moved {
+            let mut shstrtab =
+                std::mem::replace(&mut self.sections[shstrndx].content, SectionContent::default());
+            let mut r = Ok(());
+            if let Some(strtab) = shstrtab.as_strtab_mut() {
+                for sec in &mut self.sections {
+                    let off = strtab.insert(sec.name.as_bytes().to_vec()) as u64;
+                    match narrow("sh_name", off) {
+                        Ok(off) => sec.header.name = off,
+                        Err(e) => r = Err(e),
+                    }
+                }
+            }
+            self.sections[shstrndx].content = shstrtab;
+            return r;
+        }
+        Ok(())
+    }
+
+    /**
+     * put the names of all sections into the section name table. a file that has sections but
+     * no table gets one appended behind everything else if create is set.
+     * the table is built again so names of removed sections don't stay behind, unless some
+     * other section keeps its strings there too or the new one would be larger, which happens
+     * to tables read from a file that had names share their tails
+     */
+    fn sync_section_names(&mut self, create: bool) -> Result<(), Error> {
+        let found = self.shstrtab_index();
+        let i = match found.or_else(|| if create { self.add_shstrtab() } else { None }) {
+            Some(i) => i,
+            None => return Ok(()),
+        };
+        self.set_shstrndx(i);
+        self.load_at(i)?;
+        let mut shstrtab =
+            std::mem::replace(&mut self.sections[i].content, SectionContent::default());
+        let shared = self.sections[1..].iter().any(|s| s.header.link as usize == i);
+        let r = match shstrtab.as_strtab_mut() {
+            Some(strtab) => {
+                if !shared {
+                    let mut fresh = Strtab::default();
+                    fresh.set_tail_merge(strtab.tail_merge());
+                    for sec in &self.sections {
+                        fresh.insert(sec.name.as_bytes().to_vec());
+                    }
+                    if fresh.len(&self.header) <= strtab.len(&self.header) {
+                        *strtab = fresh;
+                    }
+                }
+                for sec in &mut self.sections {
+                    let off = strtab.insert(sec.name.as_bytes().to_vec()) as u64;
+                    sec.header.name = narrow("sh_name", off)?;
+                }
+                Ok(())
+            }
+            None => Err(Error::LinkedSectionIsNotStrtab("shstrtab")),
+        };
+        self.sections[i].content = shstrtab;
+        r
+    }
+
+    /// the section name table: .shstrtab, or else the string table e_shstrndx points at
+    fn shstrtab_index(&self) -> Option<usize> {
+        let named = self.sections.iter().position(|s| s.name == ".shstrtab");
+        named.or_else(|| {
+            let i = self.shstrndx();
+            match self.sections.get(i) {
+                Some(sec) if i > 0 && sec.header.shtype == types::SectionType::STRTAB => Some(i),
+                _ => None,
+            }
+        })
+    }
+
+    /// append an empty .shstrtab behind everything else in the file, for a file that has
+    /// sections but no name table. None if there are no sections to name
+    fn add_shstrtab(&mut self) -> Option<usize> {
+        if self.sections.is_empty() {
+            return None;
+        }
+        let phdrs = self.segments.len() * SegmentHeader::entsize(&self.header);
+        let end = self.sections
+            .iter()
+            .filter(|s| s.header.shtype != types::SectionType::NOBITS)
+            .map(|s| s.header.offset + s.size(&self.header) as u64)
+            .fold((self.header.size() + phdrs) as u64, u64::max);
+        let mut sec = Section::new(
+            String::from(".shstrtab"),
+            types::SectionType::STRTAB,
+            types::SectionFlags::empty(),
+            SectionContent::Strtab(Strtab::default()),
+            0,
+            0,
+        );
+        sec.header.offset = end;
+        sec.header.addralign = 1;
+        self.sections.push(sec);
+        Some(self.sections.len() - 1)
+    }
+
+    fn set_shstrndx(&mut self, i: usize) {
+        let extended = i as u32 >= types::SHN_LORESERVE;
+        self.header.shstrndx = if extended { types::SHN_XINDEX } else { i as u16 };
+        self.sync_null_section(i);
+    }
+
+    /// rebuild section 0 from the section table and segments, see SectionHeader::null.
+    /// a first section that isn't of type NULL is left alone
+    fn sync_null_section(&mut self, shstrndx: usize) {
+        let shnum = self.sections.len();
+        let phnum = self.segments.len();
+        if let Some(null) = self.sections.first_mut() {
+            if null.header.shtype == types::SectionType::NULL {
+                null.header = SectionHeader::null(shnum, shstrndx, phnum);
+                null.content = SectionContent::None;
+            }
+        }
+    }
+
+    /// index of the SYMTAB_SHNDX section holding the extended section indices of the
+    /// symbol table at index i
+    pub fn symtab_shndx(&self, i: usize) -> Option<usize> {
+        self.sections.iter().position(|s| {
+            s.header.shtype == types::SectionType::SYMTAB_SHNDX && s.header.link as usize == i
+        })
+    }
+
+    /// write the extended section indices of all loaded symbol tables into their SYMTAB_SHNDX
+    /// sections. if a table needs one and doesn't have it, it's inserted right after the table
+    /// when insert is set, otherwise that's an error
+    fn sync_symtab_shndx(&mut self, insert: bool) -> Result<(), Error> {
+        self.assign_section_ids();
+        let tables: Vec<SectionId> = self.sections
+            .iter()
+            .filter(|s| {
+                (s.header.shtype == types::SectionType::SYMTAB
+                    || s.header.shtype == types::SectionType::DYNSYM)
+                    && s.content.as_symbols().is_some()
+            })
+            .map(|s| s.id)
+            .collect();
+
+        for id in tables {
+            let i = match self.section_index(id) {
+                Some(i) => i,
+                None => continue,
+            };
+            let needed = self.sections[i]
+                .content
+                .as_symbols()
+                .map(|v| v.iter().any(|sym| sym.extended_shndx().is_some()))
+                .unwrap_or(false);
+            let j = match self.symtab_shndx(i) {
+                Some(j) => j,
+                None if !needed => continue,
+                None if !insert => {
+                    return Err(Error::MissingSymtabShndxSection(self.sections[i].name.clone()))
+                }
+                None => {
+                    let mut sec = Section::new(
+                        format!("{}_shndx", self.sections[i].name),
+                        types::SectionType::SYMTAB_SHNDX,
+                        types::SectionFlags::empty(),
+                        SectionContent::Raw(Vec::new()),
+                        i as u32,
+                        0,
+                    );
+                    sec.header.addralign = 4;
+                    sec.link_ref = Some(LinkRef::Id(id));
+                    self.insert_section(i + 1, sec)?;
+                    i + 1
+                }
+            };
+
+            let mut raw = Vec::new();
+            {
+                let io = &mut raw;
+                for sym in self.sections[i].content.as_symbols().unwrap() {
+                    elf_write_u32!(&self.header, io, sym.extended_shndx().unwrap_or(0))?;
+                }
+            }
+            let sec = &mut self.sections[j];
+            sec.header.entsize = 4;
+            sec.header.size = raw.len() as u64;
+            sec.content = SectionContent::Raw(raw);
+        }
+        Ok(())
+    }
+
+    pub fn to_writer<R>(&mut self, io: &mut R) -> Result<(), Error>
+    where
+        R: Write + Seek,
+    {
+        self.to_writer_with_options(io, &WriteOptions::default())
+    }
+
+    /// to_writer, with the gaps between sections handled as options say
+    pub fn to_writer_with_options<R>(&mut self, io: &mut R, options: &WriteOptions)
+        -> Result<(), Error>
+    where
+        R: Write + Seek,
+    {
+        if self.stale.sizes {
+            return Err(Error::OutOfSync("section sizes"));
+        }
+        if self.stale.names {
+            return Err(Error::OutOfSync("section and symbol names"));
+        }
+        let started = Instant::now();
+        if options.holes == Holes::Pack {
+            self.pack();
+        }
+        let seek = options.holes != Holes::Fill && options.fill.iter().all(|b| *b == 0);
+        // the file is written in runs: the header, segment headers and the sections after them
+        // are collected until a section starts further away than MAX_WRITE_GAP, then the run is
+        // written at once. so most files take one seek and a handful of large writes
+        io.seek(SeekFrom::Start(0))?;
+        let off = self.header.size();
+        let mut run = vec![0; off];
+        let mut run_start = 0u64;
+        let mut written = 0u64;
+
+        // segment headers, right behind the elf header. they must be in the first LOAD or
+        // the kernel passes an invalid aux vector, linker::check_program_headers checks that
+        // the layout left room for them
         if self.segments.len() > 0 {
             self.header.phoff = off as u64;
             for seg in &self.segments {
-                seg.to_writer(&self.header, io)?;
+                seg.to_writer(&self.header, &mut run)?;
             }
-            let at = io.seek(SeekFrom::Current(0))? as usize;
-            self.header.phnum = self.segments.len() as u16;
-            self.header.phentsize = ((at - off) / self.segments.len()) as u16;
+            self.header.phentsize = ((run.len() - off) / self.segments.len()) as u16;
         }
 
-        let headers: Vec<SectionHeader> = self.sections.iter().map(|s| s.header.clone()).collect();
+        let mut headers: Vec<SectionHeader> =
+            self.sections.iter().map(|s| s.header.clone()).collect();
+        let has_null = headers.first().map(|h| h.shtype == types::SectionType::NULL) == Some(true);
+        if has_null {
+            headers[0] = SectionHeader::null(headers.len(), self.shstrndx(), self.segments.len());
+        } else if self.header.shstrndx == types::SHN_XINDEX {
+            return Err(Error::NullSectionRequired("the index of the section name table"));
+        }
+        self.header.phnum = if self.segments.len() >= types::PN_XNUM as usize {
+            if self.header.shstrndx == 0 || !has_null {
+                return Err(Error::NullSectionRequired("the number of segments"));
+            }
+            types::PN_XNUM
+        } else {
+            self.segments.len() as u16
+        };
         let mut sections = std::mem::replace(&mut self.sections, Vec::new());
 
         //sections
         sections.sort_unstable_by(|a, b| a.header.offset.cmp(&b.header.offset));
+        // where the previous section ended, or began if it wasn't written
+        let mut at = run.len() as u64;
         for sec in sections {
-            let off = io.seek(SeekFrom::Current(0))? as usize;
-
-            assert_eq!(
-                io.seek(SeekFrom::Start(sec.header.offset))?,
-                sec.header.offset
-            );
-            match sec.content {
-                SectionContent::Raw(ref v) => {
-                    if off > sec.header.offset as usize {
-                        println!(
-                            "BUG: section layout is broken. \
-                             would write section '{}' at position 0x{:x} over previous section \
-                             that ended at 0x{:x}",
-                            sec.name,
-                            sec.header.offset,
-                            off
-                        );
+            let v = match sec.content {
+                SectionContent::Raw(ref v) if sec.header.shtype != types::SectionType::NULL => v,
+                _ => {
+                    at = sec.header.offset;
+                    continue;
+                }
+            };
+            // would write over the previous section
+            if at > sec.header.offset {
+                return Err(Error::SectionOverlap(sec.name.clone(), sec.header.offset, at));
+            }
+            let run_end = run_start + run.len() as u64;
+            if sec.header.offset > run_end && sec.header.offset - run_end > MAX_WRITE_GAP {
+                io.write_all(&run)?;
+                written += run.len() as u64;
+                run.clear();
+                run_start = sec.header.offset;
+                if seek {
+                    if io.seek(SeekFrom::Start(run_start))? != run_start {
+                        return Err(Error::SectionOffsetUnreachable(sec.name, sec.header.offset));
+                    }
+                } else {
+                    let gap = (run_start - run_end) as usize;
+                    let mut fill = fill_bytes(&options.fill, gap);
+                    for _ in 0..gap.div_ceil(MAX_WRITE_GAP as usize) {
+                        run.extend(fill.by_ref().take(MAX_WRITE_GAP as usize));
+                        io.write_all(&run)?;
+                        written += run.len() as u64;
+                        run.clear();
                     }
-                    io.write(&v.as_ref())?;
                 }
-                _ => {}
             }
+            let start = (sec.header.offset - run_start) as usize;
+            if run.len() < start {
+                let gap = start - run.len();
+                run.extend(fill_bytes(&options.fill, gap));
+            }
+            if run.len() < start + v.len() {
+                run.resize(start + v.len(), 0);
+            }
+            run[start..start + v.len()].copy_from_slice(v);
+            at = sec.header.offset + v.len() as u64;
         }
+        io.write_all(&run)?;
+        written += run.len() as u64;
+        run.clear();
 
-
-        //section headers
+        //section headers, then the trailing data
+        let end = io.seek(SeekFrom::End(0))?;
         if self.header.shstrndx > 0 {
-            let off = io.seek(SeekFrom::End(0))? as usize;
-            self.header.shoff = off as u64;
+            self.header.shoff = end;
+            self.header.shnum = if headers.len() as u32 >= types::SHN_LORESERVE {
+                if !has_null {
+                    return Err(Error::NullSectionRequired("the number of sections"));
+                }
+                0
+            } else {
+                headers.len() as u16
+            };
             for sec in &headers {
-                sec.to_writer(&self.header, io)?;
+                sec.to_writer(&self.header, &mut run)?;
             }
-            self.header.shnum = headers.len() as u16;
             self.header.shentsize = SectionHeader::entsize(&self.header) as u16;
         }
+        run.extend_from_slice(&self.trailing_data);
+        io.write_all(&run)?;
+        written += run.len() as u64;
 
         //hygene
         self.header.ehsize = self.header.size() as u16;
@@ -387,93 +1449,240 @@ impl Elf {
         io.seek(SeekFrom::Start(0))?;
         self.header.to_writer(io)?;
 
+        self.stats.bytes_written += written;
+        self.stats.write_time += started.elapsed();
         Ok(())
     }
 
-    //TODO the warnings need to be emited when calling store_all instead
-    pub fn remove_section(&mut self, at: usize) -> Result<(Section), Error> {
-        let r = self.sections.remove(at);
-
-        for sec in &mut self.sections {
-            if sec.header.link == at as u32 {
-                sec.header.link = 0;
-            //println!("warning: removed section {} has a dangling link from {}", at, sec.name);
-            } else if sec.header.link > at as u32 {
-                sec.header.link -= 1;
+    /// lower file offsets for Holes::Pack.
+    /// what a LOAD segment maps, the headers, and sections outside of those move as blocks,
+    /// each to the lowest offset after the previous one that keeps it congruent to where it was
+    fn pack(&mut self) {
+        const PAGE_SIZE: u64 = 0x1000;
+        let phdrs = self.segments.len() * SegmentHeader::entsize(&self.header);
+        let headers = self.header.size() + phdrs;
+        // start, end and the modulus the offset is kept congruent in
+        let mut ranges = vec![(0, headers as u64, PAGE_SIZE)];
+        for seg in &self.segments {
+            if seg.phtype == types::SegmentType::LOAD && seg.filesz > 0 {
+                ranges.push((seg.offset, seg.offset + seg.filesz, PAGE_SIZE));
             }
-
-            if sec.header.flags.contains(types::SectionFlags::INFO_LINK) {
-                if sec.header.info == at as u32 {
-                    sec.header.info = 0;
-                //println!("warning: removed section {} has a dangling info link from {}", at,
-                //sec.name);
-                } else if sec.header.info > at as u32 {
-                    sec.header.info -= 1;
+        }
+        for sec in &self.sections {
+            let t = &sec.header.shtype;
+            if *t != types::SectionType::NULL && *t != types::SectionType::NOBITS {
+                let size = sec.size(&self.header) as u64;
+                let align = sec.header.addralign.max(1);
+                ranges.push((sec.header.offset, sec.header.offset + size, align));
+            }
+        }
+        ranges.sort_by_key(|r| r.0);
+
+        // start, end, modulus, and where the start moves to
+        let mut blocks: Vec<(u64, u64, u64, u64)> = Vec::new();
+        for (start, end, m) in ranges {
+            if let Some(last) = blocks.last_mut() {
+                if start < last.1 {
+                    last.1 = last.1.max(end);
+                    last.2 = last.2.max(m);
+                    continue;
                 }
             }
+            blocks.push((start, end, m, 0));
+        }
+        let mut cursor = 0;
+        for b in &mut blocks {
+            b.3 = cursor + (b.0 % b.2 + b.2 - cursor % b.2) % b.2;
+            cursor = b.3 + (b.1 - b.0);
         }
 
-        Ok((r))
-    }
-    pub fn insert_section(&mut self, at: usize, sec: Section) -> Result<(), Error> {
-        self.sections.insert(at, sec);
-
+        let moved = |off: u64| match blocks.iter().find(|b| b.0 <= off && off <= b.1) {
+            Some(b) => off - (b.0 - b.3),
+            None => off,
+        };
         for sec in &mut self.sections {
-            if sec.header.link >= at as u32 {
-                sec.header.link += 1;
+            if sec.header.shtype != types::SectionType::NULL {
+                sec.header.offset = moved(sec.header.offset);
             }
-
-            if sec.header.flags.contains(types::SectionFlags::INFO_LINK) {
-                if sec.header.info > at as u32 {
-                    sec.header.info += 1;
-                }
+        }
+        for seg in &mut self.segments {
+            seg.offset = moved(seg.offset);
+            if seg.phtype == types::SegmentType::LOAD
+                && seg.align > PAGE_SIZE
+                && seg.offset % seg.align != seg.vaddr % seg.align
+            {
+                seg.align = PAGE_SIZE;
             }
         }
+        self.stale.offsets = false;
+    }
 
-        Ok(())
+    /// stable handle for the section currently at index i.
+    /// unlike the index, the handle stays valid across insert_section, remove_section and
+    /// move_section
+    pub fn section_id(&mut self, i: usize) -> SectionId {
+        self.assign_section_ids();
+        self.sections[i].id
     }
 
-    pub fn move_section(&mut self, from: usize, mut to: usize) -> Result<(), Error> {
-        if to == from {
-            return Ok(());
+    /// current index of the section behind a handle, or None if it was removed
+    pub fn section_index(&self, id: SectionId) -> Option<usize> {
+        if let Some(&i) = self.section_index_map.get(&id) {
+            if self.sections.get(i).map(|s| s.id) == Some(id) {
+                return Some(i);
+            }
         }
-        if to > from {
-            to -= 1;
+        self.sections.iter().position(|s| s.id == id)
+    }
+
+    /// the sections that are referenced by a section header, as handles
+    fn section_refs(&self, sec: &Section) -> SectionRefs {
+        let by_index = |i: u32| if i > 0 && (i as usize) < self.sections.len() {
+            Some(self.sections[i as usize].id)
+        } else {
+            None
+        };
+        SectionRefs {
+            link: by_index(sec.header.link),
+            info: if sec.header.flags.contains(types::SectionFlags::INFO_LINK) {
+                by_index(sec.header.info)
+            } else {
+                None
+            },
         }
+    }
+
+    /// run a modification of the section table, keeping all header references between
+    /// sections and the section indices of symbols pointing at the same sections they did before.
+    /// references to sections that no longer exist are set to 0, which for symbols means they
+    /// become undefined.
+    fn with_stable_refs<F, T>(&mut self, additional: Option<&Section>, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut Vec<Section>) -> T,
+    {
+        self.assign_section_ids();
+        let old_ids: Vec<SectionId> = self.sections.iter().map(|s| s.id).collect();
+
+        let mut refs: HashMap<SectionId, SectionRefs> = self.sections
+            .iter()
+            .map(|s| (s.id, self.section_refs(s)))
+            .collect();
+        // a section that is about to be inserted refers to the table as it is now
+        let additional_refs = additional.map(|s| self.section_refs(s));
+        let shstrndx = match self.shstrndx() {
+            0 => None,
+            i => self.sections.get(i).map(|s| s.id),
+        };
 
+        let r = f(&mut self.sections);
+        self.assign_section_ids();
 
+        let to_index = |id: Option<SectionId>, map: &HashMap<SectionId, usize>| match id {
+            Some(id) => map.get(&id).map(|i| *i as u32).unwrap_or(0),
+            None => 0,
+        };
         for sec in &mut self.sections {
-            if sec.header.link == from as u32 {
-                sec.header.link = 999999;
+            let sr = match refs.remove(&sec.id) {
+                Some(sr) => sr,
+                None => match additional_refs {
+                    Some(ref sr) => sr.clone(),
+                    None => continue,
+                },
+            };
+            if sr.link.is_some() {
+                sec.header.link = to_index(sr.link, &self.section_index_map);
             }
-            if sec.header.flags.contains(types::SectionFlags::INFO_LINK) {
-                if sec.header.info == from as u32 {
-                    sec.header.info = 999999;
-                }
+            if sr.info.is_some() {
+                sec.header.info = to_index(sr.info, &self.section_index_map);
             }
         }
-        let sec = self.remove_section(from)?;
-        self.insert_section(to, sec)?;
+        if shstrndx.is_some() {
+            let i = to_index(shstrndx, &self.section_index_map);
+            self.set_shstrndx(i as usize);
+        }
+
+        let index_map = &self.section_index_map;
+        let eh = &self.header;
+        let remap = |i: u32| to_index(old_ids.get(i as usize).cloned(), index_map);
         for sec in &mut self.sections {
-            if sec.header.link == 999999 {
-                sec.header.link = to as u32;
+            if sec.header.shtype == types::SectionType::SYMTAB_SHNDX {
+                if let SectionContent::Raw(ref mut raw) = sec.content {
+                    Symbol::remap_raw_xindex(raw, eh, remap)?;
+                }
+                continue;
+            }
+            if sec.header.shtype != types::SectionType::SYMTAB
+                && sec.header.shtype != types::SectionType::DYNSYM
+            {
+                continue;
             }
-            if sec.header.flags.contains(types::SectionFlags::INFO_LINK) {
-                if sec.header.info == 999999 {
-                    sec.header.info = to as u32;
+            match sec.content {
+                SectionContent::Symbols(ref mut symbols) => for sym in symbols {
+                    if let SymbolSectionIndex::Section(i) = sym.shndx {
+                        sym.shndx = match remap(i) {
+                            0 => SymbolSectionIndex::Undefined,
+                            i => SymbolSectionIndex::Section(i),
+                        };
+                    }
+                },
+                SectionContent::Raw(ref mut raw) => {
+                    Symbol::remap_raw_shndx(raw, eh, |i| remap(i as u32))?;
                 }
+                _ => {}
             }
         }
 
-        Ok(())
+        Ok(r)
     }
-}
-
-
 
+    pub fn remove_section(&mut self, at: usize) -> Result<(Section), Error> {
+        if at >= self.sections.len() {
+            return Err(Error::SectionIndexOutOfRange(at));
+        }
+        self.stale.sizes = true;
+        self.stale.names = true;
+        self.with_stable_refs(None, |sections| sections.remove(at))
+    }
 
+    pub fn insert_section(&mut self, at: usize, sec: Section) -> Result<(), Error> {
+        if at > self.sections.len() {
+            return Err(Error::SectionIndexOutOfRange(at));
+        }
+        let mut sec = sec;
+        // whatever id the caller's section had, it's a new section in this table
+        sec.id = SectionId::default();
+        let refs_from = sec.clone();
+        self.stale = Stale {
+            sizes: true,
+            names: true,
+            offsets: true,
+        };
+        self.with_stable_refs(Some(&refs_from), |sections| sections.insert(at, sec))
+    }
 
+    /// move the section at index from to before the section at index to
+    pub fn move_section(&mut self, from: usize, to: usize) -> Result<(), Error> {
+        if from >= self.sections.len() {
+            return Err(Error::SectionIndexOutOfRange(from));
+        }
+        if to > self.sections.len() {
+            return Err(Error::SectionIndexOutOfRange(to));
+        }
+        if to == from {
+            return Ok(());
+        }
+        self.with_stable_refs(None, |sections| {
+            let sec = sections.remove(from);
+            sections.insert(if to > from { to - 1 } else { to }, sec);
+        })
+    }
+}
 
+#[derive(Clone)]
+struct SectionRefs {
+    link: Option<SectionId>,
+    info: Option<SectionId>,
+}
 
 impl Elf {
     /// check if a global defined symbol is exported from the elf file.
@@ -483,6 +1692,11 @@ impl Elf {
     /// which is currently loading symtab into a hashmap
     /// TODO should be replaced with checking HASH and GNU_HASH
     pub fn contains_symbol(&mut self, name: &str) -> Result<bool, Error> {
+        Ok(self.defined_symbols()?.contains(name))
+    }
+
+    /// names of all global and weak symbols defined in this file, as used by contains_symbol
+    pub fn defined_symbols(&mut self) -> Result<&HashSet<String>, Error> {
         if None == self.s_lookup {
             let mut hm = HashSet::new();
 
@@ -501,6 +1715,12 @@ impl Elf {
                 .collect::<Vec<usize>>()
                 .iter()
             {
+                if self.options.symbol_index_only {
+                    if let Some(names) = self.raw_defined_names(*i)? {
+                        hm.extend(names);
+                        continue;
+                    }
+                }
                 self.load_at(*i)?;
                 for sym in self.sections[*i].content.as_symbols().unwrap() {
                     if sym.bind != types::SymbolBind::LOCAL
@@ -513,6 +1733,611 @@ impl Elf {
 
             self.s_lookup = Some(hm);
         }
-        Ok(self.s_lookup.as_ref().unwrap().contains(name))
+        Ok(self.s_lookup.as_ref().unwrap())
+    }
+
+    /// Symbol::defined_names of symbol table i, None if it or its string table isn't raw
+    fn raw_defined_names(&self, i: usize) -> Result<Option<Vec<String>>, Error> {
+        let sec = &self.sections[i];
+        let strtab = self.sections.get(sec.header.link as usize).map(|s| &s.content);
+        match (&sec.content, strtab) {
+            (&SectionContent::Raw(ref data), Some(&SectionContent::Raw(ref strtab))) => {
+                Symbol::defined_names(data, strtab, &self.header)
+                    .map(Some)
+                    .map_err(|e| e.at(Elf::location(i, sec)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// use a previously saved result of defined_symbols, so contains_symbol doesn't need
+    /// to load the symbol table again
+    pub fn set_defined_symbols(&mut self, symbols: HashSet<String>) {
+        self.s_lookup = Some(symbols);
+    }
+}
+
+impl Elf {
+    /// the bytes segment i covers in the file the elf was parsed from, which io must still be.
+    /// only filesz, the rest of memsz is zero filled when loading
+    pub fn segment_data<R>(&self, i: usize, io: &mut R) -> Result<Vec<u8>, Error>
+    where
+        R: Read + Seek,
+    {
+        let seg = match self.segments.get(i) {
+            Some(seg) => seg,
+            None => return Err(Error::SegmentIndexOutOfRange(i)),
+        };
+        let at = Location::Segment {
+            index: i,
+            offset: seg.offset,
+        };
+        if seg.filesz > self.options.max_section_size {
+            return Err(Error::LimitExceeded("segment size", seg.filesz).at(at));
+        }
+        io.seek(SeekFrom::Start(seg.offset))?;
+        let mut b = Vec::new();
+        io.take(seg.filesz).read_to_end(&mut b)?;
+        if (b.len() as u64) < seg.filesz {
+            return Err(Error::SectionBeyondEndOfFile(seg.offset, seg.filesz).at(at));
+        }
+        Ok(b)
+    }
+
+    /**
+     * the file offset of the content mapped at vaddr.
+     * LOAD segments are what the loader uses, so they are preferred. files without program
+     * headers fall back to the address of allocated sections, which is 0 for all of them in
+     * relocatable objects, so the result is only meaningful for linked files.
+     * addresses in the zero filled part of a segment or in NOBITS sections have no offset
+     */
+    pub fn vaddr_to_offset(&self, vaddr: u64) -> Option<(u64, Mapping)> {
+        for (i, seg) in self.segments.iter().enumerate() {
+            if seg.phtype == types::SegmentType::LOAD && vaddr >= seg.vaddr
+                && vaddr - seg.vaddr < seg.filesz
+            {
+                return Some((seg.offset + (vaddr - seg.vaddr), Mapping::Segment(i)));
+            }
+        }
+        for (i, sec) in self.sections.iter().enumerate() {
+            if Elf::is_mapped(sec) && vaddr >= sec.header.addr
+                && vaddr - sec.header.addr < sec.header.size
+            {
+                return Some((sec.header.offset + (vaddr - sec.header.addr), Mapping::Section(i)));
+            }
+        }
+        None
+    }
+
+    /// the virtual address the byte at this file offset is loaded to, the inverse of
+    /// vaddr_to_offset with the same preference
+    pub fn offset_to_vaddr(&self, offset: u64) -> Option<(u64, Mapping)> {
+        for (i, seg) in self.segments.iter().enumerate() {
+            if seg.phtype == types::SegmentType::LOAD && offset >= seg.offset
+                && offset - seg.offset < seg.filesz
+            {
+                return Some((seg.vaddr + (offset - seg.offset), Mapping::Segment(i)));
+            }
+        }
+        for (i, sec) in self.sections.iter().enumerate() {
+            if Elf::is_mapped(sec) && offset >= sec.header.offset
+                && offset - sec.header.offset < sec.header.size
+            {
+                return Some((sec.header.addr + (offset - sec.header.offset), Mapping::Section(i)));
+            }
+        }
+        None
+    }
+
+    fn is_mapped(sec: &Section) -> bool {
+        sec.header.flags.contains(types::SectionFlags::ALLOC)
+            && sec.header.shtype != types::SectionType::NOBITS
+    }
+
+    /**
+     * overwrite the bytes mapped at vaddr, as a binary patching tool would.
+     * the address goes through vaddr_to_offset to a file offset, and the sections at that
+     * offset get the bytes, so they have to be Raw. a range may span several sections,
+     * but every byte must be in one.
+     *
+     * segments and section offsets must be layouted, as they are after parsing or relayout
+     */
+    pub fn patch_vaddr_range(&mut self, vaddr: u64, bytes: &[u8]) -> Result<(), Error> {
+        let offset = match self.vaddr_to_offset(vaddr) {
+            Some((offset, Mapping::Segment(i))) => {
+                let seg = &self.segments[i];
+                if bytes.len() as u64 > seg.offset + seg.filesz - offset {
+                    return Err(Error::UnmappedAddress(vaddr + seg.offset + seg.filesz - offset));
+                }
+                offset
+            }
+            Some((offset, Mapping::Section(_))) => offset,
+            None => return Err(Error::UnmappedAddress(vaddr)),
+        };
+
+        // find every piece first, so an error leaves all sections as they were
+        let mut pieces = Vec::new();
+        let mut done = 0;
+        while done < bytes.len() {
+            let pos = offset + done as u64;
+            let i = match self.sections.iter().position(|sec| {
+                sec.header.shtype != types::SectionType::NOBITS && pos >= sec.header.offset
+                    && pos - sec.header.offset < sec.header.size
+            }) {
+                Some(i) => i,
+                None => return Err(Error::UnmappedAddress(vaddr + done as u64)),
+            };
+            let start = (pos - self.sections[i].header.offset) as usize;
+            match self.sections[i].content {
+                SectionContent::Raw(ref raw) if start < raw.len() => {
+                    let n = std::cmp::min(bytes.len() - done, raw.len() - start);
+                    pieces.push((i, start, done, n));
+                    done += n;
+                }
+                _ => return Err(Error::UnexpectedSectionContent.at(
+                    Elf::location(i, &self.sections[i]))),
+            }
+        }
+
+        for (i, start, done, n) in pieces {
+            if let SectionContent::Raw(ref mut raw) = self.sections[i].content {
+                raw[start..start + n].copy_from_slice(&bytes[done..done + n]);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Elf {
+    /**
+     * give section i new content and fix up everything behind it, like when editing a config
+     * blob in the .rodata of a linked file.
+     *
+     * sync_all writes out the content and the string tables. every section that changed size
+     * keeps its offset and address. when it doesn't fit in the gap before the next content in
+     * the file, everything after it moves by enough to keep the alignment of the sections and
+     * segments that move. the segments around the section grow or shrink with it.
+     * loaded sections can only grow into addresses nothing else uses, because the code refers
+     * to the addresses of everything else.
+     */
+    pub fn replace_section_content(
+        &mut self,
+        i: usize,
+        content: SectionContent,
+    ) -> Result<(), Error> {
+        if i >= self.sections.len() {
+            return Err(Error::SectionIndexOutOfRange(i));
+        }
+        let before: Vec<u64> = self.sections.iter().map(|s| s.header.size).collect();
+        self.sections[i].content = content;
+        self.sync_all()?;
+
+        let mut resized: Vec<usize> = (0..self.sections.len())
+            .filter(|&j| {
+                let h = &self.sections[j].header;
+                h.shtype != types::SectionType::NULL && h.shtype != types::SectionType::NOBITS
+                    && h.size != before[j]
+            })
+            .collect();
+        resized.sort_by_key(|&j| self.sections[j].header.offset);
+        for &j in &resized {
+            self.check_growth(j, before[j])?;
+        }
+        for &j in &resized {
+            self.resize_in_place(j, before[j])?;
+        }
+        Ok(())
+    }
+
+    fn check_growth(&self, i: usize, old_size: u64) -> Result<(), Error> {
+        let sec = &self.sections[i];
+        if !sec.header.flags.contains(types::SectionFlags::ALLOC) {
+            return Ok(());
+        }
+        let past = |addr: u64, size: u64| {
+            addr.checked_add(size)
+                .ok_or(Error::AddressOutOfRange("the address space", addr))
+        };
+        let start = past(sec.header.addr, old_size)?;
+        let end = past(sec.header.addr, sec.header.size)?;
+        for (j, other) in self.sections.iter().enumerate() {
+            let h = &other.header;
+            // .tbss has addresses, but only in the tls block of each thread
+            let tbss = h.shtype == types::SectionType::NOBITS
+                && h.flags.contains(types::SectionFlags::TLS);
+            if j == i || tbss || !h.flags.contains(types::SectionFlags::ALLOC) {
+                continue;
+            }
+            if h.addr < end && past(h.addr, h.size)? > start {
+                return Err(Error::SectionGrowsInto(sec.name.clone(), other.name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// section i was old_size long, move what is behind it in the file out of the way.
+    /// nothing changes if that would move anything past the largest offset
+    fn resize_in_place(&mut self, i: usize, old_size: u64) -> Result<(), Error> {
+        let h = self.sections[i].header.clone();
+        let beyond = || Error::SectionBeyondEndOfFile(h.offset, h.size);
+        let old_end = h.offset.checked_add(old_size).ok_or_else(beyond)?;
+        let new_end = h.offset.checked_add(h.size).ok_or_else(beyond)?;
+        let behind = |j: usize, sec: &Section| {
+            j != i && sec.header.shtype != types::SectionType::NULL && sec.header.offset >= old_end
+        };
+
+        let next = self.sections
+            .iter()
+            .enumerate()
+            .filter(|&(j, sec)| behind(j, sec) && sec.header.shtype != types::SectionType::NOBITS)
+            .map(|(_, sec)| sec.header.offset)
+            .chain(
+                self.segments
+                    .iter()
+                    .filter(|seg| seg.offset >= old_end && seg.offset != h.offset)
+                    .map(|seg| seg.offset),
+            )
+            .min();
+        let shift = match next {
+            Some(next) if new_end > next => {
+                let mut align = 1;
+                for (j, sec) in self.sections.iter().enumerate() {
+                    if behind(j, sec) {
+                        align = align.max(sec.header.addralign);
+                    }
+                }
+                for seg in &self.segments {
+                    if seg.offset >= old_end && seg.offset != h.offset {
+                        align = align.max(seg.align);
+                    }
+                }
+                (new_end - next).div_ceil(align).checked_mul(align).ok_or_else(beyond)?
+            }
+            _ => 0,
+        };
+        let moved = self.sections
+            .iter()
+            .enumerate()
+            .filter(|&(j, sec)| behind(j, sec))
+            .map(|(_, sec)| sec.header.offset.checked_add(sec.header.size))
+            .chain(
+                self.segments
+                    .iter()
+                    .filter(|seg| seg.offset >= old_end && seg.offset != h.offset)
+                    .map(|seg| seg.offset.checked_add(seg.filesz)),
+            )
+            .all(|end| end.and_then(|end| end.checked_add(shift)).is_some());
+        if !moved {
+            return Err(beyond());
+        }
+
+        for j in 0..self.sections.len() {
+            if behind(j, &self.sections[j]) {
+                self.sections[j].header.offset += shift;
+            }
+        }
+        let alloc = h.flags.contains(types::SectionFlags::ALLOC);
+        for seg in &mut self.segments {
+            if seg.offset >= old_end && seg.offset != h.offset {
+                seg.offset += shift;
+                continue;
+            }
+            if seg.offset > h.offset || old_end > seg.offset.saturating_add(seg.filesz) {
+                continue;
+            }
+            if seg.offset.saturating_add(seg.filesz) == old_end {
+                seg.filesz = new_end - seg.offset;
+            } else {
+                seg.filesz += shift;
+            }
+            if alloc {
+                let vend = seg.vaddr.saturating_add(seg.memsz);
+                if vend == h.addr + old_size || h.addr + h.size > vend {
+                    seg.memsz = h.addr + h.size - seg.vaddr;
+                }
+            }
+            seg.memsz = seg.memsz.max(seg.filesz);
+        }
+        Ok(())
+    }
+}
+
+impl Elf {
+    /**
+     * turn the raw content of a global offset table, like .got or .got.plt, into
+     * SectionContent::Got. the dynamic relocations of its slots become part of the entries.
+     * from then on sync_all and store_all make those relocations from the entries, in the
+     * relocation section got_relocation_section picks, so they follow when entries are added
+     * or the section moves.
+     */
+    pub fn load_got(&mut self, i: usize) -> Result<(), Error> {
+        if i >= self.sections.len() {
+            return Err(Error::SectionIndexOutOfRange(i));
+        }
+        let at = Elf::location(i, &self.sections[i]);
+        let word = GotEntry::entsize(&self.header);
+        let mut entries = match self.sections[i].content {
+            SectionContent::Got(_) => return Ok(()),
+            SectionContent::Raw(ref raw) => {
+                if raw.len() % word != 0 {
+                    let e = Error::InvalidGot("size is not a multiple of the pointer size");
+                    return Err(e.at(at));
+                }
+                let eh = &self.header;
+                let mut r = Vec::new();
+                for mut b in raw.chunks(word) {
+                    r.push(GotEntry::fixed(elf_read_uclass!(eh, b)?));
+                }
+                r
+            }
+            _ => return Err(Error::UnexpectedSectionContent.at(at)),
+        };
+
+        let addr = self.sections[i].header.addr;
+        let end = addr + (entries.len() * word) as u64;
+        let mut taken = Vec::new();
+        if let Some(t) = self.got_relocation_section(i) {
+            self.load_at(t)?;
+            if let Some(relocs) = self.sections[t].content.as_relocations() {
+                for r in relocs.iter().filter(|r| r.addr >= addr && r.addr < end) {
+                    if !(r.addr - addr).is_multiple_of(word as u64) {
+                        let e = Error::InvalidGot("relocation is not at the start of a slot");
+                        return Err(e.at(at));
+                    }
+                    let slot = &mut entries[(r.addr - addr) as usize / word];
+                    if slot.is_relocated() {
+                        return Err(Error::InvalidGot("slot has more than one relocation").at(at));
+                    }
+                    slot.sym = r.sym;
+                    slot.rtype = r.rtype.clone();
+                    slot.addend = r.addend;
+                    taken.push(r.clone());
+                }
+            }
+        }
+
+        let id = self.section_id(i);
+        self.got_relocations.insert(id, taken);
+        self.sections[i].content = SectionContent::Got(entries);
+        Ok(())
+    }
+
+    /// the relocation section for the dynamic relocations of the GOT at index i: the one
+    /// whose info is i, as for .rela.plt and .got.plt, otherwise .rela.dyn or .rel.dyn
+    pub fn got_relocation_section(&self, i: usize) -> Option<usize> {
+        let is_reloc = |s: &Section| {
+            s.header.shtype == types::SectionType::RELA
+                || s.header.shtype == types::SectionType::REL
+        };
+        self.sections
+            .iter()
+            .position(|s| is_reloc(s) && i > 0 && s.header.info as usize == i)
+            .or_else(|| {
+                self.sections
+                    .iter()
+                    .position(|s| is_reloc(s) && (s.name == ".rela.dyn" || s.name == ".rel.dyn"))
+            })
+    }
+
+    /// replace the relocations made for each Got section the last time with ones for its
+    /// current entries and address
+    fn sync_got_relocations(&mut self) -> Result<(), Error> {
+        for i in 0..self.sections.len() {
+            let addr = self.sections[i].header.addr;
+            let fresh: Vec<Relocation> = match self.sections[i].content {
+                SectionContent::Got(ref entries) => entries
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(j, e)| e.relocation(addr, j, &self.header))
+                    .collect(),
+                _ => continue,
+            };
+            let id = self.section_id(i);
+            let stale = self.got_relocations.remove(&id).unwrap_or_default();
+            let t = match self.got_relocation_section(i) {
+                Some(t) => t,
+                None if fresh.is_empty() => continue,
+                None => {
+                    let e = Error::SectionNotFound(String::from(".rela.dyn"));
+                    return Err(e.at(Elf::location(i, &self.sections[i])));
+                }
+            };
+            self.load_at(t)?;
+            let at = Elf::location(t, &self.sections[t]);
+            let relocs = match self.sections[t].content.as_relocations_mut() {
+                Some(relocs) => relocs,
+                None => return Err(Error::UnexpectedSectionContent.at(at)),
+            };
+
+            // the new ones go where the old ones were, so an unchanged table stays the same
+            let mut at = None;
+            for old in &stale {
+                let same = |r: &Relocation| {
+                    r.addr == old.addr && r.sym == old.sym && r.rtype == old.rtype
+                        && r.addend == old.addend
+                };
+                if let Some(k) = relocs.iter().position(same) {
+                    relocs.remove(k);
+                    at = Some(at.map_or(k, |at: usize| at.min(k)));
+                }
+            }
+            let at = at.unwrap_or(relocs.len());
+            relocs.splice(at..at, fresh.iter().cloned());
+            self.got_relocations.insert(id, fresh);
+        }
+        Ok(())
+    }
+}
+
+impl Elf {
+    /**
+     * add a relocation to the REL or RELA section at index i that names its target.
+     * it is resolved against the symbol table the section links to by the next sync_all or
+     * store_all, and appended to the section's relocations then
+     */
+    pub fn add_relocation(&mut self, i: usize, r: SymbolicRelocation) -> Result<(), Error> {
+        if i >= self.sections.len() {
+            return Err(Error::SectionIndexOutOfRange(i));
+        }
+        let id = self.section_id(i);
+        self.symbolic_relocations.entry(id).or_default().push(r);
+        self.stale = Stale {
+            sizes: true,
+            names: true,
+            offsets: true,
+        };
+        Ok(())
+    }
+
+    /// turn what add_relocation was given into relocations, in the order of the sections
+    /// and then in the order they were added. ones for removed sections are dropped
+    fn resolve_symbolic_relocations(&mut self) -> Result<(), Error> {
+        if self.symbolic_relocations.is_empty() {
+            return Ok(());
+        }
+        for i in 0..self.sections.len() {
+            let pending = match self.symbolic_relocations.remove(&self.sections[i].id) {
+                Some(pending) => pending,
+                None => continue,
+            };
+            self.load_at(i)?;
+            let at = Elf::location(i, &self.sections[i]);
+            let symtab = self.sections[i].header.link as usize;
+            if symtab == 0 || symtab >= self.sections.len() {
+                return Err(Error::MissingSymtabSection.at(at));
+            }
+            if self.sections[i].content.as_relocations().is_none() {
+                return Err(Error::UnexpectedSectionContent.at(at));
+            }
+            for r in pending {
+                let sym = match r.target {
+                    Target::Symbol(ref name) => self.named_symbol(symtab, name),
+                    Target::Section(ref name, _) => match self.sections
+                        .iter()
+                        .position(|s| &s.name == name)
+                    {
+                        Some(s) => self.section_symbol(symtab, s),
+                        None => Err(Error::SectionNotFound(name.clone())),
+                    },
+                };
+                let sym = sym.map_err(|e| e.at(at.clone()))?;
+                self.stats.symbols_resolved += 1;
+                let reloc = Relocation {
+                    addr: r.addr,
+                    sym,
+                    rtype: r.rtype.clone(),
+                    addend: r.resolved_addend(),
+                };
+                if let Some(relocs) = self.sections[i].content.as_relocations_mut() {
+                    relocs.push(reloc);
+                }
+            }
+        }
+        self.symbolic_relocations.clear();
+        Ok(())
+    }
+
+    fn symtab_symbols(&mut self, symtab: usize) -> Result<&mut Vec<Symbol>, Error> {
+        self.load_at(symtab)?;
+        let at = Elf::location(symtab, &self.sections[symtab]);
+        match self.sections[symtab].content.as_symbols_mut() {
+            Some(symbols) => Ok(symbols),
+            None => Err(Error::LinkedSectionIsNotSymtab.at(at)),
+        }
+    }
+
+    /// index of the symbol called name, appended as an undefined global if there is none
+    fn named_symbol(&mut self, symtab: usize, name: &str) -> Result<u32, Error> {
+        let symbols = self.symtab_symbols(symtab)?;
+        if let Some(k) = symbols.iter().skip(1).position(|s| s.name == name) {
+            return Ok(k as u32 + 1);
+        }
+        if symbols.is_empty() {
+            symbols.push(Symbol::default());
+        }
+        symbols.push(Symbol {
+            name: String::from(name),
+            bind: types::SymbolBind::GLOBAL,
+            ..Symbol::default()
+        });
+        Ok(symbols.len() as u32 - 1)
+    }
+
+    /**
+     * index of the STT_SECTION symbol of section s. if there is none it is inserted behind the
+     * other locals, and everything that counts the symbols of the table is renumbered to match:
+     * the relocations and groups that link to it, its sh_info and its SYMTAB_SHNDX entries
+     */
+    fn section_symbol(&mut self, symtab: usize, s: usize) -> Result<u32, Error> {
+        let is_section = |sym: &Symbol| {
+            sym.stype == types::SymbolType::SECTION
+                && sym.shndx == SymbolSectionIndex::Section(s as u32)
+        };
+        let k = {
+            let symbols = self.symtab_symbols(symtab)?;
+            if let Some(k) = symbols.iter().position(is_section) {
+                return Ok(k as u32);
+            }
+            if symbols.is_empty() {
+                symbols.push(Symbol::default());
+            }
+            let k = symbols
+                .iter()
+                .skip(1)
+                .position(|sym| sym.bind != types::SymbolBind::LOCAL)
+                .map_or(symbols.len(), |k| k + 1);
+            symbols.insert(
+                k,
+                Symbol {
+                    shndx: SymbolSectionIndex::Section(s as u32),
+                    stype: types::SymbolType::SECTION,
+                    bind: types::SymbolBind::LOCAL,
+                    ..Symbol::default()
+                },
+            );
+            k as u32
+        };
+        for j in 0..self.sections.len() {
+            let is_reloc = self.sections[j].header.shtype == types::SectionType::RELA
+                || self.sections[j].header.shtype == types::SectionType::REL;
+            if !is_reloc || self.sections[j].header.link as usize != symtab {
+                continue;
+            }
+            self.load_at(j)?;
+            if let Some(relocs) = self.sections[j].content.as_relocations_mut() {
+                for r in relocs.iter_mut().filter(|r| r.sym >= k) {
+                    r.sym += 1;
+                }
+            }
+        }
+        // the group signatures, and the count of locals, which now has one more
+        for (j, sec) in self.sections.iter_mut().enumerate() {
+            let h = &mut sec.header;
+            let group = h.shtype == types::SectionType::GROUP && h.link as usize == symtab;
+            if (group || j == symtab) && h.info >= k {
+                h.info += 1;
+            }
+        }
+        // sync_symtab_shndx only rewrites the entries when the table is synced again
+        if let Some(j) = self.symtab_shndx(symtab) {
+            self.load_at(j)?;
+            let mut entry = Vec::new();
+            {
+                let io = &mut entry;
+                let xindex = if s as u32 >= types::SHN_LORESERVE { s as u32 } else { 0 };
+                elf_write_u32!(&self.header, io, xindex)?;
+            }
+            let at = Elf::location(j, &self.sections[j]);
+            let sec = &mut self.sections[j];
+            match sec.content.as_raw_mut() {
+                Some(raw) => {
+                    let i = (k as usize * 4).min(raw.len());
+                    raw.splice(i..i, entry);
+                    sec.header.size = raw.len() as u64;
+                }
+                None => return Err(Error::UnexpectedSectionContent.at(at)),
+            }
+        }
+        Ok(k)
     }
 }
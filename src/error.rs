@@ -1,8 +1,14 @@
 use types;
+use section::SectionId;
+use io;
+#[cfg(feature = "std")]
+use std;
+use core::fmt;
+use prelude::*;
 
 #[derive(Debug)]
 pub enum Error {
-    Io(::std::io::Error),
+    Io(io::Error),
     InvalidMagic,
     InvalidIdentClass(u8),
     InvalidEndianness(u8),
@@ -19,21 +25,390 @@ pub enum Error {
     InvalidSymbolType(u8),
     InvalidSymbolBind(u8),
     InvalidSymbolVis(u8),
+    InvalidLocalEntryOffset(u64),
     InvalidDynamicType(u64),
     MissingShstrtabSection,
     LinkedSectionIsNotStrtab(&'static str),
     InvalidDynamicFlags1(u64),
     FirstSectionOffsetCanNotBeLargerThanAddress,
+    /// address of a PT_PHDR that no LOAD segment maps
+    PhdrNotInLoad(u64),
+    /// where the elf and program headers end, name and offset of the section they run into
+    ProgramHeadersOverlap(u64, String, u64),
     MissingSymtabSection,
     LinkedSectionIsNotSymtab,
     UnexpectedSectionContent,
     InvalidSymbolShndx(String, u16),
     DynsymInStaticLibrary,
     SymbolSectionIndexExtendedCannotBeWritten,
+    /// name of the symbol table, which has symbols in sections past SHN_LORESERVE
+    MissingSymtabShndxSection(String),
+    /// a count or index that doesn't fit into the elf header, but there is no section 0 to
+    /// hold it
+    NullSectionRequired(&'static str),
+    SectionNotFound(String),
+    EntrySymbolNotFound(String),
+    /// name of a section that ElfBuilder got relocations for, in a file that isn't ET_REL
+    RelocationsInLinkedFile(String),
+    SectionIdNotFound(SectionId),
+    /// an edit made what sync_all and store_all derive out of date, and neither ran since
+    OutOfSync(&'static str),
+    SectionIndexOutOfRange(usize),
+    SegmentIndexOutOfRange(usize),
+    /// virtual address that no segment maps to the content of a section
+    UnmappedAddress(u64),
+    /// section name, file offset, virtual address
+    SectionOffsetBeyondAddress(String, u64, u64),
+    /// section name, file offset
+    SectionOffsetUnreachable(String, u64),
+    /// section name, file offset, end of the previous section
+    SectionOverlap(String, u64, u64),
+    /// section name, name of the section at the addresses it would grow into
+    SectionGrowsInto(String, String),
+    /// section name, the address it is pinned to, end of the sections before it
+    SectionAddressTaken(String, u64, u64),
+    /// region name
+    MemoryRegionNotFound(String),
+    /// region name, how many bytes past its end the sections in it reach
+    MemoryRegionOverflow(String, u64),
+    /// output format, an address that it can't express
+    AddressOutOfRange(&'static str, u64),
+    /// a field of the file, a value that doesn't fit in it
+    FieldOverflow(&'static str, u64),
+    SymhashWithoutSymbols,
+    /// what is wrong with a SHT_HASH section
+    InvalidSymhash(&'static str),
+    /// name of a symbol that a lookup through the hash section doesn't find
+    SymbolNotInSymhash(String),
+    /// what is wrong with a global offset table
+    InvalidGot(&'static str),
+    InvalidRelocationType(u32),
+    UnsupportedRelocation(u32),
+    /// relocation type, value that does not fit
+    RelocationOverflow(u32, i64),
+    /// relocation type, value that is not aligned as the field requires
+    RelocationMisaligned(u32, i64),
+    /// the place of a relocation is too short for its field
+    RelocationOutOfBounds,
+    InvalidSectionLink(u32),
+    InvalidAttributes(&'static str),
+    /// vendor and tag of a build attribute that has incompatible values in two objects
+    IncompatibleAttributes(String, u64),
+    /// what two objects have e_flags that can't be linked together for, see MachineFlags::merge
+    IncompatibleMachineFlags(&'static str),
+    /// EI_OSABI of two objects that can't be linked together
+    IncompatibleAbi(types::Abi, types::Abi),
+    /// name of a symbol, what about it the os abi of the file doesn't have, and the abi
+    UnsupportedByAbi(String, &'static str, types::Abi),
+    InvalidLinkerScript(&'static str),
+    /// name of an input with code for link time optimization instead of machine code,
+    /// and what kind, see linker::Lto
+    LtoObject(String, &'static str),
+    /// what is wrong with a .note.gnu.property section
+    InvalidProperties(&'static str),
+    /// what is wrong with a section of notes
+    InvalidNote(&'static str),
+    /// what is wrong with a .gnu_debuglink section
+    InvalidDebuglink(&'static str),
+    InvalidStrtab(&'static str),
+    /// offset of a name that is not inside the string table
+    InvalidStrtabOffset(u32),
+    /// name of an elf header field that doesn't have the value it must have
+    InvalidHeaderField(&'static str),
+    InvalidNullSection,
+    InvalidSectionInfo(u32),
+    /// found, expected
+    InvalidSectionEntsize(u64, u64),
+    /// symbol name, section index
+    InvalidSymbolSection(String, u32),
+    /// sh_info of a symbol table, which isn't the index of the first non local symbol
+    InvalidSymtabInfo(u32),
+    /// symbol index, number of symbols in the linked table
+    InvalidRelocationSymbol(u32, usize),
+    /// index of a section that links to a section which, maybe indirectly, links back to it
+    SectionLinkCycle(u32),
+    /// file offset, size
+    SectionBeyondEndOfFile(u64, u64),
+    /// what was limited by ElfOptions, the value the file asked for
+    LimitExceeded(&'static str, u64),
+    /// what is wrong with an ar archive read by scan or written by Archive
+    InvalidArchive(&'static str),
+    /// an archive member that couldn't be indexed, and why
+    ArchiveMember(String, Box<Error>),
+    /// a panic, which is a bug in elfkit, caught where it would unwind into C. its message
+    Panicked(String),
+    /// an error that happened while handling a specific part of the file
+    At(Location, Box<Error>),
 }
 
-impl From<::std::io::Error> for Error {
-    fn from(error: ::std::io::Error) -> Self {
+/// where in the file an error happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    /// name is empty if the section names have not been resolved yet
+    Section {
+        index: usize,
+        name: String,
+        offset: u64,
+    },
+    Segment { index: usize, offset: u64 },
+}
+
+impl Error {
+    /// attach a location to an error.
+    /// errors that already carry a location keep the innermost one, since it's the most precise
+    pub fn at(self, location: Location) -> Error {
+        match self {
+            Error::At(..) => self,
+            e => Error::At(location, Box::new(e)),
+        }
+    }
+
+    /// the error without any location wrapped around it
+    pub fn root_cause(&self) -> &Error {
+        match self {
+            &Error::At(_, ref e) | &Error::ArchiveMember(_, ref e) => e.root_cause(),
+            e => e,
+        }
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            &Error::At(ref l, _) => Some(l),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
         Error::Io(error)
     }
 }
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Location::Section {
+                index,
+                ref name,
+                offset,
+            } => {
+                if name.is_empty() {
+                    write!(f, "section {} at offset 0x{:x}", index, offset)
+                } else {
+                    write!(f, "section {} '{}' at offset 0x{:x}", index, name, offset)
+                }
+            }
+            &Location::Segment { index, offset } => {
+                write!(f, "segment {} at offset 0x{:x}", index, offset)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Error::Io(ref e) => write!(f, "i/o error: {}", e),
+            &Error::InvalidMagic => write!(f, "not an elf file: invalid magic"),
+            &Error::InvalidIdentClass(v) => write!(f, "invalid elf class {}", v),
+            &Error::InvalidEndianness(v) => write!(f, "invalid endianness {}", v),
+            &Error::InvalidIdentVersion(v) => write!(f, "invalid ident version {}", v),
+            &Error::InvalidVersion(v) => write!(f, "invalid elf version {}", v),
+            &Error::InvalidAbi(v) => write!(f, "invalid os abi {}", v),
+            &Error::InvalidElfType(v) => write!(f, "invalid elf type {}", v),
+            &Error::InvalidMachineType(v) => write!(f, "invalid machine type {}", v),
+            &Error::InvalidHeaderFlags(v) => write!(f, "invalid header flags 0x{:x}", v),
+            &Error::InvalidSectionFlags(v) => write!(f, "invalid section flags 0x{:x}", v),
+            &Error::InvalidSegmentType(v) => write!(f, "invalid segment type 0x{:x}", v),
+            &Error::InvalidSectionType(v) => write!(f, "invalid section type 0x{:x}", v),
+            &Error::UnsupportedMachineTypeForRelocation(ref m) => {
+                write!(f, "relocations are not supported for machine {:?}", m)
+            }
+            &Error::InvalidSymbolType(v) => write!(f, "invalid symbol type {}", v),
+            &Error::InvalidSymbolBind(v) => write!(f, "invalid symbol binding {}", v),
+            &Error::InvalidSymbolVis(v) => write!(f, "invalid symbol visibility {}", v),
+            &Error::InvalidLocalEntryOffset(v) => write!(f, "invalid local entry offset {}", v),
+            &Error::InvalidDynamicType(v) => write!(f, "invalid dynamic tag 0x{:x}", v),
+            &Error::MissingShstrtabSection => write!(f, "missing section name table"),
+            &Error::LinkedSectionIsNotStrtab(what) => {
+                write!(f, "section linked from {} is not a string table", what)
+            }
+            &Error::InvalidDynamicFlags1(v) => write!(f, "invalid DT_FLAGS_1 0x{:x}", v),
+            &Error::FirstSectionOffsetCanNotBeLargerThanAddress => write!(
+                f,
+                "the offset of the first section can not be larger than its address"
+            ),
+            &Error::PhdrNotInLoad(addr) => {
+                write!(f, "PT_PHDR at 0x{:x} is not inside a LOAD segment", addr)
+            }
+            &Error::ProgramHeadersOverlap(end, ref name, offset) => write!(
+                f,
+                "the program headers end at 0x{:x}, past the start of section '{}' at 0x{:x}",
+                end, name, offset
+            ),
+            &Error::MissingSymtabSection => write!(f, "missing symbol table"),
+            &Error::LinkedSectionIsNotSymtab => write!(f, "linked section is not a symbol table"),
+            &Error::UnexpectedSectionContent => write!(f, "unexpected section content"),
+            &Error::InvalidSymbolShndx(ref name, v) => {
+                write!(f, "symbol '{}' has invalid section index {}", name, v)
+            }
+            &Error::DynsymInStaticLibrary => write!(f, "dynamic symbol in static library"),
+            &Error::SymbolSectionIndexExtendedCannotBeWritten => {
+                write!(f, "extended symbol section indices can not be written")
+            }
+            &Error::MissingSymtabShndxSection(ref name) => write!(
+                f,
+                "symbol table '{}' needs extended section indices but has no SYMTAB_SHNDX section",
+                name
+            ),
+            &Error::NullSectionRequired(what) => {
+                write!(f, "{} doesn't fit into the elf header and there is no section 0", what)
+            }
+            &Error::SectionNotFound(ref name) => write!(f, "section '{}' not found", name),
+            &Error::EntrySymbolNotFound(ref name) => {
+                write!(f, "entry symbol '{}' not found", name)
+            }
+            &Error::RelocationsInLinkedFile(ref name) => write!(
+                f,
+                "section '{}' has relocations, which only a relocatable object can have",
+                name
+            ),
+            &Error::SectionIdNotFound(id) => write!(f, "no section with {:?}", id),
+            &Error::OutOfSync(what) => {
+                write!(f, "{} changed since the last sync_all or store_all", what)
+            }
+            &Error::SectionIndexOutOfRange(i) => write!(f, "section index {} out of range", i),
+            &Error::SegmentIndexOutOfRange(i) => write!(f, "segment index {} out of range", i),
+            &Error::UnmappedAddress(v) => {
+                write!(f, "virtual address 0x{:x} is not mapped from a section", v)
+            }
+            &Error::SectionOffsetBeyondAddress(ref name, offset, addr) => write!(
+                f,
+                "section '{}' at offset 0x{:x} is beyond its address 0x{:x}",
+                name,
+                offset,
+                addr
+            ),
+            &Error::SectionOffsetUnreachable(ref name, offset) => write!(
+                f,
+                "can not seek to offset 0x{:x} of section '{}'",
+                offset,
+                name
+            ),
+            &Error::SectionOverlap(ref name, offset, end) => write!(
+                f,
+                "section '{}' at offset 0x{:x} overlaps the previous section ending at 0x{:x}",
+                name,
+                offset,
+                end
+            ),
+            &Error::SectionGrowsInto(ref name, ref other) => write!(
+                f,
+                "section '{}' can not grow into the addresses of '{}'",
+                name,
+                other
+            ),
+            &Error::SectionAddressTaken(ref name, addr, end) => write!(
+                f,
+                "section '{}' can not be placed at 0x{:x}, the sections before it end at 0x{:x}",
+                name,
+                addr,
+                end
+            ),
+            &Error::MemoryRegionNotFound(ref name) => write!(f, "no memory region {}", name),
+            &Error::MemoryRegionOverflow(ref name, n) => {
+                write!(f, "region {} overflowed by {} bytes", name, n)
+            }
+            &Error::AddressOutOfRange(format, addr) => {
+                write!(f, "address 0x{:x} does not fit in {}", addr, format)
+            }
+            &Error::FieldOverflow(field, v) => write!(f, "0x{:x} does not fit in {}", v, field),
+            &Error::SymhashWithoutSymbols => write!(f, "can not hash an empty symbol table"),
+            &Error::InvalidSymhash(what) => write!(f, "invalid hash section: {}", what),
+            &Error::InvalidGot(what) => write!(f, "invalid global offset table: {}", what),
+            &Error::SymbolNotInSymhash(ref name) => {
+                write!(f, "symbol '{}' can not be found through the hash section", name)
+            }
+            &Error::InvalidRelocationType(v) => write!(f, "unknown relocation type {}", v),
+            &Error::UnsupportedRelocation(v) => write!(f, "can not apply relocation type {}", v),
+            &Error::RelocationOverflow(t, v) => {
+                write!(f, "value 0x{:x} does not fit relocation type {}", v, t)
+            }
+            &Error::RelocationMisaligned(t, v) => {
+                write!(f, "value 0x{:x} is misaligned for relocation type {}", v, t)
+            }
+            &Error::RelocationOutOfBounds => write!(f, "relocation field is out of bounds"),
+            &Error::InvalidSectionLink(v) => write!(f, "link to section {} out of range", v),
+            &Error::InvalidAttributes(what) => write!(f, "invalid build attributes: {}", what),
+            &Error::IncompatibleAttributes(ref vendor, tag) => {
+                write!(f, "objects disagree on {} build attribute {}", vendor, tag)
+            }
+            &Error::IncompatibleMachineFlags(what) => {
+                write!(f, "objects disagree on {} in e_flags", what)
+            }
+            &Error::IncompatibleAbi(ref a, ref b) => {
+                write!(f, "objects for os abi {:?} and {:?} can't be linked together", a, b)
+            }
+            &Error::UnsupportedByAbi(ref name, what, ref abi) => {
+                write!(f, "symbol {} is {}, which os abi {:?} doesn't have", name, what, abi)
+            }
+            &Error::InvalidLinkerScript(what) => write!(f, "invalid linker script: {}", what),
+            &Error::LtoObject(ref name, what) => write!(
+                f,
+                "{} is {}, which has to be compiled to a native object before it can be linked",
+                name, what
+            ),
+            &Error::InvalidProperties(what) => write!(f, "invalid gnu property note: {}", what),
+            &Error::InvalidNote(what) => write!(f, "invalid note: {}", what),
+            &Error::InvalidDebuglink(what) => write!(f, "invalid .gnu_debuglink: {}", what),
+            &Error::InvalidStrtab(what) => write!(f, "invalid string table: {}", what),
+            &Error::InvalidStrtabOffset(v) => {
+                write!(f, "name at offset {} is outside the string table", v)
+            }
+            &Error::InvalidHeaderField(name) => write!(f, "invalid {} in the elf header", name),
+            &Error::InvalidNullSection => write!(f, "section 0 is not an empty NULL section"),
+            &Error::InvalidSectionInfo(v) => write!(f, "info link to section {} out of range", v),
+            &Error::InvalidSectionEntsize(v, expected) => {
+                write!(f, "entry size {} should be {}", v, expected)
+            }
+            &Error::InvalidSymbolSection(ref name, v) => {
+                write!(f, "symbol '{}' is in section {}, which doesn't exist", name, v)
+            }
+            &Error::InvalidSymtabInfo(v) => write!(
+                f,
+                "sh_info {} is not the index of the first non local symbol",
+                v
+            ),
+            &Error::InvalidRelocationSymbol(v, n) => write!(
+                f,
+                "relocation refers to symbol {}, but the symbol table has {}",
+                v,
+                n
+            ),
+            &Error::SectionLinkCycle(v) => write!(f, "section links loop back to section {}", v),
+            &Error::SectionBeyondEndOfFile(offset, size) => write!(
+                f,
+                "content at offset 0x{:x} with size 0x{:x} is past the end of the file",
+                offset,
+                size
+            ),
+            &Error::LimitExceeded(what, v) => write!(f, "{} {} exceeds the limit", what, v),
+            &Error::InvalidArchive(what) => write!(f, "invalid ar archive: {}", what),
+            &Error::ArchiveMember(ref name, ref e) => write!(f, "archive member {}: {}", name, e),
+            &Error::Panicked(ref msg) => write!(f, "bug in elfkit: {}", msg),
+            &Error::At(ref location, ref e) => write!(f, "{}: {}", location, e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            &Error::Io(ref e) => Some(e),
+            &Error::At(_, ref e) | &Error::ArchiveMember(_, ref e) => Some(&**e),
+            _ => None,
+        }
+    }
+}
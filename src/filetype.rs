@@ -1,38 +1,100 @@
+use std::fmt;
 use std::io::{Read, Result, Seek, SeekFrom};
+use ldscript;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum FileType {
     Archive,
     Elf,
+    /// a text linker script, see ldscript
+    LinkerScript,
+    /// raw or wrapped, as produced by -flto
+    LlvmBitcode,
+    /// thin, or a fat binary with several architectures
+    MachO,
+    /// a windows executable or dll, starting with the MZ dos header
+    Pe,
+    Compressed(Compression),
     Unknown,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const ARCHIVE_MAGIC: &[u8] = b"!<arch>\n";
+const BITCODE_MAGIC: &[u8] = b"BC\xc0\xde";
+const BITCODE_WRAPPER_MAGIC: &[u8] = b"\xde\xc0\x17\x0b";
+const MACHO_MAGICS: &[&[u8]] = &[
+    b"\xfe\xed\xfa\xce",
+    b"\xce\xfa\xed\xfe",
+    b"\xfe\xed\xfa\xcf",
+    b"\xcf\xfa\xed\xfe",
+    // fat binaries. java class files share this one, but nobody links those
+    b"\xca\xfe\xba\xbe",
+];
+const COMPRESSION_MAGICS: &[(&[u8], Compression)] = &[
+    (b"\x1f\x8b", Compression::Gzip),
+    (b"BZh", Compression::Bzip2),
+    (b"\xfd7zXZ\x00", Compression::Xz),
+    (b"\x28\xb5\x2f\xfd", Compression::Zstd),
+];
 
 pub fn filetype<T>(mut io: T) -> Result<FileType>
 where
     T: Read + Seek,
 {
     io.seek(SeekFrom::Start(0))?;
-    let mut magic = [0; 8];
-    io.read(&mut magic)?;
+    let mut head = Vec::new();
+    (&mut io).take(4096).read_to_end(&mut head)?;
     io.seek(SeekFrom::Start(0))?;
 
-    if magic[0..4] == [0x7F, 'E' as u8, 'L' as u8, 'F' as u8] {
+    if head.starts_with(ELF_MAGIC) {
         return Ok(FileType::Elf);
     }
-
-    if magic
-        == [
-            '!' as u8,
-            '<' as u8,
-            'a' as u8,
-            'r' as u8,
-            'c' as u8,
-            'h' as u8,
-            '>' as u8,
-            0x0A,
-        ] {
+    if head.starts_with(ARCHIVE_MAGIC) {
         return Ok(FileType::Archive);
     }
+    if head.starts_with(BITCODE_MAGIC) || head.starts_with(BITCODE_WRAPPER_MAGIC) {
+        return Ok(FileType::LlvmBitcode);
+    }
+    if MACHO_MAGICS.iter().any(|m| head.starts_with(m)) {
+        return Ok(FileType::MachO);
+    }
+    if head.starts_with(b"MZ") {
+        return Ok(FileType::Pe);
+    }
+    for &(magic, compression) in COMPRESSION_MAGICS {
+        if head.starts_with(magic) {
+            return Ok(FileType::Compressed(compression));
+        }
+    }
+    if ldscript::is_linker_script(&head) {
+        return Ok(FileType::LinkerScript);
+    }
 
-    return Ok(FileType::Unknown);
+    Ok(FileType::Unknown)
+}
+
+/// what the file is, and for the ones that can't be read here, why not
+impl fmt::Display for FileType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &FileType::Archive => write!(f, "ar archive"),
+            &FileType::Elf => write!(f, "elf file"),
+            &FileType::LinkerScript => write!(f, "linker script"),
+            &FileType::LlvmBitcode => {
+                write!(f, "LLVM bitcode, LTO objects have to be compiled to native ones first")
+            }
+            &FileType::MachO => write!(f, "Mach-O object, wrong platform"),
+            &FileType::Pe => write!(f, "PE/COFF object, wrong platform"),
+            &FileType::Compressed(c) => write!(f, "{:?} compressed file, decompress it first", c),
+            &FileType::Unknown => write!(f, "unknown file type"),
+        }
+    }
 }
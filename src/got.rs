@@ -0,0 +1,76 @@
+use io::Write;
+use {types, Error, Header};
+use relocation::{Relocation, RelocationType};
+
+/**
+ * one slot of a global offset table, see SectionContent::Got.
+ *
+ * sync_all and store_all turn the entries into relocations for the dynamic linker, so
+ * the slots and their relocations can't get out of step the way raw bytes and a separate
+ * relocation section do.
+ */
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GotEntry {
+    /// index of the symbol in the dynamic symbol table, 0 for none
+    pub sym: u32,
+    /// the dynamic relocation that fills the slot in at load time, like R_X86_64_GLOB_DAT.
+    /// type 0, which is NONE on every machine, for slots that are final in the file
+    pub rtype: RelocationType,
+    pub addend: i64,
+    /// what sits in the slot in the file, like the address of the lazy binding stub of a
+    /// JUMP_SLOT or the address of a symbol resolved at link time
+    pub value: u64,
+}
+
+impl GotEntry {
+    /// a slot that is complete at link time and isn't relocated
+    pub fn fixed(value: u64) -> GotEntry {
+        GotEntry {
+            value,
+            ..GotEntry::default()
+        }
+    }
+
+    /// a slot the dynamic linker fills in, holding the addend until then
+    pub fn relocated(sym: u32, rtype: RelocationType, addend: i64) -> GotEntry {
+        GotEntry {
+            sym,
+            rtype,
+            addend,
+            value: addend as u64,
+        }
+    }
+
+    /// size of a slot, which holds a pointer
+    pub fn entsize(eh: &Header) -> usize {
+        match eh.ident_class {
+            types::Class::Class64 => 8,
+            types::Class::Class32 => 4,
+        }
+    }
+
+    pub fn is_relocated(&self) -> bool {
+        self.rtype.to_u32() != 0
+    }
+
+    /// the relocation for the slot, if it has one, when the table is at got_addr
+    pub fn relocation(&self, got_addr: u64, index: usize, eh: &Header) -> Option<Relocation> {
+        if !self.is_relocated() {
+            return None;
+        }
+        Some(Relocation {
+            addr: got_addr + (index * GotEntry::entsize(eh)) as u64,
+            sym: self.sym,
+            rtype: self.rtype.clone(),
+            addend: self.addend,
+        })
+    }
+
+    pub fn to_writer<W>(&self, mut io: W, eh: &Header) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        elf_write_uclass!(eh, io, self.value)?;
+        Ok(())
+    }
+}
@@ -1,10 +1,10 @@
 use num_traits::{FromPrimitive, ToPrimitive};
-use std::io::{Read, Write};
-use std::io::BufWriter;
+use io::{Read, Write};
+use io::BufWriter;
 use error::Error;
 use types;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Header {
     pub ident_magic: [u8; 4],
     pub ident_class: types::Class,
@@ -113,11 +113,7 @@ impl Header {
         r.shoff = elf_read_uclass!(r, io)?;
 
         let reb = elf_read_u32!(r, io)?;
-        r.flags = types::HeaderFlags::from_bits_truncate(reb);
-        //r.flags = match types::HeaderFlags::from_bits(reb) {
-        //    Some(v) => v,
-        //    None => return Err(Error::InvalidHeaderFlags(reb)),
-        //};
+        r.flags = types::HeaderFlags::from_bits_retain(reb);
 
         r.ehsize = elf_read_u16!(r, io)?;
         r.phentsize = elf_read_u16!(r, io)?;
@@ -129,17 +125,23 @@ impl Header {
         Ok(r)
     }
 
+    /// parse the header from the first 64 bytes of a file, without allocating.
+    /// 64 is the size of a 64bit header, a 32bit one only uses the first 52
+    pub fn peek(b: &[u8; 64]) -> Result<Header, Error> {
+        Header::from_reader(&mut &b[..])
+    }
+
     pub fn to_writer<R>(&self, io: &mut R) -> Result<(), Error>
     where
         R: Write,
     {
         let mut w = BufWriter::new(io);
-        w.write(&self.ident_magic)?;
-        w.write(&[self.ident_class.to_u8().unwrap()])?;
-        w.write(&[self.ident_endianness.to_u8().unwrap()])?;
-        w.write(&[self.ident_version.to_u8().unwrap()])?;
-        w.write(&[self.ident_abi.to_u8().unwrap()])?;
-        w.write(&[0; 8])?;
+        w.write_all(&self.ident_magic)?;
+        w.write_all(&[self.ident_class.to_u8().unwrap()])?;
+        w.write_all(&[self.ident_endianness.to_u8().unwrap()])?;
+        w.write_all(&[self.ident_version.to_u8().unwrap()])?;
+        w.write_all(&[self.ident_abi.to_u8().unwrap()])?;
+        w.write_all(&[0; 8])?;
 
         elf_write_u16!(self, w, self.etype.to_u16().unwrap())?;
         elf_write_u16!(self, w, self.machine.to_u16().unwrap())?;
@@ -164,4 +166,366 @@ impl Header {
             types::Class::Class64 => 8 + 8 + 8,
         } + 4 + 2 + 2 + 2 + 2 + 2 + 2
     }
+
+    /// version of the ARM EABI from the top byte of e_flags, None for other machines or
+    /// files predating the EABI
+    pub fn arm_eabi_version(&self) -> Option<u8> {
+        if self.machine != types::Machine::ARM {
+            return None;
+        }
+        match (self.flags.bits() & types::HeaderFlags::ARM_EABI_MASK.bits()) >> 24 {
+            0 => None,
+            v => Some(v as u8),
+        }
+    }
+}
+
+/// the float abi of an arm file, from EF_ARM_ABI_FLOAT_HARD and EF_ARM_ABI_FLOAT_SOFT
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArmFloat {
+    /// neither flag, like in files predating EABI version 5
+    Unspecified,
+    Soft,
+    Hard,
+}
+
+/// how a risc-v file passes floating point arguments, from EF_RISCV_FLOAT_ABI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RiscvFloatAbi {
+    Soft,
+    Single,
+    Double,
+    Quad,
+}
+
+/// the calling convention of a mips file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MipsAbi {
+    O32,
+    O64,
+    Eabi32,
+    Eabi64,
+    N32,
+    N64,
+    /// a value of EF_MIPS_ABI that has no name
+    Unknown(u8),
+}
+
+/// the instruction set level of a mips file, from EF_MIPS_ARCH
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MipsArch {
+    Mips1,
+    Mips2,
+    Mips3,
+    Mips4,
+    Mips5,
+    Mips32,
+    Mips64,
+    Mips32R2,
+    Mips64R2,
+    Mips32R6,
+    Mips64R6,
+    Unknown(u8),
+}
+
+/// each level with the instructions it has as a set of bits, lowest level first.
+/// R6 removed instructions, so it doesn't contain the levels before it
+const MIPS_ISAS: [(MipsArch, u32); 11] = [
+    (MipsArch::Mips1, 0x1),
+    (MipsArch::Mips2, 0x3),
+    (MipsArch::Mips3, 0x7),
+    (MipsArch::Mips32, 0x23),
+    (MipsArch::Mips4, 0xf),
+    (MipsArch::Mips5, 0x1f),
+    (MipsArch::Mips32R2, 0x63),
+    (MipsArch::Mips64, 0x3f),
+    (MipsArch::Mips64R2, 0x7f),
+    (MipsArch::Mips32R6, 0x80),
+    (MipsArch::Mips64R6, 0x180),
+];
+
+impl MipsArch {
+    fn from_bits(v: u8) -> MipsArch {
+        match v {
+            0 => MipsArch::Mips1,
+            1 => MipsArch::Mips2,
+            2 => MipsArch::Mips3,
+            3 => MipsArch::Mips4,
+            4 => MipsArch::Mips5,
+            5 => MipsArch::Mips32,
+            6 => MipsArch::Mips64,
+            7 => MipsArch::Mips32R2,
+            8 => MipsArch::Mips64R2,
+            9 => MipsArch::Mips32R6,
+            10 => MipsArch::Mips64R6,
+            v => MipsArch::Unknown(v),
+        }
+    }
+
+    fn bits(self) -> u8 {
+        match self {
+            MipsArch::Mips1 => 0,
+            MipsArch::Mips2 => 1,
+            MipsArch::Mips3 => 2,
+            MipsArch::Mips4 => 3,
+            MipsArch::Mips5 => 4,
+            MipsArch::Mips32 => 5,
+            MipsArch::Mips64 => 6,
+            MipsArch::Mips32R2 => 7,
+            MipsArch::Mips64R2 => 8,
+            MipsArch::Mips32R6 => 9,
+            MipsArch::Mips64R6 => 10,
+            MipsArch::Unknown(v) => v,
+        }
+    }
+
+    /// the lowest level that runs code of both, like mips64 for mips3 and mips32 objects
+    fn merge(self, other: MipsArch) -> Option<MipsArch> {
+        if self == other {
+            return Some(self);
+        }
+        let isa = |arch| MIPS_ISAS.iter().find(|&&(a, _)| a == arch).map(|&(_, isa)| isa);
+        let both = isa(self)? | isa(other)?;
+        MIPS_ISAS.iter().find(|&&(_, isa)| isa & both == both).map(|&(a, _)| a)
+    }
+}
+
+/**
+ * e_flags decoded for the machines whose objects can't all be linked together.
+ * Header::machine_flags decodes them and Header::set_machine_flags writes them back,
+ * keeping the bits that aren't decoded. merge is what a linker checks for each input
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MachineFlags {
+    Arm {
+        /// version of the EABI, None for files predating it
+        eabi: Option<u8>,
+        float: ArmFloat,
+        /// BE8 code, byte invariant data with little endian instructions
+        be8: bool,
+    },
+    RiscV {
+        float_abi: RiscvFloatAbi,
+        rvc: bool,
+        rve: bool,
+        tso: bool,
+    },
+    Mips {
+        arch: MipsArch,
+        abi: MipsAbi,
+        /// position independent code
+        pic: bool,
+        /// code that calls position independent code the standard way
+        cpic: bool,
+        noreorder: bool,
+        nan2008: bool,
+        fp64: bool,
+    },
+    /// any other machine, with e_flags as they are
+    Other(u32),
+}
+
+impl MachineFlags {
+    /**
+     * combine the flags of an input with those of the inputs before, like ld does it.
+     * machines, arm EABI versions and float abis must be the same, as must risc-v float abis
+     * and RVE, and mips abis, NaN encodings and FP64 modes. mips code is only position
+     * independent if all of it is, and gets the lowest level that runs all of it.
+     * other machines must have the same e_flags.
+     * the first thing that differs is an Error::IncompatibleMachineFlags
+     */
+    pub fn merge(&mut self, other: &MachineFlags) -> Result<(), Error> {
+        let fail = |what| Err(Error::IncompatibleMachineFlags(what));
+        match (self, other) {
+            (
+                &mut MachineFlags::Arm {
+                    eabi,
+                    ref mut float,
+                    ref mut be8,
+                },
+                &MachineFlags::Arm {
+                    eabi: eabi2,
+                    float: float2,
+                    be8: be8_2,
+                },
+            ) => {
+                if eabi != eabi2 {
+                    return fail("the arm EABI version");
+                }
+                match (*float, float2) {
+                    (ArmFloat::Unspecified, f) => *float = f,
+                    (_, ArmFloat::Unspecified) => {}
+                    (a, b) if a != b => return fail("the arm float abi"),
+                    _ => {}
+                }
+                *be8 |= be8_2;
+            }
+            (
+                &mut MachineFlags::RiscV {
+                    float_abi,
+                    ref mut rvc,
+                    rve,
+                    ref mut tso,
+                },
+                &MachineFlags::RiscV {
+                    float_abi: float_abi2,
+                    rvc: rvc2,
+                    rve: rve2,
+                    tso: tso2,
+                },
+            ) => {
+                if float_abi != float_abi2 {
+                    return fail("the risc-v float abi");
+                }
+                if rve != rve2 {
+                    return fail("risc-v RVE");
+                }
+                *rvc |= rvc2;
+                *tso |= tso2;
+            }
+            (
+                &mut MachineFlags::Mips {
+                    ref mut arch,
+                    abi,
+                    ref mut pic,
+                    ref mut cpic,
+                    ref mut noreorder,
+                    nan2008,
+                    fp64,
+                },
+                &MachineFlags::Mips {
+                    arch: arch2,
+                    abi: abi2,
+                    pic: pic2,
+                    cpic: cpic2,
+                    noreorder: noreorder2,
+                    nan2008: nan2008_2,
+                    fp64: fp64_2,
+                },
+            ) => {
+                if abi != abi2 {
+                    return fail("the mips abi");
+                }
+                if nan2008 != nan2008_2 {
+                    return fail("the mips NaN encoding");
+                }
+                if fp64 != fp64_2 {
+                    return fail("the mips FP64 mode");
+                }
+                *arch = match arch.merge(arch2) {
+                    Some(a) => a,
+                    None => return fail("the mips isa level"),
+                };
+                *pic &= pic2;
+                *cpic &= cpic2;
+                *noreorder |= noreorder2;
+            }
+            (&mut MachineFlags::Other(a), &MachineFlags::Other(b)) if a == b => {}
+            (&mut MachineFlags::Other(_), &MachineFlags::Other(_)) => return fail("e_flags"),
+            _ => return fail("the machine"),
+        }
+        Ok(())
+    }
+}
+
+impl Header {
+    /// e_flags, decoded for the machine
+    pub fn machine_flags(&self) -> MachineFlags {
+        let bits = self.flags.bits();
+        let has = |f: types::HeaderFlags| bits & f.bits() == f.bits();
+        match self.machine {
+            types::Machine::ARM => MachineFlags::Arm {
+                eabi: self.arm_eabi_version(),
+                float: if has(types::HeaderFlags::ARM_ABI_FLOAT_HARD) {
+                    ArmFloat::Hard
+                } else if has(types::HeaderFlags::ARM_ABI_FLOAT_SOFT) {
+                    ArmFloat::Soft
+                } else {
+                    ArmFloat::Unspecified
+                },
+                be8: has(types::HeaderFlags::ARM_BE8),
+            },
+            types::Machine::RISCV => MachineFlags::RiscV {
+                float_abi: match bits & types::HeaderFlags::RISCV_FLOAT_ABI.bits() {
+                    0 => RiscvFloatAbi::Soft,
+                    2 => RiscvFloatAbi::Single,
+                    4 => RiscvFloatAbi::Double,
+                    _ => RiscvFloatAbi::Quad,
+                },
+                rvc: has(types::HeaderFlags::RISCV_RVC),
+                rve: has(types::HeaderFlags::RISCV_RVE),
+                tso: has(types::HeaderFlags::RISCV_TSO),
+            },
+            types::Machine::MIPS => MachineFlags::Mips {
+                arch: MipsArch::from_bits((bits >> 28) as u8),
+                abi: match (bits & types::HeaderFlags::MIPS_ABI.bits()) >> 12 {
+                    _ if has(types::HeaderFlags::MIPS_ABI2) => MipsAbi::N32,
+                    0 if self.ident_class == types::Class::Class64 => MipsAbi::N64,
+                    0 | 1 => MipsAbi::O32,
+                    2 => MipsAbi::O64,
+                    3 => MipsAbi::Eabi32,
+                    4 => MipsAbi::Eabi64,
+                    v => MipsAbi::Unknown(v as u8),
+                },
+                pic: has(types::HeaderFlags::MIPS_PIC),
+                cpic: has(types::HeaderFlags::MIPS_CPIC),
+                noreorder: has(types::HeaderFlags::MIPS_NOREORDER),
+                nan2008: has(types::HeaderFlags::MIPS_NAN2008),
+                fp64: has(types::HeaderFlags::MIPS_FP64),
+            },
+            _ => MachineFlags::Other(bits),
+        }
+    }
+
+    /**
+     * write flags to e_flags. the bits they decode from are replaced, the others kept.
+     * flags for another machine than the header's are an Error::IncompatibleMachineFlags
+     */
+    pub fn set_machine_flags(&mut self, flags: &MachineFlags) -> Result<(), Error> {
+        use types::HeaderFlags as F;
+        let set = |on: bool, f: F| if on { f.bits() } else { 0 };
+        let (mask, bits) = match (&self.machine, *flags) {
+            (&types::Machine::ARM, MachineFlags::Arm { eabi, float, be8 }) => (
+                F::ARM_EABI_MASK | F::ARM_ABI_FLOAT_HARD | F::ARM_ABI_FLOAT_SOFT | F::ARM_BE8,
+                (eabi.unwrap_or(0) as u32) << 24
+                    | set(float == ArmFloat::Hard, F::ARM_ABI_FLOAT_HARD)
+                    | set(float == ArmFloat::Soft, F::ARM_ABI_FLOAT_SOFT)
+                    | set(be8, F::ARM_BE8),
+            ),
+            (&types::Machine::RISCV, MachineFlags::RiscV { float_abi, rvc, rve, tso }) => (
+                F::RISCV_FLOAT_ABI | F::RISCV_RVC | F::RISCV_RVE | F::RISCV_TSO,
+                match float_abi {
+                    RiscvFloatAbi::Soft => 0,
+                    RiscvFloatAbi::Single => F::RISCV_FLOAT_ABI_SINGLE.bits(),
+                    RiscvFloatAbi::Double => F::RISCV_FLOAT_ABI_DOUBLE.bits(),
+                    RiscvFloatAbi::Quad => F::RISCV_FLOAT_ABI_QUAD.bits(),
+                } | set(rvc, F::RISCV_RVC) | set(rve, F::RISCV_RVE) | set(tso, F::RISCV_TSO),
+            ),
+            (
+                &types::Machine::MIPS,
+                MachineFlags::Mips { arch, abi, pic, cpic, noreorder, nan2008, fp64 },
+            ) => (
+                F::MIPS_ARCH | F::MIPS_ABI | F::MIPS_ABI2 | F::MIPS_PIC | F::MIPS_CPIC
+                    | F::MIPS_NOREORDER | F::MIPS_NAN2008 | F::MIPS_FP64,
+                (arch.bits() as u32) << 28 | match abi {
+                    // 32 bit files are o32 without the field too, and most of them leave it 0
+                    MipsAbi::O32 if self.ident_class == types::Class::Class32
+                        && self.flags.bits() & F::MIPS_ABI.bits() == 0 => 0,
+                    MipsAbi::O32 => 0x1000,
+                    MipsAbi::N64 => 0,
+                    MipsAbi::N32 => F::MIPS_ABI2.bits(),
+                    MipsAbi::O64 => 0x2000,
+                    MipsAbi::Eabi32 => 0x3000,
+                    MipsAbi::Eabi64 => 0x4000,
+                    MipsAbi::Unknown(v) => ((v as u32) << 12) & F::MIPS_ABI.bits(),
+                } | set(pic, F::MIPS_PIC) | set(cpic, F::MIPS_CPIC)
+                    | set(noreorder, F::MIPS_NOREORDER) | set(nan2008, F::MIPS_NAN2008)
+                    | set(fp64, F::MIPS_FP64),
+            ),
+            (_, MachineFlags::Other(bits)) => (F::from_bits_retain(!0), bits),
+            _ => return Err(Error::IncompatibleMachineFlags("the machine")),
+        };
+        self.flags = F::from_bits_retain(self.flags.bits() & !mask.bits() | bits);
+        Ok(())
+    }
 }
@@ -0,0 +1,187 @@
+//! flat images of what a file loads, for flashing tools and boot roms that don't read elf:
+//! raw binary as `objcopy -O binary` writes it, intel hex and motorola s-records.
+//!
+//! a section is part of the image when it is ALLOC, has content, and lies in the file range of
+//! a LOAD segment. it is placed at its load address, the segment's paddr plus where the section
+//! is in the segment, so data that is copied to ram at startup ends up behind the code in rom.
+//! sections must be stored, as to_writer needs them.
+
+use std::io::{self, Read, Write};
+
+use elf::Elf;
+use error::Error;
+use types;
+
+/// bytes per data record in hex and s-record files, as objcopy writes them
+const RECORD_SIZE: usize = 16;
+
+/// the loaded sections' content by load address, lowest first
+fn chunks(elf: &Elf) -> Result<Vec<(u64, &[u8])>, Error> {
+    let mut r = Vec::new();
+    for (i, sec) in elf.sections.iter().enumerate() {
+        if !sec.header.flags.contains(types::SectionFlags::ALLOC)
+            || sec.header.shtype == types::SectionType::NOBITS
+            || sec.header.size == 0
+        {
+            continue;
+        }
+        let seg = elf.segments.iter().find(|seg| {
+            seg.phtype == types::SegmentType::LOAD && seg.offset <= sec.header.offset
+                && sec.header.offset + sec.header.size <= seg.offset + seg.filesz
+        });
+        let seg = match seg {
+            Some(seg) => seg,
+            None => continue,
+        };
+        let data = match sec.content.as_raw() {
+            Some(v) => v,
+            None => return Err(Error::UnexpectedSectionContent.at(Elf::location(i, sec))),
+        };
+        r.push((seg.paddr + (sec.header.offset - seg.offset), &data[..]));
+    }
+    r.sort_by_key(|&(addr, _)| addr);
+    Ok(r)
+}
+
+/// the address of the first byte that is written, 0 if there is nothing to write
+pub fn base_address(elf: &Elf) -> Result<u64, Error> {
+    Ok(chunks(elf)?.first().map(|&(addr, _)| addr).unwrap_or(0))
+}
+
+/**
+ * the image from its lowest to its highest load address, with zeros in between,
+ * so the first byte is at base_address. where sections overlap the first one wins
+ */
+pub fn write_binary<W: Write>(elf: &Elf, io: &mut W) -> Result<(), Error> {
+    let chunks = chunks(elf)?;
+    let mut at = match chunks.first() {
+        Some(&(addr, _)) => addr,
+        None => return Ok(()),
+    };
+    for (addr, data) in chunks {
+        if addr > at {
+            io::copy(&mut io::repeat(0).take(addr - at), io)?;
+            at = addr;
+        }
+        let skip = ((at - addr) as usize).min(data.len());
+        io.write_all(&data[skip..])?;
+        at = at.max(addr + data.len() as u64);
+    }
+    Ok(())
+}
+
+/// the data records of every chunk, none of them crossing a multiple of boundary
+fn records<'a>(chunks: &[(u64, &'a [u8])], boundary: u64) -> Vec<(u64, &'a [u8])> {
+    let mut r = Vec::new();
+    for &(mut addr, mut data) in chunks {
+        while !data.is_empty() {
+            let room = (boundary - addr % boundary).min(RECORD_SIZE as u64) as usize;
+            let n = room.min(data.len());
+            r.push((addr, &data[..n]));
+            addr += n as u64;
+            data = &data[n..];
+        }
+    }
+    r
+}
+
+/// the address of the last byte of the image
+fn last_address(chunks: &[(u64, &[u8])]) -> u64 {
+    chunks
+        .iter()
+        .map(|&(addr, data)| (addr + data.len() as u64).saturating_sub(1))
+        .max()
+        .unwrap_or(0)
+}
+
+fn hex(line: &mut String, bytes: &[u8]) {
+    for b in bytes {
+        line.push_str(&format!("{:02X}", b));
+    }
+}
+
+/// one ":" record, the checksum makes all bytes add up to 0
+fn ihex_record<W: Write>(io: &mut W, rtype: u8, addr: u16, data: &[u8]) -> Result<(), Error> {
+    let mut bytes = vec![data.len() as u8, (addr >> 8) as u8, addr as u8, rtype];
+    bytes.extend_from_slice(data);
+    let sum = bytes.iter().fold(0u8, |s, b| s.wrapping_add(*b));
+    bytes.push(0u8.wrapping_sub(sum));
+    let mut line = String::from(":");
+    hex(&mut line, &bytes);
+    line.push_str("\r\n");
+    io.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/**
+ * intel hex with 32 bit addresses: data records, an extended linear address record
+ * whenever the upper 16 bits change, the entry as start linear address unless it is 0,
+ * and the end of file record. addresses past 4GiB are an Error::AddressOutOfRange
+ */
+pub fn write_ihex<W: Write>(elf: &Elf, io: &mut W) -> Result<(), Error> {
+    let chunks = chunks(elf)?;
+    let last = last_address(&chunks);
+    if last > u32::MAX as u64 {
+        return Err(Error::AddressOutOfRange("intel hex", last));
+    }
+    let mut upper = 0;
+    for (addr, data) in records(&chunks, 0x10000) {
+        if addr >> 16 != upper {
+            upper = addr >> 16;
+            ihex_record(io, 4, 0, &(upper as u16).to_be_bytes())?;
+        }
+        ihex_record(io, 0, addr as u16, data)?;
+    }
+    let entry = elf.header.entry;
+    if entry > u32::MAX as u64 {
+        return Err(Error::AddressOutOfRange("intel hex", entry));
+    }
+    if entry != 0 {
+        ihex_record(io, 5, 0, &(entry as u32).to_be_bytes())?;
+    }
+    ihex_record(io, 1, 0, &[])?;
+    Ok(())
+}
+
+/// one "S" record of an address that is width bytes long
+fn srec_record<W: Write>(
+    io: &mut W,
+    rtype: u8,
+    width: usize,
+    addr: u64,
+    data: &[u8],
+) -> Result<(), Error> {
+    let mut bytes = vec![(width + data.len() + 1) as u8];
+    bytes.extend_from_slice(&addr.to_be_bytes()[8 - width..]);
+    bytes.extend_from_slice(data);
+    let sum = bytes.iter().fold(0u8, |s, b| s.wrapping_add(*b));
+    bytes.push(!sum);
+    let mut line = format!("S{}", rtype);
+    hex(&mut line, &bytes);
+    line.push_str("\r\n");
+    io.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/**
+ * motorola s-records: an empty S0 header, then S1, S2 or S3 data records, whichever is the
+ * shortest that fits the highest address and the entry, and the matching S9, S8 or S7
+ * record with the entry. addresses past 4GiB are an Error::AddressOutOfRange
+ */
+pub fn write_srec<W: Write>(elf: &Elf, io: &mut W) -> Result<(), Error> {
+    let chunks = chunks(elf)?;
+    let entry = elf.header.entry;
+    let highest = entry.max(last_address(&chunks));
+    let width = match highest {
+        0..=0xffff => 2,
+        0x10000..=0xff_ffff => 3,
+        0x100_0000..=0xffff_ffff => 4,
+        _ => return Err(Error::AddressOutOfRange("s-records", highest)),
+    };
+    srec_record(io, 0, 2, 0, &[])?;
+    for (addr, data) in records(&chunks, 1 << 32) {
+        srec_record(io, width as u8 - 1, width, addr, data)?;
+    }
+    srec_record(io, 11 - width as u8, width, entry, &[])?;
+    Ok(())
+}
@@ -0,0 +1,183 @@
+/*!
+ * conversions from and to the views of goblin, so a file parsed with goblin can be edited
+ * and written with elfkit, and elfkit's tables can be handed to code that expects goblin's.
+ *
+ * goblin refers to names by their offset into a string table it keeps separately, so the
+ * conversions that need a name take that table. the other direction leaves the name offsets
+ * at 0, sync_all assigns them when the tables are written.
+ *
+ * there are no adapters for the object crate, which isn't available to build against here.
+ */
+
+use goblin::elf as g;
+use goblin::strtab::Strtab as GoblinStrtab;
+use num_traits::ToPrimitive;
+
+use error::Error;
+use relocation::{Relocation, RelocationType};
+use section::{Section, SectionContent, SectionHeader};
+use segment::SegmentHeader;
+use symbol::{Symbol, SymbolSectionIndex};
+use types;
+
+fn name(strtab: &GoblinStrtab, offset: usize) -> Result<String, Error> {
+    match strtab.get(offset) {
+        Some(Ok(s)) => Ok(s.to_string()),
+        _ => Err(Error::InvalidStrtabOffset(offset as u32)),
+    }
+}
+
+impl<'a> From<&'a g::SectionHeader> for SectionHeader {
+    fn from(sh: &'a g::SectionHeader) -> SectionHeader {
+        SectionHeader {
+            name: sh.sh_name as u32,
+            shtype: types::SectionType(sh.sh_type),
+            flags: types::SectionFlags::from_bits_retain(sh.sh_flags),
+            addr: sh.sh_addr,
+            offset: sh.sh_offset,
+            size: sh.sh_size,
+            link: sh.sh_link,
+            info: sh.sh_info,
+            addralign: sh.sh_addralign,
+            entsize: sh.sh_entsize,
+        }
+    }
+}
+
+impl<'a> From<&'a SectionHeader> for g::SectionHeader {
+    fn from(sh: &'a SectionHeader) -> g::SectionHeader {
+        g::SectionHeader {
+            sh_name: sh.name as usize,
+            sh_type: sh.shtype.to_u32(),
+            sh_flags: sh.flags.bits(),
+            sh_addr: sh.addr,
+            sh_offset: sh.offset,
+            sh_size: sh.size,
+            sh_link: sh.link,
+            sh_info: sh.info,
+            sh_addralign: sh.addralign,
+            sh_entsize: sh.entsize,
+        }
+    }
+}
+
+impl<'a> From<&'a g::ProgramHeader> for SegmentHeader {
+    fn from(ph: &'a g::ProgramHeader) -> SegmentHeader {
+        SegmentHeader {
+            phtype: types::SegmentType(ph.p_type),
+            flags: types::SegmentFlags::from_bits_retain(ph.p_flags as u64),
+            offset: ph.p_offset,
+            vaddr: ph.p_vaddr,
+            paddr: ph.p_paddr,
+            filesz: ph.p_filesz,
+            memsz: ph.p_memsz,
+            align: ph.p_align,
+        }
+    }
+}
+
+impl<'a> From<&'a g::Reloc> for Relocation {
+    fn from(r: &'a g::Reloc) -> Relocation {
+        Relocation {
+            addr: r.r_offset as u64,
+            sym: r.r_sym as u32,
+            rtype: RelocationType(r.r_type),
+            addend: r.r_addend as i64,
+        }
+    }
+}
+
+/// always a RELA, goblin only looks at is_rela to compute sizes
+impl<'a> From<&'a Relocation> for g::Reloc {
+    fn from(r: &'a Relocation) -> g::Reloc {
+        g::Reloc {
+            r_offset: r.addr as usize,
+            r_addend: r.addend as isize,
+            r_sym: r.sym as usize,
+            r_type: r.rtype.to_u32(),
+            is_rela: true,
+        }
+    }
+}
+
+/// the name offset is 0, see the module documentation.
+/// Global, which only exists while linking, becomes undefined
+impl<'a> From<&'a Symbol> for g::Sym {
+    fn from(sym: &'a Symbol) -> g::Sym {
+        let shndx = match sym.shndx {
+            SymbolSectionIndex::Undefined => 0,
+            SymbolSectionIndex::Absolute => 0xfff1,
+            SymbolSectionIndex::Common => 0xfff2,
+            SymbolSectionIndex::Section(i) => i as usize,
+            SymbolSectionIndex::Reserved(i) => i as usize,
+            SymbolSectionIndex::Global(_) => 0,
+        };
+        g::Sym {
+            st_name: 0,
+            st_info: (sym.bind.to_u8().unwrap() << 4) + (sym.stype.to_u8().unwrap() & 0xf),
+            st_other: sym.other,
+            st_shndx: shndx,
+            st_value: sym.value,
+            st_size: sym.size,
+        }
+    }
+}
+
+impl Symbol {
+    /// a symbol of a table goblin parsed, named from strtab,
+    /// which is goblin's strtab for syms and dynstrtab for dynsyms.
+    /// an st_shndx too large for the 16 bit field, like the one g::Sym::from gives a symbol
+    /// with an extended section index, is that section
+    pub fn from_goblin(sym: &g::Sym, strtab: &GoblinStrtab) -> Result<Symbol, Error> {
+        let extended = sym.st_shndx > 0xffff;
+        let mut r = Symbol::from_val(
+            None,
+            0,
+            sym.st_info,
+            sym.st_other,
+            if extended { 0 } else { sym.st_shndx as u16 },
+            sym.st_value,
+            sym.st_size,
+        )?;
+        if extended {
+            if sym.st_shndx > u32::MAX as usize {
+                return Err(Error::FieldOverflow("st_shndx", sym.st_shndx as u64));
+            }
+            r.shndx = SymbolSectionIndex::Section(sym.st_shndx as u32);
+        }
+        r.name = name(strtab, sym.st_name)?;
+        Ok(r)
+    }
+}
+
+impl Section {
+    /**
+     * a section goblin parsed from the file in bytes, named from its shdr_strtab.
+     * the content is Raw, as after Elf::from_reader, so it can be loaded and edited like
+     * any other section
+     */
+    pub fn from_goblin(
+        sh: &g::SectionHeader,
+        shdr_strtab: &GoblinStrtab,
+        bytes: &[u8],
+    ) -> Result<Section, Error> {
+        let header = SectionHeader::from(sh);
+        let name = name(shdr_strtab, sh.sh_name)?;
+        let content = match header.shtype {
+            types::SectionType::NULL | types::SectionType::NOBITS => SectionContent::None,
+            _ => {
+                let end = header.offset.checked_add(header.size);
+                match end.and_then(|end| bytes.get(header.offset as usize..end as usize)) {
+                    Some(b) => SectionContent::Raw(b.to_vec()),
+                    None => return Err(Error::SectionOffsetUnreachable(name, header.offset)),
+                }
+            }
+        };
+        Ok(Section {
+            header,
+            name,
+            content,
+            ..Section::default()
+        })
+    }
+}
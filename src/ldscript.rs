@@ -0,0 +1,272 @@
+use Error;
+use linker::MemoryRegion;
+
+/**
+ * the input files named by a text linker script, like the libc.so installed by glibc.
+ *
+ * only the commands that pick inputs are understood: INPUT, GROUP and AS_NEEDED inside them,
+ * plus SEARCH_DIR, and MEMORY for linker::relayout_in_regions.
+ * OUTPUT_FORMAT and friends only apply to the final link, so they are skipped.
+ * anything else, like SECTIONS, is an error rather than silently ignored.
+ */
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LinkerScript {
+    pub inputs: Vec<ScriptInput>,
+    pub search_dirs: Vec<String>,
+    /// the regions of MEMORY, in the order they are listed
+    pub memory: Vec<MemoryRegion>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptInput {
+    /// a path, or the name of a library if library is set
+    pub name: String,
+    /// written as -lname, to be looked up in the search directories
+    pub library: bool,
+    /// listed in AS_NEEDED, only link it if something references it
+    pub as_needed: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Begin,
+    End,
+    Word(String),
+}
+
+/// commands that take a parenthesized argument and don't affect which files are linked
+const SKIPPED: &[&str] = &["OUTPUT_FORMAT", "OUTPUT_ARCH", "TARGET", "ENTRY", "OUTPUT"];
+
+impl LinkerScript {
+    pub fn parse(text: &str) -> Result<LinkerScript, Error> {
+        let mut tokens = tokenize(text)?.into_iter();
+        let mut r = LinkerScript::default();
+        while let Some(token) = tokens.next() {
+            let command = match token {
+                Token::Word(w) => w,
+                _ => return Err(Error::InvalidLinkerScript("expected a command")),
+            };
+            if command == "MEMORY" {
+                if tokens.next() != Some(Token::Begin) {
+                    return Err(Error::InvalidLinkerScript("expected { after MEMORY"));
+                }
+                r.memory.extend(memory(&mut tokens)?);
+                continue;
+            }
+            if tokens.next() != Some(Token::Open) {
+                return Err(Error::InvalidLinkerScript("expected ( after command"));
+            }
+            match command.as_ref() {
+                "INPUT" | "GROUP" => r.inputs.extend(input_list(&mut tokens, false)?),
+                "SEARCH_DIR" => match (tokens.next(), tokens.next()) {
+                    (Some(Token::Word(dir)), Some(Token::Close)) => r.search_dirs.push(dir),
+                    _ => return Err(Error::InvalidLinkerScript("SEARCH_DIR takes one path")),
+                },
+                c if SKIPPED.contains(&c) => loop {
+                    match tokens.next() {
+                        Some(Token::Close) => break,
+                        Some(Token::Word(_)) => {}
+                        _ => return Err(Error::InvalidLinkerScript("unterminated command")),
+                    }
+                },
+                _ => return Err(Error::InvalidLinkerScript("unsupported command")),
+            }
+        }
+        Ok(r)
+    }
+}
+
+/// the files up to the closing parenthesis, with AS_NEEDED lists expanded
+fn input_list<I>(tokens: &mut I, as_needed: bool) -> Result<Vec<ScriptInput>, Error>
+where
+    I: Iterator<Item = Token>,
+{
+    let mut r = Vec::new();
+    loop {
+        match tokens.next() {
+            Some(Token::Close) => return Ok(r),
+            Some(Token::Word(ref w)) if w == "AS_NEEDED" => {
+                if tokens.next() != Some(Token::Open) {
+                    return Err(Error::InvalidLinkerScript("expected ( after AS_NEEDED"));
+                }
+                r.extend(input_list(tokens, true)?);
+            }
+            Some(Token::Word(w)) => r.push(match w.strip_prefix("-l") {
+                Some(name) => ScriptInput {
+                    name: String::from(name),
+                    library: true,
+                    as_needed,
+                },
+                None => ScriptInput {
+                    name: w,
+                    library: false,
+                    as_needed,
+                },
+            }),
+            _ => return Err(Error::InvalidLinkerScript("unterminated input list")),
+        }
+    }
+}
+
+/// the regions up to the closing brace, written as
+/// name (attributes) : ORIGIN = number, LENGTH = number.
+/// the attributes are optional and not kept, numbers may end in K, M or G
+fn memory<I>(tokens: &mut I) -> Result<Vec<MemoryRegion>, Error>
+where
+    I: Iterator<Item = Token>,
+{
+    // : and = only separate in here, elsewhere they are part of names like -l:libc.a
+    let mut body = Vec::new();
+    loop {
+        match tokens.next() {
+            Some(Token::End) => break,
+            Some(Token::Word(w)) => body.extend(
+                w.split([':', '='])
+                    .filter(|part| !part.is_empty())
+                    .map(|part| Token::Word(String::from(part))),
+            ),
+            Some(t) => body.push(t),
+            None => return Err(Error::InvalidLinkerScript("unterminated MEMORY")),
+        }
+    }
+    let mut tokens = body.into_iter();
+
+    let mut r = Vec::new();
+    loop {
+        let name = match tokens.next() {
+            None => return Ok(r),
+            Some(Token::Word(name)) => name,
+            _ => return Err(Error::InvalidLinkerScript("expected the name of a region")),
+        };
+        let mut next = tokens.next();
+        if next == Some(Token::Open) {
+            match (tokens.next(), tokens.next()) {
+                (Some(Token::Word(_)), Some(Token::Close)) => {}
+                _ => return Err(Error::InvalidLinkerScript("invalid region attributes")),
+            }
+            next = tokens.next();
+        }
+        let origin = keyword_number(next, tokens.next(), &["ORIGIN", "org", "o"])?;
+        let length = keyword_number(tokens.next(), tokens.next(), &["LENGTH", "len", "l"])?;
+        r.push(MemoryRegion {
+            name,
+            origin,
+            length,
+        });
+    }
+}
+
+/// the number after one of keywords
+fn keyword_number(k: Option<Token>, v: Option<Token>, keywords: &[&str]) -> Result<u64, Error> {
+    match (k, v) {
+        (Some(Token::Word(ref k)), Some(Token::Word(ref v))) if keywords.contains(&k.as_str()) => {
+            number(v)
+        }
+        _ => Err(Error::InvalidLinkerScript("expected ORIGIN and LENGTH of a region")),
+    }
+}
+
+/// a number as ld writes them: decimal, 0x hex or leading 0 octal, times 1024 for a K suffix,
+/// 1024*1024 for M and 1024*1024*1024 for G
+fn number(w: &str) -> Result<u64, Error> {
+    let (w, scale) = match w.chars().last() {
+        Some('K') | Some('k') => (&w[..w.len() - 1], 1 << 10),
+        Some('M') | Some('m') => (&w[..w.len() - 1], 1 << 20),
+        Some('G') | Some('g') => (&w[..w.len() - 1], 1 << 30),
+        _ => (w, 1),
+    };
+    let v = if let Some(hex) = w.strip_prefix("0x").or_else(|| w.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16)
+    } else if w.len() > 1 && w.starts_with('0') {
+        u64::from_str_radix(&w[1..], 8)
+    } else {
+        w.parse()
+    };
+    v.ok()
+        .and_then(|v| v.checked_mul(scale))
+        .ok_or(Error::InvalidLinkerScript("invalid number"))
+}
+
+/// split into parentheses, braces and words. commas and semicolons separate like whitespace,
+/// comments are /* */ and words may be double quoted
+fn tokenize(text: &str) -> Result<Vec<Token>, Error> {
+    let mut r = Vec::new();
+    let mut word = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let separator = match c {
+            '(' | ')' | '{' | '}' | ',' | ';' | '"' => true,
+            '/' if chars.peek() == Some(&'*') => true,
+            c => c.is_whitespace(),
+        };
+        if !separator {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            r.push(Token::Word(::std::mem::take(&mut word)));
+        }
+        match c {
+            '(' => r.push(Token::Open),
+            ')' => r.push(Token::Close),
+            '{' => r.push(Token::Begin),
+            '}' => r.push(Token::End),
+            '"' => {
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => quoted.push(c),
+                        None => return Err(Error::InvalidLinkerScript("unterminated string")),
+                    }
+                }
+                r.push(Token::Word(quoted));
+            }
+            '/' => {
+                chars.next();
+                let mut last = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if last == '*' => break,
+                        Some(c) => last = c,
+                        None => return Err(Error::InvalidLinkerScript("unterminated comment")),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if !word.is_empty() {
+        r.push(Token::Word(word));
+    }
+    Ok(r)
+}
+
+/// true if the start of a file looks like a linker script this module can read.
+/// that is, it's text and the first thing after comments is a known command
+pub fn is_linker_script(head: &[u8]) -> bool {
+    if head.iter().any(|c| *c == 0 || (*c < 0x20 && !(*c as char).is_whitespace())) {
+        return false;
+    }
+    let text = String::from_utf8_lossy(head);
+    let mut text = text.trim_start();
+    while text.starts_with("/*") {
+        text = match text.find("*/") {
+            Some(i) => text[i + 2..].trim_start(),
+            None => return false,
+        };
+    }
+    let command: String = text
+        .chars()
+        .take_while(|c| c.is_ascii_uppercase() || *c == '_')
+        .collect();
+    let rest = text[command.len()..].trim_start();
+    if command == "MEMORY" {
+        return rest.starts_with("{");
+    }
+    (command == "INPUT" || command == "GROUP" || command == "SEARCH_DIR"
+        || SKIPPED.contains(&command.as_ref()))
+        && rest.starts_with("(")
+}
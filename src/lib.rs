@@ -1,30 +1,114 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg_attr(not(feature = "std"), macro_use)]
+extern crate alloc;
 #[macro_use]
 extern crate bitflags;
 extern crate byteorder;
 #[macro_use]
 extern crate enum_primitive_derive;
 extern crate num_traits;
+#[cfg(feature = "goblin")]
+extern crate goblin;
+#[cfg(feature = "std")]
+extern crate sha2;
+#[cfg(feature = "std")]
+extern crate tempfile;
 #[macro_use]
 pub mod utils;
+pub mod io;
 pub mod relocation;
+pub mod arch;
 pub mod types;
 pub mod symbol;
 pub mod dynamic;
 pub mod strtab;
+pub mod got;
+pub mod plt;
+#[cfg(feature = "std")]
 pub mod linker;
 pub mod error;
 pub mod header;
 pub mod section;
 pub mod segment;
+#[cfg(feature = "std")]
 pub mod elf;
+#[cfg(feature = "std")]
 pub mod filetype;
+#[cfg(feature = "std")]
+pub mod ldscript;
+#[cfg(feature = "std")]
+pub mod builder;
+#[cfg(feature = "std")]
+pub mod attributes;
+pub mod property;
+#[cfg(feature = "std")]
+pub mod validate;
+#[cfg(feature = "std")]
+pub mod security;
+#[cfg(feature = "std")]
+pub mod diff;
+#[cfg(feature = "std")]
+pub mod normalize;
+#[cfg(feature = "std")]
+pub mod scan;
+#[cfg(feature = "std")]
+pub mod coredump;
+#[cfg(feature = "std")]
+pub mod archive;
+#[cfg(feature = "std")]
+pub mod digest;
+#[cfg(feature = "std")]
+pub mod transaction;
+#[cfg(feature = "std")]
+pub mod image;
+#[cfg(feature = "std")]
+pub mod loaded;
+#[cfg(feature = "std")]
+pub mod debuglink;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "goblin")]
+pub mod interop;
+
+/// what std's prelude has and alloc doesn't put in scope by itself
+mod prelude {
+    pub use alloc::boxed::Box;
+    pub use alloc::string::String;
+    pub use alloc::vec::Vec;
+}
 
-pub use relocation::Relocation;
+pub use relocation::{Relocation, SymbolicRelocation, Target};
 pub use symbol::{Symbol, SymbolSectionIndex};
 pub use strtab::Strtab;
+pub use got::GotEntry;
 pub use dynamic::{Dynamic, DynamicContent};
 pub use error::Error;
-pub use header::Header;
-pub use section::{Section, SectionContent, SectionHeader};
+pub use header::{ArmFloat, Header, MachineFlags, MipsAbi, MipsArch, RiscvFloatAbi};
+pub use section::{LinkRef, Placement, Section, SectionContent, SectionContentExt, SectionHeader,
+                  SectionId};
 pub use segment::SegmentHeader;
-pub use elf::Elf;
+#[cfg(feature = "std")]
+pub use elf::{Elf, ElfOptions, Holes, Mapping, QuickInfo, Stale, Stats, WriteOptions};
+#[cfg(feature = "std")]
+pub use builder::ElfBuilder;
+#[cfg(feature = "std")]
+pub use validate::{Finding, Severity};
+#[cfg(feature = "std")]
+pub use security::SecurityReport;
+#[cfg(feature = "std")]
+pub use diff::Difference;
+#[cfg(feature = "std")]
+pub use normalize::NormalizeOptions;
+#[cfg(feature = "std")]
+pub use coredump::RDebug;
+#[cfg(feature = "std")]
+pub use archive::Archive;
+#[cfg(feature = "std")]
+pub use digest::HashAlgorithm;
+#[cfg(feature = "std")]
+pub use transaction::Transaction;
+#[cfg(feature = "std")]
+pub use loaded::LoadedSection;
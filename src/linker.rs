@@ -1,6 +1,11 @@
-use {types, Dynamic, Elf, Error, SegmentHeader};
+use {types, Dynamic, Elf, Error, Header, LinkRef, Relocation, Section, SectionContent,
+     SegmentHeader, Symbol, SymbolSectionIndex};
 use dynamic::DynamicContent;
-use relocation::RelocationType;
+use arch::{self, RelocationKind};
+use symbol;
+use filetype;
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
 
 /**
  * high level linker stuff
@@ -49,9 +54,37 @@ pub fn segments(elf: &Elf) -> Result<Vec<SegmentHeader>, Error> {
                     align: 0x1,
                 });
             }
+            ".note.gnu.property" => {
+                r.push(SegmentHeader {
+                    phtype: types::SegmentType::GNU_PROPERTY,
+                    flags: types::SegmentFlags::READABLE,
+                    offset: section.header.offset,
+                    filesz: section.header.size,
+                    vaddr: section.header.addr,
+                    paddr: section.header.addr,
+                    memsz: section.header.size,
+                    align: section.header.addralign,
+                });
+            }
             _ => {}
         }
 
+        // the kernel and ld.so look for the abi tag and the build id in PT_NOTE
+        if section.header.shtype == types::SectionType::NOTE
+            && section.header.flags.contains(types::SectionFlags::ALLOC)
+        {
+            r.push(SegmentHeader {
+                phtype: types::SegmentType::NOTE,
+                flags: types::SegmentFlags::READABLE,
+                offset: section.header.offset,
+                filesz: section.header.size,
+                vaddr: section.header.addr,
+                paddr: section.header.addr,
+                memsz: section.header.size,
+                align: section.header.addralign,
+            });
+        }
+
         if section.header.flags.contains(types::SectionFlags::TLS) {
             r.push(SegmentHeader {
                 phtype: types::SegmentType::TLS,
@@ -71,6 +104,10 @@ pub fn segments(elf: &Elf) -> Result<Vec<SegmentHeader>, Error> {
         if !section.header.flags.contains(types::SectionFlags::ALLOC) {
             continue;
         }
+        // nothing is loaded there, and the addresses may be far from everything else
+        if section.placement.noload {
+            continue;
+        }
 
         if section.header.shtype == types::SectionType::NOBITS {
             voff = section.header.addr + section.header.size;
@@ -124,6 +161,10 @@ pub fn segments(elf: &Elf) -> Result<Vec<SegmentHeader>, Error> {
         align: 0x200000,
     });
 
+    if let Some(relro) = relro(elf) {
+        r.push(relro);
+    }
+
     if elf.sections[1].header.offset > elf.sections[1].header.addr {
         return Err(Error::FirstSectionOffsetCanNotBeLargerThanAddress);
     }
@@ -148,6 +189,385 @@ pub fn segments(elf: &Elf) -> Result<Vec<SegmentHeader>, Error> {
 }
 
 
+/**
+ * check what the kernel needs of the program headers once the layout is done: to_writer puts
+ * them right behind the elf header, so they must end before the first section does, and
+ * PT_PHDR must be inside a LOAD, or the aux vector gets an AT_PHDR that points nowhere.
+ * fails with Error::ProgramHeadersOverlap or Error::PhdrNotInLoad
+ */
+pub fn check_program_headers(elf: &Elf) -> Result<(), Error> {
+    let end = (elf.header.size() + elf.segments.len() * SegmentHeader::entsize(&elf.header)) as u64;
+    let first = elf.sections
+        .iter()
+        .skip(1)
+        .filter(|s| s.header.shtype != types::SectionType::NOBITS && s.header.size > 0)
+        .min_by_key(|s| s.header.offset);
+    if let Some(sec) = first {
+        if sec.header.offset < end {
+            return Err(Error::ProgramHeadersOverlap(end, sec.name.clone(), sec.header.offset));
+        }
+    }
+
+    for phdr in elf.segments.iter().filter(|s| s.phtype == types::SegmentType::PHDR) {
+        let mapped = elf.segments.iter().any(|load| {
+            load.phtype == types::SegmentType::LOAD && load.offset <= phdr.offset
+                && phdr.offset + phdr.filesz <= load.offset + load.filesz
+                && load.vaddr <= phdr.vaddr
+                && phdr.vaddr + phdr.memsz <= load.vaddr + load.memsz
+        });
+        if !mapped {
+            return Err(Error::PhdrNotInLoad(phdr.vaddr));
+        }
+    }
+    Ok(())
+}
+
+/// which of the defined global symbols of a link become dynamic symbols
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Exports {
+    /// nothing, what an executable gets without --export-dynamic
+    #[default]
+    None,
+    /// every one, like --export-dynamic
+    All,
+    /// these, like --export-dynamic-symbol
+    Names(HashSet<String>),
+}
+
+/**
+ * the visibility of a symbol that one object refers to or defines with a and another with b.
+ * like the gABI says for combining objects, the most constraining one wins:
+ * internal over hidden over protected over default
+ */
+pub fn merge_visibility(a: types::SymbolVis, b: types::SymbolVis) -> types::SymbolVis {
+    let rank = |v: &types::SymbolVis| match *v {
+        types::SymbolVis::DEFAULT => 0,
+        types::SymbolVis::PROTECTED => 1,
+        types::SymbolVis::HIDDEN => 2,
+        types::SymbolVis::INTERNAL => 3,
+    };
+    if rank(&b) > rank(&a) {
+        b
+    } else {
+        a
+    }
+}
+
+/**
+ * make the defined hidden and internal symbols of a symbol table local, like ld does in its
+ * output, so they stay in .symtab for debuggers without being visible to anything else.
+ * their visibility is kept. locals have to come before the others, so the ones that became
+ * local are moved behind the last local, everything else keeps its order
+ */
+pub fn localize_hidden(symbols: &mut [Symbol]) {
+    for sym in symbols.iter_mut() {
+        let hidden = matches!(
+            sym.vis(),
+            types::SymbolVis::HIDDEN | types::SymbolVis::INTERNAL
+        );
+        if hidden && sym.shndx != SymbolSectionIndex::Undefined {
+            sym.bind = types::SymbolBind::LOCAL;
+        }
+    }
+    symbols.sort_by_key(|sym| sym.bind != types::SymbolBind::LOCAL);
+}
+
+/**
+ * the content of .dynsym for the symbols exports selects, the null symbol first.
+ * only global and weak symbols that are defined get in, and neither hidden nor internal ones,
+ * which are not to be seen outside the output. protected ones are exported, references from
+ * inside the output still bind to them, which is what the linker did when relocating.
+ * values and section indices are taken as they are, so symbols must be placed already
+ */
+pub fn export_symbols(symbols: &[Symbol], exports: &Exports) -> Vec<Symbol> {
+    let mut r = vec![Symbol::default()];
+    for sym in symbols {
+        let selected = match *exports {
+            Exports::None => false,
+            Exports::All => true,
+            Exports::Names(ref names) => names.contains(&sym.name),
+        };
+        let visible = !matches!(
+            sym.vis(),
+            types::SymbolVis::HIDDEN | types::SymbolVis::INTERNAL
+        );
+        let global = sym.bind == types::SymbolBind::GLOBAL || sym.bind == types::SymbolBind::WEAK;
+        let named = sym.stype != types::SymbolType::SECTION
+            && sym.stype != types::SymbolType::FILE && !sym.name.is_empty();
+        if selected && visible && global && named && sym.shndx != SymbolSectionIndex::Undefined {
+            r.push(sym.clone());
+        }
+    }
+    r
+}
+
+/**
+ * .dynsym with symbols, which usually come from export_symbols, and the .hash that ld.so
+ * looks them up with. both are linked by name, .dynsym to a .dynstr the caller adds
+ * and .hash to .dynsym
+ */
+pub fn dynsym_sections(eh: &Header, symbols: Vec<Symbol>) -> Result<Vec<Section>, Error> {
+    let mut hash = symbol::symhash(eh, &symbols, 0)?;
+    hash.link_ref = Some(LinkRef::Name(String::from(".dynsym")));
+    let mut dynsym = Section::new(
+        String::from(".dynsym"),
+        types::SectionType::DYNSYM,
+        types::SectionFlags::ALLOC,
+        SectionContent::Symbols(symbols),
+        0,
+        0,
+    );
+    dynsym.link_ref = Some(LinkRef::Name(String::from(".dynstr")));
+    dynsym.header.addralign = match eh.ident_class {
+        types::Class::Class64 => 8,
+        types::Class::Class32 => 4,
+    };
+    Ok(vec![dynsym, hash])
+}
+
+/// -Bsymbolic and -Bsymbolic-functions, which references inside a shared object bind to its
+/// own definitions even if they are exported
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Symbolic {
+    /// only those that aren't exported, exported ones can be preempted by the executable
+    #[default]
+    None,
+    /// all of them
+    All,
+    /// only those to functions, so data still works with copy relocations
+    Functions,
+}
+
+/**
+ * whether references from inside the output to sym are resolved to its definition at link time.
+ * undefined symbols never are, local ones and ones that are hidden, internal or protected always
+ * are, and exported ones as symbolic says
+ */
+pub fn binds_locally(sym: &Symbol, symbolic: &Symbolic) -> bool {
+    if sym.shndx == SymbolSectionIndex::Undefined {
+        return false;
+    }
+    if sym.bind == types::SymbolBind::LOCAL || sym.vis() != types::SymbolVis::DEFAULT {
+        return true;
+    }
+    match *symbolic {
+        Symbolic::None => false,
+        Symbolic::All => true,
+        Symbolic::Functions => {
+            sym.stype == types::SymbolType::FUNC || sym.stype == types::SymbolType::GNU_IFUNC
+        }
+    }
+}
+
+/**
+ * turn the pointer sized absolute and GLOB_DAT relocations of a shared object that are against
+ * symbols binding locally into relative ones, which ld.so doesn't have to look anything up for.
+ * symbols is the .dynsym the relocations refer to, with placed values.
+ * the addends become S + A, for REL sections the caller has to write them into place.
+ * JUMP_SLOT relocations are left alone, calls that bind locally shouldn't go through the plt.
+ * returns how many relocations were changed
+ */
+pub fn symbolic_relocations(
+    eh: &Header,
+    relocs: &mut [Relocation],
+    symbols: &[Symbol],
+    symbolic: &Symbolic,
+) -> Result<usize, Error> {
+    let handler = match arch::handler(eh) {
+        Some(h) => h,
+        None => return Err(Error::UnsupportedMachineTypeForRelocation(eh.machine.clone())),
+    };
+    let pointer = match eh.ident_class {
+        types::Class::Class64 => 8,
+        types::Class::Class32 => 4,
+    };
+    let mut n = 0;
+    for reloc in relocs.iter_mut() {
+        let addend = match handler.classify(&reloc.rtype) {
+            RelocationKind::Absolute if handler.size(&reloc.rtype) == Some(pointer) => reloc.addend,
+            RelocationKind::GlobDat => 0,
+            _ => continue,
+        };
+        let sym = match symbols.get(reloc.sym as usize) {
+            Some(sym) if reloc.sym != 0 && binds_locally(sym, symbolic) => sym,
+            _ => continue,
+        };
+        reloc.rtype = handler.relative_type();
+        reloc.addend = (sym.value as i64).wrapping_add(addend);
+        reloc.sym = 0;
+        n += 1;
+    }
+    Ok(n)
+}
+
+/// the DT_FLAGS bits and dynamic entries for symbolic, DF_SYMBOLIC and DT_SYMBOLIC for
+/// -Bsymbolic, which tell ld.so to look in the object itself first. -Bsymbolic-functions has none
+pub fn symbolic_dynamic(symbolic: &Symbolic) -> (types::DynamicFlags, Vec<Dynamic>) {
+    match *symbolic {
+        Symbolic::All => (
+            types::DynamicFlags::SYMBOLIC,
+            vec![Dynamic {
+                dhtype: types::DynamicType::SYMBOLIC,
+                content: DynamicContent::Address(0),
+            }],
+        ),
+        _ => (types::DynamicFlags::empty(), Vec::new()),
+    }
+}
+
+/**
+ * the .interp section that makes segments emit a PT_INTERP with path as the dynamic linker.
+ * the kernel wants PT_INTERP before any LOAD and inside the first one, so insert it as the
+ * first section after the null section
+ */
+pub fn interp_section(path: &str) -> Section {
+    let mut path = path.as_bytes().to_vec();
+    path.push(0);
+    let mut sec = Section::new(
+        String::from(".interp"),
+        types::SectionType::PROGBITS,
+        types::SectionFlags::ALLOC,
+        SectionContent::Raw(path),
+        0,
+        0,
+    );
+    sec.header.addralign = 1;
+    sec
+}
+
+/// the os a file is branded for with a note, which is how kernels that don't look at EI_OSABI
+/// tell their binaries from linux ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiTag {
+    /// .note.ABI-tag with the oldest kernel the file runs on, as major, minor and patch level
+    Linux(u32, u32, u32),
+    /// .note.tag with __FreeBSD_version, like 1400097 for 14.0
+    FreeBsd(u32),
+    /// .note.netbsd.ident with __NetBSD_Version__, like 1000000000 for 10.0
+    NetBsd(u32),
+    /// .note.openbsd.ident
+    OpenBsd,
+}
+
+impl AbiTag {
+    /// what EI_OSABI files for the os have. only freebsd has its own
+    pub fn abi(&self) -> types::Abi {
+        match *self {
+            AbiTag::FreeBsd(_) => types::Abi::FREEBSD,
+            _ => types::Abi::SYSV,
+        }
+    }
+
+    /// the dynamic linker of the os, None for linux on machines without a well known one
+    pub fn interpreter(&self, eh: &Header) -> Option<&'static str> {
+        match *self {
+            AbiTag::Linux(..) => match (&eh.machine, &eh.ident_class) {
+                (&types::Machine::X86_64, &types::Class::Class64) => {
+                    Some("/lib64/ld-linux-x86-64.so.2")
+                }
+                (&types::Machine::X86_64, &types::Class::Class32) => {
+                    Some("/libx32/ld-linux-x32.so.2")
+                }
+                (&types::Machine::AARCH64, _) => Some("/lib/ld-linux-aarch64.so.1"),
+                (&types::Machine::RISCV, &types::Class::Class64) => {
+                    Some("/lib/ld-linux-riscv64-lp64d.so.1")
+                }
+                _ => None,
+            },
+            AbiTag::FreeBsd(_) => Some("/libexec/ld-elf.so.1"),
+            AbiTag::NetBsd(_) => Some("/usr/libexec/ld.elf_so"),
+            AbiTag::OpenBsd => Some("/usr/libexec/ld.so"),
+        }
+    }
+
+    /**
+     * the note section with the tag. it has to be in a PT_NOTE, which segments emits for every
+     * allocated NOTE section, and the kernel only reads the first pages of a file,
+     * so it goes right after .interp
+     */
+    pub fn section(&self, eh: &Header) -> Result<Section, Error> {
+        use std::io::Write;
+        let (section, owner, desc) = match *self {
+            AbiTag::Linux(major, minor, patch) => {
+                (".note.ABI-tag", "GNU", vec![0, major, minor, patch])
+            }
+            AbiTag::FreeBsd(version) => (".note.tag", "FreeBSD", vec![version]),
+            AbiTag::NetBsd(version) => (".note.netbsd.ident", "NetBSD", vec![version]),
+            AbiTag::OpenBsd => (".note.openbsd.ident", "OpenBSD", vec![0]),
+        };
+        let mut io = Vec::new();
+        {
+            let io = &mut io;
+            elf_write_u32!(eh, io, owner.len() as u32 + 1)?;
+            elf_write_u32!(eh, io, desc.len() as u32 * 4)?;
+            // NT_GNU_ABI_TAG, NT_FREEBSD_ABI_TAG, NT_NETBSD_IDENT and NT_OPENBSD_IDENT
+            elf_write_u32!(eh, io, 1)?;
+        }
+        io.extend_from_slice(owner.as_bytes());
+        io.push(0);
+        while !io.len().is_multiple_of(4) {
+            io.push(0);
+        }
+        for v in desc {
+            let w = &mut io;
+            elf_write_u32!(eh, w, v)?;
+        }
+        let mut sec = Section::new(
+            String::from(section),
+            types::SectionType::NOTE,
+            types::SectionFlags::ALLOC,
+            SectionContent::Raw(io),
+            0,
+            0,
+        );
+        sec.header.addralign = 4;
+        Ok(sec)
+    }
+}
+
+/**
+ * the EI_OSABI of a file linked from objects for a and b. SYSV goes with any os, others must
+ * be the same, or it's an Error::IncompatibleAbi
+ */
+pub fn merge_abi(a: &types::Abi, b: &types::Abi) -> Result<types::Abi, Error> {
+    match (a, b) {
+        (a, b) if a == b => Ok(a.clone()),
+        (&types::Abi::SYSV, b) => Ok(b.clone()),
+        (a, &types::Abi::SYSV) => Ok(a.clone()),
+        (a, b) => Err(Error::IncompatibleAbi(a.clone(), b.clone())),
+    }
+}
+
+/**
+ * the EI_OSABI an output with these symbols needs. GNU_IFUNC and GNU_UNIQUE symbols make a
+ * SYSV file GNU, like ld does it. in a file for an os that doesn't have them they are an
+ * Error::UnsupportedByAbi
+ */
+pub fn symbol_abi(abi: &types::Abi, symbols: &[Symbol]) -> Result<types::Abi, Error> {
+    let mut abi = abi.clone();
+    for sym in symbols {
+        let what = if sym.stype == types::SymbolType::GNU_IFUNC {
+            "an indirect function"
+        } else if sym.bind == types::SymbolBind::STB_GNU_UNIQUE {
+            "unique"
+        } else {
+            continue;
+        };
+        if abi == types::Abi::SYSV {
+            abi = types::Abi::GNU;
+        }
+        let has = if sym.stype == types::SymbolType::GNU_IFUNC {
+            abi.has_ifunc()
+        } else {
+            abi.has_unique_symbols()
+        };
+        if !has {
+            return Err(Error::UnsupportedByAbi(sym.name.clone(), what, abi));
+        }
+    }
+    Ok(abi)
+}
+
 /// generate dynamic linker instructions from fully layouted sections.
 /// sections must be synced
 /// returned list is null terminated, do not append, but call insert instead.
@@ -186,6 +606,26 @@ pub fn dynamic(elf: &Elf) -> Result<Vec<Dynamic>, Error> {
                     content: DynamicContent::Address(sec.header.entsize),
                 });
             }
+            ".got.plt" => {
+                r.push(Dynamic {
+                    dhtype: types::DynamicType::PLTGOT,
+                    content: DynamicContent::Address(sec.header.addr),
+                });
+            }
+            ".rela.plt" => {
+                r.push(Dynamic {
+                    dhtype: types::DynamicType::JMPREL,
+                    content: DynamicContent::Address(sec.header.addr),
+                });
+                r.push(Dynamic {
+                    dhtype: types::DynamicType::PLTRELSZ,
+                    content: DynamicContent::Address(sec.header.size),
+                });
+                r.push(Dynamic {
+                    dhtype: types::DynamicType::PLTREL,
+                    content: DynamicContent::Address(types::DynamicType::RELA.0),
+                });
+            }
             ".rela.dyn" => {
                 r.push(Dynamic {
                     dhtype: types::DynamicType::RELA,
@@ -200,12 +640,20 @@ pub fn dynamic(elf: &Elf) -> Result<Vec<Dynamic>, Error> {
                     content: DynamicContent::Address(sec.header.entsize),
                 });
 
+                let handler = match arch::handler(&elf.header) {
+                    Some(h) => h,
+                    None => {
+                        return Err(Error::UnsupportedMachineTypeForRelocation(
+                            elf.header.machine.clone(),
+                        ))
+                    }
+                };
                 let first_non_rela = match sec.content.as_relocations() {
                     None => return Err(Error::UnexpectedSectionContent),
                     Some(v) => v.iter()
                         .position(|ref r| {
-                            r.rtype != RelocationType::R_X86_64_RELATIVE
-                                && r.rtype != RelocationType::R_X86_64_JUMP_SLOT
+                            let kind = handler.classify(&r.rtype);
+                            kind != RelocationKind::BaseRelative && kind != RelocationKind::JumpSlot
                         })
                         .unwrap_or(v.len()),
                 } as u64;
@@ -236,12 +684,72 @@ pub fn dynamic(elf: &Elf) -> Result<Vec<Dynamic>, Error> {
     Ok(r)
 }
 
-
+/// give every section after the first an offset and an address, one behind the other,
+/// starting at pstart, and each aligned to its sh_addralign
 pub fn relayout(elf: &mut Elf, pstart: u64) -> Result<(), Error> {
+    relayout_in_regions(elf, pstart, &[])
+}
+
+/// a range of addresses, like a line of MEMORY in a linker script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub name: String,
+    pub origin: u64,
+    pub length: u64,
+}
+
+/**
+ * relayout, with the sections that name a region in their placement put into it one after
+ * the other, starting at its origin. fails with Error::MemoryRegionOverflow if they don't fit.
+ *
+ * file offsets grow with the section index, so sections have to be ordered by address across
+ * regions, like flash before ram when flash has the lower addresses
+ */
+pub fn relayout_in_regions(elf: &mut Elf, pstart: u64, regions: &[MemoryRegion])
+    -> Result<(), Error> {
     let mut poff = pstart;
     let mut voff = pstart;
+    let mut in_relro = false;
+    let mut region_end: Vec<u64> = regions.iter().map(|r| r.origin).collect();
 
     for sec in &mut elf.sections[1..] {
+        let region = match sec.placement.region {
+            Some(ref name) => match regions.iter().position(|r| &r.name == name) {
+                Some(i) => Some(i),
+                None => return Err(Error::MemoryRegionNotFound(name.clone())),
+            },
+            None => None,
+        };
+        // the relro sections get whole pages, so protecting them doesn't hit anything else
+        if sec.header.flags.contains(types::SectionFlags::ALLOC) && is_relro(sec) != in_relro {
+            poff = page_align(poff);
+            voff = page_align(voff);
+            in_relro = !in_relro;
+        }
+        let address = sec.placement.address.or_else(|| region.map(|i| region_end[i]));
+        if let Some(addr) = address {
+            if voff > addr {
+                return Err(Error::SectionAddressTaken(sec.name.clone(), addr, voff));
+            }
+            voff = addr;
+            // the file offset follows the address instead
+            if sec.header.shtype != types::SectionType::NOBITS && voff >= poff {
+                poff += (voff - poff) % 0x200000;
+            }
+        }
+        // sections without a placement start at a multiple of their sh_addralign.
+        // the file offset moves along, so it stays in the same place within the page
+        let align = sec.header.addralign.max(1);
+        if address.is_none() && !voff.is_multiple_of(align) {
+            let pad = voff.div_ceil(align) * align - voff;
+            voff += pad;
+            if sec.header.shtype != types::SectionType::NOBITS {
+                poff += pad;
+            }
+        }
+        if poff > voff {
+            return Err(Error::SectionOffsetBeyondAddress(sec.name.clone(), poff, voff));
+        }
         if sec.header.shtype != types::SectionType::NOBITS {
             if (voff - poff) % 0x200000 != 0 {
                 voff += 0x200000 - ((voff - poff) % 0x200000)
@@ -252,7 +760,743 @@ pub fn relayout(elf: &mut Elf, pstart: u64) -> Result<(), Error> {
 
         sec.header.addr = voff;
         voff += sec.header.size;
+        if let Some(i) = region {
+            region_end[i] = region_end[i].max(voff);
+        }
+    }
+
+    for (region, end) in regions.iter().zip(region_end) {
+        if end > region.origin + region.length {
+            return Err(Error::MemoryRegionOverflow(
+                region.name.clone(),
+                end - region.origin - region.length,
+            ));
+        }
     }
+    elf.set_laid_out();
+    Ok(())
+}
+
+
+/// sections only written by the dynamic linker while relocating.
+/// relayout puts them on pages of their own, and segments covers the first run of them
+/// with PT_GNU_RELRO, so ld.so makes them read only before the program starts
+pub const RELRO_SECTIONS: &[&str] = &[
+    ".preinit_array",
+    ".init_array",
+    ".fini_array",
+    ".data.rel.ro",
+    ".got",
+];
+
+const PAGE_SIZE: u64 = 0x1000;
+
+/// v rounded up to the pages relayout puts the relro sections on
+pub fn page_align(v: u64) -> u64 {
+    (v + PAGE_SIZE - 1) & !(PAGE_SIZE - 1)
+}
+
+fn is_relro(sec: &Section) -> bool {
+    RELRO_SECTIONS.contains(&sec.name.as_str())
+}
 
+/// the PT_GNU_RELRO segment segments emits.
+/// ld.so rounds its end down to a page, so it covers the padding relayout put after the
+/// sections. there is none for sections that weren't laid out by relayout,
+/// since their first page may be shared with writable data
+pub fn relro(elf: &Elf) -> Option<SegmentHeader> {
+    let mut run: Option<(&Section, u64)> = None;
+    for sec in &elf.sections {
+        if !sec.header.flags.contains(types::SectionFlags::ALLOC) {
+            continue;
+        }
+        if is_relro(sec) {
+            let end = sec.header.addr + sec.header.size;
+            run = Some((run.map_or(sec, |(first, _)| first), end));
+        } else if run.is_some() {
+            break;
+        }
+    }
+    let (first, end) = run?;
+    if first.header.addr % PAGE_SIZE != 0 {
+        return None;
+    }
+    let size = page_align(end) - first.header.addr;
+    Some(SegmentHeader {
+        phtype: types::SegmentType::GNU_RELRO,
+        flags: types::SegmentFlags::READABLE,
+        offset: first.header.offset,
+        filesz: size,
+        vaddr: first.header.addr,
+        paddr: first.header.addr,
+        memsz: size,
+        align: 0x1,
+    })
+}
+
+/// the address given to -e, if it is one rather than a symbol name. decimal or 0x hex
+pub fn entry_address(entry: &str) -> Option<u64> {
+    if let Some(hex) = entry.strip_prefix("0x").or_else(|| entry.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()
+    } else {
+        entry.parse().ok()
+    }
+}
+
+/// resolve -e like ld: the value of the symbol lookup finds with that name,
+/// or else the argument as an address
+pub fn entry<F>(entry: &str, lookup: F) -> Result<u64, Error>
+where
+    F: FnOnce(&str) -> Option<u64>,
+{
+    match lookup(entry).or_else(|| entry_address(entry)) {
+        Some(v) => Ok(v),
+        None => Err(Error::EntrySymbolNotFound(String::from(entry))),
+    }
+}
+
+/// the DT_FLAGS and DT_FLAGS_1 bits requested by these -z keywords.
+/// keywords that don't set a flag, like relro or muldefs, are ignored
+pub fn z_flags(keywords: &[String]) -> (types::DynamicFlags, types::DynamicFlags1) {
+    let mut flags = types::DynamicFlags::empty();
+    let mut flags1 = types::DynamicFlags1::empty();
+    for keyword in keywords {
+        match keyword.as_str() {
+            "now" => {
+                flags |= types::DynamicFlags::BIND_NOW;
+                flags1 |= types::DynamicFlags1::NOW;
+            }
+            "lazy" => {
+                flags.remove(types::DynamicFlags::BIND_NOW);
+                flags1.remove(types::DynamicFlags1::NOW);
+            }
+            "origin" => {
+                flags |= types::DynamicFlags::ORIGIN;
+                flags1 |= types::DynamicFlags1::ORIGIN;
+            }
+            "global" => flags1 |= types::DynamicFlags1::GLOBAL,
+            "nodelete" => flags1 |= types::DynamicFlags1::NODELETE,
+            "nodlopen" => flags1 |= types::DynamicFlags1::NOOPEN,
+            "initfirst" => flags1 |= types::DynamicFlags1::INITFIRST,
+            "interpose" => flags1 |= types::DynamicFlags1::INTERPOSE,
+            "nodefaultlib" => flags1 |= types::DynamicFlags1::NODEFLIB,
+            "nodump" => flags1 |= types::DynamicFlags1::NODUMP,
+            "loadfltr" => flags1 |= types::DynamicFlags1::LOADFLTR,
+            _ => {}
+        }
+    }
+    (flags, flags1)
+}
+
+/// the value of a defined symbol in the symbol tables of an already linked elf,
+/// for use as the lookup of entry()
+pub fn symbol_value(elf: &Elf, name: &str) -> Option<u64> {
+    elf.sections
+        .iter()
+        .filter_map(|sec| sec.content.as_symbols())
+        .flat_map(|syms| syms.iter())
+        .find(|sym| sym.name == name && sym.shndx != SymbolSectionIndex::Undefined)
+        .map(|sym| sym.value)
+}
+
+/// the symbols listed in a --symbol-ordering-file, with their position in it.
+/// one name per line, surrounding whitespace and anything after a # is ignored.
+/// a name listed twice keeps its first position
+pub fn symbol_ordering(text: &str) -> HashMap<String, usize> {
+    let mut order = HashMap::new();
+    let names = text.lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|name| !name.is_empty());
+    for name in names {
+        let next = order.len();
+        order.entry(String::from(name)).or_insert(next);
+    }
+    order
+}
+
+/// whether an object has allocated sections with SHF_GNU_RETAIN, which `__attribute__((retain))`
+/// makes. linkers keep those even when nothing refers to them, so the object has to be loaded
+pub fn retains_sections(elf: &Elf) -> bool {
+    elf.sections.iter().any(|sec| {
+        sec.header.flags.contains(types::SectionFlags::GNU_RETAIN | types::SectionFlags::ALLOC)
+    })
+}
+
+/// where an input section that defines these symbols goes in an order from symbol_ordering:
+/// the position of the first of them that is listed, or usize::MAX if none is.
+/// sorting stably by it puts the listed sections first, in the order of the file,
+/// and keeps the others in input order after them
+pub fn ordering_priority(order: &HashMap<String, usize>, symbols: &[Symbol]) -> usize {
+    symbols
+        .iter()
+        .filter(|sym| {
+            sym.shndx != SymbolSectionIndex::Undefined && sym.stype != types::SymbolType::SECTION
+                && sym.stype != types::SymbolType::FILE
+        })
+        .filter_map(|sym| order.get(&sym.name))
+        .cloned()
+        .min()
+        .unwrap_or(usize::MAX)
+}
+
+/// where a SHF_LINK_ORDER section goes, given its name and where the section it links to is in
+/// the output, None for all other sections. sorting stably by it after ordering_priority keeps
+/// the others in place and puts the link order sections behind them, those of one name together
+/// and in the order of the code they describe, the way tables like .ARM.exidx are searched
+pub fn link_order_key(name: &str, linked: Option<usize>) -> Option<(String, usize)> {
+    linked.map(|at| (String::from(name), at))
+}
+
+/// the output sections of GNU ld's default script, in the order it lays them out, with the
+/// input sections each collects. a pattern ending in `*` matches every name that starts with the
+/// rest, `*` in the middle anything, and the others only that name. the first match wins, so
+/// .text.unlikely.f doesn't end up in .text. names are compared as they are, case included
+pub const OUTPUT_SECTIONS: &[(&str, &[&str])] = &[
+    (".text.unlikely", &[".text.unlikely", ".text.*_unlikely", ".text.unlikely.*"]),
+    (".text.exit", &[".text.exit", ".text.exit.*"]),
+    (".text.startup", &[".text.startup", ".text.startup.*"]),
+    (".text.hot", &[".text.hot", ".text.hot.*"]),
+    (".text", &[".text", ".text.*", ".gnu.linkonce.t.*"]),
+    (".rodata", &[".rodata", ".rodata.*", ".gnu.linkonce.r.*"]),
+    (".tdata", &[".tdata", ".tdata.*", ".gnu.linkonce.td.*"]),
+    (".tbss", &[".tbss", ".tbss.*", ".gnu.linkonce.tb.*", ".tcommon"]),
+    (".preinit_array", &[".preinit_array", ".preinit_array.*"]),
+    (".init_array", &[".init_array", ".init_array.*", ".ctors", ".ctors.*"]),
+    (".fini_array", &[".fini_array", ".fini_array.*", ".dtors", ".dtors.*"]),
+    (".data.rel.ro", &[".data.rel.ro", ".data.rel.ro.*", ".gnu.linkonce.d.rel.ro.*"]),
+    (".data", &[".data", ".data.*", ".gnu.linkonce.d.*"]),
+    (".bss", &[".bss", ".bss.*", ".gnu.linkonce.b.*", "COMMON"]),
+];
+
+fn matches_pattern(name: &str, pattern: &str) -> bool {
+    match pattern.find('*') {
+        None => name == pattern,
+        Some(star) => {
+            let (head, tail) = (&pattern[..star], &pattern[star + 1..]);
+            name.len() >= head.len() + tail.len() && name.starts_with(head) && name.ends_with(tail)
+        }
+    }
+}
+
+/// index into OUTPUT_SECTIONS of the output section an input section goes to,
+/// None for orphans, which the default script has no place for
+fn output_section_index(name: &str) -> Option<usize> {
+    OUTPUT_SECTIONS
+        .iter()
+        .position(|&(_, patterns)| patterns.iter().any(|p| matches_pattern(name, p)))
+}
+
+/**
+ * the output section GNU ld puts an input section in, None for orphans, which keep their name.
+ * .text.unlikely, .text.exit, .text.startup and .text.hot are output sections of their own with
+ * keep_text_section_prefix, like -z keep-text-section-prefix makes them, and part of .text
+ * otherwise
+ */
+pub fn output_section(name: &str, keep_text_section_prefix: bool) -> Option<&'static str> {
+    let out = OUTPUT_SECTIONS[output_section_index(name)?].0;
+    if !keep_text_section_prefix && out.starts_with(".text.") {
+        return Some(".text");
+    }
+    Some(out)
+}
+
+/// where an input section goes among the others of its segment, the position of its output
+/// section in OUTPUT_SECTIONS, and usize::MAX for orphans. sorting stably by it groups the input
+/// sections of each output section in input order, cold and hot code at either end of .text
+pub fn output_section_rank(name: &str) -> usize {
+    output_section_index(name).unwrap_or(usize::MAX)
+}
+
+/// mips wants .dynsym to end with the global symbols that have a GOT entry, in the same order
+/// as their entries in the GOT. DT_MIPS_GOTSYM is the index of the first of them.
+/// reorders symbols that way, keeping everything else in place,
+/// and returns DT_MIPS_GOTSYM along with a table from old to new symbol index,
+/// so relocations can be fixed up.
+pub fn mips_order_dynsym(symbols: &mut Vec<Symbol>, got: &[String]) -> (u64, Vec<u32>) {
+    let in_got = |sym: &Symbol| !sym.name.is_empty() && got.iter().any(|n| n == &sym.name);
+
+    let mut order: Vec<usize> = (0..symbols.len()).filter(|&i| !in_got(&symbols[i])).collect();
+    let gotsym = order.len() as u64;
+    for name in got {
+        if let Some(i) = symbols.iter().position(|s| &s.name == name) {
+            order.push(i);
+        }
+    }
+
+    let mut remap = vec![0; symbols.len()];
+    for (new, &old) in order.iter().enumerate() {
+        remap[old] = new as u32;
+    }
+    let old = std::mem::take(symbols);
+    let mut old: Vec<Option<Symbol>> = old.into_iter().map(Some).collect();
+    for i in order {
+        if let Some(sym) = old[i].take() {
+            symbols.push(sym);
+        }
+    }
+
+    (gotsym, remap)
+}
+
+/// the value of the ppc64 TOC pointer (r2, the .TOC. symbol) for a laid out elf.
+/// by convention it points 0x8000 bytes into the table starting at .got, or .toc
+/// if there is no .got, so signed 16 bit offsets reach the first 64KiB
+pub fn ppc64_toc_base(elf: &Elf) -> Option<u64> {
+    [".got", ".toc"]
+        .iter()
+        .filter_map(|name| elf.sections.iter().find(|s| &s.name == name))
+        .next()
+        .map(|s| s.header.addr + 0x8000)
+}
+
+/// the kinds of input that carry code for link time optimization, see lto_kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lto {
+    /// gcc -flto: gcc's intermediate language in .gnu.lto_* sections and no code
+    GccSlim,
+    /// gcc -flto -ffat-lto-objects: the intermediate language next to the native code
+    GccFat,
+    /// clang -flto: a file of llvm bitcode, not an elf file at all
+    LlvmBitcode,
+    /// bitcode in .llvm.lto, .llvmbc or __llvm.embedded next to the native code,
+    /// from clang -ffat-lto-objects or -fembed-bitcode
+    LlvmFat,
+}
+
+impl Lto {
+    /// fat objects have machine code and link like any other, the rest can't be linked
+    pub fn is_native(&self) -> bool {
+        match *self {
+            Lto::GccFat | Lto::LlvmFat => true,
+            Lto::GccSlim | Lto::LlvmBitcode => false,
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match *self {
+            Lto::GccSlim => "a gcc LTO object",
+            Lto::GccFat => "a gcc fat LTO object",
+            Lto::LlvmBitcode => "LLVM bitcode",
+            Lto::LlvmFat => "an object with embedded LLVM bitcode",
+        }
+    }
+}
+
+/// whether an elf file has code for link time optimization, from its section names.
+/// gcc's intermediate language is in .gnu.lto_* sections, and an object without
+/// a single allocated byte of content besides them has nothing else
+pub fn lto_kind(elf: &Elf) -> Option<Lto> {
+    let has = |f: &dyn Fn(&str) -> bool| elf.sections.iter().any(|s| f(&s.name));
+    let native = elf.sections.iter().any(|s| {
+        s.header.flags.contains(types::SectionFlags::ALLOC)
+            && s.header.shtype != types::SectionType::NOBITS
+            && s.header.size > 0
+    });
+    if has(&|n| n.starts_with(".gnu.lto_")) {
+        return Some(if native { Lto::GccFat } else { Lto::GccSlim });
+    }
+    if has(&|n| n == ".llvm.lto" || n == ".llvmbc" || n == "__llvm.embedded") {
+        return Some(if native { Lto::LlvmFat } else { Lto::LlvmBitcode });
+    }
+    None
+}
+
+/**
+ * an input as an Elf that can be linked. elf files without LTO code and fat LTO objects are
+ * parsed as they are. LTO objects and llvm bitcode go through compile, which gets the name
+ * and the bytes and returns a native relocatable object, the way a linker plugin would.
+ * without one, or when what it returns still isn't native, they are an Error::LtoObject
+ * instead of the opaque bytes making it into the output
+ */
+pub fn native_object<F>(name: &str, data: Vec<u8>, compile: Option<F>) -> Result<Elf, Error>
+where
+    F: FnOnce(&str, &[u8]) -> Result<Vec<u8>, Error>,
+{
+    let kind = if filetype::filetype(Cursor::new(&data))? == filetype::FileType::LlvmBitcode {
+        Lto::LlvmBitcode
+    } else {
+        let elf = Elf::from_reader(&mut Cursor::new(&data))?;
+        match lto_kind(&elf) {
+            Some(kind) if !kind.is_native() => kind,
+            _ => return Ok(elf),
+        }
+    };
+    let native = match compile {
+        Some(compile) => compile(name, &data)?,
+        None => return Err(Error::LtoObject(String::from(name), kind.describe())),
+    };
+    let elf = Elf::from_reader(&mut Cursor::new(native))?;
+    match lto_kind(&elf) {
+        Some(kind) if !kind.is_native() => {
+            Err(Error::LtoObject(String::from(name), kind.describe()))
+        }
+        _ => Ok(elf),
+    }
+}
+
+/// whether a section only holds information for debuggers. DWARF keeps it in .debug_*
+/// sections, and compressed the old gnu way in .zdebug_* sections
+pub fn is_debug_section(name: &str) -> bool {
+    name.starts_with(".debug_") || name.starts_with(".zdebug_")
+}
+
+/// where an input section went: the output section, its offset there, and its size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlacedSection {
+    pub shndx: usize,
+    pub offset: u64,
+    pub size: u64,
+}
+
+/**
+ * where the input sections of a link ended up in the output, by the name of their object and
+ * their index in it. everything that has to follow code and data from the inputs to the
+ * output asks this one table: relocating debug info, writing a map file, folding identical
+ * code. sections that were not linked are not in it
+ */
+#[derive(Debug, Default, Clone)]
+pub struct SectionRemap {
+    placed: HashMap<(String, usize), PlacedSection>,
+}
+
+impl SectionRemap {
+    pub fn new() -> SectionRemap {
+        SectionRemap::default()
+    }
+
+    /// record where section shndx of object went, replacing what was recorded before
+    pub fn insert(&mut self, object: &str, shndx: usize, placed: PlacedSection) {
+        self.placed.insert((String::from(object), shndx), placed);
+    }
+
+    pub fn get(&self, object: &str, shndx: usize) -> Option<PlacedSection> {
+        self.placed.get(&(String::from(object), shndx)).cloned()
+    }
+
+    /// the output section and offset of offset in an input section. the end of the section
+    /// maps too, as symbols that mark the end of something are there
+    pub fn map(&self, object: &str, shndx: usize, offset: u64) -> Option<(usize, u64)> {
+        self.get(object, shndx)
+            .filter(|p| offset <= p.size)
+            .map(|p| (p.shndx, p.offset + offset))
+    }
+
+    /// the address of offset in an input section, given the laid out output sections
+    pub fn address(
+        &self,
+        sections: &[Section],
+        object: &str,
+        shndx: usize,
+        offset: u64,
+    ) -> Option<u64> {
+        self.map(object, shndx, offset)
+            .and_then(|(i, offset)| sections.get(i).map(|sec| sec.header.addr + offset))
+    }
+
+    /// the input section that an offset in an output section came from, and the offset in it
+    pub fn input_at(&self, shndx: usize, offset: u64) -> Option<(&str, usize, u64)> {
+        self.placed
+            .iter()
+            .find(|&(_, p)| p.shndx == shndx && p.offset <= offset && offset < p.offset + p.size)
+            .map(|(&(ref object, i), p)| (object.as_str(), i, offset - p.offset))
+    }
+
+    /// all placed input sections in output order, the way a map file lists them
+    pub fn iter(&self) -> Vec<(&str, usize, PlacedSection)> {
+        let mut r: Vec<(&str, usize, PlacedSection)> = self
+            .placed
+            .iter()
+            .map(|(&(ref object, i), p)| (object.as_str(), i, *p))
+            .collect();
+        r.sort_by_key(|&(object, i, p)| (p.shndx, p.offset, p.size, object, i));
+        r
+    }
+
+    pub fn len(&self) -> usize {
+        self.placed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.placed.is_empty()
+    }
+}
+
+/**
+ * the debug sections of one input object and their relocations, taken from it before it is
+ * split up for linking, for merge_debug_sections.
+ * compressed sections are left out, as their relocations apply to the inflated content
+ */
+#[derive(Debug, Default, Clone)]
+pub struct DebugInput {
+    /// index in the object and the section itself, with raw content
+    pub sections: Vec<(usize, Section)>,
+    /// index of the debug section they apply to, whether they are REL, and the relocations
+    pub relocations: Vec<(usize, bool, Vec<Relocation>)>,
+    /// the symbol table the relocations refer to
+    pub symbols: Vec<Symbol>,
+}
+
+impl DebugInput {
+    pub fn from_elf(elf: &mut Elf) -> Result<DebugInput, Error> {
+        let mut r = DebugInput::default();
+        for i in 0..elf.sections.len() {
+            let sec = &elf.sections[i];
+            if is_debug_section(&sec.name)
+                && !sec.header.flags.contains(types::SectionFlags::ALLOC)
+                && !sec.header.flags.contains(types::SectionFlags::COMPRESSED)
+                && sec.header.shtype == types::SectionType::PROGBITS
+            {
+                r.sections.push((i, sec.clone()));
+            }
+        }
+        if r.sections.is_empty() {
+            return Ok(r);
+        }
+        let mut symtab = None;
+        for i in 0..elf.sections.len() {
+            let rel = match elf.sections[i].header.shtype {
+                types::SectionType::RELA => false,
+                types::SectionType::REL => true,
+                _ => continue,
+            };
+            let target = elf.sections[i].header.info as usize;
+            if !r.sections.iter().any(|&(j, _)| j == target) {
+                continue;
+            }
+            elf.load_at(i)?;
+            let sec = &elf.sections[i];
+            let relocs = match sec.content.as_relocations() {
+                Some(relocs) => relocs.clone(),
+                None => return Err(Error::UnexpectedSectionContent.at(Elf::location(i, sec))),
+            };
+            symtab = Some(sec.header.link as usize);
+            r.relocations.push((target, rel, relocs));
+        }
+        if let Some(i) = symtab {
+            elf.load_at(i)?;
+            let sec = &elf.sections[i];
+            r.symbols = match sec.content.as_symbols() {
+                Some(symbols) => symbols.clone(),
+                None => return Err(Error::LinkedSectionIsNotSymtab.at(Elf::location(i, sec))),
+            };
+        }
+        for &mut (i, ref mut sec) in &mut r.sections {
+            elf.load_at(i)?;
+            if sec.content.as_raw().is_none() {
+                sec.content = elf.sections[i].content.clone();
+            }
+        }
+        Ok(r)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+}
+
+/**
+ * the debug sections of all inputs, concatenated by name in the order of the inputs, with
+ * their relocations applied.
+ * DWARF refers to other debug sections by offset from their start, so a symbol in a debug
+ * section resolves to where the part of its input starts in the merged section. for every
+ * other symbol value(input, symbol) gives the address it was placed at, or None for code that
+ * wasn't linked, which resolves to 0 like ld does it
+ */
+pub fn merge_debug_sections<F>(
+    eh: &Header,
+    inputs: &[DebugInput],
+    mut value: F,
+) -> Result<Vec<Section>, Error>
+where
+    F: FnMut(usize, &Symbol) -> Option<u64>,
+{
+    let handler = match arch::handler(eh) {
+        Some(h) => h,
+        None => return Err(Error::UnsupportedMachineTypeForRelocation(eh.machine.clone())),
+    };
+
+    let mut merged: Vec<Section> = Vec::new();
+    // input, index in the input -> output section, offset in it
+    let mut placed = HashMap::new();
+    for (n, input) in inputs.iter().enumerate() {
+        for &(i, ref sec) in &input.sections {
+            let data = match sec.content.as_raw() {
+                Some(data) => data,
+                None => return Err(Error::UnexpectedSectionContent),
+            };
+            let o = match merged.iter().position(|m| m.name == sec.name) {
+                Some(o) => o,
+                None => {
+                    let mut m = Section::new(
+                        sec.name.clone(),
+                        sec.header.shtype.clone(),
+                        sec.header.flags,
+                        SectionContent::Raw(Vec::new()),
+                        0,
+                        0,
+                    );
+                    m.header.entsize = sec.header.entsize;
+                    merged.push(m);
+                    merged.len() - 1
+                }
+            };
+            let m = &mut merged[o];
+            let align = sec.header.addralign.max(1);
+            m.header.addralign = m.header.addralign.max(align);
+            if let Some(out) = m.content.as_raw_mut() {
+                let at = (out.len() as u64).div_ceil(align) * align;
+                out.resize(at as usize, 0);
+                out.extend_from_slice(data);
+                placed.insert((n, i), (o, at));
+            }
+        }
+    }
+
+    for (n, input) in inputs.iter().enumerate() {
+        for &(target, rel, ref relocs) in &input.relocations {
+            let (o, base) = placed[&(n, target)];
+            for r in relocs {
+                let sym = match input.symbols.get(r.sym as usize) {
+                    Some(sym) => sym,
+                    None => {
+                        let e = Error::InvalidRelocationSymbol(r.sym, input.symbols.len());
+                        return Err(e);
+                    }
+                };
+                let s = match sym.shndx {
+                    _ if r.sym == 0 => Some(0),
+                    SymbolSectionIndex::Section(k) => match placed.get(&(n, k as usize)) {
+                        Some(&(_, at)) => Some(at + sym.value),
+                        None => value(n, sym),
+                    },
+                    _ => value(n, sym),
+                };
+                let p = base + r.addr;
+                let out = match merged[o].content.as_raw_mut() {
+                    Some(out) => out,
+                    None => continue,
+                };
+                let place = match out.get_mut(p as usize..) {
+                    Some(place) => place,
+                    None => return Err(Error::RelocationOutOfBounds),
+                };
+                let a = if rel {
+                    implicit_addend(eh, handler.size(&r.rtype), place)?
+                } else {
+                    r.addend
+                };
+                let v = arch::RelocationValues {
+                    s: s.unwrap_or(0),
+                    a,
+                    p,
+                    z: sym.size,
+                    ..Default::default()
+                };
+                handler.apply(&r.rtype, &v, place, eh)?;
+            }
+        }
+    }
+    Ok(merged)
+}
+
+/// the addend a REL relocation keeps in the field it relocates
+fn implicit_addend(eh: &Header, size: Option<usize>, mut place: &[u8]) -> Result<i64, Error> {
+    use std::io::Read;
+    if place.len() < size.unwrap_or(0) {
+        return Err(Error::RelocationOutOfBounds);
+    }
+    Ok(match size {
+        Some(4) => elf_read_u32!(eh, place)? as i32 as i64,
+        Some(8) => elf_read_u64!(eh, place)? as i64,
+        _ => 0,
+    })
+}
+
+/// --strip-debug and --strip-all, what a link leaves out of its output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strip {
+    #[default]
+    None,
+    /// the debug sections of the inputs
+    Debug,
+    /// the debug sections, and .symtab with its .strtab, so only the dynamic symbols remain
+    All,
+}
+
+impl Strip {
+    /// whether the debug sections of the inputs are collected at all
+    pub fn keeps_debug(&self) -> bool {
+        *self == Strip::None
+    }
+
+    pub fn keeps_symtab(&self) -> bool {
+        *self != Strip::All
+    }
+}
+
+/**
+ * remove all debug sections and the relocation sections for them, like strip --strip-debug.
+ * compressed ones go too, and the symbols that were defined in them
+ */
+pub fn strip_debug(elf: &mut Elf) -> Result<(), Error> {
+    let debug: HashSet<usize> = (0..elf.sections.len())
+        .filter(|&i| {
+            let sec = &elf.sections[i];
+            is_debug_section(&sec.name) && !sec.header.flags.contains(types::SectionFlags::ALLOC)
+        })
+        .collect();
+    if debug.is_empty() {
+        return Ok(());
+    }
+    let is_reloc = |sec: &Section| {
+        sec.header.shtype == types::SectionType::RELA
+            || sec.header.shtype == types::SectionType::REL
+    };
+    for i in 0..elf.sections.len() {
+        if elf.sections[i].header.shtype != types::SectionType::SYMTAB {
+            continue;
+        }
+        elf.load_at(i)?;
+        let symbols = match elf.sections[i].content.as_symbols_mut() {
+            Some(symbols) => symbols,
+            None => continue,
+        };
+        // the relocations that stay refer to the symbols by index
+        let mut remap = Vec::with_capacity(symbols.len());
+        let mut kept = 0;
+        for sym in symbols.iter() {
+            remap.push(kept);
+            match sym.shndx {
+                SymbolSectionIndex::Section(k) if debug.contains(&(k as usize)) => {}
+                _ => kept += 1,
+            }
+        }
+        symbols.retain(|sym| match sym.shndx {
+            SymbolSectionIndex::Section(k) => !debug.contains(&(k as usize)),
+            _ => true,
+        });
+        for j in 0..elf.sections.len() {
+            let sec = &elf.sections[j];
+            if !is_reloc(sec) || sec.header.link as usize != i
+                || debug.contains(&(sec.header.info as usize))
+            {
+                continue;
+            }
+            elf.load_at(j)?;
+            if let Some(relocs) = elf.sections[j].content.as_relocations_mut() {
+                for r in relocs {
+                    r.sym = remap.get(r.sym as usize).cloned().unwrap_or(0);
+                }
+            }
+        }
+    }
+    let mut i = elf.sections.len();
+    while i > 1 {
+        i -= 1;
+        let sec = &elf.sections[i];
+        if debug.contains(&i) || (is_reloc(sec) && debug.contains(&(sec.header.info as usize))) {
+            elf.remove_section(i)?;
+        }
+    }
     Ok(())
 }
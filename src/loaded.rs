@@ -0,0 +1,91 @@
+/*!
+ * access to section content that can't forget to load it first.
+ *
+ * section content is raw bytes until Elf::load_at parses it, and the as_ accessors of
+ * SectionContent return None for raw content, so code that skips the load compiles and then
+ * unwraps a None. Elf::loaded_section loads before handing out a LoadedSection, whose accessors
+ * fail with an error that says which section had the content they didn't expect.
+ */
+
+use std::ops::{Deref, DerefMut};
+
+use dynamic::Dynamic;
+use elf::Elf;
+use error::Error;
+use relocation::Relocation;
+use section::Section;
+use strtab::Strtab;
+use symbol::Symbol;
+
+/// a section that has been loaded as far as elfkit can parse its type, see Elf::loaded_section
+pub struct LoadedSection<'a> {
+    index: usize,
+    section: &'a mut Section,
+}
+
+impl Elf {
+    /**
+     * the section at index i, loaded along with the sections it links to.
+     * fails with Error::SectionIndexOutOfRange for an index past the end
+     * and with whatever load_at failed with
+     */
+    pub fn loaded_section(&mut self, i: usize) -> Result<LoadedSection<'_>, Error> {
+        if i >= self.sections.len() {
+            return Err(Error::SectionIndexOutOfRange(i));
+        }
+        self.load_at(i)?;
+        Ok(LoadedSection {
+            index: i,
+            section: &mut self.sections[i],
+        })
+    }
+}
+
+impl<'a> LoadedSection<'a> {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    fn unexpected(&self) -> Error {
+        Error::UnexpectedSectionContent.at(Elf::location(self.index, self.section))
+    }
+
+    pub fn symbols(self) -> Result<&'a mut Vec<Symbol>, Error> {
+        let e = self.unexpected();
+        self.section.content.as_symbols_mut().ok_or(e)
+    }
+
+    pub fn relocations(self) -> Result<&'a mut Vec<Relocation>, Error> {
+        let e = self.unexpected();
+        self.section.content.as_relocations_mut().ok_or(e)
+    }
+
+    pub fn strtab(self) -> Result<&'a mut Strtab, Error> {
+        let e = self.unexpected();
+        self.section.content.as_strtab_mut().ok_or(e)
+    }
+
+    pub fn dynamic(self) -> Result<&'a mut Vec<Dynamic>, Error> {
+        let e = self.unexpected();
+        self.section.content.as_dynamic_mut().ok_or(e)
+    }
+
+    /// the bytes of a section elfkit has no parser for. NOBITS sections have none and fail
+    pub fn raw(self) -> Result<&'a mut Vec<u8>, Error> {
+        let e = self.unexpected();
+        self.section.content.as_raw_mut().ok_or(e)
+    }
+}
+
+impl<'a> Deref for LoadedSection<'a> {
+    type Target = Section;
+    fn deref(&self) -> &Section {
+        self.section
+    }
+}
+
+impl<'a> DerefMut for LoadedSection<'a> {
+    fn deref_mut(&mut self) -> &mut Section {
+        self.section
+    }
+}
@@ -0,0 +1,185 @@
+use std::io::Read;
+use elf::Elf;
+use error::Error;
+use header::Header;
+use section::SectionContent;
+use segment::SegmentHeader;
+use strtab::Strtab;
+use types;
+
+pub const NT_GNU_BUILD_ID: u32 = 3;
+
+/// what Elf::normalize canonicalizes
+#[derive(Debug, Clone)]
+pub struct NormalizeOptions {
+    /// build directory prefixes and what to replace them with, like -ffile-prefix-map=OLD=NEW.
+    /// the first matching prefix is used
+    pub prefix_map: Vec<(String, String)>,
+    /// owner and type of notes whose descriptor is zeroed, because it differs between builds
+    /// of the same source. the default is the GNU build id, a hash over everything including
+    /// the build paths
+    pub zero_notes: Vec<(String, u32)>,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        NormalizeOptions {
+            prefix_map: Vec::new(),
+            zero_notes: vec![(String::from("GNU"), NT_GNU_BUILD_ID)],
+        }
+    }
+}
+
+impl NormalizeOptions {
+    fn map(&self, s: &[u8]) -> Vec<u8> {
+        for (from, to) in &self.prefix_map {
+            if s.starts_with(from.as_bytes()) {
+                let mut r = to.as_bytes().to_vec();
+                r.extend_from_slice(&s[from.len()..]);
+                return r;
+            }
+        }
+        s.to_vec()
+    }
+}
+
+fn pad(len: usize, align: usize) -> usize {
+    (len + align - 1) & !(align - 1)
+}
+
+/// rewrite a section of notes with zeroed padding, and zeroed descriptors where asked
+fn normalize_notes(
+    raw: &[u8],
+    align: usize,
+    eh: &Header,
+    options: &NormalizeOptions,
+) -> Result<Vec<u8>, Error> {
+    let mut r = Vec::new();
+    let mut b = raw;
+    while !b.is_empty() {
+        if b.len() < 12 {
+            return Err(Error::InvalidNote("note header is truncated"));
+        }
+        let mut h = b;
+        let namesz = elf_read_u32!(eh, h)? as usize;
+        let descsz = elf_read_u32!(eh, h)? as usize;
+        let ntype = elf_read_u32!(eh, h)?;
+        let desc = 12 + pad(namesz, 4);
+        if desc > b.len() || descsz > b.len() - desc {
+            return Err(Error::InvalidNote("note is truncated"));
+        }
+        let name = &b[12..12 + namesz];
+        let owner = name.split(|c| *c == 0).next().unwrap_or(&[]);
+        let zero = options
+            .zero_notes
+            .iter()
+            .any(|&(ref o, t)| t == ntype && o.as_bytes() == owner);
+
+        // the same layout, with everything but the header, name and descriptor zeroed
+        let start = r.len();
+        let end = (desc + pad(descsz, align)).min(b.len());
+        r.resize(start + end, 0);
+        r[start..start + 12 + namesz].copy_from_slice(&b[..12 + namesz]);
+        if !zero {
+            r[start + desc..start + desc + descsz].copy_from_slice(&b[desc..desc + descsz]);
+        }
+        b = &b[end..];
+    }
+    Ok(r)
+}
+
+impl Elf {
+    /**
+     * canonicalize what differs between two builds of the same source, so that they become
+     * byte identical once written.
+     *
+     * build paths are replaced in string tables that aren't allocated and only referred to by
+     * loaded content, like .strtab. .dynstr is left alone, moving it would move the program.
+     * DWARF strings aren't touched either, raw .debug_info points into them by offset.
+     * notes get zero padding and the descriptors in zero_notes are zeroed.
+     * padding between sections is zero already, to_writer never writes it.
+     *
+     * unallocated sections are packed after the allocated ones, since string tables may change
+     * size. everything is loaded, store_all writes it back.
+     */
+    pub fn normalize(&mut self, options: &NormalizeOptions) -> Result<(), Error> {
+        self.load_all()?;
+
+        // string tables whose offsets must not change
+        let mut fixed = vec![false; self.sections.len()];
+        for (i, sec) in self.sections.iter().enumerate() {
+            if sec.header.flags.contains(types::SectionFlags::ALLOC) {
+                fixed[i] = true;
+            }
+            if let SectionContent::Raw(_) = sec.content {
+                if let Some(f) = fixed.get_mut(sec.header.link as usize) {
+                    *f = true;
+                }
+            }
+        }
+
+        let eh = &self.header;
+        for i in 0..self.sections.len() {
+            let at = Elf::location(i, &self.sections[i]);
+            let sec = &mut self.sections[i];
+            let link_fixed = fixed.get(sec.header.link as usize).cloned().unwrap_or(true);
+            let note = sec.header.shtype == types::SectionType::NOTE;
+            match sec.content {
+                SectionContent::Strtab(ref mut strtab) if !fixed[i] => {
+                    let mut mapped = Strtab::default();
+                    mapped.set_tail_merge(strtab.tail_merge());
+                    for (_, s) in strtab.iter() {
+                        mapped.insert(options.map(s));
+                    }
+                    *strtab = mapped;
+                }
+                SectionContent::Symbols(ref mut symbols) if !link_fixed => {
+                    for sym in symbols {
+                        sym.name = String::from_utf8_lossy(&options.map(sym.name.as_bytes()))
+                            .into_owned();
+                    }
+                }
+                SectionContent::Raw(ref mut raw) if note => {
+                    let align = if sec.header.addralign == 8 { 8 } else { 4 };
+                    *raw = normalize_notes(raw, align, eh, options).map_err(|e| e.at(at))?;
+                }
+                _ => {}
+            }
+        }
+        self.sync_all()?;
+        self.pack_unallocated();
+        Ok(())
+    }
+
+    /// place the sections that aren't loaded right after the ones that are, in the order they
+    /// were in, so gaps left by the linker or shrunk string tables don't end up in the file.
+    /// their address means nothing, so it's zeroed
+    fn pack_unallocated(&mut self) {
+        let phdrs = self.segments.len() * SegmentHeader::entsize(&self.header);
+        let mut end = (self.header.size() + phdrs) as u64;
+        let mut unallocated = Vec::new();
+        for (i, sec) in self.sections.iter().enumerate() {
+            if sec.header.shtype == types::SectionType::NULL {
+                continue;
+            }
+            if !sec.header.flags.contains(types::SectionFlags::ALLOC) {
+                unallocated.push(i);
+            } else if sec.header.shtype != types::SectionType::NOBITS {
+                end = end.max(sec.header.offset + sec.header.size);
+            }
+        }
+        unallocated.sort_by_key(|&i| self.sections[i].header.offset);
+
+        for i in unallocated {
+            let size = self.sections[i].size(&self.header) as u64;
+            let header = &mut self.sections[i].header;
+            let align = header.addralign.max(1);
+            end = end.div_ceil(align) * align;
+            header.offset = end;
+            header.addr = 0;
+            if header.shtype != types::SectionType::NOBITS {
+                end += size;
+            }
+        }
+    }
+}
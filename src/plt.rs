@@ -0,0 +1,178 @@
+use {types, Error, Header};
+use got::GotEntry;
+use relocation::RelocationType;
+use prelude::*;
+
+/// how calls to functions in other objects get to them, see generate
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PltStrategy {
+    /// the dynamic linker resolves a function on its first call, through the header
+    /// of .plt and the slots for the resolver at the start of .got.plt
+    #[default]
+    Lazy,
+    /// everything is resolved at load time, as with -z now. entries just jump through their slot
+    Now,
+    /// like Lazy, but every indirect jump goes through a retpoline, as with -z retpolineplt
+    Retpoline,
+}
+
+/// .got.plt starts with the address of .dynamic, then two slots the dynamic linker fills in
+/// for lazy binding: its handle for the object and the address of its resolver
+pub const RESERVED_GOT_SLOTS: usize = 3;
+
+impl PltStrategy {
+    /// the strategy the -z keywords of a linker command line ask for
+    pub fn from_z(keywords: &[String]) -> PltStrategy {
+        if keywords.iter().any(|k| k == "retpolineplt") {
+            return PltStrategy::Retpoline;
+        }
+        match keywords.iter().rev().find(|k| *k == "now" || *k == "lazy") {
+            Some(k) if k == "now" => PltStrategy::Now,
+            _ => PltStrategy::Lazy,
+        }
+    }
+
+    /// size of the code before the first entry
+    pub fn header_size(&self) -> usize {
+        match *self {
+            PltStrategy::Lazy => 16,
+            PltStrategy::Now => 0,
+            PltStrategy::Retpoline => 48,
+        }
+    }
+
+    pub fn entry_size(&self) -> usize {
+        match *self {
+            PltStrategy::Lazy => 16,
+            PltStrategy::Now => 8,
+            PltStrategy::Retpoline => 32,
+        }
+    }
+
+    /// size of .plt with n entries, which doesn't depend on any address
+    pub fn size(&self, n: usize) -> usize {
+        self.header_size() + n * self.entry_size()
+    }
+
+    /// address of the entry for the i'th symbol, which is what calls to it go to
+    pub fn entry_address(&self, plt: u64, i: usize) -> u64 {
+        plt + (self.header_size() + i * self.entry_size()) as u64
+    }
+
+    /// the dynamic linker binds lazily unless BIND_NOW is set
+    pub fn binds_now(&self) -> bool {
+        *self == PltStrategy::Now
+    }
+}
+
+/// the content of .plt and .got.plt
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plt {
+    pub code: Vec<u8>,
+    /// for SectionContent::Got. the JUMP_SLOT relocations go to .rela.plt,
+    /// which has the index of .got.plt as info
+    pub got: Vec<GotEntry>,
+}
+
+fn rel32(code: &mut [u8], at: usize, target: u64, next: u64) {
+    let v = target.wrapping_sub(next) as u32;
+    code[at..at + 4].copy_from_slice(&v.to_le_bytes());
+}
+
+/**
+ * the PLT for calls to symbols, which are indices into .dynsym, when .plt is at plt,
+ * .got.plt at got_plt and .dynamic at dynamic.
+ * the sizes don't depend on the addresses, so sections of the right size can be laid out
+ * first and filled in once the addresses are known. only x86_64 is supported
+ */
+pub fn generate(
+    strategy: PltStrategy,
+    eh: &Header,
+    symbols: &[u32],
+    plt: u64,
+    got_plt: u64,
+    dynamic: u64,
+) -> Result<Plt, Error> {
+    if eh.machine != types::Machine::X86_64 || eh.ident_class != types::Class::Class64 {
+        return Err(Error::UnsupportedMachineTypeForRelocation(eh.machine.clone()));
+    }
+
+    let mut code = vec![0; strategy.size(symbols.len())];
+    let mut got = vec![GotEntry::fixed(dynamic), GotEntry::fixed(0), GotEntry::fixed(0)];
+    let resolver = got_plt + 8;
+    match strategy {
+        PltStrategy::Lazy => {
+            // pushq GOT+8(%rip); jmp *GOT+16(%rip); nopl 0(%rax)
+            code[..16].copy_from_slice(&[
+                0xff, 0x35, 0, 0, 0, 0, 0xff, 0x25, 0, 0, 0, 0, 0x0f, 0x1f, 0x40, 0x00,
+            ]);
+            rel32(&mut code, 2, resolver, plt + 6);
+            rel32(&mut code, 8, resolver + 8, plt + 12);
+        }
+        PltStrategy::Now => {}
+        PltStrategy::Retpoline => {
+            // pushq GOT+8(%rip); mov GOT+16(%rip), %r11; then the thunk that jumps to %r11
+            // with a call and ret, so the return predictor traps speculation in the loop
+            code[..48].copy_from_slice(&[
+                0xff, 0x35, 0, 0, 0, 0, // pushq GOT+8(%rip)
+                0x4c, 0x8b, 0x1d, 0, 0, 0, 0, // mov GOT+16(%rip), %r11
+                0xe8, 0x0e, 0x00, 0x00, 0x00, // callq next
+                0xf3, 0x90, // loop: pause
+                0x0f, 0xae, 0xe8, // lfence
+                0xeb, 0xf9, // jmp loop
+                0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, // int3 up to 0x20
+                0x4c, 0x89, 0x1c, 0x24, // next: mov %r11, (%rsp)
+                0xc3, // ret
+                0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,
+            ]);
+            rel32(&mut code, 2, resolver, plt + 6);
+            rel32(&mut code, 9, resolver + 8, plt + 13);
+        }
+    }
+
+    for (i, &sym) in symbols.iter().enumerate() {
+        let slot = got_plt + ((RESERVED_GOT_SLOTS + i) * 8) as u64;
+        let at = strategy.header_size() + i * strategy.entry_size();
+        let entry = plt + at as u64;
+        let e = &mut code[at..at + strategy.entry_size()];
+        // what the slot holds until the dynamic linker binds it
+        let lazy = match strategy {
+            PltStrategy::Lazy => {
+                // jmp *slot(%rip); pushq $i; jmp .plt
+                e.copy_from_slice(&[
+                    0xff, 0x25, 0, 0, 0, 0, 0x68, 0, 0, 0, 0, 0xe9, 0, 0, 0, 0,
+                ]);
+                rel32(e, 2, slot, entry + 6);
+                e[7..11].copy_from_slice(&(i as u32).to_le_bytes());
+                rel32(e, 12, plt, entry + 16);
+                entry + 6
+            }
+            PltStrategy::Now => {
+                // jmp *slot(%rip); xchg %ax, %ax
+                e.copy_from_slice(&[0xff, 0x25, 0, 0, 0, 0, 0x66, 0x90]);
+                rel32(e, 2, slot, entry + 6);
+                0
+            }
+            PltStrategy::Retpoline => {
+                // mov slot(%rip), %r11; callq the thunk; jmp the loop; pushq $i; jmp .plt
+                e.copy_from_slice(&[
+                    0x4c, 0x8b, 0x1d, 0, 0, 0, 0, 0xe8, 0, 0, 0, 0, 0xe9, 0, 0, 0, 0, 0x68, 0, 0,
+                    0, 0, 0xe9, 0, 0, 0, 0, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,
+                ]);
+                rel32(e, 3, slot, entry + 7);
+                rel32(e, 8, plt + 0x20, entry + 12);
+                rel32(e, 13, plt + 0x12, entry + 17);
+                e[18..22].copy_from_slice(&(i as u32).to_le_bytes());
+                rel32(e, 23, plt, entry + 27);
+                entry + 17
+            }
+        };
+        got.push(GotEntry {
+            sym,
+            rtype: RelocationType::R_X86_64_JUMP_SLOT,
+            addend: 0,
+            value: lazy,
+        });
+    }
+    Ok(Plt { code, got })
+}
@@ -0,0 +1,254 @@
+use io::{Read, Write};
+use {types, Error, Header, Section, SectionContent};
+use prelude::*;
+
+/**
+ * program properties, from the NT_GNU_PROPERTY_TYPE_0 note in .note.gnu.property.
+ *
+ * the note describes features the whole file supports, such as the x86 control flow
+ * enforcement or aarch64 branch target identification. the kernel and the dynamic loader only
+ * turn a feature on when the PT_GNU_PROPERTY segment of every loaded file advertises it.
+ * other notes that share the section are dropped.
+ */
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GnuProperties {
+    pub properties: Vec<GnuProperty>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GnuProperty {
+    /// GNU_PROPERTY_X86_FEATURE_1_AND
+    X86Feature1(types::X86Feature1),
+    /// GNU_PROPERTY_AARCH64_FEATURE_1_AND
+    Aarch64Feature1(types::Aarch64Feature1),
+    /// type and data of a property that isn't modelled here
+    Other(u32, Vec<u8>),
+}
+
+pub const SECTION_NAME: &str = ".note.gnu.property";
+pub const NT_GNU_PROPERTY_TYPE_0: u32 = 5;
+const GNU_PROPERTY_AARCH64_FEATURE_1_AND: u32 = 0xc0000000;
+const GNU_PROPERTY_X86_FEATURE_1_AND: u32 = 0xc0000002;
+
+/// properties and the note descriptor are aligned to the word size
+fn alignment(eh: &Header) -> usize {
+    match eh.ident_class {
+        types::Class::Class64 => 8,
+        types::Class::Class32 => 4,
+    }
+}
+
+fn pad(len: usize, align: usize) -> usize {
+    (len + align - 1) & !(align - 1)
+}
+
+impl GnuProperty {
+    pub fn ptype(&self) -> u32 {
+        match self {
+            &GnuProperty::X86Feature1(_) => GNU_PROPERTY_X86_FEATURE_1_AND,
+            &GnuProperty::Aarch64Feature1(_) => GNU_PROPERTY_AARCH64_FEATURE_1_AND,
+            &GnuProperty::Other(ptype, _) => ptype,
+        }
+    }
+
+    /// the types above 0xc0000000 are processor specific, so the machine decides what they are
+    fn from_data(ptype: u32, mut data: &[u8], eh: &Header) -> Result<GnuProperty, Error> {
+        let r = match (&eh.machine, ptype, data.len()) {
+            (&types::Machine::X86_64, GNU_PROPERTY_X86_FEATURE_1_AND, 4)
+            | (&types::Machine::EM386, GNU_PROPERTY_X86_FEATURE_1_AND, 4) => {
+                GnuProperty::X86Feature1(types::X86Feature1::from_bits_retain(
+                    elf_read_u32!(eh, data)?,
+                ))
+            }
+            (&types::Machine::AARCH64, GNU_PROPERTY_AARCH64_FEATURE_1_AND, 4) => {
+                GnuProperty::Aarch64Feature1(types::Aarch64Feature1::from_bits_retain(
+                    elf_read_u32!(eh, data)?,
+                ))
+            }
+            _ => GnuProperty::Other(ptype, data.to_vec()),
+        };
+        Ok(r)
+    }
+
+    fn to_writer(&self, io: &mut Vec<u8>, eh: &Header) -> Result<(), Error> {
+        let mut data = Vec::new();
+        match self {
+            &GnuProperty::X86Feature1(f) => elf_write_u32!(eh, data, f.bits())?,
+            &GnuProperty::Aarch64Feature1(f) => elf_write_u32!(eh, data, f.bits())?,
+            &GnuProperty::Other(_, ref d) => data.extend_from_slice(d),
+        }
+        elf_write_u32!(eh, io, self.ptype())?;
+        elf_write_u32!(eh, io, data.len() as u32)?;
+        let end = io.len() + pad(data.len(), alignment(eh));
+        io.extend(data);
+        io.resize(end, 0);
+        Ok(())
+    }
+}
+
+impl GnuProperties {
+    pub fn from_reader<R>(mut io: R, eh: &Header) -> Result<GnuProperties, Error>
+    where
+        R: Read,
+    {
+        let mut b = Vec::new();
+        io.read_to_end(&mut b)?;
+
+        let mut r = GnuProperties::default();
+        let mut b = &b[..];
+        while !b.is_empty() {
+            if b.len() < 12 {
+                return Err(Error::InvalidProperties("note header is truncated"));
+            }
+            let mut h = b;
+            let namesz = elf_read_u32!(eh, h)? as usize;
+            let descsz = elf_read_u32!(eh, h)? as usize;
+            let ntype = elf_read_u32!(eh, h)?;
+            let desc = 12 + pad(namesz, 4);
+            if desc > b.len() || descsz > b.len() - desc {
+                return Err(Error::InvalidProperties("note is truncated"));
+            }
+            if ntype == NT_GNU_PROPERTY_TYPE_0 && &b[12..12 + namesz] == b"GNU\0" {
+                r.properties_from(&b[desc..desc + descsz], eh)?;
+            }
+            let end = desc + pad(descsz, alignment(eh));
+            b = &b[end.min(b.len())..];
+        }
+        Ok(r)
+    }
+
+    fn properties_from(&mut self, mut b: &[u8], eh: &Header) -> Result<(), Error> {
+        while !b.is_empty() {
+            if b.len() < 8 {
+                return Err(Error::InvalidProperties("property header is truncated"));
+            }
+            let mut h = b;
+            let ptype = elf_read_u32!(eh, h)?;
+            let datasz = elf_read_u32!(eh, h)? as usize;
+            if datasz > b.len() - 8 {
+                return Err(Error::InvalidProperties("property data is truncated"));
+            }
+            self.properties.push(GnuProperty::from_data(ptype, &b[8..8 + datasz], eh)?);
+            let end = 8 + pad(datasz, alignment(eh));
+            b = &b[end.min(b.len())..];
+        }
+        Ok(())
+    }
+
+    /// write a single note. properties are sorted by type, as the abi requires
+    pub fn to_writer<W>(&self, mut io: W, eh: &Header) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let mut sorted = self.properties.iter().collect::<Vec<&GnuProperty>>();
+        sorted.sort_by_key(|p| p.ptype());
+        let mut desc = Vec::new();
+        for p in sorted {
+            p.to_writer(&mut desc, eh)?;
+        }
+        elf_write_u32!(eh, io, 4)?;
+        elf_write_u32!(eh, io, desc.len() as u32)?;
+        elf_write_u32!(eh, io, NT_GNU_PROPERTY_TYPE_0)?;
+        io.write_all(b"GNU\0")?;
+        io.write_all(&desc)?;
+        Ok(())
+    }
+
+    pub fn x86_features(&self) -> types::X86Feature1 {
+        for p in &self.properties {
+            if let &GnuProperty::X86Feature1(f) = p {
+                return f;
+            }
+        }
+        types::X86Feature1::empty()
+    }
+
+    pub fn aarch64_features(&self) -> types::Aarch64Feature1 {
+        for p in &self.properties {
+            if let &GnuProperty::Aarch64Feature1(f) = p {
+                return f;
+            }
+        }
+        types::Aarch64Feature1::empty()
+    }
+
+    /// the .note.gnu.property section holding these properties.
+    /// linker::segments covers it with a PT_GNU_PROPERTY segment
+    pub fn section(&self, eh: &Header) -> Result<Section, Error> {
+        let mut b = Vec::new();
+        self.to_writer(&mut b, eh)?;
+        let mut sec = Section::new(
+            String::from(SECTION_NAME),
+            types::SectionType::NOTE,
+            types::SectionFlags::ALLOC,
+            SectionContent::Raw(b),
+            0,
+            0,
+        );
+        sec.header.addralign = alignment(eh) as u64;
+        Ok(sec)
+    }
+}
+
+/**
+ * the properties of an output linked from these objects.
+ * every input object has to be passed, one without a .note.gnu.property section as an empty
+ * GnuProperties, since it supports none of the features.
+ *
+ * feature bits are and-ed, so the output only claims ibt or bti if all of its code has the
+ * landing pads. other properties are kept if all objects have the same value.
+ */
+pub fn merge<'a, I>(objects: I) -> GnuProperties
+where
+    I: IntoIterator<Item = &'a GnuProperties>,
+{
+    let mut objects = objects.into_iter();
+    let mut r = match objects.next() {
+        Some(p) => p.clone(),
+        None => return GnuProperties::default(),
+    };
+    for other in objects {
+        r.properties = r.properties
+            .iter()
+            .filter_map(|p| merge_property(p, other))
+            .collect();
+    }
+    r.properties.retain(|p| match p {
+        &GnuProperty::X86Feature1(f) => !f.is_empty(),
+        &GnuProperty::Aarch64Feature1(f) => !f.is_empty(),
+        &GnuProperty::Other(..) => true,
+    });
+    r
+}
+
+fn merge_property(p: &GnuProperty, other: &GnuProperties) -> Option<GnuProperty> {
+    for q in &other.properties {
+        match (p, q) {
+            (&GnuProperty::X86Feature1(a), &GnuProperty::X86Feature1(b)) => {
+                return Some(GnuProperty::X86Feature1(a & b))
+            }
+            (&GnuProperty::Aarch64Feature1(a), &GnuProperty::Aarch64Feature1(b)) => {
+                return Some(GnuProperty::Aarch64Feature1(a & b))
+            }
+            (&GnuProperty::Other(..), &GnuProperty::Other(..)) if p.ptype() == q.ptype() => {
+                return if p == q { Some(p.clone()) } else { None };
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// parse the .note.gnu.property section of this file, if there is one
+pub fn gnu_properties(sections: &[Section], eh: &Header) -> Result<Option<GnuProperties>, Error> {
+    for sec in sections {
+        if sec.name != SECTION_NAME || sec.header.shtype != types::SectionType::NOTE {
+            continue;
+        }
+        return match sec.content {
+            SectionContent::Raw(ref raw) => Ok(Some(GnuProperties::from_reader(&raw[..], eh)?)),
+            _ => Err(Error::UnexpectedSectionContent),
+        };
+    }
+    Ok(None)
+}
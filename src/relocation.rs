@@ -1,7 +1,8 @@
-use std::io::{Read, Write};
+use io::{Read, Write};
 use {Error, Header, SectionContent};
 use types;
-use num_traits::{FromPrimitive, ToPrimitive};
+use arch::{self, RelocationHandler};
+use prelude::*;
 
 /**
 A Represents the addend used to compute the value of the relocatable field.
@@ -26,57 +27,302 @@ S Represents the value of the symbol whose index resides in the relocation entry
 The AMD64 ABI architectures uses only Elf64_Rela relocation entries
 with explicit addends. The r_addend member serves as the relocation addend.
  */
-#[allow(non_camel_case_types)]
-#[derive(Debug, Primitive, PartialEq, Clone)]
-pub enum RelocationType {
-    R_X86_64_NONE = 0,      // none none
-    R_X86_64_64 = 1,        // word64 S + A
-    R_X86_64_PC32 = 2,      // word32 S + A - P
-    R_X86_64_GOT32 = 3,     // word32 G + A
-    R_X86_64_PLT32 = 4,     // word32 L + A - P
-    R_X86_64_COPY = 5,      // none none
-    R_X86_64_GLOB_DAT = 6,  // wordclass S
-    R_X86_64_JUMP_SLOT = 7, // wordclass S
-    R_X86_64_RELATIVE = 8,  // wordclass B + A
-    R_X86_64_GOTPCREL = 9,  // word32 G + GOT + A - P
-    R_X86_64_32 = 10,       // word32 S + A
-    R_X86_64_32S = 11,      // word32 S + A
-    R_X86_64_16 = 12,       // word16 S + A
-    R_X86_64_PC16 = 13,     // word16 S + A - P
-    R_X86_64_8 = 14,        // word8 S + A
-    R_X86_64_PC8 = 15,      // word8 S + A - P
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelocationType(pub u32);
+impl RelocationType {
+    pub const R_X86_64_NONE: RelocationType = RelocationType(0); // none none
+    pub const R_X86_64_64: RelocationType = RelocationType(1); // word64 S + A
+    pub const R_X86_64_PC32: RelocationType = RelocationType(2); // word32 S + A - P
+    pub const R_X86_64_GOT32: RelocationType = RelocationType(3); // word32 G + A
+    pub const R_X86_64_PLT32: RelocationType = RelocationType(4); // word32 L + A - P
+    pub const R_X86_64_COPY: RelocationType = RelocationType(5); // none none
+    pub const R_X86_64_GLOB_DAT: RelocationType = RelocationType(6); // wordclass S
+    pub const R_X86_64_JUMP_SLOT: RelocationType = RelocationType(7); // wordclass S
+    pub const R_X86_64_RELATIVE: RelocationType = RelocationType(8); // wordclass B + A
+    pub const R_X86_64_GOTPCREL: RelocationType = RelocationType(9); // word32 G + GOT + A - P
+    pub const R_X86_64_32: RelocationType = RelocationType(10); // word32 S + A
+    pub const R_X86_64_32S: RelocationType = RelocationType(11); // word32 S + A
+    pub const R_X86_64_16: RelocationType = RelocationType(12); // word16 S + A
+    pub const R_X86_64_PC16: RelocationType = RelocationType(13); // word16 S + A - P
+    pub const R_X86_64_8: RelocationType = RelocationType(14); // word8 S + A
+    pub const R_X86_64_PC8: RelocationType = RelocationType(15); // word8 S + A - P
 
     /// ID of module containing symbol
-    R_X86_64_DTPMOD64 = 16, // word64
+    pub const R_X86_64_DTPMOD64: RelocationType = RelocationType(16); // word64
     /// Offset in TLS Block
-    R_X86_64_DTPOFF64 = 17, // word64
+    pub const R_X86_64_DTPOFF64: RelocationType = RelocationType(17); // word64
     /// Offset in initial TLS Block
-    R_X86_64_TPOFF64 = 18, // word64
+    pub const R_X86_64_TPOFF64: RelocationType = RelocationType(18); // word64
     /// PC Relative address to GD GOT block
-    R_X86_64_TLSGD = 19, // word32
+    pub const R_X86_64_TLSGD: RelocationType = RelocationType(19); // word32
     /// PC Relative address to LD GOT block
-    R_X86_64_TLSLD = 20, // word32
+    pub const R_X86_64_TLSLD: RelocationType = RelocationType(20); // word32
     /// Offset in TLS Block
-    R_X86_64_DTPOFF32 = 21, // word32
+    pub const R_X86_64_DTPOFF32: RelocationType = RelocationType(21); // word32
     /// PC Relative offset to IE GOT entry
-    R_X86_64_GOTTPOFF = 22, // word32
+    pub const R_X86_64_GOTTPOFF: RelocationType = RelocationType(22); // word32
     /// offset in initial TLS entry
-    R_X86_64_TPOFF32 = 23, // word32
-
-    R_X86_64_PC64 = 24,            // word64 S + A - P
-    R_X86_64_GOTOFF64 = 25,        // word64 S + A - GOT
-    R_X86_64_GOTPC32 = 26,         // word32 GOT + A - P
-    R_X86_64_SIZE32 = 32,          // word32 Z + A
-    R_X86_64_SIZE64 = 33,          // word64 Z + A
-    R_X86_64_GOTPC32_TLSDESC = 34, // word32
-    R_X86_64_TLSDESC_CALL = 35,    // none
-    R_X86_64_TLSDESC = 36,         // word64×2
-    R_X86_64_IRELATIVE = 37,       // wordclass indirect (B + A)
-    R_X86_64_RELATIVE64 = 38,      // word64 B + A
+    pub const R_X86_64_TPOFF32: RelocationType = RelocationType(23); // word32
+
+    pub const R_X86_64_PC64: RelocationType = RelocationType(24); // word64 S + A - P
+    pub const R_X86_64_GOTOFF64: RelocationType = RelocationType(25); // word64 S + A - GOT
+    pub const R_X86_64_GOTPC32: RelocationType = RelocationType(26); // word32 GOT + A - P
+    pub const R_X86_64_SIZE32: RelocationType = RelocationType(32); // word32 Z + A
+    pub const R_X86_64_SIZE64: RelocationType = RelocationType(33); // word64 Z + A
+    pub const R_X86_64_GOTPC32_TLSDESC: RelocationType = RelocationType(34); // word32
+    pub const R_X86_64_TLSDESC_CALL: RelocationType = RelocationType(35); // none
+    pub const R_X86_64_TLSDESC: RelocationType = RelocationType(36); // word64×2
+    pub const R_X86_64_IRELATIVE: RelocationType = RelocationType(37); // wordclass indirect (B + A)
+    pub const R_X86_64_RELATIVE64: RelocationType = RelocationType(38); // word64 B + A
 
     //hopefully these are ok to be treated as R_X86_64_GOTPCREL
-    R_X86_64_GOTPCRELX = 41,     // word32 G + GOT + A - P
-    R_X86_64_REX_GOTPCRELX = 42, //word32 G + GOT + A - P
+    pub const R_X86_64_GOTPCRELX: RelocationType = RelocationType(41); // word32 G + GOT + A - P
+    pub const R_X86_64_REX_GOTPCRELX: RelocationType = RelocationType(42); //word32 G + GOT + A - P
+
+    //mips, these overlap with the x86_64 values. see typename
+    pub const R_MIPS_NONE: RelocationType = RelocationType(0);
+    pub const R_MIPS_16: RelocationType = RelocationType(1);
+    pub const R_MIPS_32: RelocationType = RelocationType(2);
+    pub const R_MIPS_REL32: RelocationType = RelocationType(3);
+    pub const R_MIPS_26: RelocationType = RelocationType(4);
+    pub const R_MIPS_HI16: RelocationType = RelocationType(5);
+    pub const R_MIPS_LO16: RelocationType = RelocationType(6);
+    pub const R_MIPS_GPREL16: RelocationType = RelocationType(7);
+    pub const R_MIPS_LITERAL: RelocationType = RelocationType(8);
+    pub const R_MIPS_GOT16: RelocationType = RelocationType(9);
+    pub const R_MIPS_PC16: RelocationType = RelocationType(10);
+    pub const R_MIPS_CALL16: RelocationType = RelocationType(11);
+    pub const R_MIPS_GPREL32: RelocationType = RelocationType(12);
+    pub const R_MIPS_SHIFT5: RelocationType = RelocationType(16);
+    pub const R_MIPS_SHIFT6: RelocationType = RelocationType(17);
+    pub const R_MIPS_64: RelocationType = RelocationType(18);
+    pub const R_MIPS_GOT_DISP: RelocationType = RelocationType(19);
+    pub const R_MIPS_GOT_PAGE: RelocationType = RelocationType(20);
+    pub const R_MIPS_GOT_OFST: RelocationType = RelocationType(21);
+    pub const R_MIPS_GOT_HI16: RelocationType = RelocationType(22);
+    pub const R_MIPS_GOT_LO16: RelocationType = RelocationType(23);
+    pub const R_MIPS_SUB: RelocationType = RelocationType(24);
+    pub const R_MIPS_INSERT_A: RelocationType = RelocationType(25);
+    pub const R_MIPS_INSERT_B: RelocationType = RelocationType(26);
+    pub const R_MIPS_DELETE: RelocationType = RelocationType(27);
+    pub const R_MIPS_HIGHER: RelocationType = RelocationType(28);
+    pub const R_MIPS_HIGHEST: RelocationType = RelocationType(29);
+    pub const R_MIPS_CALL_HI16: RelocationType = RelocationType(30);
+    pub const R_MIPS_CALL_LO16: RelocationType = RelocationType(31);
+    pub const R_MIPS_SCN_DISP: RelocationType = RelocationType(32);
+    pub const R_MIPS_REL16: RelocationType = RelocationType(33);
+    pub const R_MIPS_ADD_IMMEDIATE: RelocationType = RelocationType(34);
+    pub const R_MIPS_PJUMP: RelocationType = RelocationType(35);
+    pub const R_MIPS_RELGOT: RelocationType = RelocationType(36);
+    pub const R_MIPS_JALR: RelocationType = RelocationType(37);
+    pub const R_MIPS_TLS_DTPMOD32: RelocationType = RelocationType(38);
+    pub const R_MIPS_TLS_DTPREL32: RelocationType = RelocationType(39);
+    pub const R_MIPS_TLS_DTPMOD64: RelocationType = RelocationType(40);
+    pub const R_MIPS_TLS_DTPREL64: RelocationType = RelocationType(41);
+    pub const R_MIPS_TLS_GD: RelocationType = RelocationType(42);
+    pub const R_MIPS_TLS_LDM: RelocationType = RelocationType(43);
+    pub const R_MIPS_TLS_DTPREL_HI16: RelocationType = RelocationType(44);
+    pub const R_MIPS_TLS_DTPREL_LO16: RelocationType = RelocationType(45);
+    pub const R_MIPS_TLS_GOTTPREL: RelocationType = RelocationType(46);
+    pub const R_MIPS_TLS_TPREL32: RelocationType = RelocationType(47);
+    pub const R_MIPS_TLS_TPREL64: RelocationType = RelocationType(48);
+    pub const R_MIPS_TLS_TPREL_HI16: RelocationType = RelocationType(49);
+    pub const R_MIPS_TLS_TPREL_LO16: RelocationType = RelocationType(50);
+    pub const R_MIPS_GLOB_DAT: RelocationType = RelocationType(51);
+    pub const R_MIPS_COPY: RelocationType = RelocationType(126);
+    pub const R_MIPS_JUMP_SLOT: RelocationType = RelocationType(127);
+
+    pub const R_ARM_NONE: RelocationType = RelocationType(0);
+    pub const R_ARM_PC24: RelocationType = RelocationType(1);
+    pub const R_ARM_ABS32: RelocationType = RelocationType(2);
+    pub const R_ARM_REL32: RelocationType = RelocationType(3);
+    pub const R_ARM_LDR_PC_G0: RelocationType = RelocationType(4);
+    pub const R_ARM_ABS16: RelocationType = RelocationType(5);
+    pub const R_ARM_ABS12: RelocationType = RelocationType(6);
+    pub const R_ARM_THM_ABS5: RelocationType = RelocationType(7);
+    pub const R_ARM_ABS8: RelocationType = RelocationType(8);
+    pub const R_ARM_SBREL32: RelocationType = RelocationType(9);
+    pub const R_ARM_THM_CALL: RelocationType = RelocationType(10);
+    pub const R_ARM_THM_PC8: RelocationType = RelocationType(11);
+    pub const R_ARM_BREL_ADJ: RelocationType = RelocationType(12);
+    pub const R_ARM_TLS_DESC: RelocationType = RelocationType(13);
+    pub const R_ARM_THM_SWI8: RelocationType = RelocationType(14);
+    pub const R_ARM_XPC25: RelocationType = RelocationType(15);
+    pub const R_ARM_THM_XPC22: RelocationType = RelocationType(16);
+    pub const R_ARM_TLS_DTPMOD32: RelocationType = RelocationType(17);
+    pub const R_ARM_TLS_DTPOFF32: RelocationType = RelocationType(18);
+    pub const R_ARM_TLS_TPOFF32: RelocationType = RelocationType(19);
+    pub const R_ARM_COPY: RelocationType = RelocationType(20);
+    pub const R_ARM_GLOB_DAT: RelocationType = RelocationType(21);
+    pub const R_ARM_JUMP_SLOT: RelocationType = RelocationType(22);
+    pub const R_ARM_RELATIVE: RelocationType = RelocationType(23);
+    pub const R_ARM_GOTOFF: RelocationType = RelocationType(24);
+    pub const R_ARM_BASE_PREL: RelocationType = RelocationType(25);
+    pub const R_ARM_GOT_BREL: RelocationType = RelocationType(26);
+    pub const R_ARM_PLT32: RelocationType = RelocationType(27);
+    pub const R_ARM_CALL: RelocationType = RelocationType(28);
+    pub const R_ARM_JUMP24: RelocationType = RelocationType(29);
+    pub const R_ARM_THM_JUMP24: RelocationType = RelocationType(30);
+    pub const R_ARM_BASE_ABS: RelocationType = RelocationType(31);
+    pub const R_ARM_TARGET1: RelocationType = RelocationType(38);
+    pub const R_ARM_V4BX: RelocationType = RelocationType(40);
+    pub const R_ARM_TARGET2: RelocationType = RelocationType(41);
+    pub const R_ARM_PREL31: RelocationType = RelocationType(42);
+    pub const R_ARM_MOVW_ABS_NC: RelocationType = RelocationType(43);
+    pub const R_ARM_MOVT_ABS: RelocationType = RelocationType(44);
+    pub const R_ARM_MOVW_PREL_NC: RelocationType = RelocationType(45);
+    pub const R_ARM_MOVT_PREL: RelocationType = RelocationType(46);
+    pub const R_ARM_THM_MOVW_ABS_NC: RelocationType = RelocationType(47);
+    pub const R_ARM_THM_MOVT_ABS: RelocationType = RelocationType(48);
+    pub const R_ARM_THM_MOVW_PREL_NC: RelocationType = RelocationType(49);
+    pub const R_ARM_THM_MOVT_PREL: RelocationType = RelocationType(50);
+    pub const R_ARM_THM_JUMP19: RelocationType = RelocationType(51);
+    pub const R_ARM_GOT_PREL: RelocationType = RelocationType(96);
+    pub const R_ARM_GNU_VTENTRY: RelocationType = RelocationType(100);
+    pub const R_ARM_GNU_VTINHERIT: RelocationType = RelocationType(101);
+    pub const R_ARM_THM_JUMP11: RelocationType = RelocationType(102);
+    pub const R_ARM_THM_JUMP8: RelocationType = RelocationType(103);
+    pub const R_ARM_TLS_GD32: RelocationType = RelocationType(104);
+    pub const R_ARM_TLS_LDM32: RelocationType = RelocationType(105);
+    pub const R_ARM_TLS_LDO32: RelocationType = RelocationType(106);
+    pub const R_ARM_TLS_IE32: RelocationType = RelocationType(107);
+    pub const R_ARM_TLS_LE32: RelocationType = RelocationType(108);
+    pub const R_ARM_TLS_LDO12: RelocationType = RelocationType(109);
+    pub const R_ARM_TLS_LE12: RelocationType = RelocationType(110);
+    pub const R_ARM_TLS_IE12GP: RelocationType = RelocationType(111);
+    pub const R_ARM_IRELATIVE: RelocationType = RelocationType(160);
+
+    pub const R_PPC64_NONE: RelocationType = RelocationType(0);
+    pub const R_PPC64_ADDR32: RelocationType = RelocationType(1);
+    pub const R_PPC64_ADDR24: RelocationType = RelocationType(2);
+    pub const R_PPC64_ADDR16: RelocationType = RelocationType(3);
+    pub const R_PPC64_ADDR16_LO: RelocationType = RelocationType(4);
+    pub const R_PPC64_ADDR16_HI: RelocationType = RelocationType(5);
+    pub const R_PPC64_ADDR16_HA: RelocationType = RelocationType(6);
+    pub const R_PPC64_ADDR14: RelocationType = RelocationType(7);
+    pub const R_PPC64_ADDR14_BRTAKEN: RelocationType = RelocationType(8);
+    pub const R_PPC64_ADDR14_BRNTAKEN: RelocationType = RelocationType(9);
+    pub const R_PPC64_REL24: RelocationType = RelocationType(10);
+    pub const R_PPC64_REL14: RelocationType = RelocationType(11);
+    pub const R_PPC64_REL14_BRTAKEN: RelocationType = RelocationType(12);
+    pub const R_PPC64_REL14_BRNTAKEN: RelocationType = RelocationType(13);
+    pub const R_PPC64_GOT16: RelocationType = RelocationType(14);
+    pub const R_PPC64_GOT16_LO: RelocationType = RelocationType(15);
+    pub const R_PPC64_GOT16_HI: RelocationType = RelocationType(16);
+    pub const R_PPC64_GOT16_HA: RelocationType = RelocationType(17);
+    pub const R_PPC64_COPY: RelocationType = RelocationType(19);
+    pub const R_PPC64_GLOB_DAT: RelocationType = RelocationType(20);
+    pub const R_PPC64_JMP_SLOT: RelocationType = RelocationType(21);
+    pub const R_PPC64_RELATIVE: RelocationType = RelocationType(22);
+    pub const R_PPC64_UADDR32: RelocationType = RelocationType(24);
+    pub const R_PPC64_UADDR16: RelocationType = RelocationType(25);
+    pub const R_PPC64_REL32: RelocationType = RelocationType(26);
+    pub const R_PPC64_PLT32: RelocationType = RelocationType(27);
+    pub const R_PPC64_PLTREL32: RelocationType = RelocationType(28);
+    pub const R_PPC64_PLT16_LO: RelocationType = RelocationType(29);
+    pub const R_PPC64_PLT16_HI: RelocationType = RelocationType(30);
+    pub const R_PPC64_PLT16_HA: RelocationType = RelocationType(31);
+    pub const R_PPC64_SECTOFF: RelocationType = RelocationType(33);
+    pub const R_PPC64_SECTOFF_LO: RelocationType = RelocationType(34);
+    pub const R_PPC64_SECTOFF_HI: RelocationType = RelocationType(35);
+    pub const R_PPC64_SECTOFF_HA: RelocationType = RelocationType(36);
+    pub const R_PPC64_ADDR30: RelocationType = RelocationType(37);
+    pub const R_PPC64_ADDR64: RelocationType = RelocationType(38);
+    pub const R_PPC64_ADDR16_HIGHER: RelocationType = RelocationType(39);
+    pub const R_PPC64_ADDR16_HIGHERA: RelocationType = RelocationType(40);
+    pub const R_PPC64_ADDR16_HIGHEST: RelocationType = RelocationType(41);
+    pub const R_PPC64_ADDR16_HIGHESTA: RelocationType = RelocationType(42);
+    pub const R_PPC64_UADDR64: RelocationType = RelocationType(43);
+    pub const R_PPC64_REL64: RelocationType = RelocationType(44);
+    pub const R_PPC64_PLT64: RelocationType = RelocationType(45);
+    pub const R_PPC64_PLTREL64: RelocationType = RelocationType(46);
+    pub const R_PPC64_TOC16: RelocationType = RelocationType(47);
+    pub const R_PPC64_TOC16_LO: RelocationType = RelocationType(48);
+    pub const R_PPC64_TOC16_HI: RelocationType = RelocationType(49);
+    pub const R_PPC64_TOC16_HA: RelocationType = RelocationType(50);
+    pub const R_PPC64_TOC: RelocationType = RelocationType(51);
+    pub const R_PPC64_PLTGOT16: RelocationType = RelocationType(52);
+    pub const R_PPC64_PLTGOT16_LO: RelocationType = RelocationType(53);
+    pub const R_PPC64_PLTGOT16_HI: RelocationType = RelocationType(54);
+    pub const R_PPC64_PLTGOT16_HA: RelocationType = RelocationType(55);
+    pub const R_PPC64_ADDR16_DS: RelocationType = RelocationType(56);
+    pub const R_PPC64_ADDR16_LO_DS: RelocationType = RelocationType(57);
+    pub const R_PPC64_GOT16_DS: RelocationType = RelocationType(58);
+    pub const R_PPC64_GOT16_LO_DS: RelocationType = RelocationType(59);
+    pub const R_PPC64_PLT16_LO_DS: RelocationType = RelocationType(60);
+    pub const R_PPC64_SECTOFF_DS: RelocationType = RelocationType(61);
+    pub const R_PPC64_SECTOFF_LO_DS: RelocationType = RelocationType(62);
+    pub const R_PPC64_TOC16_DS: RelocationType = RelocationType(63);
+    pub const R_PPC64_TOC16_LO_DS: RelocationType = RelocationType(64);
+    pub const R_PPC64_PLTGOT16_DS: RelocationType = RelocationType(65);
+    pub const R_PPC64_PLTGOT16_LO_DS: RelocationType = RelocationType(66);
+    pub const R_PPC64_TLS: RelocationType = RelocationType(67);
+    pub const R_PPC64_DTPMOD64: RelocationType = RelocationType(68);
+    pub const R_PPC64_TPREL16: RelocationType = RelocationType(69);
+    pub const R_PPC64_TPREL16_LO: RelocationType = RelocationType(70);
+    pub const R_PPC64_TPREL16_HI: RelocationType = RelocationType(71);
+    pub const R_PPC64_TPREL16_HA: RelocationType = RelocationType(72);
+    pub const R_PPC64_TPREL64: RelocationType = RelocationType(73);
+    pub const R_PPC64_DTPREL16: RelocationType = RelocationType(74);
+    pub const R_PPC64_DTPREL16_LO: RelocationType = RelocationType(75);
+    pub const R_PPC64_DTPREL16_HI: RelocationType = RelocationType(76);
+    pub const R_PPC64_DTPREL16_HA: RelocationType = RelocationType(77);
+    pub const R_PPC64_DTPREL64: RelocationType = RelocationType(78);
+    pub const R_PPC64_GOT_TLSGD16: RelocationType = RelocationType(79);
+    pub const R_PPC64_GOT_TLSGD16_LO: RelocationType = RelocationType(80);
+    pub const R_PPC64_GOT_TLSGD16_HI: RelocationType = RelocationType(81);
+    pub const R_PPC64_GOT_TLSGD16_HA: RelocationType = RelocationType(82);
+    pub const R_PPC64_GOT_TLSLD16: RelocationType = RelocationType(83);
+    pub const R_PPC64_GOT_TLSLD16_LO: RelocationType = RelocationType(84);
+    pub const R_PPC64_GOT_TLSLD16_HI: RelocationType = RelocationType(85);
+    pub const R_PPC64_GOT_TLSLD16_HA: RelocationType = RelocationType(86);
+    pub const R_PPC64_GOT_TPREL16_DS: RelocationType = RelocationType(87);
+    pub const R_PPC64_GOT_TPREL16_LO_DS: RelocationType = RelocationType(88);
+    pub const R_PPC64_GOT_TPREL16_HI: RelocationType = RelocationType(89);
+    pub const R_PPC64_GOT_TPREL16_HA: RelocationType = RelocationType(90);
+    pub const R_PPC64_GOT_DTPREL16_DS: RelocationType = RelocationType(91);
+    pub const R_PPC64_GOT_DTPREL16_LO_DS: RelocationType = RelocationType(92);
+    pub const R_PPC64_GOT_DTPREL16_HI: RelocationType = RelocationType(93);
+    pub const R_PPC64_GOT_DTPREL16_HA: RelocationType = RelocationType(94);
+    pub const R_PPC64_TPREL16_DS: RelocationType = RelocationType(95);
+    pub const R_PPC64_TPREL16_LO_DS: RelocationType = RelocationType(96);
+    pub const R_PPC64_TPREL16_HIGHER: RelocationType = RelocationType(97);
+    pub const R_PPC64_TPREL16_HIGHERA: RelocationType = RelocationType(98);
+    pub const R_PPC64_TPREL16_HIGHEST: RelocationType = RelocationType(99);
+    pub const R_PPC64_TPREL16_HIGHESTA: RelocationType = RelocationType(100);
+    pub const R_PPC64_DTPREL16_DS: RelocationType = RelocationType(101);
+    pub const R_PPC64_DTPREL16_LO_DS: RelocationType = RelocationType(102);
+    pub const R_PPC64_DTPREL16_HIGHER: RelocationType = RelocationType(103);
+    pub const R_PPC64_DTPREL16_HIGHERA: RelocationType = RelocationType(104);
+    pub const R_PPC64_DTPREL16_HIGHEST: RelocationType = RelocationType(105);
+    pub const R_PPC64_DTPREL16_HIGHESTA: RelocationType = RelocationType(106);
+    pub const R_PPC64_TLSGD: RelocationType = RelocationType(107);
+    pub const R_PPC64_TLSLD: RelocationType = RelocationType(108);
+    pub const R_PPC64_TOCSAVE: RelocationType = RelocationType(109);
+    pub const R_PPC64_ADDR16_HIGH: RelocationType = RelocationType(110);
+    pub const R_PPC64_ADDR16_HIGHA: RelocationType = RelocationType(111);
+    pub const R_PPC64_TPREL16_HIGH: RelocationType = RelocationType(112);
+    pub const R_PPC64_TPREL16_HIGHA: RelocationType = RelocationType(113);
+    pub const R_PPC64_DTPREL16_HIGH: RelocationType = RelocationType(114);
+    pub const R_PPC64_DTPREL16_HIGHA: RelocationType = RelocationType(115);
+    pub const R_PPC64_REL24_NOTOC: RelocationType = RelocationType(116);
+    pub const R_PPC64_ADDR64_LOCAL: RelocationType = RelocationType(117);
+    pub const R_PPC64_ENTRY: RelocationType = RelocationType(118);
+    pub const R_PPC64_JMP_IREL: RelocationType = RelocationType(247);
+    pub const R_PPC64_IRELATIVE: RelocationType = RelocationType(248);
+    pub const R_PPC64_REL16: RelocationType = RelocationType(249);
+    pub const R_PPC64_REL16_LO: RelocationType = RelocationType(250);
+    pub const R_PPC64_REL16_HI: RelocationType = RelocationType(251);
+    pub const R_PPC64_REL16_HA: RelocationType = RelocationType(252);
+
+    pub fn to_u32(&self) -> u32 {
+        let &RelocationType(v) = self;
+        v
+    }
+
+    pub fn typename(&self, eh: &Header) -> Option<&'static str> {
+        arch::handler(eh).and_then(|h| h.typename(self))
+    }
 }
 impl Default for RelocationType {
     fn default() -> Self {
@@ -84,6 +330,82 @@ impl Default for RelocationType {
     }
 }
 
+/// see Relocation::iter
+pub struct RelocationIter<'a> {
+    data: &'a [u8],
+    eh: &'a Header,
+    handler: &'static dyn RelocationHandler,
+    rela: bool,
+    check_types: bool,
+}
+
+impl<'a> RelocationIter<'a> {
+    fn new(
+        data: &'a [u8],
+        eh: &'a Header,
+        rela: bool,
+        check_types: bool,
+    ) -> Result<RelocationIter<'a>, Error> {
+        match arch::handler(eh) {
+            Some(handler) => Ok(RelocationIter {
+                data,
+                eh,
+                handler,
+                rela,
+                check_types,
+            }),
+            None => Err(Error::UnsupportedMachineTypeForRelocation(eh.machine.clone())),
+        }
+    }
+}
+
+impl<'a> Iterator for RelocationIter<'a> {
+    type Item = Result<Relocation, Error>;
+
+    fn next(&mut self) -> Option<Result<Relocation, Error>> {
+        let eh = self.eh;
+        let mut io = self.data;
+        let addr = elf_read_uclass!(eh, io).ok()?;
+        let info = elf_read_uclass!(eh, io).ok()?;
+        let addend = if !self.rela {
+            Ok(0)
+        } else {
+            match eh.ident_class {
+                types::Class::Class64 => elf_read_u64!(eh, io).map(|v| v as i64),
+                types::Class::Class32 => elf_read_u32!(eh, io).map(|v| v as i32 as i64),
+            }
+        };
+        let addend = match addend {
+            Ok(v) => v,
+            Err(e) => {
+                self.data = &[];
+                return Some(Err(Error::from(e)));
+            }
+        };
+        self.data = io;
+
+        let (sym, rtype) = self.handler.decode(info, eh);
+        if self.check_types && self.handler.typename(&rtype).is_none() {
+            return Some(Err(Error::InvalidRelocationType(rtype.to_u32())));
+        }
+        Some(Ok(Relocation {
+            addr,
+            sym,
+            rtype,
+            addend,
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let entsize = if self.rela {
+            Relocation::entsize(self.eh)
+        } else {
+            Relocation::entsize_rel(self.eh)
+        };
+        (0, Some(self.data.len() / entsize + 1))
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct Relocation {
     pub addr: u64,
@@ -93,78 +415,196 @@ pub struct Relocation {
 }
 
 impl Relocation {
+    /// size of an entry in a RELA section
     pub fn entsize(eh: &Header) -> usize {
-        match eh.machine {
-            types::Machine::X86_64 => 3 * 8,
-            _ => 0,
+        if !Relocation::supported(eh) {
+            return 0;
+        }
+        match eh.ident_class {
+            types::Class::Class64 => 3 * 8,
+            types::Class::Class32 => 3 * 4,
+        }
+    }
+
+    /// size of an entry in a REL section, which has no addend
+    pub fn entsize_rel(eh: &Header) -> usize {
+        if !Relocation::supported(eh) {
+            return 0;
+        }
+        match eh.ident_class {
+            types::Class::Class64 => 2 * 8,
+            types::Class::Class32 => 2 * 4,
         }
     }
 
+    fn supported(eh: &Header) -> bool {
+        arch::handler(eh).is_some()
+    }
+
     pub fn from_reader<R>(
-        mut io: R,
+        io: R,
         _: Option<&SectionContent>,
         eh: &Header,
     ) -> Result<SectionContent, Error>
     where
         R: Read,
     {
-        if eh.machine != types::Machine::X86_64 {
-            return Err(Error::UnsupportedMachineTypeForRelocation(
-                eh.machine.clone(),
-            ));
-        }
+        Relocation::read_all(io, eh, true, None)
+    }
 
-        let mut r = Vec::new();
+    /// like from_reader, but relocations of unknown type are kept and reported in warnings
+    pub fn from_reader_permissive<R>(
+        io: R,
+        _: Option<&SectionContent>,
+        eh: &Header,
+        warnings: &mut Vec<Error>,
+    ) -> Result<SectionContent, Error>
+    where
+        R: Read,
+    {
+        Relocation::read_all(io, eh, true, Some(warnings))
+    }
 
-        while let Ok(addr) = elf_read_u64!(eh, io) {
-            let info = match elf_read_u64!(eh, io) {
-                Ok(v) => v,
-                _ => break,
-            };
-
-            let sym = (info >> 32) as u32;
-            let rtype = (info & 0xffffffff) as u32;
-            let rtype = match RelocationType::from_u32(rtype) {
-                Some(v) => v,
-                None => {
-                    println!(
-                        "warning: unknown relocation type {} skipped while reading",
-                        rtype
-                    );
-                    elf_read_u64!(eh, io)?;
-                    continue;
-                }
-            };
+    /**
+     * the entries of the raw content of a RELA section, decoded as they are iterated instead
+     * of all at once like from_reader. an entry of a type elfkit doesn't know is an error,
+     * iterating can go on after it.
+     * like from_reader, a partial entry at the end is ignored, unless only the addend is missing
+     */
+    pub fn iter<'a>(data: &'a [u8], eh: &'a Header) -> Result<RelocationIter<'a>, Error> {
+        RelocationIter::new(data, eh, true, true)
+    }
 
-            let addend = elf_read_u64!(eh, io)?;
+    /// like iter, for a REL section. the entries get an addend of 0
+    pub fn iter_rel<'a>(data: &'a [u8], eh: &'a Header) -> Result<RelocationIter<'a>, Error> {
+        RelocationIter::new(data, eh, false, true)
+    }
 
-            r.push(Relocation {
-                addr: addr,
-                sym: sym,
-                rtype: rtype,
-                addend: addend as i64,
-            });
+    /// read a RELA section, or a REL section if rela is false. REL entries get an addend of 0
+    pub(crate) fn read_all<R>(
+        mut io: R,
+        eh: &Header,
+        rela: bool,
+        mut warnings: Option<&mut Vec<Error>>,
+    ) -> Result<SectionContent, Error>
+    where
+        R: Read,
+    {
+        let mut raw = Vec::new();
+        io.read_to_end(&mut raw)?;
+        // with warnings, unknown types are reported here instead of by the iterator
+        let iter = RelocationIter::new(&raw, eh, rela, warnings.is_none())?;
+        let mut r = Vec::new();
+        for reloc in iter {
+            let reloc = reloc?;
+            if let Some(ref mut w) = warnings {
+                if arch::handler(eh).and_then(|h| h.typename(&reloc.rtype)).is_none() {
+                    w.push(Error::InvalidRelocationType(reloc.rtype.to_u32()));
+                }
+            }
+            r.push(reloc);
         }
-
         Ok(SectionContent::Relocations(r))
     }
 
     pub fn to_writer<W>(
         &self,
-        mut io: W,
+        io: W,
         _: Option<&mut SectionContent>,
         eh: &Header,
     ) -> Result<(), Error>
     where
         W: Write,
     {
-        elf_write_u64!(eh, io, self.addr)?;
-
-        let info = (self.sym.to_u64().unwrap() << 32) + self.rtype.to_u64().unwrap();
-        elf_write_u64!(eh, io, info)?;
+        self.write(io, eh, true)
+    }
 
-        elf_write_u64!(eh, io, self.addend as u64)?;
+    /// write a RELA entry, or a REL entry if rela is false
+    pub(crate) fn write<W>(&self, mut io: W, eh: &Header, rela: bool) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let info = match arch::handler(eh) {
+            Some(h) => h.encode(self.sym, &self.rtype, eh),
+            None => {
+                return Err(Error::UnsupportedMachineTypeForRelocation(
+                    eh.machine.clone(),
+                ))
+            }
+        };
+        match eh.ident_class {
+            types::Class::Class64 => {
+                elf_write_u64!(eh, io, self.addr)?;
+                elf_write_u64!(eh, io, info)?;
+                if rela {
+                    elf_write_u64!(eh, io, self.addend as u64)?;
+                }
+            }
+            types::Class::Class32 => {
+                // r_info has 24 bits for the symbol and r_addend is signed
+                if self.sym > 0xff_ffff {
+                    return Err(Error::FieldOverflow("r_info of a 32 bit file", self.sym as u64));
+                }
+                if rela && self.addend != self.addend as i32 as i64 {
+                    let addend = self.addend as u64;
+                    return Err(Error::FieldOverflow("r_addend of a 32 bit file", addend));
+                }
+                elf_write_uclass!(eh, io, self.addr)?;
+                elf_write_u32!(eh, io, info as u32)?;
+                if rela {
+                    elf_write_u32!(eh, io, self.addend as u32)?;
+                }
+            }
+        }
 
         Ok(())
     }
 }
+
+/// what a SymbolicRelocation refers to, by name instead of by symbol index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// the symbol of that name in the symbol table the relocation section links to.
+    /// when there is none it is added as an undefined global
+    Symbol(String),
+    /// an offset into the named section, relative to the section's STT_SECTION symbol,
+    /// which is added as a local when there is none
+    Section(String, u64),
+}
+
+/**
+ * a relocation that names its target, for objects that are put together in code rather than
+ * read from a file. Elf::add_relocation keeps it until sync_all or store_all know the symbol
+ * indices and turn it into a Relocation.
+ */
+#[derive(Debug, Clone)]
+pub struct SymbolicRelocation {
+    pub addr: u64,
+    pub rtype: RelocationType,
+    pub target: Target,
+    pub addend: i64,
+}
+
+impl SymbolicRelocation {
+    pub fn new(addr: u64, rtype: RelocationType, target: Target) -> SymbolicRelocation {
+        SymbolicRelocation {
+            addr,
+            rtype,
+            target,
+            addend: 0,
+        }
+    }
+
+    pub fn with_addend(mut self, addend: i64) -> Self {
+        self.addend = addend;
+        self
+    }
+
+    /// the addend the Relocation gets, which includes the offset of a Target::Section
+    pub fn resolved_addend(&self) -> i64 {
+        match self.target {
+            Target::Symbol(_) => self.addend,
+            Target::Section(_, offset) => self.addend.wrapping_add(offset as i64),
+        }
+    }
+}
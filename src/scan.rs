@@ -0,0 +1,607 @@
+//! run a predicate over many elf files at once, like a package scanner looking for
+//! everything that links a certain symbol.
+//!
+//! paths can be elf files, ar archives, whose elf members are scanned, or directories,
+//! which are walked recursively. anything else is skipped.
+//! results are produced one at a time, so a scan over a whole filesystem doesn't hold
+//! more than one file in memory.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str;
+use std::time::UNIX_EPOCH;
+
+use tempfile::NamedTempFile;
+
+use archive;
+use elf::{Elf, ElfOptions};
+use error::Error;
+use filetype::{self, FileType};
+use header::Header;
+use section::SectionContent;
+use symbol::SymbolSectionIndex;
+use types;
+
+/// where an elf file came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    pub path: PathBuf,
+    /// the member name if the file is in an archive
+    pub member: Option<String>,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.member {
+            Some(ref member) => write!(f, "{}({})", self.path.display(), member),
+            None => write!(f, "{}", self.path.display()),
+        }
+    }
+}
+
+/// a file the predicate said something about, or one that couldn't be read
+pub type ScanResult<T> = Result<(Source, T), (Source, Error)>;
+
+/**
+ * scan the given paths in order. Scan is an iterator yielding the value of predicate for
+ * every file it returned Some for, and the error for every file that failed to parse.
+ *
+ * the predicate gets the parsed Elf with content not loaded yet, so it only pays for
+ * the sections it looks at, e.g. through contains_symbol or load_at.
+ */
+pub fn scan<P, F, T>(paths: &[P], predicate: F) -> Scan<F>
+where
+    P: AsRef<Path>,
+    F: FnMut(&Source, &mut Elf) -> Option<T>,
+{
+    Scan {
+        walk: Walk::new(paths),
+        archive: None,
+        predicate,
+        header_filter: None,
+        options: ElfOptions::untrusted(),
+    }
+}
+
+type HeaderFilter = Box<dyn FnMut(&Header) -> bool>;
+
+pub struct Scan<F> {
+    walk: Walk,
+    archive: Option<Archive>,
+    predicate: F,
+    header_filter: Option<HeaderFilter>,
+    options: ElfOptions,
+}
+
+impl<F> Scan<F> {
+    /// options for parsing each file, ElfOptions::untrusted() by default
+    pub fn options(mut self, options: ElfOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// skip files whose header doesn't pass the filter, before reading anything else.
+    /// this is much cheaper than rejecting them in the predicate
+    pub fn header_filter<H>(mut self, filter: H) -> Self
+    where
+        H: FnMut(&Header) -> bool + 'static,
+    {
+        self.header_filter = Some(Box::new(filter));
+        self
+    }
+}
+
+impl<F, T> Scan<F>
+where
+    F: FnMut(&Source, &mut Elf) -> Option<T>,
+{
+    fn check(&mut self, source: Source, data: &[u8]) -> Option<ScanResult<T>> {
+        if let Some(ref mut filter) = self.header_filter {
+            let mut b = [0; 64];
+            let n = data.len().min(64);
+            b[..n].copy_from_slice(&data[..n]);
+            match Header::peek(&b) {
+                Ok(ref eh) if n >= eh.size() && !filter(eh) => return None,
+                _ => {}
+            }
+        }
+        let mut io = Cursor::new(data);
+        match Elf::from_reader_with_options(&mut io, self.options.clone()) {
+            Ok(mut elf) => (self.predicate)(&source, &mut elf).map(|v| Ok((source, v))),
+            Err(e) => Some(Err((source, e))),
+        }
+    }
+
+    fn visit(&mut self, path: PathBuf) -> Option<ScanResult<T>> {
+        let source = Source { path, member: None };
+        let mut data = Vec::new();
+        let read = File::open(&source.path).and_then(|mut f| {
+            let t = filetype::filetype(&mut f)?;
+            match t {
+                FileType::Elf | FileType::Archive => f.read_to_end(&mut data).map(|_| t),
+                _ => Ok(t),
+            }
+        });
+        match read {
+            Ok(FileType::Elf) => self.check(source, &data),
+            Ok(FileType::Archive) => match Archive::parse(&data) {
+                Ok(members) => {
+                    self.archive = Some(Archive {
+                        path: source.path,
+                        data,
+                        members,
+                    });
+                    None
+                }
+                Err(e) => Some(Err((source, e))),
+            },
+            Ok(_) => None,
+            Err(e) => Some(Err((source, Error::from(e)))),
+        }
+    }
+}
+
+impl<F, T> Iterator for Scan<F>
+where
+    F: FnMut(&Source, &mut Elf) -> Option<T>,
+{
+    type Item = ScanResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(mut archive) = self.archive.take() {
+                if let Some((name, start, end)) = archive.members.pop_front() {
+                    let source = Source {
+                        path: archive.path.clone(),
+                        member: Some(name),
+                    };
+                    let r = if archive.data[start..end].starts_with(b"\x7fELF") {
+                        self.check(source, &archive.data[start..end])
+                    } else {
+                        None
+                    };
+                    self.archive = Some(archive);
+                    if r.is_some() {
+                        return r;
+                    }
+                }
+                continue;
+            }
+            let r = match self.walk.next()? {
+                Ok(path) => self.visit(path),
+                Err(e) => Some(Err(e)),
+            };
+            if r.is_some() {
+                return r;
+            }
+        }
+    }
+}
+
+/// the files under a set of paths, in order, with directories expanded
+struct Walk {
+    /// stack of paths left to visit, the next one last
+    paths: Vec<PathBuf>,
+}
+
+impl Walk {
+    fn new<P: AsRef<Path>>(paths: &[P]) -> Walk {
+        Walk {
+            paths: paths.iter().rev().map(|p| p.as_ref().to_owned()).collect(),
+        }
+    }
+
+    /// the content of a directory, sorted.
+    /// symlinks to directories are left out, they could form a loop
+    fn entries(dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut r = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_symlink() && path.is_dir() {
+                continue;
+            }
+            r.push(path);
+        }
+        r.sort();
+        Ok(r)
+    }
+}
+
+impl Iterator for Walk {
+    type Item = Result<PathBuf, (Source, Error)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let path = self.paths.pop()?;
+            let fail = |path, e| {
+                let source = Source { path, member: None };
+                Some(Err((source, Error::from(e))))
+            };
+            match fs::metadata(&path) {
+                Ok(ref meta) if meta.is_dir() => match Walk::entries(&path) {
+                    Ok(entries) => self.paths.extend(entries.into_iter().rev()),
+                    Err(e) => return fail(path, e),
+                },
+                Ok(_) => return Some(Ok(path)),
+                Err(e) => return fail(path, e),
+            }
+        }
+    }
+}
+
+/// a symbol name to look for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    Exact(String),
+    /// * matches any run of characters, ? any one, [a-z] and [!a-z] a class
+    Glob(String),
+}
+
+impl Pattern {
+    /// a glob if s contains any of *?[, otherwise an exact name
+    pub fn parse(s: &str) -> Pattern {
+        if s.contains(&['*', '?', '['][..]) {
+            Pattern::Glob(String::from(s))
+        } else {
+            Pattern::Exact(String::from(s))
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            &Pattern::Exact(ref s) => s == name,
+            &Pattern::Glob(ref g) => glob(g.as_bytes(), name.as_bytes()),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Pattern::Exact(ref s) | &Pattern::Glob(ref s) => s.fmt(f),
+        }
+    }
+}
+
+fn glob(p: &[u8], s: &[u8]) -> bool {
+    // backtrack to the last * only, which is enough since * can't match less than nothing
+    let (mut pi, mut si) = (0, 0);
+    let mut star = None;
+    while si < s.len() {
+        if pi < p.len() && p[pi] == b'*' {
+            star = Some((pi, si));
+            pi += 1;
+            continue;
+        }
+        if pi < p.len() {
+            if let Some(len) = glob_one(&p[pi..], s[si]) {
+                pi += len;
+                si += 1;
+                continue;
+            }
+        }
+        match star {
+            Some((spi, ssi)) => {
+                pi = spi + 1;
+                si = ssi + 1;
+                star = Some((spi, ssi + 1));
+            }
+            None => return false,
+        }
+    }
+    p[pi..].iter().all(|c| *c == b'*')
+}
+
+/// whether the pattern element at the start of p matches c, and how long the element is
+fn glob_one(p: &[u8], c: u8) -> Option<usize> {
+    match p[0] {
+        b'?' => Some(1),
+        b'[' => {
+            let negate = p.get(1) == Some(&b'!');
+            let start = if negate { 2 } else { 1 };
+            // a ] right at the start is part of the class
+            let end = p.iter().skip(start + 1).position(|x| *x == b']')? + start + 1;
+            let class = &p[start..end];
+            let mut found = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == b'-' {
+                    found |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    found |= class[i] == c;
+                    i += 1;
+                }
+            }
+            if found != negate {
+                Some(end + 1)
+            } else {
+                None
+            }
+        }
+        x if x == c => Some(1),
+        _ => None,
+    }
+}
+
+/**
+ * the defined, non local symbols of elf that match any of the patterns, as the index of the
+ * pattern and the symbol name. a symbol matching several patterns is reported for each.
+ * this is meant to be called from a scan predicate.
+ */
+pub fn matching_symbols(
+    elf: &mut Elf,
+    patterns: &[Pattern],
+) -> Result<Vec<(usize, String)>, Error> {
+    let mut r = Vec::new();
+    for i in 0..elf.sections.len() {
+        let shtype = &elf.sections[i].header.shtype;
+        if *shtype != types::SectionType::SYMTAB && *shtype != types::SectionType::DYNSYM {
+            continue;
+        }
+        elf.load_at(i)?;
+        if let SectionContent::Symbols(ref symbols) = elf.sections[i].content {
+            for sym in symbols {
+                if sym.bind == types::SymbolBind::LOCAL
+                    || sym.shndx == SymbolSectionIndex::Undefined
+                {
+                    continue;
+                }
+                for (k, p) in patterns.iter().enumerate() {
+                    if p.matches(&sym.name) {
+                        r.push((k, sym.name.clone()));
+                    }
+                }
+            }
+        }
+    }
+    Ok(r)
+}
+
+const INDEX_MAGIC: &str = "elfkit symbol index 1";
+
+/// the defined symbols of one elf file or archive member, as recorded in a SymbolIndex
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedObject {
+    /// the member name if the object is in an archive
+    pub member: Option<String>,
+    /// sorted
+    pub symbols: Vec<String>,
+}
+
+struct IndexEntry {
+    /// size, modification time in seconds and nanoseconds
+    stamp: (u64, u64, u32),
+    objects: Vec<IndexedObject>,
+}
+
+/**
+ * remembers the defined symbols of every file it was asked about in a sidecar file,
+ * so repeated symbol lookups over the same tree only stat the files that didn't change.
+ * files that contain no elf at all are remembered too, so they aren't opened again either.
+ *
+ * the index is a text file starting with a version line. every file is a
+ * "!size mtime path" line, followed by an "@member" line per elf, "@" for a plain elf file,
+ * and one symbol name per line.
+ */
+pub struct SymbolIndex {
+    path: PathBuf,
+    entries: HashMap<PathBuf, IndexEntry>,
+    dirty: bool,
+}
+
+impl SymbolIndex {
+    /// read the index at path. an index that doesn't exist yet, can't be read or was written
+    /// by a different version starts out empty
+    pub fn open<P: AsRef<Path>>(path: P) -> SymbolIndex {
+        let mut r = SymbolIndex {
+            path: path.as_ref().to_owned(),
+            entries: HashMap::new(),
+            dirty: false,
+        };
+        let mut text = String::new();
+        if File::open(&r.path).and_then(|mut f| f.read_to_string(&mut text)).is_err() {
+            return r;
+        }
+        let mut lines = text.lines();
+        if lines.next() != Some(INDEX_MAGIC) {
+            return r;
+        }
+        let mut current: Option<(PathBuf, IndexEntry)> = None;
+        for line in lines {
+            if let Some(file) = line.strip_prefix('!') {
+                if let Some((path, entry)) = current.take() {
+                    r.entries.insert(path, entry);
+                }
+                let mut parts = file.splitn(3, ' ');
+                let size = parts.next().and_then(|v| v.parse().ok());
+                let mut mtime = parts.next().unwrap_or("").splitn(2, '.');
+                let secs = mtime.next().and_then(|v| v.parse().ok());
+                let nanos = mtime.next().and_then(|v| v.parse().ok());
+                current = match (size, secs, nanos, parts.next()) {
+                    (Some(size), Some(secs), Some(nanos), Some(path)) => Some((
+                        PathBuf::from(path),
+                        IndexEntry {
+                            stamp: (size, secs, nanos),
+                            objects: Vec::new(),
+                        },
+                    )),
+                    // a damaged line drops the file, it's read again on the next lookup
+                    _ => None,
+                };
+            } else if let Some(&mut (_, ref mut entry)) = current.as_mut() {
+                if let Some(member) = line.strip_prefix('@') {
+                    entry.objects.push(IndexedObject {
+                        member: if member.is_empty() { None } else { Some(String::from(member)) },
+                        symbols: Vec::new(),
+                    });
+                } else if let Some(object) = entry.objects.last_mut() {
+                    object.symbols.push(String::from(line));
+                }
+            }
+        }
+        if let Some((path, entry)) = current.take() {
+            r.entries.insert(path, entry);
+        }
+        r
+    }
+
+    /// write the index back, if anything was added since it was opened
+    pub fn save(&mut self) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let mut paths: Vec<&PathBuf> = self.entries.keys().collect();
+        paths.sort();
+        let mut text = String::from(INDEX_MAGIC);
+        text.push('\n');
+        // the format is line based. paths and names with a line break are only kept in
+        // memory, which is as good as not indexing them
+        let printable = |s: &str| !s.contains('\n') && !s.contains('\r');
+        for path in paths {
+            let entry = &self.entries[path];
+            let mut members = entry.objects.iter().filter_map(|o| o.member.as_ref());
+            if !printable(&path.to_string_lossy()) || !members.all(|m| printable(m)) {
+                continue;
+            }
+            let (size, secs, nanos) = entry.stamp;
+            text += &format!("!{} {}.{} {}\n", size, secs, nanos, path.display());
+            for object in &entry.objects {
+                text += &format!("@{}\n", object.member.as_ref().map(|m| &m[..]).unwrap_or(""));
+                for sym in &object.symbols {
+                    text += sym;
+                    text.push('\n');
+                }
+            }
+        }
+        // write to a temporary of our own first, so a concurrent scan never reads half a file.
+        // it is removed again if anything fails
+        let dir = match self.path.parent() {
+            Some(d) if !d.as_os_str().is_empty() => d,
+            _ => Path::new("."),
+        };
+        let mut tmp = NamedTempFile::new_in(dir)?;
+        tmp.write_all(text.as_bytes())?;
+        tmp.persist(&self.path)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn stamp(path: &Path) -> io::Result<(u64, u64, u32)> {
+        let meta = fs::metadata(path)?;
+        let mtime = meta.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default();
+        Ok((meta.len(), mtime.as_secs(), mtime.subsec_nanos()))
+    }
+
+    /**
+     * the defined symbols of every elf in the file at path, from the index if the file didn't
+     * change since it was recorded. otherwise the file is read and recorded, unless any elf in
+     * it failed to parse, then the errors are returned and the file isn't recorded.
+     */
+    pub fn objects(&mut self, path: &Path) -> Result<&[IndexedObject], Vec<(Source, Error)>> {
+        let fail = |e: io::Error| {
+            let source = Source {
+                path: path.to_owned(),
+                member: None,
+            };
+            vec![(source, Error::from(e))]
+        };
+        let stamp = SymbolIndex::stamp(path).map_err(fail)?;
+        let fresh = self.entries.get(path).map(|e| e.stamp == stamp) == Some(true);
+        if !fresh {
+            let mut objects = Vec::new();
+            let mut errors = Vec::new();
+            let found = scan(&[path], |source, elf| {
+                let object = elf.defined_symbols().map(|symbols| {
+                    let mut symbols: Vec<String> = symbols.iter().cloned().collect();
+                    symbols.sort();
+                    IndexedObject {
+                        member: source.member.clone(),
+                        symbols,
+                    }
+                });
+                Some(object.map_err(|e| (source.clone(), e)))
+            });
+            for r in found {
+                match r {
+                    Ok((_, Ok(object))) => objects.push(object),
+                    Ok((_, Err(e))) | Err(e) => errors.push(e),
+                }
+            }
+            if !errors.is_empty() {
+                return Err(errors);
+            }
+            self.dirty = true;
+            self.entries.insert(path.to_owned(), IndexEntry { stamp, objects });
+        }
+        Ok(&self.entries[path].objects)
+    }
+
+    /**
+     * like scan with matching_symbols as predicate, but only files that changed since the
+     * last lookup are opened. every object with a matching symbol is returned with
+     * the index of the pattern and the symbol name, in the order scan would return them.
+     */
+    pub fn find<P>(
+        &mut self,
+        paths: &[P],
+        patterns: &[Pattern],
+    ) -> Vec<ScanResult<Vec<(usize, String)>>>
+    where
+        P: AsRef<Path>,
+    {
+        let mut r = Vec::new();
+        for file in Walk::new(paths) {
+            let file = match file {
+                Ok(file) => file,
+                Err(e) => {
+                    r.push(Err(e));
+                    continue;
+                }
+            };
+            match self.objects(&file) {
+                Ok(objects) => for object in objects {
+                    let mut matches = Vec::new();
+                    for sym in &object.symbols {
+                        for (k, p) in patterns.iter().enumerate() {
+                            if p.matches(sym) {
+                                matches.push((k, sym.clone()));
+                            }
+                        }
+                    }
+                    if !matches.is_empty() {
+                        let source = Source {
+                            path: file.clone(),
+                            member: object.member.clone(),
+                        };
+                        r.push(Ok((source, matches)));
+                    }
+                },
+                Err(errors) => r.extend(errors.into_iter().map(Err)),
+            }
+        }
+        r
+    }
+}
+
+/// an ar archive in memory, with the members that are left to scan
+struct Archive {
+    path: PathBuf,
+    data: Vec<u8>,
+    /// name, start and end of the content in data
+    members: VecDeque<(String, usize, usize)>,
+}
+
+impl Archive {
+    /// the members, without the symbol and name tables
+    fn parse(data: &[u8]) -> Result<VecDeque<(String, usize, usize)>, Error> {
+        Ok(archive::members(data)?
+            .into_iter()
+            .map(|m| (m.name, m.start, m.end))
+            .collect())
+    }
+}
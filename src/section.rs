@@ -4,10 +4,15 @@ use relocation::Relocation;
 use dynamic::Dynamic;
 use symbol::Symbol;
 use strtab::Strtab;
+use got::GotEntry;
 use types;
+use prelude::*;
 
-use std::io::{Read, Write};
-use std::io::BufWriter;
+use io::{Read, Write};
+use io::BufWriter;
+use core::any::Any;
+use core::convert::TryFrom;
+use core::fmt;
 
 #[derive(Default, Debug, Clone)]
 pub struct SectionHeader {
@@ -31,6 +36,8 @@ impl SectionHeader {
         } + 4 + 4
     }
 
+    /// flags that are not known to elfkit are kept as they are.
+    /// Elf::from_reader rejects them unless parsing permissively.
     pub fn from_reader<R>(io: &mut R, eh: &Header) -> Result<SectionHeader, Error>
     where
         R: Read,
@@ -45,10 +52,7 @@ impl SectionHeader {
         r.shtype = types::SectionType(reb);
 
         let reb = elf_read_uclass!(eh, br)?;
-        r.flags = match types::SectionFlags::from_bits(reb) {
-            Some(v) => v,
-            None => return Err(Error::InvalidSectionFlags(reb)),
-        };
+        r.flags = types::SectionFlags::from_bits_retain(reb);
         r.addr = elf_read_uclass!(eh, br)?;
         r.offset = elf_read_uclass!(eh, br)?;
         r.size = elf_read_uclass!(eh, br)?;
@@ -59,6 +63,19 @@ impl SectionHeader {
         Ok(r)
     }
 
+    /// section 0 is reserved and never has content. its header holds what doesn't fit into the
+    /// elf header: the number of sections in size, the index of the section name table in link
+    /// and the number of segments in info. each is 0 unless it's needed
+    pub fn null(shnum: usize, shstrndx: usize, phnum: usize) -> SectionHeader {
+        let shn_loreserve = types::SHN_LORESERVE as usize;
+        SectionHeader {
+            size: if shnum >= shn_loreserve { shnum as u64 } else { 0 },
+            link: if shstrndx >= shn_loreserve { shstrndx as u32 } else { 0 },
+            info: if phnum >= types::PN_XNUM as usize { phnum as u32 } else { 0 },
+            ..SectionHeader::default()
+        }
+    }
+
     pub fn to_writer<R>(&self, eh: &Header, io: &mut R) -> Result<(), Error>
     where
         R: Write,
@@ -79,31 +96,128 @@ impl SectionHeader {
     }
 }
 
+/**
+ * typed content of a section elfkit has no parser for, like .modinfo or .gopclntab.
+ * a parser registered in ElfOptions::content turns the raw bytes into one of these on load,
+ * store_all turns it back into bytes with to_writer.
+ */
+pub trait SectionContentExt: fmt::Debug + Send + Sync {
+    fn to_writer(&self, eh: &Header, io: &mut dyn Write) -> Result<(), Error>;
+
+    /// usually Box::new(self.clone())
+    fn clone_box(&self) -> Box<dyn SectionContentExt>;
+
+    /// for SectionContent::as_ext, usually just self
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// size in bytes once written. the default writes it out to find out
+    fn size(&self, eh: &Header) -> usize {
+        let mut raw = Vec::new();
+        match self.to_writer(eh, &mut raw) {
+            Ok(()) => raw.len(),
+            Err(_) => 0,
+        }
+    }
+}
+
+impl Clone for Box<dyn SectionContentExt> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// the content of a section. D is what holds raw bytes, see Elf
 #[derive(Debug, Clone)]
-pub enum SectionContent {
+pub enum SectionContent<D = Vec<u8>> {
     None,
-    Raw(Vec<u8>),
+    Raw(D),
     Relocations(Vec<Relocation>),
     Symbols(Vec<Symbol>),
     Dynamic(Vec<Dynamic>),
     Strtab(Strtab),
+    /// a global offset table, see Elf::load_got
+    Got(Vec<GotEntry>),
+    /// content parsed by a parser registered in ElfOptions::content
+    Ext(Box<dyn SectionContentExt>),
 }
 
-impl Default for SectionContent {
+impl<D> Default for SectionContent<D> {
     fn default() -> Self {
         SectionContent::None
     }
 }
+impl<D: Into<Vec<u8>>> SectionContent<D> {
+    /// the same content with raw bytes in a Vec of its own
+    pub fn into_owned(self) -> SectionContent {
+        match self {
+            SectionContent::None => SectionContent::None,
+            SectionContent::Raw(d) => SectionContent::Raw(d.into()),
+            SectionContent::Relocations(v) => SectionContent::Relocations(v),
+            SectionContent::Symbols(v) => SectionContent::Symbols(v),
+            SectionContent::Dynamic(v) => SectionContent::Dynamic(v),
+            SectionContent::Strtab(v) => SectionContent::Strtab(v),
+            SectionContent::Got(v) => SectionContent::Got(v),
+            SectionContent::Ext(v) => SectionContent::Ext(v),
+        }
+    }
+}
+
+impl<D: AsRef<[u8]>> SectionContent<D> {
+    /// the same content with raw bytes borrowed from this one
+    pub fn view(&self) -> SectionContent<&[u8]> {
+        match self {
+            SectionContent::None => SectionContent::None,
+            SectionContent::Raw(d) => SectionContent::Raw(d.as_ref()),
+            SectionContent::Relocations(v) => SectionContent::Relocations(v.clone()),
+            SectionContent::Symbols(v) => SectionContent::Symbols(v.clone()),
+            SectionContent::Dynamic(v) => SectionContent::Dynamic(v.clone()),
+            SectionContent::Strtab(v) => SectionContent::Strtab(v.clone()),
+            SectionContent::Got(v) => SectionContent::Got(v.clone()),
+            SectionContent::Ext(v) => SectionContent::Ext(v.clone()),
+        }
+    }
+}
+
+/**
+ * the accessors of each variant: as_ for a reference, as_..._mut for a mutable one and into_ for
+ * the content itself, all None for another variant. content that isn't loaded is Raw,
+ * Elf::loaded_section loads it first
+ */
 impl SectionContent {
-    pub fn as_dynamic_mut(&mut self) -> Option<&mut Vec<Dynamic>> {
+    pub fn as_raw(&self) -> Option<&Vec<u8>> {
         match self {
-            &mut SectionContent::Dynamic(ref mut v) => Some(v),
+            &SectionContent::Raw(ref v) => Some(v),
             _ => None,
         }
     }
-    pub fn as_strtab_mut(&mut self) -> Option<&mut Strtab> {
+    pub fn as_raw_mut(&mut self) -> Option<&mut Vec<u8>> {
         match self {
-            &mut SectionContent::Strtab(ref mut v) => Some(v),
+            &mut SectionContent::Raw(ref mut v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn into_raw(self) -> Option<Vec<u8>> {
+        match self {
+            SectionContent::Raw(v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn as_relocations(&self) -> Option<&Vec<Relocation>> {
+        match self {
+            &SectionContent::Relocations(ref v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn as_relocations_mut(&mut self) -> Option<&mut Vec<Relocation>> {
+        match self {
+            &mut SectionContent::Relocations(ref mut v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn into_relocations(self) -> Option<Vec<Relocation>> {
+        match self {
+            SectionContent::Relocations(v) => Some(v),
             _ => None,
         }
     }
@@ -113,33 +227,82 @@ impl SectionContent {
             _ => None,
         }
     }
+    pub fn as_symbols_mut(&mut self) -> Option<&mut Vec<Symbol>> {
+        match self {
+            &mut SectionContent::Symbols(ref mut v) => Some(v),
+            _ => None,
+        }
+    }
     pub fn into_symbols(self) -> Option<Vec<Symbol>> {
         match self {
             SectionContent::Symbols(v) => Some(v),
             _ => None,
         }
     }
-    pub fn as_relocations(&self) -> Option<&Vec<Relocation>> {
+    pub fn as_dynamic(&self) -> Option<&Vec<Dynamic>> {
         match self {
-            &SectionContent::Relocations(ref v) => Some(v),
+            &SectionContent::Dynamic(ref v) => Some(v),
             _ => None,
         }
     }
-    pub fn into_relocations(self) -> Option<Vec<Relocation>> {
+    pub fn as_dynamic_mut(&mut self) -> Option<&mut Vec<Dynamic>> {
         match self {
-            SectionContent::Relocations(v) => Some(v),
+            &mut SectionContent::Dynamic(ref mut v) => Some(v),
             _ => None,
         }
     }
-    pub fn as_raw_mut(&mut self) -> Option<&mut Vec<u8>> {
+    pub fn into_dynamic(self) -> Option<Vec<Dynamic>> {
         match self {
-            &mut SectionContent::Raw(ref mut v) => Some(v),
+            SectionContent::Dynamic(v) => Some(v),
             _ => None,
         }
     }
-    pub fn into_raw(self) -> Option<Vec<u8>> {
+    pub fn as_strtab(&self) -> Option<&Strtab> {
         match self {
-            SectionContent::Raw(v) => Some(v),
+            &SectionContent::Strtab(ref v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn as_strtab_mut(&mut self) -> Option<&mut Strtab> {
+        match self {
+            &mut SectionContent::Strtab(ref mut v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn into_strtab(self) -> Option<Strtab> {
+        match self {
+            SectionContent::Strtab(v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn as_got(&self) -> Option<&Vec<GotEntry>> {
+        match self {
+            &SectionContent::Got(ref v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn as_got_mut(&mut self) -> Option<&mut Vec<GotEntry>> {
+        match self {
+            &mut SectionContent::Got(ref mut v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn into_got(self) -> Option<Vec<GotEntry>> {
+        match self {
+            SectionContent::Got(v) => Some(v),
+            _ => None,
+        }
+    }
+    /// the content of a registered parser, if it's a T
+    pub fn as_ext<T: Any>(&self) -> Option<&T> {
+        match self {
+            &SectionContent::Ext(ref v) => v.as_any().downcast_ref(),
+            _ => None,
+        }
+    }
+    pub fn as_ext_mut<T: Any>(&mut self) -> Option<&mut T> {
+        match self {
+            &mut SectionContent::Ext(ref mut v) => v.as_any_mut().downcast_mut(),
             _ => None,
         }
     }
@@ -151,22 +314,132 @@ impl SectionContent {
             &SectionContent::Strtab(ref v) => v.len(eh),
             &SectionContent::Symbols(ref v) => v.len() * Symbol::entsize(eh),
             &SectionContent::Relocations(ref v) => v.len() * Relocation::entsize(eh),
+            &SectionContent::Got(ref v) => v.len() * GotEntry::entsize(eh),
+            &SectionContent::Ext(ref v) => v.size(eh),
         }
     }
 }
 
+/// TryFrom for the content of each variant, Error::UnexpectedSectionContent for another one
+macro_rules! try_from_content {
+    ($($t:ty => $into:ident),*) => {$(
+        impl TryFrom<SectionContent> for $t {
+            type Error = Error;
+            fn try_from(content: SectionContent) -> Result<$t, Error> {
+                content.$into().ok_or(Error::UnexpectedSectionContent)
+            }
+        }
+    )*};
+}
+
+try_from_content!(
+    Vec<u8> => into_raw,
+    Vec<Relocation> => into_relocations,
+    Vec<Symbol> => into_symbols,
+    Vec<Dynamic> => into_dynamic,
+    Strtab => into_strtab,
+    Vec<GotEntry> => into_got
+);
+
+/// symbolic reference to another section, used instead of a raw index in header.link or
+/// header.info. the reference is resolved to the current index of the section during sync_all
+/// and store_all, so it stays correct no matter in which order sections are added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkRef {
+    Name(String),
+    Id(SectionId),
+}
+
+/// stable handle to a section in an Elf, see Elf::section_id
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SectionId(pub(crate) u64);
+
+/// where linker::relayout and linker::segments may put a section, like the attributes of an
+/// output section in a linker script. this isn't part of the file, except for keep
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Placement {
+    /// put the section at exactly this address, with the sections after it following it.
+    /// relayout fails if the sections before it already reach past it
+    pub address: Option<u64>,
+    /// take up addresses without being loaded, like NOLOAD, for memory that is there without
+    /// the program loading it, like peripherals. relayout gives the section its addresses as
+    /// usual and segments leaves it out of LOAD segments. the content stays in the file,
+    /// make the section NOBITS to leave it out like ld does
+    pub noload: bool,
+    /// never remove the section when unused sections are collected, like KEEP.
+    /// sync sets SHF_GNU_RETAIN, which is how linkers are told in an object file
+    pub keep: bool,
+    /// name of the linker::MemoryRegion to put the section in, after what is already there.
+    /// only honored by relayout_in_regions
+    pub region: Option<String>,
+}
+
 #[derive(Debug, Default, Clone)]
-pub struct Section {
+pub struct Section<D = Vec<u8>> {
     pub header: SectionHeader,
     pub name: String,
-    pub content: SectionContent,
+    pub content: SectionContent<D>,
+
+    /// when set, overrides header.link
+    pub link_ref: Option<LinkRef>,
+    /// when set, overrides header.info and marks it as INFO_LINK
+    pub info_ref: Option<LinkRef>,
+
+    pub placement: Placement,
+
+    /// assigned by Elf, there's no need to set this
+    pub id: SectionId,
 }
 
+impl<D: Into<Vec<u8>>> Section<D> {
+    /// see SectionContent::into_owned
+    pub fn into_owned(self) -> Section {
+        Section {
+            header: self.header,
+            name: self.name,
+            content: self.content.into_owned(),
+            link_ref: self.link_ref,
+            info_ref: self.info_ref,
+            placement: self.placement,
+            id: self.id,
+        }
+    }
+}
+
+impl<D: AsRef<[u8]>> Section<D> {
+    /// see SectionContent::view
+    pub fn view(&self) -> Section<&[u8]> {
+        Section {
+            header: self.header.clone(),
+            name: self.name.clone(),
+            content: self.content.view(),
+            link_ref: self.link_ref.clone(),
+            info_ref: self.info_ref.clone(),
+            placement: self.placement.clone(),
+            id: self.id,
+        }
+    }
+}
 
 impl Section {
     pub fn size(&self, eh: &Header) -> usize {
-        self.content.size(eh)
+        match self.content {
+            SectionContent::Relocations(ref v) if self.header.shtype == types::SectionType::REL => {
+                v.len() * Relocation::entsize_rel(eh)
+            }
+            _ => self.content.size(eh),
+        }
+    }
+
+    /// size of one relocation entry, which depends on whether this is a REL or RELA section
+    fn relocation_entsize(&self, eh: &Header) -> usize {
+        if self.header.shtype == types::SectionType::REL {
+            Relocation::entsize_rel(eh)
+        } else {
+            Relocation::entsize(eh)
+        }
     }
+
     pub fn new(
         name: String,
         shtype: types::SectionType,
@@ -190,6 +463,10 @@ impl Section {
                 entsize: 0,
             },
             content: content,
+            link_ref: None,
+            info_ref: None,
+            placement: Placement::default(),
+            id: SectionId::default(),
         }
     }
 
@@ -200,7 +477,7 @@ impl Section {
     ) -> Result<(), Error> {
         match self.content {
             SectionContent::Relocations(_) => {
-                self.header.entsize = Relocation::entsize(eh) as u64;
+                self.header.entsize = self.relocation_entsize(eh) as u64;
             }
             SectionContent::Symbols(ref vv) => {
                 for (i, sym) in vv.iter().enumerate() {
@@ -223,11 +500,17 @@ impl Section {
             SectionContent::Strtab(_) => {
                 self.header.entsize = Strtab::entsize(eh) as u64;
             }
-            SectionContent::None | SectionContent::Raw(_) => {}
+            SectionContent::Got(_) => {
+                self.header.entsize = GotEntry::entsize(eh) as u64;
+            }
+            SectionContent::None | SectionContent::Raw(_) | SectionContent::Ext(_) => {}
         }
         if self.header.shtype != types::SectionType::NOBITS {
             self.header.size = self.size(eh) as u64;
         }
+        if self.placement.keep {
+            self.header.flags.insert(types::SectionFlags::GNU_RETAIN);
+        }
         Ok(())
     }
 }
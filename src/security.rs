@@ -0,0 +1,171 @@
+use elf::Elf;
+use error::Error;
+use dynamic::{Dynamic, DynamicContent};
+use symbol::SymbolSectionIndex;
+use types;
+
+/// how much of the relocated data ld.so makes read only
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relro {
+    /// no PT_GNU_RELRO
+    None,
+    /// PT_GNU_RELRO, but lazy binding leaves .got.plt writable
+    Partial,
+    /// PT_GNU_RELRO and BIND_NOW
+    Full,
+}
+
+/// whether the file can be loaded at a random address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pie {
+    /// an executable at a fixed address
+    No,
+    /// a position independent executable
+    Pie,
+    /// a shared library, which is always position independent
+    Dso,
+    /// an object file, which isn't loaded at all
+    Rel,
+    /// a core dump or a file with no type, like some firmware, which isn't a program either
+    NotAProgram,
+}
+
+/// the hardening of a file, as checksec reports it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityReport {
+    /// PT_GNU_STACK without PF_X. without the segment most loaders map the stack executable
+    pub nx: bool,
+    pub relro: Relro,
+    pub pie: Pie,
+    /// references __stack_chk_fail or __stack_chk_guard, so some functions check a canary
+    pub canary: bool,
+    /// the FORTIFY_SOURCE functions the file calls, like __memcpy_chk
+    pub fortified: Vec<String>,
+    /// the paths of DT_RPATH, which is searched before LD_LIBRARY_PATH
+    pub rpath: Vec<String>,
+    /// the paths of DT_RUNPATH
+    pub runpath: Vec<String>,
+}
+
+const CANARY_SYMBOLS: &[&str] = &[
+    "__stack_chk_fail",
+    "__stack_chk_fail_local",
+    "__stack_chk_guard",
+];
+
+impl SecurityReport {
+    /// rpath and runpath entries that depend on the working directory, like "" or "lib".
+    /// anyone who can write there can make the file load their libraries
+    pub fn insecure_paths(&self) -> Vec<&str> {
+        self.rpath
+            .iter()
+            .chain(self.runpath.iter())
+            .map(|p| p.as_str())
+            .filter(|p| {
+                !p.starts_with('/') && !p.starts_with("$ORIGIN") && !p.starts_with("${ORIGIN}")
+            })
+            .collect()
+    }
+}
+
+impl Elf {
+    /// check the hardening of the file, like checksec does.
+    /// loads the dynamic section and the symbol tables
+    pub fn security_report(&mut self) -> Result<SecurityReport, Error> {
+        for i in 0..self.sections.len() {
+            match self.sections[i].header.shtype {
+                types::SectionType::DYNAMIC
+                | types::SectionType::SYMTAB
+                | types::SectionType::DYNSYM => self.load_at(i)?,
+                _ => {}
+            }
+        }
+
+        let stack = self.segments
+            .iter()
+            .find(|s| s.phtype == types::SegmentType::GNU_STACK);
+        let nx = match stack {
+            Some(s) => !s.flags.contains(types::SegmentFlags::EXECUTABLE),
+            None => false,
+        };
+
+        let mut r = SecurityReport {
+            nx,
+            relro: Relro::None,
+            pie: Pie::No,
+            canary: false,
+            fortified: Vec::new(),
+            rpath: Vec::new(),
+            runpath: Vec::new(),
+        };
+
+        let mut bind_now = false;
+        let mut flags1 = types::DynamicFlags1::empty();
+        for sec in &self.sections {
+            let dynamic = match sec.content.as_dynamic() {
+                Some(d) => d,
+                None => continue,
+            };
+            let linked = self.sections.get(sec.header.link as usize);
+            let strtab = linked.and_then(|s| s.content.as_strtab());
+            let paths = |d: &Dynamic| -> Vec<String> {
+                let s = match (&d.content, strtab) {
+                    (&DynamicContent::String(ref s), _) => s.clone(),
+                    (&DynamicContent::Address(off), Some(strtab)) => strtab.get(off as usize),
+                    _ => String::new(),
+                };
+                s.split(':').map(String::from).collect()
+            };
+            for d in dynamic {
+                match d.content {
+                    DynamicContent::Flags(f) => {
+                        bind_now |= f.contains(types::DynamicFlags::BIND_NOW)
+                    }
+                    DynamicContent::Flags1(f) => flags1 |= f,
+                    _ if d.dhtype == types::DynamicType::BIND_NOW => bind_now = true,
+                    _ if d.dhtype == types::DynamicType::RPATH => r.rpath.extend(paths(d)),
+                    _ if d.dhtype == types::DynamicType::RUNPATH => r.runpath.extend(paths(d)),
+                    _ => {}
+                }
+            }
+        }
+        bind_now |= flags1.contains(types::DynamicFlags1::NOW);
+
+        if self.segments.iter().any(|s| s.phtype == types::SegmentType::GNU_RELRO) {
+            r.relro = if bind_now { Relro::Full } else { Relro::Partial };
+        }
+
+        r.pie = match self.header.etype {
+            types::ElfType::REL => Pie::Rel,
+            types::ElfType::DYN => {
+                let interp = self.segments.iter().any(|s| s.phtype == types::SegmentType::INTERP);
+                if interp || flags1.contains(types::DynamicFlags1::PIE) {
+                    Pie::Pie
+                } else {
+                    Pie::Dso
+                }
+            }
+            types::ElfType::EXEC => Pie::No,
+            types::ElfType::CORE | types::ElfType::NONE => Pie::NotAProgram,
+        };
+
+        for sec in &self.sections {
+            let syms = match sec.content.as_symbols() {
+                Some(syms) => syms,
+                None => continue,
+            };
+            for sym in syms {
+                if CANARY_SYMBOLS.contains(&sym.name.as_str()) {
+                    r.canary = true;
+                }
+                if sym.shndx == SymbolSectionIndex::Undefined && sym.name.starts_with("__")
+                    && sym.name.ends_with("_chk") && !r.fortified.contains(&sym.name)
+                {
+                    r.fortified.push(sym.name.clone());
+                }
+            }
+        }
+        r.fortified.sort();
+        Ok(r)
+    }
+}
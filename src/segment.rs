@@ -2,9 +2,8 @@ use error::Error;
 use types;
 use header::Header;
 
-use std::io::BufWriter;
-use std::io::{Read, Write};
-use num_traits::{FromPrimitive, ToPrimitive};
+use io::BufWriter;
+use io::{Read, Write};
 
 #[derive(Default, Debug, Clone)]
 pub struct SegmentHeader {
@@ -36,10 +35,7 @@ impl SegmentHeader {
         let mut br = &b[..];
 
         let reb = elf_read_u32!(eh, br)?;
-        r.phtype = match types::SegmentType::from_u32(reb) {
-            Some(v) => v,
-            None => return Err(Error::InvalidSegmentType(reb)),
-        };
+        r.phtype = types::SegmentType(reb);
 
         match eh.ident_class {
             types::Class::Class64 => {
@@ -68,7 +64,7 @@ impl SegmentHeader {
         R: Write,
     {
         let mut w = BufWriter::new(io);
-        elf_write_u32!(eh, w, self.phtype.to_u32().unwrap())?;
+        elf_write_u32!(eh, w, self.phtype.to_u32())?;
         match eh.ident_class {
             types::Class::Class64 => {
                 elf_write_u32!(eh, w, self.flags.bits() as u32)?;
@@ -80,13 +76,13 @@ impl SegmentHeader {
                 elf_write_u64!(eh, w, self.align)?;
             }
             types::Class::Class32 => {
-                elf_write_u32!(eh, w, self.offset as u32)?;
-                elf_write_u32!(eh, w, self.vaddr as u32)?;
-                elf_write_u32!(eh, w, self.paddr as u32)?;
-                elf_write_u32!(eh, w, self.filesz as u32)?;
-                elf_write_u32!(eh, w, self.memsz as u32)?;
+                elf_write_uclass!(eh, w, self.offset)?;
+                elf_write_uclass!(eh, w, self.vaddr)?;
+                elf_write_uclass!(eh, w, self.paddr)?;
+                elf_write_uclass!(eh, w, self.filesz)?;
+                elf_write_uclass!(eh, w, self.memsz)?;
                 elf_write_u32!(eh, w, self.flags.bits() as u32)?;
-                elf_write_u32!(eh, w, self.align as u32)?;
+                elf_write_uclass!(eh, w, self.align)?;
             }
         };
         Ok(())
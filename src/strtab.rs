@@ -1,14 +1,38 @@
-use std::io::{Read, Write};
+use io::{Read, Write};
 use {Error, Header, SectionContent};
+#[cfg(feature = "std")]
 use std::collections::hash_map::{Entry, HashMap};
+#[cfg(not(feature = "std"))]
+use alloc::collections::btree_map::{BTreeMap as HashMap, Entry};
+use prelude::*;
 
 #[derive(Debug, Default, Clone)]
 pub struct Strtab {
     hash: HashMap<Vec<u8>, usize>,
     data: Vec<u8>,
+    tail_merge: bool,
 }
 
 impl Strtab {
+    /// a table where strings share the bytes of longer strings ending in them,
+    /// so "bar" is stored as the tail of "foobar". Elf::sync_all packs these tables with optimize,
+    /// which moves strings around. only use it when everything pointing into the table is loaded,
+    /// since offsets in raw sections would go stale.
+    pub fn tail_merged() -> Strtab {
+        Strtab {
+            tail_merge: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn tail_merge(&self) -> bool {
+        self.tail_merge
+    }
+
+    pub fn set_tail_merge(&mut self, on: bool) {
+        self.tail_merge = on;
+    }
+
     pub fn len(&self, _: &Header) -> usize {
         self.data.len()
     }
@@ -23,26 +47,32 @@ impl Strtab {
     where
         R: Read,
     {
-        let mut r = Strtab::default();
+        let mut data = Vec::new();
+        io.read_to_end(&mut data)?;
+        let r = Strtab::from_raw(data);
 
-        io.read_to_end(&mut r.data)?;
+        Ok(SectionContent::Strtab(r))
+    }
 
-        let mut n = Vec::new();
-        let mut start = 0;
-        for i in 0..r.data.len() {
-            let c = r.data[i];
-            if c == 0 {
-                for x in 0..n.len() {
-                    r.hash.insert(n[x..].to_vec(), start + x);
-                }
-                start = i + 1;
-                n = Vec::new()
-            } else {
-                n.push(c);
+    /// a table over already serialized strings. every string and every tail of one can be
+    /// found by insert without growing the table
+    pub fn from_raw(data: Vec<u8>) -> Strtab {
+        let mut r = Strtab {
+            data,
+            ..Default::default()
+        };
+        let mut hash = HashMap::new();
+        for (start, s) in r.iter() {
+            // an unterminated string at the end can't be shared, it would be written as is
+            if start + s.len() >= r.data.len() {
+                break;
+            }
+            for x in 0..s.len() {
+                hash.insert(s[x..].to_vec(), start + x);
             }
         }
-
-        Ok(SectionContent::Strtab(r))
+        r.hash = hash;
+        r
     }
 
     pub fn to_writer<W>(
@@ -54,17 +84,47 @@ impl Strtab {
     where
         W: Write,
     {
-        io.write(&self.data)?;
+        io.write_all(&self.data)?;
         Ok(())
     }
 
     pub fn get(&self, i: usize) -> String {
+        match self.get_bytes(i) {
+            Some(b) => String::from_utf8_lossy(b).into_owned(),
+            None => {
+                #[cfg(feature = "std")]
+                println!("pointer {} into strtab extends beyond section size", i);
+                String::from("<corrupt>")
+            }
+        }
+    }
+
+    /// the string starting at offset i, without its terminator.
+    /// None if i is outside the table. an unterminated last string runs to the end
+    pub fn get_bytes(&self, i: usize) -> Option<&[u8]> {
         if i >= self.data.len() {
-            println!("pointer {} into strtab extends beyond section size", i);
-            return String::from("<corrupt>");
+            return None;
+        }
+        self.data[i..].split(|c| *c == 0).next()
+    }
+
+    /// every string in the table with its offset, in the order they are stored.
+    /// tails of a string are not returned on their own
+    pub fn iter(&self) -> StrtabIter<'_> {
+        StrtabIter {
+            data: &self.data,
+            at: 0,
+        }
+    }
+
+    /// check that the table is empty, or starts with the empty string and ends with a terminator
+    pub fn validate(&self) -> Result<(), Error> {
+        match (self.data.first(), self.data.last()) {
+            (None, _) => Ok(()),
+            (Some(&0), Some(&0)) => Ok(()),
+            (Some(_), Some(&0)) => Err(Error::InvalidStrtab("does not start with a null byte")),
+            _ => Err(Error::InvalidStrtab("last string is not terminated")),
         }
-        String::from_utf8_lossy(self.data[i..].split(|c| *c == 0).next().unwrap_or(&[0; 0]))
-            .into_owned()
     }
 
     pub fn insert(&mut self, ns: Vec<u8>) -> usize {
@@ -83,8 +143,67 @@ impl Strtab {
                 self.data.extend(&ns);
                 self.data.extend(&[0; 1]);
                 entry.insert(i);
+                if self.tail_merge {
+                    for x in 1..ns.len() {
+                        self.hash.entry(ns[x..].to_vec()).or_insert(i + x);
+                    }
+                }
                 i
             }
         }
     }
+
+    /// rebuild the table so that every string which is the tail of another one shares its bytes,
+    /// and strings that were never inserted or looked up are dropped.
+    /// returns where each previously handed out offset moved to.
+    pub fn optimize(&mut self) -> HashMap<usize, usize> {
+        let mut strings: Vec<&Vec<u8>> = self.hash.keys().filter(|k| !k.is_empty()).collect();
+        // sorting by the reversed string puts every string right after the longer ones ending in it
+        strings.sort_by(|a, b| b.iter().rev().cmp(a.iter().rev()));
+
+        let mut data = vec![0];
+        let mut hash = HashMap::new();
+        let mut prev: Option<(&Vec<u8>, usize)> = None;
+        for s in strings {
+            let at = match prev {
+                Some((p, end)) if p.ends_with(s) => end - s.len(),
+                _ => {
+                    let at = data.len();
+                    data.extend(s.iter());
+                    data.push(0);
+                    prev = Some((s, at + s.len()));
+                    at
+                }
+            };
+            hash.insert(s.clone(), at);
+        }
+
+        let mut remap = HashMap::new();
+        remap.insert(0, 0);
+        for (s, old) in &self.hash {
+            remap.insert(*old, *hash.get(s).unwrap_or(&0));
+        }
+        self.hash = hash;
+        self.data = data;
+        remap
+    }
+}
+
+pub struct StrtabIter<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Iterator for StrtabIter<'a> {
+    type Item = (usize, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.at >= self.data.len() {
+            return None;
+        }
+        let start = self.at;
+        let s = self.data[start..].split(|c| *c == 0).next().unwrap_or(&[]);
+        self.at += s.len() + 1;
+        Some((start, s))
+    }
 }
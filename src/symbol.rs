@@ -1,16 +1,21 @@
-use std::io::{Read, Write};
+use io::{Read, Write};
 use {types, Error, Header, SectionContent};
 use num_traits::{FromPrimitive, ToPrimitive};
 use strtab::Strtab;
-use section::{Section, SectionHeader};
+use utils::narrow;
+use prelude::*;
+use section::{Placement, Section, SectionHeader, SectionId};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum SymbolSectionIndex {
-    Section(u16), // 1-6551
+    /// indices from SHN_LORESERVE up are written through the SYMTAB_SHNDX section
+    Section(u32), // 1-65279, or any with SHN_XINDEX
     Undefined,    // 0
     Absolute,     // 65521,
-    Common,       // 6552,
+    Common,       // 65522,
     Global(u64),
+    /// processor or os specific index in the reserved range, like SHN_MIPS_SCOMMON
+    Reserved(u16),
 }
 impl Default for SymbolSectionIndex {
     fn default() -> SymbolSectionIndex {
@@ -27,11 +32,98 @@ pub struct Symbol {
     pub name: String,
     pub stype: types::SymbolType,
     pub bind: types::SymbolBind,
-    pub vis: types::SymbolVis,
+    /// raw st_other. the low two bits are the visibility, see vis().
+    /// the others are processor specific, on ppc64 the top three are the local entry offset
+    pub other: u8,
 }
 
+/// see Symbol::iter
+pub struct SymbolIter<'a> {
+    data: &'a [u8],
+    strtab: Option<&'a Strtab>,
+    xindex: Option<&'a [u8]>,
+    eh: &'a Header,
+    index: usize,
+}
+
+impl<'a> SymbolIter<'a> {
+    /// the raw content of the SYMTAB_SHNDX section for this table, see from_reader_with_xindex
+    pub fn xindex(mut self, xindex: &'a [u8]) -> SymbolIter<'a> {
+        self.xindex = Some(xindex);
+        self
+    }
+
+    fn decode(&self, b: &[u8]) -> Result<Symbol, Error> {
+        let eh = self.eh;
+        let tab = self.strtab;
+        let mut br = b;
+        let _name = elf_read_u32!(eh, br)?;
+
+        let mut sym = match eh.ident_class {
+            types::Class::Class64 => {
+                let info = b[4];
+                let other = b[5];
+                br = &b[6..];
+                let shndx = elf_read_u16!(eh, br)?;
+                let value = elf_read_u64!(eh, br)?;
+                let size = elf_read_u64!(eh, br)?;
+
+                Symbol::from_val(tab, _name, info, other, shndx, value, size)?
+            }
+            types::Class::Class32 => {
+                let value = elf_read_u32!(eh, br)?;
+                let size = elf_read_u32!(eh, br)?;
+                let info = b[12];
+                let other = b[13];
+                br = &b[14..];
+                let shndx = elf_read_u16!(eh, br)?;
+
+                Symbol::from_val(tab, _name, info, other, shndx, value as u64, size as u64)?
+            }
+        };
+
+        if sym.shndx == SymbolSectionIndex::Reserved(types::SHN_XINDEX) {
+            let at = self.index * 4;
+            sym.shndx = match self.xindex {
+                Some(x) if x.len() >= at + 4 => {
+                    let mut xr = &x[at..];
+                    match elf_read_u32!(eh, xr)? {
+                        0 => SymbolSectionIndex::Undefined,
+                        i => SymbolSectionIndex::Section(i),
+                    }
+                }
+                _ => return Err(Error::InvalidSymbolShndx(sym.name, types::SHN_XINDEX)),
+            };
+        }
+        Ok(sym)
+    }
+}
+
+impl<'a> Iterator for SymbolIter<'a> {
+    type Item = Result<Symbol, Error>;
+
+    fn next(&mut self) -> Option<Result<Symbol, Error>> {
+        let entsize = Symbol::entsize(self.eh);
+        if self.data.len() < entsize {
+            return None;
+        }
+        let (b, rest) = self.data.split_at(entsize);
+        let r = self.decode(b);
+        self.data = rest;
+        self.index += 1;
+        Some(r)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.data.len() / Symbol::entsize(self.eh);
+        (n, Some(n))
+    }
+}
+
+impl<'a> ExactSizeIterator for SymbolIter<'a> {}
+
 impl Symbol {
-    fn from_val(
+    pub(crate) fn from_val(
         tab: Option<&Strtab>,
         _name: u32,
         info: u8,
@@ -49,7 +141,11 @@ impl Symbol {
             0 => SymbolSectionIndex::Undefined,
             65521 => SymbolSectionIndex::Absolute,
             65522 => SymbolSectionIndex::Common,
-            _ if shndx > 0 && shndx < 6552 => SymbolSectionIndex::Section(shndx),
+            _ if shndx > 0 && (shndx as u32) < types::SHN_LORESERVE => {
+                SymbolSectionIndex::Section(shndx as u32)
+            }
+            // SHN_XINDEX is resolved by from_reader_with_xindex
+            0xff00..=0xffff => SymbolSectionIndex::Reserved(shndx),
             _ => return Err(Error::InvalidSymbolShndx(name.clone(), shndx)),
         };
 
@@ -65,12 +161,6 @@ impl Symbol {
             None => return Err(Error::InvalidSymbolBind(reb)),
         };
 
-        let reb = other & 0x3;
-        let vis = match types::SymbolVis::from_u8(reb) {
-            Some(v) => v,
-            None => return Err(Error::InvalidSymbolVis(reb)),
-        };
-
         Ok(Symbol {
             shndx: shndx,
             value: value,
@@ -79,10 +169,52 @@ impl Symbol {
             name: name,
             stype: stype,
             bind: bind,
-            vis: vis,
+            other: other,
         })
     }
 
+    pub fn vis(&self) -> types::SymbolVis {
+        // all four values of the two bits are defined
+        types::SymbolVis::from_u8(self.other & 0x3).unwrap()
+    }
+
+    pub fn set_vis(&mut self, vis: types::SymbolVis) {
+        self.other = (self.other & !0x3) | vis.to_u8().unwrap();
+    }
+
+    /// an indirect function, whose value is a resolver that returns the address to use.
+    /// only in files for an os abi that has them, see types::Abi::has_ifunc
+    pub fn is_ifunc(&self, eh: &Header) -> bool {
+        self.stype == types::SymbolType::GNU_IFUNC && eh.ident_abi.has_ifunc()
+    }
+
+    /// a global symbol that is bound once in the whole process, see
+    /// types::Abi::has_unique_symbols
+    pub fn is_unique(&self, eh: &Header) -> bool {
+        self.bind == types::SymbolBind::STB_GNU_UNIQUE && eh.ident_abi.has_unique_symbols()
+    }
+
+    /// ppc64 ELFv2 functions have a global entry point that sets up the TOC pointer and a local
+    /// entry point a few instructions later, used by callers that share the same TOC.
+    /// returns how many bytes after st_value the local entry is
+    pub fn ppc64_local_entry_offset(&self) -> u64 {
+        match self.other >> 5 {
+            v @ 2..=6 => 1 << v,
+            _ => 0,
+        }
+    }
+
+    /// offset must be 0 or a power of two from 4 to 64
+    pub fn set_ppc64_local_entry_offset(&mut self, offset: u64) -> Result<(), Error> {
+        let v = match offset {
+            0 => 0,
+            4 | 8 | 16 | 32 | 64 => offset.trailing_zeros() as u8,
+            _ => return Err(Error::InvalidLocalEntryOffset(offset)),
+        };
+        self.other = (self.other & 0x1f) | (v << 5);
+        Ok(())
+    }
+
     pub fn entsize(eh: &Header) -> usize {
         match eh.ident_class {
             types::Class::Class64 => 24,
@@ -90,9 +222,32 @@ impl Symbol {
         }
     }
 
+    /// the section index a SYMTAB_SHNDX entry has to hold for this symbol,
+    /// None if it fits into st_shndx
+    pub fn extended_shndx(&self) -> Option<u32> {
+        match self.shndx {
+            SymbolSectionIndex::Section(i) if i >= types::SHN_LORESERVE => Some(i),
+            _ => None,
+        }
+    }
+
     pub fn from_reader<R>(
+        io: R,
+        linked: Option<&SectionContent>,
+        eh: &Header,
+    ) -> Result<SectionContent, Error>
+    where
+        R: Read,
+    {
+        Symbol::from_reader_with_xindex(io, linked, None, eh)
+    }
+
+    /// like from_reader, with the raw content of the SYMTAB_SHNDX section belonging to this
+    /// table, for symbols with an st_shndx of SHN_XINDEX
+    pub fn from_reader_with_xindex<R>(
         mut io: R,
         linked: Option<&SectionContent>,
+        xindex: Option<&[u8]>,
         eh: &Header,
     ) -> Result<SectionContent, Error>
     where
@@ -103,38 +258,64 @@ impl Symbol {
             Some(&SectionContent::Strtab(ref s)) => Some(s),
             _ => return Err(Error::LinkedSectionIsNotStrtab("reading symbols")),
         };
+        let mut raw = Vec::new();
+        io.read_to_end(&mut raw)?;
+        let mut iter = Symbol::iter(&raw, tab, eh);
+        iter.xindex = xindex;
+        Ok(SectionContent::Symbols(iter.collect::<Result<Vec<_>, _>>()?))
+    }
 
-        let mut r = Vec::new();
-        let mut b = vec![0; Self::entsize(eh)];
-        while io.read(&mut b)? > 0 {
-            let mut br = &b[..];
-            let _name = elf_read_u32!(eh, br)?;
-
-            r.push(match eh.ident_class {
-                types::Class::Class64 => {
-                    let info = b[4];
-                    let other = b[5];
-                    br = &b[6..];
-                    let shndx = elf_read_u16!(eh, br)?;
-                    let value = elf_read_u64!(eh, br)?;
-                    let size = elf_read_u64!(eh, br)?;
-
-                    Symbol::from_val(tab, _name, info, other, shndx, value, size)?
-                }
-                types::Class::Class32 => {
-                    let value = elf_read_u32!(eh, br)?;
-                    let size = elf_read_u32!(eh, br)?;
-                    let info = b[12];
-                    let other = b[13];
-                    br = &b[14..];
-                    let shndx = elf_read_u16!(eh, br)?;
-
-                    Symbol::from_val(tab, _name, info, other, shndx, value as u64, size as u64)?
-                }
-            })
+    /**
+     * the symbols in the raw content of a symbol table, decoded as they are iterated instead
+     * of all at once like from_reader. names are looked up in strtab, they are empty without.
+     * a partial entry at the end is ignored
+     */
+    pub fn iter<'a>(data: &'a [u8], strtab: Option<&'a Strtab>, eh: &'a Header) -> SymbolIter<'a> {
+        SymbolIter {
+            data,
+            strtab,
+            xindex: None,
+            eh,
+            index: 0,
         }
+    }
 
-        Ok(SectionContent::Symbols(r))
+    /**
+     * the names of the global and weak symbols a raw symbol table defines, in table order,
+     * with strtab the raw content of its string table. only st_name, st_info and st_shndx are
+     * read and nothing is decoded into a Symbol, which makes indexing what an object defines
+     * cheap for the many that are never linked. names past the end of strtab are skipped
+     */
+    pub fn defined_names(data: &[u8], strtab: &[u8], eh: &Header) -> Result<Vec<String>, Error> {
+        let (info_at, shndx_at) = match eh.ident_class {
+            types::Class::Class64 => (4, 6),
+            types::Class::Class32 => (12, 14),
+        };
+        let mut r = Vec::new();
+        for sym in data.chunks(Self::entsize(eh)) {
+            if sym.len() < Self::entsize(eh) {
+                break;
+            }
+            let shndx = {
+                let mut io = &sym[shndx_at..shndx_at + 2];
+                elf_read_u16!(eh, io)?
+            };
+            if sym[info_at] >> 4 == types::SymbolBind::LOCAL as u8 || shndx == 0 {
+                continue;
+            }
+            let name = {
+                let mut io = &sym[..4];
+                elf_read_u32!(eh, io)? as usize
+            };
+            let name = match strtab.get(name..) {
+                Some(tail) => tail.split(|b| *b == 0).next().unwrap_or(&[]),
+                None => continue,
+            };
+            if !name.is_empty() {
+                r.push(String::from_utf8_lossy(name).into_owned());
+            }
+        }
+        Ok(r)
     }
 
     pub fn to_writer<W>(
@@ -148,7 +329,8 @@ impl Symbol {
     {
         match linked {
             Some(&mut SectionContent::Strtab(ref mut strtab)) => {
-                let off = strtab.insert(self.name.bytes().collect()) as u32;
+                let off = strtab.insert(self.name.bytes().collect()) as u64;
+                let off: u32 = narrow("st_name", off)?;
                 elf_write_u32!(eh, io, off)?;
             }
             _ => return Err(Error::LinkedSectionIsNotStrtab("writing symbols")),
@@ -156,13 +338,15 @@ impl Symbol {
 
 
         let info = (self.bind.to_u8().unwrap() << 4) + (self.stype.to_u8().unwrap() & 0xf);
-        let other = self.vis.to_u8().unwrap();
+        let other = self.other;
 
         let shndx = match self.shndx {
-            SymbolSectionIndex::Section(i) => i,
+            SymbolSectionIndex::Section(i) if i >= types::SHN_LORESERVE => types::SHN_XINDEX,
+            SymbolSectionIndex::Section(i) => i as u16,
             SymbolSectionIndex::Undefined => 0,
             SymbolSectionIndex::Absolute => 65521,
             SymbolSectionIndex::Common => 65522,
+            SymbolSectionIndex::Reserved(i) => i,
             SymbolSectionIndex::Global(_) => {
                 return Err(Error::SymbolSectionIndexExtendedCannotBeWritten)
             }
@@ -170,21 +354,77 @@ impl Symbol {
 
         match eh.ident_class {
             types::Class::Class64 => {
-                io.write(&[info, other])?;
+                io.write_all(&[info, other])?;
                 elf_write_u16!(eh, io, shndx)?;
                 elf_write_u64!(eh, io, self.value)?;
                 elf_write_u64!(eh, io, self.size)?;
             }
             types::Class::Class32 => {
-                elf_write_u32!(eh, io, self.value as u32)?;
-                elf_write_u32!(eh, io, self.size as u32)?;
-                io.write(&[info, other])?;
+                elf_write_uclass!(eh, io, self.value)?;
+                elf_write_uclass!(eh, io, self.size)?;
+                io.write_all(&[info, other])?;
                 elf_write_u16!(eh, io, shndx)?;
             }
         };
         Ok(())
     }
 
+    /// rewrite the section index of every symbol in a serialized symbol table.
+    /// reserved indices (undefined, absolute, common) are left alone. an index of
+    /// SHN_LORESERVE or more needs a SYMTAB_SHNDX entry, which is an Error::FieldOverflow
+    pub fn remap_raw_shndx<F>(raw: &mut [u8], eh: &Header, f: F) -> Result<(), Error>
+    where
+        F: Fn(u16) -> u32,
+    {
+        let entsize = Self::entsize(eh);
+        let at = match eh.ident_class {
+            types::Class::Class64 => 6,
+            types::Class::Class32 => 14,
+        };
+        for sym in raw.chunks_mut(entsize) {
+            if sym.len() < entsize {
+                break;
+            }
+            let shndx = {
+                let mut br = &sym[at..at + 2];
+                elf_read_u16!(eh, br)?
+            };
+            if shndx == 0 || shndx >= 0xff00 {
+                continue;
+            }
+            let shndx = f(shndx);
+            if shndx >= types::SHN_LORESERVE {
+                return Err(Error::FieldOverflow("st_shndx", shndx as u64));
+            }
+            let mut w = &mut sym[at..at + 2];
+            elf_write_u16!(eh, w, shndx as u16)?;
+        }
+        Ok(())
+    }
+
+    /// same as remap_raw_shndx, for the entries of a serialized SYMTAB_SHNDX section.
+    /// entries of 0 belong to symbols that don't use an extended index
+    pub fn remap_raw_xindex<F>(raw: &mut [u8], eh: &Header, f: F) -> Result<(), Error>
+    where
+        F: Fn(u32) -> u32,
+    {
+        for entry in raw.chunks_mut(4) {
+            if entry.len() < 4 {
+                break;
+            }
+            let shndx = {
+                let mut br = &entry[..];
+                elf_read_u32!(eh, br)?
+            };
+            if shndx == 0 {
+                continue;
+            }
+            let mut w = &mut entry[..];
+            elf_write_u32!(eh, w, f(shndx))?;
+        }
+        Ok(())
+    }
+
     pub fn sync(&self, linked: Option<&mut SectionContent>, _: &Header) -> Result<(), Error> {
         match linked {
             Some(&mut SectionContent::Strtab(ref mut strtab)) => {
@@ -196,6 +436,7 @@ impl Symbol {
     }
 }
 
+/// the hash function of SHT_HASH sections, as in the system v abi
 pub fn sysv_hash(s: &String) -> u64 {
     let mut h: u64 = 0;
     let mut g: u64;
@@ -208,30 +449,59 @@ pub fn sysv_hash(s: &String) -> u64 {
         }
         h &= !g;
     }
-    return h;
+    // the abi computes this in 32 bits, carries past them must not leak into the result
+    h & 0x0fffffff
 }
 
+/// bucket counts ld picks from, by the number of symbols
+const SYMHASH_BUCKETS: &[usize] = &[
+    1, 3, 17, 37, 67, 97, 131, 197, 263, 521, 1031, 2053, 4099, 8209, 16411, 32771,
+];
 
-pub fn symhash(eh: &Header, symbols: &Vec<Symbol>, link: u32) -> Result<Section, Error> {
-    assert!(symbols.len() > 0);
-    //TODO i'm too lazy to do this correctly now, so we'll just emit a hashtable with nbuckets  == 1
-    let mut b = Vec::new();
-    {
-        let io = &mut b;
-        elf_write_uclass!(eh, io, 1)?; //nbuckets
-        elf_write_uclass!(eh, io, symbols.len() as u64)?; //nchains
-
-        elf_write_uclass!(eh, io, 1)?; //the bucket. pointing at symbol 1
+/// the size of a word in a SHT_HASH section. it's 32 bit everywhere but on 64 bit s390
+pub fn symhash_entsize(eh: &Header) -> usize {
+    match (&eh.machine, &eh.ident_class) {
+        (&types::Machine::S390, &types::Class::Class64) => 8,
+        _ => 4,
+    }
+}
 
-        elf_write_uclass!(eh, io, 0)?; //symbol 0
+fn write_symhash_word(eh: &Header, io: &mut Vec<u8>, v: u64) -> Result<(), Error> {
+    if symhash_entsize(eh) == 8 {
+        elf_write_u64!(eh, io, v)
+    } else {
+        elf_write_u32!(eh, io, v as u32)
+    }?;
+    Ok(())
+}
 
-        //the chains. every symbol just points at the next, because nbuckets == 1
-        for i in 1..symbols.len() - 1 {
-            elf_write_uclass!(eh, io, i as u64 + 1)?;
-        }
+/// build a SHT_HASH section for a dynamic symbol table, with link being the index of that table.
+/// to regenerate .hash of an existing file, pass its loaded .dynsym and replace the content
+/// of the old section with the one returned
+pub fn symhash(eh: &Header, symbols: &[Symbol], link: u32) -> Result<Section, Error> {
+    if symbols.is_empty() {
+        return Err(Error::SymhashWithoutSymbols);
+    }
+    let nbucket = SYMHASH_BUCKETS
+        .iter()
+        .cloned()
+        .take_while(|&n| n <= symbols.len())
+        .last()
+        .unwrap_or(1);
+    let mut buckets = vec![0; nbucket];
+    let mut chains = vec![0; symbols.len()];
+    // symbol 0 is the undefined symbol and never looked up
+    for (i, sym) in symbols.iter().enumerate().skip(1) {
+        let b = sysv_hash(&sym.name) as usize % nbucket;
+        chains[i] = buckets[b];
+        buckets[b] = i;
+    }
 
-        //except the last one
-        elf_write_uclass!(eh, io, 0)?;
+    let mut b = Vec::new();
+    write_symhash_word(eh, &mut b, nbucket as u64)?;
+    write_symhash_word(eh, &mut b, symbols.len() as u64)?;
+    for v in buckets.into_iter().chain(chains) {
+        write_symhash_word(eh, &mut b, v as u64)?;
     }
 
     Ok(Section {
@@ -245,9 +515,75 @@ pub fn symhash(eh: &Header, symbols: &Vec<Symbol>, link: u32) -> Result<Section,
             size: b.len() as u64,
             link: link,
             info: 0,
-            addralign: 0,
-            entsize: 8, // or 4 for CLass32
+            addralign: symhash_entsize(eh) as u64,
+            entsize: symhash_entsize(eh) as u64,
         },
         content: SectionContent::Raw(b),
+        link_ref: None,
+        info_ref: None,
+        placement: Placement::default(),
+        id: SectionId::default(),
     })
 }
+
+/**
+ * check that the content of a SHT_HASH section finds every symbol of its table.
+ * nchain must be the number of symbols, every index must be in the table, chains can't loop,
+ * and every named symbol has to be on the chain of the bucket its name hashes to.
+ * a corrupted or tampered hash makes the dynamic loader miss symbols, or find other ones.
+ */
+pub fn verify_symhash(eh: &Header, hash: &[u8], symbols: &[Symbol]) -> Result<(), Error> {
+    let size = symhash_entsize(eh);
+    let mut io = hash;
+    let mut words = Vec::with_capacity(hash.len() / size);
+    while io.len() >= size {
+        words.push(if size == 8 {
+            elf_read_u64!(eh, io)?
+        } else {
+            elf_read_u32!(eh, io)? as u64
+        } as usize);
+    }
+    if words.len() < 2 {
+        return Err(Error::InvalidSymhash("header is truncated"));
+    }
+    let (nbucket, nchain) = (words[0], words[1]);
+    if nbucket == 0 {
+        return Err(Error::InvalidSymhash("no buckets"));
+    }
+    if nchain != symbols.len() {
+        return Err(Error::InvalidSymhash("nchain is not the number of symbols"));
+    }
+    if words.len() - 2 < nbucket || words.len() - 2 - nbucket < nchain {
+        return Err(Error::InvalidSymhash("buckets or chains are truncated"));
+    }
+    let buckets = &words[2..2 + nbucket];
+    let chains = &words[2 + nbucket..2 + nbucket + nchain];
+    if buckets.iter().chain(chains.iter()).any(|&i| i >= nchain) {
+        return Err(Error::InvalidSymhash("symbol index out of range"));
+    }
+
+    let mut seen = vec![false; nchain];
+    for &head in buckets {
+        let mut i = head;
+        while i != 0 {
+            if seen[i] {
+                return Err(Error::InvalidSymhash("chains loop or share symbols"));
+            }
+            seen[i] = true;
+            i = chains[i];
+        }
+    }
+    for (i, sym) in symbols.iter().enumerate().skip(1) {
+        if sym.name.is_empty() {
+            continue;
+        }
+        let mut k = buckets[sysv_hash(&sym.name) as usize % nbucket];
+        while k != 0 && k != i {
+            k = chains[k];
+        }
+        if k != i {
+            return Err(Error::SymbolNotInSymhash(sym.name.clone()));
+        }
+    }
+    Ok(())
+}
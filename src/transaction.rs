@@ -0,0 +1,101 @@
+/*!
+ * editing an Elf as a batch that either applies completely or not at all.
+ *
+ * a Transaction takes a copy of the file when it starts and derefs to the Elf, so any method
+ * can be used to edit it. commit validates the result and puts the copy back if the edits
+ * introduced an error, so a mistake halfway through a batch, or a panic, doesn't leave the file
+ * half modified.
+ */
+
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use elf::Elf;
+use validate::{Finding, Severity};
+
+pub struct Transaction<'a> {
+    elf: &'a mut Elf,
+    /// the file as it was before, None once committed or rolled back
+    before: Option<Elf>,
+    /// the number of warnings before, the ones added since are dropped on rollback
+    warnings: usize,
+}
+
+impl Elf {
+    /**
+     * start editing this file as a batch, see Transaction.
+     * the whole file is copied, including all section content
+     */
+    pub fn transaction(&mut self) -> Transaction<'_> {
+        Transaction {
+            before: Some(self.snapshot()),
+            warnings: self.warnings.len(),
+            elf: self,
+        }
+    }
+}
+
+impl<'a> Transaction<'a> {
+    /**
+     * keep the edits if Elf::validate finds no error in the result that wasn't in the file
+     * before. otherwise the file is put back as it was and those errors are returned.
+     * warnings never fail a commit.
+     * like validate, this loads all sections
+     */
+    pub fn commit(mut self) -> Result<(), Vec<Finding>> {
+        let errors = |findings: Vec<Finding>| {
+            findings
+                .into_iter()
+                .filter(|f| f.severity == Severity::Error)
+                .collect::<Vec<_>>()
+        };
+        let mut new = errors(self.elf.validate());
+        if !new.is_empty() {
+            if let Some(ref mut before) = self.before {
+                let old: Vec<String> =
+                    errors(before.validate()).iter().map(|f| f.to_string()).collect();
+                new.retain(|f| !old.contains(&f.to_string()));
+            }
+        }
+        if !new.is_empty() {
+            self.restore();
+            return Err(new);
+        }
+        self.before = None;
+        Ok(())
+    }
+
+    /// put the file back as it was when the transaction started
+    pub fn rollback(mut self) {
+        self.restore();
+    }
+
+    fn restore(&mut self) {
+        if let Some(before) = self.before.take() {
+            let mut warnings = mem::take(&mut self.elf.warnings);
+            warnings.truncate(self.warnings);
+            *self.elf = before;
+            self.elf.warnings = warnings;
+        }
+    }
+}
+
+/// a transaction that is neither committed nor rolled back, by a return or a panic,
+/// rolls back
+impl<'a> Drop for Transaction<'a> {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+impl<'a> Deref for Transaction<'a> {
+    type Target = Elf;
+    fn deref(&self) -> &Elf {
+        self.elf
+    }
+}
+
+impl<'a> DerefMut for Transaction<'a> {
+    fn deref_mut(&mut self) -> &mut Elf {
+        self.elf
+    }
+}
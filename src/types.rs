@@ -1,6 +1,7 @@
-use std::fmt;
+use core::fmt;
 
 use Header;
+use prelude::*;
 
 bitflags! {
 #[derive(Default)]
@@ -30,6 +31,8 @@ bitflags! {
 
         /// Section with compressed data
         const COMPRESSED        = (1 << 11);
+        /// Not to be garbage collected by the linker
+        const GNU_RETAIN        = (1 << 21);
         /// OS-specific
         const MASKOS            = 0x0ff00000;
         /// Processor-specific
@@ -46,6 +49,14 @@ bitflags! {
     }
 }
 
+impl SectionFlags {
+    /// like from_bits, but keeps bits that have no name instead of rejecting them,
+    /// so flags from unknown toolchains are written back unchanged
+    pub fn from_bits_retain(bits: u64) -> SectionFlags {
+        SectionFlags { bits }
+    }
+}
+
 impl fmt::Display for SectionFlags {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut dstr = String::new();
@@ -83,7 +94,10 @@ impl fmt::Display for SectionFlags {
         if self.bits & SectionFlags::COMPRESSED.bits > 0 {
             dstr += "C";
         }
-        if self.bits & SectionFlags::MASKOS.bits > 0 {
+        if self.bits & SectionFlags::GNU_RETAIN.bits > 0 {
+            dstr += "R";
+        }
+        if self.bits & SectionFlags::MASKOS.bits & !SectionFlags::GNU_RETAIN.bits > 0 {
             dstr += "o";
         }
         if self.bits & SectionFlags::MIPS_GPREL.bits > 0 {
@@ -126,14 +140,57 @@ bitflags! {
 
         ///the o32 abi made 64 by some undocumented gnu stuff (i sincerely hope this isn't in use)
         const MIPS_ABI_O64      = 0x00002000;
+        const MIPS_ABI_EABI32   = 0x00003000;
+        const MIPS_ABI_EABI64   = 0x00004000;
+
+        /// n32
+        const MIPS_ABI2         = 0x00000020;
+        const MIPS_32BITMODE    = 0x00000100;
+        const MIPS_FP64         = 0x00000200;
+        const MIPS_NAN2008      = 0x00000400;
+
+        const MIPS_ARCH_2       = 0x10000000;
+        const MIPS_ARCH_3       = 0x20000000;
+        const MIPS_ARCH_4       = 0x30000000;
+        const MIPS_ARCH_5       = 0x40000000;
+        const MIPS_ARCH_32R6    = 0x90000000;
+        const MIPS_ARCH_64R6    = 0xa0000000;
+        /// where MIPS_ABI_O32 to MIPS_ABI_EABI64 are, 0 for the default of the class
+        const MIPS_ABI          = 0x0000f000;
 
         const ARM_EABI_VER1            = 0x01000000;
         const ARM_EABI_VER2            = 0x02000000;
         const ARM_EABI_VER3            = 0x03000000;
         const ARM_EABI_VER4            = 0x04000000;
         const ARM_EABI_VER5            = 0x05000000;
+        const ARM_EABI_MASK            = 0xff000000;
+        /// BE8 code, byte invariant data with little endian instructions
+        const ARM_BE8               = 0x00800000;
         const ARM_ABI_FLOAT_HARD    = 0x00000400;
         const ARM_ABI_FLOAT_SOFT    = 0x00000200;
+
+        /// ppc64 abi version, 1 for function descriptors, 2 for ELFv2
+        const PPC64_ABI_V1          = 0x00000001;
+        const PPC64_ABI_V2          = 0x00000002;
+
+        /// the code uses compressed instructions
+        const RISCV_RVC             = 0x00000001;
+        /// how floating point arguments are passed, 0 for soft float
+        const RISCV_FLOAT_ABI       = 0x00000006;
+        const RISCV_FLOAT_ABI_SINGLE = 0x00000002;
+        const RISCV_FLOAT_ABI_DOUBLE = 0x00000004;
+        const RISCV_FLOAT_ABI_QUAD  = 0x00000006;
+        /// the embedded base isa with 16 registers
+        const RISCV_RVE             = 0x00000008;
+        /// the code needs total store ordering
+        const RISCV_TSO             = 0x00000010;
+    }
+}
+
+impl HeaderFlags {
+    /// e_flags are mostly processor specific, keep what we don't know about
+    pub fn from_bits_retain(bits: u32) -> HeaderFlags {
+        HeaderFlags { bits }
     }
 }
 
@@ -146,6 +203,48 @@ bitflags! {
     }
 }
 
+bitflags! {
+#[derive(Default)]
+    /// GNU_PROPERTY_X86_FEATURE_1_AND, control flow enforcement the code supports
+    pub struct X86Feature1: u32 {
+        /// indirect branch tracking, all indirect branch targets start with endbr
+        const IBT        = 1 << 0;
+        /// the code is compatible with shadow stacks
+        const SHSTK      = 1 << 1;
+    }
+}
+
+bitflags! {
+#[derive(Default)]
+    /// GNU_PROPERTY_AARCH64_FEATURE_1_AND
+    pub struct Aarch64Feature1: u32 {
+        /// branch target identification, all indirect branch targets start with bti
+        const BTI        = 1 << 0;
+        /// return addresses are signed with pointer authentication
+        const PAC        = 1 << 1;
+    }
+}
+
+impl X86Feature1 {
+    /// features added after this was written are kept, and-ing them still works
+    pub fn from_bits_retain(bits: u32) -> X86Feature1 {
+        X86Feature1 { bits }
+    }
+}
+
+impl Aarch64Feature1 {
+    pub fn from_bits_retain(bits: u32) -> Aarch64Feature1 {
+        Aarch64Feature1 { bits }
+    }
+}
+
+impl SegmentFlags {
+    /// keeps the PF_MASKOS and PF_MASKPROC bits, which have no name here
+    pub fn from_bits_retain(bits: u64) -> SegmentFlags {
+        SegmentFlags { bits }
+    }
+}
+
 impl fmt::Display for SegmentFlags {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let dstr = format!("{:?}", self);
@@ -251,6 +350,9 @@ impl SectionType {
     pub const ARM_DEBUGOVERLAY: SectionType = SectionType(0x70000004);
     pub const ARM_OVERLAYSECTION: SectionType = SectionType(0x70000005);
 
+    //riscv
+    pub const RISCV_ATTRIBUTES: SectionType = SectionType(0x70000003);
+
     //mips
     pub const MIPS_LIBLIST: SectionType = SectionType(0x70000001);
     pub const MIPS_CONFLICT: SectionType = SectionType(0x70000002);
@@ -258,6 +360,9 @@ impl SectionType {
     pub const MIPS_UCODE: SectionType = SectionType(0x70000004);
     pub const MIPS_DEBUG: SectionType = SectionType(0x70000005);
     pub const MIPS_REGINFO: SectionType = SectionType(0x70000006);
+    pub const MIPS_OPTIONS: SectionType = SectionType(0x7000000d);
+    pub const MIPS_DWARF: SectionType = SectionType(0x7000001e);
+    pub const MIPS_ABIFLAGS: SectionType = SectionType(0x7000002a);
 
     /// Relinkable content. this is a korhal bolter extension
     pub const RELINKABLE: SectionType = SectionType(0x6fffff01);
@@ -303,11 +408,15 @@ impl SectionType {
             (&Machine::MIPS, &SectionType::MIPS_UCODE) => Some("MIPS_UCODE"),
             (&Machine::MIPS, &SectionType::MIPS_DEBUG) => Some("MIPS_DEBUG"),
             (&Machine::MIPS, &SectionType::MIPS_REGINFO) => Some("MIPS_REGINFO"),
+            (&Machine::MIPS, &SectionType::MIPS_OPTIONS) => Some("MIPS_OPTIONS"),
+            (&Machine::MIPS, &SectionType::MIPS_DWARF) => Some("MIPS_DWARF"),
+            (&Machine::MIPS, &SectionType::MIPS_ABIFLAGS) => Some("MIPS_ABIFLAGS"),
             (&Machine::ARM, &SectionType::ARM_EXIDX) => Some("ARM_EXIDX"),
             (&Machine::ARM, &SectionType::ARM_PREEMPTMAP) => Some("ARM_PREEMPTMAP"),
             (&Machine::ARM, &SectionType::ARM_ATTRIBUTES) => Some("ARM_ATTRIBUTES"),
             (&Machine::ARM, &SectionType::ARM_DEBUGOVERLAY) => Some("ARM_DEBUGOVERLAY"),
             (&Machine::ARM, &SectionType::ARM_OVERLAYSECTION) => Some("ARM_OVERLAYSECTION"),
+            (&Machine::RISCV, &SectionType::RISCV_ATTRIBUTES) => Some("RISCV_ATTRIBUTES"),
             (_, &SectionType::RELINKABLE) => Some("RELINKABLE"),
             (_, _) => None,
         }
@@ -320,6 +429,14 @@ impl Default for SectionType {
     }
 }
 
+/// section indices from here on are reserved, larger ones have to be stored elsewhere
+pub const SHN_LORESERVE: u32 = 0xff00;
+/// the real section index is somewhere else: in the SYMTAB_SHNDX section for a symbol,
+/// in the link of section 0 for e_shstrndx
+pub const SHN_XINDEX: u16 = 0xffff;
+/// e_phnum when there are too many program headers, the real number is the info of section 0
+pub const PN_XNUM: u16 = 0xffff;
+
 #[allow(non_camel_case_types)]
 #[derive(Debug, Primitive, PartialEq, Clone)]
 pub enum Abi {
@@ -334,6 +451,13 @@ pub enum Abi {
     TRU64 = 10,
     MODESTO = 11,
     OPENBSD = 12,
+    OPENVMS = 13,
+    NSK = 14,
+    AROS = 15,
+    FENIXOS = 16,
+    CLOUDABI = 17,
+    OPENVOS = 18,
+    /// also C6000_ELFABI and AMDGPU_HSA, the values from 64 up depend on the machine
     ARM_AEABI = 64,
     ARM = 97,
     STANDALONE = 255,
@@ -343,6 +467,21 @@ impl Default for Abi {
         Abi::SYSV
     }
 }
+impl Abi {
+    /// ELFOSABI_LINUX is the old name of GNU
+    pub const LINUX: Abi = Abi::GNU;
+
+    /// whether STT_GNU_IFUNC is an indirect function. for other abis type 10 is just the first
+    /// os specific one. gas marks objects that have them as GNU, and freebsd has them too
+    pub fn has_ifunc(&self) -> bool {
+        *self == Abi::GNU || *self == Abi::FREEBSD
+    }
+
+    /// whether STB_GNU_UNIQUE binds a symbol once in the whole process, which only glibc does
+    pub fn has_unique_symbols(&self) -> bool {
+        *self == Abi::GNU
+    }
+}
 
 #[allow(non_camel_case_types)]
 #[derive(Debug, Primitive, PartialEq, Clone)]
@@ -358,6 +497,13 @@ impl Default for ElfType {
         ElfType::NONE
     }
 }
+impl ElfType {
+    /// relocatable objects and shared libraries, what a linker takes as input.
+    /// executables, core dumps and files with no type can still be read and inspected
+    pub fn is_linkable(&self) -> bool {
+        *self == ElfType::REL || *self == ElfType::DYN
+    }
+}
 
 #[allow(non_camel_case_types)]
 #[derive(Debug, Primitive, PartialEq, Clone)]
@@ -398,7 +544,8 @@ pub enum SymbolBind {
     /// Weak symbol
     WEAK = 2,
 
-    /// obscure gnu thing. i hope this is the same as global
+    /// global, but bound once in the whole process, with the GNU os abi only.
+    /// see Abi::has_unique_symbols
     STB_GNU_UNIQUE = 10,
 }
 impl Default for SymbolBind {
@@ -794,44 +941,86 @@ impl Default for Machine {
 }
 
 
-#[allow(non_camel_case_types)]
-#[derive(Debug, Primitive, PartialEq, Clone)]
-pub enum SegmentType {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentType(pub u32);
+impl SegmentType {
     /// Program header table entry unused
-    NULL = 0,
+    pub const NULL: SegmentType = SegmentType(0);
     /// Loadable program segment
-    LOAD = 1,
+    pub const LOAD: SegmentType = SegmentType(1);
     /// Dynamic linking information
-    DYNAMIC = 2,
+    pub const DYNAMIC: SegmentType = SegmentType(2);
     /// Program interpreter
-    INTERP = 3,
+    pub const INTERP: SegmentType = SegmentType(3);
     /// Auxiliary information
-    NOTE = 4,
+    pub const NOTE: SegmentType = SegmentType(4);
     /// Reserved
-    SHLIB = 5,
+    pub const SHLIB: SegmentType = SegmentType(5);
     /// Entry for header table itself
-    PHDR = 6,
+    pub const PHDR: SegmentType = SegmentType(6);
     /// Thread-local storage segment
-    TLS = 7,
+    pub const TLS: SegmentType = SegmentType(7);
     /// Number of defined types
-    NUM = 8,
+    pub const NUM: SegmentType = SegmentType(8);
     /// GCC .eh_frame_hdr segment
-    GNU_EH_FRAME = 0x6474e550,
+    pub const GNU_EH_FRAME: SegmentType = SegmentType(0x6474e550);
     /// Indicates stack executability
-    GNU_STACK = 0x6474e551,
+    pub const GNU_STACK: SegmentType = SegmentType(0x6474e551);
     /// Read-only after relocation
-    GNU_RELRO = 0x6474e552,
+    pub const GNU_RELRO: SegmentType = SegmentType(0x6474e552);
+    /// GNU property notes
+    pub const GNU_PROPERTY: SegmentType = SegmentType(0x6474e553);
     /// PaX memory protection
-    PAX_FLAGS = 0x65041580,
+    pub const PAX_FLAGS: SegmentType = SegmentType(0x65041580);
     /// Sun Specific segment
-    SUNWBSS = 0x6ffffffa,
+    pub const SUNWBSS: SegmentType = SegmentType(0x6ffffffa);
     /// Stack segment
-    SUNWSTACK = 0x6ffffffb,
+    pub const SUNWSTACK: SegmentType = SegmentType(0x6ffffffb);
+
+    pub const CPU0: SegmentType = SegmentType(0x70000000);
+    pub const CPU1: SegmentType = SegmentType(0x70000001);
+    pub const CPU2: SegmentType = SegmentType(0x70000002);
+    pub const CPU3: SegmentType = SegmentType(0x70000003);
+
+    pub const MIPS_REGINFO: SegmentType = SegmentType(0x70000000);
+    pub const MIPS_RTPROC: SegmentType = SegmentType(0x70000001);
+    pub const MIPS_OPTIONS: SegmentType = SegmentType(0x70000002);
+    pub const MIPS_ABIFLAGS: SegmentType = SegmentType(0x70000003);
 
-    CPU0 = 0x70000000,
-    CPU1 = 0x70000001,
-    CPU2 = 0x70000002,
-    CPU3 = 0x70000003,
+    pub fn to_u32(&self) -> u32 {
+        let &SegmentType(v) = self;
+        v
+    }
+
+    pub fn typename(&self, eh: &Header) -> Option<&'static str> {
+        match (&eh.machine, self) {
+            (_, &SegmentType::NULL) => Some("NULL"),
+            (_, &SegmentType::LOAD) => Some("LOAD"),
+            (_, &SegmentType::DYNAMIC) => Some("DYNAMIC"),
+            (_, &SegmentType::INTERP) => Some("INTERP"),
+            (_, &SegmentType::NOTE) => Some("NOTE"),
+            (_, &SegmentType::SHLIB) => Some("SHLIB"),
+            (_, &SegmentType::PHDR) => Some("PHDR"),
+            (_, &SegmentType::TLS) => Some("TLS"),
+            (_, &SegmentType::NUM) => Some("NUM"),
+            (_, &SegmentType::GNU_EH_FRAME) => Some("GNU_EH_FRAME"),
+            (_, &SegmentType::GNU_STACK) => Some("GNU_STACK"),
+            (_, &SegmentType::GNU_RELRO) => Some("GNU_RELRO"),
+            (_, &SegmentType::GNU_PROPERTY) => Some("GNU_PROPERTY"),
+            (_, &SegmentType::PAX_FLAGS) => Some("PAX_FLAGS"),
+            (_, &SegmentType::SUNWBSS) => Some("SUNWBSS"),
+            (_, &SegmentType::SUNWSTACK) => Some("SUNWSTACK"),
+            (&Machine::MIPS, &SegmentType::MIPS_REGINFO) => Some("MIPS_REGINFO"),
+            (&Machine::MIPS, &SegmentType::MIPS_RTPROC) => Some("MIPS_RTPROC"),
+            (&Machine::MIPS, &SegmentType::MIPS_OPTIONS) => Some("MIPS_OPTIONS"),
+            (&Machine::MIPS, &SegmentType::MIPS_ABIFLAGS) => Some("MIPS_ABIFLAGS"),
+            (_, &SegmentType::CPU0) => Some("CPU0"),
+            (_, &SegmentType::CPU1) => Some("CPU1"),
+            (_, &SegmentType::CPU2) => Some("CPU2"),
+            (_, &SegmentType::CPU3) => Some("CPU3"),
+            (_, _) => None,
+        }
+    }
 }
 
 impl Default for SegmentType {
@@ -840,152 +1029,320 @@ impl Default for SegmentType {
     }
 }
 
-#[allow(non_camel_case_types)]
-#[derive(Debug, Primitive, PartialEq, Clone)]
-pub enum DynamicType {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamicType(pub u64);
+impl DynamicType {
     /// Marks end of dynamic section
-    NULL = 0,
+    pub const NULL: DynamicType = DynamicType(0);
     /// Name of needed library
-    NEEDED = 1,
+    pub const NEEDED: DynamicType = DynamicType(1);
     /// Size in bytes of PLT relocs
-    PLTRELSZ = 2,
+    pub const PLTRELSZ: DynamicType = DynamicType(2);
     /// Processor defined value
-    PLTGOT = 3,
+    pub const PLTGOT: DynamicType = DynamicType(3);
     /// Address of symbol hash table
-    HASH = 4,
+    pub const HASH: DynamicType = DynamicType(4);
     /// Address of string table
-    STRTAB = 5,
+    pub const STRTAB: DynamicType = DynamicType(5);
     /// Address of symbol table
-    SYMTAB = 6,
+    pub const SYMTAB: DynamicType = DynamicType(6);
     /// Address of Rela relocs
-    RELA = 7,
+    pub const RELA: DynamicType = DynamicType(7);
     /// Total size of Rela relocs
-    RELASZ = 8,
+    pub const RELASZ: DynamicType = DynamicType(8);
     /// Size of one Rela reloc
-    RELAENT = 9,
+    pub const RELAENT: DynamicType = DynamicType(9);
     /// Size of string table
-    STRSZ = 10,
+    pub const STRSZ: DynamicType = DynamicType(10);
     /// Size of one symbol table entry
-    SYMENT = 11,
+    pub const SYMENT: DynamicType = DynamicType(11);
     /// Address of init function
-    INIT = 12,
+    pub const INIT: DynamicType = DynamicType(12);
     /// Address of termination function
-    FINI = 13,
+    pub const FINI: DynamicType = DynamicType(13);
     /// Name of shared object
-    SONAME = 14,
+    pub const SONAME: DynamicType = DynamicType(14);
     /// Library search path (deprecated
-    RPATH = 15,
+    pub const RPATH: DynamicType = DynamicType(15);
     /// Start symbol search here
-    SYMBOLIC = 16,
+    pub const SYMBOLIC: DynamicType = DynamicType(16);
     /// Address of Rel relocs
-    REL = 17,
+    pub const REL: DynamicType = DynamicType(17);
     /// Total size of Rel relocs
-    RELSZ = 18,
+    pub const RELSZ: DynamicType = DynamicType(18);
     /// Size of one Rel reloc
-    RELENT = 19,
+    pub const RELENT: DynamicType = DynamicType(19);
     /// Type of reloc in PLT
-    PLTREL = 20,
+    pub const PLTREL: DynamicType = DynamicType(20);
     /// For debugging; unspecified
-    DEBUG = 21,
+    pub const DEBUG: DynamicType = DynamicType(21);
     /// Reloc might modify .text
-    TEXTREL = 22,
+    pub const TEXTREL: DynamicType = DynamicType(22);
     /// Address of PLT relocs
-    JMPREL = 23,
+    pub const JMPREL: DynamicType = DynamicType(23);
     /// Process relocations of object
-    BIND_NOW = 24,
+    pub const BIND_NOW: DynamicType = DynamicType(24);
     /// Array with addresses of init fct
-    INIT_ARRAY = 25,
+    pub const INIT_ARRAY: DynamicType = DynamicType(25);
     /// Array with addresses of fini fct
-    FINI_ARRAY = 26,
+    pub const FINI_ARRAY: DynamicType = DynamicType(26);
     /// Size in bytes of DT_INIT_ARRAY
-    INIT_ARRAYSZ = 27,
+    pub const INIT_ARRAYSZ: DynamicType = DynamicType(27);
     /// Size in bytes of DT_FINI_ARRAY
-    FINI_ARRAYSZ = 28,
+    pub const FINI_ARRAYSZ: DynamicType = DynamicType(28);
     /// Library search path
-    RUNPATH = 29,
+    pub const RUNPATH: DynamicType = DynamicType(29);
     /// Flags for the object being loaded
-    FLAGS = 30,
+    pub const FLAGS: DynamicType = DynamicType(30);
     /// Array with addresses of preinit fct
-    PREINIT_ARRAY = 32,
+    pub const PREINIT_ARRAY: DynamicType = DynamicType(32);
     /// size in bytes of DT_PREINIT_ARRAY
-    PREINIT_ARRAYSZ = 33,
+    pub const PREINIT_ARRAYSZ: DynamicType = DynamicType(33);
     /// Number used
-    NUM = 34,
+    pub const NUM: DynamicType = DynamicType(34);
 
     /// Prelinking timestamp
-    GNU_PRELINKED = 0x6ffffdf5,
+    pub const GNU_PRELINKED: DynamicType = DynamicType(0x6ffffdf5);
     /// Size of conflict section
-    GNU_CONFLICTSZ = 0x6ffffdf6,
+    pub const GNU_CONFLICTSZ: DynamicType = DynamicType(0x6ffffdf6);
     /// Size of library list
-    GNU_LIBLISTSZ = 0x6ffffdf7,
-    CHECKSUM = 0x6ffffdf8,
-    PLTPADSZ = 0x6ffffdf9,
-    MOVEENT = 0x6ffffdfa,
-    MOVESZ = 0x6ffffdfb,
+    pub const GNU_LIBLISTSZ: DynamicType = DynamicType(0x6ffffdf7);
+    pub const CHECKSUM: DynamicType = DynamicType(0x6ffffdf8);
+    pub const PLTPADSZ: DynamicType = DynamicType(0x6ffffdf9);
+    pub const MOVEENT: DynamicType = DynamicType(0x6ffffdfa);
+    pub const MOVESZ: DynamicType = DynamicType(0x6ffffdfb);
     /// Feature selection (DTF_
-    FEATURE_1 = 0x6ffffdfc,
+    pub const FEATURE_1: DynamicType = DynamicType(0x6ffffdfc);
     /// Flags for DT_* entries, effecting the following DT_* entry
-    POSFLAG_1 = 0x6ffffdfd,
+    pub const POSFLAG_1: DynamicType = DynamicType(0x6ffffdfd);
     /// Size of syminfo table (in bytes
-    SYMINSZ = 0x6ffffdfe,
+    pub const SYMINSZ: DynamicType = DynamicType(0x6ffffdfe);
     /// Entry size of syminfo
-    SYMINENT = 0x6ffffdff,
+    pub const SYMINENT: DynamicType = DynamicType(0x6ffffdff);
 
     /// GNU-style hash table
-    GNU_HASH = 0x6ffffef5,
-    TLSDESC_PLT = 0x6ffffef6,
-    TLSDESC_GOT = 0x6ffffef7,
+    pub const GNU_HASH: DynamicType = DynamicType(0x6ffffef5);
+    pub const TLSDESC_PLT: DynamicType = DynamicType(0x6ffffef6);
+    pub const TLSDESC_GOT: DynamicType = DynamicType(0x6ffffef7);
     /// Start of conflict section
-    GNU_CONFLICT = 0x6ffffef8,
+    pub const GNU_CONFLICT: DynamicType = DynamicType(0x6ffffef8);
     /// Library list
-    GNU_LIBLIST = 0x6ffffef9,
+    pub const GNU_LIBLIST: DynamicType = DynamicType(0x6ffffef9);
     /// Configuration information
-    CONFIG = 0x6ffffefa,
+    pub const CONFIG: DynamicType = DynamicType(0x6ffffefa);
     /// Dependency auditing
-    DEPAUDIT = 0x6ffffefb,
+    pub const DEPAUDIT: DynamicType = DynamicType(0x6ffffefb);
     /// Object auditing
-    AUDIT = 0x6ffffefc,
+    pub const AUDIT: DynamicType = DynamicType(0x6ffffefc);
     /// PLT padding
-    PLTPAD = 0x6ffffefd,
+    pub const PLTPAD: DynamicType = DynamicType(0x6ffffefd);
     /// Move table
-    MOVETAB = 0x6ffffefe,
+    pub const MOVETAB: DynamicType = DynamicType(0x6ffffefe);
     /// Syminfo table
-    SYMINFO = 0x6ffffeff,
+    pub const SYMINFO: DynamicType = DynamicType(0x6ffffeff);
 
-    VERSYM = 0x6ffffff0,
-    RELACOUNT = 0x6ffffff9,
-    RELCOUNT = 0x6ffffffa,
+    pub const VERSYM: DynamicType = DynamicType(0x6ffffff0);
+    pub const RELACOUNT: DynamicType = DynamicType(0x6ffffff9);
+    pub const RELCOUNT: DynamicType = DynamicType(0x6ffffffa);
     /// State flags, see DF_1_* below
-    FLAGS_1 = 0x6ffffffb,
+    pub const FLAGS_1: DynamicType = DynamicType(0x6ffffffb);
     /// Address of version definition table
-    VERDEF = 0x6ffffffc,
+    pub const VERDEF: DynamicType = DynamicType(0x6ffffffc);
     /// Number of version definitions
-    VERDEFNUM = 0x6ffffffd,
+    pub const VERDEFNUM: DynamicType = DynamicType(0x6ffffffd);
     /// Address of table with needed versions
-    VERNEED = 0x6ffffffe,
+    pub const VERNEED: DynamicType = DynamicType(0x6ffffffe);
     /// Number of needed versions
-    VERNEEDNUM = 0x6fffffff,
+    pub const VERNEEDNUM: DynamicType = DynamicType(0x6fffffff);
     /// Shared object to load before self
-    AUXILIARY = 0x7ffffffd,
+    pub const AUXILIARY: DynamicType = DynamicType(0x7ffffffd);
     /// Shared object to get values from
-    FILTER = 0x7fffffff,
-
-    MIPS_RLD_VERSION = 0x70000001,
-    MIPS_TIME_STAMP = 0x70000002,
-    MIPS_ICHECKSUM = 0x70000003,
-    MIPS_IVERSION = 0x70000004,
-    MIPS_FLAGS = 0x70000005,
-    MIPS_BASE_ADDRESS = 0x70000006,
-    MIPS_CONFLICT = 0x70000008,
-    MIPS_LIBLIST = 0x70000009,
-    MIPS_LOCAL_GOTNO = 0x7000000A,
-    MIPS_CONFLICTNO = 0x7000000B,
-    MIPS_LIBLISTNO = 0x70000010,
-    MIPS_SYMTABNO = 0x70000011,
-    MIPS_UNREFEXTNO = 0x70000012,
-    MIPS_GOTSYM = 0x70000013,
-    MIPS_HIPAGENO = 0x70000014,
-    MIPS_RLD_MAP = 0x70000016,
+    pub const FILTER: DynamicType = DynamicType(0x7fffffff);
+
+    pub const MIPS_RLD_VERSION: DynamicType = DynamicType(0x70000001);
+    pub const MIPS_TIME_STAMP: DynamicType = DynamicType(0x70000002);
+    pub const MIPS_ICHECKSUM: DynamicType = DynamicType(0x70000003);
+    pub const MIPS_IVERSION: DynamicType = DynamicType(0x70000004);
+    pub const MIPS_FLAGS: DynamicType = DynamicType(0x70000005);
+    pub const MIPS_BASE_ADDRESS: DynamicType = DynamicType(0x70000006);
+    pub const MIPS_CONFLICT: DynamicType = DynamicType(0x70000008);
+    pub const MIPS_LIBLIST: DynamicType = DynamicType(0x70000009);
+    pub const MIPS_LOCAL_GOTNO: DynamicType = DynamicType(0x7000000A);
+    pub const MIPS_CONFLICTNO: DynamicType = DynamicType(0x7000000B);
+    pub const MIPS_LIBLISTNO: DynamicType = DynamicType(0x70000010);
+    pub const MIPS_SYMTABNO: DynamicType = DynamicType(0x70000011);
+    pub const MIPS_UNREFEXTNO: DynamicType = DynamicType(0x70000012);
+    pub const MIPS_GOTSYM: DynamicType = DynamicType(0x70000013);
+    pub const MIPS_HIPAGENO: DynamicType = DynamicType(0x70000014);
+    pub const MIPS_RLD_MAP: DynamicType = DynamicType(0x70000016);
+    pub const MIPS_MSYM: DynamicType = DynamicType(0x70000007);
+    pub const MIPS_DELTA_CLASS: DynamicType = DynamicType(0x70000017);
+    pub const MIPS_DELTA_CLASS_NO: DynamicType = DynamicType(0x70000018);
+    pub const MIPS_DELTA_INSTANCE: DynamicType = DynamicType(0x70000019);
+    pub const MIPS_DELTA_INSTANCE_NO: DynamicType = DynamicType(0x7000001A);
+    pub const MIPS_DELTA_RELOC: DynamicType = DynamicType(0x7000001B);
+    pub const MIPS_DELTA_RELOC_NO: DynamicType = DynamicType(0x7000001C);
+    pub const MIPS_DELTA_SYM: DynamicType = DynamicType(0x7000001D);
+    pub const MIPS_DELTA_SYM_NO: DynamicType = DynamicType(0x7000001E);
+    pub const MIPS_DELTA_CLASSSYM: DynamicType = DynamicType(0x70000020);
+    pub const MIPS_DELTA_CLASSSYM_NO: DynamicType = DynamicType(0x70000021);
+    pub const MIPS_CXX_FLAGS: DynamicType = DynamicType(0x70000022);
+    pub const MIPS_PIXIE_INIT: DynamicType = DynamicType(0x70000023);
+    pub const MIPS_SYMBOL_LIB: DynamicType = DynamicType(0x70000024);
+    pub const MIPS_LOCALPAGE_GOTIDX: DynamicType = DynamicType(0x70000025);
+    pub const MIPS_LOCAL_GOTIDX: DynamicType = DynamicType(0x70000026);
+    pub const MIPS_HIDDEN_GOTIDX: DynamicType = DynamicType(0x70000027);
+    pub const MIPS_PROTECTED_GOTIDX: DynamicType = DynamicType(0x70000028);
+    pub const MIPS_OPTIONS: DynamicType = DynamicType(0x70000029);
+    pub const MIPS_INTERFACE: DynamicType = DynamicType(0x7000002A);
+    pub const MIPS_DYNSTR_ALIGN: DynamicType = DynamicType(0x7000002B);
+    pub const MIPS_INTERFACE_SIZE: DynamicType = DynamicType(0x7000002C);
+    pub const MIPS_RLD_TEXT_RESOLVE_ADDR: DynamicType = DynamicType(0x7000002D);
+    pub const MIPS_PERF_SUFFIX: DynamicType = DynamicType(0x7000002E);
+    pub const MIPS_COMPACT_SIZE: DynamicType = DynamicType(0x7000002F);
+    pub const MIPS_GP_VALUE: DynamicType = DynamicType(0x70000030);
+    pub const MIPS_AUX_DYNAMIC: DynamicType = DynamicType(0x70000031);
+    pub const MIPS_PLTGOT: DynamicType = DynamicType(0x70000032);
+    pub const MIPS_RWPLT: DynamicType = DynamicType(0x70000034);
+    pub const MIPS_RLD_MAP_REL: DynamicType = DynamicType(0x70000035);
+
+    pub const PPC64_GLINK: DynamicType = DynamicType(0x70000000);
+    pub const PPC64_OPD: DynamicType = DynamicType(0x70000001);
+    pub const PPC64_OPDSZ: DynamicType = DynamicType(0x70000002);
+    pub const PPC64_OPT: DynamicType = DynamicType(0x70000003);
+
+    pub fn to_u64(&self) -> u64 {
+        let &DynamicType(v) = self;
+        v
+    }
+
+    pub fn typename(&self, eh: &Header) -> Option<&'static str> {
+        match (&eh.machine, self) {
+            (_, &DynamicType::NULL) => Some("NULL"),
+            (_, &DynamicType::NEEDED) => Some("NEEDED"),
+            (_, &DynamicType::PLTRELSZ) => Some("PLTRELSZ"),
+            (_, &DynamicType::PLTGOT) => Some("PLTGOT"),
+            (_, &DynamicType::HASH) => Some("HASH"),
+            (_, &DynamicType::STRTAB) => Some("STRTAB"),
+            (_, &DynamicType::SYMTAB) => Some("SYMTAB"),
+            (_, &DynamicType::RELA) => Some("RELA"),
+            (_, &DynamicType::RELASZ) => Some("RELASZ"),
+            (_, &DynamicType::RELAENT) => Some("RELAENT"),
+            (_, &DynamicType::STRSZ) => Some("STRSZ"),
+            (_, &DynamicType::SYMENT) => Some("SYMENT"),
+            (_, &DynamicType::INIT) => Some("INIT"),
+            (_, &DynamicType::FINI) => Some("FINI"),
+            (_, &DynamicType::SONAME) => Some("SONAME"),
+            (_, &DynamicType::RPATH) => Some("RPATH"),
+            (_, &DynamicType::SYMBOLIC) => Some("SYMBOLIC"),
+            (_, &DynamicType::REL) => Some("REL"),
+            (_, &DynamicType::RELSZ) => Some("RELSZ"),
+            (_, &DynamicType::RELENT) => Some("RELENT"),
+            (_, &DynamicType::PLTREL) => Some("PLTREL"),
+            (_, &DynamicType::DEBUG) => Some("DEBUG"),
+            (_, &DynamicType::TEXTREL) => Some("TEXTREL"),
+            (_, &DynamicType::JMPREL) => Some("JMPREL"),
+            (_, &DynamicType::BIND_NOW) => Some("BIND_NOW"),
+            (_, &DynamicType::INIT_ARRAY) => Some("INIT_ARRAY"),
+            (_, &DynamicType::FINI_ARRAY) => Some("FINI_ARRAY"),
+            (_, &DynamicType::INIT_ARRAYSZ) => Some("INIT_ARRAYSZ"),
+            (_, &DynamicType::FINI_ARRAYSZ) => Some("FINI_ARRAYSZ"),
+            (_, &DynamicType::RUNPATH) => Some("RUNPATH"),
+            (_, &DynamicType::FLAGS) => Some("FLAGS"),
+            (_, &DynamicType::PREINIT_ARRAY) => Some("PREINIT_ARRAY"),
+            (_, &DynamicType::PREINIT_ARRAYSZ) => Some("PREINIT_ARRAYSZ"),
+            (_, &DynamicType::NUM) => Some("NUM"),
+            (_, &DynamicType::GNU_PRELINKED) => Some("GNU_PRELINKED"),
+            (_, &DynamicType::GNU_CONFLICTSZ) => Some("GNU_CONFLICTSZ"),
+            (_, &DynamicType::GNU_LIBLISTSZ) => Some("GNU_LIBLISTSZ"),
+            (_, &DynamicType::CHECKSUM) => Some("CHECKSUM"),
+            (_, &DynamicType::PLTPADSZ) => Some("PLTPADSZ"),
+            (_, &DynamicType::MOVEENT) => Some("MOVEENT"),
+            (_, &DynamicType::MOVESZ) => Some("MOVESZ"),
+            (_, &DynamicType::FEATURE_1) => Some("FEATURE_1"),
+            (_, &DynamicType::POSFLAG_1) => Some("POSFLAG_1"),
+            (_, &DynamicType::SYMINSZ) => Some("SYMINSZ"),
+            (_, &DynamicType::SYMINENT) => Some("SYMINENT"),
+            (_, &DynamicType::GNU_HASH) => Some("GNU_HASH"),
+            (_, &DynamicType::TLSDESC_PLT) => Some("TLSDESC_PLT"),
+            (_, &DynamicType::TLSDESC_GOT) => Some("TLSDESC_GOT"),
+            (_, &DynamicType::GNU_CONFLICT) => Some("GNU_CONFLICT"),
+            (_, &DynamicType::GNU_LIBLIST) => Some("GNU_LIBLIST"),
+            (_, &DynamicType::CONFIG) => Some("CONFIG"),
+            (_, &DynamicType::DEPAUDIT) => Some("DEPAUDIT"),
+            (_, &DynamicType::AUDIT) => Some("AUDIT"),
+            (_, &DynamicType::PLTPAD) => Some("PLTPAD"),
+            (_, &DynamicType::MOVETAB) => Some("MOVETAB"),
+            (_, &DynamicType::SYMINFO) => Some("SYMINFO"),
+            (_, &DynamicType::VERSYM) => Some("VERSYM"),
+            (_, &DynamicType::RELACOUNT) => Some("RELACOUNT"),
+            (_, &DynamicType::RELCOUNT) => Some("RELCOUNT"),
+            (_, &DynamicType::FLAGS_1) => Some("FLAGS_1"),
+            (_, &DynamicType::VERDEF) => Some("VERDEF"),
+            (_, &DynamicType::VERDEFNUM) => Some("VERDEFNUM"),
+            (_, &DynamicType::VERNEED) => Some("VERNEED"),
+            (_, &DynamicType::VERNEEDNUM) => Some("VERNEEDNUM"),
+            (_, &DynamicType::AUXILIARY) => Some("AUXILIARY"),
+            (_, &DynamicType::FILTER) => Some("FILTER"),
+            (&Machine::MIPS, &DynamicType::MIPS_RLD_VERSION) => Some("MIPS_RLD_VERSION"),
+            (&Machine::MIPS, &DynamicType::MIPS_TIME_STAMP) => Some("MIPS_TIME_STAMP"),
+            (&Machine::MIPS, &DynamicType::MIPS_ICHECKSUM) => Some("MIPS_ICHECKSUM"),
+            (&Machine::MIPS, &DynamicType::MIPS_IVERSION) => Some("MIPS_IVERSION"),
+            (&Machine::MIPS, &DynamicType::MIPS_FLAGS) => Some("MIPS_FLAGS"),
+            (&Machine::MIPS, &DynamicType::MIPS_BASE_ADDRESS) => Some("MIPS_BASE_ADDRESS"),
+            (&Machine::MIPS, &DynamicType::MIPS_CONFLICT) => Some("MIPS_CONFLICT"),
+            (&Machine::MIPS, &DynamicType::MIPS_LIBLIST) => Some("MIPS_LIBLIST"),
+            (&Machine::MIPS, &DynamicType::MIPS_LOCAL_GOTNO) => Some("MIPS_LOCAL_GOTNO"),
+            (&Machine::MIPS, &DynamicType::MIPS_CONFLICTNO) => Some("MIPS_CONFLICTNO"),
+            (&Machine::MIPS, &DynamicType::MIPS_LIBLISTNO) => Some("MIPS_LIBLISTNO"),
+            (&Machine::MIPS, &DynamicType::MIPS_SYMTABNO) => Some("MIPS_SYMTABNO"),
+            (&Machine::MIPS, &DynamicType::MIPS_UNREFEXTNO) => Some("MIPS_UNREFEXTNO"),
+            (&Machine::MIPS, &DynamicType::MIPS_GOTSYM) => Some("MIPS_GOTSYM"),
+            (&Machine::MIPS, &DynamicType::MIPS_HIPAGENO) => Some("MIPS_HIPAGENO"),
+            (&Machine::MIPS, &DynamicType::MIPS_RLD_MAP) => Some("MIPS_RLD_MAP"),
+            (&Machine::MIPS, &DynamicType::MIPS_MSYM) => Some("MIPS_MSYM"),
+            (&Machine::MIPS, &DynamicType::MIPS_DELTA_CLASS) => Some("MIPS_DELTA_CLASS"),
+            (&Machine::MIPS, &DynamicType::MIPS_DELTA_CLASS_NO) => Some("MIPS_DELTA_CLASS_NO"),
+            (&Machine::MIPS, &DynamicType::MIPS_DELTA_INSTANCE) => Some("MIPS_DELTA_INSTANCE"),
+            (&Machine::MIPS, &DynamicType::MIPS_DELTA_INSTANCE_NO) => {
+                Some("MIPS_DELTA_INSTANCE_NO")
+            }
+            (&Machine::MIPS, &DynamicType::MIPS_DELTA_RELOC) => Some("MIPS_DELTA_RELOC"),
+            (&Machine::MIPS, &DynamicType::MIPS_DELTA_RELOC_NO) => Some("MIPS_DELTA_RELOC_NO"),
+            (&Machine::MIPS, &DynamicType::MIPS_DELTA_SYM) => Some("MIPS_DELTA_SYM"),
+            (&Machine::MIPS, &DynamicType::MIPS_DELTA_SYM_NO) => Some("MIPS_DELTA_SYM_NO"),
+            (&Machine::MIPS, &DynamicType::MIPS_DELTA_CLASSSYM) => Some("MIPS_DELTA_CLASSSYM"),
+            (&Machine::MIPS, &DynamicType::MIPS_DELTA_CLASSSYM_NO) => {
+                Some("MIPS_DELTA_CLASSSYM_NO")
+            }
+            (&Machine::MIPS, &DynamicType::MIPS_CXX_FLAGS) => Some("MIPS_CXX_FLAGS"),
+            (&Machine::MIPS, &DynamicType::MIPS_PIXIE_INIT) => Some("MIPS_PIXIE_INIT"),
+            (&Machine::MIPS, &DynamicType::MIPS_SYMBOL_LIB) => Some("MIPS_SYMBOL_LIB"),
+            (&Machine::MIPS, &DynamicType::MIPS_LOCALPAGE_GOTIDX) => Some("MIPS_LOCALPAGE_GOTIDX"),
+            (&Machine::MIPS, &DynamicType::MIPS_LOCAL_GOTIDX) => Some("MIPS_LOCAL_GOTIDX"),
+            (&Machine::MIPS, &DynamicType::MIPS_HIDDEN_GOTIDX) => Some("MIPS_HIDDEN_GOTIDX"),
+            (&Machine::MIPS, &DynamicType::MIPS_PROTECTED_GOTIDX) => Some("MIPS_PROTECTED_GOTIDX"),
+            (&Machine::MIPS, &DynamicType::MIPS_OPTIONS) => Some("MIPS_OPTIONS"),
+            (&Machine::MIPS, &DynamicType::MIPS_INTERFACE) => Some("MIPS_INTERFACE"),
+            (&Machine::MIPS, &DynamicType::MIPS_DYNSTR_ALIGN) => Some("MIPS_DYNSTR_ALIGN"),
+            (&Machine::MIPS, &DynamicType::MIPS_INTERFACE_SIZE) => Some("MIPS_INTERFACE_SIZE"),
+            (&Machine::MIPS, &DynamicType::MIPS_RLD_TEXT_RESOLVE_ADDR) => {
+                Some("MIPS_RLD_TEXT_RESOLVE_ADDR")
+            }
+            (&Machine::MIPS, &DynamicType::MIPS_PERF_SUFFIX) => Some("MIPS_PERF_SUFFIX"),
+            (&Machine::MIPS, &DynamicType::MIPS_COMPACT_SIZE) => Some("MIPS_COMPACT_SIZE"),
+            (&Machine::MIPS, &DynamicType::MIPS_GP_VALUE) => Some("MIPS_GP_VALUE"),
+            (&Machine::MIPS, &DynamicType::MIPS_AUX_DYNAMIC) => Some("MIPS_AUX_DYNAMIC"),
+            (&Machine::MIPS, &DynamicType::MIPS_PLTGOT) => Some("MIPS_PLTGOT"),
+            (&Machine::MIPS, &DynamicType::MIPS_RWPLT) => Some("MIPS_RWPLT"),
+            (&Machine::MIPS, &DynamicType::MIPS_RLD_MAP_REL) => Some("MIPS_RLD_MAP_REL"),
+            (&Machine::PPC64, &DynamicType::PPC64_GLINK) => Some("PPC64_GLINK"),
+            (&Machine::PPC64, &DynamicType::PPC64_OPD) => Some("PPC64_OPD"),
+            (&Machine::PPC64, &DynamicType::PPC64_OPDSZ) => Some("PPC64_OPDSZ"),
+            (&Machine::PPC64, &DynamicType::PPC64_OPT) => Some("PPC64_OPT"),
+            (_, _) => None,
+        }
+    }
 }
 
 
@@ -1057,5 +1414,40 @@ bitflags! {
             const STUB = 1 << 26;
             ///position independant executable
             const PIE  = 1 << 27;
+            ///kernel module
+            const KMOD = 1 << 28;
+            ///weak filter
+            const WEAK = 1 << 29;
+            ///no common symbols
+            const NOCOMMON = 1 << 30;
+    }
+}
+
+impl DynamicFlags1 {
+    /// loaders ignore flags they don't know, so files carrying newer ones are still fine
+    pub fn from_bits_retain(bits: u64) -> DynamicFlags1 {
+        DynamicFlags1 { bits }
+    }
+}
+
+bitflags! {
+#[derive(Default)]
+    pub struct DynamicFlags: u64 {
+            ///the object may reference $ORIGIN
+            const ORIGIN     = 1 << 0;
+            ///symbol lookup starts in the object itself
+            const SYMBOLIC   = 1 << 1;
+            ///relocations may modify a non writable segment
+            const TEXTREL    = 1 << 2;
+            ///process all relocations before transferring control
+            const BIND_NOW   = 1 << 3;
+            ///the object uses the static tls model
+            const STATIC_TLS = 1 << 4;
+    }
+}
+
+impl DynamicFlags {
+    pub fn from_bits_retain(bits: u64) -> DynamicFlags {
+        DynamicFlags { bits }
     }
 }
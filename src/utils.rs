@@ -1,61 +1,41 @@
-use std::io::{Read, Result};
-use Header;
-use types;
-
-pub trait ElfEndianReadExt: Read {
-    fn elf_read_u16(&mut self, eh: &Header) -> Result<u16> {
-        use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
-        match eh.ident_endianness {
-            types::Endianness::LittleEndian => self.read_u16::<LittleEndian>(),
-            types::Endianness::BigEndian => self.read_u16::<BigEndian>(),
-        }
-    }
-    fn elf_read_u32(&mut self, eh: &Header) -> Result<u32> {
-        use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
-        match eh.ident_endianness {
-            types::Endianness::LittleEndian => self.read_u32::<LittleEndian>(),
-            types::Endianness::BigEndian => self.read_u32::<BigEndian>(),
-        }
-    }
-}
-impl<R: Read + ?Sized> ElfEndianReadExt for R {}
-
-
 //adapted from https://github.com/cole14/rust-elf/blob/master/src/utils.rs
 
 #[macro_export]
 macro_rules! elf_read_u16 {
     ($header:expr, $io:ident) => ({
-        use byteorder::{LittleEndian, BigEndian, ReadBytesExt};
+        use byteorder::{LittleEndian, BigEndian, ByteOrder};
         use types;
-        match $header.ident_endianness {
-            types::Endianness::LittleEndian => $io.read_u16::<LittleEndian>(),
-            types::Endianness::BigEndian    => $io.read_u16::<BigEndian>(),
-        }
+        let mut b = [0; 2];
+        $io.read_exact(&mut b).map(|_| match $header.ident_endianness {
+            types::Endianness::LittleEndian => LittleEndian::read_u16(&b),
+            types::Endianness::BigEndian    => BigEndian::read_u16(&b),
+        })
     });
 }
 
 #[macro_export]
 macro_rules! elf_read_u32 {
     ($header:expr, $io:ident) => ({
-        use byteorder::{LittleEndian, BigEndian, ReadBytesExt};
+        use byteorder::{LittleEndian, BigEndian, ByteOrder};
         use types;
-        match $header.ident_endianness {
-            types::Endianness::LittleEndian  => $io.read_u32::<LittleEndian>(),
-            types::Endianness::BigEndian     => $io.read_u32::<BigEndian>(),
-        }
+        let mut b = [0; 4];
+        $io.read_exact(&mut b).map(|_| match $header.ident_endianness {
+            types::Endianness::LittleEndian => LittleEndian::read_u32(&b),
+            types::Endianness::BigEndian    => BigEndian::read_u32(&b),
+        })
     });
 }
 
 #[macro_export]
 macro_rules! elf_read_u64 {
     ($header:expr, $io:ident) => ({
-        use byteorder::{LittleEndian, BigEndian, ReadBytesExt};
+        use byteorder::{LittleEndian, BigEndian, ByteOrder};
         use types;
-        match $header.ident_endianness {
-             types::Endianness::LittleEndian  => $io.read_u64::<LittleEndian>(),
-             types::Endianness::BigEndian     => $io.read_u64::<BigEndian>(),
-        }
+        let mut b = [0; 8];
+        $io.read_exact(&mut b).map(|_| match $header.ident_endianness {
+            types::Endianness::LittleEndian => LittleEndian::read_u64(&b),
+            types::Endianness::BigEndian    => BigEndian::read_u64(&b),
+        })
     });
 }
 
@@ -77,46 +57,79 @@ macro_rules! elf_read_uclass {
 #[macro_export]
 macro_rules! elf_write_u16 {
     ($header:expr, $io:ident, $val:expr) => ({
-        use byteorder::{LittleEndian, BigEndian, WriteBytesExt};
+        use byteorder::{LittleEndian, BigEndian, ByteOrder};
         use types;
+        let mut b = [0; 2];
         match $header.ident_endianness {
-            types::Endianness::LittleEndian => $io.write_u16::<LittleEndian>($val),
-            types::Endianness::BigEndian    => $io.write_u16::<BigEndian>($val),
+            types::Endianness::LittleEndian => LittleEndian::write_u16(&mut b, $val),
+            types::Endianness::BigEndian    => BigEndian::write_u16(&mut b, $val),
         }
+        $io.write_all(&b)
     });
 }
 
 #[macro_export]
 macro_rules! elf_write_u32 {
     ($header:expr, $io:ident, $val:expr) => ({
-        use byteorder::{LittleEndian, BigEndian, WriteBytesExt};
+        use byteorder::{LittleEndian, BigEndian, ByteOrder};
         use types;
+        let mut b = [0; 4];
         match $header.ident_endianness {
-            types::Endianness::LittleEndian => $io.write_u32::<LittleEndian>($val),
-            types::Endianness::BigEndian    => $io.write_u32::<BigEndian>($val),
+            types::Endianness::LittleEndian => LittleEndian::write_u32(&mut b, $val),
+            types::Endianness::BigEndian    => BigEndian::write_u32(&mut b, $val),
         }
+        $io.write_all(&b)
     });
 }
 
 #[macro_export]
 macro_rules! elf_write_u64 {
     ($header:expr, $io:ident, $val:expr) => ({
-        use byteorder::{LittleEndian, BigEndian, WriteBytesExt};
+        use byteorder::{LittleEndian, BigEndian, ByteOrder};
         use types;
+        let mut b = [0; 8];
         match $header.ident_endianness {
-            types::Endianness::LittleEndian => $io.write_u64::<LittleEndian>($val),
-            types::Endianness::BigEndian    => $io.write_u64::<BigEndian>($val),
+            types::Endianness::LittleEndian => LittleEndian::write_u64(&mut b, $val),
+            types::Endianness::BigEndian    => BigEndian::write_u64(&mut b, $val),
         }
+        $io.write_all(&b)
     });
 }
 
+/// fails with Error::FieldOverflow for values past 4GiB in 32 bit files,
+/// so it can only be used where errors are elfkit's
 #[macro_export]
 macro_rules! elf_write_uclass {
     ($header:expr, $io:ident, $val:expr) => ({
         use types;
         match $header.ident_class {
-            types::Class::Class32 => elf_write_u32!($header, $io, $val as u32),
+            types::Class::Class32 => {
+                let v: u32 = $crate::utils::narrow("a field of a 32 bit file", $val)?;
+                elf_write_u32!($header, $io, v)
+            }
             types::Class::Class64 => elf_write_u64!($header, $io, $val),
         }
     });
 }
+
+/// v as the type of a narrower field, Error::FieldOverflow naming the field if it doesn't fit
+pub fn narrow<T: TryFrom<u64>>(field: &'static str, v: u64) -> core::result::Result<T, Error> {
+    T::try_from(v).map_err(|_| Error::FieldOverflow(field, v))
+}
+
+use core::convert::TryFrom;
+use error::Error;
+use io::{Read, Result};
+use Header;
+
+pub trait ElfEndianReadExt: Read {
+    fn elf_read_u16(&mut self, eh: &Header) -> Result<u16> {
+        let io = self;
+        elf_read_u16!(eh, io)
+    }
+    fn elf_read_u32(&mut self, eh: &Header) -> Result<u32> {
+        let io = self;
+        elf_read_u32!(eh, io)
+    }
+}
+impl<R: Read + ?Sized> ElfEndianReadExt for R {}
@@ -0,0 +1,219 @@
+use std::fmt;
+use elf::Elf;
+use error::Error;
+use section::{SectionContent, SectionHeader};
+use segment::SegmentHeader;
+use symbol::{self, Symbol, SymbolSectionIndex};
+use relocation::Relocation;
+use dynamic::Dynamic;
+use types;
+
+/// how bad a finding of Elf::validate is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// unusual, but readers cope with it
+    Warning,
+    /// readers will misread the file or refuse it
+    Error,
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    pub severity: Severity,
+    /// located at the section it was found in, if any
+    pub problem: Error,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "warning: {}", self.problem),
+            Severity::Error => write!(f, "error: {}", self.problem),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Report {
+    findings: Vec<Finding>,
+}
+
+impl Report {
+    fn push(&mut self, severity: Severity, problem: Error) {
+        self.findings.push(Finding { severity, problem });
+    }
+
+    fn error(&mut self, problem: Error) {
+        self.push(Severity::Error, problem);
+    }
+
+    fn warning(&mut self, problem: Error) {
+        self.push(Severity::Warning, problem);
+    }
+}
+
+impl Elf {
+    /**
+     * check the whole file for consistency and return everything that's wrong with it.
+     * an empty result means the file is fine, as far as elfkit can tell.
+     *
+     * this is meant for files as they were read by from_reader, values that to_writer fills in
+     * like e_shentsize are expected to be set.
+     * all sections are loaded, a section that fails to load is reported as a finding.
+     */
+    pub fn validate(&mut self) -> Vec<Finding> {
+        let mut r = Report::default();
+        self.validate_header(&mut r);
+        self.validate_section_headers(&mut r);
+        for i in 0..self.sections.len() {
+            if let Err(e) = self.load_at(i) {
+                r.error(e);
+            }
+        }
+        self.validate_content(&mut r);
+        r.findings
+    }
+
+    fn validate_header(&self, r: &mut Report) {
+        let eh = &self.header;
+        if eh.ehsize as usize != eh.size() {
+            r.warning(Error::InvalidHeaderField("e_ehsize"));
+        }
+        if !self.segments.is_empty() && eh.phentsize as usize != SegmentHeader::entsize(eh) {
+            r.error(Error::InvalidHeaderField("e_phentsize"));
+        }
+        if self.sections.is_empty() {
+            return;
+        }
+        if eh.shentsize as usize != SectionHeader::entsize(eh) {
+            r.error(Error::InvalidHeaderField("e_shentsize"));
+        }
+        match self.sections.get(self.shstrndx()) {
+            Some(sec) if sec.header.shtype == types::SectionType::STRTAB => {}
+            Some(_) => r.error(Error::LinkedSectionIsNotStrtab("e_shstrndx")),
+            None => r.error(Error::MissingShstrtabSection),
+        }
+        let null = &self.sections[0].header;
+        if null.shtype != types::SectionType::NULL || null.name != 0 || !null.flags.is_empty()
+            || null.addr != 0 || null.offset != 0 || null.entsize != 0
+        {
+            r.warning(Error::InvalidNullSection);
+        }
+    }
+
+    fn validate_section_headers(&self, r: &mut Report) {
+        let eh = &self.header;
+        let shtype = |i: u32| self.sections.get(i as usize).map(|s| s.header.shtype.clone());
+        let mut ranges = Vec::new();
+        for (i, sec) in self.sections.iter().enumerate().skip(1) {
+            let at = Elf::location(i, sec);
+            let sh = &sec.header;
+            if sh.link as usize >= self.sections.len() {
+                r.error(Error::InvalidSectionLink(sh.link).at(at.clone()));
+            }
+            if sh.flags.contains(types::SectionFlags::INFO_LINK)
+                && sh.info as usize >= self.sections.len()
+            {
+                r.error(Error::InvalidSectionInfo(sh.info).at(at.clone()));
+            }
+
+            let is_symtab = |t: Option<types::SectionType>| {
+                t == Some(types::SectionType::SYMTAB) || t == Some(types::SectionType::DYNSYM)
+            };
+            let (entsize, linked) = match sh.shtype {
+                types::SectionType::SYMTAB | types::SectionType::DYNSYM => {
+                    (Symbol::entsize(eh), shtype(sh.link) == Some(types::SectionType::STRTAB))
+                }
+                types::SectionType::DYNAMIC => {
+                    (Dynamic::entsize(eh), shtype(sh.link) == Some(types::SectionType::STRTAB))
+                }
+                types::SectionType::RELA => {
+                    (Relocation::entsize(eh), sh.link == 0 || is_symtab(shtype(sh.link)))
+                }
+                types::SectionType::REL => {
+                    (Relocation::entsize_rel(eh), sh.link == 0 || is_symtab(shtype(sh.link)))
+                }
+                types::SectionType::SYMTAB_SHNDX => (4, is_symtab(shtype(sh.link))),
+                _ => (0, true),
+            };
+            // 0 is for machines elfkit doesn't know the relocations of
+            if entsize > 0 && sh.entsize != entsize as u64 {
+                r.error(Error::InvalidSectionEntsize(sh.entsize, entsize as u64).at(at.clone()));
+            }
+            if !linked && (sh.link as usize) < self.sections.len() {
+                r.error(match sh.shtype {
+                    types::SectionType::RELA
+                    | types::SectionType::REL
+                    | types::SectionType::SYMTAB_SHNDX => Error::LinkedSectionIsNotSymtab,
+                    _ => Error::LinkedSectionIsNotStrtab("validating"),
+                }.at(at.clone()));
+            }
+
+            // NULL sections take no place in the file, whatever their size says
+            let in_file = sh.shtype != types::SectionType::NOBITS
+                && sh.shtype != types::SectionType::NULL;
+            if in_file && sh.size > 0 {
+                match sh.offset.checked_add(sh.size) {
+                    Some(end) => ranges.push((sh.offset, end, i)),
+                    None => r.error(Error::SectionBeyondEndOfFile(sh.offset, sh.size).at(at)),
+                }
+            }
+        }
+
+        ranges.sort();
+        for w in ranges.windows(2) {
+            let (prev_end, (offset, _, i)) = (w[0].1, w[1]);
+            if offset < prev_end {
+                let sec = &self.sections[i];
+                let e = Error::SectionOverlap(sec.name.clone(), offset, prev_end);
+                r.error(e.at(Elf::location(i, sec)));
+            }
+        }
+    }
+
+    fn validate_content(&self, r: &mut Report) {
+        let symbol_count = |i: u32| match self.sections.get(i as usize).map(|s| &s.content) {
+            Some(&SectionContent::Symbols(ref v)) => v.len(),
+            _ => 0,
+        };
+        for (i, sec) in self.sections.iter().enumerate() {
+            let at = Elf::location(i, sec);
+            match sec.content {
+                SectionContent::Symbols(ref symbols) => {
+                    for (k, sym) in symbols.iter().enumerate() {
+                        if let SymbolSectionIndex::Section(shndx) = sym.shndx {
+                            if shndx as usize >= self.sections.len() {
+                                let e = Error::InvalidSymbolSection(sym.name.clone(), shndx);
+                                r.error(e.at(at.clone()));
+                            }
+                        }
+                        let local = sym.bind == types::SymbolBind::LOCAL;
+                        if k > 0 && local != (k < sec.header.info as usize) {
+                            r.error(Error::InvalidSymtabInfo(sec.header.info).at(at.clone()));
+                            break;
+                        }
+                    }
+                }
+                SectionContent::Raw(ref raw) if sec.header.shtype == types::SectionType::HASH => {
+                    let linked = self.sections.get(sec.header.link as usize);
+                    if let Some(&SectionContent::Symbols(ref symbols)) = linked.map(|s| &s.content)
+                    {
+                        if let Err(e) = symbol::verify_symhash(&self.header, raw, symbols) {
+                            r.error(e.at(at.clone()));
+                        }
+                    }
+                }
+                SectionContent::Relocations(ref relocs) => {
+                    let count = symbol_count(sec.header.link);
+                    for reloc in relocs {
+                        if reloc.sym as usize >= count && reloc.sym > 0 {
+                            let e = Error::InvalidRelocationSymbol(reloc.sym, count);
+                            r.error(e.at(at.clone()));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+extern crate elfkit;
+
+use elfkit::archive::{self, Member};
+use elfkit::{types, Archive, ElfBuilder, Error, Symbol};
+use std::io::Cursor;
+use std::str;
+
+fn object(defines: &[(&str, types::SymbolBind)]) -> Vec<u8> {
+    let mut b = ElfBuilder::new(types::Machine::X86_64)
+        .etype(types::ElfType::REL)
+        .text(vec![0xc3]);
+    for &(name, ref bind) in defines {
+        b = b.symbol(
+            ".text",
+            Symbol {
+                name: String::from(name),
+                bind: bind.clone(),
+                ..Symbol::default()
+            },
+        );
+    }
+    let mut elf = b.build().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    io.into_inner()
+}
+
+fn field(b: &[u8]) -> &str {
+    str::from_utf8(b).unwrap().trim_end()
+}
+
+/// name and content of the member whose header is at offset at
+fn member(ar: &[u8], at: usize) -> (&str, &[u8]) {
+    let h = &ar[at..at + 60];
+    assert_eq!(&h[58..], b"`\n");
+    let size: usize = field(&h[48..58]).parse().unwrap();
+    (field(&h[..16]), &ar[at + 60..at + 60 + size])
+}
+
+#[test]
+fn index_points_at_the_defining_members() {
+    let a = object(&[("add_one", types::SymbolBind::GLOBAL), ("helper", types::SymbolBind::LOCAL)]);
+    let b = object(&[("twice", types::SymbolBind::WEAK)]);
+    let mut ar = Archive::new();
+    ar.add("a.o", a.clone());
+    ar.add("a_rather_long_member_name.o", b.clone());
+    ar.add("README", b"odd".to_vec());
+    assert_eq!(
+        ar.symbol_index().unwrap(),
+        vec![(String::from("add_one"), 0), (String::from("twice"), 1)]
+    );
+    let mut io = Vec::new();
+    ar.to_writer(&mut io).unwrap();
+
+    assert!(io.starts_with(b"!<arch>\n"));
+    let (name, index) = member(&io, 8);
+    assert_eq!(name, "/");
+    let word = |i: usize| {
+        u32::from_be_bytes([index[4 * i], index[4 * i + 1], index[4 * i + 2], index[4 * i + 3]])
+            as usize
+    };
+    assert_eq!(word(0), 2);
+    assert_eq!(&index[12..], b"add_one\0twice\0");
+
+    let (name, names) = member(&io, 8 + 60 + index.len());
+    assert_eq!(name, "//");
+    assert_eq!(names, b"a_rather_long_member_name.o/\n\n");
+
+    assert_eq!(member(&io, word(1)), ("a.o/", &a[..]));
+    assert_eq!(member(&io, word(2)), ("/0", &b[..]));
+    let readme = word(2) + 60 + b.len() + b.len() % 2;
+    assert_eq!(member(&io, readme), ("README/", &b"odd"[..]));
+    assert_eq!(io.len(), readme + 60 + 4);
+}
+
+#[test]
+fn archives_without_symbols_have_no_index() {
+    let mut ar = Archive::new();
+    ar.add("notes.txt", b"not an object\n".to_vec());
+    let mut io = Vec::new();
+    ar.to_writer(&mut io).unwrap();
+    assert_eq!(member(&io, 8), ("notes.txt/", &b"not an object\n"[..]));
+
+    // and the same members always make the same archive
+    let mut again = Vec::new();
+    ar.to_writer(&mut again).unwrap();
+    assert_eq!(io, again);
+}
+
+#[test]
+fn bad_members() {
+    let mut ar = Archive::new();
+    ar.add("dir/a.o", Vec::new());
+    match ar.to_writer(&mut Vec::new()) {
+        Err(Error::InvalidArchive("member name is not a file name")) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+
+    let mut ar = Archive::new();
+    ar.add("broken.o", b"\x7fELF\x02\x01\x01\x00".to_vec());
+    match ar.to_writer(&mut Vec::new()) {
+        Err(e @ Error::ArchiveMember(..)) => {
+            assert!(e.to_string().starts_with("archive member broken.o: "));
+        }
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[test]
+fn ranlib_indexes_an_archive_without_index() {
+    let a = object(&[("add_one", types::SymbolBind::GLOBAL)]);
+    let b = object(&[("twice", types::SymbolBind::GLOBAL)]);
+    // as ar qS writes it, with timestamps and owners
+    let mut unindexed = b"!<arch>\n".to_vec();
+    for &(name, data) in &[("a.o/", &a), ("b.o/", &b)] {
+        let h = format!(
+            "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+            name, 1500000000, 1000, 100, 100600, data.len()
+        );
+        unindexed.extend_from_slice(h.as_bytes());
+        unindexed.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            unindexed.push(b'\n');
+        }
+    }
+    assert_eq!(archive::read_index(&unindexed).unwrap(), vec![]);
+
+    let indexed = archive::ranlib(&unindexed).unwrap();
+    let members = archive::members(&indexed).unwrap();
+    assert_eq!(
+        archive::read_index(&indexed).unwrap(),
+        vec![
+            (String::from("add_one"), members[0].header as u64),
+            (String::from("twice"), members[1].header as u64),
+        ]
+    );
+    // members keep their headers
+    let parsed = Archive::parse(&indexed).unwrap();
+    assert_eq!(
+        parsed.members[1],
+        Member {
+            name: String::from("b.o"),
+            data: b,
+            mtime: 1500000000,
+            uid: 1000,
+            gid: 100,
+            mode: 0o100600,
+        }
+    );
+    assert!(!parsed.sym64);
+    assert_eq!(archive::ranlib(&indexed).unwrap(), indexed);
+}
+
+#[test]
+fn sym64_index() {
+    let mut ar = Archive::new();
+    ar.add("a.o", object(&[("add_one", types::SymbolBind::GLOBAL)]));
+    ar.add("b.o", object(&[("twice", types::SymbolBind::GLOBAL)]));
+    ar.sym64 = true;
+    let mut io = Vec::new();
+    ar.to_writer(&mut io).unwrap();
+    let (name, index) = member(&io, 8);
+    assert_eq!(name, "/SYM64/");
+    assert_eq!(&index[..8], &2u64.to_be_bytes());
+
+    let members = archive::members(&io).unwrap();
+    assert_eq!(
+        archive::read_index(&io).unwrap(),
+        vec![
+            (String::from("add_one"), members[0].header as u64),
+            (String::from("twice"), members[1].header as u64),
+        ]
+    );
+    // ranlib keeps the format
+    assert!(Archive::parse(&io).unwrap().sym64);
+    assert_eq!(archive::ranlib(&io).unwrap(), io);
+
+    // the count says there is more than there is
+    let mut broken = io.clone();
+    broken[8 + 60 + 7] = 9;
+    match archive::read_index(&broken) {
+        Err(Error::InvalidArchive("symbol index is truncated")) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+/// an archive header in the deterministic form, blank where ar -D writes zeros
+fn header(name: &str, size: usize) -> Vec<u8> {
+    format!("{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n", name, "", "", "", "", size).into_bytes()
+}
+
+#[test]
+fn bsd_and_gnu_long_names() {
+    let a = object(&[("add_one", types::SymbolBind::GLOBAL)]);
+
+    // bsd: names in front of the content, and a symbol table behind an extended name
+    let mut bsd = b"!<arch>\n".to_vec();
+    let symdef = b"__.SYMDEF SORTED\0\0\0\0";
+    bsd.extend(header("#1/20", symdef.len() + 8));
+    bsd.extend_from_slice(symdef);
+    bsd.extend_from_slice(&[0; 8]);
+    let long = b"a_rather_long_member_name.o\0";
+    bsd.extend(header("#1/28", long.len() + a.len()));
+    bsd.extend_from_slice(long);
+    bsd.extend_from_slice(&a);
+    if a.len() % 2 == 1 {
+        bsd.push(b'\n');
+    }
+    let members = archive::members(&bsd).unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].name, "a_rather_long_member_name.o");
+    assert_eq!(&bsd[members[0].start..members[0].end], &a[..]);
+
+    // gnu, with a name table that ends names in a nul instead of "/\n"
+    let mut gnu = b"!<arch>\n".to_vec();
+    let names = b"another_long_member_name.o\0";
+    gnu.extend(header("//", names.len()));
+    gnu.extend_from_slice(names);
+    gnu.push(b'\n');
+    gnu.extend(header("/0", a.len()));
+    gnu.extend_from_slice(&a);
+    if a.len() % 2 == 1 {
+        gnu.push(b'\n');
+    }
+    let parsed = Archive::parse(&gnu).unwrap();
+    assert_eq!(parsed.members.len(), 1);
+    assert_eq!(
+        parsed.members[0],
+        Member {
+            mode: 0,
+            ..Member::new("another_long_member_name.o", a.clone())
+        }
+    );
+
+    gnu.extend(header("/99", 0));
+    match archive::members(&gnu) {
+        Err(Error::InvalidArchive("long name offset out of range")) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
@@ -0,0 +1,125 @@
+extern crate elfkit;
+
+use elfkit::{types, Elf, ElfBuilder, SectionContent};
+use elfkit::attributes::{self, Attribute, AttributeScope, AttributeSet, AttributeValue, Attributes,
+                         VendorAttributes, VendorContent};
+use elfkit::relocation::RelocationType;
+use std::io::Cursor;
+
+fn reparse(elf: &mut Elf) -> Elf {
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    let mut elf = Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap();
+    elf.load_all().unwrap();
+    elf
+}
+
+// Tag_CPU_name "7-A", Tag_CPU_arch v7, Tag_compatibility 1 "gnu", Tag_DIV_use 2
+const AEABI: &[u8] = &[
+    b'A', 0x1e, 0, 0, 0, b'a', b'e', b'a', b'b', b'i', 0, 0x01, 0x14, 0, 0, 0, 0x05, b'7', b'-',
+    b'A', 0, 0x06, 0x0a, 0x20, 0x01, b'g', b'n', b'u', 0, 0x2c, 0x02,
+];
+
+#[test]
+fn arm_attributes_round_trip() {
+    let mut elf = ElfBuilder::new(types::Machine::ARM)
+        .class(types::Class::Class32)
+        .text(vec![0x1e, 0xff, 0x2f, 0xe1])
+        .section(
+            ".ARM.attributes",
+            types::SectionType::ARM_ATTRIBUTES,
+            types::SectionFlags::empty(),
+            AEABI.to_vec(),
+        )
+        .build()
+        .unwrap();
+    let elf = reparse(&mut elf);
+
+    let attrs = attributes::machine_attributes(&elf.sections, &elf.header)
+        .unwrap()
+        .unwrap();
+    let file = attrs.file_attributes("aeabi");
+    assert_eq!(file.len(), 4);
+    assert_eq!(file[0].value, AttributeValue::String(String::from("7-A")));
+    assert_eq!(attributes::arm_tag_name(file[1].tag), Some("CPU_arch"));
+    assert_eq!(file[1].value, AttributeValue::Int(10));
+    assert_eq!(file[2].value, AttributeValue::Compatibility(1, String::from("gnu")));
+    assert_eq!(file[3].value, AttributeValue::Int(2));
+
+    let mut out = Vec::new();
+    attrs.to_writer(&mut out, &elf.header).unwrap();
+    assert_eq!(out, AEABI);
+}
+
+#[test]
+fn unknown_vendors_and_scopes_survive() {
+    let eh = ElfBuilder::new(types::Machine::ARM)
+        .class(types::Class::Class32)
+        .endianness(types::Endianness::BigEndian)
+        .build()
+        .unwrap()
+        .header;
+    let attrs = Attributes {
+        vendors: vec![
+            VendorAttributes {
+                vendor: String::from("aeabi"),
+                content: VendorContent::Parsed(vec![
+                    AttributeSet {
+                        scope: AttributeScope::Sections(vec![1, 300]),
+                        attributes: vec![Attribute {
+                            tag: 26,
+                            value: AttributeValue::Int(1),
+                        }],
+                    },
+                ]),
+            },
+            VendorAttributes {
+                vendor: String::from("ARM"),
+                content: VendorContent::Raw(vec![1, 2, 3]),
+            },
+        ],
+    };
+    let mut out = Vec::new();
+    attrs.to_writer(&mut out, &eh).unwrap();
+    assert_eq!(Attributes::from_reader(&out[..], &eh).unwrap(), attrs);
+
+    // truncated in the middle of the section list
+    assert!(Attributes::from_reader(&out[..10], &eh).is_err());
+}
+
+#[test]
+fn rel_relocations_and_eabi_flags() {
+    // r_offset 0x20, r_info sym 2 type R_ARM_JUMP_SLOT, little endian
+    let rel = vec![0x20, 0, 0, 0, 0x16, 0x02, 0, 0];
+    let mut elf = ElfBuilder::new(types::Machine::ARM)
+        .class(types::Class::Class32)
+        .text(vec![0x1e, 0xff, 0x2f, 0xe1])
+        .section(
+            ".rel.plt",
+            types::SectionType::REL,
+            types::SectionFlags::ALLOC,
+            rel,
+        )
+        .build()
+        .unwrap();
+    elf.header.flags = types::HeaderFlags::ARM_EABI_VER5 | types::HeaderFlags::ARM_ABI_FLOAT_HARD;
+
+    let mut elf = reparse(&mut elf);
+    assert_eq!(elf.header.arm_eabi_version(), Some(5));
+    assert!(elf.header.flags.contains(types::HeaderFlags::ARM_ABI_FLOAT_HARD));
+    let sec = elf.sections.iter().find(|s| s.name == ".rel.plt").unwrap();
+    match sec.content {
+        SectionContent::Relocations(ref r) => {
+            assert_eq!(r.len(), 1);
+            assert_eq!(r[0].addr, 0x20);
+            assert_eq!(r[0].sym, 2);
+            assert_eq!(r[0].rtype, RelocationType::R_ARM_JUMP_SLOT);
+            assert_eq!(r[0].rtype.typename(&elf.header), Some("R_ARM_JUMP_SLOT"));
+        }
+        _ => panic!(".rel.plt not loaded as relocations"),
+    }
+
+    elf.header.machine = types::Machine::X86_64;
+    assert_eq!(elf.header.arm_eabi_version(), None);
+}
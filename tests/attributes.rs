@@ -0,0 +1,124 @@
+extern crate elfkit;
+
+use elfkit::attributes::{self, Attribute, AttributeScope, AttributeSet, AttributeValue, Attributes,
+                         VendorAttributes, VendorContent};
+use elfkit::{types, ElfBuilder, Error};
+
+fn vendor(name: &str, scope: AttributeScope, attrs: &[(u64, AttributeValue)]) -> Attributes {
+    Attributes {
+        vendors: vec![
+            VendorAttributes {
+                vendor: String::from(name),
+                content: VendorContent::Parsed(vec![
+                    AttributeSet {
+                        scope,
+                        attributes: attrs
+                            .iter()
+                            .map(|&(tag, ref value)| Attribute {
+                                tag,
+                                value: value.clone(),
+                            })
+                            .collect(),
+                    },
+                ]),
+            },
+        ],
+    }
+}
+
+fn file(name: &str, attrs: &[(u64, AttributeValue)]) -> Attributes {
+    vendor(name, AttributeScope::File, attrs)
+}
+
+fn int(v: u64) -> AttributeValue {
+    AttributeValue::Int(v)
+}
+
+fn string(s: &str) -> AttributeValue {
+    AttributeValue::String(String::from(s))
+}
+
+#[test]
+fn aeabi_merge() {
+    // CPU_name, CPU_arch v7, enum_size unused, VFP_args base
+    let mut merged = file("aeabi", &[(5, string("7-A")), (6, int(10)), (26, int(0)), (28, int(0))]);
+    // CPU_arch v8, enum_size int, VFP_args compatible with both, DIV_use
+    let other = file("aeabi", &[(5, string("8-A")), (6, int(14)), (26, int(2)), (28, int(3))]);
+    merged.merge(&other).unwrap();
+    let mut expected =
+        file("aeabi", &[(5, string("7-A")), (6, int(14)), (26, int(2)), (28, int(0))]);
+    assert_eq!(merged, expected);
+
+    merged.merge(&file("aeabi", &[(44, int(2))])).unwrap();
+    if let VendorContent::Parsed(ref mut sets) = expected.vendors[0].content {
+        sets[0].attributes.push(Attribute {
+            tag: 44,
+            value: int(2),
+        });
+    }
+    assert_eq!(merged, expected);
+
+    // a hard float object can't be linked with a soft float one
+    match merged.merge(&file("aeabi", &[(28, int(1))])) {
+        Err(Error::IncompatibleAttributes(ref v, 28)) => assert_eq!(v, "aeabi"),
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[test]
+fn section_scopes_and_raw_vendors() {
+    let mut merged = vendor("aeabi", AttributeScope::Sections(vec![1]), &[(6, int(10))]);
+    merged.vendors.push(VendorAttributes {
+        vendor: String::from("ARM"),
+        content: VendorContent::Raw(vec![1, 2]),
+    });
+    let mut other = file("gnu", &[(4, int(1))]);
+    other.vendors.push(VendorAttributes {
+        vendor: String::from("ARM"),
+        content: VendorContent::Raw(vec![3]),
+    });
+    merged.merge(&other).unwrap();
+
+    assert_eq!(merged.vendors.len(), 3);
+    assert_eq!(merged.vendors[0], file("aeabi", &[]).vendors[0]);
+    assert_eq!(merged.vendors[1].content, VendorContent::Raw(vec![1, 2]));
+    assert_eq!(merged.file_attributes("gnu")[0].value, int(1));
+
+    // Tag_GNU_Power_ABI_FP, hard against soft float
+    assert!(merged.merge(&file("gnu", &[(4, int(2))])).is_err());
+    merged.merge(&file("gnu", &[(4, int(0))])).unwrap();
+}
+
+#[test]
+fn riscv_arch_union() {
+    let eh = ElfBuilder::new(types::Machine::RISCV).build().unwrap().header;
+    assert_eq!(
+        attributes::section_type(&eh.machine),
+        types::SectionType::RISCV_ATTRIBUTES
+    );
+    assert_eq!(attributes::section_name(&eh.machine), ".riscv.attributes");
+
+    let mut merged = file("riscv", &[(4, int(16)), (5, string("rv64i2p1_m2p0_zicsr2p0"))]);
+    let other = file("riscv", &[(5, string("rv64imac")), (6, int(1))]);
+    merged.merge(&other).unwrap();
+    let arch = file("riscv", &[
+        (4, int(16)),
+        (5, string("rv64i2p1_m2p0_a_c_zicsr2p0")),
+        (6, int(1)),
+    ]);
+    assert_eq!(merged, arch);
+
+    merged.merge(&file("riscv", &[(5, string("rv64i2p1_m3p0_zve32x1p0"))])).unwrap();
+    assert_eq!(
+        merged.file_attributes("riscv")[1].value,
+        string("rv64i2p1_m3p0_a_c_zicsr2p0_zve32x1p0")
+    );
+
+    // the arch string is a string tag for riscv, so it survives writing and reading
+    let mut out = Vec::new();
+    merged.to_writer(&mut out, &eh).unwrap();
+    assert_eq!(Attributes::from_reader(&out[..], &eh).unwrap(), merged);
+
+    assert!(merged.merge(&file("riscv", &[(5, string("rv32i"))])).is_err());
+    assert!(merged.merge(&file("riscv", &[(4, int(8))])).is_err());
+}
@@ -0,0 +1,87 @@
+extern crate elfkit;
+
+use elfkit::{types, Elf, ElfBuilder, SectionContent, Symbol};
+use std::io::Cursor;
+
+fn sample() -> Vec<u8> {
+    let start = Symbol {
+        name: String::from("_start"),
+        bind: types::SymbolBind::GLOBAL,
+        stype: types::SymbolType::FUNC,
+        ..Symbol::default()
+    };
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0x90, 0xc3])
+        .section(
+            ".data",
+            types::SectionType::PROGBITS,
+            types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+            vec![1, 2, 3, 4],
+        )
+        .symbol(".text", start)
+        .entry("_start")
+        .build()
+        .unwrap();
+    elf.set_trailing_data(b"signature".to_vec());
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    io.into_inner()
+}
+
+#[test]
+fn borrowed_content_points_into_the_input() {
+    let bytes = sample();
+    let owned = Elf::from_reader(&mut Cursor::new(&bytes)).unwrap();
+    let borrowed = Elf::from_bytes(&bytes).unwrap();
+
+    assert_eq!(borrowed.sections.len(), owned.sections.len());
+    for (b, o) in borrowed.sections.iter().zip(owned.sections.iter()) {
+        assert_eq!(b.name, o.name);
+        assert_eq!(format!("{:?}", b.header), format!("{:?}", o.header));
+        match (&b.content, &o.content) {
+            (SectionContent::Raw(b), SectionContent::Raw(o)) => {
+                assert_eq!(*b, &o[..]);
+                // not a copy
+                assert!(b.is_empty() || bytes.as_ptr_range().contains(&b.as_ptr()));
+            }
+            (SectionContent::None, SectionContent::None) => {}
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+    assert_eq!(borrowed.trailing_data(), b"signature");
+    assert_eq!(borrowed.shstrndx(), owned.shstrndx());
+
+    // and everything else works on the owned copy
+    let mut elf = borrowed.into_owned();
+    elf.load_all().unwrap();
+    let symtab = elf.sections.iter().find(|s| s.name == ".symtab").unwrap();
+    assert!(symtab.content.as_symbols().unwrap().iter().any(|s| s.name == "_start"));
+    assert_eq!(elf.trailing_data(), b"signature");
+}
+
+#[test]
+fn borrowed_parse_checks_like_from_reader() {
+    let bytes = sample();
+    let shoff = Elf::from_bytes(&bytes).unwrap().header.shoff as usize;
+    let mut truncated = bytes.clone();
+    truncated.truncate(shoff + 64 * 2);
+    match Elf::from_bytes(&truncated).map(|_| ()).map_err(|e| e.root_cause().to_string()) {
+        Err(e) => assert!(e.starts_with("i/o error")),
+        Ok(()) => panic!("parsed a truncated file"),
+    }
+
+    // the offset of section 1 far past the end
+    let mut broken = bytes.clone();
+    broken[shoff + 64 + 24..shoff + 64 + 32].copy_from_slice(&(1u64 << 40).to_le_bytes());
+    match Elf::from_bytes(&broken).map(|_| ()).map_err(|e| e.root_cause().to_string()) {
+        Err(e) => assert_eq!(
+            e,
+            Elf::from_reader(&mut Cursor::new(&broken))
+                .map(|_| ())
+                .unwrap_err()
+                .root_cause()
+                .to_string()
+        ),
+        Ok(()) => panic!("parsed a section beyond the end"),
+    }
+}
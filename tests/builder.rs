@@ -0,0 +1,301 @@
+extern crate elfkit;
+extern crate tempfile;
+
+use elfkit::{builder, linker, types, Elf, ElfBuilder, Error, Header, SectionContent, Symbol,
+             SymbolSectionIndex, SymbolicRelocation, Target};
+use elfkit::relocation::RelocationType;
+use std::fs::File;
+use std::io::Cursor;
+use std::os::unix::fs::PermissionsExt;
+use std::process::Command;
+
+// mov edi, 42; mov eax, 60; syscall
+const EXIT_42: [u8; 12] = [0xbf, 0x2a, 0, 0, 0, 0xb8, 0x3c, 0, 0, 0, 0x0f, 0x05];
+
+fn start_symbol() -> Symbol {
+    let mut start = Symbol::default();
+    start.name = String::from("_start");
+    start.stype = types::SymbolType::FUNC;
+    start.bind = types::SymbolBind::GLOBAL;
+    start.size = EXIT_42.len() as u64;
+    start
+}
+
+#[test]
+fn build_minimal_executable() {
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(EXIT_42.to_vec())
+        .symbol(".text", start_symbol())
+        .entry("_start")
+        .build()
+        .unwrap();
+
+    // kept under its unique name, but closed once written, since a file that is open for
+    // writing can't be run
+    let tmp = tempfile::NamedTempFile::new().unwrap();
+    let path = tmp.path().to_owned();
+    {
+        let mut out = tmp.persist(&path).unwrap();
+        elf.to_writer(&mut out).unwrap();
+        let mut perms = out.metadata().unwrap().permissions();
+        perms.set_mode(0o755);
+        out.set_permissions(perms).unwrap();
+    }
+
+    let mut elf = Elf::from_reader(&mut File::open(&path).unwrap()).unwrap();
+    elf.load_all().unwrap();
+    let secnames: Vec<&str> = elf.sections.iter().map(|s| s.name.as_ref()).collect();
+    assert_eq!(secnames, vec!["", ".text", ".symtab", ".strtab", ".shstrtab"]);
+    let symbols = elf.sections[2].content.as_symbols().unwrap();
+    assert_eq!(symbols[1].name, "_start");
+    assert_eq!(symbols[1].value, elf.header.entry);
+
+    let status = Command::new(&path).status().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(status.code(), Some(42));
+}
+
+#[test]
+fn missing_entry_symbol() {
+    let r = ElfBuilder::new(types::Machine::X86_64)
+        .text(EXIT_42.to_vec())
+        .entry("_start")
+        .build();
+    assert!(r.is_err());
+}
+
+#[test]
+fn entry_from_symbol_or_address() {
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(EXIT_42.to_vec())
+        .symbol(".text", start_symbol())
+        .build()
+        .unwrap();
+    elf.load_all().unwrap();
+    let lookup = |name: &str| linker::symbol_value(&elf, name);
+    let text = elf.sections[1].header.addr;
+    assert_eq!(linker::entry("_start", lookup).unwrap(), text);
+    assert_eq!(linker::entry("0x401000", lookup).unwrap(), 0x401000);
+    assert_eq!(linker::entry("4096", lookup).unwrap(), 4096);
+    match linker::entry("main", lookup) {
+        Err(Error::EntrySymbolNotFound(ref name)) => assert_eq!(name, "main"),
+        r => panic!("unexpected {:?}", r),
+    }
+    assert_eq!(linker::entry_address("_start"), None);
+}
+
+#[test]
+fn binary_blob_objects() {
+    assert_eq!(builder::binary_symbol_prefix("assets/logo-2.png"), "_binary_assets_logo_2_png");
+    let mut elf =
+        builder::binary_object(types::Machine::X86_64, "assets/logo.png", b"\x89PNG".to_vec())
+            .unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+
+    let mut elf = Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap();
+    elf.load_all().unwrap();
+    assert_eq!(elf.header.etype, types::ElfType::REL);
+    assert_eq!(elf.sections[1].name, ".data");
+    match elf.sections[1].content {
+        SectionContent::Raw(ref v) => assert_eq!(v, b"\x89PNG"),
+        ref c => panic!("unexpected {:?}", c),
+    }
+    let symbols: Vec<(&str, SymbolSectionIndex, u64)> = elf.sections[2]
+        .content
+        .as_symbols()
+        .unwrap()
+        .iter()
+        .skip(1)
+        .map(|s| (s.name.as_ref(), s.shndx.clone(), s.value))
+        .collect();
+    assert_eq!(
+        symbols,
+        vec![
+            ("_binary_assets_logo_png_start", SymbolSectionIndex::Section(1), 0),
+            ("_binary_assets_logo_png_end", SymbolSectionIndex::Section(1), 4),
+            ("_binary_assets_logo_png_size", SymbolSectionIndex::Absolute, 4),
+        ]
+    );
+    assert!(elf.validate().is_empty());
+}
+
+#[test]
+fn interpreter_in_the_first_load() {
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(EXIT_42.to_vec())
+        .interpreter("/lib/ld-musl-x86_64.so.1")
+        .build()
+        .unwrap();
+    assert_eq!(elf.sections[1].name, ".interp");
+    assert_eq!(elf.sections[1].header.addralign, 1);
+    let phtypes: Vec<types::SegmentType> = elf.segments.iter().map(|s| s.phtype.clone()).collect();
+    assert_eq!(
+        phtypes,
+        vec![types::SegmentType::PHDR, types::SegmentType::INTERP, types::SegmentType::LOAD]
+    );
+    let (interp, load) = (&elf.segments[1], &elf.segments[2]);
+    assert_eq!(interp.filesz, "/lib/ld-musl-x86_64.so.1".len() as u64 + 1);
+    assert!(load.offset <= interp.offset);
+    assert!(interp.offset + interp.filesz <= load.offset + load.filesz);
+
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    let info = Elf::quick_info(&mut Cursor::new(io.into_inner())).unwrap();
+    assert_eq!(info.interpreter.as_ref().map(|s| s.as_ref()), Some("/lib/ld-musl-x86_64.so.1"));
+}
+
+#[test]
+fn relocatable_object_links() {
+    // sub rsp, 8; lea rdi, [rip + .rodata]; call puts; xor eax, eax; add rsp, 8; ret
+    let code = vec![
+        0x48, 0x83, 0xec, 0x08, 0x48, 0x8d, 0x3d, 0, 0, 0, 0, 0xe8, 0, 0, 0, 0, 0x31, 0xc0, 0x48,
+        0x83, 0xc4, 0x08, 0xc3,
+    ];
+    let main = Symbol {
+        name: String::from("main"),
+        stype: types::SymbolType::FUNC,
+        bind: types::SymbolBind::GLOBAL,
+        size: code.len() as u64,
+        ..Symbol::default()
+    };
+    let rodata = Target::Section(String::from(".rodata"), 6);
+    let puts = Target::Symbol(String::from("puts"));
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .etype(types::ElfType::REL)
+        .text(code)
+        .section(
+            ".rodata",
+            types::SectionType::PROGBITS,
+            types::SectionFlags::ALLOC,
+            b"skip: hello from elfkit\0".to_vec(),
+        )
+        .symbol(".text", main)
+        .relocation(
+            ".text",
+            SymbolicRelocation::new(7, RelocationType::R_X86_64_PC32, rodata).with_addend(-4),
+        )
+        .relocation(
+            ".text",
+            SymbolicRelocation::new(12, RelocationType::R_X86_64_PLT32, puts).with_addend(-4),
+        )
+        .build()
+        .unwrap();
+    let secnames: Vec<&str> = elf.sections.iter().map(|s| s.name.as_ref()).collect();
+    assert_eq!(
+        secnames,
+        vec![
+            "",
+            ".text",
+            ".rela.text",
+            ".rodata",
+            ".note.GNU-stack",
+            ".symtab",
+            ".strtab",
+            ".shstrtab",
+        ]
+    );
+
+    // gcc only takes it for an object with the suffix. the executable is closed again, or
+    // it couldn't be run
+    let mut obj = tempfile::NamedTempFileOptions::new().suffix(".o").create().unwrap();
+    let tmp = tempfile::NamedTempFile::new().unwrap();
+    let exe = tmp.path().to_owned();
+    drop(tmp.persist(&exe).unwrap());
+    elf.to_writer(&mut obj).unwrap();
+    let mut elf = Elf::from_reader(&mut File::open(obj.path()).unwrap()).unwrap();
+    let findings: Vec<String> = elf.validate().iter().map(|f| f.to_string()).collect();
+    assert_eq!(findings, Vec::<String>::new());
+    let status = Command::new("gcc").arg(obj.path()).arg("-o").arg(&exe).status().unwrap();
+    assert!(status.success());
+    let out = Command::new(&exe).output().unwrap();
+    std::fs::remove_file(&exe).unwrap();
+    assert!(out.status.success());
+    assert_eq!(out.stdout, b"hello from elfkit\n");
+
+    let r = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .relocation(
+            ".text",
+            SymbolicRelocation::new(0, RelocationType::R_X86_64_64, Target::Symbol("x".into())),
+        )
+        .build();
+    match r {
+        Err(Error::RelocationsInLinkedFile(ref name)) if name == ".text" => {}
+        r => panic!("unexpected {:?}", r.map(|_| ())),
+    }
+}
+
+#[test]
+fn abi_tag_brands_the_file() {
+    let tag = linker::AbiTag::FreeBsd(1400097);
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(EXIT_42.to_vec())
+        .interpreter(tag.interpreter(&Header::default()).unwrap())
+        .abi_tag(tag)
+        .build()
+        .unwrap();
+    assert_eq!(elf.header.ident_abi, types::Abi::FREEBSD);
+    assert_eq!(elf.sections[2].name, ".note.tag");
+    let phtypes: Vec<types::SegmentType> = elf.segments.iter().map(|s| s.phtype.clone()).collect();
+    assert!(phtypes.contains(&types::SegmentType::NOTE));
+
+    let mut tmp = tempfile::NamedTempFile::new().unwrap();
+    elf.to_writer(&mut tmp).unwrap();
+    let out = Command::new("readelf").arg("-hln").arg(tmp.path()).output().unwrap();
+    let out = String::from_utf8_lossy(&out.stdout);
+    assert!(out.contains("UNIX - FreeBSD"), "{}", out);
+    assert!(out.contains("[Requesting program interpreter: /libexec/ld-elf.so.1]"), "{}", out);
+    assert!(out.contains("FreeBSD              0x00000004\tNT_VERSION (version)"), "{}", out);
+
+    // a linux one still runs
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(EXIT_42.to_vec())
+        .symbol(".text", start_symbol())
+        .entry("_start")
+        .abi_tag(linker::AbiTag::Linux(3, 2, 0))
+        .build()
+        .unwrap();
+    // closed once written, or it couldn't be run
+    let tmp = tempfile::NamedTempFile::new().unwrap();
+    let path = tmp.path().to_owned();
+    {
+        let mut out = tmp.persist(&path).unwrap();
+        elf.to_writer(&mut out).unwrap();
+        out.set_permissions(std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let notes = Command::new("readelf").arg("-n").arg(&path).output().unwrap();
+    let status = Command::new(&path).status().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(String::from_utf8_lossy(&notes.stdout).contains("OS: Linux, ABI: 3.2.0"));
+    assert_eq!(status.code(), Some(42));
+}
+
+#[test]
+fn gnu_symbols_need_the_gnu_abi() {
+    let mut resolver = start_symbol();
+    resolver.stype = types::SymbolType::GNU_IFUNC;
+    let mut eh = Header::default();
+    assert!(!resolver.is_ifunc(&eh));
+    eh.ident_abi = linker::symbol_abi(&eh.ident_abi, &[resolver.clone()]).unwrap();
+    assert_eq!(eh.ident_abi, types::Abi::GNU);
+    assert!(resolver.is_ifunc(&eh));
+
+    // freebsd has indirect functions but not unique symbols
+    let bsd = types::Abi::FREEBSD;
+    assert_eq!(linker::symbol_abi(&bsd, &[resolver.clone()]).unwrap(), bsd);
+    let mut unique = start_symbol();
+    unique.bind = types::SymbolBind::STB_GNU_UNIQUE;
+    match linker::symbol_abi(&bsd, &[resolver, unique]) {
+        Err(Error::UnsupportedByAbi(ref name, "unique", types::Abi::FREEBSD)) => {
+            assert_eq!(name, "_start")
+        }
+        r => panic!("unexpected {:?}", r),
+    }
+
+    assert_eq!(linker::merge_abi(&types::Abi::SYSV, &bsd).unwrap(), bsd);
+    match linker::merge_abi(&types::Abi::GNU, &bsd) {
+        Err(Error::IncompatibleAbi(types::Abi::GNU, types::Abi::FREEBSD)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
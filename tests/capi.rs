@@ -0,0 +1,83 @@
+#![cfg(feature = "capi")]
+extern crate elfkit;
+
+use elfkit::capi::*;
+use elfkit::{types, Elf, ElfBuilder, Symbol};
+use std::ffi::{CStr, CString};
+use std::io::Cursor;
+use std::mem;
+use std::ptr;
+
+fn bytes() -> Vec<u8> {
+    let mut main = Symbol::default();
+    main.name = String::from("main");
+    main.bind = types::SymbolBind::GLOBAL;
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .symbol(".text", main)
+        .build()
+        .unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    io.into_inner()
+}
+
+fn c_str<'a>(p: *const std::os::raw::c_char) -> &'a str {
+    unsafe { CStr::from_ptr(p) }.to_str().unwrap()
+}
+
+#[test]
+fn sections_and_symbols() {
+    let b = bytes();
+    unsafe {
+        let elf = elfkit_parse(b.as_ptr(), b.len());
+        assert!(!elf.is_null());
+        let mut h: ElfkitHeader = mem::zeroed();
+        assert_eq!(elfkit_get_header(elf, &mut h), 0);
+        assert_eq!(h.machine, 62);
+        assert_eq!(h.shnum as usize, elfkit_section_count(elf));
+
+        let mut names = Vec::new();
+        let mut s: ElfkitSection = mem::zeroed();
+        for i in 0..elfkit_section_count(elf) {
+            assert_eq!(elfkit_get_section(elf, i, &mut s), 0);
+            names.push(c_str(s.name).to_string());
+        }
+        assert!(names.iter().any(|n| n == ".text"));
+        assert!(names.iter().any(|n| n == ".symtab"));
+        assert_eq!(elfkit_get_section(elf, 99, &mut s), -1);
+        assert_eq!(c_str(elfkit_last_error()), "section index 99 out of range");
+
+        let mut sym: ElfkitSymbol = mem::zeroed();
+        let main = CString::new("main").unwrap();
+        assert_eq!(elfkit_symbol_lookup(elf, main.as_ptr(), &mut sym), 0);
+        assert_eq!(c_str(sym.name), "main");
+        assert_ne!(sym.shndx, 0);
+        let nope = CString::new("nope").unwrap();
+        assert_eq!(elfkit_symbol_lookup(elf, nope.as_ptr(), &mut sym), 1);
+        assert_eq!(elfkit_symbol_lookup(elf, ptr::null(), &mut sym), -1);
+        elfkit_free(elf);
+    }
+}
+
+#[test]
+fn errors() {
+    unsafe {
+        let elf = elfkit_parse(b"\x7fELF".as_ptr(), 4);
+        assert!(elf.is_null());
+        assert!(!elfkit_last_error().is_null());
+
+        let path = CString::new("/nonexistent/elfkit").unwrap();
+        assert!(elfkit_open(path.as_ptr()).is_null());
+        assert!(c_str(elfkit_last_error()).starts_with("i/o error"));
+        elfkit_free(ptr::null_mut());
+
+        // a section claiming 1GiB is refused before anything is read
+        let mut b = bytes();
+        let shoff = Elf::from_reader(&mut Cursor::new(&b[..])).unwrap().header.shoff as usize;
+        let size = shoff + 64 + 0x20;
+        b[size..size + 8].copy_from_slice(&(1u64 << 30).to_le_bytes());
+        assert!(elfkit_parse(b.as_ptr(), b.len()).is_null());
+        assert!(c_str(elfkit_last_error()).ends_with("section size 1073741824 exceeds the limit"));
+    }
+}
@@ -0,0 +1,93 @@
+extern crate elfkit;
+
+use elfkit::{linker, types, Elf, ElfBuilder, ElfOptions, Error, Header, SectionContent,
+             SectionContentExt, SectionHeader};
+use std::any::Any;
+use std::io::{Cursor, Write};
+
+/// key=value strings, as in a kernel module's .modinfo
+#[derive(Debug, Clone, PartialEq)]
+struct ModInfo(Vec<(String, String)>);
+
+impl ModInfo {
+    fn parse(
+        raw: &[u8],
+        _: &SectionHeader,
+        _: &Header,
+    ) -> Result<Box<dyn SectionContentExt>, Error> {
+        let mut r = Vec::new();
+        for s in raw.split(|c| *c == 0).filter(|s| !s.is_empty()) {
+            let s = String::from_utf8_lossy(s);
+            let mut kv = s.splitn(2, '=');
+            let k = kv.next().unwrap_or("");
+            r.push((String::from(k), String::from(kv.next().unwrap_or(""))));
+        }
+        Ok(Box::new(ModInfo(r)))
+    }
+}
+
+impl SectionContentExt for ModInfo {
+    fn to_writer(&self, _: &Header, io: &mut dyn Write) -> Result<(), Error> {
+        for (k, v) in &self.0 {
+            write!(io, "{}={}\0", k, v)?;
+        }
+        Ok(())
+    }
+    fn clone_box(&self) -> Box<dyn SectionContentExt> {
+        Box::new(self.clone())
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+fn write(elf: &mut Elf) -> Vec<u8> {
+    elf.sync_all().unwrap();
+    linker::relayout(elf, 0x300000).unwrap();
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    io.into_inner()
+}
+
+#[test]
+fn registered_parsers_give_typed_access() {
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .section(
+            ".modinfo",
+            types::SectionType::PROGBITS,
+            types::SectionFlags::ALLOC,
+            b"license=GPL\0author=someone\0".to_vec(),
+        )
+        .build()
+        .unwrap();
+    let raw = write(&mut elf);
+
+    let mut options = ElfOptions::default();
+    options.content.register_name(".modinfo", ModInfo::parse);
+    let mut elf = Elf::from_reader_with_options(&mut Cursor::new(raw), options).unwrap();
+    elf.load_all().unwrap();
+    let i = elf.sections.iter().position(|s| s.name == ".modinfo").unwrap();
+    {
+        let info = elf.sections[i].content.as_ext_mut::<ModInfo>().unwrap();
+        assert_eq!(info.0[0], (String::from("license"), String::from("GPL")));
+        info.0.push((String::from("version"), String::from("1.0")));
+    }
+    assert!(elf.sections[i].content.as_ext::<String>().is_none());
+    assert_eq!(elf.sections[i].clone().content.as_ext::<ModInfo>().unwrap().0.len(), 3);
+
+    let raw = write(&mut elf);
+    let mut elf = Elf::from_reader(&mut Cursor::new(raw)).unwrap();
+    elf.load_all().unwrap();
+    let sec = elf.sections.iter().find(|s| s.name == ".modinfo").unwrap();
+    match sec.content {
+        SectionContent::Raw(ref v) => {
+            assert_eq!(&v[..], &b"license=GPL\0author=someone\0version=1.0\0"[..])
+        }
+        ref c => panic!("unexpected {:?}", c),
+    }
+    assert_eq!(sec.header.size, 39);
+}
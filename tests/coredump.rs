@@ -0,0 +1,219 @@
+extern crate elfkit;
+
+use elfkit::coredump::{LinkMapEntry, RState, NT_AUXV};
+use elfkit::security::Pie;
+use elfkit::{types, Elf, Error, HashAlgorithm, Header, SegmentHeader};
+use std::io::Cursor;
+
+const BIAS: u64 = 0x5555_5555_4000;
+const DATA: u64 = 0x7f00_0000_1000;
+
+fn put(b: &mut [u8], at: usize, words: &[u64]) {
+    for (i, w) in words.iter().enumerate() {
+        b[at + 8 * i..at + 8 * i + 8].copy_from_slice(&w.to_le_bytes());
+    }
+}
+
+fn note(ntype: u32, desc: &[u8]) -> Vec<u8> {
+    let mut n = Vec::new();
+    n.extend_from_slice(&5u32.to_le_bytes());
+    n.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    n.extend_from_slice(&ntype.to_le_bytes());
+    n.extend_from_slice(b"CORE\0\0\0\0");
+    n.extend_from_slice(desc);
+    n
+}
+
+fn load(offset: u64, vaddr: u64, filesz: u64, memsz: u64) -> SegmentHeader {
+    SegmentHeader {
+        phtype: types::SegmentType::LOAD,
+        flags: types::SegmentFlags::READABLE | types::SegmentFlags::WRITABLE,
+        offset,
+        vaddr,
+        paddr: 0,
+        filesz,
+        memsz,
+        align: 0x1000,
+    }
+}
+
+/// a core file without section headers, like the kernel writes them,
+/// of a process with the executable and libc in its link map
+fn core(debug: u64, last_next: u64) -> Vec<u8> {
+    // a prstatus note the reader has to skip, then AT_PHDR, AT_ENTRY, AT_NULL
+    let mut auxv = vec![0; 48];
+    put(&mut auxv, 0, &[3, BIAS + 0x40, 9, BIAS + 0x1040]);
+    let mut notes = note(1, &[0; 12]);
+    notes.extend(note(NT_AUXV, &auxv));
+
+    // DT_FLAGS_1, DT_DEBUG, DT_NULL at the executable's PT_DYNAMIC
+    let mut dynamic = vec![0; 48];
+    put(&mut dynamic, 0, &[0x6fff_fffb, 0x0800_0000, 21, debug]);
+
+    // r_debug, then two link_map entries chained by l_next, then their names
+    let mut data = vec![0; 0x100];
+    put(&mut data, 0, &[1, DATA + 0x40, DATA + 0x1aa, 0, 0x7f00_0001_0000]);
+    put(&mut data, 0x40, &[BIAS, DATA + 0xc0, BIAS + 0x2000, DATA + 0x60]);
+    put(&mut data, 0x60, &[0x7f00_0010_0000, DATA + 0xc8, 0x7f00_0010_3000, last_next]);
+    data[0xc8..0xd6].copy_from_slice(b"/lib/libc.so.6");
+
+    let eh = Header {
+        etype: types::ElfType::CORE,
+        machine: types::Machine::X86_64,
+        phoff: 64,
+        ehsize: 64,
+        phentsize: 56,
+        phnum: 4,
+        ..Default::default()
+    };
+    let notes_at = 64 + 4 * 56;
+    let dynamic_at = 0x1000;
+    let data_at = 0x2000;
+    let segments = vec![
+        SegmentHeader {
+            phtype: types::SegmentType::NOTE,
+            flags: types::SegmentFlags::empty(),
+            offset: notes_at,
+            vaddr: 0,
+            paddr: 0,
+            filesz: notes.len() as u64,
+            memsz: 0,
+            align: 4,
+        },
+        load(dynamic_at, BIAS + 0x2000, 0x1000, 0x1000),
+        load(data_at, DATA, 0x1000, 0x1000),
+        // not dumped
+        load(0x3000, 0x7f00_0020_0000, 0, 0x1000),
+    ];
+
+    let mut io = Cursor::new(Vec::new());
+    eh.to_writer(&mut io).unwrap();
+    for seg in &segments {
+        seg.to_writer(&eh, &mut io).unwrap();
+    }
+    let mut b = io.into_inner();
+    b.resize(0x3000, 0);
+    b[notes_at as usize..notes_at as usize + notes.len()].copy_from_slice(&notes);
+    b[dynamic_at as usize..dynamic_at as usize + 48].copy_from_slice(&dynamic);
+    b[data_at as usize..data_at as usize + 0x100].copy_from_slice(&data);
+    b
+}
+
+/// the executable, only its entry point and PT_DYNAMIC matter
+fn exe() -> Elf {
+    let mut exe = Elf::default();
+    exe.header.entry = 0x1040;
+    exe.segments.push(SegmentHeader {
+        phtype: types::SegmentType::DYNAMIC,
+        flags: types::SegmentFlags::READABLE | types::SegmentFlags::WRITABLE,
+        offset: 0x2000,
+        vaddr: 0x2000,
+        paddr: 0x2000,
+        filesz: 48,
+        memsz: 48,
+        align: 8,
+    });
+    exe
+}
+
+#[test]
+fn link_map_from_core() {
+    let mut io = Cursor::new(core(DATA, 0));
+    let elf = Elf::from_reader(&mut io).unwrap();
+    assert!(elf.sections.is_empty());
+    assert_eq!(elf.core_auxv(&mut io).unwrap(), vec![(3, BIAS + 0x40), (9, BIAS + 0x1040)]);
+
+    let r = elf.r_debug(&mut io, &exe()).unwrap().unwrap();
+    assert_eq!(r.version, 1);
+    assert_eq!(r.brk, DATA + 0x1aa);
+    assert_eq!(r.state, RState::Consistent);
+    assert_eq!(r.ldbase, 0x7f00_0001_0000);
+    assert_eq!(
+        r.map,
+        vec![
+            LinkMapEntry {
+                addr: BIAS,
+                name: String::new(),
+                ld: BIAS + 0x2000,
+            },
+            LinkMapEntry {
+                addr: 0x7f00_0010_0000,
+                name: String::from("/lib/libc.so.6"),
+                ld: 0x7f00_0010_3000,
+            },
+        ]
+    );
+
+    assert_eq!(elf.read_memory(&mut io, DATA + 0xc8, 4).unwrap(), b"/lib");
+    match elf.read_memory(&mut io, 0x7f00_0020_0000, 8) {
+        Err(Error::UnmappedAddress(0x7f00_0020_0000)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[test]
+fn no_link_map() {
+    // ld.so never filled in DT_DEBUG
+    let mut io = Cursor::new(core(0, 0));
+    let elf = Elf::from_reader(&mut io).unwrap();
+    assert_eq!(elf.r_debug(&mut io, &exe()).unwrap(), None);
+
+    // a static executable has no dynamic section at all
+    assert_eq!(elf.r_debug(&mut io, &Elf::default()).unwrap(), None);
+
+    let mut exe = exe();
+    exe.header.etype = types::ElfType::EXEC;
+    match exe.core_auxv(&mut Cursor::new(Vec::new())) {
+        Err(Error::InvalidHeaderField("e_type")) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[test]
+fn circular_link_map() {
+    let mut io = Cursor::new(core(DATA, DATA + 0x40));
+    let elf = Elf::from_reader(&mut io).unwrap();
+    match elf.r_debug(&mut io, &exe()) {
+        Err(Error::LimitExceeded("link map entries", _)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[test]
+fn memory_at_the_top_of_the_address_space() {
+    let mut io = Cursor::new(core(DATA, 0));
+    let mut elf = Elf::from_reader(&mut io).unwrap();
+    // the last 16 bytes, with the DT_FLAGS_1 of the executable's PT_DYNAMIC
+    let top = u64::MAX - 0xf;
+    elf.segments.push(load(0x1000, top, 0x10, 0x10));
+    assert_eq!(elf.read_memory(&mut io, top, 16).unwrap().len(), 16);
+    match elf.read_memory(&mut io, top, 32) {
+        Err(Error::UnmappedAddress(_)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+
+    // a PT_DYNAMIC that runs past the end of it
+    let mut exe = exe();
+    exe.segments[0].vaddr = top.wrapping_sub(BIAS);
+    match elf.r_debug(&mut io, &exe) {
+        Err(Error::UnmappedAddress(_)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[test]
+fn cores_can_be_inspected_like_any_file() {
+    let mut elf = Elf::from_reader(&mut Cursor::new(core(DATA, 0))).unwrap();
+    assert!(!elf.header.etype.is_linkable());
+    elf.load_all().unwrap();
+    assert!(elf.validate().is_empty());
+    assert_eq!(elf.security_report().unwrap().pie, Pie::NotAProgram);
+    assert!(elf.defined_symbols().unwrap().is_empty());
+    elf.content_hash(HashAlgorithm::XxHash64).unwrap();
+
+    // firmware often comes without a type
+    elf.header.etype = types::ElfType::NONE;
+    assert_eq!(elf.security_report().unwrap().pie, Pie::NotAProgram);
+    assert!(types::ElfType::REL.is_linkable() && types::ElfType::DYN.is_linkable());
+    assert!(!types::ElfType::EXEC.is_linkable());
+}
@@ -0,0 +1,88 @@
+extern crate elfkit;
+extern crate tempfile;
+
+use elfkit::debuglink;
+use elfkit::{linker, types, Elf};
+use std::fs::{self, File};
+use std::io::Cursor;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+
+fn read(path: &Path) -> Elf {
+    Elf::from_reader(&mut File::open(path).unwrap()).unwrap()
+}
+
+#[test]
+fn crc32_of_the_check_string() {
+    assert_eq!(debuglink::crc32(b""), 0);
+    assert_eq!(debuglink::crc32(b"123456789"), 0xcbf4_3926);
+}
+
+#[test]
+fn split_debug_like_objcopy() {
+    // everything is named after a temporary file that stays ours until the end
+    let tmp = tempfile::NamedTempFile::new().unwrap();
+    let exe = tmp.path().with_extension("exe");
+    let src = exe.with_extension("c");
+    let stripped = exe.with_extension("stripped");
+    let companion = exe.with_extension("debug");
+    fs::write(&src, "#include <stdio.h>\nint main() { puts(\"split\"); return 0; }\n").unwrap();
+    let status = Command::new("gcc")
+        .args(["-g", "-O0"])
+        .arg(&src)
+        .arg("-o")
+        .arg(&exe)
+        .status()
+        .unwrap();
+    fs::remove_file(&src).unwrap();
+    assert!(status.success());
+
+    let mut elf = read(&exe);
+    fs::remove_file(&exe).unwrap();
+    let name = companion.file_name().unwrap().to_str().unwrap().to_owned();
+    let bytes = debuglink::split_debug(&mut elf, &name).unwrap();
+    assert!(elf.sections.iter().all(|s| !linker::is_debug_section(&s.name)));
+    let crc = debuglink::crc32(&bytes);
+    assert_eq!(debuglink::debuglink(&mut elf).unwrap(), Some((name.clone(), crc)));
+    elf.store_all().unwrap();
+    elf.to_writer(&mut File::create(&stripped).unwrap()).unwrap();
+    fs::write(&companion, &bytes).unwrap();
+
+    fs::set_permissions(&stripped, fs::Permissions::from_mode(0o755)).unwrap();
+    let out = Command::new(&stripped).output().unwrap();
+    assert!(out.status.success());
+    assert_eq!(out.stdout, b"split\n");
+
+    // the companion has the debug info, and nothing the loader would map
+    let mut debug = Elf::from_reader(&mut Cursor::new(&bytes)).unwrap();
+    debug.load_all().unwrap();
+    let info = debug.sections.iter().find(|s| s.name == ".debug_info").unwrap();
+    assert_eq!(info.content.as_raw().map(|r| r.len() as u64), Some(info.header.size));
+    let text = debug.sections.iter().find(|s| s.name == ".text").unwrap();
+    assert_eq!(text.header.shtype, types::SectionType::NOBITS);
+    assert!(debug.segments.iter().all(|s| s.filesz == 0));
+    let out = Command::new("readelf")
+        .arg("--debug-dump=info")
+        .arg(&companion)
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert!(String::from_utf8_lossy(&out.stdout).contains("DW_AT_name"));
+
+    // objcopy links the companion with the same crc
+    let relinked = exe.with_extension("relinked");
+    let status = Command::new("objcopy")
+        .arg("--remove-section=.gnu_debuglink")
+        .arg(format!("--add-gnu-debuglink={}", companion.display()))
+        .arg(&stripped)
+        .arg(&relinked)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let theirs = debuglink::debuglink(&mut read(&relinked)).unwrap();
+    for f in &[&relinked, &stripped, &companion] {
+        fs::remove_file(f).unwrap();
+    }
+    assert_eq!(theirs, Some((name, crc)));
+}
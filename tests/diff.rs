@@ -0,0 +1,106 @@
+extern crate elfkit;
+
+use elfkit::diff::DiffKind;
+use elfkit::{types, Dynamic, DynamicContent, Elf, ElfBuilder, Section, SectionContent, Symbol};
+use std::io::Cursor;
+
+fn function(name: &str, value: u64) -> Symbol {
+    let mut sym = Symbol::default();
+    sym.name = String::from(name);
+    sym.stype = types::SymbolType::FUNC;
+    sym.bind = types::SymbolBind::GLOBAL;
+    sym.value = value;
+    sym
+}
+
+fn build(code: Vec<u8>, symbols: Vec<Symbol>) -> Elf {
+    let mut builder = ElfBuilder::new(types::Machine::X86_64).text(code);
+    for sym in symbols {
+        builder = builder.symbol(".text", sym);
+    }
+    let mut elf = builder.build().unwrap();
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    let mut elf = Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap();
+    elf.load_all().unwrap();
+    elf
+}
+
+#[test]
+fn identical_files_have_no_differences() {
+    let a = build(vec![0x90, 0xc3], vec![function("main", 0)]);
+    let b = build(vec![0x90, 0xc3], vec![function("main", 0)]);
+    assert_eq!(a.diff(&b), vec![]);
+}
+
+#[test]
+fn symbols_and_content() {
+    let a = build(vec![0x90, 0xc3], vec![function("main", 0), function("old", 1)]);
+    let b = build(
+        vec![0x90, 0xcc, 0xc3],
+        vec![function("main", 1), function("new", 2)],
+    );
+    let found: Vec<String> = a.diff(&b).iter().map(|d| d.to_string()).collect();
+    for line in &[
+        "~ section .text size: 0x2 -> 0x3",
+        "~ section .text content at 0x1: c3 -> cc",
+        // the builder adds the address of .text
+        "- symbol in .symtab old: FUNC GLOBAL 0xb1",
+        "+ symbol in .symtab new: FUNC GLOBAL 0xb2",
+        "~ symbol in .symtab main value: 0xb0 -> 0xb1",
+        "~ section .strtab string 2: old -> new",
+    ] {
+        assert!(found.iter().any(|f| f == line), "{} not in {:#?}", line, found);
+    }
+}
+
+#[test]
+fn sections_and_dynamic() {
+    let mut a = build(vec![0xc3], vec![]);
+    let mut b = build(vec![0xc3], vec![]);
+    let dynamic = |needed: &[&str]| {
+        let mut d: Vec<Dynamic> = needed
+            .iter()
+            .map(|n| Dynamic {
+                dhtype: types::DynamicType::NEEDED,
+                content: DynamicContent::String(n.to_string()),
+            })
+            .collect();
+        d.push(Dynamic {
+            dhtype: types::DynamicType::NULL,
+            content: DynamicContent::None,
+        });
+        Section::new(
+            String::from(".dynamic"),
+            types::SectionType::DYNAMIC,
+            types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+            SectionContent::Dynamic(d),
+            0,
+            0,
+        )
+    };
+    a.sections.push(dynamic(&["libc.so.6", "libm.so.6"]));
+    b.sections.push(dynamic(&["libc.so.6", "libz.so.1"]));
+    b.sections.push(Section::new(
+        String::from(".comment"),
+        types::SectionType::PROGBITS,
+        types::SectionFlags::empty(),
+        SectionContent::Raw(b"GCC\0".to_vec()),
+        0,
+        0,
+    ));
+
+    let differences = a.diff(&b);
+    let kinds = |what: &str| {
+        differences
+            .iter()
+            .filter(|d| d.what == what)
+            .map(|d| d.kind)
+            .collect::<Vec<DiffKind>>()
+    };
+    assert_eq!(kinds("dynamic NEEDED libm.so.6"), vec![DiffKind::Removed]);
+    assert_eq!(kinds("dynamic NEEDED libz.so.1"), vec![DiffKind::Added]);
+    assert_eq!(kinds("dynamic NEEDED libc.so.6"), vec![]);
+    assert_eq!(kinds("section .comment"), vec![DiffKind::Added]);
+}
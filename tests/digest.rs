@@ -0,0 +1,105 @@
+extern crate elfkit;
+
+use elfkit::digest::{self, to_hex};
+use elfkit::{types, Elf, ElfBuilder, HashAlgorithm, Section, SectionContent, Symbol};
+use std::io::Cursor;
+
+fn write(elf: &mut Elf) -> Vec<u8> {
+    elf.sync_all().unwrap();
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    io.into_inner()
+}
+
+fn read(bytes: &[u8]) -> Elf {
+    Elf::from_reader(&mut Cursor::new(bytes)).unwrap()
+}
+
+fn sample() -> Vec<u8> {
+    let start = Symbol {
+        name: String::from("_start"),
+        bind: types::SymbolBind::GLOBAL,
+        stype: types::SymbolType::FUNC,
+        ..Symbol::default()
+    };
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0x90, 0xc3])
+        .section(
+            ".data",
+            types::SectionType::PROGBITS,
+            types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+            vec![1, 2, 3, 4],
+        )
+        .symbol(".text", start)
+        .entry("_start")
+        .build()
+        .unwrap();
+    write(&mut elf)
+}
+
+#[test]
+fn known_digests() {
+    let xx = |s: &str| to_hex(&digest::hash(HashAlgorithm::XxHash64, s.as_bytes()));
+    assert_eq!(xx(""), "ef46db3751d8e999");
+    assert_eq!(xx("abc"), "44bc2cf5ad770999");
+    assert_eq!(xx("Nobody inspects the spammish repetition"), "fbcea83c8a378bf1");
+    assert_eq!(
+        to_hex(&digest::hash(HashAlgorithm::Sha256, b"abc")),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
+
+#[test]
+fn content_hash_ignores_layout() {
+    let bytes = sample();
+    let mut elf = read(&bytes);
+    let sha = elf.content_hash(HashAlgorithm::Sha256).unwrap();
+    let xx = elf.content_hash(HashAlgorithm::XxHash64).unwrap();
+    assert_eq!((sha.len(), xx.len()), (32, 8));
+
+    // written again, with everything loaded and the string tables rebuilt
+    let mut again = read(&write(&mut elf));
+    assert_eq!(again.content_hash(HashAlgorithm::Sha256).unwrap(), sha);
+    assert_eq!(again.content_hash(HashAlgorithm::XxHash64).unwrap(), xx);
+
+    let mut changed = read(&bytes);
+    changed.load_all().unwrap();
+    let data = changed.sections.iter().position(|s| s.name == ".data").unwrap();
+    changed.sections[data].content.as_raw_mut().unwrap()[0] = 9;
+    assert!(changed.content_hash(HashAlgorithm::Sha256).unwrap() != sha);
+}
+
+#[test]
+fn section_digest_survives_sections_being_added() {
+    let mut elf = read(&sample());
+    elf.load_all().unwrap();
+    let digests = |elf: &Elf| {
+        elf.sections
+            .iter()
+            .filter(|s| s.name == ".text" || s.name == ".symtab")
+            .map(|s| s.digest(&elf.header, HashAlgorithm::XxHash64).unwrap())
+            .collect::<Vec<_>>()
+    };
+    let before = digests(&elf);
+    assert_eq!(before.len(), 2);
+
+    let mut comment = Section::new(
+        String::from(".comment"),
+        types::SectionType::PROGBITS,
+        types::SectionFlags::empty(),
+        SectionContent::Raw(b"elfkit\0".to_vec()),
+        0,
+        0,
+    );
+    // past the section headers, which move to the end, and .shstrtab, which grows
+    comment.header.offset = elf.header.shoff + 0x1000;
+    elf.insert_section(3, comment).unwrap();
+    let mut moved = read(&write(&mut elf));
+    moved.load_all().unwrap();
+    assert_eq!(digests(&moved), before);
+
+    let text = moved.sections.iter().position(|s| s.name == ".text").unwrap();
+    moved.sections[text].header.addr += 0x1000;
+    assert!(digests(&moved) != before);
+}
@@ -0,0 +1,121 @@
+extern crate elfkit;
+
+use elfkit::linker::Symbolic;
+use elfkit::relocation::RelocationType;
+use elfkit::{linker, types, Dynamic, DynamicContent, ElfBuilder, Relocation, SectionContent,
+             Symbol, SymbolSectionIndex};
+use elfkit::types::{DynamicFlags, DynamicFlags1};
+
+fn entries(raw: &[(u64, u64)]) -> Vec<u8> {
+    let mut b = Vec::new();
+    for &(tag, val) in raw {
+        b.extend_from_slice(&tag.to_le_bytes());
+        b.extend_from_slice(&val.to_le_bytes());
+    }
+    b
+}
+
+#[test]
+fn flags_are_parsed_and_kept() {
+    let eh = ElfBuilder::new(types::Machine::X86_64).build().unwrap().header;
+    // DT_FLAGS BIND_NOW|ORIGIN and a bit without a name, DT_FLAGS_1 NOW|PIE and bit 40
+    let raw = entries(&[(30, 0x109), (0x6ffffffb, 1 << 40 | 1 << 27 | 1), (0, 0)]);
+    let dynamic = match Dynamic::from_reader(&raw[..], None, &eh).unwrap() {
+        SectionContent::Dynamic(d) => d,
+        c => panic!("unexpected {:?}", c),
+    };
+    match dynamic[0].content {
+        DynamicContent::Flags(f) => {
+            assert!(f.contains(DynamicFlags::BIND_NOW | DynamicFlags::ORIGIN));
+            assert!(!f.contains(DynamicFlags::TEXTREL));
+            assert_eq!(f.bits(), 0x109);
+        }
+        ref c => panic!("unexpected {:?}", c),
+    }
+    match dynamic[1].content {
+        DynamicContent::Flags1(f) => {
+            assert!(f.contains(DynamicFlags1::NOW | DynamicFlags1::PIE));
+            assert_eq!(f.bits(), 1 << 40 | 1 << 27 | 1);
+        }
+        ref c => panic!("unexpected {:?}", c),
+    }
+
+    let mut out = Vec::new();
+    for d in &dynamic {
+        d.to_writer(&mut out, None, &eh).unwrap();
+    }
+    assert_eq!(out, raw);
+}
+
+#[test]
+fn z_keywords() {
+    let z = |k: &[&str]| linker::z_flags(&k.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+    assert_eq!(
+        z(&["now", "relro", "nodelete"]),
+        (DynamicFlags::BIND_NOW, DynamicFlags1::NOW | DynamicFlags1::NODELETE)
+    );
+    assert_eq!(
+        z(&["origin", "global", "nodlopen"]),
+        (
+            DynamicFlags::ORIGIN,
+            DynamicFlags1::ORIGIN | DynamicFlags1::GLOBAL | DynamicFlags1::NOOPEN
+        )
+    );
+    assert_eq!(z(&["now", "lazy"]), (DynamicFlags::empty(), DynamicFlags1::empty()));
+    assert_eq!(z(&["muldefs"]), (DynamicFlags::empty(), DynamicFlags1::empty()));
+}
+
+#[test]
+fn symbolic_binds_exported_symbols_locally() {
+    let eh = ElfBuilder::new(types::Machine::X86_64).build().unwrap().header;
+    let sym = |name: &str, stype, vis, shndx| {
+        let mut s = Symbol {
+            name: String::from(name),
+            bind: types::SymbolBind::GLOBAL,
+            stype,
+            shndx,
+            value: 0x1000,
+            ..Symbol::default()
+        };
+        s.set_vis(vis);
+        s
+    };
+    let text = SymbolSectionIndex::Section(1);
+    let symbols = vec![
+        Symbol::default(),
+        sym("f", types::SymbolType::FUNC, types::SymbolVis::DEFAULT, text.clone()),
+        sym("d", types::SymbolType::OBJECT, types::SymbolVis::DEFAULT, text.clone()),
+        sym("p", types::SymbolType::OBJECT, types::SymbolVis::PROTECTED, text.clone()),
+        sym("u", types::SymbolType::FUNC, types::SymbolVis::DEFAULT, SymbolSectionIndex::Undefined),
+    ];
+    let reloc = |addr, sym, rtype, addend| Relocation { addr, sym, rtype, addend };
+    let relocs = vec![
+        reloc(0x10, 1, RelocationType::R_X86_64_64, 4),
+        reloc(0x18, 2, RelocationType::R_X86_64_GLOB_DAT, 0),
+        reloc(0x20, 3, RelocationType::R_X86_64_GLOB_DAT, 0),
+        reloc(0x28, 1, RelocationType::R_X86_64_JUMP_SLOT, 0),
+        reloc(0x30, 4, RelocationType::R_X86_64_64, 0),
+    ];
+    let relative = |relocs: &[Relocation]| -> Vec<(u64, i64)> {
+        relocs
+            .iter()
+            .filter(|r| r.rtype == RelocationType::R_X86_64_RELATIVE && r.sym == 0)
+            .map(|r| (r.addr, r.addend))
+            .collect()
+    };
+    let link = |symbolic| {
+        let mut r = relocs.clone();
+        let n = linker::symbolic_relocations(&eh, &mut r, &symbols, &symbolic).unwrap();
+        assert_eq!(n, relative(&r).len());
+        relative(&r)
+    };
+    assert_eq!(link(Symbolic::None), vec![(0x20, 0x1000)]);
+    assert_eq!(link(Symbolic::Functions), vec![(0x10, 0x1004), (0x20, 0x1000)]);
+    assert_eq!(link(Symbolic::All), vec![(0x10, 0x1004), (0x18, 0x1000), (0x20, 0x1000)]);
+
+    let (flags, dynamic) = linker::symbolic_dynamic(&Symbolic::All);
+    assert_eq!(flags, DynamicFlags::SYMBOLIC);
+    assert_eq!(dynamic[0].dhtype, types::DynamicType::SYMBOLIC);
+    let (flags, dynamic) = linker::symbolic_dynamic(&Symbolic::Functions);
+    assert!(flags.is_empty() && dynamic.is_empty());
+}
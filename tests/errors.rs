@@ -0,0 +1,297 @@
+extern crate elfkit;
+
+use elfkit::{types, Elf, ElfBuilder, ElfOptions, Error, SectionContent, Symbol};
+use elfkit::error::Location;
+use elfkit::relocation::RelocationType;
+use std::io::Cursor;
+
+fn sample() -> Vec<u8> {
+    let mut start = Symbol::default();
+    start.name = String::from("_start");
+    start.bind = types::SymbolBind::GLOBAL;
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .symbol(".text", start)
+        .build()
+        .unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    io.into_inner()
+}
+
+#[test]
+fn symbol_errors_carry_their_section() {
+    let mut raw = sample();
+    let (index, offset) = {
+        let elf = Elf::from_reader(&mut Cursor::new(&raw)).unwrap();
+        let i = elf.sections.iter().position(|s| s.name == ".symtab").unwrap();
+        (i, elf.sections[i].header.offset)
+    };
+    // st_info of the first real symbol, with an undefined binding
+    raw[offset as usize + 24 + 4] = 0x70;
+
+    let mut elf = Elf::from_reader(&mut Cursor::new(&raw)).unwrap();
+    let e = elf.load_all().unwrap_err();
+    match e.root_cause() {
+        &Error::InvalidSymbolBind(7) => {}
+        e => panic!("unexpected error {:?}", e),
+    }
+    assert_eq!(
+        e.location(),
+        Some(&Location::Section {
+            index: index,
+            name: String::from(".symtab"),
+            offset: offset,
+        })
+    );
+    assert_eq!(
+        e.to_string(),
+        format!(
+            "section {} '.symtab' at offset 0x{:x}: invalid symbol binding 7",
+            index, offset
+        )
+    );
+}
+
+#[test]
+fn truncated_section_reports_its_index() {
+    let mut raw = sample();
+    let shoff = {
+        let elf = Elf::from_reader(&mut Cursor::new(&raw)).unwrap();
+        assert_eq!(elf.sections[1].name, ".text");
+        elf.header.shoff as usize
+    };
+    // make sh_size of .text extend past the end of the file
+    let len = raw.len() as u8;
+    raw[shoff + 64 + 0x20] = len;
+    raw[shoff + 64 + 0x21] = 0xff;
+
+    match Elf::from_reader(&mut Cursor::new(&raw)) {
+        Err(Error::At(Location::Section { index: 1, .. }, ref e)) => match **e {
+            Error::SectionBeyondEndOfFile(..) => {}
+            ref e => panic!("unexpected error {:?}", e),
+        },
+        r => panic!("unexpected result {:?}", r.map(|_| ())),
+    }
+}
+
+/// .text with an unnamed flag bit (1 << 3) and sh_link pointing nowhere
+fn odd_text() -> Vec<u8> {
+    let mut raw = sample();
+    let shoff = Elf::from_reader(&mut Cursor::new(&raw)).unwrap().header.shoff as usize;
+    raw[shoff + 64 + 0x08] |= 0x08;
+    raw[shoff + 64 + 0x28] = 99;
+    raw
+}
+
+#[test]
+fn strict_rejects_unknown_section_flags() {
+    let options = ElfOptions {
+        strict: true,
+        ..ElfOptions::default()
+    };
+    match Elf::from_reader_with_options(&mut Cursor::new(&odd_text()), options) {
+        Err(Error::At(Location::Section { index: 1, .. }, ref e)) => match **e {
+            Error::InvalidSectionFlags(f) => assert_eq!(f & 0x08, 0x08),
+            ref e => panic!("unexpected error {:?}", e),
+        },
+        r => panic!("unexpected result {:?}", r.map(|_| ())),
+    }
+}
+
+#[test]
+fn permissive_keeps_unknown_section_flags_and_links() {
+    // which is the default
+    let elf = Elf::from_reader(&mut Cursor::new(&odd_text())).unwrap();
+    assert_eq!(elf.warnings.len(), 2);
+    match elf.warnings[1].root_cause() {
+        &Error::InvalidSectionLink(99) => {}
+        e => panic!("unexpected warning {:?}", e),
+    }
+    let text = &elf.sections[1].header;
+    assert_eq!(text.flags.bits() & 0x08, 0x08);
+    assert!(text.flags.contains(types::SectionFlags::ALLOC));
+    assert_eq!(text.link, 99);
+}
+
+#[test]
+fn permissive_keeps_unknown_relocations() {
+    // r_offset 0, r_info type 0xfff, r_addend 0
+    let mut rela = vec![0; 24];
+    rela[8] = 0xff;
+    rela[9] = 0x0f;
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .section(".rela.text", types::SectionType::RELA, types::SectionFlags::empty(), rela)
+        .build()
+        .unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    let raw = io.into_inner();
+
+    let options = ElfOptions {
+        strict: true,
+        ..ElfOptions::default()
+    };
+    let mut strict = Elf::from_reader_with_options(&mut Cursor::new(&raw), options).unwrap();
+    match strict.load_all().unwrap_err().root_cause() {
+        &Error::InvalidRelocationType(0xfff) => {}
+        e => panic!("unexpected error {:?}", e),
+    }
+
+    let mut elf = Elf::from_reader(&mut Cursor::new(&raw)).unwrap();
+    elf.load_all().unwrap();
+    let i = elf.sections.iter().position(|s| s.name == ".rela.text").unwrap();
+    match elf.sections[i].content {
+        SectionContent::Relocations(ref r) => {
+            assert_eq!(r.len(), 1);
+            assert_eq!(r[0].rtype, RelocationType(0xfff));
+        }
+        _ => panic!("relocations not loaded"),
+    }
+    assert_eq!(elf.warnings.len(), 1);
+    match elf.warnings[0].location() {
+        Some(&Location::Section { ref name, .. }) => assert_eq!(name, ".rela.text"),
+        l => panic!("unexpected location {:?}", l),
+    }
+}
+
+#[test]
+fn section_name_outside_shstrtab() {
+    let mut raw = sample();
+    let mut shoff = [0; 8];
+    shoff.copy_from_slice(&raw[40..48]);
+    let shoff = u64::from_le_bytes(shoff) as usize;
+    // sh_name of section 1
+    raw[shoff + 64..shoff + 68].copy_from_slice(&0xffffu32.to_le_bytes());
+
+    let e = match Elf::from_reader(&mut Cursor::new(&raw)) {
+        Ok(_) => panic!("name outside of .shstrtab accepted"),
+        Err(e) => e,
+    };
+    match e.root_cause() {
+        &Error::InvalidStrtabOffset(0xffff) => {}
+        e => panic!("unexpected error {:?}", e),
+    }
+    match e.location() {
+        Some(&Location::Section { index: 1, .. }) => {}
+        l => panic!("unexpected location {:?}", l),
+    }
+}
+
+#[test]
+fn resource_limits() {
+    let mut raw = sample();
+    let shoff = Elf::from_reader(&mut Cursor::new(&raw)).unwrap().header.shoff as usize;
+    // sh_size of .text far beyond anything that could be allocated
+    raw[shoff + 64 + 0x20..shoff + 64 + 0x28].copy_from_slice(&(1u64 << 50).to_le_bytes());
+
+    match Elf::from_reader(&mut Cursor::new(&raw)).map(|_| ()).unwrap_err().root_cause() {
+        &Error::SectionBeyondEndOfFile(_, v) if v == 1 << 50 => {}
+        e => panic!("unexpected error {:?}", e),
+    }
+    let e = Elf::from_reader_with_options(&mut Cursor::new(&raw), ElfOptions::untrusted())
+        .map(|_| ())
+        .unwrap_err();
+    match e.root_cause() {
+        &Error::LimitExceeded("section size", v) if v == 1 << 50 => {}
+        e => panic!("unexpected error {:?}", e),
+    }
+
+    let options = ElfOptions {
+        max_sections: 2,
+        ..ElfOptions::default()
+    };
+    match Elf::from_reader_with_options(&mut Cursor::new(&sample()), options) {
+        Err(Error::LimitExceeded("number of sections", 5)) => {}
+        r => panic!("unexpected result {:?}", r.map(|_| ())),
+    }
+}
+
+#[test]
+fn section_link_cycles() {
+    let raw = sample();
+    let (symtab, strtab, shoff) = {
+        let elf = Elf::from_reader(&mut Cursor::new(&raw)).unwrap();
+        let symtab = elf.sections.iter().position(|s| s.name == ".symtab").unwrap();
+        (symtab, elf.sections[symtab].header.link as usize, elf.header.shoff as usize)
+    };
+
+    // .strtab links back to .symtab
+    let mut cycle = raw.clone();
+    let link = shoff + strtab * 64 + 0x28;
+    cycle[link..link + 4].copy_from_slice(&(symtab as u32).to_le_bytes());
+    let mut elf = Elf::from_reader(&mut Cursor::new(&cycle)).unwrap();
+    let e = elf.load_at(symtab).unwrap_err();
+    match e.root_cause() {
+        &Error::SectionLinkCycle(v) if v as usize == symtab => {}
+        e => panic!("unexpected error {:?}", e),
+    }
+    // a failed load leaves the section as it was
+    assert_eq!(elf.sections[symtab].name, ".symtab");
+    match elf.sections[symtab].content {
+        SectionContent::Raw(_) => {}
+        _ => panic!(".symtab lost its content"),
+    }
+
+    // .symtab links to itself
+    let mut own = raw.clone();
+    let link = shoff + symtab * 64 + 0x28;
+    own[link..link + 4].copy_from_slice(&(symtab as u32).to_le_bytes());
+    let mut elf = Elf::from_reader(&mut Cursor::new(&own)).unwrap();
+    assert!(elf.load_all().is_err());
+}
+
+#[test]
+fn values_past_4g_do_not_wrap_in_32_bit_files() {
+    let mut eh = elfkit::Header::default();
+    eh.ident_class = types::Class::Class32;
+    eh.machine = types::Machine::ARM;
+
+    let mut sh = elfkit::SectionHeader::default();
+    sh.size = 0x1_0000_0000;
+    match sh.to_writer(&eh, &mut Vec::new()) {
+        Err(Error::FieldOverflow(_, 0x1_0000_0000)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+    let seg = elfkit::SegmentHeader {
+        memsz: 0x1_0000_0010,
+        ..elfkit::SegmentHeader::default()
+    };
+    match seg.to_writer(&eh, &mut Vec::new()) {
+        Err(Error::FieldOverflow(_, 0x1_0000_0010)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+
+    // r_info of 32 bit files has 24 bits for the symbol
+    let mut reloc = elfkit::Relocation {
+        addr: 0,
+        sym: 0x100_0000,
+        rtype: RelocationType::R_ARM_ABS32,
+        addend: 0,
+    };
+    match reloc.to_writer(&mut Vec::new(), None, &eh) {
+        Err(Error::FieldOverflow("r_info of a 32 bit file", 0x100_0000)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+    reloc.sym = 0xff_ffff;
+    reloc.to_writer(&mut Vec::new(), None, &eh).unwrap();
+    reloc.addend = 0x8000_0000;
+    assert!(reloc.to_writer(&mut Vec::new(), None, &eh).is_err());
+}
+
+#[test]
+fn raw_symbols_can_not_take_extended_indices() {
+    let eh = elfkit::Header::default();
+    let mut sym = Symbol::default();
+    sym.shndx = elfkit::SymbolSectionIndex::Section(1);
+    let mut raw = Vec::new();
+    let mut strtab = SectionContent::Strtab(elfkit::Strtab::default());
+    sym.to_writer(&mut raw, Some(&mut strtab), &eh).unwrap();
+
+    Symbol::remap_raw_shndx(&mut raw, &eh, |i| i as u32 + 0xfefe).unwrap();
+    match Symbol::remap_raw_shndx(&mut raw, &eh, |i| i as u32 + 1) {
+        Err(Error::FieldOverflow("st_shndx", 0xff00)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
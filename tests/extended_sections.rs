@@ -0,0 +1,143 @@
+extern crate elfkit;
+
+use elfkit::{linker, types, Elf, Section, SectionContent, SegmentHeader, Strtab, Symbol,
+             SymbolSectionIndex};
+use std::io::Cursor;
+
+const FILLERS: usize = 0xff10;
+
+fn section(name: &str, shtype: types::SectionType, link: u32) -> Section {
+    Section::new(
+        String::from(name),
+        shtype,
+        types::SectionFlags::empty(),
+        SectionContent::Raw(Vec::new()),
+        link,
+        0,
+    )
+}
+
+/// null, FILLERS empty sections, .text, .symtab -> .strtab, .strtab, .shstrtab
+/// with a symbol main defined in .text and one in the first filler
+fn sample() -> Elf {
+    let mut elf = Elf::default();
+    elf.sections.push(Section::default());
+    for i in 0..FILLERS {
+        elf.sections.push(section(&format!(".s{}", i), types::SectionType::PROGBITS, 0));
+    }
+    let text = elf.sections.len();
+    let mut sec = section(".text", types::SectionType::PROGBITS, 0);
+    sec.content = SectionContent::Raw(vec![0xc3]);
+    elf.sections.push(sec);
+
+    let mut symtab = section(".symtab", types::SectionType::SYMTAB, text as u32 + 2);
+    let mut main = Symbol::default();
+    main.name = String::from("main");
+    main.bind = types::SymbolBind::GLOBAL;
+    main.shndx = SymbolSectionIndex::Section(text as u32);
+    let mut first = Symbol::default();
+    first.name = String::from("first");
+    first.shndx = SymbolSectionIndex::Section(1);
+    symtab.content = SectionContent::Symbols(vec![Symbol::default(), first, main]);
+    elf.sections.push(symtab);
+    let mut strtab = section(".strtab", types::SectionType::STRTAB, 0);
+    strtab.content = SectionContent::Strtab(Strtab::default());
+    elf.sections.push(strtab);
+    let mut shstrtab = section(".shstrtab", types::SectionType::STRTAB, 0);
+    shstrtab.content = SectionContent::Strtab(Strtab::default());
+    elf.sections.push(shstrtab);
+    elf
+}
+
+fn write(elf: &mut Elf) -> Vec<u8> {
+    elf.sync_all().unwrap();
+    linker::relayout(elf, 0x300000).unwrap();
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    io.into_inner()
+}
+
+#[test]
+fn more_sections_than_e_shnum_can_hold() {
+    let mut elf = sample();
+    let raw = write(&mut elf);
+    assert_eq!(elf.header.shnum, 0);
+    assert_eq!(elf.header.shstrndx, types::SHN_XINDEX);
+
+    let mut elf = Elf::from_reader(&mut Cursor::new(raw)).unwrap();
+    // null, fillers, .text, .symtab, .symtab_shndx, .strtab, .shstrtab
+    assert_eq!(elf.sections.len(), FILLERS + 6);
+    assert_eq!(elf.sections[0].header.size, FILLERS as u64 + 6);
+    assert_eq!(elf.shstrndx(), FILLERS + 5);
+    assert_eq!(elf.sections[FILLERS + 5].name, ".shstrtab");
+
+    let symtab = FILLERS + 2;
+    let shndx = elf.symtab_shndx(symtab).unwrap();
+    assert_eq!(elf.sections[shndx].name, ".symtab_shndx");
+    assert_eq!(elf.sections[shndx].header.size, 3 * 4);
+
+    elf.load_all().unwrap();
+    let symbols = elf.sections[symtab].content.as_symbols().unwrap();
+    assert_eq!(symbols[1].shndx, SymbolSectionIndex::Section(1));
+    assert_eq!(symbols[2].name, "main");
+    assert_eq!(symbols[2].shndx, SymbolSectionIndex::Section(FILLERS as u32 + 1));
+}
+
+#[test]
+fn extended_indices_need_a_symtab_shndx() {
+    let mut elf = sample();
+    elf.sync_all().unwrap();
+    let symtab = FILLERS + 2;
+    let shndx = elf.symtab_shndx(symtab).unwrap();
+    elf.remove_section(shndx).unwrap();
+    assert!(elf.store_all().is_err());
+}
+
+#[test]
+fn more_segments_than_e_phnum_can_hold() {
+    let mut elf = Elf::default();
+    elf.segments = vec![SegmentHeader::default(); 0x10000];
+    elf.sections.push(Section::default());
+    let mut shstrtab = section(".shstrtab", types::SectionType::STRTAB, 0);
+    shstrtab.content = SectionContent::Strtab(Strtab::default());
+    elf.sections.push(shstrtab);
+    elf.sync_all().unwrap();
+    elf.sections[1].header.offset = 0x400000;
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    assert_eq!(elf.header.phnum, types::PN_XNUM);
+
+    let elf = Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap();
+    assert_eq!(elf.segments.len(), 0x10000);
+    assert_eq!(elf.sections[0].header.info, 0x10000);
+    assert_eq!(elf.sections[1].name, ".shstrtab");
+
+    let mut elf = Elf::default();
+    elf.segments = vec![SegmentHeader::default(); 0x10000];
+    assert!(elf.to_writer(&mut Cursor::new(Vec::new())).is_err());
+}
+
+#[test]
+fn null_section_only_holds_overflow_values() {
+    let mut elf = Elf::default();
+    let mut null = Section::default();
+    null.content = SectionContent::Raw(vec![1, 2, 3]);
+    null.header.size = 3;
+    null.header.info = 7;
+    elf.sections.push(null);
+    let mut shstrtab = section(".shstrtab", types::SectionType::STRTAB, 0);
+    shstrtab.content = SectionContent::Strtab(Strtab::default());
+    elf.sections.push(shstrtab);
+    elf.sync_all().unwrap();
+    assert_eq!(elf.sections[0].header.size, 0);
+    assert_eq!(elf.sections[0].header.info, 0);
+    assert!(elf.sections[0].content.clone().into_raw().is_none());
+
+    let mut elf = sample();
+    elf.sync_all().unwrap();
+    assert_eq!(elf.sections[0].header.size, elf.sections.len() as u64);
+    assert_eq!(elf.sections[0].header.link as usize, elf.shstrndx());
+    assert_eq!(elf.sections[0].header.info, 0);
+}
@@ -0,0 +1,97 @@
+extern crate elfkit;
+extern crate tempfile;
+
+use elfkit::filetype::{self, Compression, FileType};
+use elfkit::{linker, Elf, Error};
+use std::fs;
+use std::io::{Cursor, Write};
+use std::process::{Command, Stdio};
+
+fn detect(head: &[u8]) -> FileType {
+    filetype::filetype(Cursor::new(head)).unwrap()
+}
+
+#[test]
+fn foreign_formats_are_named() {
+    assert_eq!(detect(b"\x7fELF\x02\x01\x01"), FileType::Elf);
+    assert_eq!(detect(b"!<arch>\n/               "), FileType::Archive);
+    assert_eq!(detect(b"BC\xc0\xde\x35\x14\x00\x00"), FileType::LlvmBitcode);
+    assert_eq!(detect(b"\xde\xc0\x17\x0b\x00\x00\x00\x00"), FileType::LlvmBitcode);
+    assert_eq!(detect(b"\xcf\xfa\xed\xfe\x07\x00\x00\x01"), FileType::MachO);
+    assert_eq!(detect(b"\xca\xfe\xba\xbe\x00\x00\x00\x02"), FileType::MachO);
+    assert_eq!(detect(b"MZ\x90\x00\x03\x00"), FileType::Pe);
+    assert_eq!(detect(b"\x1f\x8b\x08\x00"), FileType::Compressed(Compression::Gzip));
+    assert_eq!(detect(b"\xfd7zXZ\x00\x00\x04"), FileType::Compressed(Compression::Xz));
+    assert_eq!(detect(b"\x28\xb5\x2f\xfd\x24"), FileType::Compressed(Compression::Zstd));
+    assert_eq!(detect(b"\x00\x01"), FileType::Unknown);
+    assert_eq!(detect(b""), FileType::Unknown);
+
+    assert!(FileType::LlvmBitcode.to_string().contains("LTO"));
+    assert!(FileType::MachO.to_string().contains("wrong platform"));
+}
+
+/// gcc -c with these flags, of a function that returns 42
+fn gcc_object(flags: &[&str]) -> Vec<u8> {
+    let out = tempfile::NamedTempFileOptions::new().suffix(".o").create().unwrap();
+    let mut gcc = Command::new("gcc")
+        .args(["-x", "c", "-c", "-o"])
+        .arg(out.path())
+        .args(flags)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap();
+    gcc.stdin.take().unwrap().write_all(b"int answer(void) { return 42; }\n").unwrap();
+    assert!(gcc.wait().unwrap().success());
+    fs::read(out.path()).unwrap()
+}
+
+type Compiler = fn(&str, &[u8]) -> Result<Vec<u8>, Error>;
+
+/// what the gcc linker plugin does with an LTO object
+fn gcc_compile(input: &[u8]) -> Result<Vec<u8>, Error> {
+    let object = || tempfile::NamedTempFileOptions::new().suffix(".o").create();
+    let (mut lto, native) = (object()?, object()?);
+    lto.write_all(input)?;
+    let status = Command::new("gcc")
+        .args(["-flto", "-r", "-nostdlib", "-flinker-output=nolto-rel", "-o"])
+        .arg(native.path())
+        .arg(lto.path())
+        .status()?;
+    assert!(status.success());
+    Ok(fs::read(native.path())?)
+}
+
+#[test]
+fn lto_objects_need_a_compiler() {
+    let parse = |data: &[u8]| Elf::from_reader(&mut Cursor::new(data)).unwrap();
+    let plain = gcc_object(&[]);
+    assert_eq!(linker::lto_kind(&parse(&plain)), None);
+    let fat = gcc_object(&["-flto", "-ffat-lto-objects"]);
+    assert_eq!(linker::lto_kind(&parse(&fat)), Some(linker::Lto::GccFat));
+    let slim = gcc_object(&["-flto"]);
+    assert_eq!(linker::lto_kind(&parse(&slim)), Some(linker::Lto::GccSlim));
+
+    // native code links as it is, without asking the compiler
+    let never = Some(|_: &str, _: &[u8]| -> Result<Vec<u8>, Error> { panic!("compiled") });
+    linker::native_object("plain.o", plain, never).unwrap();
+    linker::native_object("fat.o", fat, never).unwrap();
+
+    let none: Option<Compiler> = None;
+    match linker::native_object("slim.o", slim.clone(), none) {
+        Err(Error::LtoObject(ref name, "a gcc LTO object")) if name == "slim.o" => {}
+        r => panic!("unexpected {:?}", r.map(|_| ())),
+    }
+    match linker::native_object("main.bc", b"BC\xc0\xde\x35\x14\x00\x00".to_vec(), none) {
+        Err(Error::LtoObject(_, "LLVM bitcode")) => {}
+        r => panic!("unexpected {:?}", r.map(|_| ())),
+    }
+    // a compiler that hands back what it got doesn't help either
+    let same = Some(|_: &str, data: &[u8]| Ok(data.to_vec()));
+    assert!(linker::native_object("slim.o", slim.clone(), same).is_err());
+
+    let gcc = Some(|_: &str, data: &[u8]| gcc_compile(data));
+    let native = linker::native_object("slim.o", slim, gcc).unwrap();
+    assert_eq!(linker::lto_kind(&native), None);
+    assert!(native.sections.iter().any(|s| s.name == ".text" && s.header.size > 0));
+}
@@ -0,0 +1,175 @@
+extern crate elfkit;
+
+use elfkit::relocation::RelocationType;
+use elfkit::{linker, types, Elf, ElfBuilder, Error, GotEntry, Relocation, Section, SectionContent};
+use std::io::Cursor;
+
+fn entries() -> Vec<GotEntry> {
+    vec![
+        GotEntry::fixed(0x1234),
+        GotEntry::relocated(3, RelocationType::R_X86_64_GLOB_DAT, 0),
+        GotEntry::relocated(0, RelocationType::R_X86_64_RELATIVE, 0x2000),
+    ]
+}
+
+/// a pie with a .got and a .rela.dyn that also relocates something else
+fn build() -> Elf {
+    let rw = types::SectionFlags::ALLOC | types::SectionFlags::WRITE;
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .etype(types::ElfType::DYN)
+        .text(vec![0xc3])
+        .section(".got", types::SectionType::PROGBITS, rw, Vec::new())
+        .section(".data", types::SectionType::PROGBITS, rw, vec![0; 8])
+        .build()
+        .unwrap();
+    let i = elf.sections.iter().position(|s| s.name == ".got").unwrap();
+    elf.sections[i].content = SectionContent::Got(entries());
+    let other = Relocation {
+        addr: 0x4000,
+        rtype: RelocationType::R_X86_64_RELATIVE,
+        addend: 0x10,
+        ..Default::default()
+    };
+    elf.sections.push(Section::new(
+        String::from(".rela.dyn"),
+        types::SectionType::RELA,
+        types::SectionFlags::ALLOC,
+        SectionContent::Relocations(vec![other]),
+        0,
+        0,
+    ));
+    elf
+}
+
+fn section<'a>(elf: &'a Elf, name: &str) -> &'a Section {
+    elf.sections.iter().find(|s| s.name == name).unwrap()
+}
+
+fn relocations(elf: &Elf) -> Vec<(u64, u32, u32, i64)> {
+    section(elf, ".rela.dyn")
+        .content
+        .as_relocations()
+        .unwrap()
+        .iter()
+        .map(|r| (r.addr, r.sym, r.rtype.0, r.addend))
+        .collect()
+}
+
+#[test]
+fn entries_become_slots_and_relocations() {
+    let mut elf = build();
+    elf.sync_all().unwrap();
+    assert_eq!(section(&elf, ".got").header.size, 24);
+    assert_eq!(section(&elf, ".got").header.entsize, 8);
+    assert_eq!(section(&elf, ".rela.dyn").header.size, 3 * 24);
+
+    // moving the table moves its relocations, syncing again doesn't add more
+    linker::relayout(&mut elf, 0x300).unwrap();
+    elf.sync_all().unwrap();
+    let got = section(&elf, ".got").header.addr;
+    assert!(got > 0);
+    let expected = vec![
+        (0x4000, 0, 8, 0x10),
+        (got + 8, 3, 6, 0),
+        (got + 16, 0, 8, 0x2000),
+    ];
+    assert_eq!(relocations(&elf), expected);
+
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    let mut elf = Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap();
+    match section(&elf, ".got").content {
+        SectionContent::Raw(ref b) => {
+            assert_eq!(&b[..8], &0x1234u64.to_le_bytes());
+            assert_eq!(&b[16..], &0x2000u64.to_le_bytes());
+        }
+        ref c => panic!("unexpected {:?}", c),
+    }
+
+    // and back from a linked file
+    let i = elf.sections.iter().position(|s| s.name == ".got").unwrap();
+    elf.load_got(i).unwrap();
+    assert_eq!(elf.sections[i].content.as_got(), Some(&entries()));
+    elf.sections[i]
+        .content
+        .as_got_mut()
+        .unwrap()
+        .push(GotEntry::relocated(4, RelocationType::R_X86_64_GLOB_DAT, 0));
+    elf.sync_all().unwrap();
+    let mut expected = expected;
+    expected.push((got + 24, 4, 6, 0));
+    assert_eq!(relocations(&elf), expected);
+}
+
+#[test]
+fn plt_slots_go_to_the_section_that_points_at_them() {
+    let mut elf = build();
+    let mut got_plt = Section::new(
+        String::from(".got.plt"),
+        types::SectionType::PROGBITS,
+        types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+        SectionContent::Got(vec![GotEntry {
+            sym: 5,
+            rtype: RelocationType::R_X86_64_JUMP_SLOT,
+            addend: 0,
+            value: 0x1036,
+        }]),
+        0,
+        0,
+    );
+    got_plt.header.addr = 0x3000;
+    elf.sections.push(got_plt);
+    let p = elf.sections.len() as u32 - 1;
+    elf.sections.push(Section::new(
+        String::from(".rela.plt"),
+        types::SectionType::RELA,
+        types::SectionFlags::ALLOC | types::SectionFlags::INFO_LINK,
+        SectionContent::Relocations(Vec::new()),
+        0,
+        p,
+    ));
+    elf.sync_all().unwrap();
+    let plt = section(&elf, ".rela.plt").content.as_relocations().unwrap();
+    assert_eq!(plt.len(), 1);
+    assert_eq!((plt[0].addr, plt[0].sym), (0x3000, 5));
+    assert_eq!(relocations(&elf).len(), 3);
+}
+
+#[test]
+fn broken_tables() {
+    // relocated slots need somewhere to put the relocations
+    let mut elf = build();
+    elf.sections.pop();
+    match elf.sync_all().map_err(|e| e.root_cause().to_string()) {
+        Err(e) => assert_eq!(e, "section '.rela.dyn' not found"),
+        r => panic!("unexpected {:?}", r),
+    }
+
+    let mut elf = build();
+    let i = elf.sections.iter().position(|s| s.name == ".got").unwrap();
+    elf.sections[i].content = SectionContent::Raw(vec![0; 12]);
+    match elf.load_got(i) {
+        Err(e) => assert_eq!(
+            e.root_cause().to_string(),
+            "invalid global offset table: size is not a multiple of the pointer size"
+        ),
+        r => panic!("unexpected {:?}", r),
+    }
+
+    elf.sections[i].content = SectionContent::Raw(vec![0; 16]);
+    elf.sections[i].header.addr = 0x4000;
+    let r = elf.sections.len() - 1;
+    elf.sections[r].content.as_relocations_mut().unwrap()[0].addr = 0x4004;
+    match elf.load_got(i) {
+        Err(e) => assert_eq!(
+            e.root_cause().to_string(),
+            "invalid global offset table: relocation is not at the start of a slot"
+        ),
+        r => panic!("unexpected {:?}", r),
+    }
+    match elf.load_got(99) {
+        Err(Error::SectionIndexOutOfRange(99)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
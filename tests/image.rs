@@ -0,0 +1,132 @@
+extern crate elfkit;
+
+use elfkit::{image, types, Elf, ElfBuilder, Error, SegmentHeader};
+use std::str;
+
+/// code in rom at 0x08000000 and data that runs from ram at 0x20000000 but is loaded
+/// behind the code, as a linker script with AT> lays it out
+fn firmware(data: Vec<u8>) -> Elf {
+    let mut elf = ElfBuilder::new(types::Machine::ARM)
+        .text(vec![0x90, 0xc3])
+        .section(
+            ".data",
+            types::SectionType::PROGBITS,
+            types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+            data,
+        )
+        .entry_address(0x080000b0)
+        .build()
+        .unwrap();
+    let data = elf.sections.iter().find(|s| s.name == ".data").unwrap().header.clone();
+    let load = |offset, vaddr, paddr, size| SegmentHeader {
+        phtype: types::SegmentType::LOAD,
+        flags: types::SegmentFlags::READABLE,
+        offset,
+        vaddr,
+        paddr,
+        filesz: size,
+        memsz: size,
+        align: 4,
+    };
+    elf.segments = vec![
+        load(0, 0x08000000, 0x08000000, data.offset),
+        load(data.offset, 0x20000000, 0x08000100, data.size),
+    ];
+    elf
+}
+
+fn lines(b: &[u8]) -> Vec<&str> {
+    str::from_utf8(b).unwrap().split_terminator("\r\n").collect()
+}
+
+/// the bytes at each address of an intel hex file, after checking every checksum
+fn read_ihex(b: &[u8]) -> Vec<(u64, u8)> {
+    let mut r = Vec::new();
+    let mut upper = 0;
+    for line in lines(b) {
+        assert!(line.starts_with(':'));
+        let bytes: Vec<u8> = (1..line.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&line[i..i + 2], 16).unwrap())
+            .collect();
+        assert_eq!(bytes.iter().fold(0u8, |s, b| s.wrapping_add(*b)), 0);
+        assert_eq!(bytes[0] as usize, bytes.len() - 5);
+        let addr = (bytes[1] as u64) << 8 | bytes[2] as u64;
+        let data = &bytes[4..bytes.len() - 1];
+        match bytes[3] {
+            0 => r.extend(data.iter().enumerate().map(|(i, b)| (upper + addr + i as u64, *b))),
+            4 => upper = ((data[0] as u64) << 8 | data[1] as u64) << 16,
+            _ => {}
+        }
+    }
+    r
+}
+
+#[test]
+fn binary_starts_at_the_lowest_load_address() {
+    let elf = firmware(vec![1, 2, 3, 4]);
+    assert_eq!(image::base_address(&elf).unwrap(), 0x080000b0);
+    let mut bin = Vec::new();
+    image::write_binary(&elf, &mut bin).unwrap();
+    let mut expected = vec![0; 0x54];
+    expected[..2].copy_from_slice(&[0x90, 0xc3]);
+    expected[0x50..].copy_from_slice(&[1, 2, 3, 4]);
+    assert_eq!(bin, expected);
+}
+
+#[test]
+fn intel_hex_and_srec_records() {
+    let elf = firmware(vec![1, 2, 3, 4]);
+    let mut hex = Vec::new();
+    image::write_ihex(&elf, &mut hex).unwrap();
+    assert_eq!(
+        lines(&hex),
+        vec![
+            ":020000040800F2",
+            ":0200B00090C3FB",
+            ":0401000001020304F1",
+            ":04000005080000B03F",
+            ":00000001FF",
+        ]
+    );
+
+    let mut srec = Vec::new();
+    image::write_srec(&elf, &mut srec).unwrap();
+    assert_eq!(
+        lines(&srec),
+        vec!["S0030000FC", "S307080000B090C3ED", "S3090800010001020304E3", "S705080000B042"]
+    );
+}
+
+#[test]
+fn intel_hex_records_stay_in_their_64k() {
+    let mut elf = firmware((0..40).collect());
+    // .data starts 16 bytes before 0x08010000
+    elf.segments[1].paddr = 0x0800fff0;
+    let mut hex = Vec::new();
+    image::write_ihex(&elf, &mut hex).unwrap();
+    assert_eq!(lines(&hex).iter().filter(|l| l.starts_with(":02000004")).count(), 2);
+
+    let mut bin = Vec::new();
+    image::write_binary(&elf, &mut bin).unwrap();
+    let base = image::base_address(&elf).unwrap();
+    let from_bin: Vec<(u64, u8)> = [(0, 2), (0xfff0 - 0xb0, 40)]
+        .iter()
+        .flat_map(|&(at, n)| (at..at + n).map(|i| (base + i as u64, bin[i])))
+        .collect();
+    assert_eq!(read_ihex(&hex), from_bin);
+}
+
+#[test]
+fn addresses_past_4g() {
+    let mut elf = firmware(vec![1, 2, 3, 4]);
+    elf.segments[1].paddr = 0x1_0000_0000;
+    match image::write_ihex(&elf, &mut Vec::new()) {
+        Err(Error::AddressOutOfRange("intel hex", 0x1_0000_0003)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+    match image::write_srec(&elf, &mut Vec::new()) {
+        Err(Error::AddressOutOfRange("s-records", 0x1_0000_0003)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
@@ -0,0 +1,126 @@
+#![cfg(feature = "goblin")]
+extern crate elfkit;
+extern crate goblin;
+
+use elfkit::relocation::RelocationType;
+use elfkit::{
+    types, Elf, ElfBuilder, Relocation, Section, SectionHeader, SegmentHeader, Symbol,
+    SymbolSectionIndex,
+};
+use std::io::Cursor;
+
+fn sample() -> Vec<u8> {
+    let start = Symbol {
+        name: String::from("_start"),
+        bind: types::SymbolBind::GLOBAL,
+        stype: types::SymbolType::FUNC,
+        ..Symbol::default()
+    };
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0x90, 0xc3])
+        .section(
+            ".data",
+            types::SectionType::PROGBITS,
+            types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+            vec![1, 2, 3, 4],
+        )
+        .symbol(".text", start)
+        .entry("_start")
+        .build()
+        .unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    io.into_inner()
+}
+
+#[test]
+fn goblin_views_convert_to_what_elfkit_parses() {
+    let bytes = sample();
+    let mut elf = Elf::from_reader(&mut Cursor::new(&bytes)).unwrap();
+    let g = goblin::elf::Elf::parse(&bytes).unwrap();
+
+    assert_eq!(g.section_headers.len(), elf.sections.len());
+    for (gh, sec) in g.section_headers.iter().zip(elf.sections.iter()) {
+        let converted = Section::from_goblin(gh, &g.shdr_strtab, &bytes).unwrap();
+        assert_eq!(converted.name, sec.name);
+        assert_eq!(format!("{:?}", converted.header), format!("{:?}", sec.header));
+        assert_eq!(format!("{:?}", converted.content), format!("{:?}", sec.content));
+        let back = goblin::elf::SectionHeader::from(&sec.header);
+        assert!(back == *gh);
+    }
+    for (ph, seg) in g.program_headers.iter().zip(elf.segments.iter()) {
+        assert_eq!(format!("{:?}", SegmentHeader::from(ph)), format!("{:?}", seg));
+    }
+
+    elf.load_all().unwrap();
+    let symtab = elf.sections.iter().find(|s| s.name == ".symtab").unwrap();
+    let syms = symtab.content.as_symbols().unwrap();
+    assert_eq!(g.syms.len(), syms.len());
+    for (gs, sym) in g.syms.iter().zip(syms.iter()) {
+        let converted = Symbol::from_goblin(gs, &g.strtab).unwrap();
+        assert_eq!(format!("{:?}", converted), format!("{:?}", sym));
+        let mut back = goblin::elf::Sym::from(sym);
+        back.st_name = gs.st_name;
+        assert!(back == *gs);
+    }
+    assert!(syms.iter().any(|s| s.name == "_start"));
+}
+
+#[test]
+fn relocations_round_trip() {
+    let r = Relocation {
+        addr: 0x2000,
+        sym: 3,
+        rtype: RelocationType::R_X86_64_GLOB_DAT,
+        addend: -4,
+    };
+    let g = goblin::elf::Reloc::from(&r);
+    assert_eq!((g.r_offset, g.r_sym, g.r_type, g.r_addend), (0x2000, 3, 6, -4));
+    assert_eq!(format!("{:?}", Relocation::from(&g)), format!("{:?}", r));
+}
+
+#[test]
+fn broken_goblin_views() {
+    let bytes = sample();
+    let g = goblin::elf::Elf::parse(&bytes).unwrap();
+    let mut sh = g.section_headers[1].clone();
+    sh.sh_offset = bytes.len() as u64;
+    match Section::from_goblin(&sh, &g.shdr_strtab, &bytes) {
+        Err(e) => assert_eq!(
+            e.to_string(),
+            format!("can not seek to offset {:#x} of section '.text'", bytes.len())
+        ),
+        r => panic!("unexpected {:?}", r.map(|s| s.name)),
+    }
+    sh.sh_name = 9999;
+    match Section::from_goblin(&sh, &g.shdr_strtab, &bytes) {
+        Err(elfkit::Error::InvalidStrtabOffset(9999)) => {}
+        r => panic!("unexpected {:?}", r.map(|s| s.name)),
+    }
+
+    let null = SectionHeader::from(&g.section_headers[0]);
+    assert_eq!(null.shtype, types::SectionType::NULL);
+}
+
+#[test]
+fn nothing_is_lost_on_the_way() {
+    let bytes = sample();
+    let g = goblin::elf::Elf::parse(&bytes).unwrap();
+
+    // PF_R | PF_X and a bit of PF_MASKOS
+    let mut ph = g.program_headers[0].clone();
+    ph.p_flags = 0x0010_0005;
+    assert_eq!(SegmentHeader::from(&ph).flags.bits(), 0x0010_0005);
+
+    // in a file with more sections than fit in st_shndx
+    let sym = Symbol {
+        name: String::from("far"),
+        shndx: SymbolSectionIndex::Section(70000),
+        ..Symbol::default()
+    };
+    let mut gs = goblin::elf::Sym::from(&sym);
+    assert_eq!(gs.st_shndx, 70000);
+    gs.st_name = g.syms.iter().find(|s| s.st_name > 0).unwrap().st_name;
+    let back = Symbol::from_goblin(&gs, &g.strtab).unwrap();
+    assert_eq!(back.shndx, SymbolSectionIndex::Section(70000));
+}
@@ -0,0 +1,131 @@
+extern crate elfkit;
+
+use elfkit::relocation::RelocationType;
+use elfkit::{types, Error, Header, Relocation, SectionContent, Strtab, Symbol,
+             SymbolSectionIndex};
+
+fn header() -> Header {
+    Header {
+        machine: types::Machine::X86_64,
+        ident_class: types::Class::Class64,
+        ident_endianness: types::Endianness::LittleEndian,
+        ..Header::default()
+    }
+}
+
+fn relocations(eh: &Header, n: u64) -> Vec<u8> {
+    let mut raw = Vec::new();
+    for i in 0..n {
+        let r = Relocation {
+            addr: 0x1000 + 8 * i,
+            sym: i as u32,
+            rtype: RelocationType::R_X86_64_64,
+            addend: -(i as i64),
+        };
+        r.to_writer(&mut raw, None, eh).unwrap();
+    }
+    raw
+}
+
+#[test]
+fn relocations_decode_one_at_a_time() {
+    let eh = header();
+    let raw = relocations(&eh, 1000);
+    let all = match Relocation::from_reader(&raw[..], None, &eh).unwrap() {
+        SectionContent::Relocations(v) => v,
+        _ => unreachable!(),
+    };
+    let mut iter = Relocation::iter(&raw, &eh).unwrap();
+    let third = iter.nth(2).unwrap().unwrap();
+    assert_eq!((third.addr, third.sym, third.addend), (0x1010, 2, -2));
+    assert_eq!(iter.count(), 997);
+    let streamed: Vec<Relocation> = Relocation::iter(&raw, &eh)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(format!("{:?}", streamed), format!("{:?}", all));
+
+    // REL entries are two words, without an addend
+    let r: Vec<_> = Relocation::iter_rel(&raw[..16], &eh).unwrap().collect();
+    assert_eq!(r.len(), 1);
+    assert_eq!(r[0].as_ref().unwrap().addend, 0);
+}
+
+#[test]
+fn relocation_errors_dont_end_the_iteration() {
+    let eh = header();
+    let mut raw = relocations(&eh, 3);
+    // an unknown type in the middle
+    raw[24 + 8] = 0xff;
+    let r: Vec<_> = Relocation::iter(&raw, &eh).unwrap().collect();
+    assert_eq!(r.len(), 3);
+    match r[1] {
+        Err(Error::InvalidRelocationType(0xff)) => {}
+        ref e => panic!("unexpected {:?}", e),
+    }
+    assert_eq!(r[2].as_ref().unwrap().sym, 2);
+
+    // an entry without its addend
+    raw.truncate(24 * 3 - 4);
+    let r: Vec<_> = Relocation::iter(&raw, &eh).unwrap().collect();
+    assert_eq!(r.len(), 3);
+    assert!(r[2].is_err());
+
+    let mut mips64 = header();
+    mips64.machine = types::Machine::MIPS;
+    match Relocation::iter(&raw, &mips64) {
+        Err(Error::UnsupportedMachineTypeForRelocation(_)) => {}
+        r => panic!("unexpected {:?}", r.map(|i| i.count())),
+    }
+}
+
+#[test]
+fn symbols_decode_one_at_a_time() {
+    let eh = header();
+    let mut strtab = SectionContent::Strtab(Strtab::default());
+    let mut raw = Vec::new();
+    for i in 0..100 {
+        let sym = Symbol {
+            name: format!("sym{}", i),
+            value: i,
+            shndx: SymbolSectionIndex::Section(1),
+            bind: types::SymbolBind::GLOBAL,
+            ..Symbol::default()
+        };
+        sym.to_writer(&mut raw, Some(&mut strtab), &eh).unwrap();
+    }
+    let tab = match strtab {
+        SectionContent::Strtab(ref t) => t,
+        _ => unreachable!(),
+    };
+
+    let mut iter = Symbol::iter(&raw, Some(tab), &eh);
+    assert_eq!(iter.len(), 100);
+    let sym = iter.nth(42).unwrap().unwrap();
+    assert_eq!((sym.name.as_str(), sym.value), ("sym42", 42));
+    assert_eq!(iter.len(), 57);
+
+    let all = match Symbol::from_reader(&raw[..], Some(&strtab), &eh).unwrap() {
+        SectionContent::Symbols(v) => v,
+        _ => unreachable!(),
+    };
+    let streamed: Vec<Symbol> = Symbol::iter(&raw, Some(tab), &eh)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(format!("{:?}", streamed), format!("{:?}", all));
+
+    // without a string table names are empty, a partial entry at the end is left out
+    let unnamed: Vec<_> = Symbol::iter(&raw[..24 * 2 + 5], None, &eh).collect();
+    assert_eq!(unnamed.len(), 2);
+    assert_eq!(unnamed[1].as_ref().unwrap().name, "");
+
+    // SHN_XINDEX goes through the extended index table
+    raw[6..8].copy_from_slice(&types::SHN_XINDEX.to_le_bytes());
+    let xindex = 0x10000u32.to_le_bytes();
+    let sym = Symbol::iter(&raw, Some(tab), &eh).xindex(&xindex).next().unwrap().unwrap();
+    assert_eq!(sym.shndx, SymbolSectionIndex::Section(0x10000));
+    match Symbol::iter(&raw, Some(tab), &eh).next().unwrap() {
+        Err(Error::InvalidSymbolShndx(ref name, _)) if name == "sym0" => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
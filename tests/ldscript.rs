@@ -0,0 +1,88 @@
+extern crate elfkit;
+
+use elfkit::filetype::{self, FileType};
+use elfkit::ldscript::{self, LinkerScript, ScriptInput};
+use elfkit::linker::MemoryRegion;
+use std::io::Cursor;
+
+const LIBC_SO: &str = "/* GNU ld script
+   Use the shared library, but some functions are only in
+   the static library, so try that secondarily.  */
+OUTPUT_FORMAT(elf64-x86-64)
+GROUP ( /lib/x86_64-linux-gnu/libc.so.6 /usr/lib/x86_64-linux-gnu/libc_nonshared.a  \
+AS_NEEDED ( /lib64/ld-linux-x86-64.so.2 ) )
+";
+
+fn input(name: &str, library: bool, as_needed: bool) -> ScriptInput {
+    ScriptInput {
+        name: String::from(name),
+        library,
+        as_needed,
+    }
+}
+
+#[test]
+fn glibc_libc_so() {
+    match filetype::filetype(Cursor::new(LIBC_SO.as_bytes())).unwrap() {
+        FileType::LinkerScript => {}
+        _ => panic!("libc.so not detected as a linker script"),
+    }
+
+    let script = LinkerScript::parse(LIBC_SO).unwrap();
+    assert_eq!(
+        script.inputs,
+        vec![
+            input("/lib/x86_64-linux-gnu/libc.so.6", false, false),
+            input("/usr/lib/x86_64-linux-gnu/libc_nonshared.a", false, false),
+            input("/lib64/ld-linux-x86-64.so.2", false, true),
+        ]
+    );
+}
+
+#[test]
+fn libraries_search_dirs_and_errors() {
+    let script =
+        LinkerScript::parse("SEARCH_DIR(\"/opt/lib\"); INPUT(-lm, crt1.o) GROUP(-lc)").unwrap();
+    assert_eq!(script.search_dirs, vec![String::from("/opt/lib")]);
+    assert_eq!(
+        script.inputs,
+        vec![input("m", true, false), input("crt1.o", false, false), input("c", true, false)]
+    );
+
+    assert!(LinkerScript::parse("SECTIONS { .text : { *(.text) } }").is_err());
+    assert!(LinkerScript::parse("GROUP ( libc.so.6 ").is_err());
+    assert!(LinkerScript::parse("/* never closed").is_err());
+
+    match filetype::filetype(Cursor::new(&b"hello world\n"[..])).unwrap() {
+        FileType::Unknown => {}
+        _ => panic!("plain text detected as a linker script"),
+    }
+}
+
+#[test]
+fn memory_regions() {
+    let text = "MEMORY\n{\n  FLASH (rx) : ORIGIN = 0x08000000, LENGTH = 256K\n  \
+                RAM(rwx):org=0x20000000,len=0100\n}\nINPUT(-l:libc.a)";
+    let script = LinkerScript::parse(text).unwrap();
+    assert_eq!(
+        script.memory,
+        vec![
+            MemoryRegion {
+                name: String::from("FLASH"),
+                origin: 0x08000000,
+                length: 256 * 1024,
+            },
+            MemoryRegion {
+                name: String::from("RAM"),
+                origin: 0x20000000,
+                length: 64,
+            },
+        ]
+    );
+    assert_eq!(script.inputs, vec![input(":libc.a", true, false)]);
+    assert!(ldscript::is_linker_script(b"/* stm32 */ MEMORY {"));
+
+    assert!(LinkerScript::parse("MEMORY { FLASH : ORIGIN = 0x0 }").is_err());
+    assert!(LinkerScript::parse("MEMORY { FLASH : ORIGIN = 0x0, LENGTH = 12Q }").is_err());
+    assert!(LinkerScript::parse("MEMORY { FLASH : ORIGIN = 0x0, LENGTH = 1K").is_err());
+}
@@ -0,0 +1,3 @@
+// one byte each, so whatever comes behind them is only aligned if the linker pads
+char odd_bss;
+char odd_data = 1;
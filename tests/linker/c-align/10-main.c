@@ -0,0 +1,15 @@
+#include <stdio.h>
+#include <stdint.h>
+
+__attribute__((aligned(64))) char bss_buf[64];
+__attribute__((aligned(32))) char data_buf[3] = {1};
+__attribute__((common, aligned(16))) long common_slot;
+
+extern char odd_bss, odd_data;
+
+int main(int argc, char**argv){
+    // through volatile, or the compiler knows the answer from the declarations
+    volatile uintptr_t at[] = {(uintptr_t)bss_buf, (uintptr_t)data_buf, (uintptr_t)&common_slot};
+    printf("%d %d %d\n", (int)(at[0] % 64), (int)(at[1] % 32), (int)(at[2] % 16));
+    return odd_bss + odd_data - 1;
+}
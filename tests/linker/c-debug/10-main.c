@@ -0,0 +1,10 @@
+#include <stdio.h>
+
+static int square(int x) {
+    return x * x;
+}
+
+int main(int argc, char**argv){
+    printf("%d\n", square(argc + 5));
+    return 42;
+}
@@ -0,0 +1,13 @@
+#include <stdio.h>
+
+__attribute__((noinline)) int f1(int x) { return x + 1; }
+__attribute__((noinline)) int f2(int x) { return x * 2; }
+
+// an entry for each function that nothing refers to, the way .ARM.exidx has them
+__asm__(".section meta,\"ao\",@progbits,f1,unique,1\n.ascii \"LINKORDER1\"\n.previous");
+__asm__(".section meta,\"ao\",@progbits,f2,unique,2\n.ascii \"LINKORDER2\"\n.previous");
+
+int main() {
+    printf("%d\n", f2(f1(20)));
+    return 0;
+}
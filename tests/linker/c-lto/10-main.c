@@ -0,0 +1,8 @@
+#include <stdio.h>
+
+int answer(int x);
+
+int main(int argc, char**argv){
+    printf("%d\n", answer(argc));
+    return 0;
+}
@@ -0,0 +1,4 @@
+// compiled with -flto, so the object has gcc's intermediate language and no code
+int answer(int x) {
+    return x * 41 + 1;
+}
@@ -0,0 +1,15 @@
+#include <stdio.h>
+
+// a few pages, so it reaches well past the end of the got
+char zeros[3 * 4096 + 17];
+extern int answer;
+
+int main(int argc, char**argv){
+    int sum = 0;
+    for (unsigned i = 0; i < sizeof(zeros); i++) {
+        sum += zeros[i];
+    }
+    // answer is in another object, so the compiler goes through the got
+    printf("%d %d\n", sum, answer);
+    return 0;
+}
@@ -0,0 +1,2 @@
+// nothing refers to this object, only retain makes it part of the link
+__attribute__((used, retain, section(".rodata.tag"))) static const char tag[] = "RETAINED-TAG";
@@ -0,0 +1,30 @@
+// what rcrt1 does before main, without a libc: apply the R_X86_64_RELATIVE entries
+// that .dynamic points at, relative to where the kernel loaded the file
+typedef struct { unsigned long tag, val; } Dyn;
+typedef struct { unsigned long offset, info; long addend; } Rela;
+
+extern Dyn _DYNAMIC[] __attribute__((visibility("hidden")));
+extern char __ehdr_start[] __attribute__((visibility("hidden")));
+int main(void);
+
+__attribute__((force_align_arg_pointer, noreturn))
+void _start(void) {
+    unsigned long base = (unsigned long)__ehdr_start;
+    Rela *rela = 0;
+    unsigned long size = 0;
+    for (Dyn *d = _DYNAMIC; d->tag; d++) {
+        if (d->tag == 7) {
+            rela = (Rela *)(base + d->val);
+        } else if (d->tag == 8) {
+            size = d->val;
+        }
+    }
+    for (unsigned long i = 0; i < size / sizeof(Rela); i++) {
+        if ((rela[i].info & 0xffffffff) == 8) {
+            *(unsigned long *)(base + rela[i].offset) = base + rela[i].addend;
+        }
+    }
+    long code = main();
+    __asm__ volatile("syscall" :: "a"(231), "D"(code));
+    __builtin_unreachable();
+}
@@ -0,0 +1,20 @@
+// pointers in data and a got slot, which are only right once the relocations are applied
+extern const char *greeting;
+static const char *const parts[] = {"static", "-", "pie", "\n"};
+
+static void put(const char *s) {
+    unsigned long n = 0;
+    while (s[n]) {
+        n++;
+    }
+    long r;
+    __asm__ volatile("syscall" : "=a"(r) : "a"(1), "D"(1), "S"(s), "d"(n) : "rcx", "r11", "memory");
+}
+
+int main(void) {
+    put(greeting);
+    for (unsigned i = 0; i < sizeof(parts) / sizeof(parts[0]); i++) {
+        put(parts[i]);
+    }
+    return 0;
+}
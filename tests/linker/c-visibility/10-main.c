@@ -0,0 +1,14 @@
+#include <stdio.h>
+
+// defined with default visibility, this reference makes it hidden in the output
+extern int counter __attribute__((visibility("hidden")));
+
+int bump(void);
+int secret(void);
+
+int main(int argc, char**argv){
+    bump();
+    bump();
+    printf("%d %d\n", counter, secret());
+    return 0;
+}
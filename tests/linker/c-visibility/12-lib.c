@@ -0,0 +1,9 @@
+int counter;
+
+__attribute__((visibility("hidden"))) int secret(void){
+    return 7;
+}
+
+__attribute__((visibility("protected"))) int bump(void){
+    return ++counter;
+}
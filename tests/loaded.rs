@@ -0,0 +1,94 @@
+extern crate elfkit;
+
+use elfkit::error::Location;
+use elfkit::{types, Dynamic, DynamicContent, Elf, ElfBuilder, Error, SectionContent, Strtab,
+             Symbol};
+use std::convert::TryFrom;
+use std::io::Cursor;
+
+fn sample() -> Elf {
+    let start = Symbol {
+        name: String::from("_start"),
+        bind: types::SymbolBind::GLOBAL,
+        stype: types::SymbolType::FUNC,
+        ..Symbol::default()
+    };
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0x90, 0xc3])
+        .symbol(".text", start)
+        .entry("_start")
+        .build()
+        .unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap()
+}
+
+fn index(elf: &Elf, name: &str) -> usize {
+    elf.sections.iter().position(|s| s.name == name).unwrap()
+}
+
+#[test]
+fn content_is_loaded_before_it_is_handed_out() {
+    let mut elf = sample();
+    let symtab = index(&elf, ".symtab");
+    assert!(elf.sections[symtab].content.as_symbols().is_none());
+
+    let symbols = elf.loaded_section(symtab).unwrap().symbols().unwrap();
+    assert!(symbols.iter().any(|s| s.name == "_start"));
+    // along with the string table it links to
+    let strtab = index(&elf, ".strtab");
+    assert!(elf.loaded_section(strtab).unwrap().strtab().is_ok());
+    assert_eq!(elf.loaded_section(index(&elf, ".text")).unwrap().raw().unwrap(), &[0x90, 0xc3]);
+}
+
+#[test]
+fn unexpected_content_is_an_error() {
+    let mut elf = sample();
+    let text = index(&elf, ".text");
+    match elf.loaded_section(text).unwrap().symbols() {
+        Err(Error::At(Location::Section { index, ref name, .. }, ref e)) => {
+            assert_eq!((index, name.as_ref()), (text, ".text"));
+            match **e {
+                Error::UnexpectedSectionContent => {}
+                ref e => panic!("unexpected {:?}", e),
+            }
+        }
+        r => panic!("unexpected {:?}", r.map(|_| ())),
+    }
+    let n = elf.sections.len();
+    match elf.loaded_section(n) {
+        Err(Error::SectionIndexOutOfRange(i)) => assert_eq!(i, n),
+        r => panic!("unexpected {:?}", r.map(|_| ())),
+    }
+}
+
+#[test]
+fn accessors_and_conversions_match_the_variant() {
+    let null = Dynamic {
+        dhtype: types::DynamicType::NULL,
+        content: DynamicContent::None,
+    };
+    let mut dynamic = SectionContent::Dynamic(vec![null]);
+    assert_eq!(dynamic.as_dynamic().map(|d| d.len()), Some(1));
+    assert!(dynamic.as_dynamic_mut().is_some());
+    assert!(dynamic.as_strtab().is_none() && dynamic.as_raw().is_none());
+    assert_eq!(dynamic.clone().into_dynamic().map(|d| d.len()), Some(1));
+    match Vec::<Symbol>::try_from(dynamic.clone()) {
+        Err(Error::UnexpectedSectionContent) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+    assert_eq!(Vec::<Dynamic>::try_from(dynamic).unwrap().len(), 1);
+
+    let mut strtab = Strtab::default();
+    strtab.insert(b"main".to_vec());
+    let strtab = SectionContent::Strtab(strtab);
+    assert_eq!(strtab.as_strtab().unwrap().get(1), "main");
+    assert!(strtab.clone().into_got().is_none());
+    assert_eq!(Strtab::try_from(strtab).unwrap().get(1), "main");
+
+    let raw = SectionContent::Raw(vec![1, 2]);
+    assert_eq!(raw.as_raw().unwrap(), &[1, 2]);
+    assert_eq!(Vec::<u8>::try_from(raw).unwrap(), vec![1, 2]);
+    assert!(Vec::<u8>::try_from(SectionContent::None).is_err());
+}
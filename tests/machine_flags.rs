@@ -0,0 +1,132 @@
+extern crate elfkit;
+
+use elfkit::{types, ArmFloat, Error, Header, MachineFlags, MipsAbi, MipsArch, RiscvFloatAbi};
+
+fn header(machine: types::Machine, class: types::Class, flags: u32) -> Header {
+    Header {
+        machine,
+        ident_class: class,
+        flags: types::HeaderFlags::from_bits_retain(flags),
+        ..Header::default()
+    }
+}
+
+fn merged(a: &Header, b: &Header) -> Result<MachineFlags, Error> {
+    let mut flags = a.machine_flags();
+    flags.merge(&b.machine_flags())?;
+    Ok(flags)
+}
+
+#[test]
+fn arm_eabi_and_float_abi() {
+    let hard = header(types::Machine::ARM, types::Class::Class32, 0x05000400);
+    assert_eq!(
+        hard.machine_flags(),
+        MachineFlags::Arm {
+            eabi: Some(5),
+            float: ArmFloat::Hard,
+            be8: false,
+        }
+    );
+    // assembly without float code doesn't say
+    let plain = header(types::Machine::ARM, types::Class::Class32, 0x05000000);
+    assert_eq!(merged(&plain, &hard).unwrap(), hard.machine_flags());
+
+    let soft = header(types::Machine::ARM, types::Class::Class32, 0x05000200);
+    match merged(&hard, &soft) {
+        Err(Error::IncompatibleMachineFlags("the arm float abi")) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+    let old = header(types::Machine::ARM, types::Class::Class32, 0x04000000);
+    match merged(&plain, &old) {
+        Err(Error::IncompatibleMachineFlags("the arm EABI version")) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+    let x86 = header(types::Machine::X86_64, types::Class::Class64, 0);
+    match merged(&plain, &x86) {
+        Err(Error::IncompatibleMachineFlags("the machine")) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[test]
+fn riscv_float_abi() {
+    // rv64gc, lp64d
+    let gc = header(types::Machine::RISCV, types::Class::Class64, 0x5);
+    let g = header(types::Machine::RISCV, types::Class::Class64, 0x4);
+    let r = merged(&g, &gc).unwrap();
+    assert_eq!(
+        r,
+        MachineFlags::RiscV {
+            float_abi: RiscvFloatAbi::Double,
+            rvc: true,
+            rve: false,
+            tso: false,
+        }
+    );
+    let mut out = header(types::Machine::RISCV, types::Class::Class64, 0);
+    out.set_machine_flags(&r).unwrap();
+    assert_eq!(out.flags.bits(), 0x5);
+
+    let soft = header(types::Machine::RISCV, types::Class::Class64, 0x1);
+    match merged(&gc, &soft) {
+        Err(Error::IncompatibleMachineFlags("the risc-v float abi")) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[test]
+fn mips_levels_and_abis() {
+    // o32, arch 32r2, pic, cpic, noreorder and nan2008
+    let eh = header(types::Machine::MIPS, types::Class::Class32, 0x70001407);
+    let flags = eh.machine_flags();
+    assert_eq!(
+        flags,
+        MachineFlags::Mips {
+            arch: MipsArch::Mips32R2,
+            abi: MipsAbi::O32,
+            pic: true,
+            cpic: true,
+            noreorder: true,
+            nan2008: true,
+            fp64: false,
+        }
+    );
+    let mut again = header(types::Machine::MIPS, types::Class::Class32, 0x0000_0800);
+    again.set_machine_flags(&flags).unwrap();
+    // bits that aren't decoded stay, and o32 stays without the abi field it didn't have
+    assert_eq!(again.flags.bits(), 0x70000c07);
+
+    // o32 with the abi field and without it comes back unchanged
+    for &bits in &[0x7000_1007u32, 0x7000_0007, 0x1000_0000] {
+        let mut eh = header(types::Machine::MIPS, types::Class::Class32, bits);
+        let flags = eh.machine_flags();
+        match flags {
+            MachineFlags::Mips { abi: MipsAbi::O32, .. } => {}
+            r => panic!("unexpected {:?}", r),
+        }
+        eh.set_machine_flags(&flags).unwrap();
+        assert_eq!(eh.flags.bits(), bits);
+    }
+    // a 64 bit file needs the field for o32, since without it it's n64
+    let mut eh = header(types::Machine::MIPS, types::Class::Class64, 0x6000_0000);
+    eh.set_machine_flags(&flags).unwrap();
+    assert_eq!(eh.flags.bits() & 0xf000, 0x1000);
+
+    // mips3 and mips32 code together needs mips64, non pic code makes all of it non pic
+    let n64 = |flags| header(types::Machine::MIPS, types::Class::Class64, flags);
+    match merged(&n64(0x20000006), &n64(0x50000004)).unwrap() {
+        MachineFlags::Mips { arch, abi, pic, cpic, .. } => {
+            assert_eq!((arch, abi, pic, cpic), (MipsArch::Mips64, MipsAbi::N64, false, true))
+        }
+        r => panic!("unexpected {:?}", r),
+    }
+    match merged(&n64(0xa0000000), &n64(0x20000000)) {
+        Err(Error::IncompatibleMachineFlags("the mips isa level")) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+    match merged(&n64(0x20000000), &n64(0x20000020)) {
+        Err(Error::IncompatibleMachineFlags("the mips abi")) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
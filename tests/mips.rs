@@ -0,0 +1,75 @@
+extern crate elfkit;
+
+use elfkit::{linker, types, Elf, ElfBuilder, SectionContent, Symbol};
+use elfkit::relocation::RelocationType;
+use std::io::Cursor;
+
+fn reparse(elf: &mut Elf) -> Elf {
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    let mut elf = Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap();
+    elf.load_all().unwrap();
+    elf
+}
+
+fn section<'a>(elf: &'a Elf, name: &str) -> &'a SectionContent {
+    &elf.sections.iter().find(|s| s.name == name).unwrap().content
+}
+
+#[test]
+fn rel_sections_and_flags_round_trip() {
+    // r_offset 0x10, r_info sym 1 type R_MIPS_REL32, big endian
+    let rel = vec![0, 0, 0, 0x10, 0, 0, 0x01, 0x03];
+    let mut elf = ElfBuilder::new(types::Machine::MIPS)
+        .class(types::Class::Class32)
+        .endianness(types::Endianness::BigEndian)
+        .text(vec![0x03, 0xe0, 0x00, 0x08, 0, 0, 0, 0])
+        .section(
+            ".rel.dyn",
+            types::SectionType::REL,
+            types::SectionFlags::ALLOC,
+            rel,
+        )
+        .build()
+        .unwrap();
+    // o32, arch 32r2, pic, cpic, noreorder, and nan2008 which is not named on read
+    elf.header.flags = types::HeaderFlags::from_bits_retain(0x70001407);
+
+    let mut elf = reparse(&mut elf);
+    assert_eq!(elf.header.flags.bits(), 0x70001407);
+    match section(&elf, ".rel.dyn") {
+        &SectionContent::Relocations(ref r) => {
+            assert_eq!(r.len(), 1);
+            assert_eq!(r[0].addr, 0x10);
+            assert_eq!(r[0].sym, 1);
+            assert_eq!(r[0].rtype, RelocationType::R_MIPS_REL32);
+            assert_eq!(r[0].rtype.typename(&elf.header), Some("R_MIPS_REL32"));
+        }
+        _ => panic!(".rel.dyn not loaded as relocations"),
+    }
+
+    let elf = reparse(&mut elf);
+    let sec = elf.sections.iter().find(|s| s.name == ".rel.dyn").unwrap();
+    assert_eq!(sec.header.entsize, 8);
+    assert_eq!(sec.header.size, 8);
+}
+
+fn sym(name: &str) -> Symbol {
+    let mut s = Symbol::default();
+    s.name = String::from(name);
+    s.bind = types::SymbolBind::GLOBAL;
+    s
+}
+
+#[test]
+fn dynsym_ends_with_got_symbols() {
+    let mut symbols = vec![Symbol::default(), sym("puts"), sym("main"), sym("exit"), sym("data")];
+    let got = vec![String::from("exit"), String::from("puts")];
+    let (gotsym, remap) = linker::mips_order_dynsym(&mut symbols, &got);
+
+    let names: Vec<&str> = symbols.iter().map(|s| s.name.as_ref()).collect();
+    assert_eq!(names, vec!["", "main", "data", "exit", "puts"]);
+    assert_eq!(gotsym, 3);
+    assert_eq!(remap, vec![0, 4, 1, 3, 2]);
+}
@@ -0,0 +1,95 @@
+extern crate elfkit;
+
+use elfkit::{types, Elf, ElfBuilder, NormalizeOptions, SectionContent, Symbol};
+use std::io::Cursor;
+
+fn note(owner: &[u8], ntype: u32, desc: &[u8], padding: u8) -> Vec<u8> {
+    let mut b = Vec::new();
+    b.extend_from_slice(&(owner.len() as u32).to_le_bytes());
+    b.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    b.extend_from_slice(&ntype.to_le_bytes());
+    b.extend_from_slice(owner);
+    while b.len() % 4 != 0 {
+        b.push(padding);
+    }
+    b.extend_from_slice(desc);
+    while b.len() % 4 != 0 {
+        b.push(padding);
+    }
+    b
+}
+
+/// the same program, built in dir
+fn build(dir: &str, build_id: u8) -> Elf {
+    let mut file = Symbol::default();
+    file.name = format!("{}/src/main.c", dir);
+    file.stype = types::SymbolType::FILE;
+    file.shndx = elfkit::SymbolSectionIndex::Absolute;
+    let mut notes = note(b"GNU\0", 3, &[build_id; 20], 0);
+    notes.extend(note(b"Go\0", 4, b"abcdef", build_id));
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .section(
+            ".note",
+            types::SectionType::NOTE,
+            types::SectionFlags::ALLOC,
+            notes,
+        )
+        .symbol(".text", file)
+        .build()
+        .unwrap();
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap()
+}
+
+fn normalized(mut elf: Elf, dir: &str) -> Vec<u8> {
+    let mut options = NormalizeOptions::default();
+    options.prefix_map.push((String::from(dir), String::from("/build")));
+    elf.normalize(&options).unwrap();
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    io.into_inner()
+}
+
+#[test]
+fn builds_in_different_places_become_identical() {
+    let raw_a = normalized(build("/home/me/project", 1), "/home/me/project");
+    let raw_b = normalized(build("/tmp/b", 2), "/tmp/b");
+    assert_eq!(raw_a, raw_b);
+
+    let mut elf = Elf::from_reader(&mut Cursor::new(raw_a)).unwrap();
+    elf.load_all().unwrap();
+    let symtab = elf.sections.iter().find(|s| s.name == ".symtab").unwrap();
+    let names: Vec<&str> = symtab
+        .content
+        .as_symbols()
+        .unwrap()
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect();
+    assert!(names.contains(&"/build/src/main.c"));
+    match elf.sections.iter().find(|s| s.name == ".strtab").unwrap().content {
+        SectionContent::Strtab(ref s) => assert!(s.iter().all(|(_, s)| !s.starts_with(b"/tmp"))),
+        ref c => panic!("unexpected {:?}", c),
+    }
+
+    // the build id is zeroed, the other note only loses its padding
+    let mut expected = note(b"GNU\0", 3, &[0; 20], 0);
+    expected.extend(note(b"Go\0", 4, b"abcdef", 0));
+    match elf.sections.iter().find(|s| s.name == ".note").unwrap().content {
+        SectionContent::Raw(ref v) => assert_eq!(v, &expected),
+        ref c => panic!("unexpected {:?}", c),
+    }
+}
+
+#[test]
+fn broken_notes() {
+    let mut elf = build("/src", 1);
+    let i = elf.sections.iter().position(|s| s.name == ".note").unwrap();
+    elf.sections[i].content = SectionContent::Raw(vec![4, 0, 0, 0, 100, 0, 0, 0]);
+    let e = elf.normalize(&NormalizeOptions::default()).unwrap_err();
+    assert_eq!(e.root_cause().to_string(), "invalid note: note header is truncated");
+}
@@ -0,0 +1,56 @@
+extern crate elfkit;
+
+use elfkit::{linker, types, Symbol, SymbolSectionIndex};
+
+fn defined(name: &str) -> Symbol {
+    Symbol {
+        name: String::from(name),
+        shndx: SymbolSectionIndex::Global(1),
+        stype: types::SymbolType::FUNC,
+        ..Symbol::default()
+    }
+}
+
+#[test]
+fn ordering_file() {
+    let text = "  hot_loop \n# the rest of the fast path\nparse # inline\n\nhot_loop\ncold";
+    let order = linker::symbol_ordering(text);
+    assert_eq!(order.len(), 3);
+    assert_eq!((order["hot_loop"], order["parse"], order["cold"]), (0, 1, 2));
+}
+
+#[test]
+fn sections_are_placed_by_their_first_listed_symbol() {
+    let order = linker::symbol_ordering("main\nparse\ncold\n");
+    let priority = |syms: &[Symbol]| linker::ordering_priority(&order, syms);
+    assert_eq!(priority(&[defined("helper"), defined("cold"), defined("parse")]), 1);
+    assert_eq!(priority(&[defined("helper")]), usize::MAX);
+
+    // a section that only references main doesn't go where main does
+    let mut reference = defined("main");
+    reference.shndx = SymbolSectionIndex::Undefined;
+    assert_eq!(priority(&[reference, defined("cold")]), 2);
+}
+
+#[test]
+fn input_sections_go_where_gnu_ld_puts_them() {
+    let out = |name| linker::output_section(name, true);
+    assert_eq!(out(".text.unlikely.cold_path"), Some(".text.unlikely"));
+    assert_eq!(out(".text.startup_unlikely"), Some(".text.unlikely"));
+    assert_eq!(out(".text.hot.loop"), Some(".text.hot"));
+    assert_eq!(out(".text.hotter"), Some(".text"));
+    assert_eq!(out(".data.rel.ro.local.vtable"), Some(".data.rel.ro"));
+    assert_eq!(out(".data.rel.local"), Some(".data"));
+    assert_eq!(out(".tbss.errno"), Some(".tbss"));
+    assert_eq!(out(".init_array.00100"), Some(".init_array"));
+    assert_eq!(out(".TEXT"), None);
+    assert_eq!(out(".ARM.exidx"), None);
+    assert_eq!(linker::output_section(".text.unlikely.f", false), Some(".text"));
+    assert_eq!(linker::output_section(".tdata.x", false), Some(".tdata"));
+
+    let mut names = vec![".data.x", ".ARM.exidx", ".text.f", ".text.hot.g", ".rodata"];
+    names.push(".text.unlikely");
+    names.sort_by_key(|name| linker::output_section_rank(name));
+    let expected = [".text.unlikely", ".text.hot.g", ".text.f", ".rodata", ".data.x", ".ARM.exidx"];
+    assert_eq!(names, expected);
+}
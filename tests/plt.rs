@@ -0,0 +1,153 @@
+extern crate elfkit;
+
+use elfkit::plt::{self, PltStrategy};
+use elfkit::relocation::RelocationType;
+use elfkit::{linker, types, DynamicContent, ElfBuilder, Error, GotEntry, Header, Section,
+             SectionContent};
+
+fn header() -> Header {
+    ElfBuilder::new(types::Machine::X86_64).build().unwrap().header
+}
+
+fn z(keywords: &[&str]) -> PltStrategy {
+    let keywords: Vec<String> = keywords.iter().map(|k| k.to_string()).collect();
+    PltStrategy::from_z(&keywords)
+}
+
+fn rel32(code: &[u8], at: usize) -> i32 {
+    i32::from_le_bytes([code[at], code[at + 1], code[at + 2], code[at + 3]])
+}
+
+#[test]
+fn strategies_follow_the_z_keywords() {
+    assert_eq!(z(&[]), PltStrategy::Lazy);
+    assert_eq!(z(&["relro", "now"]), PltStrategy::Now);
+    assert_eq!(z(&["now", "lazy"]), PltStrategy::Lazy);
+    assert_eq!(z(&["now", "retpolineplt"]), PltStrategy::Retpoline);
+    assert!(PltStrategy::Now.binds_now());
+    assert_eq!(PltStrategy::Lazy.size(2), 48);
+    assert_eq!(PltStrategy::Now.size(2), 16);
+    assert_eq!(PltStrategy::Retpoline.size(2), 112);
+    assert_eq!(PltStrategy::Retpoline.entry_address(0x1000, 1), 0x1050);
+}
+
+#[test]
+fn lazy_plt_is_what_ld_makes() {
+    // gcc -fcf-protection=none -Wl,-z,lazy, for a main calling puts and exit
+    let plt = plt::generate(PltStrategy::Lazy, &header(), &[3, 5], 0x1020, 0x3fe8, 0x3de0).unwrap();
+    assert_eq!(
+        plt.code,
+        vec![
+            0xff, 0x35, 0xca, 0x2f, 0x00, 0x00, 0xff, 0x25, 0xcc, 0x2f, 0x00, 0x00, 0x0f, 0x1f,
+            0x40, 0x00, 0xff, 0x25, 0xca, 0x2f, 0x00, 0x00, 0x68, 0x00, 0x00, 0x00, 0x00, 0xe9,
+            0xe0, 0xff, 0xff, 0xff, 0xff, 0x25, 0xc2, 0x2f, 0x00, 0x00, 0x68, 0x01, 0x00, 0x00,
+            0x00, 0xe9, 0xd0, 0xff, 0xff, 0xff,
+        ]
+    );
+    let slot = |sym, value| GotEntry {
+        sym,
+        rtype: RelocationType::R_X86_64_JUMP_SLOT,
+        addend: 0,
+        value,
+    };
+    assert_eq!(
+        plt.got,
+        vec![
+            GotEntry::fixed(0x3de0),
+            GotEntry::fixed(0),
+            GotEntry::fixed(0),
+            slot(3, 0x1036),
+            slot(5, 0x1046),
+        ]
+    );
+}
+
+#[test]
+fn eager_and_retpoline_entries() {
+    let eh = header();
+    let now = plt::generate(PltStrategy::Now, &eh, &[7], 0x1000, 0x3000, 0x2000).unwrap();
+    assert_eq!(&now.code[..2], &[0xff, 0x25]);
+    assert_eq!(0x1006 + rel32(&now.code, 2) as u64, 0x3018);
+    assert_eq!(now.got[3].value, 0);
+
+    let ret = plt::generate(PltStrategy::Retpoline, &eh, &[7, 8], 0x1000, 0x3000, 0x2000).unwrap();
+    let e = 0x1050;
+    let code = &ret.code[0x50..];
+    // mov slot(%rip), %r11; call the thunk; jmp the trap; push the index; jmp the header
+    assert_eq!(&code[..3], &[0x4c, 0x8b, 0x1d]);
+    assert_eq!((e + 7) as i64 + rel32(code, 3) as i64, 0x3020);
+    assert_eq!((e + 12) as i64 + rel32(code, 8) as i64, 0x1020);
+    assert_eq!((e + 17) as i64 + rel32(code, 13) as i64, 0x1012);
+    assert_eq!(rel32(code, 18), 1);
+    assert_eq!((e + 27) as i64 + rel32(code, 23) as i64, 0x1000);
+    assert_eq!(ret.got[4].value, e + 17);
+
+    let mut arm = eh;
+    arm.machine = types::Machine::ARM;
+    match plt::generate(PltStrategy::Lazy, &arm, &[1], 0, 0, 0) {
+        Err(Error::UnsupportedMachineTypeForRelocation(types::Machine::ARM)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[test]
+fn plt_sections_get_dynamic_entries() {
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .etype(types::ElfType::DYN)
+        .text(vec![0xc3])
+        .build()
+        .unwrap();
+    let strategy = PltStrategy::Lazy;
+    elf.sections.push(Section::new(
+        String::from(".plt"),
+        types::SectionType::PROGBITS,
+        types::SectionFlags::ALLOC | types::SectionFlags::EXECINSTR,
+        SectionContent::Raw(vec![0; strategy.size(2)]),
+        0,
+        0,
+    ));
+    let p = elf.sections.len() - 1;
+    let got = plt::generate(strategy, &elf.header, &[1, 2], 0, 0, 0).unwrap().got;
+    elf.sections.push(Section::new(
+        String::from(".got.plt"),
+        types::SectionType::PROGBITS,
+        types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+        SectionContent::Got(got),
+        0,
+        0,
+    ));
+    let g = elf.sections.len() - 1;
+    elf.sections.push(Section::new(
+        String::from(".rela.plt"),
+        types::SectionType::RELA,
+        types::SectionFlags::ALLOC | types::SectionFlags::INFO_LINK,
+        SectionContent::Relocations(Vec::new()),
+        0,
+        g as u32,
+    ));
+    elf.sync_all().unwrap();
+    linker::relayout(&mut elf, 0x300).unwrap();
+
+    // filled in once the addresses are known, the sizes stay the same
+    let (plt_addr, got_addr) = (elf.sections[p].header.addr, elf.sections[g].header.addr);
+    let generated = plt::generate(strategy, &elf.header, &[1, 2], plt_addr, got_addr, 0).unwrap();
+    elf.sections[p].content = SectionContent::Raw(generated.code);
+    elf.sections[g].content = SectionContent::Got(generated.got);
+    elf.sync_all().unwrap();
+
+    let rela = elf.sections.last().unwrap();
+    let relocs = rela.content.as_relocations().unwrap();
+    assert_eq!(relocs.len(), 2);
+    assert_eq!(relocs[1].addr, got_addr + 32);
+    assert_eq!(relocs[1].rtype, RelocationType::R_X86_64_JUMP_SLOT);
+
+    let dynamic = linker::dynamic(&elf).unwrap();
+    let value = |t: types::DynamicType| match dynamic.iter().find(|d| d.dhtype == t) {
+        Some(&elfkit::Dynamic { content: DynamicContent::Address(v), .. }) => v,
+        d => panic!("unexpected {:?}", d),
+    };
+    assert_eq!(value(types::DynamicType::PLTGOT), got_addr);
+    assert_eq!(value(types::DynamicType::JMPREL), rela.header.addr);
+    assert_eq!(value(types::DynamicType::PLTRELSZ), 48);
+    assert_eq!(value(types::DynamicType::PLTREL), 7);
+}
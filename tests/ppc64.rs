@@ -0,0 +1,89 @@
+extern crate elfkit;
+
+use elfkit::{linker, types, Elf, ElfBuilder, SectionContent, Symbol};
+use elfkit::relocation::RelocationType;
+use std::io::Cursor;
+
+fn reparse(elf: &mut Elf) -> Elf {
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    let mut elf = Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap();
+    elf.load_all().unwrap();
+    elf
+}
+
+fn section<'a>(elf: &'a Elf, name: &str) -> &'a SectionContent {
+    &elf.sections.iter().find(|s| s.name == name).unwrap().content
+}
+
+#[test]
+fn local_entry_and_relocations_round_trip() {
+    let mut main = Symbol::default();
+    main.name = String::from("main");
+    main.stype = types::SymbolType::FUNC;
+    main.bind = types::SymbolBind::GLOBAL;
+    main.set_vis(types::SymbolVis::PROTECTED);
+    main.set_ppc64_local_entry_offset(8).unwrap();
+
+    // r_offset 0x18, sym 1, R_PPC64_JMP_SLOT, addend 0
+    let mut rela = vec![0x18, 0, 0, 0, 0, 0, 0, 0, 21, 0, 0, 0, 1, 0, 0, 0];
+    rela.extend(vec![0; 8]);
+
+    let mut elf = ElfBuilder::new(types::Machine::PPC64)
+        // addis r2,r12,0 ; addi r2,r2,0 ; blr
+        .text(vec![0, 0, 0x4c, 0x3c, 0, 0, 0x42, 0x38, 0x20, 0, 0x80, 0x4e])
+        .section(
+            ".got",
+            types::SectionType::PROGBITS,
+            types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+            vec![0; 16],
+        )
+        .section(
+            ".rela.plt",
+            types::SectionType::RELA,
+            types::SectionFlags::ALLOC,
+            rela,
+        )
+        .symbol(".text", main)
+        .build()
+        .unwrap();
+    elf.header.flags = types::HeaderFlags::PPC64_ABI_V2;
+
+    let elf = reparse(&mut elf);
+    assert_eq!(elf.header.flags, types::HeaderFlags::PPC64_ABI_V2);
+
+    let sym = match section(&elf, ".symtab") {
+        &SectionContent::Symbols(ref s) => s.iter().find(|s| s.name == "main").unwrap().clone(),
+        _ => panic!(".symtab not loaded as symbols"),
+    };
+    assert_eq!(sym.vis(), types::SymbolVis::PROTECTED);
+    assert_eq!(sym.ppc64_local_entry_offset(), 8);
+    assert_eq!(sym.other, 3 << 5 | 3);
+
+    match section(&elf, ".rela.plt") {
+        &SectionContent::Relocations(ref r) => {
+            assert_eq!(r.len(), 1);
+            assert_eq!(r[0].addr, 0x18);
+            assert_eq!(r[0].sym, 1);
+            assert_eq!(r[0].rtype, RelocationType::R_PPC64_JMP_SLOT);
+            assert_eq!(r[0].rtype.typename(&elf.header), Some("R_PPC64_JMP_SLOT"));
+        }
+        _ => panic!(".rela.plt not loaded as relocations"),
+    }
+
+    let got = elf.sections.iter().find(|s| s.name == ".got").unwrap();
+    assert_eq!(linker::ppc64_toc_base(&elf), Some(got.header.addr + 0x8000));
+}
+
+#[test]
+fn local_entry_offset_must_be_encodable() {
+    let mut sym = Symbol::default();
+    assert_eq!(sym.ppc64_local_entry_offset(), 0);
+    assert!(sym.set_ppc64_local_entry_offset(12).is_err());
+    assert!(sym.set_ppc64_local_entry_offset(128).is_err());
+    sym.set_ppc64_local_entry_offset(64).unwrap();
+    assert_eq!(sym.ppc64_local_entry_offset(), 64);
+    sym.set_ppc64_local_entry_offset(0).unwrap();
+    assert_eq!(sym.other, 0);
+}
@@ -0,0 +1,79 @@
+extern crate elfkit;
+
+use elfkit::property::{self, GnuProperties, GnuProperty};
+use elfkit::{linker, types, ElfBuilder};
+
+// one NT_GNU_PROPERTY_TYPE_0 note with X86_FEATURE_1_AND IBT|SHSTK, as gcc -fcf-protection emits
+const CET: &[u8] = &[
+    4, 0, 0, 0, 0x10, 0, 0, 0, 5, 0, 0, 0, b'G', b'N', b'U', 0, 0x02, 0, 0, 0xc0, 4, 0, 0, 0, 3, 0,
+    0, 0, 0, 0, 0, 0,
+];
+
+#[test]
+fn x86_cet_note() {
+    let eh = ElfBuilder::new(types::Machine::X86_64).build().unwrap().header;
+    let props = GnuProperties::from_reader(CET, &eh).unwrap();
+    assert_eq!(props.x86_features(), types::X86Feature1::IBT | types::X86Feature1::SHSTK);
+    assert_eq!(props.aarch64_features(), types::Aarch64Feature1::empty());
+
+    let mut out = Vec::new();
+    props.to_writer(&mut out, &eh).unwrap();
+    assert_eq!(out, CET);
+
+    // the same type means something else on another machine
+    let eh = ElfBuilder::new(types::Machine::AARCH64).build().unwrap().header;
+    let props = GnuProperties::from_reader(CET, &eh).unwrap();
+    assert_eq!(props.properties, vec![GnuProperty::Other(0xc0000002, vec![3, 0, 0, 0])]);
+
+    assert!(GnuProperties::from_reader(&CET[..20], &eh).is_err());
+}
+
+#[test]
+fn features_are_anded() {
+    let x86 = |f: types::X86Feature1| GnuProperties {
+        properties: vec![GnuProperty::X86Feature1(f), GnuProperty::Other(1, vec![0x10, 0, 0, 0])],
+    };
+    let both = x86(types::X86Feature1::IBT | types::X86Feature1::SHSTK);
+    let shstk = x86(types::X86Feature1::SHSTK);
+    let merged = property::merge(&[both.clone(), shstk.clone()]);
+    assert_eq!(merged, shstk);
+
+    // an object without the note supports nothing, so the output doesn't either
+    let merged = property::merge(&[both.clone(), GnuProperties::default()]);
+    assert!(merged.properties.is_empty());
+
+    // no features left, and the other property differs
+    let mut ibt = x86(types::X86Feature1::IBT);
+    ibt.properties[1] = GnuProperty::Other(1, vec![0x20, 0, 0, 0]);
+    let merged = property::merge(vec![&both, &shstk, &ibt]);
+    assert!(merged.properties.is_empty());
+
+    assert_eq!(property::merge(&[]), GnuProperties::default());
+}
+
+#[test]
+fn property_segment() {
+    let props = GnuProperties {
+        properties: vec![GnuProperty::Aarch64Feature1(types::Aarch64Feature1::BTI)],
+    };
+    let mut elf = ElfBuilder::new(types::Machine::AARCH64)
+        .text(vec![0xc0, 0x03, 0x5f, 0xd6])
+        .build()
+        .unwrap();
+    let sec = props.section(&elf.header).unwrap();
+    elf.sections.push(sec);
+    elf.sync_all().unwrap();
+    linker::relayout(&mut elf, 0x300000).unwrap();
+
+    let found = property::gnu_properties(&elf.sections, &elf.header).unwrap().unwrap();
+    assert_eq!(found.aarch64_features(), types::Aarch64Feature1::BTI);
+
+    let segments = linker::segments(&elf).unwrap();
+    let sec = &elf.sections[elf.sections.len() - 1].header;
+    let seg = segments
+        .iter()
+        .find(|s| s.phtype == types::SegmentType::GNU_PROPERTY)
+        .unwrap();
+    assert_eq!((seg.vaddr, seg.filesz, seg.align), (sec.addr, sec.size, 8));
+    assert_eq!(sec.offset % 8, 0);
+}
@@ -0,0 +1,62 @@
+extern crate elfkit;
+
+use elfkit::{types, Elf, ElfBuilder, Header, SegmentHeader, Symbol};
+use std::io::Cursor;
+
+fn build(interp: Option<&[u8]>) -> Vec<u8> {
+    let mut start = Symbol::default();
+    start.name = String::from("_start");
+    start.bind = types::SymbolBind::GLOBAL;
+    let mut builder = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .symbol(".text", start)
+        .entry("_start");
+    if let Some(interp) = interp {
+        builder = builder.section(
+            ".interp",
+            types::SectionType::PROGBITS,
+            types::SectionFlags::ALLOC,
+            interp.to_vec(),
+        );
+    }
+    let mut elf = builder.build().unwrap();
+    if let Some(sec) = elf.sections.iter().find(|s| s.name == ".interp") {
+        let mut ph = SegmentHeader::default();
+        ph.phtype = types::SegmentType::INTERP;
+        ph.offset = sec.header.offset;
+        ph.filesz = sec.header.size;
+        elf.segments.push(ph);
+    }
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    io.into_inner()
+}
+
+#[test]
+fn header_only() {
+    let raw = build(None);
+    let mut b = [0; 64];
+    b.copy_from_slice(&raw[..64]);
+    let eh = Header::peek(&b).unwrap();
+    assert_eq!(eh.machine, types::Machine::X86_64);
+    assert_eq!(eh.etype, types::ElfType::DYN);
+
+    let info = Elf::quick_info(&mut Cursor::new(&raw)).unwrap();
+    assert_eq!(info.class, types::Class::Class64);
+    assert_eq!(info.machine, types::Machine::X86_64);
+    assert_eq!(info.etype, types::ElfType::DYN);
+    assert_eq!(info.entry, eh.entry);
+    assert_eq!(info.interpreter, None);
+
+    b[0] = 0;
+    assert!(Header::peek(&b).is_err());
+    assert!(Elf::quick_info(&mut Cursor::new(&raw[..40])).is_err());
+}
+
+#[test]
+fn interpreter() {
+    let raw = build(Some(b"/lib/ld-musl-x86_64.so.1\0"));
+    let info = Elf::quick_info(&mut Cursor::new(&raw)).unwrap();
+    assert_eq!(info.interpreter.as_ref().map(|s| &s[..]), Some("/lib/ld-musl-x86_64.so.1"));
+}
@@ -0,0 +1,298 @@
+extern crate elfkit;
+
+use elfkit::{types, Elf, ElfBuilder, Header, Relocation, Section, SectionContent, Strtab,
+             Symbol, SymbolSectionIndex};
+use elfkit::arch::{self, RelocationKind, RelocationValues};
+use elfkit::linker::{self, DebugInput};
+use elfkit::relocation::{RelocationType, SymbolicRelocation, Target};
+use elfkit::Error;
+use std::io::Cursor;
+
+fn header(machine: types::Machine, class: types::Class, endianness: types::Endianness) -> Header {
+    let mut eh = Header::default();
+    eh.machine = machine;
+    eh.ident_class = class;
+    eh.ident_endianness = endianness;
+    eh
+}
+
+#[test]
+fn handlers_follow_the_machine() {
+    let le = types::Endianness::LittleEndian;
+    let x86 = header(types::Machine::X86_64, types::Class::Class64, le.clone());
+    let h = arch::handler(&x86).unwrap();
+    assert_eq!(h.classify(&RelocationType::R_X86_64_PLT32), RelocationKind::Plt);
+    assert_eq!(h.classify(&RelocationType::R_X86_64_REX_GOTPCRELX), RelocationKind::Got);
+    assert_eq!(h.classify(&RelocationType::R_X86_64_RELATIVE), RelocationKind::BaseRelative);
+    assert_eq!(h.size(&RelocationType::R_X86_64_64), Some(8));
+    assert_eq!(h.size(&RelocationType(0xfff)), None);
+    let info = h.encode(7, &RelocationType::R_X86_64_PC32, &x86);
+    assert_eq!(h.decode(info, &x86), (7, RelocationType::R_X86_64_PC32));
+
+    let arm = header(types::Machine::ARM, types::Class::Class32, le);
+    let h = arch::handler(&arm).unwrap();
+    assert_eq!(h.encode(2, &RelocationType::R_ARM_JUMP_SLOT, &arm), 0x216);
+    assert_eq!(h.typename(&RelocationType::R_ARM_JUMP_SLOT), Some("R_ARM_JUMP_SLOT"));
+
+    let mips64 = header(types::Machine::MIPS, types::Class::Class64, types::Endianness::BigEndian);
+    assert!(arch::handler(&mips64).is_none());
+}
+
+#[test]
+fn apply_checks_range() {
+    let eh = header(types::Machine::X86_64, types::Class::Class64, types::Endianness::LittleEndian);
+    let h = arch::handler(&eh).unwrap();
+    let mut v = RelocationValues::default();
+    v.s = 0x1000;
+    v.a = -4;
+    v.p = 0x2000;
+
+    let mut place = [0xff; 6];
+    h.apply(&RelocationType::R_X86_64_PC32, &v, &mut place, &eh).unwrap();
+    assert_eq!(place, [0xfc, 0xef, 0xff, 0xff, 0xff, 0xff]);
+
+    v.s = 0x1_0000_0000;
+    match h.apply(&RelocationType::R_X86_64_PC32, &v, &mut place, &eh) {
+        Err(Error::RelocationOverflow(2, _)) => {}
+        r => panic!("expected overflow, got {:?}", r),
+    }
+    match h.apply(&RelocationType::R_X86_64_64, &v, &mut place, &eh) {
+        Err(Error::RelocationOutOfBounds) => {}
+        r => panic!("expected out of bounds, got {:?}", r),
+    }
+}
+
+#[test]
+fn apply_merges_into_instructions() {
+    // bl with a zero offset, big endian arm
+    let eh = header(types::Machine::ARM, types::Class::Class32, types::Endianness::BigEndian);
+    let h = arch::handler(&eh).unwrap();
+    let mut v = RelocationValues::default();
+    v.s = 0x8100;
+    v.a = -8;
+    v.p = 0x8000;
+    let mut place = [0xeb, 0, 0, 0];
+    h.apply(&RelocationType::R_ARM_CALL, &v, &mut place, &eh).unwrap();
+    assert_eq!(place, [0xeb, 0, 0, 0x3e]);
+
+    // addis r2,r12,.TOC.-func@ha ; addi r2,r2,.TOC.-func@l, little endian ppc64
+    let eh = header(types::Machine::PPC64, types::Class::Class64, types::Endianness::LittleEndian);
+    let h = arch::handler(&eh).unwrap();
+    let mut v = RelocationValues::default();
+    v.s = 0x1_8100;
+    v.p = 0x1000;
+    let mut insn = [0, 0, 0x4c, 0x3c, 0, 0, 0x42, 0x38];
+    h.apply(&RelocationType::R_PPC64_REL16_HA, &v, &mut insn[..], &eh).unwrap();
+    v.a = 4;
+    h.apply(&RelocationType::R_PPC64_REL16_LO, &v, &mut insn[4..], &eh).unwrap();
+    // 0x18100 - 0x1000 = 0x17100, which is 1 << 16 + 0x7100
+    assert_eq!(insn, [0x01, 0, 0x4c, 0x3c, 0x04, 0x71, 0x42, 0x38]);
+}
+
+#[test]
+fn symbolic_targets_resolve_at_sync() {
+    let mut elf = Elf::default();
+    let le = types::Endianness::LittleEndian;
+    elf.header = header(types::Machine::X86_64, types::Class::Class64, le);
+    elf.header.etype = types::ElfType::REL;
+    let section = |name: &str, shtype, content| {
+        Section::new(String::from(name), shtype, types::SectionFlags::empty(), content, 0, 0)
+    };
+    let main = Symbol {
+        name: String::from("main"),
+        shndx: SymbolSectionIndex::Section(1),
+        bind: types::SymbolBind::GLOBAL,
+        stype: types::SymbolType::FUNC,
+        ..Symbol::default()
+    };
+    let symbols = vec![Symbol::default(), main];
+    let relocs = vec![
+        Relocation {
+            addr: 0,
+            sym: 1,
+            rtype: RelocationType::R_X86_64_PLT32,
+            addend: -4,
+        },
+    ];
+    elf.sections = vec![
+        Section::default(),
+        section(".text", types::SectionType::PROGBITS, SectionContent::Raw(vec![0; 32])),
+        section(".data", types::SectionType::PROGBITS, SectionContent::Raw(vec![0; 16])),
+        section(".symtab", types::SectionType::SYMTAB, SectionContent::Symbols(symbols)),
+        section(".strtab", types::SectionType::STRTAB, SectionContent::Strtab(Strtab::default())),
+        section(".rela.text", types::SectionType::RELA, SectionContent::Relocations(relocs)),
+        section(".group", types::SectionType::GROUP, SectionContent::Raw(vec![1, 0, 0, 0])),
+    ];
+    elf.sections[3].header.link = 4;
+    elf.sections[5].header.link = 3;
+    elf.sections[5].header.info = 1;
+    // the group is named after main
+    elf.sections[6].header.link = 3;
+    elf.sections[6].header.info = 1;
+
+    let plt32 = RelocationType::R_X86_64_PLT32;
+    let pc32 = RelocationType::R_X86_64_PC32;
+    let adds = vec![
+        SymbolicRelocation::new(4, plt32, Target::Symbol("puts".into())).with_addend(-4),
+        SymbolicRelocation::new(8, pc32.clone(), Target::Symbol("main".into())).with_addend(-4),
+        SymbolicRelocation::new(12, pc32, Target::Section(".data".into(), 8)).with_addend(-4),
+    ];
+    for r in adds {
+        elf.add_relocation(5, r).unwrap();
+    }
+    let r = SymbolicRelocation::new(0, RelocationType(0), Target::Symbol("x".into()));
+    match elf.add_relocation(7, r) {
+        Err(Error::SectionIndexOutOfRange(7)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+
+    elf.sync_all().unwrap();
+    // nothing is added twice
+    elf.sync_all().unwrap();
+
+    let symbols = elf.sections[3].content.as_symbols().unwrap().clone();
+    let names: Vec<&str> = symbols.iter().map(|s| &s.name[..]).collect();
+    assert_eq!(names, vec!["", "", "main", "puts"]);
+    assert_eq!(symbols[1].stype, types::SymbolType::SECTION);
+    assert_eq!(symbols[1].shndx, SymbolSectionIndex::Section(2));
+    assert_eq!(symbols[3].shndx, SymbolSectionIndex::Undefined);
+    assert_eq!(elf.sections[3].header.info, 2);
+    assert_eq!(elf.sections[6].header.info, 2);
+
+    let relocs: Vec<(u64, u32, i64)> = elf.sections[5]
+        .content
+        .as_relocations()
+        .unwrap()
+        .iter()
+        .map(|r| (r.addr, r.sym, r.addend))
+        .collect();
+    // the relocation that was there already moved along with main
+    assert_eq!(relocs, vec![(0, 2, -4), (4, 3, -4), (8, 2, -4), (12, 1, 4)]);
+}
+
+#[test]
+fn section_symbols_keep_the_extended_indices_in_line() {
+    let mut elf = Elf::default();
+    let le = types::Endianness::LittleEndian;
+    elf.header = header(types::Machine::X86_64, types::Class::Class64, le);
+    elf.header.etype = types::ElfType::REL;
+    let section = |name: &str, shtype, content| {
+        Section::new(String::from(name), shtype, types::SectionFlags::empty(), content, 0, 0)
+    };
+    let far = Symbol {
+        name: String::from("far"),
+        shndx: SymbolSectionIndex::Section(70000),
+        bind: types::SymbolBind::GLOBAL,
+        ..Symbol::default()
+    };
+    elf.sections = vec![
+        Section::default(),
+        section(".data", types::SectionType::PROGBITS, SectionContent::Raw(vec![0; 8])),
+        section(".symtab", types::SectionType::SYMTAB, SectionContent::Symbols(vec![
+            Symbol::default(),
+            far,
+        ])),
+        section(".strtab", types::SectionType::STRTAB, SectionContent::Strtab(Strtab::default())),
+        section(".symtab_shndx", types::SectionType::SYMTAB_SHNDX, SectionContent::Raw(vec![])),
+        section(".rela.data", types::SectionType::RELA, SectionContent::Relocations(vec![])),
+    ];
+    elf.sections[2].header.link = 3;
+    elf.sections[4].header.link = 2;
+    elf.sections[5].header.link = 2;
+    elf.sections[5].header.info = 1;
+    let data = Target::Section(String::from(".data"), 0);
+    let r = SymbolicRelocation::new(0, RelocationType::R_X86_64_64, data);
+    elf.add_relocation(5, r).unwrap();
+    elf.sync_all().unwrap();
+
+    // far moved behind the section symbol, and its index with it
+    let symbols = elf.sections[2].content.as_symbols().unwrap();
+    assert_eq!(symbols[2].name, "far");
+    let xindex = elf.sections[4].content.as_raw().unwrap();
+    assert_eq!(xindex, &vec![0, 0, 0, 0, 0, 0, 0, 0, 0x70, 0x11, 1, 0]);
+}
+
+/// an object with code at .text+0 and a function f at .text+8, and debug info for f
+fn object_with_debug_info() -> Elf {
+    let f = Symbol {
+        name: String::from("f"),
+        bind: types::SymbolBind::GLOBAL,
+        stype: types::SymbolType::FUNC,
+        value: 8,
+        ..Symbol::default()
+    };
+    let debug = types::SectionFlags::empty();
+    let to_f = || Target::Symbol(String::from("f"));
+    let abbrev = Target::Section(String::from(".debug_abbrev"), 0);
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .etype(types::ElfType::REL)
+        .text(vec![0xe8, 0, 0, 0, 0, 0xc3, 0x90, 0x90, 0xc3])
+        .section(".debug_info", types::SectionType::PROGBITS, debug, vec![0; 12])
+        .section(".debug_abbrev", types::SectionType::PROGBITS, debug, vec![7; 4])
+        .symbol(".text", f)
+        .relocation(
+            ".text",
+            SymbolicRelocation::new(1, RelocationType::R_X86_64_PLT32, to_f()).with_addend(-4),
+        )
+        .relocation(
+            ".debug_info",
+            SymbolicRelocation::new(0, RelocationType::R_X86_64_32, abbrev),
+        )
+        .relocation(
+            ".debug_info",
+            SymbolicRelocation::new(4, RelocationType::R_X86_64_64, to_f()),
+        )
+        .build()
+        .unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap()
+}
+
+#[test]
+fn debug_sections_are_merged_and_relocated() {
+    let inputs: Vec<DebugInput> = (0..2)
+        .map(|_| DebugInput::from_elf(&mut object_with_debug_info()).unwrap())
+        .collect();
+    assert_eq!(inputs[0].sections.len(), 2);
+    assert_eq!(inputs[0].relocations.len(), 1);
+
+    let le = types::Endianness::LittleEndian;
+    let eh = header(types::Machine::X86_64, types::Class::Class64, le);
+    // the first object's f is at 0x1008, the second one's wasn't linked
+    let merged = linker::merge_debug_sections(&eh, &inputs, |n, sym| {
+        assert_eq!(sym.name, "f");
+        if n == 0 {
+            Some(0x1008)
+        } else {
+            None
+        }
+    });
+    let merged = merged.unwrap();
+    let names: Vec<&str> = merged.iter().map(|s| s.name.as_ref()).collect();
+    assert_eq!(names, vec![".debug_info", ".debug_abbrev"]);
+    let info = merged[0].content.as_raw().unwrap();
+    let mut expected = vec![0, 0, 0, 0, 8, 0x10, 0, 0, 0, 0, 0, 0];
+    // the second unit's abbreviations follow the first one's
+    expected.extend_from_slice(&[4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(info, &expected);
+    assert_eq!(merged[1].content.as_raw().unwrap(), &vec![7; 8]);
+}
+
+#[test]
+fn strip_debug_renumbers_what_stays() {
+    let mut elf = object_with_debug_info();
+    elf.load_all().unwrap();
+    linker::strip_debug(&mut elf).unwrap();
+    let names: Vec<&str> = elf.sections.iter().map(|s| s.name.as_ref()).collect();
+    assert_eq!(
+        names,
+        vec!["", ".text", ".rela.text", ".note.GNU-stack", ".symtab", ".strtab", ".shstrtab"]
+    );
+    let symtab = elf.sections.iter().position(|s| s.name == ".symtab").unwrap();
+    let symbols = elf.sections[symtab].content.as_symbols().unwrap().clone();
+    let sym = elf.sections[2].content.as_relocations().unwrap()[0].sym;
+    assert_eq!(symbols[sym as usize].name, "f");
+    assert_eq!(elf.sections[2].header.link as usize, symtab);
+    assert_eq!(elf.sections[2].header.info, 1);
+}
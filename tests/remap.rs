@@ -0,0 +1,53 @@
+extern crate elfkit;
+
+use elfkit::linker::{PlacedSection, SectionRemap};
+use elfkit::{types, Section, SectionContent};
+
+fn remap() -> SectionRemap {
+    let mut remap = SectionRemap::new();
+    let text = |offset, size| PlacedSection { shndx: 1, offset, size };
+    remap.insert("main.o", 3, text(0, 0x20));
+    remap.insert("libc.a(puts.o)", 1, text(0x20, 0x10));
+    remap.insert("main.o", 5, PlacedSection { shndx: 2, offset: 0, size: 8 });
+    remap
+}
+
+#[test]
+fn input_offsets_map_to_the_output() {
+    let remap = remap();
+    assert_eq!(remap.len(), 3);
+    assert_eq!(remap.map("main.o", 3, 4), Some((1, 4)));
+    assert_eq!(remap.map("libc.a(puts.o)", 1, 4), Some((1, 0x24)));
+    // the end of a section is still in it, past that it's not
+    assert_eq!(remap.map("libc.a(puts.o)", 1, 0x10), Some((1, 0x30)));
+    assert_eq!(remap.map("libc.a(puts.o)", 1, 0x11), None);
+    // sections that were not linked
+    assert_eq!(remap.map("main.o", 4, 0), None);
+    assert_eq!(remap.map("puts.o", 1, 0), None);
+
+    let mut text = Section::new(
+        String::from(".text"),
+        types::SectionType::PROGBITS,
+        types::SectionFlags::ALLOC | types::SectionFlags::EXECINSTR,
+        SectionContent::Raw(vec![0; 0x30]),
+        0,
+        0,
+    );
+    text.header.addr = 0x1000;
+    let sections = vec![Section::default(), text];
+    assert_eq!(remap.address(&sections, "libc.a(puts.o)", 1, 2), Some(0x1022));
+    // section 2 is not in the output yet
+    assert_eq!(remap.address(&sections, "main.o", 5, 0), None);
+}
+
+#[test]
+fn output_offsets_map_back_to_their_input() {
+    let remap = remap();
+    assert_eq!(remap.input_at(1, 0), Some(("main.o", 3, 0)));
+    assert_eq!(remap.input_at(1, 0x2f), Some(("libc.a(puts.o)", 1, 0xf)));
+    assert_eq!(remap.input_at(1, 0x30), None);
+    assert_eq!(remap.input_at(2, 7), Some(("main.o", 5, 7)));
+
+    let order: Vec<(&str, usize)> = remap.iter().iter().map(|&(o, i, _)| (o, i)).collect();
+    assert_eq!(order, vec![("main.o", 3), ("libc.a(puts.o)", 1), ("main.o", 5)]);
+}
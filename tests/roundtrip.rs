@@ -0,0 +1,354 @@
+extern crate elfkit;
+
+use elfkit::relocation::RelocationType;
+use elfkit::{types, DynamicContent, Elf, ElfBuilder, ElfOptions, Holes, SectionContent,
+             SegmentHeader, Symbol, SymbolicRelocation, Target, WriteOptions};
+use std::collections::HashSet;
+use std::io::Cursor;
+
+fn reparse(elf: &mut Elf) -> Elf {
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    let mut elf = Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap();
+    elf.load_all().unwrap();
+    elf
+}
+
+#[test]
+fn unknown_dynamic_tags_and_segment_types_survive() {
+    // DT 0x6000000f = 0x1234, DT_NULL
+    let mut dynamic = vec![0; 32];
+    dynamic[0] = 0x0f;
+    dynamic[3] = 0x60;
+    dynamic[8] = 0x34;
+    dynamic[9] = 0x12;
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .section(
+            ".dynamic",
+            types::SectionType::DYNAMIC,
+            types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+            dynamic,
+        )
+        .build()
+        .unwrap();
+    let i = elf.sections.iter().position(|s| s.name == ".dynamic").unwrap();
+    let last = elf.segments.len() - 1;
+    elf.segments[last].phtype = types::SegmentType(0x60000123);
+
+    // the second pass writes back what was parsed
+    let elf = reparse(&mut reparse(&mut elf));
+    assert_eq!(
+        elf.segments.last().unwrap().phtype,
+        types::SegmentType(0x60000123)
+    );
+    assert_eq!(elf.segments.last().unwrap().phtype.typename(&elf.header), None);
+    match elf.sections[i].content {
+        SectionContent::Dynamic(ref d) => {
+            assert_eq!(d[0].dhtype, types::DynamicType(0x6000000f));
+            match d[0].content {
+                DynamicContent::Address(0x1234) => {}
+                ref c => panic!("unexpected content {:?}", c),
+            }
+        }
+        _ => panic!("dynamic not loaded"),
+    }
+}
+
+#[test]
+fn symbol_other_bits_survive() {
+    let mut sym = Symbol::default();
+    sym.name = String::from("f");
+    sym.bind = types::SymbolBind::GLOBAL;
+    sym.other = 0xa6;
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .symbol(".text", sym)
+        .build()
+        .unwrap();
+
+    let mut elf = reparse(&mut elf);
+    {
+        let sym = match elf.sections.iter_mut().find(|s| s.name == ".symtab").unwrap().content {
+            SectionContent::Symbols(ref mut s) => s.iter_mut().find(|s| s.name == "f").unwrap(),
+            _ => panic!(".symtab not loaded as symbols"),
+        };
+        assert_eq!(sym.other, 0xa6);
+        assert_eq!(sym.vis(), types::SymbolVis::HIDDEN);
+        sym.set_vis(types::SymbolVis::PROTECTED);
+    }
+
+    let elf = reparse(&mut elf);
+    match elf.sections.iter().find(|s| s.name == ".symtab").unwrap().content {
+        SectionContent::Symbols(ref s) => {
+            assert_eq!(s.iter().find(|s| s.name == "f").unwrap().other, 0xa7)
+        }
+        _ => panic!(".symtab not loaded as symbols"),
+    }
+}
+
+#[test]
+fn defined_symbols_can_be_cached() {
+    let mut sym = Symbol::default();
+    sym.name = String::from("f");
+    sym.bind = types::SymbolBind::GLOBAL;
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .symbol(".text", sym)
+        .build()
+        .unwrap();
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+
+    let mut elf = Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap();
+    assert_eq!(elf.defined_symbols().unwrap().iter().collect::<Vec<_>>(), vec!["f"]);
+
+    let mut cached = HashSet::new();
+    cached.insert(String::from("g"));
+    elf.set_defined_symbols(cached);
+    assert!(elf.contains_symbol("g").unwrap());
+    assert!(!elf.contains_symbol("f").unwrap());
+}
+
+#[test]
+fn symbol_index_only_leaves_symbols_raw() {
+    let symbol = |name: &str, bind| Symbol {
+        name: String::from(name),
+        bind,
+        ..Symbol::default()
+    };
+    let puts = Target::Symbol(String::from("puts"));
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .etype(types::ElfType::REL)
+        .text(vec![0xe8, 0, 0, 0, 0, 0xc3])
+        .symbol(".text", symbol("f", types::SymbolBind::GLOBAL))
+        .symbol(".text", symbol("w", types::SymbolBind::WEAK))
+        .symbol(".text", symbol("local", types::SymbolBind::LOCAL))
+        .relocation(".text", SymbolicRelocation::new(1, RelocationType::R_X86_64_PLT32, puts))
+        .build()
+        .unwrap();
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    let bytes = io.into_inner();
+
+    let options = ElfOptions {
+        symbol_index_only: true,
+        ..ElfOptions::default()
+    };
+    let mut elf = Elf::from_reader_with_options(&mut Cursor::new(&bytes), options).unwrap();
+    let names = elf.defined_symbols().unwrap().clone();
+    let expected: HashSet<String> = ["f", "w"].iter().map(|s| String::from(*s)).collect();
+    assert_eq!(names, expected);
+    let symtab = elf.sections.iter().position(|s| s.name == ".symtab").unwrap();
+    assert!(elf.sections[symtab].content.as_raw().is_some());
+    assert_eq!(elf.stats().symbols_parsed, 0);
+
+    // the same names as parsing them all, which load_at still does
+    let mut parsed = Elf::from_reader(&mut Cursor::new(&bytes)).unwrap();
+    assert_eq!(*parsed.defined_symbols().unwrap(), names);
+    elf.load_at(symtab).unwrap();
+    let symbols = elf.sections[symtab].content.as_symbols().unwrap();
+    assert!(symbols.iter().any(|s| s.name == "local"));
+    assert!(symbols.iter().any(|s| s.name == "puts"));
+}
+
+#[test]
+fn trailing_data_survives() {
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .build()
+        .unwrap();
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    let mut raw = io.into_inner();
+    let plain = Elf::from_reader(&mut Cursor::new(raw.clone())).unwrap();
+    assert!(plain.trailing_data().is_empty());
+
+    raw.extend_from_slice(b"PAYLOAD\0signature");
+    let mut elf = Elf::from_reader(&mut Cursor::new(raw.clone())).unwrap();
+    assert_eq!(elf.trailing_data(), b"PAYLOAD\0signature");
+    let mut io = Cursor::new(Vec::new());
+    elf.store_all().unwrap();
+    elf.to_writer(&mut io).unwrap();
+    assert_eq!(io.into_inner(), raw);
+
+    let mut elf = reparse(&mut Elf::from_reader(&mut Cursor::new(raw)).unwrap());
+    assert_eq!(elf.trailing_data(), b"PAYLOAD\0signature");
+    elf.set_trailing_data(Vec::new());
+    assert!(reparse(&mut elf).trailing_data().is_empty());
+}
+
+/// a Cursor that counts how often it is written to and seeked
+struct Counting {
+    io: Cursor<Vec<u8>>,
+    writes: usize,
+    seeks: usize,
+}
+
+impl std::io::Write for Counting {
+    fn write(&mut self, b: &[u8]) -> std::io::Result<usize> {
+        self.writes += 1;
+        self.io.write(b)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl std::io::Seek for Counting {
+    fn seek(&mut self, to: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.seeks += 1;
+        self.io.seek(to)
+    }
+}
+
+#[test]
+fn sections_are_written_in_runs() {
+    let mut b = ElfBuilder::new(types::Machine::X86_64).text(vec![0xc3]);
+    for i in 0..200 {
+        b = b.section(
+            &format!(".note.{}", i),
+            types::SectionType::NOTE,
+            types::SectionFlags::empty(),
+            vec![i as u8; 12],
+        );
+    }
+    let mut elf = b.build().unwrap();
+    elf.store_all().unwrap();
+    // one section far behind the others, which is seeked to instead of padded
+    let last = elf.sections.len() - 1;
+    elf.sections[last].header.offset += 1 << 20;
+    let mut io = Counting {
+        io: Cursor::new(Vec::new()),
+        writes: 0,
+        seeks: 0,
+    };
+    elf.to_writer(&mut io).unwrap();
+    assert!(io.seeks < 8 && io.writes < 8, "{} seeks, {} writes", io.seeks, io.writes);
+
+    let raw = io.io.into_inner();
+    let mut elf = Elf::from_reader(&mut Cursor::new(raw)).unwrap();
+    elf.load_all().unwrap();
+    let notes: Vec<_> = elf.sections.iter().filter(|s| s.name.starts_with(".note.")).collect();
+    assert_eq!(notes.len(), 200);
+    for (i, sec) in notes.iter().enumerate() {
+        match sec.content {
+            SectionContent::Raw(ref v) => assert_eq!(v, &vec![i as u8; 12]),
+            ref c => panic!("unexpected {:?}", c),
+        }
+    }
+}
+
+/// text and data in LOADs of their own, with the data 2MiB further into the file and memory,
+/// like linkers that align file offsets of segments to their alignment leave them
+fn gapped() -> Elf {
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3; 100])
+        .section(
+            ".data",
+            types::SectionType::PROGBITS,
+            types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+            vec![7; 100],
+        )
+        .build()
+        .unwrap();
+    let i = elf.sections.iter().position(|s| s.name == ".data").unwrap();
+    let data = elf.sections[i].header.offset;
+    for sec in &mut elf.sections[i..] {
+        sec.header.offset += 0x200000;
+    }
+    elf.sections[i].header.addr += 0x200000;
+    let d = elf.sections[i].header.clone();
+
+    // the builder made one LOAD for everything, and a PHDR which makes room for the second
+    assert_eq!(elf.segments.len(), 2);
+    elf.segments[1].filesz = data;
+    elf.segments[1].memsz = data;
+    elf.segments[0] = SegmentHeader {
+        phtype: types::SegmentType::LOAD,
+        flags: types::SegmentFlags::READABLE | types::SegmentFlags::WRITABLE,
+        offset: d.offset,
+        vaddr: d.addr,
+        paddr: d.addr,
+        filesz: d.size,
+        memsz: d.size,
+        align: 0x200000,
+    };
+    elf
+}
+
+#[test]
+fn holes_are_seeked_over_or_filled() {
+    let mut io = Cursor::new(Vec::new());
+    gapped().to_writer(&mut io).unwrap();
+    let seeked = io.into_inner();
+    assert!(seeked.len() > 0x200000);
+
+    let mut io = Counting {
+        io: Cursor::new(Vec::new()),
+        writes: 0,
+        seeks: 0,
+    };
+    let fill = WriteOptions {
+        holes: Holes::Fill,
+        ..WriteOptions::default()
+    };
+    gapped().to_writer_with_options(&mut io, &fill).unwrap();
+    // to the start, to the end for the section headers, and back for the header
+    assert_eq!(io.seeks, 3);
+    assert_eq!(io.io.into_inner(), seeked);
+}
+
+#[test]
+fn packed_files_map_the_same_content() {
+    let mut io = Cursor::new(Vec::new());
+    let pack = WriteOptions {
+        holes: Holes::Pack,
+        ..WriteOptions::default()
+    };
+    gapped().to_writer_with_options(&mut io, &pack).unwrap();
+    let packed = io.into_inner();
+    assert!(packed.len() < 0x10000, "{} bytes", packed.len());
+
+    let mut elf = Elf::from_reader(&mut Cursor::new(packed.clone())).unwrap();
+    elf.load_all().unwrap();
+    let data = elf.sections.iter().find(|s| s.name == ".data").unwrap();
+    assert_eq!(data.header.offset % 0x1000, data.header.addr % 0x1000);
+    match data.content {
+        SectionContent::Raw(ref v) => assert_eq!(v, &vec![7; 100]),
+        ref c => panic!("unexpected {:?}", c),
+    }
+    // every LOAD still maps what it did, at the same address
+    for seg in elf.segments.iter().filter(|s| s.phtype == types::SegmentType::LOAD) {
+        assert_eq!(seg.offset % seg.align, seg.vaddr % seg.align);
+        for sec in elf.sections.iter().filter(|s| s.header.shtype == types::SectionType::PROGBITS) {
+            if sec.header.addr >= seg.vaddr && sec.header.addr < seg.vaddr + seg.filesz {
+                assert_eq!(sec.header.addr - seg.vaddr, sec.header.offset - seg.offset);
+            }
+        }
+    }
+}
+
+#[test]
+fn gaps_get_the_fill_pattern() {
+    let mut elf = gapped();
+    let text = elf.sections.iter().find(|s| s.name == ".text").unwrap().header.clone();
+    let data = elf.sections.iter().find(|s| s.name == ".data").unwrap().header.offset;
+    let mut io = Cursor::new(Vec::new());
+    let nops = WriteOptions {
+        fill: vec![0x66, 0x90],
+        ..WriteOptions::default()
+    };
+    elf.to_writer_with_options(&mut io, &nops).unwrap();
+    let raw = io.into_inner();
+
+    // too far to be padded, but seeking would leave zeros
+    let gap = &raw[(text.offset + text.size) as usize..data as usize];
+    assert_eq!(gap.len(), 0x200000);
+    assert!(gap.chunks(2).all(|c| c == [0x66, 0x90]));
+    assert_eq!(&raw[data as usize..data as usize + 100], &[7; 100][..]);
+}
@@ -0,0 +1,195 @@
+extern crate elfkit;
+
+use elfkit::scan::{self, Pattern, Source, SymbolIndex};
+use elfkit::{types, ElfBuilder, Symbol};
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+fn elf_defining(name: &str, machine: types::Machine) -> Vec<u8> {
+    let mut sym = Symbol::default();
+    sym.name = String::from(name);
+    sym.bind = types::SymbolBind::GLOBAL;
+    let mut elf = ElfBuilder::new(machine)
+        .text(vec![0xc3])
+        .symbol(".text", sym)
+        .build()
+        .unwrap();
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    io.into_inner()
+}
+
+/// a gnu archive, names longer than 15 bytes go into the // table
+fn archive(members: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut names = Vec::new();
+    let mut body = Vec::new();
+    for &(name, content) in members {
+        let field = if name.len() > 15 {
+            let f = format!("/{}", names.len());
+            names.extend_from_slice(name.as_bytes());
+            names.extend_from_slice(b"/\n");
+            f
+        } else {
+            format!("{}/", name)
+        };
+        body.extend_from_slice(format!("{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+                                       field, 0, 0, 0, 644, content.len()).as_bytes());
+        body.extend_from_slice(content);
+        if content.len() % 2 == 1 {
+            body.push(b'\n');
+        }
+    }
+    let mut r = b"!<arch>\n".to_vec();
+    r.extend_from_slice(format!("{:<48}{:<10}`\n", "//", names.len()).as_bytes());
+    r.extend_from_slice(&names);
+    if names.len() % 2 == 1 {
+        r.push(b'\n');
+    }
+    r.extend_from_slice(&body);
+    r
+}
+
+struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// tests run in parallel, each needs its own tree
+fn tree(test: &str) -> TempDir {
+    let dir = std::env::temp_dir().join(format!("elfkit-scan-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    fs::write(dir.join("a"), elf_defining("needle", types::Machine::X86_64)).unwrap();
+    fs::write(dir.join("b"), elf_defining("hay", types::Machine::X86_64)).unwrap();
+    fs::write(dir.join("c"), elf_defining("needle", types::Machine::AARCH64)).unwrap();
+    fs::write(dir.join("notes.txt"), "not an elf file").unwrap();
+    fs::write(dir.join("lib/broken"), b"\x7fELF\x02\x01\x01\x00").unwrap();
+    let needle = elf_defining("needle", types::Machine::X86_64);
+    let hay = elf_defining("hay", types::Machine::X86_64);
+    fs::write(
+        dir.join("lib/libx.a"),
+        archive(&[("hay.o", &hay), ("a_rather_long_member_name.o", &needle), ("README", b"x")]),
+    ).unwrap();
+    TempDir(dir)
+}
+
+fn needles(source: &Source, elf: &mut elfkit::Elf) -> Option<String> {
+    match elf.contains_symbol("needle") {
+        Ok(true) => Some(source.to_string()),
+        _ => None,
+    }
+}
+
+#[test]
+fn directories_and_archives() {
+    let dir = tree("walk");
+    let mut found = Vec::new();
+    let mut failed = Vec::new();
+    for r in scan::scan(&[&dir.0], needles) {
+        match r {
+            Ok((_, v)) => found.push(v),
+            Err((source, _)) => failed.push(source),
+        }
+    }
+    let at = |p: &str| dir.0.join(p).display().to_string();
+    assert_eq!(
+        found,
+        vec![
+            at("a"),
+            at("c"),
+            format!("{}(a_rather_long_member_name.o)", at("lib/libx.a")),
+        ]
+    );
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].path, dir.0.join("lib/broken"));
+}
+
+#[test]
+fn header_filter_runs_before_parsing() {
+    let dir = tree("filter");
+    let found: Vec<Source> = scan::scan(&[dir.0.join("c"), dir.0.join("a")], needles)
+        .header_filter(|eh| eh.machine == types::Machine::X86_64)
+        .filter_map(|r| r.ok())
+        .map(|(source, _)| source)
+        .collect();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].path, dir.0.join("a"));
+    assert_eq!(found[0].member, None);
+}
+
+#[test]
+fn glob_patterns() {
+    let p = |s: &str, name: &str| Pattern::parse(s).matches(name);
+    assert_eq!(Pattern::parse("memcpy"), Pattern::Exact(String::from("memcpy")));
+    assert!(p("mem*", "memcpy"));
+    assert!(p("mem*", "mem"));
+    assert!(!p("mem*", "me"));
+    assert!(p("*cpy", "__memcpy"));
+    assert!(p("st?cpy", "stpcpy"));
+    assert!(p("str[!n]cpy", "strlcpy"));
+    assert!(!p("str[!n]cpy", "strncpy"));
+    assert!(p("x[a-c]*[0-9]", "xbyz7"));
+    assert!(!p("x[a-c]*[0-9]", "xdyz7"));
+    assert!(p("*a*b*", "xxaxxbxx"));
+    assert!(!p("*a*b*", "xxbxxaxx"));
+}
+
+#[test]
+fn matches_are_attributed_to_patterns() {
+    let dir = tree("patterns");
+    let patterns = vec![Pattern::parse("nee*"), Pattern::parse("hay"), Pattern::parse("*e*")];
+    let mut found = Vec::new();
+    for r in scan::scan(&[dir.0.join("lib/libx.a")], |_, elf| {
+        scan::matching_symbols(elf, &patterns).ok()
+    }) {
+        let (source, matches) = r.unwrap();
+        for (k, name) in matches {
+            found.push((source.member.clone().unwrap(), k, name));
+        }
+    }
+    let m = |member: &str, k: usize, name: &str| (String::from(member), k, String::from(name));
+    assert_eq!(
+        found,
+        vec![
+            m("hay.o", 1, "hay"),
+            m("a_rather_long_member_name.o", 0, "needle"),
+            m("a_rather_long_member_name.o", 2, "needle"),
+        ]
+    );
+}
+
+#[test]
+fn symbol_index_skips_unchanged_files() {
+    let dir = tree("index");
+    let patterns = vec![Pattern::parse("needle")];
+    let sources = |r: Vec<scan::ScanResult<Vec<(usize, String)>>>| -> Vec<String> {
+        r.into_iter().filter_map(|r| r.ok()).map(|(s, _)| s.to_string()).collect()
+    };
+    let file = dir.0.join("index");
+    let mut index = SymbolIndex::open(&file);
+    let found = sources(index.find(&[&dir.0], &patterns));
+    assert_eq!(found.len(), 3);
+    index.save().unwrap();
+
+    // same size and mtime, so the index still believes b defines hay
+    let b = dir.0.join("b");
+    let mtime = fs::metadata(&b).unwrap().modified().unwrap();
+    let len = fs::metadata(&b).unwrap().len() as usize;
+    fs::write(&b, vec![b'x'; len]).unwrap();
+    fs::File::options().write(true).open(&b).unwrap().set_modified(mtime).unwrap();
+    // a changed file is read again
+    fs::write(dir.0.join("a"), elf_defining("hay", types::Machine::X86_64)).unwrap();
+
+    let mut index = SymbolIndex::open(&file);
+    let objects = index.objects(&b).unwrap();
+    assert_eq!(objects.len(), 1);
+    assert_eq!(objects[0].symbols, vec![String::from("hay")]);
+    let found = sources(index.find(&[&dir.0], &patterns));
+    assert_eq!(found.len(), 2);
+    assert!(!found.iter().any(|s| s.ends_with("/a")));
+}
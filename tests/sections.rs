@@ -0,0 +1,294 @@
+extern crate elfkit;
+
+use elfkit::{linker, types, Elf, Error, LinkRef, Section, SectionContent, Stale, Strtab,
+             Symbol, SymbolSectionIndex};
+use std::io::Cursor;
+
+fn section(name: &str, shtype: types::SectionType, link: u32, info: u32) -> Section {
+    let mut sec = Section::new(
+        String::from(name),
+        shtype,
+        types::SectionFlags::empty(),
+        SectionContent::Raw(Vec::new()),
+        link,
+        info,
+    );
+    if name.starts_with(".rela") {
+        sec.header.flags.insert(types::SectionFlags::INFO_LINK);
+    }
+    sec
+}
+
+/// null, .text, .rela.text -> (.symtab, .text), .symtab -> .strtab, .strtab
+/// with a symbol main defined in .text
+fn sample() -> Elf {
+    let mut elf = Elf::default();
+    elf.sections.push(Section::default());
+    elf.sections.push(section(".text", types::SectionType::PROGBITS, 0, 0));
+    elf.sections.push(section(".rela.text", types::SectionType::RELA, 3, 1));
+    let mut symtab = section(".symtab", types::SectionType::SYMTAB, 4, 0);
+    let mut main = Symbol::default();
+    main.name = String::from("main");
+    main.bind = types::SymbolBind::GLOBAL;
+    main.shndx = SymbolSectionIndex::Section(1);
+    let mut abs = Symbol::default();
+    abs.name = String::from("abs");
+    abs.shndx = SymbolSectionIndex::Absolute;
+    symtab.content = SectionContent::Symbols(vec![Symbol::default(), abs, main]);
+    elf.sections.push(symtab);
+    let mut strtab = section(".strtab", types::SectionType::STRTAB, 0, 0);
+    strtab.content = SectionContent::Strtab(Strtab::default());
+    elf.sections.push(strtab);
+    elf
+}
+
+fn link_of(elf: &Elf, name: &str) -> (String, String) {
+    let sec = elf.sections.iter().find(|s| s.name == name).unwrap();
+    (
+        elf.sections[sec.header.link as usize].name.clone(),
+        elf.sections[sec.header.info as usize].name.clone(),
+    )
+}
+
+#[test]
+fn insert_keeps_links() {
+    let mut elf = sample();
+    elf.insert_section(1, section(".data", types::SectionType::PROGBITS, 0, 0))
+        .unwrap();
+    assert_eq!(
+        link_of(&elf, ".rela.text"),
+        (String::from(".symtab"), String::from(".text"))
+    );
+    assert_eq!(link_of(&elf, ".symtab").0, ".strtab");
+    assert_eq!(elf.sections[4].header.link, 5);
+}
+
+#[test]
+fn remove_keeps_links() {
+    let mut elf = sample();
+    elf.remove_section(1).unwrap();
+    let rela = &elf.sections[1];
+    assert_eq!(rela.name, ".rela.text");
+    assert_eq!(rela.header.link, 2);
+    // the section it applied to is gone
+    assert_eq!(rela.header.info, 0);
+    assert_eq!(link_of(&elf, ".symtab").0, ".strtab");
+}
+
+#[test]
+fn move_keeps_links() {
+    let mut elf = sample();
+    elf.move_section(4, 1).unwrap();
+    let names: Vec<&str> = elf.sections.iter().map(|s| s.name.as_ref()).collect();
+    assert_eq!(names, vec!["", ".strtab", ".text", ".rela.text", ".symtab"]);
+    assert_eq!(
+        link_of(&elf, ".rela.text"),
+        (String::from(".symtab"), String::from(".text"))
+    );
+    assert_eq!(link_of(&elf, ".symtab").0, ".strtab");
+
+    elf.move_section(1, 5).unwrap();
+    let names: Vec<&str> = elf.sections.iter().map(|s| s.name.as_ref()).collect();
+    assert_eq!(names, vec!["", ".text", ".rela.text", ".symtab", ".strtab"]);
+    assert_eq!(link_of(&elf, ".symtab").0, ".strtab");
+}
+
+#[test]
+fn section_ids_survive_edits() {
+    let mut elf = sample();
+    let symtab = elf.section_id(3);
+    elf.insert_section(1, section(".data", types::SectionType::PROGBITS, 0, 0))
+        .unwrap();
+    assert_eq!(elf.section_index(symtab), Some(4));
+    elf.move_section(4, 1).unwrap();
+    assert_eq!(elf.section_index(symtab), Some(1));
+    elf.remove_section(1).unwrap();
+    assert_eq!(elf.section_index(symtab), None);
+}
+
+fn main_shndx(elf: &mut Elf) -> SymbolSectionIndex {
+    let i = elf.sections.iter().position(|s| s.name == ".symtab").unwrap();
+    elf.load_at(i).unwrap();
+    let symbols = elf.sections[i].content.as_symbols().unwrap();
+    assert_eq!(symbols[1].shndx, SymbolSectionIndex::Absolute);
+    symbols[2].shndx.clone()
+}
+
+fn data() -> Section {
+    section(".data", types::SectionType::PROGBITS, 0, 0)
+}
+
+#[test]
+fn symbols_follow_insert_before_their_section() {
+    let mut elf = sample();
+    elf.insert_section(0, data()).unwrap();
+    assert_eq!(main_shndx(&mut elf), SymbolSectionIndex::Section(2));
+}
+
+#[test]
+fn symbols_follow_insert_at_their_section() {
+    let mut elf = sample();
+    elf.insert_section(1, data()).unwrap();
+    assert_eq!(main_shndx(&mut elf), SymbolSectionIndex::Section(2));
+}
+
+#[test]
+fn symbols_unaffected_by_insert_after_their_section() {
+    let mut elf = sample();
+    elf.insert_section(2, data()).unwrap();
+    assert_eq!(main_shndx(&mut elf), SymbolSectionIndex::Section(1));
+}
+
+#[test]
+fn symbols_follow_moved_section() {
+    let mut elf = sample();
+    elf.move_section(1, 4).unwrap();
+    assert_eq!(elf.sections[3].name, ".text");
+    assert_eq!(main_shndx(&mut elf), SymbolSectionIndex::Section(3));
+}
+
+#[test]
+fn symbols_in_removed_section_become_undefined() {
+    let mut elf = sample();
+    elf.remove_section(1).unwrap();
+    assert_eq!(main_shndx(&mut elf), SymbolSectionIndex::Undefined);
+}
+
+#[test]
+fn raw_symbols_follow_insert() {
+    let mut elf = sample();
+    elf.store_all().unwrap();
+    match elf.sections[3].content {
+        SectionContent::Raw(_) => {}
+        _ => panic!("symtab should be serialized"),
+    }
+    elf.insert_section(1, data()).unwrap();
+    assert_eq!(main_shndx(&mut elf), SymbolSectionIndex::Section(2));
+}
+
+#[test]
+fn overlapping_sections_are_an_error() {
+    let mut elf = sample();
+    elf.sections[1].content = SectionContent::Raw(vec![0x90; 0x20]);
+    elf.sections[1].header.offset = 0x100;
+    elf.sections[2].content = SectionContent::Raw(vec![0; 0x10]);
+    elf.sections[2].header.offset = 0x110;
+    match elf.to_writer(&mut Cursor::new(Vec::new())) {
+        Err(Error::SectionOverlap(name, 0x110, 0x120)) => assert_eq!(name, ".rela.text"),
+        r => panic!("expected overlap, got {:?}", r),
+    }
+}
+
+#[test]
+fn relayout_with_stale_sizes_is_an_error() {
+    let mut elf = sample();
+    elf.sections[1].content = SectionContent::Raw(vec![0x90; 0x20]);
+    match linker::relayout(&mut elf, 0x40) {
+        Err(Error::SectionOffsetBeyondAddress(name, 0x60, 0x40)) => {
+            assert_eq!(name, ".rela.text")
+        }
+        r => panic!("expected layout error, got {:?}", r),
+    }
+}
+
+#[test]
+fn store_adds_a_section_name_table() {
+    let mut elf = sample();
+    elf.sections[1].content = SectionContent::Raw(vec![0x90; 0x20]);
+    elf.sync_all().unwrap();
+    linker::relayout(&mut elf, 0x40).unwrap();
+    assert!(elf.sections.iter().all(|s| s.name != ".shstrtab"));
+    let end = elf.sections.iter().map(|s| s.header.offset + s.header.size).max().unwrap();
+
+    elf.store_all().unwrap();
+    assert_eq!(elf.sections.len(), 6);
+    assert_eq!(elf.sections[5].name, ".shstrtab");
+    assert_eq!(elf.sections[5].header.offset, end);
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    let mut elf = Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap();
+    let names: Vec<&str> = elf.sections.iter().map(|s| s.name.as_ref()).collect();
+    assert_eq!(names, vec!["", ".text", ".rela.text", ".symtab", ".strtab", ".shstrtab"]);
+
+    // the name of a removed section goes away with it
+    elf.remove_section(2).unwrap();
+    elf.sync_all().unwrap();
+    elf.store_all().unwrap();
+    assert_eq!(elf.sections.iter().filter(|s| s.name == ".shstrtab").count(), 1);
+    let table = elf.sections[4].content.as_raw().unwrap();
+    assert!(!table.windows(5).any(|w| w == b".rela"));
+}
+
+#[test]
+fn edits_say_what_they_made_stale() {
+    let mut elf = sample();
+    assert_eq!(elf.stale(), Stale::default());
+    elf.insert_section(1, data()).unwrap();
+    assert_eq!(
+        elf.stale(),
+        Stale {
+            sizes: true,
+            names: true,
+            offsets: true,
+        }
+    );
+    match elf.to_writer(&mut Cursor::new(Vec::new())) {
+        Err(Error::OutOfSync("section sizes")) => {}
+        r => panic!("expected out of sync, got {:?}", r),
+    }
+    elf.sync_all().unwrap();
+    assert!(!elf.stale().sizes && !elf.stale().names && elf.stale().offsets);
+    linker::relayout(&mut elf, 0x40).unwrap();
+    assert_eq!(elf.stale(), Stale::default());
+
+    elf.remove_section(1).unwrap();
+    assert!(elf.stale().names && !elf.stale().offsets);
+    elf.store_all().unwrap();
+    assert_eq!(elf.stale(), Stale::default());
+    elf.to_writer(&mut Cursor::new(Vec::new())).unwrap();
+}
+
+#[test]
+fn deep_clone_has_section_ids_of_its_own() {
+    let mut elf = sample();
+    let symtab = elf.section_id(3);
+    let mut copy = elf.deep_clone();
+    assert_eq!(copy.section_index(symtab), None);
+    let copied = copy.section_id(3);
+    assert_eq!(elf.section_index(copied), None);
+
+    // a section that links to its table by id, taken from the copy, can't link to a
+    // section of the original that happens to have the same id
+    let mut rela = section(".rela.data", types::SectionType::RELA, 0, 0);
+    rela.link_ref = Some(LinkRef::Id(copied));
+    copy.sections.push(rela.clone());
+    copy.sync_all().unwrap();
+    assert_eq!(link_of(&copy, ".rela.data").0, ".symtab");
+    elf.sections.push(rela);
+    match elf.sync_all() {
+        Err(Error::SectionIdNotFound(id)) => assert_eq!(id, copied),
+        r => panic!("expected a missing id, got {:?}", r),
+    }
+}
+
+#[test]
+fn view_borrows_raw_content() {
+    let mut elf = sample();
+    elf.sections[1].content = SectionContent::Raw(vec![0x90; 0x20]);
+    elf.sync_all().unwrap();
+    linker::relayout(&mut elf, 0x40).unwrap();
+    elf.store_all().unwrap();
+    let view = elf.view();
+    let text = elf.sections[1].content.as_raw().unwrap();
+    match view.sections[1].content {
+        SectionContent::Raw(v) => assert_eq!(v.as_ptr(), text.as_ptr()),
+        ref c => panic!("expected raw content, got {:?}", c),
+    }
+    assert_eq!(view.sections[1].id, elf.sections[1].id);
+
+    let mut io = Cursor::new(Vec::new());
+    elf.deep_clone().to_writer(&mut io).unwrap();
+    let mut again = Cursor::new(Vec::new());
+    view.into_owned().to_writer(&mut again).unwrap();
+    assert_eq!(io.into_inner(), again.into_inner());
+}
@@ -0,0 +1,124 @@
+extern crate elfkit;
+
+use elfkit::security::{Pie, Relro};
+use elfkit::{types, Dynamic, DynamicContent, Elf, ElfBuilder, Section, SectionContent,
+             SegmentHeader, Strtab, Symbol};
+
+fn segment(phtype: types::SegmentType, flags: types::SegmentFlags) -> SegmentHeader {
+    SegmentHeader {
+        phtype,
+        flags,
+        ..SegmentHeader::default()
+    }
+}
+
+fn undefined(name: &str) -> Symbol {
+    let mut sym = Symbol::default();
+    sym.name = String::from(name);
+    sym.bind = types::SymbolBind::GLOBAL;
+    sym
+}
+
+fn hardened() -> Elf {
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .etype(types::ElfType::DYN)
+        .text(vec![0xc3])
+        .build()
+        .unwrap();
+    let rw = types::SegmentFlags::READABLE | types::SegmentFlags::WRITABLE;
+    elf.segments.push(segment(types::SegmentType::GNU_STACK, rw));
+    elf.segments.push(segment(types::SegmentType::GNU_RELRO, types::SegmentFlags::READABLE));
+
+    let mut dynstr = Strtab::default();
+    let runpath = dynstr.insert(b"$ORIGIN/../lib:/opt/lib".to_vec()) as u64;
+    let dynstr_index = elf.sections.len();
+    elf.sections.push(Section::new(
+        String::from(".dynstr"),
+        types::SectionType::STRTAB,
+        types::SectionFlags::ALLOC,
+        SectionContent::Strtab(dynstr),
+        0,
+        0,
+    ));
+    let dynamic = vec![
+        Dynamic {
+            dhtype: types::DynamicType::RUNPATH,
+            content: DynamicContent::Address(runpath),
+        },
+        Dynamic {
+            dhtype: types::DynamicType::FLAGS,
+            content: DynamicContent::Flags(types::DynamicFlags::BIND_NOW),
+        },
+        Dynamic {
+            dhtype: types::DynamicType::FLAGS_1,
+            content: DynamicContent::Flags1(types::DynamicFlags1::NOW | types::DynamicFlags1::PIE),
+        },
+    ];
+    elf.sections.push(Section::new(
+        String::from(".dynamic"),
+        types::SectionType::DYNAMIC,
+        types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+        SectionContent::Dynamic(dynamic),
+        dynstr_index as u32,
+        0,
+    ));
+    let symbols = vec![
+        Symbol::default(),
+        undefined("__stack_chk_fail"),
+        undefined("__printf_chk"),
+        undefined("__memcpy_chk"),
+        undefined("memcpy"),
+    ];
+    elf.sections.push(Section::new(
+        String::from(".dynsym"),
+        types::SectionType::DYNSYM,
+        types::SectionFlags::ALLOC,
+        SectionContent::Symbols(symbols),
+        dynstr_index as u32,
+        1,
+    ));
+    elf
+}
+
+#[test]
+fn fully_hardened() {
+    let r = hardened().security_report().unwrap();
+    assert!(r.nx);
+    assert_eq!(r.relro, Relro::Full);
+    assert_eq!(r.pie, Pie::Pie);
+    assert!(r.canary);
+    assert_eq!(r.fortified, vec!["__memcpy_chk", "__printf_chk"]);
+    assert!(r.rpath.is_empty());
+    assert_eq!(r.runpath, vec!["$ORIGIN/../lib", "/opt/lib"]);
+    assert!(r.insecure_paths().is_empty());
+}
+
+#[test]
+fn weaknesses() {
+    let mut elf = hardened();
+    // executable stack, lazy binding, a shared library, a relative rpath
+    for ph in &mut elf.segments {
+        if ph.phtype == types::SegmentType::GNU_STACK {
+            ph.flags.insert(types::SegmentFlags::EXECUTABLE);
+        }
+    }
+    let i = elf.sections.iter().position(|s| s.name == ".dynamic").unwrap();
+    elf.sections[i].content = SectionContent::Dynamic(vec![
+        Dynamic {
+            dhtype: types::DynamicType::RPATH,
+            content: DynamicContent::String(String::from("lib:/usr/lib")),
+        },
+    ]);
+    let r = elf.security_report().unwrap();
+    assert!(!r.nx);
+    assert_eq!(r.relro, Relro::Partial);
+    assert_eq!(r.pie, Pie::Dso);
+    assert_eq!(r.insecure_paths(), vec!["lib"]);
+
+    elf.segments.clear();
+    elf.header.etype = types::ElfType::EXEC;
+    let r = elf.security_report().unwrap();
+    assert!(!r.nx);
+    assert_eq!(r.relro, Relro::None);
+    assert_eq!(r.pie, Pie::No);
+}
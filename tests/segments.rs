@@ -0,0 +1,380 @@
+extern crate elfkit;
+
+use elfkit::linker::MemoryRegion;
+use elfkit::{linker, types, Elf, ElfBuilder, Error, Mapping, SectionContent};
+use std::io::Cursor;
+
+fn linked() -> Vec<u8> {
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0x90, 0x90, 0x90, 0xc3])
+        .section(
+            ".rodata",
+            types::SectionType::PROGBITS,
+            types::SectionFlags::ALLOC,
+            b"hello".to_vec(),
+        )
+        .build()
+        .unwrap();
+    elf.sync_all().unwrap();
+    linker::relayout(&mut elf, 0x300000).unwrap();
+    elf.segments = linker::segments(&elf).unwrap();
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    io.into_inner()
+}
+
+fn section<'a>(elf: &'a Elf, name: &str) -> &'a elfkit::Section {
+    elf.sections.iter().find(|s| s.name == name).unwrap()
+}
+
+#[test]
+fn segment_data_and_patching() {
+    let raw = linked();
+    let mut io = Cursor::new(raw);
+    let mut elf = Elf::from_reader(&mut io).unwrap();
+
+    let text = section(&elf, ".text").header.clone();
+    let rodata = section(&elf, ".rodata").header.clone();
+    let i = elf.segments
+        .iter()
+        .position(|s| {
+            s.phtype == types::SegmentType::LOAD && s.vaddr <= text.addr
+                && text.addr < s.vaddr + s.filesz
+        })
+        .unwrap();
+    let data = elf.segment_data(i, &mut io).unwrap();
+    assert_eq!(data.len() as u64, elf.segments[i].filesz);
+    let at = (text.offset - elf.segments[i].offset) as usize;
+    assert_eq!(&data[at..at + 4], &[0x90, 0x90, 0x90, 0xc3]);
+    match elf.segment_data(elf.segments.len(), &mut io) {
+        Err(Error::SegmentIndexOutOfRange(_)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+
+    // int3 over the second nop
+    elf.patch_vaddr_range(text.addr + 1, &[0xcc]).unwrap();
+    elf.patch_vaddr_range(rodata.addr + 1, b"EL").unwrap();
+    match elf.patch_vaddr_range(0x10, &[0]) {
+        Err(Error::UnmappedAddress(0x10)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+
+    elf.store_all().unwrap();
+    let mut out = Cursor::new(Vec::new());
+    elf.to_writer(&mut out).unwrap();
+    let elf = Elf::from_reader(&mut Cursor::new(out.into_inner())).unwrap();
+    match section(&elf, ".text").content {
+        SectionContent::Raw(ref v) => assert_eq!(&v[..], &[0x90, 0xcc, 0x90, 0xc3]),
+        ref c => panic!("unexpected {:?}", c),
+    }
+    match section(&elf, ".rodata").content {
+        SectionContent::Raw(ref v) => assert_eq!(&v[..], b"hELlo"),
+        ref c => panic!("unexpected {:?}", c),
+    }
+}
+
+#[test]
+fn patching_needs_raw_content() {
+    let mut elf = Elf::from_reader(&mut Cursor::new(linked())).unwrap();
+    let addr = section(&elf, ".text").header.addr;
+    let i = elf.sections.iter().position(|s| s.name == ".text").unwrap();
+    elf.sections[i].content = SectionContent::None;
+    match elf.patch_vaddr_range(addr, &[0xcc]).map_err(|e| e.root_cause().to_string()) {
+        Err(e) => assert_eq!(e, "unexpected section content"),
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[test]
+fn failed_patch_changes_nothing() {
+    let mut elf = Elf::from_reader(&mut Cursor::new(linked())).unwrap();
+    let text = section(&elf, ".text").header.clone();
+    let r = elf.sections.iter().position(|s| s.name == ".rodata").unwrap();
+    elf.sections[r].content = SectionContent::None;
+    // all of .text and on into .rodata right behind it, which can't be patched
+    assert!(elf.patch_vaddr_range(text.addr, &[0xcc; 6]).is_err());
+    match section(&elf, ".text").content {
+        SectionContent::Raw(ref v) => assert_eq!(&v[..], &[0x90, 0x90, 0x90, 0xc3]),
+        ref c => panic!("unexpected {:?}", c),
+    }
+}
+
+#[test]
+fn address_translation() {
+    let mut elf = Elf::from_reader(&mut Cursor::new(linked())).unwrap();
+    let text = section(&elf, ".text").header.clone();
+    let (offset, by) = elf.vaddr_to_offset(text.addr + 2).unwrap();
+    assert_eq!(offset, text.offset + 2);
+    let i = match by {
+        Mapping::Segment(i) => i,
+        m => panic!("unexpected {:?}", m),
+    };
+    assert_eq!(elf.segments[i].phtype, types::SegmentType::LOAD);
+    assert_eq!(elf.offset_to_vaddr(offset), Some((text.addr + 2, Mapping::Segment(i))));
+    assert_eq!(elf.vaddr_to_offset(0x7fff_0000_0000), None);
+
+    // without program headers the sections are used
+    elf.segments.clear();
+    let t = elf.sections.iter().position(|s| s.name == ".text").unwrap();
+    assert_eq!(elf.vaddr_to_offset(text.addr + 3), Some((text.offset + 3, Mapping::Section(t))));
+    assert_eq!(elf.offset_to_vaddr(text.offset), Some((text.addr, Mapping::Section(t))));
+    let r = elf.sections.iter().position(|s| s.name == ".rodata").unwrap();
+    let rodata = elf.sections[r].header.clone();
+    assert_eq!(elf.vaddr_to_offset(rodata.addr), Some((rodata.offset, Mapping::Section(r))));
+    assert_eq!(elf.vaddr_to_offset(rodata.addr + rodata.size), None);
+    assert_eq!(elf.offset_to_vaddr(0), None);
+}
+
+#[test]
+fn relro_gets_whole_pages() {
+    let rw = types::SectionFlags::ALLOC | types::SectionFlags::WRITE;
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .section(".got", types::SectionType::PROGBITS, rw, vec![0; 16])
+        .section(".data", types::SectionType::PROGBITS, rw, vec![1; 4])
+        .build()
+        .unwrap();
+    elf.sync_all().unwrap();
+    linker::relayout(&mut elf, 0x300).unwrap();
+    let got = section(&elf, ".got").header.clone();
+    let data = section(&elf, ".data").header.clone();
+    assert_eq!((got.addr % 0x1000, got.offset % 0x1000), (0, 0));
+    assert_eq!(data.addr, got.addr + 0x1000);
+    assert_eq!(data.offset, got.offset + 0x1000);
+
+    let segments = linker::segments(&elf).unwrap();
+    let relro = segments
+        .iter()
+        .find(|s| s.phtype == types::SegmentType::GNU_RELRO)
+        .unwrap();
+    assert_eq!((relro.vaddr, relro.offset, relro.memsz), (got.addr, got.offset, 0x1000));
+    assert_eq!(linker::relro(&elf).map(|s| s.vaddr), Some(relro.vaddr));
+
+    // not laid out for relro, so the page might hold other data
+    let i = elf.sections.iter().position(|s| s.name == ".got").unwrap();
+    elf.sections[i].header.addr += 8;
+    assert!(linker::relro(&elf).is_none());
+    let elf = Elf::from_reader(&mut Cursor::new(linked())).unwrap();
+    assert!(elf.segments.iter().all(|s| s.phtype != types::SegmentType::GNU_RELRO));
+}
+
+#[test]
+fn replaced_content_moves_what_follows() {
+    let mut elf = Elf::from_reader(&mut Cursor::new(linked())).unwrap();
+    let i = elf.sections.iter().position(|s| s.name == ".rodata").unwrap();
+    let rodata = elf.sections[i].header.clone();
+    let after: Vec<(String, u64)> = elf.sections
+        .iter()
+        .filter(|s| s.header.offset > rodata.offset)
+        .map(|s| (s.name.clone(), s.header.offset))
+        .collect();
+    let blob = vec![0x42; 0x1800];
+    elf.replace_section_content(i, SectionContent::Raw(blob.clone())).unwrap();
+    let shift = section(&elf, &after[0].0).header.offset - after[0].1;
+    for (name, offset) in after {
+        let moved = section(&elf, &name).header.offset;
+        assert!(moved >= rodata.offset + 0x1800, "{}", name);
+        assert_eq!(moved, offset + shift);
+    }
+    for seg in &elf.segments {
+        for sec in &elf.sections {
+            let alloc = sec.header.flags.contains(types::SectionFlags::ALLOC);
+            if seg.phtype == types::SegmentType::LOAD && alloc && sec.header.addr >= seg.vaddr
+                && sec.header.addr < seg.vaddr + seg.memsz
+            {
+                assert_eq!(sec.header.offset - seg.offset, sec.header.addr - seg.vaddr);
+            }
+        }
+    }
+    let last = elf.vaddr_to_offset(rodata.addr + 0x17ff).map(|m| m.0);
+    assert_eq!(last, Some(rodata.offset + 0x17ff));
+
+    elf.store_all().unwrap();
+    let mut out = Cursor::new(Vec::new());
+    elf.to_writer(&mut out).unwrap();
+    let mut io = Cursor::new(out.into_inner());
+    let elf = Elf::from_reader(&mut io).unwrap();
+    match section(&elf, ".rodata").content {
+        SectionContent::Raw(ref v) => assert_eq!(v, &blob),
+        ref c => panic!("unexpected {:?}", c),
+    }
+
+    // .text can't take the addresses of .rodata
+    let mut elf = Elf::from_reader(&mut Cursor::new(linked())).unwrap();
+    let t = elf.sections.iter().position(|s| s.name == ".text").unwrap();
+    let r = elf.replace_section_content(t, SectionContent::Raw(vec![0x90; 0x2000]));
+    match r.map_err(|e| e.to_string()) {
+        Err(e) => assert_eq!(e, "section '.text' can not grow into the addresses of '.rodata'"),
+        r => panic!("unexpected {:?}", r),
+    }
+
+    // shrinking leaves everything else where it is
+    let mut elf = Elf::from_reader(&mut Cursor::new(linked())).unwrap();
+    let segments = elf.segments.clone();
+    elf.replace_section_content(t, SectionContent::Raw(vec![0xc3])).unwrap();
+    assert_eq!(section(&elf, ".rodata").header.offset, rodata.offset);
+    assert_eq!(elf.segments.len(), segments.len());
+    assert!(elf.replace_section_content(99, SectionContent::None).is_err());
+
+    // nothing grows past the end of the address space or the largest offset
+    let r = elf.sections.iter().position(|s| s.name == ".rodata").unwrap();
+    let grown = || SectionContent::Raw(vec![0x42; 0x1800]);
+    let mut elf = Elf::from_reader(&mut Cursor::new(linked())).unwrap();
+    elf.sections[r].header.addr = u64::MAX - 0x10;
+    match elf.replace_section_content(r, grown()) {
+        Err(Error::AddressOutOfRange(_, addr)) => assert_eq!(addr, u64::MAX - 0x10),
+        r => panic!("unexpected {:?}", r.map_err(|e| e.to_string())),
+    }
+    let mut elf = Elf::from_reader(&mut Cursor::new(linked())).unwrap();
+    elf.sections[r].header.offset = u64::MAX - 0x10;
+    match elf.replace_section_content(r, grown()) {
+        Err(Error::SectionBeyondEndOfFile(offset, 0x1800)) => assert_eq!(offset, u64::MAX - 0x10),
+        r => panic!("unexpected {:?}", r.map_err(|e| e.to_string())),
+    }
+}
+
+#[test]
+fn sections_start_at_their_alignment() {
+    let rw = types::SectionFlags::ALLOC | types::SectionFlags::WRITE;
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .section(".data", types::SectionType::PROGBITS, rw, vec![1; 3])
+        .section(".bss", types::SectionType::NOBITS, rw, Vec::new())
+        .build()
+        .unwrap();
+    let index = |elf: &Elf, name: &str| elf.sections.iter().position(|s| s.name == name).unwrap();
+    let (data, bss) = (index(&elf, ".data"), index(&elf, ".bss"));
+    elf.sections[data].header.addralign = 32;
+    elf.sections[bss].header.addralign = 64;
+    elf.sections[bss].header.size = 8;
+    elf.sync_all().unwrap();
+    linker::relayout(&mut elf, 0x301).unwrap();
+
+    let d = section(&elf, ".data").header.clone();
+    assert_eq!((d.addr % 32, d.offset % 32), (0, 0));
+    assert_eq!(section(&elf, ".bss").header.addr % 64, 0);
+}
+
+#[test]
+fn pinned_and_noload_sections() {
+    let rw = types::SectionFlags::ALLOC | types::SectionFlags::WRITE;
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .section(".vectors", types::SectionType::PROGBITS, rw, vec![1; 64])
+        .section(".data", types::SectionType::PROGBITS, rw, vec![2; 4])
+        .section(".uart", types::SectionType::PROGBITS, rw, vec![0; 0x100])
+        .build()
+        .unwrap();
+    let index = |elf: &Elf, name: &str| elf.sections.iter().position(|s| s.name == name).unwrap();
+    let (vectors, uart) = (index(&elf, ".vectors"), index(&elf, ".uart"));
+    elf.sections[vectors].placement.address = Some(0x600000);
+    elf.sections[vectors].placement.keep = true;
+    elf.sections[uart].placement.address = Some(0x40000000);
+    elf.sections[uart].placement.noload = true;
+    elf.sync_all().unwrap();
+    linker::relayout(&mut elf, 0x300).unwrap();
+
+    let v = section(&elf, ".vectors").header.clone();
+    assert_eq!(v.addr, 0x600000);
+    assert_eq!(v.offset % 0x200000, 0);
+    assert!(v.flags.contains(types::SectionFlags::GNU_RETAIN));
+    // what follows a pinned section follows it
+    assert_eq!(section(&elf, ".data").header.addr, 0x600040);
+
+    // it keeps what it had, only nothing loads it
+    let u = section(&elf, ".uart");
+    assert_eq!(u.header.shtype, types::SectionType::PROGBITS);
+    assert_eq!((u.header.addr, u.header.size), (0x40000000, 0x100));
+    assert_eq!(u.content.as_raw().map(|r| r.len()), Some(0x100));
+
+    let segments = linker::segments(&elf).unwrap();
+    let loads: Vec<_> = segments.iter().filter(|s| s.phtype == types::SegmentType::LOAD).collect();
+    assert!(loads.iter().any(|s| s.vaddr == 0x600000 && s.offset == v.offset));
+    assert!(loads.iter().all(|s| s.vaddr + s.memsz <= 0x600044));
+
+    // a pinned section can't go below where the sections before it end
+    elf.sections[vectors].placement.address = Some(0x200);
+    match linker::relayout(&mut elf, 0x300) {
+        Err(Error::SectionAddressTaken(ref name, 0x200, _)) if name == ".vectors" => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[test]
+fn sections_in_memory_regions() {
+    let flash = MemoryRegion {
+        name: String::from("FLASH"),
+        origin: 0x8000000,
+        length: 0x100,
+    };
+    let ram = MemoryRegion {
+        name: String::from("RAM"),
+        origin: 0x20000000,
+        length: 0x20,
+    };
+    let rw = types::SectionFlags::ALLOC | types::SectionFlags::WRITE;
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3; 0x80])
+        .section(".rodata", types::SectionType::PROGBITS, types::SectionFlags::ALLOC, vec![1; 0x40])
+        .section(".data", types::SectionType::PROGBITS, rw, vec![2; 0x10])
+        .build()
+        .unwrap();
+    let place = |elf: &mut Elf, name: &str, region: &str| {
+        let i = elf.sections.iter().position(|s| s.name == name).unwrap();
+        elf.sections[i].placement.region = Some(String::from(region));
+    };
+    place(&mut elf, ".text", "FLASH");
+    place(&mut elf, ".rodata", "FLASH");
+    place(&mut elf, ".data", "RAM");
+    elf.sync_all().unwrap();
+    let regions = [flash, ram];
+    linker::relayout_in_regions(&mut elf, 0x300, &regions).unwrap();
+    let addr = |elf: &Elf, name: &str| section(elf, name).header.addr;
+    assert_eq!(addr(&elf, ".text"), 0x8000000);
+    assert_eq!(addr(&elf, ".rodata"), 0x8000080);
+    assert_eq!(addr(&elf, ".data"), 0x20000000);
+
+    let i = elf.sections.iter().position(|s| s.name == ".data").unwrap();
+    elf.sections[i].content = SectionContent::Raw(vec![2; 0x2c]);
+    elf.sync_all().unwrap();
+    match linker::relayout_in_regions(&mut elf, 0x300, &regions) {
+        Err(e @ Error::MemoryRegionOverflow(..)) => {
+            assert_eq!(e.to_string(), "region RAM overflowed by 12 bytes")
+        }
+        r => panic!("unexpected {:?}", r),
+    }
+    place(&mut elf, ".data", "SRAM2");
+    match linker::relayout_in_regions(&mut elf, 0x300, &regions) {
+        Err(Error::MemoryRegionNotFound(ref name)) if name == "SRAM2" => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[test]
+fn program_headers_before_the_first_section() {
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0x90, 0xc3])
+        .build()
+        .unwrap();
+    linker::check_program_headers(&elf).unwrap();
+
+    // laid out for fewer program headers than there are
+    let text = section(&elf, ".text").header.offset;
+    let mut more = elf.segments.clone();
+    more.extend(elf.segments.clone());
+    elf.segments.extend(more);
+    match linker::check_program_headers(&elf) {
+        Err(Error::ProgramHeadersOverlap(end, ref name, offset)) => {
+            assert_eq!((end, name.as_ref(), offset), (64 + 6 * 56, ".text", text));
+        }
+        r => panic!("unexpected {:?}", r),
+    }
+    elf.segments.truncate(2);
+
+    elf.segments[0].vaddr += 0x10000;
+    match linker::check_program_headers(&elf) {
+        Err(Error::PhdrNotInLoad(0x10040)) => {}
+        r => panic!("unexpected {:?}", r),
+    }
+}
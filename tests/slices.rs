@@ -0,0 +1,43 @@
+extern crate elfkit;
+
+use elfkit::{types, ElfBuilder, Header, SectionHeader, Strtab, Symbol};
+use std::io::Cursor;
+
+// the type layer only needs a Read, which in a no_std build is a plain &[u8]
+#[test]
+fn type_layer_parses_from_slices() {
+    let mut start = Symbol::default();
+    start.name = String::from("_start");
+    start.bind = types::SymbolBind::GLOBAL;
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .symbol(".text", start)
+        .entry("_start")
+        .build()
+        .unwrap();
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    let raw = io.into_inner();
+
+    let eh = Header::from_reader(&mut &raw[..]).unwrap();
+    assert_eq!(eh.machine, types::Machine::X86_64);
+    let headers: Vec<SectionHeader> = (0..eh.shnum as usize)
+        .map(|i| {
+            let at = eh.shoff as usize + i * eh.shentsize as usize;
+            SectionHeader::from_reader(&mut &raw[at..], &eh).unwrap()
+        })
+        .collect();
+    let content = |sh: &SectionHeader| &raw[sh.offset as usize..(sh.offset + sh.size) as usize];
+
+    let symtab = headers
+        .iter()
+        .find(|sh| sh.shtype == types::SectionType::SYMTAB)
+        .unwrap();
+    let strtab = Strtab::from_reader(content(&headers[symtab.link as usize]), None, &eh).unwrap();
+    let symbols = Symbol::from_reader(content(symtab), Some(&strtab), &eh).unwrap();
+    let symbols = symbols.as_symbols().unwrap();
+    assert_eq!(symbols.len(), 2);
+    assert_eq!(symbols[1].name, "_start");
+    assert_eq!(symbols[1].bind, types::SymbolBind::GLOBAL);
+}
@@ -0,0 +1,58 @@
+extern crate elfkit;
+
+use elfkit::relocation::RelocationType;
+use elfkit::{types, Elf, ElfBuilder, Symbol, SymbolicRelocation, Target};
+use std::io::Cursor;
+
+#[test]
+fn counters_follow_the_work() {
+    let main = Symbol {
+        name: String::from("main"),
+        stype: types::SymbolType::FUNC,
+        bind: types::SymbolBind::GLOBAL,
+        size: 16,
+        ..Symbol::default()
+    };
+    let call = |at, name: &str| {
+        let target = Target::Symbol(String::from(name));
+        SymbolicRelocation::new(at, RelocationType::R_X86_64_PLT32, target).with_addend(-4)
+    };
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .etype(types::ElfType::REL)
+        .text(vec![0xe8, 0, 0, 0, 0, 0xe8, 0, 0, 0, 0, 0xc3])
+        .symbol(".text", main)
+        .relocation(".text", call(1, "puts"))
+        .relocation(".text", call(6, "exit"))
+        .build()
+        .unwrap();
+    assert_eq!(elf.stats().symbols_resolved, 2);
+    assert_eq!(elf.stats().bytes_read, 0);
+
+    let mut io = Cursor::new(Vec::new());
+    elf.store_all().unwrap();
+    elf.to_writer(&mut io).unwrap();
+    let bytes = io.into_inner();
+    assert_eq!(elf.stats().bytes_written, bytes.len() as u64);
+
+    let mut elf = Elf::from_reader(&mut Cursor::new(&bytes)).unwrap();
+    let content: u64 = elf.sections.iter()
+        .filter(|s| s.header.shtype != types::SectionType::NOBITS)
+        .map(|s| s.header.size)
+        .sum();
+    assert_eq!(elf.stats().bytes_read, content);
+    assert_eq!(elf.stats().sections_parsed, 0);
+
+    elf.load_all().unwrap();
+    let symbols = elf.sections.iter()
+        .filter_map(|s| s.content.as_symbols())
+        .map(|s| s.len() as u64)
+        .sum();
+    let stats = elf.stats().clone();
+    assert_eq!(stats.relocations_parsed, 2);
+    assert_eq!(stats.symbols_parsed, symbols);
+    assert!(stats.sections_parsed >= 4);
+
+    // loading again parses nothing
+    elf.load_all().unwrap();
+    assert_eq!(*elf.stats(), stats);
+}
@@ -0,0 +1,96 @@
+extern crate elfkit;
+
+use elfkit::{types, Elf, ElfBuilder, SectionContent, Strtab, Symbol};
+use std::io::Cursor;
+
+fn reparse(elf: &mut Elf) -> Elf {
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    let mut elf = Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap();
+    elf.load_all().unwrap();
+    elf
+}
+
+#[test]
+fn tails_share_bytes() {
+    let mut plain = Strtab::default();
+    let mut merged = Strtab::tail_merged();
+    let names = ["foobar", "bar", "obar", "baz", "ar", "foobar"];
+    let mut offsets = Vec::new();
+    for n in &names {
+        plain.insert(n.bytes().collect());
+        offsets.push(merged.insert(n.bytes().collect()));
+    }
+    // incremental merging only catches tails of strings inserted earlier
+    assert_eq!(merged.get(offsets[1]), "bar");
+    assert_eq!(offsets[1], offsets[0] + 3);
+    assert_eq!(offsets[5], offsets[0]);
+
+    let remap = merged.optimize();
+    // "\0foobar\0baz\0"
+    assert_eq!(merged.len(&Default::default()), 12);
+    assert!(plain.len(&Default::default()) > 12);
+    for (n, off) in names.iter().zip(offsets) {
+        assert_eq!(merged.get(remap[&off]), *n);
+    }
+    assert_eq!(merged.insert(b"obar".to_vec()), merged.insert(b"foobar".to_vec()) + 2);
+}
+
+#[test]
+fn sync_packs_tail_merged_tables() {
+    let mut elf = ElfBuilder::new(types::Machine::X86_64).text(vec![0xc3]);
+    for name in &["main", "domain", "ain", "x"] {
+        let mut sym = Symbol::default();
+        sym.name = String::from(*name);
+        sym.bind = types::SymbolBind::GLOBAL;
+        elf = elf.symbol(".text", sym);
+    }
+    let mut elf = reparse(&mut elf.build().unwrap());
+    for sec in &mut elf.sections {
+        if sec.name == ".strtab" || sec.name == ".shstrtab" {
+            sec.content = SectionContent::Strtab(Strtab::tail_merged());
+        }
+    }
+    elf.sync_all().unwrap();
+    let elf = reparse(&mut elf);
+    let strtab = elf.sections.iter().find(|s| s.name == ".strtab").unwrap();
+    // "\0domain\0x\0"
+    assert_eq!(strtab.header.size, 10);
+    match elf.sections.iter().find(|s| s.name == ".symtab").unwrap().content {
+        SectionContent::Symbols(ref s) => {
+            let names: Vec<&str> = s.iter().map(|s| s.name.as_ref()).collect();
+            assert_eq!(names, vec!["", "main", "domain", "ain", "x"]);
+        }
+        _ => panic!(".symtab not loaded as symbols"),
+    }
+}
+
+#[test]
+fn random_access_and_iteration() {
+    let strtab = Strtab::from_raw(b"\0.text\0foo\0\0bar\0".to_vec());
+    strtab.validate().unwrap();
+    assert_eq!(strtab.get_bytes(1), Some(&b".text"[..]));
+    assert_eq!(strtab.get_bytes(2), Some(&b"text"[..]));
+    assert_eq!(strtab.get_bytes(11), Some(&b""[..]));
+    assert_eq!(strtab.get_bytes(16), None);
+    assert_eq!(
+        strtab.iter().collect::<Vec<_>>(),
+        vec![
+            (0, &b""[..]),
+            (1, &b".text"[..]),
+            (7, &b"foo"[..]),
+            (11, &b""[..]),
+            (12, &b"bar"[..]),
+        ]
+    );
+
+    // existing strings and their tails are found rather than appended
+    let mut strtab = strtab;
+    assert_eq!(strtab.insert(b"ext".to_vec()), 3);
+    assert_eq!(strtab.insert(b"bar".to_vec()), 12);
+
+    assert!(Strtab::from_raw(b"\0foo".to_vec()).validate().is_err());
+    assert!(Strtab::from_raw(b"foo\0".to_vec()).validate().is_err());
+    Strtab::default().validate().unwrap();
+}
@@ -0,0 +1,229 @@
+extern crate elfkit;
+
+use elfkit::linker::{self, Exports};
+use elfkit::symbol::{self, sysv_hash};
+use elfkit::{types, Elf, ElfBuilder, Error, Section, SectionContent, Strtab, Symbol,
+             SymbolSectionIndex};
+use std::io::Cursor;
+
+fn symbols(n: usize) -> Vec<Symbol> {
+    let mut r = vec![Symbol::default()];
+    for i in 1..n {
+        let mut sym = Symbol::default();
+        sym.name = format!("symbol_{}", i);
+        sym.bind = types::SymbolBind::GLOBAL;
+        r.push(sym);
+    }
+    r
+}
+
+fn raw(content: &SectionContent) -> Vec<u8> {
+    match content {
+        &SectionContent::Raw(ref v) => v.clone(),
+        c => panic!("unexpected {:?}", c),
+    }
+}
+
+#[test]
+fn sysv_hash_values() {
+    assert_eq!(sysv_hash(&String::from("")), 0);
+    assert_eq!(sysv_hash(&String::from("printf")), 0x077905a6);
+    assert_eq!(sysv_hash(&String::from("__libc_start_main")), 0x0177ff8e);
+}
+
+#[test]
+fn generated_hash_verifies() {
+    let eh = ElfBuilder::new(types::Machine::X86_64).build().unwrap().header;
+    for &n in &[1, 2, 40, 300] {
+        let syms = symbols(n);
+        let sec = symbol::symhash(&eh, &syms, 3).unwrap();
+        assert_eq!(sec.header.entsize, 4);
+        assert_eq!(sec.header.link, 3);
+        let b = raw(&sec.content);
+        symbol::verify_symhash(&eh, &b, &syms).unwrap();
+        // nbucket, nchain, the buckets and one chain entry per symbol
+        let nbucket = u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize;
+        assert_eq!(b.len(), 4 * (2 + nbucket + n));
+    }
+    assert!(symbol::symhash(&eh, &[], 3).is_err());
+}
+
+#[test]
+fn broken_hashes_are_found() {
+    let eh = ElfBuilder::new(types::Machine::X86_64).build().unwrap().header;
+    let syms = symbols(40);
+    let good = raw(&symbol::symhash(&eh, &syms, 3).unwrap().content);
+    let nbucket = good[0] as usize;
+
+    let problem = |b: &[u8], syms: &[Symbol]| match symbol::verify_symhash(&eh, b, syms) {
+        Err(e) => e.to_string(),
+        Ok(()) => String::from("ok"),
+    };
+    assert_eq!(problem(&good[..4], &syms), "invalid hash section: header is truncated");
+    assert_eq!(
+        problem(&good, &syms[..39]),
+        "invalid hash section: nchain is not the number of symbols"
+    );
+
+    // a renamed symbol hashes to another bucket
+    let mut renamed = syms.clone();
+    renamed[7].name = String::from("system");
+    match symbol::verify_symhash(&eh, &good, &renamed) {
+        Err(Error::SymbolNotInSymhash(ref name)) => assert_eq!(name, "system"),
+        r => panic!("unexpected {:?}", r),
+    }
+
+    // a chain pointing back to itself
+    let mut looped = good.clone();
+    let head = (0..nbucket).map(|b| good[8 + 4 * b] as usize).find(|&i| i > 0).unwrap();
+    let chain = 4 * (2 + nbucket + head);
+    looped[chain] = head as u8;
+    assert_eq!(problem(&looped, &syms), "invalid hash section: chains loop or share symbols");
+
+    let mut out_of_range = good.clone();
+    out_of_range[8] = 200;
+    assert_eq!(problem(&out_of_range, &syms), "invalid hash section: symbol index out of range");
+}
+
+#[test]
+fn validate_checks_hash_sections() {
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .build()
+        .unwrap();
+    let syms = symbols(10);
+    let mut hash = symbol::symhash(&elf.header, &syms, 1).unwrap();
+    elf.sections.push(elfkit::Section::new(
+        String::from(".dynsym"),
+        types::SectionType::DYNSYM,
+        types::SectionFlags::ALLOC,
+        SectionContent::Symbols(syms),
+        0,
+        0,
+    ));
+    hash.header.link = elf.sections.len() as u32 - 1;
+    if let SectionContent::Raw(ref mut b) = hash.content {
+        b[8] = 9;
+    }
+    elf.sections.push(hash);
+    let found = elf.validate().iter().any(|f| {
+        matches!(f.problem.root_cause(), Error::InvalidSymhash(_) | Error::SymbolNotInSymhash(_))
+    });
+    assert!(found);
+}
+
+fn sym(name: &str, bind: types::SymbolBind, vis: types::SymbolVis, shndx: SymbolSectionIndex)
+    -> Symbol {
+    let mut s = Symbol {
+        name: String::from(name),
+        bind,
+        shndx,
+        ..Symbol::default()
+    };
+    s.set_vis(vis);
+    s
+}
+
+#[test]
+fn exported_dynamic_symbols() {
+    let text = SymbolSectionIndex::Section(1);
+    let symbols = vec![
+        sym("local", types::SymbolBind::LOCAL, types::SymbolVis::DEFAULT, text.clone()),
+        sym("weak", types::SymbolBind::WEAK, types::SymbolVis::DEFAULT, text.clone()),
+        sym("global", types::SymbolBind::GLOBAL, types::SymbolVis::DEFAULT, text.clone()),
+        sym("protected", types::SymbolBind::GLOBAL, types::SymbolVis::PROTECTED, text.clone()),
+        sym("hidden", types::SymbolBind::GLOBAL, types::SymbolVis::HIDDEN, text.clone()),
+        sym("undefined", types::SymbolBind::GLOBAL, types::SymbolVis::DEFAULT,
+            SymbolSectionIndex::Undefined),
+    ];
+    let names = |exports: &Exports| -> Vec<String> {
+        linker::export_symbols(&symbols, exports).into_iter().map(|s| s.name).collect()
+    };
+    assert_eq!(names(&Exports::None), vec![""]);
+    assert_eq!(names(&Exports::All), vec!["", "weak", "global", "protected"]);
+    let some = ["global", "hidden"].iter().map(|s| String::from(*s)).collect();
+    assert_eq!(names(&Exports::Names(some)), vec!["", "global"]);
+
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .build()
+        .unwrap();
+    at_end(&mut elf, Section::new(
+        String::from(".dynstr"),
+        types::SectionType::STRTAB,
+        types::SectionFlags::ALLOC,
+        SectionContent::Strtab(Strtab::default()),
+        0,
+        0,
+    ));
+    let dynsym = linker::export_symbols(&symbols, &Exports::All);
+    for sec in linker::dynsym_sections(&elf.header, dynsym.clone()).unwrap() {
+        at_end(&mut elf, sec);
+    }
+    elf.sync_all().unwrap();
+    linker::relayout(&mut elf, 0x300).unwrap();
+    elf.segments = linker::segments(&elf).unwrap();
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+
+    let mut elf = Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap();
+    assert!(elf.validate().is_empty());
+    let i = elf.sections.iter().position(|s| s.name == ".dynsym").unwrap();
+    assert_eq!(elf.sections[i].header.info, 1);
+    let hash = elf.sections.iter().find(|s| s.name == ".hash").unwrap();
+    assert_eq!(hash.header.link as usize, i);
+    let read: Vec<&str> = elf.sections[i]
+        .content
+        .as_symbols()
+        .unwrap()
+        .iter()
+        .map(|s| s.name.as_ref())
+        .collect();
+    assert_eq!(read, vec!["", "weak", "global", "protected"]);
+}
+
+/// add an alloc section behind the other alloc sections
+fn at_end(elf: &mut Elf, sec: Section) {
+    let i = elf.sections
+        .iter()
+        .rposition(|s| s.header.flags.contains(types::SectionFlags::ALLOC))
+        .unwrap();
+    elf.insert_section(i + 1, sec).unwrap();
+}
+
+#[test]
+fn hidden_symbols_become_local() {
+    use types::SymbolVis::*;
+    assert_eq!(linker::merge_visibility(DEFAULT, HIDDEN), HIDDEN);
+    assert_eq!(linker::merge_visibility(PROTECTED, DEFAULT), PROTECTED);
+    assert_eq!(linker::merge_visibility(HIDDEN, PROTECTED), HIDDEN);
+    assert_eq!(linker::merge_visibility(INTERNAL, HIDDEN), INTERNAL);
+
+    let text = SymbolSectionIndex::Section(1);
+    let mut symbols = vec![
+        Symbol::default(),
+        sym("a", types::SymbolBind::LOCAL, DEFAULT, text.clone()),
+        sym("b", types::SymbolBind::GLOBAL, DEFAULT, text.clone()),
+        sym("c", types::SymbolBind::GLOBAL, HIDDEN, text.clone()),
+        sym("d", types::SymbolBind::WEAK, PROTECTED, text.clone()),
+        sym("e", types::SymbolBind::WEAK, INTERNAL, text.clone()),
+        sym("f", types::SymbolBind::WEAK, HIDDEN, SymbolSectionIndex::Undefined),
+    ];
+    linker::localize_hidden(&mut symbols);
+    let order: Vec<(&str, types::SymbolBind)> =
+        symbols.iter().map(|s| (s.name.as_ref(), s.bind.clone())).collect();
+    assert_eq!(
+        order,
+        vec![
+            ("", types::SymbolBind::LOCAL),
+            ("a", types::SymbolBind::LOCAL),
+            ("c", types::SymbolBind::LOCAL),
+            ("e", types::SymbolBind::LOCAL),
+            ("b", types::SymbolBind::GLOBAL),
+            ("d", types::SymbolBind::WEAK),
+            ("f", types::SymbolBind::WEAK),
+        ]
+    );
+    assert_eq!(symbols[2].vis(), HIDDEN);
+}
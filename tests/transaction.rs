@@ -0,0 +1,97 @@
+extern crate elfkit;
+
+use elfkit::{types, Elf, ElfBuilder, Error, Symbol};
+use std::io::Cursor;
+use std::panic::{self, AssertUnwindSafe};
+
+fn sample() -> Elf {
+    let start = Symbol {
+        name: String::from("_start"),
+        bind: types::SymbolBind::GLOBAL,
+        stype: types::SymbolType::FUNC,
+        ..Symbol::default()
+    };
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0x90, 0xc3])
+        .section(
+            ".data",
+            types::SectionType::PROGBITS,
+            types::SectionFlags::ALLOC | types::SectionFlags::WRITE,
+            vec![1, 2, 3, 4],
+        )
+        .symbol(".text", start)
+        .entry("_start")
+        .build()
+        .unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap()
+}
+
+fn index(elf: &Elf, name: &str) -> usize {
+    elf.sections.iter().position(|s| s.name == name).unwrap()
+}
+
+#[test]
+fn commit_keeps_valid_edits() {
+    let mut elf = sample();
+    let data = index(&elf, ".data");
+    let entry = elf.header.entry;
+    {
+        let mut tx = elf.transaction();
+        tx.load_at(data).unwrap();
+        tx.sections[data].content.as_raw_mut().unwrap()[0] = 9;
+        tx.header.entry += 1;
+        tx.commit().unwrap();
+    }
+    assert_eq!(elf.sections[data].content.as_raw_mut().unwrap()[0], 9);
+    assert_eq!(elf.header.entry, entry + 1);
+}
+
+#[test]
+fn commit_rolls_back_new_errors() {
+    let mut elf = sample();
+    let symtab = index(&elf, ".symtab");
+    let entry = elf.header.entry;
+    {
+        let mut tx = elf.transaction();
+        tx.header.entry = 0;
+        tx.sections[symtab].header.link = 99;
+        let errors = tx.commit().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        match errors[0].problem.root_cause() {
+            &Error::InvalidSectionLink(99) => {}
+            e => panic!("unexpected {:?}", e),
+        }
+    }
+    assert_eq!(elf.sections[symtab].header.link as usize, index(&elf, ".strtab"));
+    assert_eq!(elf.header.entry, entry);
+
+    // errors the file already had don't stop a commit
+    elf.sections[symtab].header.link = 99;
+    let mut tx = elf.transaction();
+    tx.header.entry = 0;
+    tx.commit().unwrap();
+    assert_eq!(elf.header.entry, 0);
+}
+
+#[test]
+fn dropped_and_panicked_transactions_roll_back() {
+    let mut elf = sample();
+    let sections = elf.sections.len();
+    {
+        let mut tx = elf.transaction();
+        tx.remove_section(1).unwrap();
+        assert_eq!(tx.sections.len(), sections - 1);
+    }
+    assert_eq!(elf.sections.len(), sections);
+
+    let r = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut tx = elf.transaction();
+        tx.remove_section(1).unwrap();
+        tx.remove_section(99).unwrap();
+    }));
+    assert!(r.is_err());
+    assert_eq!(elf.sections.len(), sections);
+    assert_eq!(elf.sections[1].name, ".text");
+}
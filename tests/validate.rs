@@ -0,0 +1,123 @@
+extern crate elfkit;
+
+use elfkit::relocation::RelocationType;
+use elfkit::{types, Elf, ElfBuilder, Error, Relocation, Section, SectionContent, Severity,
+             Symbol, SymbolSectionIndex};
+use std::io::Cursor;
+
+fn reparse(elf: &mut Elf) -> Elf {
+    elf.store_all().unwrap();
+    let mut io = Cursor::new(Vec::new());
+    elf.to_writer(&mut io).unwrap();
+    let mut elf = Elf::from_reader(&mut Cursor::new(io.into_inner())).unwrap();
+    elf.load_all().unwrap();
+    elf
+}
+
+fn sample() -> Elf {
+    let mut start = Symbol::default();
+    start.name = String::from("_start");
+    start.bind = types::SymbolBind::GLOBAL;
+    let mut elf = ElfBuilder::new(types::Machine::X86_64)
+        .text(vec![0xc3])
+        .symbol(".text", start)
+        .entry("_start")
+        .build()
+        .unwrap();
+    reparse(&mut elf)
+}
+
+fn problems(elf: &mut Elf) -> Vec<String> {
+    elf.validate()
+        .iter()
+        .map(|f| format!("{}", f.problem.root_cause()))
+        .collect()
+}
+
+#[test]
+fn builder_output_is_valid() {
+    let mut elf = sample();
+    let findings = elf.validate();
+    assert!(findings.is_empty(), "{:?}", findings);
+}
+
+#[test]
+fn broken_tables_are_reported() {
+    let mut elf = sample();
+    let symtab = elf.sections.iter().position(|s| s.name == ".symtab").unwrap();
+    elf.sections[symtab].header.entsize = 16;
+    elf.sections[symtab].content.as_symbols_mut().unwrap()[1].shndx =
+        SymbolSectionIndex::Section(40);
+
+    let mut rela = Section::new(
+        String::from(".rela.text"),
+        types::SectionType::RELA,
+        types::SectionFlags::INFO_LINK,
+        SectionContent::Relocations(vec![Relocation {
+            addr: 0,
+            sym: 9,
+            rtype: RelocationType::R_X86_64_64,
+            addend: 0,
+        }]),
+        symtab as u32,
+        1,
+    );
+    rela.header.entsize = 24;
+    elf.sections.push(rela);
+
+    let findings = elf.validate();
+    assert_eq!(findings.len(), 3);
+    assert!(findings.iter().all(|f| f.severity == Severity::Error));
+    match findings[0].problem.root_cause() {
+        &Error::InvalidSectionEntsize(16, 24) => {}
+        e => panic!("unexpected {}", e),
+    }
+    let problems = problems(&mut elf);
+    assert_eq!(problems[1], "symbol '_start' is in section 40, which doesn't exist");
+    assert_eq!(problems[2], "relocation refers to symbol 9, but the symbol table has 2");
+}
+
+#[test]
+fn header_and_layout_problems() {
+    let mut elf = sample();
+    elf.header.ehsize = 0;
+    let text = elf.sections.iter().position(|s| s.name == ".text").unwrap();
+    let offset = elf.sections[text].header.offset;
+    elf.sections[text + 1].header.offset = offset;
+    elf.sections[text].header.link = 99;
+
+    let findings = elf.validate();
+    assert_eq!(findings[0].severity, Severity::Warning);
+    let problems = problems(&mut elf);
+    assert_eq!(problems[0], "invalid e_ehsize in the elf header");
+    assert_eq!(problems[1], "link to section 99 out of range");
+    assert_eq!(
+        problems[2],
+        format!(
+            "section '.symtab' at offset 0x{:x} overlaps the previous section ending at 0x{:x}",
+            offset,
+            offset + 1
+        )
+    );
+    assert_eq!(problems.len(), 3);
+}
+
+#[test]
+fn sizes_that_dont_fit_the_file() {
+    let mut elf = sample();
+    let text = elf.sections.iter().position(|s| s.name == ".text").unwrap();
+    elf.sections[text].header.offset = u64::MAX - 1;
+    elf.sections[text].header.size = 16;
+    match elf.validate()[0].problem.root_cause() {
+        &Error::SectionBeyondEndOfFile(o, 16) if o == u64::MAX - 1 => {}
+        e => panic!("unexpected {}", e),
+    }
+
+    // an inactive NULL section claims nothing, even over other sections
+    let mut elf = sample();
+    let mut null = Section::default();
+    null.header.offset = elf.sections[text].header.offset;
+    null.header.size = u64::MAX;
+    elf.sections.push(null);
+    assert_eq!(problems(&mut elf), Vec::<String>::new());
+}